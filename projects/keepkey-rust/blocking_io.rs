@@ -0,0 +1,167 @@
+//! Dedicated threads for blocking transport I/O.
+//!
+//! USB and HID reads block for as long as the device takes to answer, which
+//! is minutes when it waits for a button press. Running them on these threads
+//! instead of inline in a device worker keeps tokio's worker threads free for
+//! everything else in the process (the REST server in particular).
+//!
+//! The pool has a fixed number of threads, started on first use. When all of
+//! them are busy, jobs wait in submission order.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tracing::{error, warn};
+
+/// Threads in the pool. Each device worker runs one job at a time, so this
+/// is how many devices can be mid-exchange at once.
+pub const BLOCKING_IO_THREADS: usize = 8;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+#[derive(Default)]
+struct Counters {
+    submitted: AtomicU64,
+    completed: AtomicU64,
+    panicked: AtomicU64,
+    busy: AtomicU64,
+    queue_wait_ms_total: AtomicU64,
+    queue_wait_ms_max: AtomicU64,
+}
+
+static COUNTERS: OnceLock<Counters> = OnceLock::new();
+static POOL: OnceLock<Mutex<mpsc::Sender<Job>>> = OnceLock::new();
+
+fn counters() -> &'static Counters {
+    COUNTERS.get_or_init(Counters::default)
+}
+
+fn pool() -> &'static Mutex<mpsc::Sender<Job>> {
+    POOL.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        for i in 0..BLOCKING_IO_THREADS {
+            let rx = Arc::clone(&rx);
+            let spawned = std::thread::Builder::new()
+                .name(format!("keepkey-io-{}", i))
+                .spawn(move || loop {
+                    // Only one idle thread waits on the channel at a time
+                    let job = match rx.lock() {
+                        Ok(rx) => rx.recv(),
+                        Err(poisoned) => poisoned.into_inner().recv(),
+                    };
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => return,
+                    }
+                });
+            if let Err(e) = spawned {
+                error!("Failed to start blocking I/O thread {}: {}", i, e);
+            }
+        }
+        Mutex::new(tx)
+    })
+}
+
+/// Snapshot of the blocking I/O pool
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BlockingIoStats {
+    pub threads: usize,
+    /// Jobs running right now
+    pub busy: u64,
+    /// Jobs submitted but not yet started
+    pub queued: u64,
+    pub completed: u64,
+    pub panicked: u64,
+    pub avg_queue_wait_ms: u64,
+    pub max_queue_wait_ms: u64,
+}
+
+pub fn blocking_io_stats() -> BlockingIoStats {
+    let c = counters();
+    let submitted = c.submitted.load(Ordering::Relaxed);
+    let completed = c.completed.load(Ordering::Relaxed);
+    let busy = c.busy.load(Ordering::Relaxed);
+    let started = completed + busy;
+    BlockingIoStats {
+        threads: BLOCKING_IO_THREADS,
+        busy,
+        queued: submitted.saturating_sub(started),
+        completed,
+        panicked: c.panicked.load(Ordering::Relaxed),
+        avg_queue_wait_ms: c.queue_wait_ms_total.load(Ordering::Relaxed).checked_div(started).unwrap_or(0),
+        max_queue_wait_ms: c.queue_wait_ms_max.load(Ordering::Relaxed),
+    }
+}
+
+/// Run `job` on the pool and wait for its result without blocking the caller's thread
+pub(crate) async fn run<T, F>(job: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let submitted_at = Instant::now();
+    let c = counters();
+    c.submitted.fetch_add(1, Ordering::Relaxed);
+
+    let wrapped: Job = Box::new(move || {
+        let c = counters();
+        let waited_ms = submitted_at.elapsed().as_millis() as u64;
+        c.busy.fetch_add(1, Ordering::Relaxed);
+        c.queue_wait_ms_total.fetch_add(waited_ms, Ordering::Relaxed);
+        c.queue_wait_ms_max.fetch_max(waited_ms, Ordering::Relaxed);
+
+        let result = catch_unwind(AssertUnwindSafe(job));
+
+        c.busy.fetch_sub(1, Ordering::Relaxed);
+        c.completed.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            c.panicked.fetch_add(1, Ordering::Relaxed);
+        }
+        let _ = tx.send(result);
+    });
+
+    let sent = pool()
+        .lock()
+        .map_err(|_| anyhow!("Blocking I/O pool is unavailable"))?
+        .send(wrapped);
+    if sent.is_err() {
+        c.submitted.fetch_sub(1, Ordering::Relaxed);
+        return Err(anyhow!("Blocking I/O pool has no threads"));
+    }
+
+    match rx.await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(_)) => {
+            warn!("Blocking I/O job panicked");
+            Err(anyhow!("Device I/O task panicked"))
+        }
+        Err(_) => Err(anyhow!("Blocking I/O pool dropped the job")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn runs_jobs_off_the_runtime_and_counts_them() {
+        let before = blocking_io_stats();
+        let name = run(|| std::thread::current().name().map(str::to_string)).await.unwrap();
+        assert!(name.unwrap().starts_with("keepkey-io-"));
+
+        assert!(run(|| -> u8 { panic!("transport blew up") }).await.is_err());
+
+        let after = blocking_io_stats();
+        assert_eq!(after.threads, BLOCKING_IO_THREADS);
+        assert!(after.completed >= before.completed + 2);
+        assert!(after.panicked > before.panicked);
+    }
+}
@@ -2573,13 +2573,13 @@ pub async fn sync_device_xpubs(
         log::info!("📡 Requesting xpub for {} ({})", path_info.path, path_info.label);
         
         // Parse derivation path to vector
-        let derivation_path = crate::utils::parse_derivation_path(&path_info.path).map_err(|e| {
+        let derivation_path = path_info.path.parse::<crate::derivation_path::DerivationPath>().map_err(|e| {
             log::error!("Failed to parse derivation path {}: {}", path_info.path, e);
             format!("Invalid derivation path: {}", e)
         })?;
         
         // Use the device queue to get address (which contains xpub info for account level)
-        match queue_handle.get_address(derivation_path, "Bitcoin".to_string(), None).await {
+        match queue_handle.get_address(derivation_path.into_vec(), "Bitcoin".to_string(), None).await {
             Ok(response) => {
                 log::info!("✅ Got response for {}: {}", path_info.path, response);
                 
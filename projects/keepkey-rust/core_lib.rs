@@ -1,7 +1,58 @@
 //! Core, headless KeepKey library – no Tauri/UI code.
+//!
+//! The protocol layer (message encoding, firmware capability checks and, with
+//! the `psbt` feature, BIP-174 signing) has no USB dependencies. Everything that
+//! talks to a device sits behind the default `usb` feature (`hid` adds the HID
+//! fallback, `queue` the async device worker), so `--no-default-features` builds
+//! for `wasm32-unknown-unknown` and web tools can pair the same encoding with
+//! WebUSB.
+//!
+//! # Stability
+//!
+//! [`prelude`], `features`, `hotplug`, `device_identity`, `device_queue`,
+//! `diagnostics`, `protocol_trace`, `request_journal`, [`friendly_usb`], [`protocol`],
+//! [`derivation_path`], [`progress`], [`failure`], `psbt`, `multisig` and the
+//! message types in [`messages`] follow semver: a breaking change to them
+//! needs a major version bump. `tests/public_api.txt` records their public items, and
+//! `tests/public_api.rs` fails when the list changes so that API changes are
+//! visible in review.
+//!
+//! `transport` and `debug_link` are hidden from the docs. They hand out rusb
+//! devices and raw transports, and they may change in any release. Use
+//! `DeviceQueueFactory` / `DeviceQueueHandle` to talk to devices.
 
+pub mod prelude;
 pub mod friendly_usb;
 pub mod messages;
+pub mod protocol;
+pub mod derivation_path;
+pub mod progress;
+pub mod failure;
+#[cfg(feature = "psbt")]
+pub mod psbt;
+#[cfg(feature = "psbt")]
+pub mod multisig;
+#[cfg(feature = "usb")]
+#[doc(hidden)]
 pub mod transport;
+#[cfg(feature = "usb")]
 pub mod features;
+#[cfg(feature = "usb")]
+pub mod hotplug;
+#[cfg(feature = "usb")]
+pub mod protocol_trace;
+#[cfg(feature = "usb")]
+pub mod device_identity;
+#[cfg(feature = "queue")]
 pub mod device_queue;
+#[cfg(feature = "queue")]
+pub mod diagnostics;
+#[cfg(feature = "journal")]
+pub mod request_journal;
+#[cfg(feature = "queue")]
+mod blocking_io;
+#[cfg(feature = "usb")]
+#[doc(hidden)]
+pub mod debug_link;
+#[cfg(feature = "python")]
+mod python;
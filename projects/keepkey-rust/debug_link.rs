@@ -0,0 +1,299 @@
+//! DEBUG_LINK automation for hardware-in-the-loop test rigs.
+//!
+//! Firmware built with DEBUG_LINK exposes a second USB interface that can read
+//! internal device state and inject button presses. [`DebugLinkSession`] wraps
+//! that interface so signing and recovery flows can run without a human at the
+//! device: button requests are confirmed, PIN matrices are decoded from the
+//! debug state, and passphrases are answered from the session configuration.
+//!
+//! An [`AutoConfirmPolicy`] does the same for the device queue, so unattended
+//! CI suites can run the display-confirmation paths of the app or REST API.
+//! It is off unless set for the run (see [`AUTO_CONFIRM_ENV`]) and only acts
+//! on devices whose DEBUG_LINK interface opens; release firmware has none.
+
+use crate::messages::{self, ButtonRequest, ButtonRequestType, Message};
+use crate::transport::{ProtocolAdapter, UsbTransport};
+use anyhow::{anyhow, bail, Result};
+use log::{info, warn};
+use rusb::{Device, GlobalContext};
+use std::sync::{OnceLock, RwLock};
+
+/// USB interface index of the DEBUG_LINK endpoint pair
+pub const DEBUG_LINK_INTERFACE: usize = 1;
+
+/// Environment variable holding the [`AutoConfirmPolicy`] for this run, e.g.
+/// `confirm`, `reject` or `confirm,WipeDevice=reject`
+pub const AUTO_CONFIRM_ENV: &str = "KEEPKEY_DEBUG_LINK_AUTO_CONFIRM";
+
+/// Which ButtonRequests to answer through DEBUG_LINK instead of waiting for a
+/// real button press
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AutoConfirmPolicy {
+    /// Decision for buttons without an override; `None` leaves them to a person
+    pub default: Option<bool>,
+    /// Decisions for specific screens, e.g. reject `WipeDevice` while
+    /// confirming everything else
+    pub overrides: Vec<(ButtonRequestType, bool)>,
+}
+
+impl AutoConfirmPolicy {
+    pub fn confirm_all() -> Self {
+        Self { default: Some(true), overrides: Vec::new() }
+    }
+
+    pub fn reject_all() -> Self {
+        Self { default: Some(false), overrides: Vec::new() }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.default.is_some() || !self.overrides.is_empty()
+    }
+
+    /// The button to press for `request`, or `None` to wait for a person
+    pub fn decide(&self, request: &ButtonRequest) -> Option<bool> {
+        let code = request.code.and_then(ButtonRequestType::from_i32);
+        self.overrides
+            .iter()
+            .find(|(button, _)| Some(*button) == code)
+            .map(|(_, yes)| *yes)
+            .or(self.default)
+    }
+
+    /// Parse a comma-separated policy: `confirm`, `reject` or `off` sets the
+    /// default, `<ButtonRequest>=confirm|reject` overrides one screen. Button
+    /// names are the protobuf ones, with or without the `ButtonRequest_` prefix.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut policy = Self::default();
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            match item.split_once('=') {
+                None => policy.default = parse_decision(item)?,
+                Some((button, decision)) => {
+                    let button = button.trim();
+                    let name = if button.starts_with("ButtonRequest_") {
+                        button.to_string()
+                    } else {
+                        format!("ButtonRequest_{}", button)
+                    };
+                    let button = ButtonRequestType::from_str_name(&name)
+                        .ok_or_else(|| anyhow!("unknown ButtonRequest type {:?}", button))?;
+                    let yes = parse_decision(decision.trim())?
+                        .ok_or_else(|| anyhow!("{:?} needs confirm or reject", item))?;
+                    policy.overrides.push((button, yes));
+                }
+            }
+        }
+        Ok(policy)
+    }
+
+    /// The policy in [`AUTO_CONFIRM_ENV`]; off when unset or invalid
+    pub fn from_env() -> Self {
+        match std::env::var(AUTO_CONFIRM_ENV) {
+            Ok(spec) => Self::parse(&spec).unwrap_or_else(|e| {
+                warn!("Ignoring {}={:?}: {}", AUTO_CONFIRM_ENV, spec, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+fn parse_decision(value: &str) -> Result<Option<bool>> {
+    match value.to_ascii_lowercase().as_str() {
+        "confirm" | "yes" | "1" | "true" => Ok(Some(true)),
+        "reject" | "no" => Ok(Some(false)),
+        "off" | "0" | "false" | "" => Ok(None),
+        other => bail!("unknown decision {:?} (expected confirm, reject or off)", other),
+    }
+}
+
+fn auto_confirm_state() -> &'static RwLock<AutoConfirmPolicy> {
+    static POLICY: OnceLock<RwLock<AutoConfirmPolicy>> = OnceLock::new();
+    POLICY.get_or_init(|| RwLock::new(AutoConfirmPolicy::from_env()))
+}
+
+/// Replace the run's policy (initially read from [`AUTO_CONFIRM_ENV`]).
+/// Device queue workers pick it up when they next open the device.
+pub fn set_auto_confirm_policy(policy: AutoConfirmPolicy) {
+    if policy.is_active() {
+        warn!("DEBUG_LINK auto-confirmation enabled: {:?}", policy);
+    }
+    *auto_confirm_state().write().unwrap_or_else(|e| e.into_inner()) = policy;
+}
+
+pub fn auto_confirm_policy() -> AutoConfirmPolicy {
+    auto_confirm_state().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Open the normal and DEBUG_LINK interfaces of a device over USB.
+///
+/// Fails if the firmware was not built with DEBUG_LINK support.
+pub fn open_usb_debug_link(
+    device: &Device<GlobalContext>,
+) -> Result<(UsbTransport<GlobalContext>, UsbTransport<GlobalContext>)> {
+    let (transport, config_descriptor, handle) = UsbTransport::new(device, 0)?;
+    let debug_transport =
+        UsbTransport::new_from_descriptor_and_handle(&config_descriptor, handle, DEBUG_LINK_INTERFACE)
+            .map_err(|e| anyhow!("DEBUG_LINK interface not available ({}); is this a debug firmware?", e))?;
+    Ok((transport, debug_transport))
+}
+
+/// Translate a PIN into matrix positions using the scrambled matrix reported by
+/// `DebugLinkState.matrix`; the firmware maps position `n` back to `matrix[n - 1]`.
+pub fn encode_pin(pin: &str, matrix: &str) -> Result<String> {
+    pin.chars()
+        .map(|digit| {
+            matrix
+                .chars()
+                .position(|c| c == digit)
+                .map(|pos| char::from(b'1' + pos as u8))
+                .ok_or_else(|| anyhow!("PIN digit {:?} not present in matrix", digit))
+        })
+        .collect()
+}
+
+/// Drives a device through its DEBUG_LINK interface.
+pub struct DebugLinkSession<'a> {
+    debug: &'a mut dyn ProtocolAdapter,
+    policy: AutoConfirmPolicy,
+    pin: Option<String>,
+    passphrase: Option<String>,
+}
+
+impl<'a> DebugLinkSession<'a> {
+    pub fn new(debug: &'a mut dyn ProtocolAdapter) -> Self {
+        Self {
+            debug,
+            policy: AutoConfirmPolicy::confirm_all(),
+            pin: None,
+            passphrase: None,
+        }
+    }
+
+    /// Answer every ButtonRequest with this decision (default: confirm)
+    pub fn with_decision(mut self, yes: bool) -> Self {
+        self.policy = AutoConfirmPolicy { default: Some(yes), overrides: Vec::new() };
+        self
+    }
+
+    /// Answer ButtonRequests per `policy`; buttons it leaves undecided wait
+    /// for a real press
+    pub fn with_policy(mut self, policy: AutoConfirmPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// PIN to enter when the device shows a PIN matrix
+    pub fn with_pin(mut self, pin: impl Into<String>) -> Self {
+        self.pin = Some(pin.into());
+        self
+    }
+
+    /// Passphrase to answer PassphraseRequest with (default: empty)
+    pub fn with_passphrase(mut self, passphrase: impl Into<String>) -> Self {
+        self.passphrase = Some(passphrase.into());
+        self
+    }
+
+    /// Read the device's internal state (layout, PIN matrix, mnemonic, ...)
+    pub fn get_state(&mut self) -> Result<messages::DebugLinkState> {
+        match self.debug.handle(messages::DebugLinkGetState {}.into())? {
+            Message::DebugLinkState(state) => Ok(state),
+            other => bail!("unexpected message ({:?})", other.message_type()),
+        }
+    }
+
+    /// Press the device button; `true` confirms, `false` cancels
+    pub fn press_button(&mut self, yes: bool) -> Result<()> {
+        self.debug.send(messages::DebugLinkDecision { yes_no: yes }.into())
+    }
+
+    pub fn press_yes(&mut self) -> Result<()> {
+        self.press_button(true)
+    }
+
+    pub fn press_no(&mut self) -> Result<()> {
+        self.press_button(false)
+    }
+
+    /// Fill the storage sectors with a dummy pattern (DEBUG_LINK bootloader only)
+    pub fn fill_config(&mut self) -> Result<()> {
+        self.debug.send(messages::DebugLinkFillConfig {}.into())
+    }
+
+    /// Ask the firmware to halt
+    pub fn stop(&mut self) -> Result<()> {
+        self.debug.send(messages::DebugLinkStop {}.into())
+    }
+
+    /// Send `msg` on the normal interface and drive any interaction the device
+    /// asks for until it produces a final response.
+    pub fn call(&mut self, main: &mut dyn ProtocolAdapter, msg: Message) -> Result<Message> {
+        let policy = self.policy.clone();
+        let pin = self.pin.clone();
+        let passphrase = self.passphrase.clone().unwrap_or_default();
+        let debug = &mut *self.debug;
+
+        let mut handler = |resp: &Message| -> Result<Option<Message>> {
+            Ok(match resp {
+                Message::ButtonRequest(req) => {
+                    if let Some(decision) = policy.decide(req) {
+                        info!("DebugLink: pressing {} for ButtonRequest {:?}", if decision { "yes" } else { "no" }, req.code);
+                        // The decision is buffered by the firmware until it starts waiting for the button
+                        debug.send(messages::DebugLinkDecision { yes_no: decision }.into())?;
+                    }
+                    Some(messages::ButtonAck::default().into())
+                }
+                Message::PinMatrixRequest(_) => {
+                    let pin = pin.as_deref().ok_or_else(|| anyhow!("device requested a PIN but the session has none"))?;
+                    let state = match debug.handle(messages::DebugLinkGetState {}.into())? {
+                        Message::DebugLinkState(state) => state,
+                        other => bail!("unexpected message ({:?})", other.message_type()),
+                    };
+                    let matrix = state.matrix.ok_or_else(|| anyhow!("DebugLinkState did not include a PIN matrix"))?;
+                    Some(messages::PinMatrixAck { pin: encode_pin(pin, &matrix)? }.into())
+                }
+                Message::PassphraseRequest(_) => Some(messages::PassphraseAck { passphrase: passphrase.clone() }.into()),
+                Message::Failure(x) => bail!("Failure: {}", x.message()),
+                _ => None,
+            })
+        };
+
+        let result = main.with_mut_handler(&mut handler).handle(msg);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_pin_identity_matrix() {
+        assert_eq!(encode_pin("1234", "123456789").unwrap(), "1234");
+    }
+
+    #[test]
+    fn encode_pin_scrambled_matrix() {
+        assert_eq!(encode_pin("159", "951847362").unwrap(), "321");
+    }
+
+    #[test]
+    fn encode_pin_rejects_unknown_digit() {
+        assert!(encode_pin("0", "123456789").is_err());
+    }
+
+    #[test]
+    fn auto_confirm_policy_overrides_per_button() {
+        let button = |code: ButtonRequestType| ButtonRequest { code: Some(code as i32), ..Default::default() };
+        let policy = AutoConfirmPolicy::parse("confirm, WipeDevice=reject").unwrap();
+        assert_eq!(policy.decide(&button(ButtonRequestType::ButtonRequestSignTx)), Some(true));
+        assert_eq!(policy.decide(&button(ButtonRequestType::ButtonRequestWipeDevice)), Some(false));
+
+        let only_addresses = AutoConfirmPolicy::parse("ButtonRequest_Address=yes").unwrap();
+        assert_eq!(only_addresses.decide(&button(ButtonRequestType::ButtonRequestAddress)), Some(true));
+        assert_eq!(only_addresses.decide(&button(ButtonRequestType::ButtonRequestSignTx)), None);
+
+        assert!(!AutoConfirmPolicy::parse("off").unwrap().is_active());
+        assert!(AutoConfirmPolicy::parse("Teleport=confirm").is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! BIP-32 derivation paths.
+//!
+//! Messages carry paths as `address_n: Vec<u32>` with the hardened bit set on
+//! hardened components. [`DerivationPath`] wraps that list and converts it
+//! to and from the `m/84'/0'/0'/0/0` form people and APIs use. It serializes
+//! as the string form and deserializes from either the string or the raw
+//! `u32` array, so stored rows and REST bodies can use whichever they already
+//! have.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+/// Bit set on hardened path components
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// `index'`, as stored in `address_n`
+pub const fn hardened(index: u32) -> u32 {
+    index | HARDENED
+}
+
+pub const fn is_hardened(component: u32) -> bool {
+    component & HARDENED != 0
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DerivationPathError {
+    #[error("derivation path {0:?} must start with \"m\"")]
+    MissingRoot(String),
+    #[error("invalid component {component:?} in derivation path {path:?}")]
+    InvalidComponent { path: String, component: String },
+    /// The index already has the hardened bit set, e.g. `2147483648'`
+    #[error("index {index} in derivation path {path:?} is out of range")]
+    IndexOutOfRange { path: String, index: u32 },
+}
+
+/// A BIP-32 path such as `m/84'/0'/0'/0/0`
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// The master key, `m`
+    pub const fn master() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn as_slice(&self) -> &[u32] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<u32> {
+        self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// This path extended by `component` (pass [`hardened`] for `index'`)
+    pub fn child(&self, component: u32) -> Self {
+        let mut components = self.0.clone();
+        components.push(component);
+        Self(components)
+    }
+
+    /// This path extended by several components, e.g. `[0, 5]` for receive address 5
+    pub fn extend(&self, components: impl IntoIterator<Item = u32>) -> Self {
+        let mut path = self.0.clone();
+        path.extend(components);
+        Self(path)
+    }
+
+    /// The path one level up; `None` for `m`
+    pub fn parent(&self) -> Option<Self> {
+        self.0.split_last().map(|(_, parent)| Self(parent.to_vec()))
+    }
+
+    /// Unhardened index at `depth` (0 is purpose, 1 coin type, 2 account)
+    pub fn index_at(&self, depth: usize) -> Option<u32> {
+        self.0.get(depth).map(|component| component & !HARDENED)
+    }
+
+    /// BIP-44 style account index (third component)
+    pub fn account(&self) -> Option<u32> {
+        self.index_at(2)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for &component in &self.0 {
+            if is_hardened(component) {
+                write!(f, "/{}'", component & !HARDENED)?;
+            } else {
+                write!(f, "/{}", component)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = DerivationPathError;
+
+    /// Accepts `'`, `h` or `H` for hardened components; `m` and `m/` are the master key
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        let rest = match trimmed.strip_prefix('m').or_else(|| trimmed.strip_prefix('M')) {
+            Some(rest) => rest,
+            None => return Err(DerivationPathError::MissingRoot(value.to_string())),
+        };
+        let rest = match rest.strip_prefix('/') {
+            Some(rest) => rest,
+            None if rest.is_empty() => rest,
+            None => return Err(DerivationPathError::MissingRoot(value.to_string())),
+        };
+        if rest.is_empty() {
+            return Ok(Self::master());
+        }
+
+        let mut components = Vec::new();
+        for component in rest.split('/') {
+            let (digits, is_hardened) = match component.strip_suffix(['\'', 'h', 'H']) {
+                Some(digits) => (digits, true),
+                None => (component, false),
+            };
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(DerivationPathError::InvalidComponent {
+                    path: value.to_string(),
+                    component: component.to_string(),
+                });
+            }
+            let index: u32 = digits.parse().map_err(|_| DerivationPathError::InvalidComponent {
+                path: value.to_string(),
+                component: component.to_string(),
+            })?;
+            if index >= HARDENED {
+                return Err(DerivationPathError::IndexOutOfRange { path: value.to_string(), index });
+            }
+            components.push(if is_hardened { hardened(index) } else { index });
+        }
+        Ok(Self(components))
+    }
+}
+
+impl From<Vec<u32>> for DerivationPath {
+    fn from(components: Vec<u32>) -> Self {
+        Self(components)
+    }
+}
+
+impl From<&[u32]> for DerivationPath {
+    fn from(components: &[u32]) -> Self {
+        Self(components.to_vec())
+    }
+}
+
+impl From<DerivationPath> for Vec<u32> {
+    fn from(path: DerivationPath) -> Self {
+        path.0
+    }
+}
+
+impl AsRef<[u32]> for DerivationPath {
+    fn as_ref(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl Serialize for DerivationPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DerivationPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PathVisitor;
+
+        impl<'de> Visitor<'de> for PathVisitor {
+            type Value = DerivationPath;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a derivation path string like \"m/84'/0'/0'\" or an array of u32 components")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                value.parse().map_err(E::custom)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut components = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(component) = seq.next_element::<u32>()? {
+                    components.push(component);
+                }
+                Ok(DerivationPath(components))
+            }
+        }
+
+        deserializer.deserialize_any(PathVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_round_trip() {
+        let path: DerivationPath = "m/84'/0'/0'/0/5".parse().unwrap();
+        assert_eq!(path.as_slice(), &[hardened(84), hardened(0), hardened(0), 0, 5]);
+        assert_eq!(path.to_string(), "m/84'/0'/0'/0/5");
+        assert_eq!(path.account(), Some(0));
+
+        let h_form: DerivationPath = "m/44h/0H/1'".parse().unwrap();
+        assert_eq!(h_form.to_string(), "m/44'/0'/1'");
+        assert_eq!("m".parse::<DerivationPath>().unwrap(), DerivationPath::master());
+        assert_eq!("m/".parse::<DerivationPath>().unwrap(), DerivationPath::master());
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        assert!(matches!("44'/0'".parse::<DerivationPath>(), Err(DerivationPathError::MissingRoot(_))));
+        assert!(matches!("m44'".parse::<DerivationPath>(), Err(DerivationPathError::MissingRoot(_))));
+        for bad in ["m/44'//0", "m/-1", "m/0x10", "m/'", "m/1''"] {
+            assert!(bad.parse::<DerivationPath>().is_err(), "{} should not parse", bad);
+        }
+        assert!(matches!(
+            "m/2147483648'".parse::<DerivationPath>(),
+            Err(DerivationPathError::IndexOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn serde_accepts_string_or_array() {
+        let from_str: DerivationPath = serde_json::from_str("\"m/49'/0'/0'\"").unwrap();
+        let from_array: DerivationPath = serde_json::from_str("[2147483697, 2147483648, 2147483648]").unwrap();
+        assert_eq!(from_str, from_array);
+        assert_eq!(serde_json::to_string(&from_array).unwrap(), "\"m/49'/0'/0'\"");
+    }
+}
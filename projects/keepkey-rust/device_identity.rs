@@ -0,0 +1,360 @@
+//! Stable ids for KeepKeys that report no USB serial number.
+//!
+//! Such devices used to be named after their bus address, which changes on
+//! every replug, so each reconnect looked like a new device and two of them
+//! could not be told apart. Instead each one now gets a sticky slot
+//! (`keepkey_slot1`, `keepkey_slot2`, ...) when it appears: it takes the free
+//! slot last used at its USB port, otherwise the free slot seen most
+//! recently, and a new slot is only opened while every slot is in use. The
+//! number of slots therefore stays at the number of such devices attached at
+//! once.
+//!
+//! Which slot a device gets before it is unlocked is a guess. Once it is
+//! unlocked, [`bind_wallet_fingerprint`] ties the slot to the wallet's master
+//! fingerprint and moves the device to the slot of that wallet when it was
+//! given another one.
+//!
+//! Slots live in memory until [`load_identity_slots`] names a file to keep
+//! them in.
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use rusb::{Device, UsbContext};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const SLOT_ID_PREFIX: &str = "keepkey_slot";
+
+static SLOTS: Lazy<Mutex<SlotRegistry>> = Lazy::new(|| Mutex::new(SlotRegistry::default()));
+
+fn slots() -> std::sync::MutexGuard<'static, SlotRegistry> {
+    SLOTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Keep slots in `path` (created on first change) from now on, starting
+/// from the slots already saved there
+pub fn load_identity_slots(path: impl Into<PathBuf>) -> Result<()> {
+    let loaded = SlotRegistry::load(path.into())?;
+    let mut slots = slots();
+    log::info!("Loaded {} identity slot(s) for KeepKeys without a serial number", loaded.slots.len());
+    *slots = loaded;
+    Ok(())
+}
+
+/// Every slot, attached or not
+pub fn identity_slots() -> Vec<IdentitySlot> {
+    slots().slots.clone()
+}
+
+/// Tie the wallet now unlocked on `device_id` to its slot; see [`Binding`]
+pub fn bind_wallet_fingerprint(device_id: &str, fingerprint: &str) -> Result<Binding> {
+    slots().bind(device_id, fingerprint)
+}
+
+/// Drop a slot that is not in use. `false` if there is no such detached slot.
+pub fn forget_identity_slot(device_id: &str) -> Result<bool> {
+    slots().forget(device_id)
+}
+
+pub fn is_slot_id(device_id: &str) -> bool {
+    device_id.starts_with(SLOT_ID_PREFIX)
+}
+
+/// Ids for the serial-less KeepKeys attached right now, in the same order
+pub(crate) fn assign_slots(devices: &[Attachment]) -> Vec<String> {
+    slots().assign(devices)
+}
+
+/// Bus and address of the device behind `device_id`: the device holding the
+/// slot, or the address spelled out in an id of the older `..._bus1_addr5` form
+pub(crate) fn usb_address(device_id: &str) -> Option<(u8, u8)> {
+    if is_slot_id(device_id) {
+        return slots().attached_at(device_id);
+    }
+    let part = |prefix: &str| device_id.split('_').find_map(|p| p.strip_prefix(prefix)?.parse::<u8>().ok());
+    Some((part("bus")?, part("addr")?))
+}
+
+/// Where a device is plugged in. Unlike the bus address this stays the same
+/// when the device is replugged into the same port.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsbPort {
+    pub bus: u8,
+    /// Hub port numbers from the root port down
+    pub ports: Vec<u8>,
+}
+
+impl UsbPort {
+    pub(crate) fn of<T: UsbContext>(device: &Device<T>) -> Option<Self> {
+        let ports = device.port_numbers().ok()?;
+        Some(Self { bus: device.bus_number(), ports })
+    }
+}
+
+/// A serial-less KeepKey seen on the bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Attachment {
+    pub bus: u8,
+    pub address: u8,
+    pub port: Option<UsbPort>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentitySlot {
+    pub id: String,
+    pub last_port: Option<UsbPort>,
+    /// Master fingerprint of the wallet last unlocked in this slot
+    pub wallet_fingerprint: Option<String>,
+    /// Unix seconds
+    pub last_seen: i64,
+}
+
+/// Outcome of [`bind_wallet_fingerprint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    /// The slot already belonged to this wallet, or to none yet
+    Bound,
+    /// The slot belongs to another wallet (or the wallet has a slot of its
+    /// own), so the device now holds `to`. Device lists report it under the
+    /// new id from the next scan on.
+    Moved { from: String, to: String },
+}
+
+#[derive(Default)]
+struct SlotRegistry {
+    path: Option<PathBuf>,
+    slots: Vec<IdentitySlot>,
+    // (bus, address) of each attached device -> slot id
+    attached: HashMap<(u8, u8), String>,
+}
+
+impl SlotRegistry {
+    fn load(path: PathBuf) -> Result<Self> {
+        let slots = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| anyhow!("Corrupt identity slots in {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path: Some(path), slots, attached: HashMap::new() })
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else { return };
+        if let Err(e) = write_json(path, &self.slots) {
+            log::warn!("Failed to save identity slots to {}: {}", path.display(), e);
+        }
+    }
+
+    fn is_attached(&self, id: &str) -> bool {
+        self.attached.values().any(|attached| attached == id)
+    }
+
+    fn attached_at(&self, id: &str) -> Option<(u8, u8)> {
+        self.attached.iter().find(|(_, attached)| *attached == id).map(|(key, _)| *key)
+    }
+
+    fn slot_mut(&mut self, id: &str) -> Option<&mut IdentitySlot> {
+        self.slots.iter_mut().find(|slot| slot.id == id)
+    }
+
+    fn assign(&mut self, devices: &[Attachment]) -> Vec<String> {
+        let now = unix_now();
+        self.attached.retain(|key, _| devices.iter().any(|d| (d.bus, d.address) == *key));
+
+        let mut changed = false;
+        let mut ids = Vec::with_capacity(devices.len());
+        for device in devices {
+            let key = (device.bus, device.address);
+            let id = match self.attached.get(&key) {
+                Some(id) => id.clone(),
+                None => {
+                    let id = self.claim(device.port.as_ref());
+                    log::info!("KeepKey without a serial number at bus {} address {} is {}", device.bus, device.address, id);
+                    self.attached.insert(key, id.clone());
+                    changed = true;
+                    id
+                }
+            };
+            if let Some(slot) = self.slot_mut(&id) {
+                if device.port.is_some() && slot.last_port != device.port {
+                    slot.last_port = device.port.clone();
+                    changed = true;
+                }
+                slot.last_seen = now;
+            }
+            ids.push(id);
+        }
+        if changed {
+            self.save();
+        }
+        ids
+    }
+
+    /// A free slot for a device that just appeared at `port`, opening a new
+    /// one when all are in use
+    fn claim(&mut self, port: Option<&UsbPort>) -> String {
+        let free = self.slots.iter().filter(|slot| !self.is_attached(&slot.id));
+        let same_port = free.clone().find(|slot| port.is_some() && slot.last_port.as_ref() == port);
+        if let Some(slot) = same_port.or_else(|| free.max_by_key(|slot| slot.last_seen)) {
+            return slot.id.clone();
+        }
+        self.open_slot(port.cloned())
+    }
+
+    fn open_slot(&mut self, last_port: Option<UsbPort>) -> String {
+        let next = self
+            .slots
+            .iter()
+            .filter_map(|slot| slot.id.strip_prefix(SLOT_ID_PREFIX)?.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let id = format!("{}{}", SLOT_ID_PREFIX, next);
+        self.slots.push(IdentitySlot { id: id.clone(), last_port, wallet_fingerprint: None, last_seen: unix_now() });
+        id
+    }
+
+    fn bind(&mut self, id: &str, fingerprint: &str) -> Result<Binding> {
+        let key = self.attached_at(id).ok_or_else(|| anyhow!("{} is not attached", id))?;
+        let slot = self.slots.iter().find(|slot| slot.id == id).cloned().ok_or_else(|| anyhow!("No identity slot {}", id))?;
+        if slot.wallet_fingerprint.as_deref() == Some(fingerprint) {
+            return Ok(Binding::Bound);
+        }
+
+        // The wallet's own slot, unless another device with the same seed holds it
+        let owner = self
+            .slots
+            .iter()
+            .find(|other| other.id != id && other.wallet_fingerprint.as_deref() == Some(fingerprint) && !self.is_attached(&other.id))
+            .map(|other| other.id.clone());
+        let to = match (owner, &slot.wallet_fingerprint) {
+            (None, None) => {
+                if let Some(slot) = self.slot_mut(id) {
+                    slot.wallet_fingerprint = Some(fingerprint.to_string());
+                }
+                self.save();
+                return Ok(Binding::Bound);
+            }
+            (Some(owner), wallet) => {
+                // A slot that never held a wallet was only a placeholder
+                if wallet.is_none() {
+                    self.slots.retain(|other| other.id != id);
+                }
+                owner
+            }
+            (None, Some(_)) => {
+                let to = self.open_slot(None);
+                if let Some(new) = self.slot_mut(&to) {
+                    new.wallet_fingerprint = Some(fingerprint.to_string());
+                }
+                to
+            }
+        };
+        if let Some(moved) = self.slot_mut(&to) {
+            moved.last_port = slot.last_port.clone();
+            moved.last_seen = unix_now();
+        }
+        self.attached.insert(key, to.clone());
+        self.drop_stale_duplicates();
+        self.save();
+        log::info!("Wallet {} belongs in {}; moved it there from {}", fingerprint, to, id);
+        Ok(Binding::Moved { from: id.to_string(), to })
+    }
+
+    /// Detached slots of a wallet that is attached in another slot
+    fn drop_stale_duplicates(&mut self) {
+        let attached: Vec<(String, Option<String>)> = self
+            .slots
+            .iter()
+            .filter(|slot| self.is_attached(&slot.id))
+            .map(|slot| (slot.id.clone(), slot.wallet_fingerprint.clone()))
+            .collect();
+        self.slots.retain(|slot| {
+            slot.wallet_fingerprint.is_none()
+                || attached.iter().any(|(id, _)| *id == slot.id)
+                || !attached.iter().any(|(_, wallet)| *wallet == slot.wallet_fingerprint)
+        });
+    }
+
+    fn forget(&mut self, id: &str) -> Result<bool> {
+        if self.is_attached(id) {
+            return Err(anyhow!("{} is attached", id));
+        }
+        let before = self.slots.len();
+        self.slots.retain(|slot| slot.id != id);
+        let removed = self.slots.len() < before;
+        if removed {
+            self.save();
+        }
+        Ok(removed)
+    }
+}
+
+fn write_json(path: &Path, slots: &[IdentitySlot]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(slots)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(address: u8, port: u8) -> Attachment {
+        Attachment { bus: 1, address, port: Some(UsbPort { bus: 1, ports: vec![port] }) }
+    }
+
+    #[test]
+    fn replugged_devices_keep_their_slot_until_the_wallet_says_otherwise() {
+        let mut registry = SlotRegistry::default();
+        assert_eq!(registry.assign(&[at(5, 1), at(6, 2)]), ["keepkey_slot1", "keepkey_slot2"]);
+        registry.bind("keepkey_slot1", "aaaa0001").unwrap();
+        registry.bind("keepkey_slot2", "bbbb0002").unwrap();
+
+        // Both unplugged, then replugged at new addresses in swapped ports:
+        // the port decides before unlock
+        assert!(registry.assign(&[]).is_empty());
+        assert_eq!(registry.assign(&[at(9, 2), at(10, 1)]), ["keepkey_slot2", "keepkey_slot1"]);
+
+        // Unlocking shows the device in port 2 holds wallet aaaa0001 after all
+        // (the devices were swapped), and slot1 is taken by the other device
+        assert_eq!(registry.bind("keepkey_slot2", "aaaa0001").unwrap(), Binding::Moved { from: "keepkey_slot2".into(), to: "keepkey_slot3".into() });
+        assert_eq!(registry.assign(&[at(9, 2), at(10, 1)]), ["keepkey_slot3", "keepkey_slot1"]);
+        assert_eq!(registry.bind("keepkey_slot1", "bbbb0002").unwrap(), Binding::Moved { from: "keepkey_slot1".into(), to: "keepkey_slot2".into() });
+        assert_eq!(registry.assign(&[at(9, 2), at(10, 1)]), ["keepkey_slot3", "keepkey_slot2"]);
+
+        // The first slot of wallet aaaa0001 was a stale duplicate
+        assert_eq!(registry.slots.len(), 2);
+
+        // Many replugs of one device never add slots
+        for address in 20..30 {
+            assert_eq!(registry.assign(&[at(address, 3)]).len(), 1);
+        }
+        assert_eq!(registry.slots.len(), 2);
+
+        // A wallet that came back on a placeholder slot is moved to its own
+        let mut registry = SlotRegistry::default();
+        registry.assign(&[at(5, 1)]);
+        registry.bind("keepkey_slot1", "aaaa0001").unwrap();
+        assert_eq!(registry.assign(&[at(6, 1), at(7, 2)]), ["keepkey_slot1", "keepkey_slot2"]);
+        registry.assign(&[at(7, 2)]);
+        assert_eq!(registry.bind("keepkey_slot2", "aaaa0001").unwrap(), Binding::Moved { from: "keepkey_slot2".into(), to: "keepkey_slot1".into() });
+        assert_eq!(registry.assign(&[at(7, 2)]), ["keepkey_slot1"]);
+        assert_eq!(registry.slots.len(), 1);
+        assert_eq!(registry.attached_at("keepkey_slot1"), Some((1, 7)));
+        assert_eq!(usb_address("keepkey_2b24_0002_bus3_addr17"), Some((3, 17)));
+    }
+}
@@ -1,35 +1,138 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, oneshot};
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::time::{timeout, sleep};
 use anyhow::{anyhow, Result};
 use tracing::{info, warn, error, debug, instrument};
 
-use crate::messages::{Message, GetFeatures, GetAddress, Features};
-use crate::transport::ProtocolAdapter;
+use crate::debug_link::{auto_confirm_policy, AutoConfirmPolicy};
+use crate::diagnostics::{self, DeviceDiagnostic};
+use crate::messages::{Cancel, DebugLinkDecision, Message, MessageType, GetFeatures, GetAddress, GetPublicKey, PublicKey, Features};
+use crate::transport::{pin_flow_message_handler, standard_message_handler, AsyncMessageHandler, AsyncProtocolAdapter, WriteProgress};
 use crate::friendly_usb::FriendlyUsbDevice;
+use crate::protocol::{self, ProtocolVersion};
+use crate::progress::{Progress, ProgressOperation, UpdateStage};
+use crate::psbt::{Fingerprint, Psbt, PsbtSigner, SignedPsbt};
+#[cfg(feature = "journal")]
+use crate::request_journal::{JournalEntry, JournaledRequest};
 
-/// Transport type detection for different KeepKey device modes
-#[derive(Debug, Clone, Copy)]
-enum TransportType {
-    /// Modern WebUSB devices (firmware 7.10.0+) with bulk endpoints
-    WebUsb,
-    /// Traditional USB devices with interrupt endpoints and HID-style protocol
-    TraditionalUsb,
-    /// Legacy devices or fallback mode that only work with HID API
-    HidOnly,
-}
+#[cfg(not(feature = "journal"))]
+type JournalEntry = ();
+
+pub use crate::blocking_io::{blocking_io_stats, BlockingIoStats, BLOCKING_IO_THREADS};
+pub use tokio_util::sync::CancellationToken;
 
 // Default timeouts and limits
-const DEVICE_OPERATION_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time a request may spend waiting for its turn in the queue, on top of its
+/// own time on the device (see [`request_timeout`])
+const QUEUE_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+const PSBT_SIGNING_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 const QUEUE_CHANNEL_SIZE: usize = 100;
 const CACHE_MAX_ENTRIES: usize = 256;
 const CACHE_TTL: Duration = Duration::from_secs(30);
+/// Features fetched this recently are handed out again as long as no other
+/// command has reached the device in between
+const FEATURES_REUSE_WINDOW: Duration = Duration::from_secs(5);
+const DEFAULT_SLOW_REQUEST_THRESHOLD_MS: u64 = 2_000;
+const PROGRESS_CHANNEL_SIZE: usize = 256;
+/// A request that waited longer than this behind other clients counts as starved
+const STARVATION_THRESHOLD: Duration = Duration::from_secs(5);
+/// How long to wait for another DEBUG_LINK log line before the buffer counts as empty
+const DIAGNOSTICS_POLL: Duration = Duration::from_millis(20);
+/// Log lines read per drain, so a chatty device can't hold up the queue
+const DIAGNOSTICS_DRAIN_LIMIT: usize = 64;
+
+/// Which lane a request waits in. Interactive requests (something the user
+/// is looking at) are always served before background ones (frontloading
+/// xpubs, balance sync), so a long frontload doesn't hold up a GetAddress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    #[default]
+    Interactive,
+    Background,
+}
+
+/// Client name used by handles that were not given one with [`DeviceQueueHandle::for_client`]
+pub const DEFAULT_CLIENT: &str = "local";
+
+/// Upper bounds (inclusive) of the latency histogram buckets, in milliseconds.
+/// Anything slower lands in a final overflow bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 12] = [5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+
+static SLOW_REQUEST_THRESHOLD_MS: AtomicU64 = AtomicU64::new(DEFAULT_SLOW_REQUEST_THRESHOLD_MS);
+// 0 = release the transport after every request
+static KEEP_CLAIMED_IDLE_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Requests whose total time (queue wait + device round trip) exceeds this are
+/// logged as warnings with their full timing context
+pub fn set_slow_request_threshold(threshold: Duration) {
+    SLOW_REQUEST_THRESHOLD_MS.store(threshold.as_millis() as u64, Ordering::Relaxed);
+}
+
+pub fn slow_request_threshold() -> Duration {
+    Duration::from_millis(SLOW_REQUEST_THRESHOLD_MS.load(Ordering::Relaxed))
+}
+
+/// Keep each worker's USB interface claimed between requests, releasing it
+/// once the queue has been idle for `idle_release`. Saves the open/claim round
+/// trip and re-enumeration races on bursts of requests (frontloading, sync),
+/// at the cost of holding the device exclusively while the burst lasts.
+/// `None` (the default) releases it after every request.
+pub fn set_keep_claimed(idle_release: Option<Duration>) {
+    let ms = idle_release.map_or(0, |idle| (idle.as_millis() as u64).max(1));
+    KEEP_CLAIMED_IDLE_MS.store(ms, Ordering::Relaxed);
+}
+
+pub fn keep_claimed() -> Option<Duration> {
+    match KEEP_CLAIMED_IDLE_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// Progress reported by one device's worker
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceProgress {
+    pub device_id: String,
+    #[serde(flatten)]
+    pub progress: Progress,
+}
+
+/// Shared by every worker, so one subscriber sees all devices, including
+/// workers spawned after it subscribed
+fn progress_sender() -> &'static broadcast::Sender<DeviceProgress> {
+    static SENDER: OnceLock<broadcast::Sender<DeviceProgress>> = OnceLock::new();
+    SENDER.get_or_init(|| broadcast::channel(PROGRESS_CHANNEL_SIZE).0)
+}
+
+/// Progress for a single device, from [`DeviceQueueHandle::subscribe_progress`]
+pub struct ProgressSubscription {
+    device_id: String,
+    rx: broadcast::Receiver<DeviceProgress>,
+}
+
+impl ProgressSubscription {
+    /// Next progress step for the device. Steps missed by a slow reader are
+    /// skipped rather than replayed.
+    pub async fn recv(&mut self) -> Option<Progress> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) if event.device_id == self.device_id => return Some(event.progress),
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
 
 /// Unique key for caching device responses
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct CacheKey {
+pub(crate) struct CacheKey {
     device_id: String,
     operation: String,
     params_hash: u64,
@@ -48,9 +151,38 @@ impl CacheKey {
     }
 }
 
+/// Key for xpubs remembered for the lifetime of a worker. Unlike [`CacheKey`]
+/// entries these don't expire: a public key only changes with the seed, and the
+/// wallet fingerprint is part of the key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct XpubCacheKey {
+    wallet: u64,
+    address_n: Vec<u32>,
+    coin_name: Option<String>,
+    script_type: Option<i32>,
+    ecdsa_curve_name: Option<String>,
+}
+
+/// Identity of the seed behind a device, from its Features. `None` when xpubs
+/// must not be cached: no seed yet, bootloader mode, or passphrase protection,
+/// where every session may open a different hidden wallet.
+fn wallet_fingerprint(features: &Features) -> Option<u64> {
+    if features.initialized != Some(true)
+        || features.bootloader_mode == Some(true)
+        || features.passphrase_protection == Some(true)
+    {
+        return None;
+    }
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    features.device_id.hash(&mut hasher);
+    features.imported.hash(&mut hasher);
+    features.no_backup.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
 /// Cached response with timestamp
 #[derive(Debug, Clone)]
-pub struct CachedResponse {
+pub(crate) struct CachedResponse {
     value: serde_json::Value,
     timestamp: Instant,
 }
@@ -70,7 +202,7 @@ impl CachedResponse {
 
 /// Commands that can be sent to the device worker
 #[derive(Debug)]
-pub enum DeviceCmd {
+pub(crate) enum DeviceCmd {
     GetFeatures {
         respond_to: oneshot::Sender<Result<Features>>,
         enqueued_at: Instant,
@@ -101,6 +233,12 @@ pub enum DeviceCmd {
         respond_to: oneshot::Sender<Result<bool>>,
         enqueued_at: Instant,
     },
+    SignPsbt {
+        psbt: Box<Psbt>,
+        fingerprint: Option<Fingerprint>,
+        respond_to: oneshot::Sender<Result<SignedPsbt>>,
+        enqueued_at: Instant,
+    },
     Shutdown {
         respond_to: oneshot::Sender<Result<()>>,
     },
@@ -114,6 +252,7 @@ impl DeviceCmd {
             DeviceCmd::SendRaw { enqueued_at, .. } => *enqueued_at,
             DeviceCmd::UpdateBootloader { enqueued_at, .. } => *enqueued_at,
             DeviceCmd::UpdateFirmware { enqueued_at, .. } => *enqueued_at,
+            DeviceCmd::SignPsbt { enqueued_at, .. } => *enqueued_at,
             DeviceCmd::Shutdown { .. } => Instant::now(),
         }
     }
@@ -125,10 +264,19 @@ impl DeviceCmd {
             DeviceCmd::SendRaw { .. } => "send_raw",
             DeviceCmd::UpdateBootloader { .. } => "update_bootloader",
             DeviceCmd::UpdateFirmware { .. } => "update_firmware",
+            DeviceCmd::SignPsbt { .. } => "sign_psbt",
             DeviceCmd::Shutdown { .. } => "shutdown",
         }
     }
     
+    /// Label used for per-message latency metrics; raw messages are broken out by type
+    fn metrics_label(&self) -> String {
+        match self {
+            DeviceCmd::SendRaw { message, .. } => format!("{:?}", message.message_type()),
+            other => other.operation_name().to_string(),
+        }
+    }
+    
     fn should_cache(&self) -> bool {
         match self {
             DeviceCmd::GetFeatures { .. } => true,
@@ -136,13 +284,195 @@ impl DeviceCmd {
             DeviceCmd::SendRaw { bypass_cache, .. } => !*bypass_cache,
             DeviceCmd::UpdateBootloader { .. } => false,
             DeviceCmd::UpdateFirmware { .. } => false,
+            DeviceCmd::SignPsbt { .. } => false,
             DeviceCmd::Shutdown { .. } => false,
         }
     }
 }
 
+/// A command tagged with the client that submitted it, its lane, the token
+/// that cancels it and the caller's request id
+#[derive(Debug)]
+pub(crate) struct QueuedCmd {
+    client: Arc<str>,
+    priority: RequestPriority,
+    cancel: Option<CancellationToken>,
+    request_id: Option<Arc<str>>,
+    cmd: DeviceCmd,
+}
+
+impl QueuedCmd {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+/// Pending commands bucketed per client and served round-robin, so one busy
+/// client cannot keep the device away from the others
+#[derive(Debug, Default)]
+struct FairQueue {
+    pending: HashMap<Arc<str>, VecDeque<QueuedCmd>>,
+    /// Clients with pending commands, in serving order
+    rotation: VecDeque<Arc<str>>,
+}
+
+impl FairQueue {
+    fn push(&mut self, queued: QueuedCmd) {
+        let queue = self.pending.entry(queued.client.clone()).or_default();
+        if queue.is_empty() {
+            self.rotation.push_back(queued.client.clone());
+        }
+        queue.push_back(queued);
+    }
+    
+    /// Oldest command of the next client in turn
+    fn pop(&mut self) -> Option<QueuedCmd> {
+        let client = self.rotation.pop_front()?;
+        let queue = self.pending.get_mut(&client)?;
+        let queued = queue.pop_front()?;
+        if queue.is_empty() {
+            self.pending.remove(&client);
+        } else {
+            self.rotation.push_back(client);
+        }
+        Some(queued)
+    }
+    
+    fn len(&self) -> usize {
+        self.pending.values().map(VecDeque::len).sum()
+    }
+    
+    fn pending_for(&self, client: &str) -> usize {
+        self.pending.get(client).map_or(0, VecDeque::len)
+    }
+    
+    /// Drop commands whose token was cancelled, returning how many
+    fn remove_cancelled(&mut self) -> usize {
+        let before = self.len();
+        for queue in self.pending.values_mut() {
+            queue.retain(|queued| !queued.is_cancelled());
+        }
+        self.pending.retain(|_, queue| !queue.is_empty());
+        self.rotation.retain(|client| self.pending.contains_key(client));
+        before - self.len()
+    }
+}
+
+/// One [`FairQueue`] per [`RequestPriority`]; background commands only run
+/// when no interactive one is waiting
+#[derive(Debug, Default)]
+struct PriorityQueue {
+    interactive: FairQueue,
+    background: FairQueue,
+}
+
+impl PriorityQueue {
+    fn push(&mut self, queued: QueuedCmd) {
+        match queued.priority {
+            RequestPriority::Interactive => self.interactive.push(queued),
+            RequestPriority::Background => self.background.push(queued),
+        }
+    }
+    
+    fn pop(&mut self) -> Option<QueuedCmd> {
+        self.interactive.pop().or_else(|| self.background.pop())
+    }
+    
+    fn len(&self) -> usize {
+        self.interactive.len() + self.background.len()
+    }
+    
+    fn pending_for(&self, client: &str) -> usize {
+        self.interactive.pending_for(client) + self.background.pending_for(client)
+    }
+    
+    fn clients(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.interactive.pending.keys().chain(self.background.pending.keys())
+    }
+    
+    fn remove_cancelled(&mut self) -> usize {
+        self.interactive.remove_cancelled() + self.background.remove_cancelled()
+    }
+}
+
+/// Latency distribution over [`LATENCY_BUCKETS_MS`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyHistogram {
+    pub bucket_bounds_ms: Vec<u64>,
+    /// One count per bucket bound, plus a trailing overflow bucket
+    pub bucket_counts: Vec<u64>,
+    pub count: u64,
+    pub sum_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            bucket_bounds_ms: LATENCY_BUCKETS_MS.to_vec(),
+            bucket_counts: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            count: 0,
+            sum_ms: 0,
+            max_ms: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub(crate) fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.bucket_counts[bucket] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+    
+    pub fn mean_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum_ms as f64 / self.count as f64
+        }
+    }
+    
+    /// Upper bound of the bucket holding the given percentile (0-100);
+    /// the overflow bucket reports the observed maximum
+    pub fn percentile_ms(&self, percentile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let target = ((percentile / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.bucket_counts.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return LATENCY_BUCKETS_MS.get(i).copied().unwrap_or(self.max_ms).min(self.max_ms);
+            }
+        }
+        self.max_ms
+    }
+}
+
+/// How one client has been served by a device queue
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientQueueMetrics {
+    pub served: u64,
+    /// Commands currently waiting for this client
+    pub pending: usize,
+    pub max_queue_wait_ms: u64,
+    /// Commands that waited longer than the starvation threshold (5s)
+    pub starved: u64,
+}
+
 /// Metrics for monitoring queue performance
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DeviceQueueMetrics {
     pub queue_wait_ms: Vec<u64>,
     pub device_rtt_ms: Vec<u64>,
@@ -150,6 +480,25 @@ pub struct DeviceQueueMetrics {
     pub queue_depth: usize,
     pub cache_hits: u64,
     pub cache_misses: u64,
+    /// Total (queue wait + device) latency per message type
+    pub latency_by_message: HashMap<String, LatencyHistogram>,
+    /// Requests that exceeded the slow-request threshold
+    pub slow_requests: u64,
+    /// Per-client counters, keyed by the name given to [`DeviceQueueHandle::for_client`]
+    pub clients: HashMap<String, ClientQueueMetrics>,
+    /// Requests from any client that counted as starved
+    pub starved_requests: u64,
+    /// Times the worker rebound to the same wallet at a new USB address
+    pub reattachments: u64,
+    /// Read-only requests replayed after the device dropped off the bus mid-call
+    pub resumed_requests: u64,
+    /// Requests dropped from the queue or abandoned mid-call through their
+    /// [`CancellationToken`]
+    pub cancelled_requests: u64,
+    /// Times the USB interface was opened and claimed; with [`set_keep_claimed`]
+    /// this grows once per burst instead of once per request
+    pub transport_claims: u64,
+    pub transport_releases: u64,
 }
 
 impl DeviceQueueMetrics {
@@ -162,15 +511,15 @@ impl DeviceQueueMetrics {
         }
     }
     
-    pub fn record_cache_hit(&mut self) {
+    pub(crate) fn record_cache_hit(&mut self) {
         self.cache_hits += 1;
     }
     
-    pub fn record_cache_miss(&mut self) {
+    pub(crate) fn record_cache_miss(&mut self) {
         self.cache_misses += 1;
     }
     
-    pub fn record_operation(&mut self, queue_wait: Duration, device_rtt: Duration, total: Duration) {
+    pub(crate) fn record_operation(&mut self, queue_wait: Duration, device_rtt: Duration, total: Duration) {
         self.queue_wait_ms.push(queue_wait.as_millis() as u64);
         self.device_rtt_ms.push(device_rtt.as_millis() as u64);
         self.total_ms.push(total.as_millis() as u64);
@@ -182,52 +531,137 @@ impl DeviceQueueMetrics {
             self.total_ms.remove(0);
         }
     }
+    
+    pub(crate) fn record_latency(&mut self, label: &str, total: Duration) {
+        self.latency_by_message
+            .entry(label.to_string())
+            .or_default()
+            .record(total);
+    }
+    
+    /// Returns true when the wait counts as starvation
+    pub(crate) fn record_client(&mut self, client: &str, queue_wait: Duration) -> bool {
+        let starved = queue_wait > STARVATION_THRESHOLD;
+        let stats = self.clients.entry(client.to_string()).or_default();
+        stats.served += 1;
+        stats.max_queue_wait_ms = stats.max_queue_wait_ms.max(queue_wait.as_millis() as u64);
+        if starved {
+            stats.starved += 1;
+            self.starved_requests += 1;
+        }
+        starved
+    }
 }
 
 /// Worker task that processes device commands sequentially
-pub struct DeviceWorker {
+pub(crate) struct DeviceWorker {
     device_id: String,
     device_info: FriendlyUsbDevice,
-    transport: Option<Box<dyn ProtocolAdapter + Send>>,
+    transport: Option<Box<dyn AsyncProtocolAdapter>>,
+    /// DEBUG_LINK interface opened with `transport` while an auto-confirm
+    /// policy is set, and the policy it presses buttons by
+    debug_link: Option<(Box<dyn AsyncProtocolAdapter>, AutoConfirmPolicy)>,
     cache: HashMap<CacheKey, CachedResponse>,
-    metrics: DeviceQueueMetrics,
-    cmd_rx: mpsc::Receiver<DeviceCmd>,
+    metrics: Arc<Mutex<DeviceQueueMetrics>>,
+    cmd_rx: mpsc::Receiver<QueuedCmd>,
+    /// Commands taken off the channel but not yet served
+    pending: PriorityQueue,
     /// Track if device is in PIN flow mode (ResetDevice, PIN setup, etc)
     is_pin_flow: bool,
+    /// Firmware version learned from the last Features response
+    protocol: Option<ProtocolVersion>,
+    /// Wallet `device_id` from the last Features response; survives re-enumeration
+    wallet_id: Option<String>,
+    /// See [`wallet_fingerprint`]; a change drops every cached xpub
+    wallet_fingerprint: Option<u64>,
+    xpub_cache: HashMap<XpubCacheKey, PublicKey>,
+    /// Last Features and when they were read; dropped by any other command
+    recent_features: Option<(Instant, Features)>,
+    /// Request id and operation label of the command being served, for
+    /// tagging device diagnostics
+    serving: Option<(Option<Arc<str>>, String)>,
+    /// Input and output counts of the transaction being signed
+    signing: Option<(u64, u64)>,
+    /// Word count of a recovery in progress and how many words were asked for
+    recovery: Option<(u64, u64)>,
 }
 
 impl DeviceWorker {
     fn new(
         device_id: String,
         device_info: FriendlyUsbDevice,
-        cmd_rx: mpsc::Receiver<DeviceCmd>,
+        cmd_rx: mpsc::Receiver<QueuedCmd>,
+        metrics: Arc<Mutex<DeviceQueueMetrics>>,
     ) -> Self {
         Self {
             device_id,
             device_info,
             transport: None,
+            debug_link: None,
             cache: HashMap::new(),
-            metrics: DeviceQueueMetrics::default(),
+            metrics,
             cmd_rx,
+            pending: PriorityQueue::default(),
             is_pin_flow: false,
+            protocol: None,
+            wallet_id: None,
+            wallet_fingerprint: None,
+            xpub_cache: HashMap::new(),
+            recent_features: None,
+            serving: None,
+            signing: None,
+            recovery: None,
         }
     }
     
-    /// Main worker loop - processes commands sequentially
+    /// Main worker loop - processes commands one at a time, interactive ones
+    /// first, taking turns between clients within a lane
     #[instrument(level = "info", skip(self))]
-    pub async fn run(mut self) {
+    pub(crate) async fn run(mut self) {
         info!("🚀 DeviceWorker starting for device {}", self.device_id);
         
-        while let Some(cmd) = self.cmd_rx.recv().await {
+        loop {
+            // Take everything already sent so every waiting client gets its turn
+            while let Ok(queued) = self.cmd_rx.try_recv() {
+                self.pending.push(queued);
+            }
+            let dropped = self.pending.remove_cancelled();
+            if dropped > 0 {
+                debug!("🚫 Dropped {} cancelled command(s) for device {}", dropped, self.device_id);
+                self.metrics().cancelled_requests += dropped as u64;
+            }
+            let queued = match self.pending.pop() {
+                Some(next) => next,
+                None => match self.recv_command().await {
+                    Some(queued) if queued.is_cancelled() => {
+                        self.metrics().cancelled_requests += 1;
+                        continue;
+                    }
+                    Some(queued) => queued,
+                    None => break,
+                },
+            };
+            let QueuedCmd { client, priority, cancel, request_id, cmd } = queued;
+            
             let start_time = Instant::now();
             let queue_wait = start_time.duration_since(cmd.enqueued_at());
             
-            // Update queue depth metric
-            self.metrics.queue_depth = self.cmd_rx.len();
+            self.update_queue_depth();
             
-            debug!("📝 Processing {} command (queue wait: {:?})", cmd.operation_name(), queue_wait);
+            debug!("📝 Processing {} {:?} command for {} (queue wait: {:?})", cmd.operation_name(), priority, client, queue_wait);
+            self.serving = Some((request_id, cmd.metrics_label()));
             
-            let result = self.process_command(cmd).await;
+            let result = match cancel {
+                Some(token) => tokio::select! {
+                    result = self.process_command(&client, cmd) => result,
+                    _ = token.cancelled() => {
+                        self.abandon_command().await;
+                        Err(anyhow!("Request cancelled"))
+                    }
+                },
+                None => self.process_command(&client, cmd).await,
+            };
+            self.serving = None;
             
             if let Err(ref e) = result {
                 error!("❌ Command failed: {}", e);
@@ -237,30 +671,119 @@ impl DeviceWorker {
         info!("🛑 DeviceWorker shutting down for device {}", self.device_id);
     }
     
+    /// Wait for the next command. A transport held by [`set_keep_claimed`] is
+    /// released once nothing has arrived for the idle timeout.
+    async fn recv_command(&mut self) -> Option<QueuedCmd> {
+        if let (Some(idle), true) = (keep_claimed(), self.transport.is_some()) {
+            match timeout(idle, self.cmd_rx.recv()).await {
+                Ok(queued) => return queued,
+                Err(_) => {
+                    debug!("🔌 Device {} idle for {:?}, releasing transport", self.device_id, idle);
+                    self.release_transport();
+                }
+            }
+        }
+        self.cmd_rx.recv().await
+    }
+    
+    /// Close the transport (and DEBUG_LINK interface); the next command reopens it
+    fn release_transport(&mut self) {
+        if self.transport.take().is_some() {
+            self.metrics().transport_releases += 1;
+        }
+        self.debug_link = None;
+    }
+    
+    fn metrics(&self) -> std::sync::MutexGuard<'_, DeviceQueueMetrics> {
+        self.metrics.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    fn queue_depth(&self) -> usize {
+        self.pending.len() + self.cmd_rx.len()
+    }
+    
+    fn update_queue_depth(&self) {
+        let mut metrics = self.metrics();
+        metrics.queue_depth = self.queue_depth();
+        for (client, stats) in metrics.clients.iter_mut() {
+            stats.pending = self.pending.pending_for(client);
+        }
+        for client in self.pending.clients() {
+            let pending = self.pending.pending_for(client);
+            metrics.clients.entry(client.to_string()).or_default().pending = pending;
+        }
+    }
+    
+    /// Clean up after a command whose caller cancelled it mid-call. The
+    /// transport may have been dropped halfway through a read, so it is
+    /// reopened, and the device is told to abandon whatever it is waiting on
+    /// (a button press, the next TxAck) before the next command reaches it.
+    async fn abandon_command(&mut self) {
+        warn!("🚫 Abandoning cancelled command on device {}", self.device_id);
+        self.metrics().cancelled_requests += 1;
+        self.release_transport();
+        self.recent_features = None;
+        self.signing = None;
+        self.recovery = None;
+        let cancel = async {
+            self.ensure_transport().await?.send(Cancel {}.into()).await
+        };
+        if let Err(e) = cancel.await {
+            debug!("Could not send Cancel to device {}: {}", self.device_id, e);
+        }
+        self.release_transport();
+    }
+    
     /// Process a single command
-    async fn process_command(&mut self, cmd: DeviceCmd) -> Result<()> {
+    async fn process_command(&mut self, client: &str, cmd: DeviceCmd) -> Result<()> {
         let device_start = Instant::now();
         let enqueued_at = cmd.enqueued_at();
+        let label = cmd.metrics_label();
+        let reads_features_only = matches!(cmd, DeviceCmd::GetFeatures { .. });
+        let failed;
         
         match cmd {
             DeviceCmd::GetFeatures { respond_to, .. } => {
-                let result = self.handle_get_features().await;
+                let mut result = self.handle_get_features().await;
+                if self.resume_after_disconnect(&result, "get_features") {
+                    result = self.handle_get_features().await;
+                }
+                failed = result.is_err();
                 let _ = respond_to.send(result);
             }
             DeviceCmd::GetAddress { path, coin_name, script_type, show_display, respond_to, .. } => {
-                let result = self.handle_get_address(path, coin_name, script_type, show_display).await;
+                let mut result = self.handle_get_address(path.clone(), coin_name.clone(), script_type, show_display).await;
+                // An address shown on screen may already have been confirmed; don't show it twice
+                if show_display != Some(true) && self.resume_after_disconnect(&result, "get_address") {
+                    result = self.handle_get_address(path, coin_name, script_type, show_display).await;
+                }
+                failed = result.is_err();
                 let _ = respond_to.send(result);
             }
             DeviceCmd::SendRaw { message, respond_to, bypass_cache, .. } => {
-                let result = self.handle_send_raw(message, bypass_cache).await;
+                let replay = is_resumable(&message).then(|| message.clone());
+                let mut result = self.handle_send_raw(message, bypass_cache).await;
+                if let Some(message) = replay {
+                    if self.resume_after_disconnect(&result, "send_raw") {
+                        result = self.handle_send_raw(message, bypass_cache).await;
+                    }
+                }
+                failed = result.is_err();
                 let _ = respond_to.send(result);
             }
             DeviceCmd::UpdateBootloader { target_version, bootloader_bytes, respond_to, enqueued_at: _ } => {
                 let result = self.handle_update_bootloader(target_version, bootloader_bytes).await;
+                failed = result.is_err();
                 let _ = respond_to.send(result);
             }
             DeviceCmd::UpdateFirmware { target_version, firmware_bytes, respond_to, enqueued_at: _ } => {
                 let result = self.handle_update_firmware(target_version, firmware_bytes).await;
+                failed = result.is_err();
+                let _ = respond_to.send(result);
+            }
+            DeviceCmd::SignPsbt { psbt, fingerprint, respond_to, .. } => {
+                let result = self.handle_sign_psbt(*psbt, fingerprint).await;
+                failed = result.is_err();
                 let _ = respond_to.send(result);
             }
             DeviceCmd::Shutdown { respond_to } => {
@@ -268,19 +791,66 @@ impl DeviceWorker {
                 return Ok(());
             }
         }
+        if !reads_features_only {
+            // Anything else may have changed what Features would say (PIN cached, label, ...)
+            self.recent_features = None;
+        }
         
         let device_rtt = device_start.elapsed();
         let total_time = enqueued_at.elapsed();
         let queue_wait = device_start.duration_since(enqueued_at);
         
-        self.metrics.record_operation(queue_wait, device_rtt, total_time);
+        let threshold = slow_request_threshold();
+        let slow = total_time > threshold;
+        let starved = {
+            let mut metrics = self.metrics();
+            metrics.record_operation(queue_wait, device_rtt, total_time);
+            metrics.record_latency(&label, total_time);
+            if slow {
+                metrics.slow_requests += 1;
+            }
+            metrics.record_client(client, queue_wait)
+        };
+        
+        if starved {
+            warn!(
+                "⏳ Client {} waited {:?} for device {} ({} commands from {} clients still queued)",
+                client,
+                queue_wait,
+                self.device_id,
+                self.pending.len(),
+                self.pending.clients().count(),
+            );
+        }
+        
+        if slow {
+            warn!(
+                "🐢 Slow request on device {}: {} took {:?} (queue wait {:?}, device {:?}, threshold {:?}, queue depth {}, transport {:04x}:{:04x} {})",
+                self.device_id,
+                label,
+                total_time,
+                queue_wait,
+                device_rtt,
+                threshold,
+                self.queue_depth(),
+                self.device_info.vid,
+                self.device_info.pid,
+                self.device_info.name,
+            );
+        }
+    
+    self.collect_diagnostics().await;
     
-    // Always drop transport after each command to avoid exclusive handle issues,
-    // it will be recreated lazily on the next command.
-    if self.transport.is_some() {
-        info!("🔌 Releasing transport handle for device {} after operation", self.device_id);
+    // Drop the transport after each command to avoid exclusive handle issues,
+    // it will be recreated lazily on the next command. In keep-claimed mode it
+    // stays open for the next request unless this one failed, since a failed
+    // exchange can leave the interface out of step with the device.
+    if keep_claimed().is_none() || failed {
+        if self.transport.is_some() {
+            info!("🔌 Releasing transport handle for device {} after operation", self.device_id);
+        }
+        self.release_transport();
     }
-    self.transport = None;
     
     Ok(())
     }
@@ -288,13 +858,13 @@ impl DeviceWorker {
 
     
     /// Ensure transport is available, creating if necessary
-    async fn ensure_transport(&mut self) -> Result<&mut (dyn ProtocolAdapter + Send)> {
+    async fn ensure_transport(&mut self) -> Result<&mut dyn AsyncProtocolAdapter> {
         loop {
             if self.transport.is_none() {
                 info!("🔗 Attempting to create transport for device {}", self.device_id);
                 
                 // Try to create transport with current device info
-                let mut transport_result = DeviceQueueFactory::create_transport_for_device(&self.device_info);
+                let mut transport_result = self.open_transport();
                 
                 // If failed and PID is 0x0002, try looking for a device with same serial but different PID
                 // This handles the case where device reconnected after bootloader update
@@ -337,13 +907,14 @@ impl DeviceWorker {
                     
                     if found_reconnected {
                         // Try again with updated device info
-                        transport_result = DeviceQueueFactory::create_transport_for_device(&self.device_info);
+                        transport_result = self.open_transport();
                     }
                 }
                 
                 match transport_result {
                     Ok(transport) => {
                         self.transport = Some(transport);
+                        self.metrics().transport_claims += 1;
                         info!("✅ Transport ready for {}", self.device_id);
                     }
                     Err(e) => {
@@ -358,7 +929,10 @@ impl DeviceWorker {
                         }
                         
                         // Drop any stale transport reference just in case
-                        self.transport = None;
+                        self.release_transport();
+                        if self.reattach() {
+                            continue;
+                        }
                         // Wait a bit before retrying.  This keeps the queue worker alive
                         // and effectively makes the queue "just wait" for the device to return.
                         sleep(Duration::from_secs(2)).await;
@@ -372,21 +946,79 @@ impl DeviceWorker {
         }
     }
     
+    /// Open the device, along with its DEBUG_LINK interface when the run has
+    /// an auto-confirm policy or collects diagnostics and the firmware is a
+    /// debug build
+    fn open_transport(&mut self) -> Result<Box<dyn AsyncProtocolAdapter>> {
+        self.debug_link = None;
+        let policy = auto_confirm_policy();
+        if policy.is_active() || diagnostics::collection_enabled() {
+            match crate::transport::create_async_debug_link_for_device(&self.device_info) {
+                Ok((transport, debug_link)) => {
+                    if policy.is_active() {
+                        info!("🤖 Auto-confirming buttons on device {} through DEBUG_LINK", self.device_id);
+                    }
+                    self.debug_link = Some((debug_link, policy));
+                    return Ok(transport);
+                }
+                Err(e) => debug!("DEBUG_LINK unavailable for device {}: {}", self.device_id, e),
+            }
+        }
+        crate::transport::create_async_transport_for_device(&self.device_info)
+    }
+    
+    /// Send `message` and answer the device's follow-up requests with `handler`
+    async fn exchange(&mut self, message: Message, handler: &AsyncMessageHandler<'_>) -> Result<Message> {
+        self.ensure_transport().await?;
+        let (Some(transport), Some((debug_link, policy))) = (self.transport.as_mut(), self.debug_link.as_mut()) else {
+            return self.ensure_transport().await?.handle_with(message, handler).await;
+        };
+        let mut message = message;
+        loop {
+            let response = transport.handle(message).await?;
+            drain_diagnostics(debug_link.as_mut(), &self.device_id, self.serving.as_ref()).await;
+            if let Message::ButtonRequest(request) = &response {
+                if let Some(yes) = policy.decide(request) {
+                    info!("🤖 Pressing {} for ButtonRequest {:?} on device {}", if yes { "yes" } else { "no" }, request.code, self.device_id);
+                    // Buffered by the firmware until it starts waiting for the button
+                    debug_link.send(DebugLinkDecision { yes_no: yes }.into()).await?;
+                }
+            }
+            match handler(&response)? {
+                Some(reply) => message = reply,
+                None => return Ok(response),
+            }
+        }
+    }
+    
+    /// Record what the firmware logged to DEBUG_LINK during the command
+    async fn collect_diagnostics(&mut self) {
+        if let Some((debug_link, _)) = self.debug_link.as_mut() {
+            drain_diagnostics(debug_link.as_mut(), &self.device_id, self.serving.as_ref()).await;
+        }
+    }
+    
     /// Handle GetFeatures command with caching
     async fn handle_get_features(&mut self) -> Result<Features> {
-        // NOTE: We purposely skip normal caching for GetFeatures because features are
-        // lightweight and the user generally expects fresh information about the
-        // device. We still record a miss so that cache-hit ratio maths stay sane.
-        self.metrics.record_cache_miss();
+        // Startup asks for Features from several places at once (device scan,
+        // status checks, PIN readiness); answer those from one device round trip.
+        if let Some((fetched_at, features)) = &self.recent_features {
+            if fetched_at.elapsed() < FEATURES_REUSE_WINDOW {
+                let features = features.clone();
+                self.metrics().record_cache_hit();
+                return Ok(features);
+            }
+        }
+        self.metrics().record_cache_miss();
 
         // First attempt the standard GetFeatures call.
         // For OOB bootloaders, we need to handle raw responses directly since
         // the standard handler throws an error on Failure messages
-        let transport = self.ensure_transport().await?;
-        let response = transport.handle(GetFeatures {}.into())?;
+        let response = self.ensure_transport().await?.handle(GetFeatures {}.into()).await?;
 
         match response {
             Message::Features(features) => {
+                self.record_protocol(&features);
                 return Ok(features);
             }
             // Some very old bootloaders (so-called "OOB bootloader" devices) do not
@@ -404,13 +1036,10 @@ impl DeviceWorker {
 
                 // Re-establish transport just in case previous attempt left it in an
                 // undefined state.
-                self.transport = None;
-                let transport = self.ensure_transport().await?;
+                self.release_transport();
 
                 use crate::messages::Initialize;
-                let fallback_resp = transport
-                    .with_standard_handler()
-                    .handle(Initialize {}.into())?;
+                let fallback_resp = self.exchange(Initialize {}.into(), &standard_message_handler).await?;
 
                 if let Message::Features(features) = fallback_resp {
                     tracing::info!(
@@ -418,6 +1047,7 @@ impl DeviceWorker {
                         self.device_id
                     );
                     println!("✅ OOB bootloader Initialize fallback successful for device {}", self.device_id);
+                    self.record_protocol(&features);
                     return Ok(features);
                 } else {
                     return Err(anyhow!("Unexpected response to Initialize fallback"));
@@ -434,6 +1064,116 @@ impl DeviceWorker {
         }
     }
     
+    /// Remember the version the device reported so later commands can be
+    /// checked against it, and the Features themselves for quick re-reads
+    fn record_protocol(&mut self, features: &Features) {
+        let protocol = ProtocolVersion::from_features(features);
+        if self.protocol != Some(protocol) {
+            info!(
+                "🤝 Device {} reports {} v{}",
+                self.device_id,
+                if protocol.bootloader_mode { "bootloader" } else { "firmware" },
+                protocol.version
+            );
+        }
+        self.protocol = Some(protocol);
+        self.recent_features = Some((Instant::now(), features.clone()));
+        if features.device_id.is_some() {
+            self.wallet_id = features.device_id.clone();
+        }
+        let fingerprint = wallet_fingerprint(features);
+        if fingerprint != self.wallet_fingerprint {
+            self.clear_xpub_cache("wallet fingerprint changed");
+            self.wallet_fingerprint = fingerprint;
+        }
+    }
+
+    fn clear_xpub_cache(&mut self, reason: &str) {
+        if !self.xpub_cache.is_empty() {
+            info!("🧹 Dropping {} cached xpubs for device {}: {}", self.xpub_cache.len(), self.device_id, reason);
+            self.xpub_cache.clear();
+        }
+    }
+
+    /// Where a non-interactive GetPublicKey is cached, if it can be
+    fn xpub_cache_key(&self, request: &GetPublicKey) -> Option<XpubCacheKey> {
+        if request.show_display == Some(true) {
+            return None;
+        }
+        Some(XpubCacheKey {
+            wallet: self.wallet_fingerprint?,
+            address_n: request.address_n.clone(),
+            coin_name: request.coin_name.clone(),
+            script_type: request.script_type,
+            ecdsa_curve_name: request.ecdsa_curve_name.clone(),
+        })
+    }
+    
+    /// Decide whether a failed read-only command should be replayed: only when
+    /// the device went away mid-call (e.g. re-enumerating after passphrase
+    /// protection was toggled), never when it answered with an error.
+    fn resume_after_disconnect<T>(&mut self, result: &Result<T>, operation: &str) -> bool {
+        match result {
+            Err(e) if is_disconnect(e) => {
+                warn!("🔌 Device {} dropped off the bus during {}, resuming once it is back: {}", self.device_id, operation, e);
+                self.release_transport();
+                self.metrics().resumed_requests += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    /// Look for this wallet at a new USB address. Devices without a serial
+    /// number are identified by bus/address, which changes when the device
+    /// re-enumerates, so the wallet id from Features is matched instead.
+    /// Returns true when the worker was rebound; the queue handle and its
+    /// pending requests are unaffected.
+    fn reattach(&mut self) -> bool {
+        let wallet_id = match &self.wallet_id {
+            Some(id) => id.clone(),
+            None => return false,
+        };
+        for candidate in crate::features::list_connected_devices() {
+            if candidate.unique_id == self.device_info.unique_id {
+                continue;
+            }
+            let same_wallet = match (&candidate.serial_number, &self.device_info.serial_number) {
+                (Some(found), Some(expected)) => found == expected,
+                // A different serial is a different device; don't touch it
+                (Some(_), None) | (None, Some(_)) => false,
+                (None, None) => crate::features::get_device_features_for_device(&candidate)
+                    .map(|features| features.device_id.as_deref() == Some(wallet_id.as_str()))
+                    .unwrap_or(false),
+            };
+            if same_wallet {
+                info!(
+                    "🔄 Wallet {} reappeared as {} (was {}), rebinding device {}",
+                    wallet_id, candidate.unique_id, self.device_info.unique_id, self.device_id
+                );
+                self.device_info = candidate;
+                self.metrics().reattachments += 1;
+                return true;
+            }
+        }
+        false
+    }
+    
+    /// Refuse messages the connected firmware can't handle. If the version
+    /// isn't known yet, a GetFeatures handshake runs first.
+    async fn check_protocol(&mut self, message_type: MessageType) -> Result<()> {
+        if self.protocol.is_none() && protocol::needs_handshake(message_type) {
+            if let Err(e) = self.handle_get_features().await {
+                // Fall through and let the device answer for itself
+                warn!("⚠️ Protocol handshake failed for device {}: {}", self.device_id, e);
+            }
+        }
+        if let Some(protocol) = self.protocol {
+            protocol.check(message_type)?;
+        }
+        Ok(())
+    }
+    
     /// Handle GetAddress command with caching
     async fn handle_get_address(&mut self, path: Vec<u32>, coin_name: String, script_type: Option<i32>, show_display: Option<bool>) -> Result<String> {
         // Simple hash for parameters without bincode dependency
@@ -454,16 +1194,16 @@ impl DeviceWorker {
         // Check cache first
         if let Some(cached) = self.cache.get(&cache_key) {
             if cached.is_fresh() {
-                self.metrics.record_cache_hit();
+                self.metrics().record_cache_hit();
                 debug!("💰 Cache hit for GetAddress");
                 return Ok(cached.value.as_str().unwrap_or_default().to_string());
             }
         }
         
-        self.metrics.record_cache_miss();
+        self.metrics().record_cache_miss();
+        self.check_protocol(MessageType::GetAddress).await?;
         
         // Execute on device
-        let transport = self.ensure_transport().await?;
         let get_address = GetAddress {
             address_n: path,
             coin_name: Some(coin_name),
@@ -472,7 +1212,7 @@ impl DeviceWorker {
             ..Default::default()
         };
         
-        let response = transport.with_pin_flow_handler().handle(get_address.into())?;
+        let response = self.exchange(get_address.into(), &pin_flow_message_handler).await?;
         
         match response {
             Message::Address(addr_response) => {
@@ -490,8 +1230,144 @@ impl DeviceWorker {
         }
     }
     
+    fn report(&self, progress: Progress) {
+        // Nobody listening is fine
+        let _ = progress_sender().send(DeviceProgress { device_id: self.device_id.clone(), progress });
+    }
+
+    /// Report the bytes of the next writes as `uploading` steps, once per whole percent
+    fn report_upload(&mut self, operation: ProgressOperation) {
+        let device_id = self.device_id.clone();
+        let last_percent = AtomicU64::new(u64::MAX);
+        let upload_len = AtomicU64::new(0);
+        let progress: WriteProgress = Arc::new(move |sent, total| {
+            // Only the upload itself; button acks written after it aren't part of it
+            match upload_len.compare_exchange(0, total as u64, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {}
+                Err(len) if len == total as u64 => {}
+                Err(_) => return,
+            }
+            let percent = (sent * 100 / total.max(1)) as u64;
+            if last_percent.swap(percent, Ordering::Relaxed) != percent {
+                let progress = Progress::update(operation, UpdateStage::Uploading, sent as u64, total as u64);
+                let _ = progress_sender().send(DeviceProgress { device_id: device_id.clone(), progress });
+            }
+        });
+        if let Some(transport) = self.transport.as_mut() {
+            transport.set_write_progress(Some(progress));
+        }
+    }
+
+    fn stop_upload_report(&mut self) {
+        if let Some(transport) = self.transport.as_mut() {
+            transport.set_write_progress(None);
+        }
+    }
+
+    /// Hash sent with a firmware upload, reported as the `verifying_hash` step
+    fn upload_hash(&self, operation: ProgressOperation, payload: &[u8]) -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+        let hash = Sha256::digest(payload).to_vec();
+        self.report(
+            Progress::update(operation, UpdateStage::VerifyingHash, 1, 1)
+                .with_message(format!("SHA-256 {}", hex::encode(&hash))),
+        );
+        hash
+    }
+
+    /// Remember the step counts announced by a signing or recovery request
+    fn start_progress(&mut self, request: &Message) {
+        match request {
+            Message::SignTx(sign_tx) => {
+                self.signing = Some((sign_tx.inputs_count as u64, sign_tx.outputs_count as u64));
+            }
+            Message::RecoveryDevice(recovery) => {
+                self.recovery = Some((recovery.word_count.unwrap_or(0) as u64, 0));
+            }
+            _ => {}
+        }
+    }
+
+    /// Turn signing and recovery round trips into progress steps
+    fn track_progress(&mut self, response: &Message) {
+        use crate::messages::RequestType;
+
+        match response {
+            Message::TxRequest(tx_request) => {
+                let (inputs, outputs) = match self.signing {
+                    Some(counts) => counts,
+                    None => return,
+                };
+                let details = tx_request.details.as_ref();
+                // Requests about previous transactions aren't steps of this one
+                if details.and_then(|d| d.tx_hash.as_ref()).is_some() {
+                    return;
+                }
+                let index = details.and_then(|d| d.request_index).unwrap_or(0) as u64;
+                let request_type = tx_request.request_type.and_then(RequestType::from_i32);
+                match request_type {
+                    Some(RequestType::Txinput) => {
+                        self.report(Progress::new(ProgressOperation::Signing, "input", index + 1, inputs));
+                    }
+                    Some(RequestType::Txoutput) => {
+                        self.report(Progress::new(ProgressOperation::Signing, "output", index + 1, outputs));
+                    }
+                    Some(RequestType::Txfinished) => {
+                        self.signing = None;
+                        self.report(Progress::new(ProgressOperation::Signing, "finished", 1, 1));
+                    }
+                    _ => {}
+                }
+            }
+            Message::WordRequest(_) => {
+                if let Some((words, asked)) = self.recovery.as_mut() {
+                    *asked += 1;
+                    let (words, asked) = (*words, *asked);
+                    self.report(Progress::new(ProgressOperation::Recovery, "word", asked, words));
+                }
+            }
+            Message::CharacterRequest(request) => {
+                let words = self.recovery.map_or(0, |(words, _)| words);
+                self.report(Progress::new(ProgressOperation::Recovery, "word", request.word_pos as u64 + 1, words));
+            }
+            Message::Success(_) | Message::Failure(_) => {
+                if self.recovery.take().is_some() {
+                    let stage = if matches!(response, Message::Success(_)) { "finished" } else { "failed" };
+                    self.report(Progress::new(ProgressOperation::Recovery, stage, 1, 1));
+                }
+                self.signing = None;
+            }
+            _ => {}
+        }
+    }
+    
     /// Handle raw message sending 
     async fn handle_send_raw(&mut self, message: Message, bypass_cache: bool) -> Result<Message> {
+        self.check_protocol(message.message_type()).await?;
+
+        // Xpubs are answered from the cache without a device round trip
+        let xpub_key = match &message {
+            Message::GetPublicKey(request) if !bypass_cache => self.xpub_cache_key(request),
+            _ => None,
+        };
+        if let Some(key) = &xpub_key {
+            if let Some(public_key) = self.xpub_cache.get(key) {
+                let response = Message::PublicKey(public_key.clone());
+                self.metrics().record_cache_hit();
+                debug!("📦 Xpub cache hit for {:?}", key.address_n);
+                return Ok(response);
+            }
+            self.metrics().record_cache_miss();
+        }
+        if matches!(
+            &message,
+            Message::WipeDevice(_) | Message::ResetDevice(_) | Message::RecoveryDevice(_) | Message::LoadDevice(_)
+        ) {
+            // The seed is about to change; the next Features re-establishes the fingerprint
+            self.clear_xpub_cache("seed operation");
+            self.wallet_fingerprint = None;
+        }
+        
         // Detect if this is a PIN flow related message
         let is_pin_flow_message = matches!(
             &message,
@@ -510,22 +1386,29 @@ impl DeviceWorker {
             self.is_pin_flow = true;
         }
         
+        self.start_progress(&message);
+
         // Store PIN flow state before mutable borrow
         let use_pin_flow_handler = self.is_pin_flow || is_pin_flow_message;
         
-        // For raw messages, we generally don't cache unless specifically allowed
-        let transport = self.ensure_transport().await?;
-        
         // Use appropriate handler based on current state and message type
-        let response = if use_pin_flow_handler {
+        if use_pin_flow_handler {
             info!("🔐 Using PIN flow handler for message {:?}", message.message_type());
-            transport.with_pin_flow_handler().handle(message)?
+        }
+        let handler: &AsyncMessageHandler = if use_pin_flow_handler {
+            &pin_flow_message_handler
         } else {
-            transport.with_standard_handler().handle(message)?
+            &standard_message_handler
         };
+        let response = self.exchange(message, handler).await?;
+        self.track_progress(&response);
         
         // Update PIN flow state based on response
         match &response {
+            Message::Features(features) => {
+                // Initialize/GetFeatures sent raw still count as the handshake
+                self.record_protocol(features);
+            }
             Message::Success(_) | Message::Failure(_) => {
                 // PIN flow completed (either success or failure)
                 if self.is_pin_flow {
@@ -540,6 +1423,10 @@ impl DeviceWorker {
             _ => {}
         }
         
+        if let (Some(key), Message::PublicKey(public_key)) = (xpub_key, &response) {
+            self.xpub_cache.insert(key, public_key.clone());
+        }
+
         // If this was a mutable operation, purge cache
         if bypass_cache || self.is_mutable_operation(&response) {
             self.cache.clear();
@@ -549,27 +1436,46 @@ impl DeviceWorker {
         Ok(response)
     }
     
+    /// Run a whole SignTx exchange for a PSBT as one command, so no other
+    /// client's request lands between the TxAcks
+    async fn handle_sign_psbt(&mut self, psbt: Psbt, fingerprint: Option<Fingerprint>) -> Result<SignedPsbt> {
+        let mut signer = PsbtSigner::new(psbt, fingerprint)?;
+        let mut response = self.handle_send_raw(signer.sign_tx().into(), true).await?;
+        loop {
+            match response {
+                Message::TxRequest(request) => match signer.ack(&request)? {
+                    Some(ack) => response = self.handle_send_raw(ack, true).await?,
+                    None => return Ok(signer.finish()?),
+                },
+                Message::Failure(f) => return Err(anyhow!("Signing failed: {}", f.message())),
+                other => return Err(anyhow!("Unexpected response while signing: {:?}", other.message_type())),
+            }
+        }
+    }
+    
     /// Handle bootloader update command
     async fn handle_update_bootloader(&mut self, target_version: String, bootloader_bytes: Vec<u8>) -> Result<bool> {
         use crate::messages::{FirmwareErase, FirmwareUpload, Message};
-        use sha2::{Digest, Sha256};
         
         info!("🔄 Starting bootloader update to version {} ({} bytes)", target_version, bootloader_bytes.len());
         
         // Clear cache for this potentially disruptive operation
         self.cache.clear();
+        self.clear_xpub_cache("bootloader update");
+        self.protocol = None;
+        self.wallet_fingerprint = None;
         info!("🧹 Cache cleared for bootloader update");
         
         // Remember if we started with PID 0x0001 (old bootloader)
         let started_with_old_bootloader = self.device_info.pid == 0x0001;
         
-        // Get transport
-        let transport = self.ensure_transport().await?;
-        let mut handler = transport.with_standard_handler();
+        let payload_hash = self.upload_hash(ProgressOperation::BootloaderUpdate, &bootloader_bytes);
         
         // First, send FirmwareErase command for v1.0.3 bootloader compatibility
         info!("🧹 Sending FirmwareErase command for bootloader compatibility...");
-        match handler.handle(FirmwareErase::default().into()) {
+        self.report(Progress::update(ProgressOperation::BootloaderUpdate, UpdateStage::Erasing, 1, 1));
+        let erase = self.exchange(FirmwareErase::default().into(), &standard_message_handler).await;
+        match erase {
             Ok(Message::Success(s)) => {
                 info!("✅ FirmwareErase successful: {}", s.message());
             }
@@ -588,20 +1494,22 @@ impl DeviceWorker {
         
         // Now send the actual bootloader upload
         info!("📤 Sending FirmwareUpload command...");
-        let payload_hash = Sha256::digest(&bootloader_bytes).to_vec();
+        self.report_upload(ProgressOperation::BootloaderUpdate);
         
-        let result = handler.handle(FirmwareUpload {
+        let upload = FirmwareUpload {
             payload_hash,
             payload: bootloader_bytes,
-        }.into());
+        };
+        let result = self.exchange(upload.into(), &standard_message_handler).await;
+        self.stop_upload_report();
         
         // Clear transport after upload completes (device will disconnect)
-        drop(handler);
-        self.transport = None;
+        self.release_transport();
         
         match result {
             Ok(Message::Success(s)) => {
                 info!("✅ Bootloader update successful: {}", s.message());
+                self.report(Progress::update(ProgressOperation::BootloaderUpdate, UpdateStage::Rebooting, 1, 1));
                 info!("🔄 Device may reboot. Please wait a moment.");
                 
                 // IMPORTANT: After bootloader update, the device will reconnect with a different PID
@@ -633,21 +1541,23 @@ impl DeviceWorker {
     /// Handle firmware update command
     async fn handle_update_firmware(&mut self, target_version: String, firmware_bytes: Vec<u8>) -> Result<bool> {
         use crate::messages::{FirmwareErase, FirmwareUpload, Message};
-        use sha2::{Digest, Sha256};
         
         info!("🔄 Starting firmware update to version {} ({} bytes)", target_version, firmware_bytes.len());
         
         // Clear cache for this potentially disruptive operation
         self.cache.clear();
+        self.clear_xpub_cache("firmware update");
+        self.protocol = None;
+        self.wallet_fingerprint = None;
         info!("🧹 Cache cleared for firmware update");
         
-        // Get transport
-        let transport = self.ensure_transport().await?;
-        let mut handler = transport.with_standard_handler();
+        let payload_hash = self.upload_hash(ProgressOperation::FirmwareUpdate, &firmware_bytes);
         
         // First, send FirmwareErase command to prepare device for firmware update
         info!("🧹 Sending FirmwareErase command to prepare for firmware update...");
-        match handler.handle(FirmwareErase::default().into()) {
+        self.report(Progress::update(ProgressOperation::FirmwareUpdate, UpdateStage::Erasing, 1, 1));
+        let erase = self.exchange(FirmwareErase::default().into(), &standard_message_handler).await;
+        match erase {
             Ok(Message::Success(s)) => {
                 info!("✅ FirmwareErase successful: {}", s.message());
             }
@@ -666,14 +1576,18 @@ impl DeviceWorker {
         
         // Now send the actual firmware upload
         info!("📤 Sending FirmwareUpload command...");
-        let payload_hash = Sha256::digest(&firmware_bytes).to_vec();
+        self.report_upload(ProgressOperation::FirmwareUpdate);
         
-        match handler.handle(FirmwareUpload {
+        let upload = FirmwareUpload {
             payload_hash,
             payload: firmware_bytes,
-        }.into()) {
+        };
+        let result = self.exchange(upload.into(), &standard_message_handler).await;
+        self.stop_upload_report();
+        match result {
             Ok(Message::Success(s)) => {
                 info!("✅ Firmware update successful: {}", s.message());
+                self.report(Progress::update(ProgressOperation::FirmwareUpdate, UpdateStage::Rebooting, 1, 1));
                 info!("🔄 Device may reboot. Please wait a moment.");
                 Ok(true)
             }
@@ -724,38 +1638,215 @@ impl DeviceWorker {
     }
 }
 
+/// Whether `error` means the device went away (unplugged or re-enumerating)
+/// rather than the device refusing the request
+/// Read the `DebugLinkLog` lines waiting on a DEBUG_LINK interface, tagged
+/// with the command being served
+async fn drain_diagnostics(
+    debug_link: &mut dyn AsyncProtocolAdapter,
+    device_id: &str,
+    serving: Option<&(Option<Arc<str>>, String)>,
+) {
+    if !diagnostics::collection_enabled() {
+        return;
+    }
+    let (request_id, operation) = serving.map_or((None, ""), |(id, operation)| (id.as_deref(), operation.as_str()));
+    for _ in 0..DIAGNOSTICS_DRAIN_LIMIT {
+        match debug_link.recv(DIAGNOSTICS_POLL).await {
+            Ok(Message::DebugLinkLog(log)) => {
+                debug!("📟 Device {} [{}] {}", device_id, log.bucket(), log.text());
+                diagnostics::record(DeviceDiagnostic::from_log(device_id, request_id, operation, log));
+            }
+            Ok(other) => debug!("Ignoring unprompted {:?} on DEBUG_LINK of device {}", other.message_type(), device_id),
+            // Nothing more buffered
+            Err(_) => break,
+        }
+    }
+}
+
+fn is_disconnect(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<rusb::Error>(),
+            Some(rusb::Error::NoDevice | rusb::Error::NotFound | rusb::Error::Io | rusb::Error::Pipe)
+        )
+    })
+}
+
+/// Deadline for a queued request: its turn in the queue, then its time on the
+/// device, which includes the user's time to confirm when it may ask them.
+/// Timeouts of the device exchange itself come back first as a
+/// [`DeviceTimeout`](crate::failure::DeviceTimeout); this one is the backstop.
+fn request_timeout(message: &Message) -> Duration {
+    QUEUE_WAIT_TIMEOUT + message.operation_timeout()
+}
+
+/// Requests that neither change device state nor ask the user anything, so
+/// replaying them after a reconnect can't sign or confirm anything twice
+fn is_resumable(message: &Message) -> bool {
+    match message {
+        Message::Initialize(_) | Message::GetFeatures(_) => true,
+        Message::GetPublicKey(m) => m.show_display != Some(true),
+        Message::GetAddress(m) => m.show_display != Some(true),
+        _ => false,
+    }
+}
+
 /// Handle for communicating with a device worker
 #[derive(Clone, Debug)]
 pub struct DeviceQueueHandle {
     device_id: String,
-    cmd_tx: mpsc::Sender<DeviceCmd>,
+    client: Arc<str>,
+    priority: RequestPriority,
+    cancel: Option<CancellationToken>,
+    request_id: Option<Arc<str>>,
+    cmd_tx: mpsc::Sender<QueuedCmd>,
+    metrics: Arc<Mutex<DeviceQueueMetrics>>,
 }
 
 impl DeviceQueueHandle {
-    pub fn new(device_id: String, cmd_tx: mpsc::Sender<DeviceCmd>) -> Self {
-        Self { device_id, cmd_tx }
+    fn with_metrics(device_id: String, cmd_tx: mpsc::Sender<QueuedCmd>, metrics: Arc<Mutex<DeviceQueueMetrics>>) -> Self {
+        Self {
+            device_id,
+            client: Arc::from(DEFAULT_CLIENT),
+            priority: RequestPriority::default(),
+            cancel: None,
+            request_id: None,
+            cmd_tx,
+            metrics,
+        }
+    }
+    
+    /// A handle to the same worker that submits as `client` (an API key,
+    /// origin or app component). The worker serves clients round-robin, so a
+    /// busy client only delays its own requests.
+    pub fn for_client(&self, client: impl Into<String>) -> Self {
+        Self { client: Arc::from(client.into()), ..self.clone() }
+    }
+    
+    pub fn client(&self) -> &str {
+        &self.client
+    }
+    
+    /// A handle to the same worker whose requests wait in the `priority` lane
+    pub fn with_priority(&self, priority: RequestPriority) -> Self {
+        Self { priority, ..self.clone() }
+    }
+    
+    pub fn priority(&self) -> RequestPriority {
+        self.priority
+    }
+    
+    /// A handle whose requests carry the caller's `request_id`; device
+    /// diagnostics read while serving them are tagged with it
+    pub fn with_request_id(&self, request_id: impl Into<String>) -> Self {
+        Self { request_id: Some(Arc::from(request_id.into())), ..self.clone() }
+    }
+    
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+    
+    /// A handle for a batch of requests (a frontload, a sync pass) and the
+    /// token that cancels it. Cancelling drops the batch's queued requests,
+    /// abandons the one on the device and makes every pending call return an
+    /// error. The token is a child of this handle's own, if it has one.
+    pub fn cancellable(&self) -> (Self, CancellationToken) {
+        let token = self.cancel.as_ref().map_or_else(CancellationToken::new, CancellationToken::child_token);
+        (Self { cancel: Some(token.clone()), ..self.clone() }, token)
+    }
+    
+    /// Queue `cmd`. Background requests are journaled (with the `journal`
+    /// feature and a journal installed) until the returned entry is dropped.
+    async fn enqueue(&self, cmd: DeviceCmd) -> Result<Option<JournalEntry>> {
+        if self.cancel.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            return Err(anyhow!("Request cancelled"));
+        }
+        let journaled = self.journal(&cmd);
+        let queued = QueuedCmd {
+            client: self.client.clone(),
+            priority: self.priority,
+            cancel: self.cancel.clone(),
+            request_id: self.request_id.clone(),
+            cmd,
+        };
+        self.cmd_tx.send(queued).await
+            .map_err(|_| anyhow!("Device worker unavailable"))?;
+        Ok(journaled)
+    }
+    
+    #[cfg(feature = "journal")]
+    fn journal(&self, cmd: &DeviceCmd) -> Option<JournalEntry> {
+        if self.priority != RequestPriority::Background {
+            return None;
+        }
+        let request = match cmd {
+            DeviceCmd::GetFeatures { .. } => JournaledRequest::GetFeatures,
+            DeviceCmd::GetAddress { path, coin_name, script_type, show_display, .. } => JournaledRequest::GetAddress {
+                path: path.clone(),
+                coin_name: coin_name.clone(),
+                script_type: *script_type,
+                show_display: *show_display,
+            },
+            DeviceCmd::SendRaw { message, bypass_cache, .. } => JournaledRequest::send_raw(message, *bypass_cache)?,
+            DeviceCmd::SignPsbt { psbt, fingerprint, .. } => JournaledRequest::sign_psbt(psbt, *fingerprint),
+            // Replaying these unattended is never what the user wants
+            DeviceCmd::UpdateBootloader { .. } | DeviceCmd::UpdateFirmware { .. } | DeviceCmd::Shutdown { .. } => return None,
+        };
+        JournalEntry::record(&self.device_id, &self.client, self.request_id.as_deref(), &request)
+    }
+    
+    #[cfg(not(feature = "journal"))]
+    fn journal(&self, _cmd: &DeviceCmd) -> Option<JournalEntry> {
+        None
+    }
+    
+    /// Wait for the worker's reply, giving up after `limit` or when this
+    /// handle's batch is cancelled
+    async fn reply<T>(&self, rx: oneshot::Receiver<Result<T>>, limit: Duration, timed_out: &str) -> Result<T> {
+        let cancelled = async {
+            match &self.cancel {
+                Some(token) => token.cancelled().await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            reply = timeout(limit, rx) => reply
+                .map_err(|_| anyhow!("{}", timed_out))?
+                .map_err(|_| anyhow!("Device worker channel closed"))?,
+            _ = cancelled => Err(anyhow!("Request cancelled")),
+        }
+    }
+    
+    /// Snapshot of the worker's queue metrics and latency histograms
+    pub fn metrics(&self) -> DeviceQueueMetrics {
+        self.metrics.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    /// Progress of firmware updates, signing and recovery on this device
+    pub fn subscribe_progress(&self) -> ProgressSubscription {
+        ProgressSubscription { device_id: self.device_id.clone(), rx: progress_sender().subscribe() }
     }
     
     /// Get device features
     #[instrument(level = "debug", skip(self))]
     pub async fn get_features(&self) -> Result<Features> {
+        let limit = request_timeout(&GetFeatures {}.into());
         let (tx, rx) = oneshot::channel();
         let cmd = DeviceCmd::GetFeatures {
             respond_to: tx,
             enqueued_at: Instant::now(),
         };
         
-        self.cmd_tx.send(cmd).await
-            .map_err(|_| anyhow!("Device worker unavailable"))?;
+        let _journaled = self.enqueue(cmd).await?;
             
-        timeout(DEVICE_OPERATION_TIMEOUT, rx).await
-            .map_err(|_| anyhow!("Device operation timed out"))?
-            .map_err(|_| anyhow!("Device worker channel closed"))?
+        self.reply(rx, limit, "Device operation timed out").await
     }
     
     /// Get address for given path
     #[instrument(level = "debug", skip(self))]
     pub async fn get_address(&self, path: Vec<u32>, coin_name: String, script_type: Option<i32>, show_display: Option<bool>) -> Result<String> {
+        let limit = request_timeout(&GetAddress { show_display, ..Default::default() }.into());
         let (tx, rx) = oneshot::channel();
         let cmd = DeviceCmd::GetAddress {
             path,
@@ -766,17 +1857,15 @@ impl DeviceQueueHandle {
             enqueued_at: Instant::now(),
         };
         
-        self.cmd_tx.send(cmd).await
-            .map_err(|_| anyhow!("Device worker unavailable"))?;
+        let _journaled = self.enqueue(cmd).await?;
             
-        timeout(DEVICE_OPERATION_TIMEOUT, rx).await
-            .map_err(|_| anyhow!("Device operation timed out"))?
-            .map_err(|_| anyhow!("Device worker channel closed"))?
+        self.reply(rx, limit, "Device operation timed out").await
     }
     
     /// Send raw message to device
     #[instrument(level = "debug", skip(self, message))]
     pub async fn send_raw(&self, message: Message, bypass_cache: bool) -> Result<Message> {
+        let limit = request_timeout(&message);
         let (tx, rx) = oneshot::channel();
         let cmd = DeviceCmd::SendRaw {
             message,
@@ -785,12 +1874,9 @@ impl DeviceQueueHandle {
             bypass_cache,
         };
         
-        self.cmd_tx.send(cmd).await
-            .map_err(|_| anyhow!("Device worker unavailable"))?;
+        let _journaled = self.enqueue(cmd).await?;
             
-        timeout(DEVICE_OPERATION_TIMEOUT, rx).await
-            .map_err(|_| anyhow!("Device operation timed out"))?
-            .map_err(|_| anyhow!("Device worker channel closed"))?
+        self.reply(rx, limit, "Device operation timed out").await
     }
     
     /// Update device bootloader
@@ -804,13 +1890,10 @@ impl DeviceQueueHandle {
             enqueued_at: Instant::now(),
         };
         
-        self.cmd_tx.send(cmd).await
-            .map_err(|_| anyhow!("Device worker unavailable"))?;
+        let _journaled = self.enqueue(cmd).await?;
             
         // Use longer timeout for firmware operations (2 minutes)
-        timeout(Duration::from_secs(120), rx).await
-            .map_err(|_| anyhow!("Bootloader update timed out"))?
-            .map_err(|_| anyhow!("Device worker channel closed"))?
+        self.reply(rx, Duration::from_secs(120), "Bootloader update timed out").await
     }
     
     /// Update device firmware
@@ -824,13 +1907,36 @@ impl DeviceQueueHandle {
             enqueued_at: Instant::now(),
         };
         
-        self.cmd_tx.send(cmd).await
-            .map_err(|_| anyhow!("Device worker unavailable"))?;
+        let _journaled = self.enqueue(cmd).await?;
             
         // Use longer timeout for firmware operations (2 minutes)
-        timeout(Duration::from_secs(120), rx).await
-            .map_err(|_| anyhow!("Firmware update timed out"))?
-            .map_err(|_| anyhow!("Device worker channel closed"))?
+        self.reply(rx, Duration::from_secs(120), "Firmware update timed out").await
+    }
+    
+    /// Sign every input of `psbt` this device holds the key for. The
+    /// device's signatures come back as partial signatures in the PSBT.
+    #[instrument(level = "debug", skip(self, psbt))]
+    pub async fn sign_psbt(&self, psbt: Psbt) -> Result<SignedPsbt> {
+        self.sign_psbt_as(psbt, None).await
+    }
+
+    /// Like [`sign_psbt`](Self::sign_psbt), signing only with the key of the
+    /// wallet whose master fingerprint is `fingerprint`. Multisig PSBTs list a
+    /// derivation for every cosigner, so they need it.
+    #[instrument(level = "debug", skip(self, psbt))]
+    pub async fn sign_psbt_as(&self, psbt: Psbt, fingerprint: Option<Fingerprint>) -> Result<SignedPsbt> {
+        let (tx, rx) = oneshot::channel();
+        let cmd = DeviceCmd::SignPsbt {
+            psbt: Box::new(psbt),
+            fingerprint,
+            respond_to: tx,
+            enqueued_at: Instant::now(),
+        };
+        
+        let _journaled = self.enqueue(cmd).await?;
+        
+        // Every output waits for a button press
+        self.reply(rx, PSBT_SIGNING_TIMEOUT, "PSBT signing timed out").await
     }
     
     /// Shutdown the device worker
@@ -838,8 +1944,7 @@ impl DeviceQueueHandle {
         let (tx, rx) = oneshot::channel();
         let cmd = DeviceCmd::Shutdown { respond_to: tx };
         
-        self.cmd_tx.send(cmd).await
-            .map_err(|_| anyhow!("Device worker unavailable"))?;
+        self.enqueue(cmd).await?;
             
         timeout(Duration::from_secs(5), rx).await
             .map_err(|_| anyhow!("Shutdown timed out"))?
@@ -859,207 +1964,224 @@ impl DeviceQueueFactory {
     pub fn spawn_worker(device_id: String, device_info: FriendlyUsbDevice) -> DeviceQueueHandle {
         let (cmd_tx, cmd_rx) = mpsc::channel(QUEUE_CHANNEL_SIZE);
         
-        let worker = DeviceWorker::new(device_id.clone(), device_info, cmd_rx);
+        let metrics = Arc::new(Mutex::new(DeviceQueueMetrics::default()));
+        
+        let worker = DeviceWorker::new(device_id.clone(), device_info, cmd_rx, metrics.clone());
         
         // Spawn the worker task
         tokio::spawn(worker.run());
         
-        DeviceQueueHandle::new(device_id, cmd_tx)
+        DeviceQueueHandle::with_metrics(device_id, cmd_tx, metrics)
     }
-    
-    /// Create transport with WebUSB/USB/HID auto-detection
-    pub fn create_transport_for_device(device_info: &FriendlyUsbDevice) -> Result<Box<dyn ProtocolAdapter + Send>> {
-        // Find physical device for transport
-        let devices = crate::features::list_devices();
-        let physical_device = Self::find_physical_device_by_info(device_info, &devices)?;
-        
-        // Detect transport type based on device endpoints
-        let transport_type = Self::detect_transport_type(&physical_device, device_info)?;
-        
-        match transport_type {
-            TransportType::WebUsb => {
-                info!("🌐 Detected WebUSB device, using WebUSB transport for {}", device_info.unique_id);
-                info!("🔧 Attempting to create WebUSB transport...");
-                match crate::transport::WebUsbTransport::new(&physical_device, 0) {
-                    Ok((transport, _, _)) => {
-                        info!("✅ Successfully created WebUSB transport for device {}", device_info.unique_id);
-                        Ok(Box::new(transport))
-                    }
-                    Err(webusb_err) => {
-                        error!("❌ WebUSB transport creation failed for device {}: {}", device_info.unique_id, webusb_err);
-                        warn!("⚠️ WebUSB transport failed for device {}: {}, trying HID fallback", device_info.unique_id, webusb_err);
-                        Self::try_hid_fallback(device_info, webusb_err.to_string())
-                    }
-                }
-            }
-            TransportType::TraditionalUsb => {
-                info!("🔌 Detected traditional USB device, using interrupt transport for {}", device_info.unique_id);
-                match crate::transport::UsbTransport::new(&physical_device, 0) {
-                    Ok((transport, _, _)) => {
-                        info!("✅ Created USB transport for device {}", device_info.unique_id);
-                        Ok(Box::new(transport))
-                    }
-                    Err(usb_err) => {
-                        warn!("⚠️ USB transport failed for device {}: {}, trying HID fallback", device_info.unique_id, usb_err);
-                        Self::try_hid_fallback(device_info, usb_err.to_string())
-                    }
-                }
-            }
-            TransportType::HidOnly => {
-                info!("🎛️ Device requires HID transport, using HID for {}", device_info.unique_id);
-                Self::try_hid_fallback(device_info, "Device requires HID transport".to_string())
-            }
-        }
+
+    /// A worker for the simulated device, which needs no USB hardware
+    #[cfg(feature = "mock-device")]
+    pub fn spawn_mock_worker() -> DeviceQueueHandle {
+        let device_info = crate::transport::MockDevice::device_info();
+        Self::spawn_worker(device_info.unique_id.clone(), device_info)
     }
-    
-    /// Detect the appropriate transport type for a device
-    fn detect_transport_type(device: &rusb::Device<rusb::GlobalContext>, device_info: &FriendlyUsbDevice) -> Result<TransportType> {
-        info!("🔍 Detecting transport type for device {} (VID: {:04x}, PID: {:04x})", 
-              device_info.unique_id, device_info.vid, device_info.pid);
-        
-        // Legacy devices (PID 0x0001) must use HID on all platforms
-        if device_info.pid == 0x0001 {
-            info!("🎛️ Legacy device (PID 0x0001) detected - using HID transport");
-            return Ok(TransportType::HidOnly);
+
+    /// Progress from every device worker, current and future
+    pub fn subscribe_progress() -> broadcast::Receiver<DeviceProgress> {
+        progress_sender().subscribe()
+    }
+
+    /// Publish a step the application drives itself, like downloading an
+    /// update or checking the version after the device rebooted, alongside
+    /// the workers' own
+    pub fn report_progress(device_id: &str, progress: Progress) {
+        let _ = progress_sender().send(DeviceProgress { device_id: device_id.to_string(), progress });
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_histogram_buckets_and_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        for ms in [3, 8, 40, 40, 90, 45_000] {
+            histogram.record(Duration::from_millis(ms));
         }
-        
-        // Modern devices (PID 0x0002 and newer) should prefer USB transport
-        // PID 0x0002 devices have interrupt endpoints and use USB transport (not WebUSB with bulk endpoints)
-        if device_info.pid == 0x0002 {
-            info!("🔌 Modern KeepKey device (PID 0x0002) detected - preferring USB transport");
-            info!("   📡 Firmware 7.10.0+ devices use USB transport with interrupt endpoints");
-            
-            // On Windows, we'll try USB first and let the fallback mechanism handle FIDO blocklist issues
-            #[cfg(target_os = "windows")]
-            {
-                info!("🪟 Windows detected - will try USB first, HID fallback available if FIDO blocklist blocks access");
-            }
-            
-            return Ok(TransportType::TraditionalUsb);
+        assert_eq!(histogram.count, 6);
+        assert_eq!(histogram.bucket_counts[0], 1);
+        assert_eq!(histogram.bucket_counts[3], 2);
+        assert_eq!(*histogram.bucket_counts.last().unwrap(), 1);
+        assert_eq!(histogram.percentile_ms(50.0), 50);
+        assert_eq!(histogram.percentile_ms(100.0), 45_000);
+    }
+
+    #[test]
+    fn only_read_only_requests_are_resumed() {
+        use crate::messages::{GetPublicKey, SignMessage};
+
+        assert!(is_resumable(&GetFeatures {}.into()));
+        assert!(is_resumable(&GetPublicKey::default().into()));
+        assert!(!is_resumable(&GetAddress { show_display: Some(true), ..Default::default() }.into()));
+        assert!(!is_resumable(&SignMessage::default().into()));
+
+        assert!(is_disconnect(&anyhow::Error::from(rusb::Error::NoDevice).context("read failed")));
+        assert!(!is_disconnect(&anyhow!("Failure: PIN invalid")));
+    }
+
+    #[test]
+    fn requests_that_ask_the_user_get_time_to_confirm() {
+        let quick = request_timeout(&GetFeatures {}.into());
+        let shown = request_timeout(&GetAddress { show_display: Some(true), ..Default::default() }.into());
+        let hidden = request_timeout(&GetAddress::default().into());
+        assert_eq!(quick, QUEUE_WAIT_TIMEOUT + Message::from(GetFeatures {}).read_timeout());
+        assert_eq!(shown, hidden + crate::messages::confirmation_timeout());
+    }
+
+    #[test]
+    fn wallet_fingerprint_tracks_the_seed() {
+        let features = Features {
+            device_id: Some("ABC123".to_string()),
+            initialized: Some(true),
+            ..Default::default()
+        };
+        let fingerprint = wallet_fingerprint(&features);
+        assert!(fingerprint.is_some());
+        assert_eq!(fingerprint, wallet_fingerprint(&Features { pin_cached: Some(true), ..features.clone() }));
+        assert_ne!(fingerprint, wallet_fingerprint(&Features { imported: Some(true), ..features.clone() }));
+
+        assert_eq!(wallet_fingerprint(&Features { initialized: Some(false), ..features.clone() }), None);
+        assert_eq!(wallet_fingerprint(&Features { passphrase_protection: Some(true), ..features }), None);
+    }
+
+    fn queued(client: &str, priority: RequestPriority, cancel: Option<&CancellationToken>) -> QueuedCmd {
+        QueuedCmd {
+            client: Arc::from(client),
+            priority,
+            cancel: cancel.cloned(),
+            request_id: None,
+            cmd: DeviceCmd::Shutdown { respond_to: oneshot::channel().0 },
         }
-        
-        // For other newer device PIDs, inspect the endpoints
-        match device.active_config_descriptor() {
-            Ok(config_desc) => {
-                info!("📋 Successfully read device config descriptor");
-                
-                // Look at the first interface (index 0) which is what we use
-                if let Some(interface) = config_desc.interfaces().nth(0) {
-                    info!("📋 Found interface 0");
-                    if let Some(interface_desc) = interface.descriptors().next() {
-                        let endpoints: Vec<_> = interface_desc.endpoint_descriptors().collect();
-                        info!("📋 Found {} endpoints", endpoints.len());
-                        
-                        // Log all endpoints for debugging
-                        for (i, ep) in endpoints.iter().enumerate() {
-                            info!("   Endpoint {}: addr=0x{:02x}, type={:?}, dir={:?}, max_packet={}",
-                                  i, ep.address(), ep.transfer_type(), ep.direction(), ep.max_packet_size());
-                        }
-                        
-                        // Check if we have bulk endpoints (WebUSB) or interrupt endpoints (USB)
-                        let has_bulk = endpoints.iter().any(|ep| ep.transfer_type() == rusb::TransferType::Bulk);
-                        let has_interrupt = endpoints.iter().any(|ep| ep.transfer_type() == rusb::TransferType::Interrupt);
-                        
-                        info!("📋 Endpoint analysis: has_bulk={}, has_interrupt={}", has_bulk, has_interrupt);
-                        
-                        if has_bulk {
-                            info!("🌐 Device {} has bulk endpoints - using WebUSB", device_info.unique_id);
-                            return Ok(TransportType::WebUsb);
-                        } else if has_interrupt {
-                            info!("🔌 Device {} has interrupt endpoints - using USB transport", device_info.unique_id);
-                            return Ok(TransportType::TraditionalUsb);
-                        } else {
-                            warn!("⚠️ Device {} has no recognizable endpoints - defaulting to WebUSB", device_info.unique_id);
-                            return Ok(TransportType::WebUsb);
-                        }
-                    } else {
-                        warn!("⚠️ Could not get interface descriptor for device {}", device_info.unique_id);
-                    }
-                } else {
-                    warn!("⚠️ Could not find interface 0 for device {}", device_info.unique_id);
-                }
-            }
-            Err(e) => {
-                warn!("⚠️ Could not read device descriptors for {}: {}, defaulting to WebUSB", device_info.unique_id, e);
-            }
+    }
+
+    #[test]
+    fn fair_queue_alternates_between_clients() {
+        let mut queue = FairQueue::default();
+        for _ in 0..3 {
+            queue.push(queued("api-key-1", RequestPriority::Interactive, None));
         }
+        queue.push(queued("ui", RequestPriority::Interactive, None));
+        assert_eq!(queue.pending_for("api-key-1"), 3);
         
-        // Default to WebUSB for modern devices, not HID
-        info!("🌐 Defaulting to WebUSB transport for device {}", device_info.unique_id);
-        Ok(TransportType::WebUsb)
-    }
-    
-    /// Try HID transport as fallback
-    fn try_hid_fallback(device_info: &FriendlyUsbDevice, previous_error: String) -> Result<Box<dyn ProtocolAdapter + Send>> {
-        // Check if this is a Windows FIDO blocklist error
-        #[cfg(target_os = "windows")]
-        {
-            let error_lower = previous_error.to_lowercase();
-            if error_lower.contains("access") || error_lower.contains("denied") || 
-               error_lower.contains("permission") || error_lower.contains("0x00000005") {
-                info!("🪟 Windows FIDO blocklist detected - switching to HID transport");
-                info!("   📝 Windows CTAP-HID filter is blocking USB access");
-                info!("   ✅ Using HID transport to bypass FIDO restrictions");
-            }
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|q| q.client.to_string()).collect();
+        assert_eq!(order, ["api-key-1", "ui", "api-key-1", "api-key-1"]);
+        assert_eq!(queue.len(), 0);
+    }
+    
+    #[test]
+    fn interactive_requests_jump_background_work_and_cancelled_ones_drop_out() {
+        let frontload = CancellationToken::new();
+        let mut queue = PriorityQueue::default();
+        for _ in 0..3 {
+            queue.push(queued("frontload", RequestPriority::Background, Some(&frontload)));
         }
+        queue.push(queued("sync", RequestPriority::Background, None));
+        queue.push(queued("ui", RequestPriority::Interactive, None));
+        assert_eq!(queue.pop().map(|q| q.client.to_string()).as_deref(), Some("ui"));
         
-        match crate::transport::HidTransport::new_for_device(device_info.serial_number.as_deref()) {
-            Ok(hid_transport) => {
-                info!("✅ Created HID transport for device {}", device_info.unique_id);
-                Ok(Box::new(hid_transport))
-            }
-            Err(hid_err) => {
-                Err(anyhow!("Failed with both primary transport ({}) and HID fallback ({})", previous_error, hid_err))
-            }
-        }
+        frontload.cancel();
+        assert_eq!(queue.remove_cancelled(), 3);
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|q| q.client.to_string()).collect();
+        assert_eq!(order, ["sync"]);
     }
     
-    /// Find the physical device matching device info (static method)
-    fn find_physical_device_by_info(device_info: &FriendlyUsbDevice, devices: &[rusb::Device<rusb::GlobalContext>]) -> Result<rusb::Device<rusb::GlobalContext>> {
-        if let Some(serial) = &device_info.serial_number {
-            // Match by serial number (flexible - allows PID change after bootloader update)
-            for device in devices {
-                if let Ok(handle) = device.open() {
-                    let timeout = std::time::Duration::from_millis(100);
-                    if let Ok(langs) = handle.read_languages(timeout) {
-                        if let Some(lang) = langs.first() {
-                            if let Ok(desc) = device.device_descriptor() {
-                                // Only check VID matches (KeepKey vendor ID)
-                                if desc.vendor_id() == device_info.vid {
-                                    if let Ok(device_serial) = handle.read_serial_number_string(*lang, &desc, timeout) {
-                                        if device_serial == *serial {
-                                            // Log if PID changed (happens after bootloader update)
-                                            if desc.product_id() != device_info.pid {
-                                                info!("📝 Device reconnected with different PID: 0x{:04x} -> 0x{:04x}", 
-                                                      device_info.pid, desc.product_id());
-                                            }
-                                            return Ok(device.clone());
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    #[tokio::test]
+    async fn cancelling_a_batch_fails_its_pending_calls() {
+        let (cmd_tx, mut cmd_rx) = mpsc::channel(4);
+        let handle = DeviceQueueHandle::with_metrics("cancel-test".into(), cmd_tx, Arc::default());
+        let (batch, token) = handle.with_priority(RequestPriority::Background).cancellable();
         
-        // Try to parse bus and address from unique_id
-        let parts: Vec<&str> = device_info.unique_id.split('_').collect();
-        if parts.len() >= 2 {
-            let bus_str = parts[0].strip_prefix("bus").unwrap_or("");
-            let addr_str = parts[1].strip_prefix("addr").unwrap_or("");
-            
-            if let (Ok(bus), Ok(addr)) = (bus_str.parse::<u8>(), addr_str.parse::<u8>()) {
-                for device in devices {
-                    if device.bus_number() == bus && device.address() == addr {
-                        return Ok(device.clone());
-                    }
-                }
+        let call = tokio::spawn(async move { batch.get_features().await });
+        let queued = cmd_rx.recv().await.unwrap();
+        assert_eq!(queued.priority, RequestPriority::Background);
+        token.cancel();
+        assert!(queued.is_cancelled());
+        assert_eq!(call.await.unwrap().unwrap_err().to_string(), "Request cancelled");
+    }
+    
+    #[test]
+    fn long_waits_count_as_starvation_per_client() {
+        let mut metrics = DeviceQueueMetrics::default();
+        assert!(!metrics.record_client("ui", Duration::from_millis(20)));
+        assert!(metrics.record_client("ui", STARVATION_THRESHOLD + Duration::from_secs(1)));
+        let ui = &metrics.clients["ui"];
+        assert_eq!((ui.served, ui.starved, ui.max_queue_wait_ms), (2, 1, 6_000));
+        assert_eq!(metrics.starved_requests, 1);
+    }
+
+    #[cfg(feature = "mock-device")]
+    #[tokio::test]
+    async fn mock_worker_answers_without_hardware() {
+        let handle = DeviceQueueFactory::spawn_mock_worker();
+        let features = handle.get_features().await.unwrap();
+        assert_eq!(features.device_id.as_deref(), Some(crate::transport::mock::MOCK_DEVICE_ID));
+
+        let hardened = 0x8000_0000;
+        let address = handle
+            .get_address(vec![84 | hardened, hardened, hardened, 0, 0], "Bitcoin".to_string(), Some(3), None)
+            .await
+            .unwrap();
+        assert_eq!(address, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    }
+
+    #[cfg(feature = "mock-device")]
+    #[tokio::test]
+    async fn keep_claimed_holds_the_transport_across_a_burst() {
+        let hardened = 0x8000_0000;
+        let burst = |handle: DeviceQueueHandle| async move {
+            // Distinct indexes, so none is answered from the response cache
+            for index in 0..3 {
+                let path = vec![84 | hardened, hardened, hardened, 0, index];
+                handle.get_address(path, "Bitcoin".to_string(), Some(3), None).await.unwrap();
             }
+            let metrics = handle.metrics();
+            (metrics.transport_claims, metrics.transport_releases)
+        };
+
+        assert_eq!(burst(DeviceQueueFactory::spawn_mock_worker()).await, (3, 3));
+
+        set_keep_claimed(Some(Duration::from_millis(50)));
+        let handle = DeviceQueueFactory::spawn_mock_worker();
+        assert_eq!(burst(handle.clone()).await, (1, 0));
+        sleep(Duration::from_millis(200)).await;
+        set_keep_claimed(None);
+        assert_eq!(handle.metrics().transport_releases, 1);
+    }
+
+    #[tokio::test]
+    async fn signing_round_trips_report_progress() {
+        use crate::messages::{RequestType, SignTx, TxRequest, TxRequestDetailsType};
+
+        let device = FriendlyUsbDevice::new("progress-test".into(), 0x2b24, 0x0002, None, None, None);
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut worker = DeviceWorker::new("progress-test".into(), device, cmd_rx, Arc::default());
+        let handle = DeviceQueueHandle::with_metrics("progress-test".into(), mpsc::channel(1).0, Arc::default());
+        let mut progress = handle.subscribe_progress();
+
+        let request = |request_type: RequestType, index: u32, tx_hash: Option<Vec<u8>>| {
+            Message::TxRequest(TxRequest {
+                request_type: Some(request_type as i32),
+                details: Some(TxRequestDetailsType { request_index: Some(index), tx_hash, ..Default::default() }),
+                ..Default::default()
+            })
+        };
+        worker.start_progress(&Message::SignTx(SignTx { inputs_count: 2, outputs_count: 1, ..Default::default() }));
+        worker.track_progress(&request(RequestType::Txinput, 0, Some(vec![0xab])));
+        worker.track_progress(&request(RequestType::Txinput, 1, None));
+        worker.track_progress(&request(RequestType::Txoutput, 0, None));
+        worker.track_progress(&request(RequestType::Txfinished, 0, None));
+
+        let mut steps = Vec::new();
+        for _ in 0..3 {
+            let step = progress.recv().await.unwrap();
+            steps.push((step.stage, step.current, step.total));
         }
-        
-        Err(anyhow!("Physical device not found for {} (VID: 0x{:04x}, PID: 0x{:04x}, Serial: {:?})", 
-                    device_info.unique_id, device_info.vid, device_info.pid, device_info.serial_number))
+        assert_eq!(steps, [("input".to_string(), 2, 2), ("output".to_string(), 1, 1), ("finished".to_string(), 1, 1)]);
+        assert_eq!(worker.signing, None);
     }
-} 
\ No newline at end of file
+}
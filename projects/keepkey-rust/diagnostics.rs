@@ -0,0 +1,148 @@
+//! Diagnostics the firmware writes to its DEBUG_LINK interface (`DebugLinkLog`).
+//!
+//! When collection is on, device queue workers open DEBUG_LINK next to the
+//! normal interface (only debug firmware builds have one) and read the log
+//! lines the device wrote while serving each command. Every line is tagged
+//! with the command's operation and with the request id the caller set through
+//! [`DeviceQueueHandle::with_request_id`](crate::device_queue::DeviceQueueHandle::with_request_id),
+//! so it can be matched with the host's own logs.
+//!
+//! Applications read the most recent lines with [`recent`] or follow them with
+//! [`subscribe`]. Collection is off unless set for the run (see
+//! [`DIAGNOSTICS_ENV`] and [`set_collection_enabled`]).
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+use crate::messages::DebugLinkLog;
+
+/// Environment variable that turns collection on for the run (`1` or `true`)
+pub const DIAGNOSTICS_ENV: &str = "KEEPKEY_DEVICE_DIAGNOSTICS";
+
+/// Lines kept for [`recent`], across all devices
+pub const RECENT_CAPACITY: usize = 1_000;
+
+const CHANNEL_SIZE: usize = 256;
+
+/// One `DebugLinkLog` line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceDiagnostic {
+    pub device_id: String,
+    /// Request id of the command the device was serving, if the caller set one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Queue operation being served, e.g. `send_raw:SignTx`
+    pub operation: String,
+    /// Firmware log level
+    pub level: u32,
+    /// Firmware subsystem that wrote the line
+    pub bucket: String,
+    pub text: String,
+    /// When the host read the line, in milliseconds since the Unix epoch
+    pub received_at: u64,
+}
+
+impl DeviceDiagnostic {
+    pub(crate) fn from_log(device_id: &str, request_id: Option<&str>, operation: &str, log: DebugLinkLog) -> Self {
+        Self {
+            device_id: device_id.to_string(),
+            request_id: request_id.map(str::to_string),
+            operation: operation.to_string(),
+            level: log.level.unwrap_or_default(),
+            bucket: log.bucket.unwrap_or_default(),
+            text: log.text.unwrap_or_default(),
+            received_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn enabled() -> &'static AtomicBool {
+    static ENABLED: OnceLock<AtomicBool> = OnceLock::new();
+    ENABLED.get_or_init(|| {
+        let on = std::env::var(DIAGNOSTICS_ENV)
+            .is_ok_and(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "on"));
+        AtomicBool::new(on)
+    })
+}
+
+pub fn collection_enabled() -> bool {
+    enabled().load(Ordering::Relaxed)
+}
+
+/// Turn collection on or off (initially read from [`DIAGNOSTICS_ENV`]).
+/// Workers pick it up when they next open the device.
+pub fn set_collection_enabled(on: bool) {
+    enabled().store(on, Ordering::Relaxed);
+}
+
+fn recent_lines() -> &'static Mutex<VecDeque<DeviceDiagnostic>> {
+    static RECENT: OnceLock<Mutex<VecDeque<DeviceDiagnostic>>> = OnceLock::new();
+    RECENT.get_or_init(|| Mutex::new(VecDeque::with_capacity(RECENT_CAPACITY)))
+}
+
+fn sender() -> &'static broadcast::Sender<DeviceDiagnostic> {
+    static SENDER: OnceLock<broadcast::Sender<DeviceDiagnostic>> = OnceLock::new();
+    SENDER.get_or_init(|| broadcast::channel(CHANNEL_SIZE).0)
+}
+
+pub(crate) fn record(diagnostic: DeviceDiagnostic) {
+    {
+        let mut recent = recent_lines().lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(diagnostic.clone());
+    }
+    let _ = sender().send(diagnostic);
+}
+
+/// Lines from every device as workers read them, including workers spawned
+/// after subscribing
+pub fn subscribe() -> broadcast::Receiver<DeviceDiagnostic> {
+    sender().subscribe()
+}
+
+/// Up to `limit` of the latest lines, oldest first, optionally only those of
+/// one device or one request
+pub fn recent(device_id: Option<&str>, request_id: Option<&str>, limit: usize) -> Vec<DeviceDiagnostic> {
+    let recent = recent_lines().lock().unwrap_or_else(|e| e.into_inner());
+    let mut lines: Vec<DeviceDiagnostic> = recent
+        .iter()
+        .rev()
+        .filter(|line| device_id.is_none_or(|id| line.device_id == id))
+        .filter(|line| request_id.is_none_or(|id| line.request_id.as_deref() == Some(id)))
+        .take(limit)
+        .cloned()
+        .collect();
+    lines.reverse();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_lines_filter_by_device_and_request() {
+        let log = |text: &str| DebugLinkLog { level: Some(1), bucket: Some("signing".into()), text: Some(text.into()) };
+        record(DeviceDiagnostic::from_log("diag-a", Some("req-1"), "send_raw:SignTx", log("first")));
+        record(DeviceDiagnostic::from_log("diag-b", None, "get_features", log("other device")));
+        record(DeviceDiagnostic::from_log("diag-a", Some("req-2"), "send_raw:SignTx", log("second")));
+
+        let device: Vec<String> = recent(Some("diag-a"), None, 10).into_iter().map(|line| line.text).collect();
+        assert_eq!(device, ["first", "second"]);
+
+        let request = recent(Some("diag-a"), Some("req-1"), 10);
+        assert_eq!(request.len(), 1);
+        assert_eq!(request[0].operation, "send_raw:SignTx");
+
+        assert_eq!(recent(Some("diag-a"), None, 1)[0].text, "second");
+    }
+}
@@ -0,0 +1,298 @@
+//! Stable, machine-readable codes for the `Failure` messages the firmware
+//! sends.
+//!
+//! Firmware failures carry a protobuf `FailureType` (often missing, or just
+//! `Failure_Other`) and free English text that varies between firmware
+//! versions. [`FailureCode`] folds both into one stable name that scripts can
+//! branch on, and [`FailureCode::description`] gives user-facing text per
+//! language so frontends can localize it. English is the only table today;
+//! another language is one more entry in `DESCRIPTIONS`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{Failure, FailureType, Message};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCode {
+    UnexpectedMessage,
+    /// The firmware doesn't know the message type
+    UnknownMessage,
+    ButtonExpected,
+    SyntaxError,
+    ActionCancelled,
+    PinExpected,
+    PinCancelled,
+    PinInvalid,
+    PinMismatch,
+    InvalidSignature,
+    NotEnoughFunds,
+    NotInitialized,
+    AlreadyInitialized,
+    FirmwareError,
+    Other,
+}
+
+/// Descriptions per language, English first; every table lists every code
+static DESCRIPTIONS: &[(&str, &[(FailureCode, &str)])] = &[("en", ENGLISH)];
+
+static ENGLISH: &[(FailureCode, &str)] = &[
+    (FailureCode::UnexpectedMessage, "The device wasn't expecting that request right now."),
+    (FailureCode::UnknownMessage, "The device firmware doesn't support this request."),
+    (FailureCode::ButtonExpected, "Confirm or cancel the pending action on the device first."),
+    (FailureCode::SyntaxError, "The device couldn't read the request."),
+    (FailureCode::ActionCancelled, "The action was cancelled on the device."),
+    (FailureCode::PinExpected, "The device is waiting for its PIN."),
+    (FailureCode::PinCancelled, "PIN entry was cancelled."),
+    (FailureCode::PinInvalid, "Incorrect PIN."),
+    (FailureCode::PinMismatch, "The PINs entered don't match."),
+    (FailureCode::InvalidSignature, "The signature is not valid."),
+    (FailureCode::NotEnoughFunds, "Not enough funds for this transaction."),
+    (FailureCode::NotInitialized, "The device has no wallet yet. Create or recover one first."),
+    (FailureCode::AlreadyInitialized, "The device already holds a wallet. Wipe it first."),
+    (FailureCode::FirmwareError, "The device firmware reported an internal error."),
+    (FailureCode::Other, "The device reported an error."),
+];
+
+impl FailureCode {
+    pub const ALL: [FailureCode; 15] = [
+        FailureCode::UnexpectedMessage,
+        FailureCode::UnknownMessage,
+        FailureCode::ButtonExpected,
+        FailureCode::SyntaxError,
+        FailureCode::ActionCancelled,
+        FailureCode::PinExpected,
+        FailureCode::PinCancelled,
+        FailureCode::PinInvalid,
+        FailureCode::PinMismatch,
+        FailureCode::InvalidSignature,
+        FailureCode::NotEnoughFunds,
+        FailureCode::NotInitialized,
+        FailureCode::AlreadyInitialized,
+        FailureCode::FirmwareError,
+        FailureCode::Other,
+    ];
+
+    /// Code for a Failure. Firmware text refines a missing or generic
+    /// `FailureType`, since several errors are only told apart by their text.
+    pub fn from_failure(failure: &Failure) -> Self {
+        let text = failure.message.as_deref().unwrap_or_default().to_ascii_lowercase();
+        if text.contains("unknown message") {
+            return FailureCode::UnknownMessage;
+        }
+        if text.contains("already initialized") {
+            return FailureCode::AlreadyInitialized;
+        }
+        match failure.code.and_then(FailureType::from_i32) {
+            Some(FailureType::FailureUnexpectedMessage) => FailureCode::UnexpectedMessage,
+            Some(FailureType::FailureButtonExpected) => FailureCode::ButtonExpected,
+            Some(FailureType::FailureSyntaxError) => FailureCode::SyntaxError,
+            Some(FailureType::FailureActionCancelled) => FailureCode::ActionCancelled,
+            Some(FailureType::FailurePinExpected) => FailureCode::PinExpected,
+            Some(FailureType::FailurePinCancelled) => FailureCode::PinCancelled,
+            Some(FailureType::FailurePinInvalid) => FailureCode::PinInvalid,
+            Some(FailureType::FailurePinMismatch) => FailureCode::PinMismatch,
+            Some(FailureType::FailureInvalidSignature) => FailureCode::InvalidSignature,
+            Some(FailureType::FailureNotEnoughFunds) => FailureCode::NotEnoughFunds,
+            Some(FailureType::FailureNotInitialized) => FailureCode::NotInitialized,
+            Some(FailureType::FailureFirmwareError) => FailureCode::FirmwareError,
+            Some(FailureType::FailureOther) | None => Self::from_text(&text),
+        }
+    }
+
+    fn from_text(text: &str) -> Self {
+        if text.contains("invalid pin") {
+            FailureCode::PinInvalid
+        } else if text.contains("pin cancelled") {
+            FailureCode::PinCancelled
+        } else if text.contains("pins do not match") {
+            FailureCode::PinMismatch
+        } else if text.contains("cancelled") || text.contains("canceled") {
+            FailureCode::ActionCancelled
+        } else if text.contains("not initialized") {
+            FailureCode::NotInitialized
+        } else {
+            FailureCode::Other
+        }
+    }
+
+    /// The code as serialized, e.g. `pin_invalid`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureCode::UnexpectedMessage => "unexpected_message",
+            FailureCode::UnknownMessage => "unknown_message",
+            FailureCode::ButtonExpected => "button_expected",
+            FailureCode::SyntaxError => "syntax_error",
+            FailureCode::ActionCancelled => "action_cancelled",
+            FailureCode::PinExpected => "pin_expected",
+            FailureCode::PinCancelled => "pin_cancelled",
+            FailureCode::PinInvalid => "pin_invalid",
+            FailureCode::PinMismatch => "pin_mismatch",
+            FailureCode::InvalidSignature => "invalid_signature",
+            FailureCode::NotEnoughFunds => "not_enough_funds",
+            FailureCode::NotInitialized => "not_initialized",
+            FailureCode::AlreadyInitialized => "already_initialized",
+            FailureCode::FirmwareError => "firmware_error",
+            FailureCode::Other => "other",
+        }
+    }
+
+    /// User-facing text in `language` (e.g. `en`, `en-US`), falling back to
+    /// English for languages without a table
+    pub fn description(self, language: &str) -> &'static str {
+        descriptions(language)
+            .iter()
+            .find(|(code, _)| *code == self)
+            .map_or("The device reported an error.", |(_, text)| text)
+    }
+}
+
+impl std::fmt::Display for FailureCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The whole description table for `language`, English when there is none
+pub fn descriptions(language: &str) -> &'static [(FailureCode, &'static str)] {
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    DESCRIPTIONS
+        .iter()
+        .find(|(lang, _)| lang.eq_ignore_ascii_case(primary))
+        .map_or(ENGLISH, |(_, table)| table)
+}
+
+/// A Failure from the device, as an error. Displays as `Failure: <firmware
+/// text>` like the message handlers always have; callers holding an
+/// `anyhow::Error` get the code back with `downcast_ref::<DeviceFailure>()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+#[error("Failure: {message}")]
+pub struct DeviceFailure {
+    pub code: FailureCode,
+    /// Raw `FailureType` value, if the firmware sent one
+    pub firmware_code: Option<i32>,
+    /// Firmware text, untranslated
+    pub message: String,
+}
+
+impl From<&Failure> for DeviceFailure {
+    fn from(failure: &Failure) -> Self {
+        Self {
+            code: FailureCode::from_failure(failure),
+            firmware_code: failure.code,
+            message: failure.message.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl DeviceFailure {
+    /// The Failure behind `error`, if a device answered with one
+    pub fn find(error: &anyhow::Error) -> Option<&DeviceFailure> {
+        error.chain().find_map(|cause| cause.downcast_ref::<DeviceFailure>())
+    }
+}
+
+/// Why a request ran out of time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutKind {
+    /// The device didn't answer; it may need unplugging and reconnecting
+    DeviceUnresponsive,
+    /// The device was waiting for a button press that never came
+    UserTookTooLong,
+}
+
+/// A device reply that didn't arrive in time. Like [`DeviceFailure`], callers
+/// holding an `anyhow::Error` get it back with [`DeviceTimeout::find`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+pub struct DeviceTimeout {
+    pub kind: TimeoutKind,
+    /// Message whose reply was awaited, e.g. `GetFeatures` or `ButtonAck`
+    pub waiting_for: String,
+    pub limit_ms: u64,
+}
+
+impl std::fmt::Display for DeviceTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let seconds = (self.limit_ms as f64 / 1000.0).ceil();
+        match self.kind {
+            TimeoutKind::DeviceUnresponsive => {
+                write!(f, "Device unresponsive: no reply to {} within {}s", self.waiting_for, seconds)
+            }
+            TimeoutKind::UserTookTooLong => {
+                write!(f, "User took too long: nothing was confirmed on the device within {}s", seconds)
+            }
+        }
+    }
+}
+
+impl DeviceTimeout {
+    /// Timeout waiting for the reply to `sent`, blamed on the user when the
+    /// device was showing a screen for them
+    pub fn waiting_for(sent: &Message, limit: std::time::Duration) -> Self {
+        let kind = if sent.waits_on_user() { TimeoutKind::UserTookTooLong } else { TimeoutKind::DeviceUnresponsive };
+        let name = sent.message_type().as_str_name();
+        Self {
+            kind,
+            waiting_for: name.strip_prefix("MessageType_").unwrap_or(name).to_string(),
+            limit_ms: limit.as_millis() as u64,
+        }
+    }
+
+    /// The timeout behind `error`, if a reply didn't arrive in time
+    pub fn find(error: &anyhow::Error) -> Option<&DeviceTimeout> {
+        error.chain().find_map(|cause| cause.downcast_ref::<DeviceTimeout>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(code: Option<FailureType>, message: &str) -> Failure {
+        Failure { code: code.map(|c| c as i32), message: Some(message.to_string()) }
+    }
+
+    #[test]
+    fn codes_come_from_the_type_and_refine_generic_text() {
+        let cases = [
+            (failure(Some(FailureType::FailureActionCancelled), "Wipe cancelled"), FailureCode::ActionCancelled),
+            (failure(Some(FailureType::FailureOther), "Invalid PIN"), FailureCode::PinInvalid),
+            (failure(None, "Action cancelled by user"), FailureCode::ActionCancelled),
+            (failure(Some(FailureType::FailureUnexpectedMessage), "Unknown message"), FailureCode::UnknownMessage),
+            (failure(Some(FailureType::FailureOther), "Something new"), FailureCode::Other),
+        ];
+        for (failure, expected) in cases {
+            assert_eq!(FailureCode::from_failure(&failure), expected, "{:?}", failure.message);
+        }
+
+        let error = anyhow::Error::from(DeviceFailure::from(&failure(None, "PIN Cancelled"))).context("Unlock failed");
+        let found = DeviceFailure::find(&error).unwrap();
+        assert_eq!(serde_json::to_value(found.code).unwrap(), "pin_cancelled");
+        assert_eq!(found.to_string(), "Failure: PIN Cancelled");
+    }
+
+    #[test]
+    fn every_code_is_described_in_every_language() {
+        for (language, table) in DESCRIPTIONS {
+            for code in FailureCode::ALL {
+                assert!(table.iter().any(|(c, _)| *c == code), "{} lacks {}", language, code);
+                assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+            }
+        }
+        assert_eq!(FailureCode::PinInvalid.description("fr-CA"), "Incorrect PIN.");
+    }
+
+    #[test]
+    fn timeouts_blame_the_user_only_after_a_button_ack() {
+        let limit = std::time::Duration::from_millis(4500);
+        let device = DeviceTimeout::waiting_for(&crate::messages::GetFeatures::default().into(), limit);
+        assert_eq!(device.kind, TimeoutKind::DeviceUnresponsive);
+        assert_eq!(device.to_string(), "Device unresponsive: no reply to GetFeatures within 5s");
+
+        let user = DeviceTimeout::waiting_for(&crate::messages::ButtonAck::default().into(), limit);
+        assert_eq!(user.kind, TimeoutKind::UserTookTooLong);
+        let error = anyhow::Error::from(user).context("Wipe failed");
+        assert_eq!(DeviceTimeout::find(&error).map(|t| t.kind), Some(TimeoutKind::UserTookTooLong));
+    }
+}
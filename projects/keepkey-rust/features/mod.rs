@@ -8,7 +8,10 @@ use std::sync::{Arc, Mutex};
 use once_cell::sync::Lazy;
 
 use crate::messages::{Initialize, Message};
-use crate::transport::{ProtocolAdapter, UsbTransport, HidTransport};
+use crate::transport::{ProtocolAdapter, UsbTransport};
+#[cfg(feature = "hid")]
+use crate::transport::HidTransport;
+use crate::device_identity::{Attachment, UsbPort};
 use crate::friendly_usb::FriendlyUsbDevice;
 
 
@@ -48,7 +51,7 @@ fn clean_device_cache() {
 //   - /docs/usb/oob_mode_detection.md
 //   - /docs/usb/hid_fallback_implementation.md
 //   - Vault backend (src-tauri/src/features/mod.rs)
-pub fn list_devices() -> Box<[Device<GlobalContext>]> {
+pub(crate) fn list_devices() -> Box<[Device<GlobalContext>]> {
     rusb::devices()
         .unwrap()
         .iter()
@@ -171,7 +174,10 @@ pub fn get_device_features_for_device(target_device: &FriendlyUsbDevice) -> Resu
     
     let devices = list_devices();
     
-    let device = if let Some(serial) = &target_device.serial_number {
+    let device = if crate::transport::is_mock_device(target_device) {
+        // The simulator has no USB device behind it
+        None
+    } else if let Some(serial) = target_device.serial_number.as_ref().filter(|s| !s.is_empty()) {
         devices.iter().find(|d| {
             if let Ok(handle) = d.open() {
                 let timeout = std::time::Duration::from_millis(100);
@@ -188,27 +194,16 @@ pub fn get_device_features_for_device(target_device: &FriendlyUsbDevice) -> Resu
             false
         })
     } else {
-        let parts: Vec<&str> = target_device.unique_id.split('_').collect();
-        if parts.len() >= 2 {
-            let bus_str = parts[0].strip_prefix("bus").unwrap_or("");
-            let addr_str = parts[1].strip_prefix("addr").unwrap_or("");
-            
-            if let (Ok(bus), Ok(addr)) = (bus_str.parse::<u8>(), addr_str.parse::<u8>()) {
-                devices.iter().find(|d| d.bus_number() == bus && d.address() == addr)
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+        crate::device_identity::usb_address(&target_device.unique_id)
+            .and_then(|(bus, addr)| devices.iter().find(|d| d.bus_number() == bus && d.address() == addr))
     };
 
-    let device = device
-        .ok_or_else(|| anyhow!("Specific KeepKey device not found: {}", target_device.unique_id))?
-        .to_owned();
+    if device.is_none() && !crate::transport::is_mock_device(target_device) {
+        return Err(anyhow!("Specific KeepKey device not found: {}", target_device.unique_id));
+    }
 
     // Use device queue's smart transport selection (WebUSB aware)
-    let mut transport = crate::device_queue::DeviceQueueFactory::create_transport_for_device(target_device)
+    let mut transport = crate::transport::create_transport_for_device(target_device)
         .map_err(|e| anyhow!("Failed to initialize transport for device {}: {}", target_device.unique_id, e))?;
 
     // Reset the device to clear any stuck state
@@ -282,6 +277,7 @@ pub fn get_device_features_for_device(target_device: &FriendlyUsbDevice) -> Resu
 /// # Returns
 /// - `Ok(DeviceFeatures)` if successful with all device information
 /// - `Err` if device connection fails or the device doesn't respond properly
+#[deprecated(note = "picks an arbitrary device; use get_device_features_by_id")]
 pub fn get_device_features_impl() -> Result<DeviceFeatures> {
     // Find and connect to device
     let device = list_devices()
@@ -445,6 +441,7 @@ pub fn get_device_features_with_fallback(target_device: &FriendlyUsbDevice) -> R
 /// # Returns
 /// - `Ok(DeviceFeatures)` if successful with all device information
 /// - `Err` if device connection fails or the device doesn't respond properly
+#[cfg(feature = "hid")]
 pub fn get_device_features_via_hid(target_device: &FriendlyUsbDevice) -> Result<DeviceFeatures> {
     use hidapi::HidApi;
     log::info!("{TAG} Getting features for device via HID: {} ({})", target_device.name, target_device.unique_id);
@@ -586,6 +583,11 @@ pub fn get_device_features_via_hid(target_device: &FriendlyUsbDevice) -> Result<
     Err(anyhow!("All HID attempts failed for device {}. Errors: {}", target_device.unique_id, errors.join(" | ")))
 }
 
+#[cfg(not(feature = "hid"))]
+pub fn get_device_features_via_hid(target_device: &FriendlyUsbDevice) -> Result<DeviceFeatures> {
+    Err(anyhow!("HID transport not built (enable the `hid` feature); cannot reach {}", target_device.unique_id))
+}
+
 /// Convert a low-level USB device to a FriendlyUsbDevice
 /// This function handles all the USB string descriptor reading internally
 fn device_to_friendly(device: &rusb::Device<rusb::GlobalContext>) -> FriendlyUsbDevice {
@@ -686,6 +688,7 @@ pub fn list_connected_devices() -> Vec<FriendlyUsbDevice> {
     let devices = list_devices();
     let mut current_devices = Vec::new();
     let mut seen_bus_addr = std::collections::HashSet::new();
+    let mut serial_less = Vec::new();
     
     for device in devices.iter() {
         // Only process KeepKey devices
@@ -702,11 +705,25 @@ pub fn list_connected_devices() -> Vec<FriendlyUsbDevice> {
                 seen_bus_addr.insert(bus_addr_key.clone());
                 
                 let friendly_device = device_to_friendly_with_cache(device);
+                if friendly_device.serial_number.is_none() {
+                    serial_less.push((current_devices.len(), Attachment { bus, address: addr, port: UsbPort::of(device) }));
+                }
                 current_devices.push(friendly_device);
             }
         }
     }
     
+    // Serial-less devices are named after sticky slots, not their bus address
+    let (indexes, attached): (Vec<usize>, Vec<Attachment>) = serial_less.into_iter().unzip();
+    for (index, slot_id) in indexes.into_iter().zip(crate::device_identity::assign_slots(&attached)) {
+        current_devices[index].unique_id = slot_id;
+    }
+    
+    #[cfg(feature = "mock-device")]
+    if crate::transport::mock::demo_mode() {
+        current_devices.push(crate::transport::MockDevice::device_info());
+    }
+    
     current_devices
 }
 
@@ -775,6 +792,9 @@ fn device_to_friendly_with_cache(device: &rusb::Device<rusb::GlobalContext>) ->
         }
     };
     
+    // Some devices report an empty serial; that identifies nothing
+    let serial_number = serial_number.filter(|serial| !serial.is_empty());
+    
     // Determine stable unique ID - prefer serial if available
     let stable_id = if let Some(ref serial) = serial_number {
         if !serial.is_empty() {
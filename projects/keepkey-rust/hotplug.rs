@@ -0,0 +1,195 @@
+//! KeepKey connect and disconnect notifications.
+//!
+//! [`DeviceWatcher::start`] registers a libusb hotplug callback for the
+//! KeepKey vendor id, so changes are reported as soon as the OS sees them and
+//! nothing runs in between. Where libusb has no hotplug support (Windows) the
+//! watcher falls back to comparing the KeepKey bus addresses every
+//! [`POLL_INTERVAL`], which only enumerates and never opens a device.
+//!
+//! Changes only say that something happened at a bus address; call
+//! [`list_connected_devices`](crate::features::list_connected_devices) to
+//! learn which devices are now attached.
+
+use anyhow::{anyhow, Result};
+use rusb::{Device, Hotplug, HotplugBuilder, UsbContext};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::friendly_usb::KEEPKEY_VID;
+
+/// How often the fallback compares bus addresses
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// How long the hotplug thread waits for libusb events before checking for shutdown
+const EVENT_WAIT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsbChangeKind {
+    Arrived,
+    Left,
+}
+
+/// A KeepKey appeared at or disappeared from a bus address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsbChange {
+    pub kind: UsbChangeKind,
+    pub product_id: u16,
+    pub bus: u8,
+    pub address: u8,
+}
+
+/// How a [`DeviceWatcher`] learns about changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    Hotplug,
+    Polling,
+}
+
+type Callback = Arc<dyn Fn(UsbChange) + Send + Sync>;
+
+/// Reports KeepKey connects and disconnects to a callback until dropped
+pub struct DeviceWatcher {
+    mode: WatchMode,
+    stop: Arc<AtomicBool>,
+    context: Option<rusb::Context>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DeviceWatcher {
+    /// Start watching. `on_change` runs on the watcher's thread and should
+    /// only hand the change off (it must not open the device).
+    pub fn start(on_change: impl Fn(UsbChange) + Send + Sync + 'static) -> Result<Self> {
+        let on_change: Callback = Arc::new(on_change);
+        if rusb::has_hotplug() {
+            match Self::start_hotplug(Arc::clone(&on_change)) {
+                Ok(watcher) => return Ok(watcher),
+                Err(e) => log::warn!("USB hotplug unavailable, polling instead: {}", e),
+            }
+        }
+        Self::start_polling(on_change)
+    }
+
+    pub fn mode(&self) -> WatchMode {
+        self.mode
+    }
+
+    fn start_hotplug(on_change: Callback) -> Result<Self> {
+        // A context of its own, so handling hotplug events never runs
+        // alongside transfers on the global one
+        let context = rusb::Context::new().map_err(|e| anyhow!("Failed to create USB context: {}", e))?;
+        let registration: rusb::Registration<rusb::Context> = HotplugBuilder::new()
+            .vendor_id(KEEPKEY_VID)
+            .enumerate(false)
+            .register(&context, Box::new(Forward(on_change)))
+            .map_err(|e| anyhow!("Failed to register hotplug callback: {}", e))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let context = context.clone();
+            let stop = Arc::clone(&stop);
+            std::thread::Builder::new().name("keepkey-hotplug".into()).spawn(move || {
+                let _registration = registration;
+                while !stop.load(Ordering::Relaxed) {
+                    if let Err(e) = context.handle_events(Some(EVENT_WAIT)) {
+                        log::warn!("USB hotplug event handling failed: {}", e);
+                        std::thread::sleep(EVENT_WAIT);
+                    }
+                }
+            })?
+        };
+        log::info!("Watching for KeepKey connections with USB hotplug");
+        Ok(Self { mode: WatchMode::Hotplug, stop, context: Some(context), thread: Some(thread) })
+    }
+
+    fn start_polling(on_change: Callback) -> Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            std::thread::Builder::new().name("keepkey-usb-poll".into()).spawn(move || {
+                let mut attached = keepkey_addresses();
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(POLL_INTERVAL);
+                    let now = keepkey_addresses();
+                    for change in changes(&attached, &now) {
+                        on_change(change);
+                    }
+                    attached = now;
+                }
+            })?
+        };
+        log::info!("Watching for KeepKey connections by polling every {:?}", POLL_INTERVAL);
+        Ok(Self { mode: WatchMode::Polling, stop, context: None, thread: Some(thread) })
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(context) = &self.context {
+            context.interrupt_handle_events();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Forward(Callback);
+
+impl Forward {
+    fn forward<T: UsbContext>(&self, kind: UsbChangeKind, device: &Device<T>) {
+        // Descriptors are cached by libusb, so reading them here is allowed
+        let product_id = device.device_descriptor().map(|d| d.product_id()).unwrap_or_default();
+        (self.0)(UsbChange { kind, product_id, bus: device.bus_number(), address: device.address() });
+    }
+}
+
+impl<T: UsbContext> Hotplug<T> for Forward {
+    fn device_arrived(&mut self, device: Device<T>) {
+        self.forward(UsbChangeKind::Arrived, &device);
+    }
+
+    fn device_left(&mut self, device: Device<T>) {
+        self.forward(UsbChangeKind::Left, &device);
+    }
+}
+
+/// `(product id, bus, address)` of every attached KeepKey
+fn keepkey_addresses() -> HashSet<(u16, u8, u8)> {
+    let Ok(devices) = rusb::devices() else {
+        return HashSet::new();
+    };
+    devices
+        .iter()
+        .filter_map(|device| {
+            let descriptor = device.device_descriptor().ok()?;
+            (descriptor.vendor_id() == KEEPKEY_VID).then(|| (descriptor.product_id(), device.bus_number(), device.address()))
+        })
+        .collect()
+}
+
+fn changes(before: &HashSet<(u16, u8, u8)>, after: &HashSet<(u16, u8, u8)>) -> Vec<UsbChange> {
+    let change = |kind, &(product_id, bus, address): &(u16, u8, u8)| UsbChange { kind, product_id, bus, address };
+    let mut changes: Vec<UsbChange> = before.difference(after).map(|d| change(UsbChangeKind::Left, d)).collect();
+    changes.extend(after.difference(before).map(|d| change(UsbChangeKind::Arrived, d)));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polling_reports_departures_before_arrivals() {
+        let before: HashSet<_> = [(0x0002, 1, 4), (0x0001, 1, 7)].into();
+        // Re-enumerated at a new address after a reboot into the bootloader
+        let after: HashSet<_> = [(0x0002, 1, 4), (0x0001, 1, 9)].into();
+
+        let summary: Vec<_> = changes(&before, &after).into_iter().map(|c| (c.kind, c.address)).collect();
+        assert_eq!(summary, [(UsbChangeKind::Left, 7), (UsbChangeKind::Arrived, 9)]);
+        assert!(changes(&after, &after).is_empty());
+    }
+}
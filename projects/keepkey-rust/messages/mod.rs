@@ -3,6 +3,8 @@ mod macros;
 mod protos;
 mod timeouts;
 
+pub use encoding::EncodeError;
+pub use timeouts::{confirmation_timeout, set_timeout_overrides, timeout_overrides, TimeoutOverrides};
 pub use protos::*;
 
 use macros::kk_message;
@@ -1,5 +1,8 @@
-use super::Message;
+use super::{Message, MessageType};
+use anyhow::{anyhow, bail, Result};
 use core::time::Duration;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
 
 const TIMEOUT: Duration = Duration::from_millis(5000); // Increased for better device compatibility
 const LONG_TIMEOUT: Duration = Duration::from_millis(5 * 60 * 1000);
@@ -22,6 +25,76 @@ thread_local! {
     static USING_HID_TRANSPORT: std::cell::RefCell<bool> = std::cell::RefCell::new(false);
 }
 
+/// Application overrides of the reply deadlines below, e.g. from its config.
+/// Apply them with [`set_timeout_overrides`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeoutOverrides {
+    /// How long the device gets to answer a request of each type
+    pub read: HashMap<MessageType, Duration>,
+    /// How long the user gets to confirm a screen on the device
+    pub confirmation: Option<Duration>,
+}
+
+impl TimeoutOverrides {
+    /// Parse a comma-separated list like `GetFeatures=2s, SignTx=90s, confirm=10m`.
+    /// Keys are message names (with or without the `MessageType_` prefix) or
+    /// `confirm`; durations take `ms`, `s` or `m`, seconds when bare.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut overrides = Self::default();
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| anyhow!("{:?} needs a message type and a duration", item))?;
+            let (key, limit) = (key.trim(), parse_duration(value.trim())?);
+            if key.eq_ignore_ascii_case("confirm") {
+                overrides.confirmation = Some(limit);
+                continue;
+            }
+            let name = if key.starts_with("MessageType_") { key.to_string() } else { format!("MessageType_{}", key) };
+            let message_type = MessageType::from_str_name(&name).ok_or_else(|| anyhow!("unknown message type {:?}", key))?;
+            overrides.read.insert(message_type, limit);
+        }
+        Ok(overrides)
+    }
+}
+
+fn parse_duration(value: &str) -> Result<Duration> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(at) => value.split_at(at),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse().map_err(|_| anyhow!("invalid duration {:?}", value))?;
+    let limit = match unit.trim() {
+        "ms" => Duration::from_millis(number),
+        "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number * 60),
+        other => bail!("unknown unit {:?} in {:?} (expected ms, s or m)", other, value),
+    };
+    if limit.is_zero() {
+        bail!("timeout {:?} must be above zero", value);
+    }
+    Ok(limit)
+}
+
+fn overrides_state() -> &'static RwLock<TimeoutOverrides> {
+    static OVERRIDES: OnceLock<RwLock<TimeoutOverrides>> = OnceLock::new();
+    OVERRIDES.get_or_init(Default::default)
+}
+
+/// Replace the run's overrides; later requests pick them up
+pub fn set_timeout_overrides(overrides: TimeoutOverrides) {
+    *overrides_state().write().unwrap_or_else(|e| e.into_inner()) = overrides;
+}
+
+pub fn timeout_overrides() -> TimeoutOverrides {
+    overrides_state().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// How long the user gets to confirm a screen on the device
+pub fn confirmation_timeout() -> Duration {
+    timeout_overrides().confirmation.unwrap_or(LONG_TIMEOUT)
+}
+
 impl Message {
     /// Set whether we're communicating with a legacy device (PID 0x0001)
     /// This affects timeouts for all messages in the current thread
@@ -50,7 +123,65 @@ impl Message {
         USING_HID_TRANSPORT.with(|mode| *mode.borrow())
     }
     
+    /// How long the device gets to answer this message, overrides first
     pub fn read_timeout(&self) -> Duration {
+        {
+            let overrides = overrides_state().read().unwrap_or_else(|e| e.into_inner());
+            let limit = match self {
+                Message::ButtonAck(_) => overrides.confirmation,
+                _ => overrides.read.get(&self.message_type()).copied(),
+            };
+            if let Some(limit) = limit {
+                return limit;
+            }
+        }
+        self.default_read_timeout()
+    }
+
+    /// The answer to this message waits on the user rather than the device:
+    /// after a ButtonAck the device shows its screen until it is confirmed
+    pub fn waits_on_user(&self) -> bool {
+        matches!(self, Message::ButtonAck(_))
+    }
+
+    /// The request may put a screen up for the user to confirm before it completes
+    pub fn is_interactive(&self) -> bool {
+        match self {
+            Message::Ping(ping) => ping.button_protection == Some(true),
+            Message::GetAddress(req) => req.show_display == Some(true),
+            Message::GetPublicKey(req) => req.show_display == Some(true),
+            Message::ChangePin(_)
+            | Message::ChangeWipeCode(_)
+            | Message::WipeDevice(_)
+            | Message::ApplySettings(_)
+            | Message::ApplyPolicies(_)
+            | Message::LoadDevice(_)
+            | Message::ResetDevice(_)
+            | Message::RecoveryDevice(_)
+            | Message::SignTx(_)
+            | Message::TxAck(_)
+            | Message::SignMessage(_)
+            | Message::CipherKeyValue(_)
+            | Message::EncryptMessage(_)
+            | Message::DecryptMessage(_)
+            | Message::SignIdentity(_)
+            | Message::FirmwareErase(_)
+            | Message::FirmwareUpload(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Longest a whole request should take: the device's reply deadline, plus
+    /// the user's if it may wait on a confirmation
+    pub fn operation_timeout(&self) -> Duration {
+        if self.is_interactive() {
+            self.read_timeout() + confirmation_timeout()
+        } else {
+            self.read_timeout()
+        }
+    }
+
+    fn default_read_timeout(&self) -> Duration {
         // Windows HID fix: Use longer timeouts for Windows HID communication
         #[cfg(target_os = "windows")]
         {
@@ -107,3 +238,21 @@ impl Message {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_parse_per_message_type() {
+        let overrides = TimeoutOverrides::parse("GetFeatures=1500ms, MessageType_SignTx=2m, confirm=90").unwrap();
+        assert_eq!(overrides.read[&MessageType::GetFeatures], Duration::from_millis(1500));
+        assert_eq!(overrides.read[&MessageType::SignTx], Duration::from_secs(120));
+        assert_eq!(overrides.confirmation, Some(Duration::from_secs(90)));
+
+        assert!(TimeoutOverrides::parse("NoSuchMessage=5s").is_err());
+        assert!(TimeoutOverrides::parse("GetFeatures=5h").is_err());
+        assert!(TimeoutOverrides::parse("GetFeatures=0").is_err());
+        assert!(TimeoutOverrides::parse("GetFeatures").is_err());
+    }
+}
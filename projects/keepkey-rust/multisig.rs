@@ -0,0 +1,434 @@
+//! P2WSH multisig wallets described by an output descriptor.
+//!
+//! [`MultisigDescriptor`] reads `wsh(sortedmulti(...))` and `wsh(multi(...))`
+//! descriptors as Sparrow, Specter and Caravan export them, with every key
+//! given as `[fingerprint/origin]xpub/<0;1>/*`. It derives the receive and
+//! change addresses, checks that a device holds one of the cosigner keys, and
+//! fills in the `multisig` field of `GetAddress`, so the device can show (and
+//! vouch for) an address only when it recomputes the same script.
+//!
+//! Signing goes through [`PsbtSigner`](crate::psbt::PsbtSigner), which reads
+//! the cosigner xpubs from the PSBT's global xpub map;
+//! [`MultisigDescriptor::add_global_xpubs`] fills it in for PSBTs from wallets
+//! that leave it out.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint, KeySource};
+use bitcoin::blockdata::opcodes::all::OP_CHECKMULTISIG;
+use bitcoin::blockdata::opcodes::{Class, ClassifyContext};
+use bitcoin::blockdata::script::{Builder, Instruction};
+use bitcoin::secp256k1::{self, Secp256k1};
+use bitcoin::{Address, Network, PublicKey, Script, ScriptBuf};
+use thiserror::Error;
+
+use crate::messages::{GetAddress, HdNodePathType, HdNodeType, InputScriptType, MultisigRedeemScriptType};
+use crate::psbt::Psbt;
+
+const COIN_NAME: &str = "Bitcoin";
+/// Keys allowed in a standard P2WSH CHECKMULTISIG
+const MAX_COSIGNERS: usize = 15;
+
+// BIP-380 descriptor checksum alphabets
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+#[derive(Debug, Error)]
+pub enum MultisigError {
+    #[error("invalid multisig descriptor: {0}")]
+    Parse(String),
+    #[error("descriptor checksum is {found}, expected {expected}")]
+    Checksum { found: String, expected: String },
+    #[error("no cosigner has master fingerprint {0}")]
+    NotACosigner(Fingerprint),
+    #[error("device key at {path} doesn't match cosigner {fingerprint} in the descriptor")]
+    XpubMismatch { fingerprint: Fingerprint, path: String },
+    #[error("cannot derive {0}")]
+    Derivation(String),
+}
+
+/// One key of the wallet: whose seed it comes from, where, and the account xpub
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cosigner {
+    pub fingerprint: Fingerprint,
+    pub origin_path: DerivationPath,
+    pub xpub: ExtendedPubKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigDescriptor {
+    /// Signatures needed to spend
+    pub threshold: usize,
+    /// `sortedmulti` (keys sorted per address, BIP-67) rather than `multi`
+    pub sorted: bool,
+    pub cosigners: Vec<Cosigner>,
+}
+
+impl MultisigDescriptor {
+    /// Parse a `wsh(sortedmulti(k,KEY,...))` or `wsh(multi(k,KEY,...))`
+    /// descriptor. A trailing `#checksum` is verified when present.
+    pub fn parse(descriptor: &str) -> Result<Self, MultisigError> {
+        let descriptor = descriptor.trim();
+        let body = match descriptor.split_once('#') {
+            Some((body, found)) => {
+                let expected = descriptor_checksum(body)?;
+                if found != expected {
+                    return Err(MultisigError::Checksum { found: found.to_string(), expected });
+                }
+                body
+            }
+            None => descriptor,
+        };
+        let parse_err = |msg: &str| MultisigError::Parse(msg.to_string());
+
+        let inner = body
+            .strip_prefix("wsh(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(|| parse_err("only wsh(...) descriptors are supported"))?;
+        let (sorted, args) = if let Some(args) = inner.strip_prefix("sortedmulti(") {
+            (true, args)
+        } else if let Some(args) = inner.strip_prefix("multi(") {
+            (false, args)
+        } else {
+            return Err(parse_err("expected sortedmulti(...) or multi(...) inside wsh()"));
+        };
+        let args = args.strip_suffix(')').ok_or_else(|| parse_err("unbalanced parentheses"))?;
+
+        let mut parts = args.split(',').map(str::trim);
+        let threshold: usize = parts
+            .next()
+            .and_then(|k| k.parse().ok())
+            .ok_or_else(|| parse_err("missing signature threshold"))?;
+        let cosigners = parts.map(parse_key).collect::<Result<Vec<_>, _>>()?;
+        if cosigners.is_empty() || cosigners.len() > MAX_COSIGNERS {
+            return Err(parse_err("a multisig needs between 1 and 15 keys"));
+        }
+        if threshold == 0 || threshold > cosigners.len() {
+            return Err(MultisigError::Parse(format!("threshold {} of {} keys", threshold, cosigners.len())));
+        }
+        Ok(Self { threshold, sorted, cosigners })
+    }
+
+    /// The cosigner whose seed has master fingerprint `fingerprint`
+    pub fn cosigner(&self, fingerprint: Fingerprint) -> Result<&Cosigner, MultisigError> {
+        self.cosigners
+            .iter()
+            .find(|cosigner| cosigner.fingerprint == fingerprint)
+            .ok_or(MultisigError::NotACosigner(fingerprint))
+    }
+
+    /// Check that `device_xpub`, read from the device at the cosigner's origin
+    /// path, is the key the descriptor lists for `fingerprint`
+    pub fn verify_cosigner(&self, fingerprint: Fingerprint, device_xpub: &str) -> Result<&Cosigner, MultisigError> {
+        let cosigner = self.cosigner(fingerprint)?;
+        let device_xpub = ExtendedPubKey::from_str(device_xpub).map_err(|e| MultisigError::Parse(e.to_string()))?;
+        if device_xpub.public_key != cosigner.xpub.public_key || device_xpub.chain_code != cosigner.xpub.chain_code {
+            return Err(MultisigError::XpubMismatch { fingerprint, path: cosigner.origin_path.to_string() });
+        }
+        Ok(cosigner)
+    }
+
+    /// Every cosigner's key for address `index` on the receive or change
+    /// branch, in script order
+    fn keys(&self, change: bool, index: u32) -> Result<Vec<(PublicKey, &Cosigner)>, MultisigError> {
+        let secp = Secp256k1::verification_only();
+        let suffix = address_suffix(change, index)?;
+        let mut keys = self
+            .cosigners
+            .iter()
+            .map(|cosigner| {
+                let child = cosigner
+                    .xpub
+                    .derive_pub(&secp, &suffix)
+                    .map_err(|e| MultisigError::Derivation(e.to_string()))?;
+                Ok((PublicKey::new(child.public_key), cosigner))
+            })
+            .collect::<Result<Vec<_>, MultisigError>>()?;
+        if self.sorted {
+            keys.sort_by_key(|(key, _)| key.inner.serialize());
+        }
+        Ok(keys)
+    }
+
+    /// The CHECKMULTISIG script for address `index`
+    pub fn witness_script(&self, change: bool, index: u32) -> Result<ScriptBuf, MultisigError> {
+        let keys = self.keys(change, index)?;
+        let mut builder = Builder::new().push_int(self.threshold as i64);
+        for (key, _) in &keys {
+            builder = builder.push_key(key);
+        }
+        Ok(builder.push_int(keys.len() as i64).push_opcode(OP_CHECKMULTISIG).into_script())
+    }
+
+    pub fn address(&self, change: bool, index: u32) -> Result<Address, MultisigError> {
+        Ok(Address::p2wsh(&self.witness_script(change, index)?, Network::Bitcoin))
+    }
+
+    /// The `multisig` field the device needs to rebuild the script for
+    /// address `index`
+    pub fn redeem_script_type(&self, change: bool, index: u32) -> Result<MultisigRedeemScriptType, MultisigError> {
+        let suffix: Vec<u32> = address_suffix(change, index)?.into_iter().map(|child| u32::from(*child)).collect();
+        Ok(MultisigRedeemScriptType {
+            pubkeys: self
+                .keys(change, index)?
+                .into_iter()
+                .map(|(_, cosigner)| HdNodePathType { node: hd_node(&cosigner.xpub), address_n: suffix.clone() })
+                .collect(),
+            signatures: vec![Vec::new(); self.cosigners.len()],
+            m: Some(self.threshold as u32),
+        })
+    }
+
+    /// `GetAddress` for address `index` as the cosigner with `fingerprint`
+    /// derives it
+    pub fn get_address(
+        &self,
+        fingerprint: Fingerprint,
+        change: bool,
+        index: u32,
+        show_display: bool,
+    ) -> Result<GetAddress, MultisigError> {
+        let cosigner = self.cosigner(fingerprint)?;
+        let path = cosigner.origin_path.extend(address_suffix(change, index)?);
+        Ok(GetAddress {
+            address_n: path.into_iter().map(|child| u32::from(*child)).collect(),
+            coin_name: Some(COIN_NAME.to_string()),
+            show_display: Some(show_display),
+            multisig: Some(self.redeem_script_type(change, index)?),
+            script_type: Some(InputScriptType::Spendwitness as i32),
+        })
+    }
+
+    /// Add every cosigner's account xpub to the PSBT's global xpub map, which
+    /// the signer needs to recognise multisig change
+    pub fn add_global_xpubs(&self, psbt: &mut Psbt) {
+        for cosigner in &self.cosigners {
+            psbt.xpub.insert(cosigner.xpub, (cosigner.fingerprint, cosigner.origin_path.clone()));
+        }
+    }
+}
+
+/// The canonical form: `h` hardened markers, `<0;1>/*` branches and a checksum
+impl fmt::Display for MultisigDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self
+            .cosigners
+            .iter()
+            .map(|c| {
+                let origin = c.origin_path.to_string().trim_start_matches('m').replace('\'', "h");
+                format!("[{}{}]{}/<0;1>/*", c.fingerprint, origin, c.xpub)
+            })
+            .collect();
+        let body = format!(
+            "wsh({}({},{}))",
+            if self.sorted { "sortedmulti" } else { "multi" },
+            self.threshold,
+            keys.join(",")
+        );
+        let checksum = descriptor_checksum(&body).map_err(|_| fmt::Error)?;
+        write!(f, "{}#{}", body, checksum)
+    }
+}
+
+/// `[d34db33f/48h/0h/0h/2h]xpub.../<0;1>/*`; `/0/*` and `/1/*` name the same
+/// account key
+fn parse_key(key: &str) -> Result<Cosigner, MultisigError> {
+    let parse_err = MultisigError::Parse;
+    let (origin, rest) = key
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .ok_or_else(|| parse_err(format!("key {:?} has no [fingerprint/path] origin", key)))?;
+    let (fingerprint, origin_path) = origin.split_once('/').unwrap_or((origin, ""));
+    let fingerprint = Fingerprint::from_str(fingerprint).map_err(|e| parse_err(format!("fingerprint {:?}: {}", fingerprint, e)))?;
+    let origin_path = DerivationPath::from_str(format!("m/{}", origin_path.replace('h', "'")).trim_end_matches('/'))
+        .map_err(|e| parse_err(format!("origin path {:?}: {}", origin_path, e)))?;
+
+    let (xpub, suffix) = rest.split_once('/').unwrap_or((rest, ""));
+    if !matches!(suffix, "<0;1>/*" | "0/*" | "1/*") {
+        return Err(parse_err(format!("key {:?} must end in /<0;1>/*", key)));
+    }
+    let xpub = ExtendedPubKey::from_str(xpub).map_err(|e| parse_err(format!("xpub {:?}: {}", xpub, e)))?;
+    if xpub.network != Network::Bitcoin {
+        return Err(parse_err(format!("{} is not a mainnet xpub", xpub)));
+    }
+    Ok(Cosigner { fingerprint, origin_path, xpub })
+}
+
+fn address_suffix(change: bool, index: u32) -> Result<DerivationPath, MultisigError> {
+    let child = |n| ChildNumber::from_normal_idx(n).map_err(|e| MultisigError::Derivation(e.to_string()));
+    Ok(DerivationPath::from(vec![child(u32::from(change))?, child(index)?]))
+}
+
+/// The device's form of an xpub
+pub fn hd_node(xpub: &ExtendedPubKey) -> HdNodeType {
+    HdNodeType {
+        depth: u32::from(xpub.depth),
+        fingerprint: u32::from_be_bytes(xpub.parent_fingerprint.to_bytes()),
+        child_num: u32::from(xpub.child_number),
+        chain_code: xpub.chain_code.to_bytes().to_vec(),
+        private_key: None,
+        public_key: Some(xpub.public_key.serialize().to_vec()),
+    }
+}
+
+/// `m` and the keys of a bare CHECKMULTISIG script
+pub fn parse_multisig_script(script: &Script) -> Option<(usize, Vec<PublicKey>)> {
+    let instructions: Vec<Instruction> = script.instructions().collect::<Result<_, _>>().ok()?;
+    let (first, rest) = instructions.split_first()?;
+    let (last, rest) = rest.split_last()?;
+    let (count, keys) = rest.split_last()?;
+    if *last != Instruction::Op(OP_CHECKMULTISIG) {
+        return None;
+    }
+    let small_int = |instruction: &Instruction| match instruction {
+        Instruction::Op(op) => match op.classify(ClassifyContext::Legacy) {
+            Class::PushNum(n) if n > 0 => Some(n as usize),
+            _ => None,
+        },
+        _ => None,
+    };
+    let (m, n) = (small_int(first)?, small_int(count)?);
+    let keys = keys
+        .iter()
+        .map(|instruction| match instruction {
+            Instruction::PushBytes(bytes) => PublicKey::from_slice(bytes.as_bytes()).ok(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (m >= 1 && m <= n && keys.len() == n).then_some((m, keys))
+}
+
+/// The `multisig` field for a PSBT input or output spending `witness_script`.
+/// Each key is given as its account xpub from `xpubs` plus the remaining path
+/// when the PSBT has both; otherwise as the bare key, which is enough to sign
+/// but not to recognise change. The flag is true when every key resolved.
+pub(crate) fn psbt_redeem_script_type(
+    witness_script: &Script,
+    derivations: &BTreeMap<secp256k1::PublicKey, KeySource>,
+    xpubs: &BTreeMap<ExtendedPubKey, KeySource>,
+) -> Option<(MultisigRedeemScriptType, bool)> {
+    let (m, keys) = parse_multisig_script(witness_script)?;
+    let mut all_resolved = true;
+    let pubkeys = keys
+        .iter()
+        .map(|key| {
+            let account = derivations.get(&key.inner).and_then(|(fingerprint, path)| {
+                xpubs.iter().find_map(|(xpub, (xpub_fingerprint, origin))| {
+                    let suffix = path.as_ref().strip_prefix(origin.as_ref())?;
+                    (xpub_fingerprint == fingerprint).then(|| HdNodePathType {
+                        node: hd_node(xpub),
+                        address_n: suffix.iter().map(|child| u32::from(*child)).collect(),
+                    })
+                })
+            });
+            account.unwrap_or_else(|| {
+                all_resolved = false;
+                HdNodePathType {
+                    node: HdNodeType {
+                        chain_code: vec![0; 32],
+                        public_key: Some(key.inner.serialize().to_vec()),
+                        ..Default::default()
+                    },
+                    address_n: Vec::new(),
+                }
+            })
+        })
+        .collect();
+    Some((
+        MultisigRedeemScriptType { pubkeys, signatures: vec![Vec::new(); keys.len()], m: Some(m as u32) },
+        all_resolved,
+    ))
+}
+
+fn polymod(c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7_ffff_ffff) << 5) ^ val;
+    for (bit, generator) in [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd].iter().enumerate() {
+        if (c0 >> bit) & 1 == 1 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// BIP-380 checksum of a descriptor without its `#` suffix
+pub fn descriptor_checksum(descriptor: &str) -> Result<String, MultisigError> {
+    let mut c = 1u64;
+    let mut class = 0u64;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let pos = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| MultisigError::Parse(format!("invalid character {:?}", ch)))? as u64;
+        c = polymod(c, pos & 31);
+        class = class * 3 + (pos >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..8).map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP-48 account keys of the "abandon ... about" and "legal winner ... yellow" test seeds
+    const KEY_A: &str = "[73c5da0a/48h/0h/0h/2h]xpub6DkFAXWQ2dHxq2vatrt9qyA3bXYU4ToWQwCHbf5XB2mSTexcHZCeKS1VZYcPoBd5X8yVcbXFHJR9R8UCVpt82VX1VhR28mCyxUFL4r6KFrf/<0;1>/*";
+    const KEY_B: &str = "[2e0ea6f3/48h/0h/0h/2h]xpub6EKmKYGYc1WY6t9d3d9SksR8keSaPZbFa6tqsGiH4xVxx8d2YyxSX7WG6yXEX3CmG54dPCxaapDw1XsjwCmfoqP7tbsAeqMVfKvqSAu4ndy/<0;1>/*";
+
+    fn two_of_two() -> MultisigDescriptor {
+        MultisigDescriptor::parse(&format!("wsh(sortedmulti(2,{},{}))", KEY_A, KEY_B)).unwrap()
+    }
+
+    #[test]
+    fn parses_and_round_trips_a_sortedmulti_descriptor() {
+        let wallet = two_of_two();
+        assert_eq!((wallet.threshold, wallet.sorted, wallet.cosigners.len()), (2, true, 2));
+        assert_eq!(wallet.cosigners[0].origin_path.to_string(), "m/48'/0'/0'/2'");
+
+        let canonical = wallet.to_string();
+        assert_eq!(MultisigDescriptor::parse(&canonical).unwrap(), wallet);
+        let mut tampered = canonical.clone();
+        tampered.pop();
+        tampered.push('x');
+        assert!(matches!(MultisigDescriptor::parse(&tampered), Err(MultisigError::Checksum { .. })));
+        assert!(MultisigDescriptor::parse(&format!("wsh(sortedmulti(3,{},{}))", KEY_A, KEY_B)).is_err());
+        assert!(MultisigDescriptor::parse(&format!("sh(multi(1,{}))", KEY_A)).is_err());
+    }
+
+    #[test]
+    fn addresses_and_device_requests_agree_on_the_script() {
+        let wallet = two_of_two();
+        let script = wallet.witness_script(false, 0).unwrap();
+        let (m, keys) = parse_multisig_script(&script).unwrap();
+        assert_eq!((m, keys.len()), (2, 2));
+        assert!(keys[0].inner.serialize() < keys[1].inner.serialize());
+        assert_eq!(wallet.address(false, 0).unwrap().script_pubkey(), ScriptBuf::new_v0_p2wsh(&script.wscript_hash()));
+        assert_ne!(wallet.address(false, 0).unwrap(), wallet.address(true, 0).unwrap());
+
+        let fingerprint = Fingerprint::from_str("73c5da0a").unwrap();
+        let request = wallet.get_address(fingerprint, true, 5, false).unwrap();
+        assert_eq!(request.address_n, [0x8000_0030, 0x8000_0000, 0x8000_0000, 0x8000_0002, 1, 5]);
+        let multisig = request.multisig.unwrap();
+        assert_eq!((multisig.m, multisig.pubkeys[0].address_n.as_slice()), (Some(2), [1, 5].as_slice()));
+
+        let xpub_a = KEY_A.split(']').nth(1).unwrap().trim_end_matches("/<0;1>/*");
+        assert!(wallet.verify_cosigner(fingerprint, xpub_a).is_ok());
+        let xpub_b = KEY_B.split(']').nth(1).unwrap().trim_end_matches("/<0;1>/*");
+        assert!(matches!(wallet.verify_cosigner(fingerprint, xpub_b), Err(MultisigError::XpubMismatch { .. })));
+        assert!(matches!(wallet.cosigner(Fingerprint::default()), Err(MultisigError::NotACosigner(_))));
+    }
+}
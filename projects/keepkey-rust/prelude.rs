@@ -0,0 +1,23 @@
+//! Everything most applications need: `use keepkey_rust::prelude::*;`
+//!
+//! Names exported here follow semver; see the crate docs for the policy.
+
+pub use crate::derivation_path::{DerivationPath, DerivationPathError};
+pub use crate::failure::{DeviceFailure, DeviceTimeout, FailureCode, TimeoutKind};
+pub use crate::friendly_usb::{FriendlyUsbDevice, KEEPKEY_VID};
+pub use crate::messages::{Message, MessageType};
+pub use crate::progress::{Progress, ProgressOperation, UpdateStage};
+#[cfg(feature = "psbt")]
+pub use crate::psbt::{parse_psbt, Psbt, PsbtError, SignedPsbt};
+pub use crate::protocol::{FirmwareVersion, PassphraseEntry, ProtocolVersion, UnsupportedByFirmware};
+
+#[cfg(feature = "queue")]
+pub use crate::device_queue::{
+    CancellationToken, ClientQueueMetrics, DeviceProgress, DeviceQueueFactory, DeviceQueueHandle, DeviceQueueMetrics,
+    LatencyHistogram, RequestPriority,
+};
+#[cfg(feature = "usb")]
+pub use crate::features::{
+    detect_device_state, get_device_features_by_id, get_device_features_with_fallback, list_connected_devices,
+    DetectedDeviceState, DeviceFeatures,
+};
@@ -0,0 +1,133 @@
+//! Progress of long device operations, in one shape for every consumer.
+//!
+//! The device worker publishes these for firmware and bootloader updates,
+//! transaction signing and seed recovery; applications forward them as UI
+//! events, WebSocket frames or terminal progress bars without reinterpreting
+//! per-operation strings.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressOperation {
+    Frontload,
+    FirmwareUpdate,
+    BootloaderUpdate,
+    Signing,
+    Recovery,
+}
+
+/// Stage of a firmware or bootloader update, in the order they happen.
+/// `downloading` and `uploading` count bytes, so `fraction` is the share done;
+/// the others are single steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStage {
+    /// Fetching the image, by applications that download it
+    Downloading,
+    /// Hashing the image sent along with the upload
+    VerifyingHash,
+    Erasing,
+    Uploading,
+    /// The device accepted the image and is restarting
+    Rebooting,
+    /// Waiting for the device to come back and report the new version
+    Verifying,
+    Finished,
+    /// The update didn't take; `message` says why
+    Failed,
+}
+
+impl UpdateStage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UpdateStage::Downloading => "downloading",
+            UpdateStage::VerifyingHash => "verifying_hash",
+            UpdateStage::Erasing => "erasing",
+            UpdateStage::Uploading => "uploading",
+            UpdateStage::Rebooting => "rebooting",
+            UpdateStage::Verifying => "verifying",
+            UpdateStage::Finished => "finished",
+            UpdateStage::Failed => "failed",
+        }
+    }
+
+    pub fn parse(stage: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(stage.to_string())).ok()
+    }
+}
+
+/// One step of an operation. `current` counts up to `total`; a `total` of 0
+/// means the number of steps isn't known.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    pub operation: ProgressOperation,
+    /// Operation-specific step name, e.g. `erase`, `upload`, `input`, `word`
+    pub stage: String,
+    pub current: u64,
+    pub total: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl Progress {
+    pub fn new(operation: ProgressOperation, stage: impl Into<String>, current: u64, total: u64) -> Self {
+        Self { operation, stage: stage.into(), current, total, message: None }
+    }
+
+    /// A firmware or bootloader update step
+    pub fn update(operation: ProgressOperation, stage: UpdateStage, current: u64, total: u64) -> Self {
+        Self::new(operation, stage.as_str(), current, total)
+    }
+
+    /// The update stage, for firmware and bootloader updates
+    pub fn update_stage(&self) -> Option<UpdateStage> {
+        match self.operation {
+            ProgressOperation::FirmwareUpdate | ProgressOperation::BootloaderUpdate => UpdateStage::parse(&self.stage),
+            _ => None,
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Completed share between 0 and 1, if the total is known
+    pub fn fraction(&self) -> Option<f64> {
+        (self.total > 0).then(|| (self.current.min(self.total)) as f64 / self.total as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_flat_and_reports_fraction() {
+        let progress = Progress::new(ProgressOperation::Signing, "input", 1, 4).with_message("Input 1 of 4");
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(
+            serde_json::to_value(&progress).unwrap(),
+            serde_json::json!({
+                "operation": "signing",
+                "stage": "input",
+                "current": 1,
+                "total": 4,
+                "message": "Input 1 of 4",
+            })
+        );
+        assert_eq!(Progress::new(ProgressOperation::Recovery, "word", 3, 0).fraction(), None);
+    }
+
+    #[test]
+    fn update_stages_round_trip() {
+        let upload = Progress::update(ProgressOperation::FirmwareUpdate, UpdateStage::Uploading, 512, 2048);
+        assert_eq!(upload.stage, "uploading");
+        assert_eq!(upload.update_stage(), Some(UpdateStage::Uploading));
+        assert_eq!(upload.fraction(), Some(0.25));
+        assert_eq!(UpdateStage::parse("verifying_hash"), Some(UpdateStage::VerifyingHash));
+        // Stage names of other operations aren't update stages
+        assert_eq!(Progress::new(ProgressOperation::Signing, "finished", 1, 1).update_stage(), None);
+    }
+}
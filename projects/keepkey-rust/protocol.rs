@@ -0,0 +1,272 @@
+//! Protocol version negotiation.
+//!
+//! Older firmware answers messages it doesn't know with a generic
+//! `Failure_UnexpectedMessage`, and a device sitting in its bootloader rejects
+//! everything except the update flow. After the first `Initialize`/`GetFeatures`
+//! the device queue records the reported version here and refuses to send
+//! messages the device can't handle, returning [`UnsupportedByFirmware`]
+//! instead of a round trip that ends in an opaque Failure.
+//!
+//! Optional features the host has to negotiate, like on-device passphrase
+//! entry, are answered from the same recorded version.
+
+use std::fmt;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::messages::{self, Features, Message, MessageType};
+
+/// A firmware (or bootloader) version as reported in `Features`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct FirmwareVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FirmwareVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Messages the bootloader understands; anything else needs the firmware
+const BOOTLOADER_MESSAGES: &[MessageType] = &[
+    MessageType::Initialize,
+    MessageType::GetFeatures,
+    MessageType::Ping,
+    MessageType::Cancel,
+    MessageType::ButtonAck,
+    MessageType::FirmwareErase,
+    MessageType::FirmwareUpload,
+];
+
+/// Replies inside a flow the device started. These are never gated: the device
+/// asked for them, so it understands them.
+const FLOW_REPLIES: &[MessageType] = &[
+    MessageType::PinMatrixAck,
+    MessageType::PassphraseAck,
+    MessageType::WordAck,
+    MessageType::CharacterAck,
+    MessageType::EntropyAck,
+    MessageType::TxAck,
+    MessageType::RawTxAck,
+];
+
+/// First firmware release that accepts each message. Messages not listed have
+/// been supported by every firmware this library talks to.
+const MIN_FIRMWARE: &[(MessageType, FirmwareVersion)] = &[
+    (MessageType::ApplyPolicies, FirmwareVersion::new(5, 0, 0)),
+    (MessageType::GetCoinTable, FirmwareVersion::new(6, 1, 0)),
+    (MessageType::SoftReset, FirmwareVersion::new(6, 4, 0)),
+    (MessageType::FlashHash, FirmwareVersion::new(6, 4, 0)),
+    (MessageType::FlashWrite, FirmwareVersion::new(6, 4, 0)),
+    (MessageType::ChangeWipeCode, FirmwareVersion::new(7, 1, 0)),
+];
+
+/// Minimum firmware version for a message, if it was added after the first release
+pub fn min_firmware_for(message_type: MessageType) -> Option<FirmwareVersion> {
+    MIN_FIRMWARE
+        .iter()
+        .find(|(t, _)| *t == message_type)
+        .map(|(_, v)| *v)
+}
+
+/// Oldest firmware that accepts every message this library can send; older
+/// firmware works, minus the messages in [`min_firmware_for`]
+pub fn full_support_firmware() -> FirmwareVersion {
+    MIN_FIRMWARE.iter().map(|(_, v)| *v).max().unwrap_or(FirmwareVersion::new(0, 0, 0))
+}
+
+/// Version of this library
+pub const LIBRARY_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Cargo features this build of the library was compiled with
+pub fn enabled_features() -> Vec<&'static str> {
+    [
+        ("usb", cfg!(feature = "usb")),
+        ("hid", cfg!(feature = "hid")),
+        ("queue", cfg!(feature = "queue")),
+        ("psbt", cfg!(feature = "psbt")),
+        ("cli", cfg!(feature = "cli")),
+        ("python", cfg!(feature = "python")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect()
+}
+
+/// Whether the queue should learn the device's version before sending this
+/// message. Bootloader-safe messages and flow replies go through regardless.
+pub fn needs_handshake(message_type: MessageType) -> bool {
+    !BOOTLOADER_MESSAGES.contains(&message_type) && !FLOW_REPLIES.contains(&message_type)
+}
+
+/// Returned (inside `anyhow::Error`) when the connected firmware can't handle a
+/// message. Callers can `downcast_ref::<UnsupportedByFirmware>()` to tell this
+/// apart from a device Failure.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize)]
+pub struct UnsupportedByFirmware {
+    pub message: String,
+    pub version: FirmwareVersion,
+    pub bootloader_mode: bool,
+    /// Firmware needed for this message; `None` when the device is in its
+    /// bootloader and the message needs the firmware itself
+    pub required: Option<FirmwareVersion>,
+}
+
+impl fmt::Display for UnsupportedByFirmware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bootloader_mode {
+            write!(
+                f,
+                "{} unsupported by bootloader v{} (the device must be running firmware)",
+                self.message, self.version
+            )
+        } else {
+            write!(f, "{} unsupported by firmware v{}", self.message, self.version)?;
+            if let Some(required) = self.required {
+                write!(f, " (requires v{} or newer)", required)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// How the host answers a `PassphraseRequest`, chosen per request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassphraseEntry {
+    /// Send the passphrase to the device in `PassphraseAck`
+    Host(String),
+    /// Have the user type it on the device, so it never crosses USB
+    OnDevice,
+}
+
+/// What the connected device reported about itself during the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ProtocolVersion {
+    pub version: FirmwareVersion,
+    pub bootloader_mode: bool,
+}
+
+impl ProtocolVersion {
+    pub fn from_features(features: &Features) -> Self {
+        Self {
+            version: FirmwareVersion::new(
+                features.major_version.unwrap_or(0),
+                features.minor_version.unwrap_or(0),
+                features.patch_version.unwrap_or(0),
+            ),
+            bootloader_mode: features.bootloader_mode.unwrap_or(false),
+        }
+    }
+
+    /// Check that the device can handle `message_type`
+    pub fn check(&self, message_type: MessageType) -> Result<(), UnsupportedByFirmware> {
+        let unsupported = |required| UnsupportedByFirmware {
+            message: format!("{:?}", message_type),
+            version: self.version,
+            bootloader_mode: self.bootloader_mode,
+            required,
+        };
+
+        if FLOW_REPLIES.contains(&message_type) {
+            return Ok(());
+        }
+        if self.bootloader_mode {
+            return if BOOTLOADER_MESSAGES.contains(&message_type) {
+                Ok(())
+            } else {
+                Err(unsupported(None))
+            };
+        }
+        match min_firmware_for(message_type) {
+            Some(required) if self.version < required => Err(unsupported(Some(required))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the device can take the passphrase on its own screen.
+    ///
+    /// No KeepKey firmware can yet: `PassphraseRequest` carries no on-device
+    /// flag and `PassphraseAck` only holds the passphrase. Hosts should offer
+    /// [`PassphraseEntry::OnDevice`] based on this rather than on a version.
+    pub fn supports_on_device_passphrase(&self) -> bool {
+        false
+    }
+
+    /// Reply to a `PassphraseRequest`. `OnDevice` fails instead of silently
+    /// falling back to sending a passphrase over USB.
+    pub fn passphrase_ack(&self, entry: PassphraseEntry) -> Result<Message, UnsupportedByFirmware> {
+        match entry {
+            PassphraseEntry::Host(passphrase) => Ok(messages::PassphraseAck { passphrase }.into()),
+            PassphraseEntry::OnDevice => Err(UnsupportedByFirmware {
+                message: "On-device passphrase entry".to_string(),
+                version: self.version,
+                bootloader_mode: self.bootloader_mode,
+                required: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(major: u32, minor: u32, patch: u32, bootloader_mode: bool) -> Features {
+        Features {
+            major_version: Some(major),
+            minor_version: Some(minor),
+            patch_version: Some(patch),
+            bootloader_mode: Some(bootloader_mode),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gates_messages_by_firmware_version() {
+        let old = ProtocolVersion::from_features(&features(6, 0, 4, false));
+        let err = old.check(MessageType::GetCoinTable).unwrap_err();
+        assert_eq!(err.required, Some(FirmwareVersion::new(6, 1, 0)));
+        assert_eq!(
+            err.to_string(),
+            "GetCoinTable unsupported by firmware v6.0.4 (requires v6.1.0 or newer)"
+        );
+        assert!(old.check(MessageType::GetAddress).is_ok());
+
+        let current = ProtocolVersion::from_features(&features(7, 10, 0, false));
+        assert!(current.check(MessageType::ChangeWipeCode).is_ok());
+    }
+
+    #[test]
+    fn bootloader_only_accepts_update_flow() {
+        let bootloader = ProtocolVersion::from_features(&features(2, 1, 4, true));
+        assert!(bootloader.check(MessageType::FirmwareUpload).is_ok());
+        assert!(bootloader.check(MessageType::ButtonAck).is_ok());
+        let err = bootloader.check(MessageType::SignTx).unwrap_err();
+        assert!(err.bootloader_mode);
+        assert!(err.to_string().starts_with("SignTx unsupported by bootloader v2.1.4"));
+    }
+
+    #[test]
+    fn on_device_passphrase_is_refused_not_downgraded() {
+        let protocol = ProtocolVersion::from_features(&features(7, 10, 0, false));
+        assert!(!protocol.supports_on_device_passphrase());
+
+        let err = protocol.passphrase_ack(PassphraseEntry::OnDevice).unwrap_err();
+        assert_eq!(err.to_string(), "On-device passphrase entry unsupported by firmware v7.10.0");
+
+        match protocol.passphrase_ack(PassphraseEntry::Host("hunter2".into())).unwrap() {
+            Message::PassphraseAck(ack) => assert_eq!(ack.passphrase, "hunter2"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Opt-in record of the protobuf messages exchanged with devices.
+//!
+//! While tracing is on, both protocol adapters note every message they write
+//! and every reply they read: its type, direction, size, the round trip for
+//! replies and the start of the encoded frame. The latest entries are kept in
+//! a ring buffer that applications hand out with [`entries`], so a device bug
+//! report can say exactly what was sent and what came back.
+//!
+//! Frames of messages that carry a PIN, passphrase, seed words or entropy are
+//! never kept. Tracing is off unless set for the run (see [`PROTOCOL_TRACE_ENV`]
+//! and [`set_tracing_enabled`]).
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::messages::{Message, MessageType};
+
+/// Environment variable that turns tracing on for the run (`1` or `true`)
+pub const PROTOCOL_TRACE_ENV: &str = "KEEPKEY_PROTOCOL_TRACE";
+
+/// Entries kept for [`entries`], across all devices
+pub const TRACE_CAPACITY: usize = 1_000;
+
+/// Bytes of each encoded frame kept; enough for everything but firmware
+/// uploads and large transactions
+pub const FRAME_LIMIT: usize = 512;
+
+// Recorded by type and size only
+const SECRET_MESSAGES: [MessageType; 8] = [
+    MessageType::PinMatrixAck,
+    MessageType::PassphraseAck,
+    MessageType::WordAck,
+    MessageType::CharacterAck,
+    MessageType::LoadDevice,
+    MessageType::Entropy,
+    MessageType::CipheredKeyValue,
+    MessageType::DebugLinkState,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceDirection {
+    ToDevice,
+    FromDevice,
+}
+
+/// One message written to or read from a device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    /// Increases by one per entry, so a reader can ask for what came after
+    pub seq: u64,
+    pub direction: TraceDirection,
+    /// e.g. `GetFeatures`
+    pub message_type: String,
+    /// Size of the whole frame in bytes
+    pub len: usize,
+    /// Hex of the first [`FRAME_LIMIT`] bytes of the frame (`##`, type,
+    /// length, protobuf body); empty for messages carrying secrets
+    pub frame: String,
+    /// Whether `frame` stops short of `len`
+    pub truncated: bool,
+    /// For replies, how long after the request went out they arrived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    /// Milliseconds since the Unix epoch
+    pub at: u64,
+}
+
+struct Trace {
+    next_seq: u64,
+    entries: VecDeque<TraceEntry>,
+}
+
+fn enabled() -> &'static AtomicBool {
+    static ENABLED: OnceLock<AtomicBool> = OnceLock::new();
+    ENABLED.get_or_init(|| {
+        let on = std::env::var(PROTOCOL_TRACE_ENV)
+            .is_ok_and(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "on"));
+        AtomicBool::new(on)
+    })
+}
+
+pub fn tracing_enabled() -> bool {
+    enabled().load(Ordering::Relaxed)
+}
+
+/// Turn tracing on or off (initially read from [`PROTOCOL_TRACE_ENV`]).
+/// Entries already recorded are kept until [`clear`].
+pub fn set_tracing_enabled(on: bool) {
+    enabled().store(on, Ordering::Relaxed);
+}
+
+fn trace() -> &'static Mutex<Trace> {
+    static TRACE: OnceLock<Mutex<Trace>> = OnceLock::new();
+    TRACE.get_or_init(|| Mutex::new(Trace { next_seq: 1, entries: VecDeque::with_capacity(TRACE_CAPACITY) }))
+}
+
+/// Note `msg`, encoded as `frame`, if tracing is on. `elapsed` is the round
+/// trip of a reply.
+pub(crate) fn record(direction: TraceDirection, msg: &Message, frame: &[u8], elapsed: Option<Duration>) {
+    if !tracing_enabled() {
+        return;
+    }
+    let message_type = msg.message_type();
+    let kept = if SECRET_MESSAGES.contains(&message_type) { &[][..] } else { &frame[..frame.len().min(FRAME_LIMIT)] };
+    let name = message_type.as_str_name();
+
+    let mut trace = trace().lock().unwrap_or_else(|e| e.into_inner());
+    let entry = TraceEntry {
+        seq: trace.next_seq,
+        direction,
+        message_type: name.strip_prefix("MessageType_").unwrap_or(name).to_string(),
+        len: frame.len(),
+        frame: hex::encode(kept),
+        truncated: kept.len() < frame.len(),
+        elapsed_ms: elapsed.map(|elapsed| elapsed.as_millis() as u64),
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default(),
+    };
+    trace.next_seq += 1;
+    if trace.entries.len() == TRACE_CAPACITY {
+        trace.entries.pop_front();
+    }
+    trace.entries.push_back(entry);
+}
+
+/// Up to `limit` of the latest entries after `since` (a `seq`), oldest first
+pub fn entries(since: Option<u64>, limit: usize) -> Vec<TraceEntry> {
+    let trace = trace().lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries: Vec<TraceEntry> = trace
+        .entries
+        .iter()
+        .rev()
+        .take_while(|entry| since.is_none_or(|since| entry.seq > since))
+        .take(limit)
+        .cloned()
+        .collect();
+    entries.reverse();
+    entries
+}
+
+/// Drop every recorded entry
+pub fn clear() {
+    trace().lock().unwrap_or_else(|e| e.into_inner()).entries.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{GetFeatures, PinMatrixAck};
+
+    fn frame(msg: &Message) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(msg.encoded_len());
+        msg.encode(&mut frame).unwrap();
+        frame
+    }
+
+    #[test]
+    fn secrets_are_traced_by_type_only() {
+        set_tracing_enabled(true);
+        let before = entries(None, 1).last().map(|entry| entry.seq);
+
+        let get_features: Message = GetFeatures::default().into();
+        record(TraceDirection::ToDevice, &get_features, &frame(&get_features), None);
+        let pin: Message = PinMatrixAck { pin: "1234".to_string() }.into();
+        record(TraceDirection::ToDevice, &pin, &frame(&pin), Some(Duration::from_millis(5)));
+
+        let traced: Vec<_> = entries(before, TRACE_CAPACITY)
+            .into_iter()
+            .filter(|entry| entry.message_type == "GetFeatures" || entry.message_type == "PinMatrixAck")
+            .collect();
+        assert_eq!(traced.len(), 2);
+        assert_eq!(traced[0].frame, hex::encode(frame(&get_features)));
+        assert!(!traced[0].truncated);
+        assert_eq!(traced[1].frame, "");
+        assert!(traced[1].truncated);
+        assert_eq!(traced[1].elapsed_ms, Some(5));
+    }
+}
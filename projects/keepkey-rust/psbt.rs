@@ -0,0 +1,439 @@
+//! BIP-174 PSBT signing.
+//!
+//! [`PsbtSigner`] maps a PSBT's inputs and outputs onto the KeepKey
+//! `SignTx` → `TxRequest` / `TxAck` exchange and merges the device's
+//! signatures back into the PSBT as partial signatures, so wallets such as
+//! Sparrow or Specter can finalize and broadcast it. The signer does no I/O;
+//! `DeviceQueueHandle::sign_psbt` drives it against a device.
+//!
+//! Supported inputs are P2PKH, P2SH-P2WPKH, P2WPKH and P2WSH multisig spends
+//! with a single BIP-32 derivation (or one matching the given master
+//! fingerprint). Multisig inputs carry the cosigner keys in the `multisig`
+//! field; a multisig output only counts as change when the PSBT's global xpub
+//! map covers every cosigner, since the device must rebuild its script from
+//! account xpubs to hide it from the confirmation screens.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use bitcoin::bip32::KeySource;
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::consensus::deserialize;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1;
+use bitcoin::{Address, Network, Script, Transaction, TxOut, Txid};
+use thiserror::Error;
+
+use crate::messages::{
+    InputScriptType, Message, OutputAddressType, OutputScriptType, RequestType, SignTx, TransactionType, TxAck,
+    TxInputType, TxOutputBinType, TxOutputType, TxRequest,
+};
+use crate::multisig::psbt_redeem_script_type;
+
+pub use bitcoin::bip32::Fingerprint;
+pub use bitcoin::psbt::Psbt;
+
+const COIN_NAME: &str = "Bitcoin";
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+#[derive(Debug, Error)]
+pub enum PsbtError {
+    #[error("invalid PSBT: {0}")]
+    Parse(String),
+    #[error("input {0} has neither a witness nor a non-witness UTXO")]
+    MissingUtxo(usize),
+    #[error("input {0} has no BIP-32 derivation for this wallet")]
+    MissingDerivation(usize),
+    /// Several keys could sign and no master fingerprint picks one
+    #[error("input {0} has several BIP-32 derivations; pass the wallet's master fingerprint")]
+    AmbiguousDerivation(usize),
+    #[error("input {0} spends an unsupported script type")]
+    UnsupportedInput(usize),
+    #[error("output {0} has a script with no address")]
+    UnsupportedOutput(usize),
+    #[error("device asked for previous transaction {0}, which the PSBT doesn't include")]
+    MissingPrevTx(Txid),
+    #[error("unexpected TxRequest from device: {0}")]
+    UnexpectedRequest(String),
+    #[error("device returned a signed transaction that doesn't match the PSBT: {0}")]
+    BadSignedTx(String),
+}
+
+/// Read a PSBT in binary form or as base64 text
+pub fn parse_psbt(data: &[u8]) -> Result<Psbt, PsbtError> {
+    if data.starts_with(PSBT_MAGIC) {
+        return Psbt::deserialize(data).map_err(|e| PsbtError::Parse(e.to_string()));
+    }
+    let text = std::str::from_utf8(data).map_err(|_| PsbtError::Parse("neither binary nor base64".to_string()))?;
+    Psbt::from_str(text.trim()).map_err(|e| PsbtError::Parse(e.to_string()))
+}
+
+/// The PSBT with the device's partial signatures added, and the fully signed
+/// transaction the device serialized
+#[derive(Debug, Clone)]
+pub struct SignedPsbt {
+    pub psbt: Psbt,
+    pub tx: Transaction,
+}
+
+/// Answers the device's TxRequests for one PSBT
+pub struct PsbtSigner {
+    psbt: Psbt,
+    inputs: Vec<TxInputType>,
+    /// The key the device signs each input with
+    signing_keys: Vec<secp256k1::PublicKey>,
+    outputs: Vec<TxOutputType>,
+    /// Previous transactions by txid, for the device to check input amounts
+    prev_txs: HashMap<Txid, Transaction>,
+    serialized: Vec<u8>,
+}
+
+impl PsbtSigner {
+    /// Map every input and output. `fingerprint` selects this wallet's key
+    /// when an input lists derivations for several keys.
+    pub fn new(psbt: Psbt, fingerprint: Option<Fingerprint>) -> Result<Self, PsbtError> {
+        let mut prev_txs = HashMap::new();
+        let mut inputs = Vec::with_capacity(psbt.inputs.len());
+        let mut signing_keys = Vec::with_capacity(psbt.inputs.len());
+        for (index, (txin, input)) in psbt.unsigned_tx.input.iter().zip(&psbt.inputs).enumerate() {
+            let spent = spent_output(index, txin.previous_output.vout, input)?;
+            let (key, (_, path)) = pick_derivation(index, input.bip32_derivation.iter(), fingerprint)?;
+            let multisig = input
+                .witness_script
+                .as_ref()
+                .filter(|script| spent.script_pubkey == script.to_v0_p2wsh())
+                .and_then(|script| psbt_redeem_script_type(script, &input.bip32_derivation, &psbt.xpub))
+                .map(|(multisig, _)| multisig);
+            let script_type = if multisig.is_some() {
+                InputScriptType::Spendwitness
+            } else if spent.script_pubkey.is_p2pkh() {
+                InputScriptType::Spendaddress
+            } else if spent.script_pubkey.is_v0_p2wpkh() {
+                InputScriptType::Spendwitness
+            } else if spent.script_pubkey.is_p2sh() && input.redeem_script.as_ref().is_some_and(|s| s.is_v0_p2wpkh()) {
+                InputScriptType::Spendp2shwitness
+            } else {
+                return Err(PsbtError::UnsupportedInput(index));
+            };
+            if let Some(prev_tx) = &input.non_witness_utxo {
+                prev_txs.insert(prev_tx.txid(), prev_tx.clone());
+            }
+            inputs.push(TxInputType {
+                address_n: path.into_iter().map(|child| u32::from(*child)).collect(),
+                prev_hash: txid_bytes(&txin.previous_output.txid),
+                prev_index: txin.previous_output.vout,
+                sequence: Some(txin.sequence.0),
+                script_type: Some(script_type as i32),
+                amount: Some(spent.value),
+                multisig,
+                ..Default::default()
+            });
+            signing_keys.push(*key);
+        }
+
+        let mut outputs = Vec::with_capacity(psbt.outputs.len());
+        for (index, (txout, output)) in psbt.unsigned_tx.output.iter().zip(&psbt.outputs).enumerate() {
+            let change = pick_derivation(index, output.bip32_derivation.iter(), fingerprint).ok();
+            let script = &txout.script_pubkey;
+            let multisig = output
+                .witness_script
+                .as_ref()
+                .filter(|witness_script| *script == witness_script.to_v0_p2wsh())
+                .and_then(|witness_script| psbt_redeem_script_type(witness_script, &output.bip32_derivation, &psbt.xpub))
+                .filter(|(_, all_resolved)| *all_resolved)
+                .map(|(multisig, _)| multisig);
+            let change_type = if multisig.is_some() {
+                Some(OutputScriptType::Paytowitness)
+            } else if script.is_p2pkh() {
+                Some(OutputScriptType::Paytoaddress)
+            } else if script.is_v0_p2wpkh() {
+                Some(OutputScriptType::Paytowitness)
+            } else if script.is_p2sh() && output.redeem_script.as_ref().is_some_and(|s| s.is_v0_p2wpkh()) {
+                Some(OutputScriptType::Paytop2shwitness)
+            } else {
+                None
+            };
+            let mapped = match (change, change_type) {
+                (Some((_, (_, path))), Some(script_type)) => TxOutputType {
+                    address_n: path.into_iter().map(|child| u32::from(*child)).collect(),
+                    amount: txout.value,
+                    script_type: script_type as i32,
+                    address_type: Some(OutputAddressType::Change as i32),
+                    multisig,
+                    ..Default::default()
+                },
+                _ if script.is_op_return() => TxOutputType {
+                    amount: txout.value,
+                    script_type: OutputScriptType::Paytoopreturn as i32,
+                    op_return_data: Some(op_return_data(script)),
+                    ..Default::default()
+                },
+                _ => TxOutputType {
+                    address: Some(
+                        Address::from_script(script, Network::Bitcoin)
+                            .map_err(|_| PsbtError::UnsupportedOutput(index))?
+                            .to_string(),
+                    ),
+                    amount: txout.value,
+                    script_type: OutputScriptType::Paytoaddress as i32,
+                    address_type: Some(OutputAddressType::Spend as i32),
+                    ..Default::default()
+                },
+            };
+            outputs.push(mapped);
+        }
+
+        Ok(Self { psbt, inputs, signing_keys, outputs, prev_txs, serialized: Vec::new() })
+    }
+
+    /// The message that starts signing
+    pub fn sign_tx(&self) -> SignTx {
+        let tx = &self.psbt.unsigned_tx;
+        SignTx {
+            inputs_count: self.inputs.len() as u32,
+            outputs_count: self.outputs.len() as u32,
+            coin_name: Some(COIN_NAME.to_string()),
+            version: Some(tx.version as u32),
+            lock_time: Some(tx.lock_time.to_consensus_u32()),
+            ..Default::default()
+        }
+    }
+
+    /// The TxAck answering `request`, or `None` once the device is finished
+    pub fn ack(&mut self, request: &TxRequest) -> Result<Option<Message>, PsbtError> {
+        if let Some(part) = request.serialized.as_ref().and_then(|s| s.serialized_tx.as_ref()) {
+            self.serialized.extend_from_slice(part);
+        }
+
+        let details = request.details.clone().unwrap_or_default();
+        let index = details.request_index.unwrap_or(0) as usize;
+        let request_type = request.request_type.and_then(RequestType::from_i32);
+        if request_type == Some(RequestType::Txfinished) {
+            return Ok(None);
+        }
+        let unexpected = || PsbtError::UnexpectedRequest(format!("{:?} index {}", request_type, index));
+
+        let tx = match &details.tx_hash {
+            None => match request_type {
+                Some(RequestType::Txinput) => TransactionType {
+                    inputs: vec![self.inputs.get(index).cloned().ok_or_else(unexpected)?],
+                    ..Default::default()
+                },
+                Some(RequestType::Txoutput) => TransactionType {
+                    outputs: vec![self.outputs.get(index).cloned().ok_or_else(unexpected)?],
+                    ..Default::default()
+                },
+                _ => return Err(unexpected()),
+            },
+            Some(hash) => {
+                let mut internal = hash.clone();
+                internal.reverse();
+                let txid = Txid::from_slice(&internal).map_err(|_| unexpected())?;
+                let prev = self.prev_txs.get(&txid).ok_or(PsbtError::MissingPrevTx(txid))?;
+                match request_type {
+                    Some(RequestType::Txmeta) => TransactionType {
+                        version: Some(prev.version as u32),
+                        lock_time: Some(prev.lock_time.to_consensus_u32()),
+                        inputs_cnt: Some(prev.input.len() as u32),
+                        outputs_cnt: Some(prev.output.len() as u32),
+                        extra_data_len: Some(0),
+                        ..Default::default()
+                    },
+                    Some(RequestType::Txinput) => {
+                        let txin = prev.input.get(index).ok_or_else(unexpected)?;
+                        TransactionType {
+                            inputs: vec![TxInputType {
+                                prev_hash: txid_bytes(&txin.previous_output.txid),
+                                prev_index: txin.previous_output.vout,
+                                script_sig: Some(txin.script_sig.to_bytes()),
+                                sequence: Some(txin.sequence.0),
+                                ..Default::default()
+                            }],
+                            ..Default::default()
+                        }
+                    }
+                    Some(RequestType::Txoutput) => {
+                        let txout = prev.output.get(index).ok_or_else(unexpected)?;
+                        TransactionType {
+                            bin_outputs: vec![TxOutputBinType {
+                                amount: txout.value,
+                                script_pubkey: txout.script_pubkey.to_bytes(),
+                                ..Default::default()
+                            }],
+                            ..Default::default()
+                        }
+                    }
+                    _ => return Err(unexpected()),
+                }
+            }
+        };
+        Ok(Some(TxAck { tx: Some(tx) }.into()))
+    }
+
+    /// Add the signatures from the device's serialized transaction to the PSBT
+    pub fn finish(mut self) -> Result<SignedPsbt, PsbtError> {
+        let tx: Transaction = deserialize(&self.serialized).map_err(|e| PsbtError::BadSignedTx(e.to_string()))?;
+        if tx.txid() != self.psbt.unsigned_tx.txid() || tx.input.len() != self.psbt.inputs.len() {
+            return Err(PsbtError::BadSignedTx("transaction id differs".to_string()));
+        }
+        for (index, txin) in tx.input.iter().enumerate() {
+            if self.inputs[index].multisig.is_some() {
+                // [empty, signatures..., witness script], with only our signature filled in
+                let witness = txin.witness.to_vec();
+                let signature = match witness.get(1..witness.len().saturating_sub(1)) {
+                    Some(middle) => match middle.iter().filter(|sig| !sig.is_empty()).collect::<Vec<_>>()[..] {
+                        [signature] => signature.clone(),
+                        _ => return Err(PsbtError::BadSignedTx(format!("input {} has no single signature", index))),
+                    },
+                    None => return Err(PsbtError::BadSignedTx(format!("input {} has no signature", index))),
+                };
+                let signature = bitcoin::ecdsa::Signature::from_slice(&signature)
+                    .map_err(|e| PsbtError::BadSignedTx(format!("input {}: {}", index, e)))?;
+                let pubkey = bitcoin::PublicKey::new(self.signing_keys[index]);
+                self.psbt.inputs[index].partial_sigs.insert(pubkey, signature);
+                continue;
+            }
+            // Witness spends carry [signature, pubkey]; P2PKH carries them as script_sig pushes
+            let pushes: Vec<Vec<u8>> = if txin.witness.is_empty() {
+                script_pushes(&txin.script_sig)
+            } else {
+                txin.witness.to_vec()
+            };
+            let [signature, pubkey] = <[Vec<u8>; 2]>::try_from(pushes)
+                .map_err(|_| PsbtError::BadSignedTx(format!("input {} has no signature", index)))?;
+            let signature = bitcoin::ecdsa::Signature::from_slice(&signature)
+                .map_err(|e| PsbtError::BadSignedTx(format!("input {}: {}", index, e)))?;
+            let pubkey = bitcoin::PublicKey::from_slice(&pubkey)
+                .map_err(|e| PsbtError::BadSignedTx(format!("input {}: {}", index, e)))?;
+            self.psbt.inputs[index].partial_sigs.insert(pubkey, signature);
+        }
+        Ok(SignedPsbt { psbt: self.psbt, tx })
+    }
+}
+
+/// KeepKey wants txids in display (big-endian) byte order
+fn txid_bytes(txid: &Txid) -> Vec<u8> {
+    let mut bytes = txid.to_byte_array().to_vec();
+    bytes.reverse();
+    bytes
+}
+
+fn spent_output(index: usize, vout: u32, input: &bitcoin::psbt::Input) -> Result<TxOut, PsbtError> {
+    if let Some(utxo) = &input.witness_utxo {
+        return Ok(utxo.clone());
+    }
+    input
+        .non_witness_utxo
+        .as_ref()
+        .and_then(|tx| tx.output.get(vout as usize).cloned())
+        .ok_or(PsbtError::MissingUtxo(index))
+}
+
+fn pick_derivation<'a, K: 'a>(
+    index: usize,
+    derivations: impl Iterator<Item = (&'a K, &'a KeySource)>,
+    fingerprint: Option<Fingerprint>,
+) -> Result<(&'a K, &'a KeySource), PsbtError> {
+    let mut candidates = derivations.filter(|(_, (fp, _))| fingerprint.is_none_or(|wanted| *fp == wanted));
+    let first = candidates.next().ok_or(PsbtError::MissingDerivation(index))?;
+    if candidates.next().is_some() {
+        return Err(PsbtError::AmbiguousDerivation(index));
+    }
+    Ok(first)
+}
+
+fn script_pushes(script: &Script) -> Vec<Vec<u8>> {
+    script
+        .instructions()
+        .filter_map(|instruction| match instruction {
+            Ok(Instruction::PushBytes(bytes)) => Some(bytes.as_bytes().to_vec()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn op_return_data(script: &Script) -> Vec<u8> {
+    script_pushes(script).concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::TxRequestDetailsType;
+    use bitcoin::bip32::DerivationPath;
+    use bitcoin::blockdata::locktime::absolute::LockTime;
+    use bitcoin::{OutPoint, ScriptBuf, Sequence, TxIn};
+
+    fn request(request_type: RequestType, index: u32, tx_hash: Option<Vec<u8>>) -> TxRequest {
+        TxRequest {
+            request_type: Some(request_type as i32),
+            details: Some(TxRequestDetailsType { request_index: Some(index), tx_hash, ..Default::default() }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn maps_a_p2wpkh_spend_and_answers_the_device() {
+        let key = bitcoin::PublicKey::from_str("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        let script = ScriptBuf::new_v0_p2wpkh(&key.wpubkey_hash().unwrap());
+        let prev_tx = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut { value: 50_000, script_pubkey: script.clone() }],
+        };
+        let unsigned = Transaction {
+            version: 2,
+            lock_time: LockTime::from_consensus(800_000),
+            input: vec![TxIn {
+                previous_output: OutPoint { txid: prev_tx.txid(), vout: 0 },
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                ..Default::default()
+            }],
+            output: vec![TxOut { value: 40_000, script_pubkey: script.clone() }],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(unsigned).unwrap();
+        let path = DerivationPath::from_str("m/84'/0'/0'/0/0").unwrap();
+        psbt.inputs[0].witness_utxo = Some(prev_tx.output[0].clone());
+        psbt.inputs[0].non_witness_utxo = Some(prev_tx.clone());
+        psbt.inputs[0].bip32_derivation.insert(key.inner, (Fingerprint::default(), path.clone()));
+        psbt.outputs[0].bip32_derivation.insert(key.inner, (Fingerprint::default(), path));
+
+        let reparsed = parse_psbt(psbt.to_string().as_bytes()).unwrap();
+        assert_eq!(parse_psbt(&reparsed.serialize()).unwrap(), psbt);
+
+        let mut signer = PsbtSigner::new(reparsed, None).unwrap();
+        let sign_tx = signer.sign_tx();
+        assert_eq!((sign_tx.version, sign_tx.lock_time), (Some(2), Some(800_000)));
+
+        let Some(Message::TxAck(ack)) = signer.ack(&request(RequestType::Txinput, 0, None)).unwrap() else {
+            panic!("expected a TxAck");
+        };
+        let input = &ack.tx.unwrap().inputs[0];
+        assert_eq!(input.address_n, [0x8000_0054, 0x8000_0000, 0x8000_0000, 0, 0]);
+        assert_eq!(input.script_type, Some(InputScriptType::Spendwitness as i32));
+        assert_eq!((input.amount, input.sequence), (Some(50_000), Some(0xffff_fffd)));
+
+        let Some(Message::TxAck(ack)) = signer.ack(&request(RequestType::Txoutput, 0, None)).unwrap() else {
+            panic!("expected a TxAck");
+        };
+        let output = &ack.tx.unwrap().outputs[0];
+        assert_eq!(output.address_type, Some(OutputAddressType::Change as i32));
+        assert_eq!(output.script_type, OutputScriptType::Paytowitness as i32);
+
+        let Some(Message::TxAck(ack)) = signer.ack(&request(RequestType::Txoutput, 0, Some(txid_bytes(&prev_tx.txid())))).unwrap() else {
+            panic!("expected a TxAck");
+        };
+        assert_eq!(ack.tx.unwrap().bin_outputs[0].amount, 50_000);
+        assert!(matches!(
+            signer.ack(&request(RequestType::Txmeta, 0, Some(vec![7; 32]))),
+            Err(PsbtError::MissingPrevTx(_))
+        ));
+        assert!(signer.ack(&request(RequestType::Txfinished, 0, None)).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_text_that_is_not_a_psbt() {
+        assert!(matches!(parse_psbt(b"not a psbt"), Err(PsbtError::Parse(_))));
+    }
+}
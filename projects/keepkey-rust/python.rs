@@ -0,0 +1,156 @@
+//! Python bindings for scripting device farms (`--features python`, built with maturin).
+//!
+//! Every call opens its own transport to the device, runs one request and
+//! closes it, so scripts can drive many devices from worker threads; the GIL
+//! is released while waiting for the device. Button requests are confirmed
+//! automatically and PIN/passphrase prompts fall back to stdin.
+//!
+//! ```python
+//! import keepkey_rust as kk
+//! for dev in kk.enumerate():
+//!     print(dev["uniqueId"], kk.get_features(dev["uniqueId"])["version"])
+//! ```
+
+use anyhow::{anyhow, Result};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::features::{get_device_features_with_fallback, list_connected_devices};
+use crate::friendly_usb::FriendlyUsbDevice;
+use crate::messages::{self, InputScriptType, Message};
+
+create_exception!(keepkey_rust, KeepKeyError, PyException);
+
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    KeepKeyError::new_err(e.to_string())
+}
+
+/// Hand serde data to Python as plain dicts/lists via the `json` module
+fn to_py_object<T: serde::Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| to_py_err(e.into()))?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+fn script_type_from_name(name: &str) -> Result<InputScriptType> {
+    match name {
+        "p2pkh" => Ok(InputScriptType::Spendaddress),
+        "p2sh-p2wpkh" => Ok(InputScriptType::Spendp2shwitness),
+        "p2wpkh" => Ok(InputScriptType::Spendwitness),
+        "p2tr" => Ok(InputScriptType::Spendtaproot),
+        other => Err(anyhow!("unknown script type '{}', expected p2pkh, p2sh-p2wpkh, p2wpkh or p2tr", other)),
+    }
+}
+
+fn find_device(device_id: &str) -> Result<FriendlyUsbDevice> {
+    list_connected_devices()
+        .into_iter()
+        .find(|d| d.unique_id == device_id)
+        .ok_or_else(|| anyhow!("Device {} not found", device_id))
+}
+
+/// Send one request to the device and return its final response
+fn call_device(device_id: &str, request: Message) -> Result<Message> {
+    let device = find_device(device_id)?;
+    let mut transport = crate::transport::create_transport_for_device(&device)?;
+    let response = transport.with_standard_handler().handle(request)?;
+    Ok(response)
+}
+
+/// Connected KeepKeys as a list of dicts (`uniqueId`, `name`, `vid`, `pid`, `serialNumber`, ...)
+#[pyfunction]
+#[pyo3(name = "enumerate")]
+fn enumerate_devices(py: Python<'_>) -> PyResult<PyObject> {
+    let devices: Vec<FriendlyUsbDevice> = py.allow_threads(|| {
+        list_connected_devices().into_iter().filter(|d| d.is_keepkey).collect()
+    });
+    to_py_object(py, &devices)
+}
+
+/// Features of one device as a dict; falls back to HID when USB access fails
+#[pyfunction]
+fn get_features(py: Python<'_>, device_id: &str) -> PyResult<PyObject> {
+    let features = py
+        .allow_threads(|| get_device_features_with_fallback(&find_device(device_id)?))
+        .map_err(to_py_err)?;
+    to_py_object(py, &features)
+}
+
+/// Bitcoin address at `path` (hardened indices include 0x80000000)
+#[pyfunction]
+#[pyo3(signature = (device_id, path, script_type = "p2pkh", coin = "Bitcoin", show_display = false))]
+fn get_address(
+    py: Python<'_>,
+    device_id: &str,
+    path: Vec<u32>,
+    script_type: &str,
+    coin: &str,
+    show_display: bool,
+) -> PyResult<String> {
+    let script_type = script_type_from_name(script_type).map_err(to_py_err)?;
+    let request = messages::GetAddress {
+        address_n: path,
+        coin_name: Some(coin.to_string()),
+        show_display: Some(show_display),
+        script_type: Some(script_type as i32),
+        ..Default::default()
+    };
+    py.allow_threads(|| match call_device(device_id, request.into())? {
+        Message::Address(address) => Ok(address.address),
+        other => Err(anyhow!("unexpected response to GetAddress: {:?}", other.message_type())),
+    })
+    .map_err(to_py_err)
+}
+
+/// Sign `message` with the key at `path`; returns `(address, signature)`
+#[pyfunction]
+#[pyo3(signature = (device_id, path, message, script_type = "p2pkh", coin = "Bitcoin"))]
+fn sign_message<'py>(
+    py: Python<'py>,
+    device_id: &str,
+    path: Vec<u32>,
+    message: &[u8],
+    script_type: &str,
+    coin: &str,
+) -> PyResult<(String, Bound<'py, PyBytes>)> {
+    let script_type = script_type_from_name(script_type).map_err(to_py_err)?;
+    let request = messages::SignMessage {
+        address_n: path,
+        message: message.to_vec(),
+        coin_name: Some(coin.to_string()),
+        script_type: Some(script_type as i32),
+    };
+    let signature = py
+        .allow_threads(|| match call_device(device_id, request.into())? {
+            Message::MessageSignature(signature) => Ok(signature),
+            other => Err(anyhow!("unexpected response to SignMessage: {:?}", other.message_type())),
+        })
+        .map_err(to_py_err)?;
+    Ok((
+        signature.address.unwrap_or_default(),
+        PyBytes::new(py, &signature.signature.unwrap_or_default()),
+    ))
+}
+
+#[pymodule]
+fn keepkey_rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("KeepKeyError", m.py().get_type::<KeepKeyError>())?;
+    m.add_function(wrap_pyfunction!(enumerate_devices, m)?)?;
+    m.add_function(wrap_pyfunction!(get_features, m)?)?;
+    m.add_function(wrap_pyfunction!(get_address, m)?)?;
+    m.add_function(wrap_pyfunction!(sign_message, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_type_names_map_to_input_script_types() {
+        assert_eq!(script_type_from_name("p2wpkh").unwrap(), InputScriptType::Spendwitness);
+        assert_eq!(script_type_from_name("p2sh-p2wpkh").unwrap(), InputScriptType::Spendp2shwitness);
+        assert!(script_type_from_name("bech32").is_err());
+    }
+}
@@ -0,0 +1,311 @@
+//! Crash recovery for queued device requests.
+//!
+//! Once a journal is installed with [`set_request_journal`], every request
+//! submitted in the [`RequestPriority::Background`] lane is written to SQLite
+//! before it is queued and removed again when its caller has the answer (or
+//! gave up on it). Anything still in the journal when it is next opened was
+//! cut short by a crash: [`RequestJournal::pending`] lists it, and each entry
+//! is then either replayed on the device or discarded.
+//!
+//! Interactive requests are never journaled, and neither are firmware
+//! updates or messages that carry a PIN, passphrase or seed words.
+
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::device_queue::{DeviceQueueHandle, RequestPriority};
+use crate::messages::{Features, Message, MessageType};
+use crate::psbt::{parse_psbt, Fingerprint, SignedPsbt};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS queued_requests (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        device_id   TEXT NOT NULL,
+        client      TEXT NOT NULL,
+        request_id  TEXT,
+        request     TEXT NOT NULL,
+        enqueued_at INTEGER NOT NULL,
+        interrupted INTEGER NOT NULL DEFAULT 0
+    );
+";
+
+// Never written to disk, even in the background lane
+const SECRET_MESSAGES: [MessageType; 5] = [
+    MessageType::PinMatrixAck,
+    MessageType::PassphraseAck,
+    MessageType::WordAck,
+    MessageType::CharacterAck,
+    MessageType::LoadDevice,
+];
+
+static JOURNAL: Mutex<Option<Arc<RequestJournal>>> = Mutex::new(None);
+
+/// Journal background requests of every device worker to `journal`; `None`
+/// turns journaling off again
+pub fn set_request_journal(journal: Option<Arc<RequestJournal>>) {
+    *JOURNAL.lock().unwrap_or_else(|e| e.into_inner()) = journal;
+}
+
+pub fn request_journal() -> Option<Arc<RequestJournal>> {
+    JOURNAL.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// A request as stored in the journal, enough to submit it again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum JournaledRequest {
+    GetFeatures,
+    #[serde(rename_all = "camelCase")]
+    GetAddress {
+        path: Vec<u32>,
+        coin_name: String,
+        script_type: Option<i32>,
+        show_display: Option<bool>,
+    },
+    #[serde(rename_all = "camelCase")]
+    SendRaw {
+        message_type: String,
+        /// The framed message, hex encoded
+        message: String,
+        bypass_cache: bool,
+    },
+    SignPsbt {
+        /// Base64
+        psbt: String,
+        fingerprint: Option<String>,
+    },
+}
+
+impl JournaledRequest {
+    /// `None` for messages that must not be written to disk
+    pub(crate) fn send_raw(message: &Message, bypass_cache: bool) -> Option<Self> {
+        let message_type = message.message_type();
+        if SECRET_MESSAGES.contains(&message_type) {
+            return None;
+        }
+        let mut frame = Vec::with_capacity(message.encoded_len());
+        message.encode(&mut frame).ok()?;
+        Some(Self::SendRaw { message_type: format!("{:?}", message_type), message: hex::encode(frame), bypass_cache })
+    }
+
+    pub(crate) fn sign_psbt(psbt: &crate::psbt::Psbt, fingerprint: Option<Fingerprint>) -> Self {
+        Self::SignPsbt { psbt: psbt.to_string(), fingerprint: fingerprint.map(|f| f.to_string()) }
+    }
+}
+
+/// A request left over from a run that ended before it completed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingRequest {
+    pub id: i64,
+    pub device_id: String,
+    pub client: String,
+    pub request_id: Option<String>,
+    /// Unix seconds
+    pub enqueued_at: i64,
+    #[serde(flatten)]
+    pub request: JournaledRequest,
+}
+
+/// The device's answer to a replayed request
+#[derive(Debug)]
+pub enum Replayed {
+    Features(Box<Features>),
+    Address(String),
+    Message(Box<Message>),
+    SignedPsbt(Box<SignedPsbt>),
+}
+
+/// SQLite journal of background requests in flight
+pub struct RequestJournal {
+    conn: Mutex<Connection>,
+}
+
+impl RequestJournal {
+    /// Open (or create) the journal at `path`. Entries already in it belong to
+    /// a previous run and are reported by [`pending`](Self::pending).
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        let interrupted = conn.execute("UPDATE queued_requests SET interrupted = 1", [])?;
+        if interrupted > 0 {
+            log::warn!("{} queued device request(s) were interrupted by the last shutdown", interrupted);
+        }
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn record(&self, device_id: &str, client: &str, request_id: Option<&str>, request: &JournaledRequest) -> Result<i64> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO queued_requests (device_id, client, request_id, request, enqueued_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![device_id, client, request_id, serde_json::to_string(request)?, unix_now()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn remove(&self, id: i64) -> Result<bool> {
+        Ok(self.conn().execute("DELETE FROM queued_requests WHERE id = ?1", [id])? > 0)
+    }
+
+    /// Requests interrupted by the last shutdown, oldest first
+    pub fn pending(&self) -> Result<Vec<PendingRequest>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, device_id, client, request_id, request, enqueued_at
+             FROM queued_requests WHERE interrupted = 1 ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get::<_, String>(4)?, row.get(5)?))
+        })?;
+        let mut pending = Vec::new();
+        for row in rows {
+            let (id, device_id, client, request_id, request, enqueued_at) = row?;
+            match serde_json::from_str(&request) {
+                Ok(request) => pending.push(PendingRequest { id, device_id, client, request_id, enqueued_at, request }),
+                Err(e) => log::warn!("Skipping unreadable journaled request {}: {}", id, e),
+            }
+        }
+        Ok(pending)
+    }
+
+    fn get(&self, id: i64) -> Result<Option<PendingRequest>> {
+        Ok(self.pending()?.into_iter().find(|p| p.id == id))
+    }
+
+    /// Drop an interrupted request without sending it. `false` if there is no such entry.
+    pub fn discard(&self, id: i64) -> Result<bool> {
+        Ok(self.conn().execute("DELETE FROM queued_requests WHERE id = ?1 AND interrupted = 1", [id])? > 0)
+    }
+
+    /// Submit an interrupted request again through `handle`, which must be
+    /// for the device it was queued for. The entry is removed once the device
+    /// answers; after an error it stays for another attempt or a discard.
+    pub async fn replay(&self, id: i64, handle: &DeviceQueueHandle) -> Result<Replayed> {
+        let pending = self
+            .get(id)?
+            .ok_or_else(|| anyhow!("No interrupted request {}", id))?;
+        if pending.device_id != handle.device_id() {
+            return Err(anyhow!(
+                "Request {} was queued for device {}, not {}",
+                id,
+                pending.device_id,
+                handle.device_id()
+            ));
+        }
+
+        let mut handle = handle.for_client(pending.client.clone()).with_priority(RequestPriority::Background);
+        if let Some(request_id) = &pending.request_id {
+            handle = handle.with_request_id(request_id.clone());
+        }
+        let replayed = match pending.request {
+            JournaledRequest::GetFeatures => Replayed::Features(Box::new(handle.get_features().await?)),
+            JournaledRequest::GetAddress { path, coin_name, script_type, show_display } => {
+                Replayed::Address(handle.get_address(path, coin_name, script_type, show_display).await?)
+            }
+            JournaledRequest::SendRaw { message, bypass_cache, .. } => {
+                let frame = hex::decode(message)?;
+                let message = Message::decode(&mut frame.as_slice()).map_err(|e| anyhow!("Corrupt journaled message: {}", e))?;
+                Replayed::Message(Box::new(handle.send_raw(message, bypass_cache).await?))
+            }
+            JournaledRequest::SignPsbt { psbt, fingerprint } => {
+                let psbt = parse_psbt(psbt.as_bytes())?;
+                let fingerprint = fingerprint.map(|f| f.parse::<Fingerprint>()).transpose()?;
+                Replayed::SignedPsbt(Box::new(handle.sign_psbt_as(psbt, fingerprint).await?))
+            }
+        };
+        self.remove(id)?;
+        Ok(replayed)
+    }
+}
+
+/// Keeps a request in the journal until dropped
+pub(crate) struct JournalEntry {
+    journal: Arc<RequestJournal>,
+    id: i64,
+}
+
+impl JournalEntry {
+    pub(crate) fn record(device_id: &str, client: &str, request_id: Option<&str>, request: &JournaledRequest) -> Option<Self> {
+        let journal = request_journal()?;
+        match journal.record(device_id, client, request_id, request) {
+            Ok(id) => Some(Self { journal, id }),
+            Err(e) => {
+                log::warn!("Failed to journal queued request for {}: {}", device_id, e);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for JournalEntry {
+    fn drop(&mut self) {
+        if let Err(e) = self.journal.remove(self.id) {
+            log::warn!("Failed to clear journaled request {}: {}", self.id, e);
+        }
+    }
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{self, Initialize};
+
+    #[test]
+    fn only_requests_from_an_earlier_run_are_pending() {
+        let path = std::env::temp_dir().join(format!("keepkey-request-journal-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let address = JournaledRequest::GetAddress {
+            path: vec![0x8000_0054, 0x8000_0000, 0x8000_0000, 0, 0],
+            coin_name: "Bitcoin".to_string(),
+            script_type: Some(3),
+            show_display: None,
+        };
+
+        let journal = RequestJournal::open(&path).unwrap();
+        let interrupted = journal.record("kk-1", "frontload", Some("req-7"), &address).unwrap();
+        let completed = journal.record("kk-1", "frontload", None, &JournaledRequest::GetFeatures).unwrap();
+        assert!(journal.remove(completed).unwrap());
+        // Still in flight in this run
+        assert!(journal.pending().unwrap().is_empty());
+        drop(journal);
+
+        let journal = RequestJournal::open(&path).unwrap();
+        journal.record("kk-1", "sync", None, &JournaledRequest::GetFeatures).unwrap();
+        let pending = journal.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].id, pending[0].request_id.as_deref()), (interrupted, Some("req-7")));
+        assert_eq!(pending[0].request, address);
+        assert!(journal.discard(interrupted).unwrap());
+        assert!(!journal.discard(interrupted).unwrap());
+        drop(journal);
+        let _ = std::fs::remove_file(&path);
+
+        // Secrets stay off disk
+        let ack: Message = messages::PassphraseAck { passphrase: "hunter2".to_string() }.into();
+        assert_eq!(JournaledRequest::send_raw(&ack, true), None);
+        assert!(matches!(
+            JournaledRequest::send_raw(&Initialize::default().into(), false),
+            Some(JournaledRequest::SendRaw { ref message_type, .. }) if message_type == "Initialize"
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+//! Public API snapshot for the semver-covered modules.
+//!
+//! Lists every `pub` item (with its signature) of the files below and compares
+//! the list with `tests/public_api.txt`. An intentional change is recorded with
+//!
+//! ```text
+//! UPDATE_PUBLIC_API=1 cargo test --test public_api
+//! ```
+//!
+//! and then needs a version bump that matches: removed or changed lines are
+//! breaking, added lines are a minor release. Generated protobuf types are left
+//! out; the `.proto` files are their contract.
+
+use std::path::Path;
+
+const STABLE_SOURCES: &[(&str, &str)] = &[
+    ("prelude", "prelude.rs"),
+    ("friendly_usb", "friendly_usb.rs"),
+    ("protocol", "protocol.rs"),
+    ("derivation_path", "derivation_path.rs"),
+    ("progress", "progress.rs"),
+    ("failure", "failure.rs"),
+    ("psbt", "psbt.rs"),
+    ("multisig", "multisig.rs"),
+    ("features", "features/mod.rs"),
+    ("hotplug", "hotplug.rs"),
+    ("device_identity", "device_identity.rs"),
+    ("device_queue", "device_queue.rs"),
+    ("diagnostics", "diagnostics.rs"),
+    ("protocol_trace", "protocol_trace.rs"),
+    ("request_journal", "request_journal.rs"),
+    ("messages", "messages/mod.rs"),
+    ("messages", "messages/encoding.rs"),
+    ("messages", "messages/timeouts.rs"),
+];
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_public(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("pub ") && !line.starts_with("pub(")
+}
+
+/// Signature of the item starting at `lines[start]`, joined onto one line
+fn signature(lines: &[&str], start: usize) -> String {
+    let first = lines[start].trim();
+    // Fields and variants end with `,`, imports with `;`, other items at their body
+    let terminators: &[char] = if first.starts_with("pub use") {
+        &[';']
+    } else if first.contains("fn ") || first.starts_with("pub struct") || first.starts_with("pub enum") {
+        &['{', ';']
+    } else {
+        &['{', ';', ',']
+    };
+    let mut parts = Vec::new();
+    for line in &lines[start..] {
+        let line = line.trim();
+        parts.push(line);
+        if line.ends_with(terminators) {
+            break;
+        }
+    }
+    parts
+        .join(" ")
+        .replace("( ", "(")
+        .replace(", )", ")")
+        .replace("{ ", "{")
+        .replace(", }", "}")
+        .trim_end_matches(['{', ';', ','])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `pub` items at the top level and directly inside top-level blocks
+/// (methods of `impl` blocks, fields and variants of public types). Test modules and
+/// `#[doc(hidden)]` items are skipped.
+fn public_items(module: &str, source: &str) -> Vec<String> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut items = Vec::new();
+    let mut parent: Option<String> = None;
+    let mut hidden = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            continue;
+        }
+        if trimmed.starts_with("#[") {
+            if trimmed == "#[cfg(test)]" && indent(line) == 0 {
+                break;
+            }
+            hidden |= trimmed == "#[doc(hidden)]";
+            continue;
+        }
+
+        match indent(line) {
+            0 => {
+                parent = None;
+                if hidden {
+                    // skip the item and everything inside it
+                } else if trimmed.starts_with("impl") {
+                    parent = Some(signature(&lines, i));
+                } else if is_public(line) {
+                    let item = signature(&lines, i);
+                    if trimmed.starts_with("pub struct") || trimmed.starts_with("pub enum") {
+                        parent = Some(item.clone());
+                    }
+                    items.push(format!("{}: {}", module, item));
+                }
+            }
+            4 if !hidden => {
+                let variant = trimmed.starts_with(|c: char| c.is_ascii_uppercase());
+                if let Some(parent) = &parent {
+                    if is_public(line) || (variant && parent.starts_with("pub enum")) {
+                        items.push(format!("{}: {} :: {}", module, parent, signature(&lines, i)));
+                    }
+                }
+            }
+            _ => {}
+        }
+        hidden = false;
+    }
+    items
+}
+
+#[test]
+fn public_api_matches_snapshot() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut items = Vec::new();
+    for (module, file) in STABLE_SOURCES {
+        let source = std::fs::read_to_string(root.join(file)).unwrap();
+        for item in public_items(module, &source) {
+            // `#[cfg]` alternatives of one item (e.g. feature stubs) are the same API
+            if !items.contains(&item) {
+                items.push(item);
+            }
+        }
+    }
+    let current = items.join("\n") + "\n";
+
+    let snapshot_path = root.join("tests/public_api.txt");
+    if std::env::var_os("UPDATE_PUBLIC_API").is_some() {
+        std::fs::write(&snapshot_path, &current).unwrap();
+        return;
+    }
+    let snapshot = std::fs::read_to_string(&snapshot_path).unwrap_or_default();
+    if snapshot != current {
+        let added: Vec<&str> = current.lines().filter(|l| !snapshot.lines().any(|s| s == *l)).collect();
+        let removed: Vec<&str> = snapshot.lines().filter(|l| !current.lines().any(|c| c == *l)).collect();
+        panic!(
+            "public API changed; review, then run UPDATE_PUBLIC_API=1 cargo test --test public_api\n\nadded:\n  {}\n\nremoved:\n  {}\n",
+            added.join("\n  "),
+            removed.join("\n  ")
+        );
+    }
+}
+
+#[cfg(feature = "queue")]
+#[test]
+fn prelude_covers_device_workflow() {
+    use keepkey_rust::prelude::*;
+
+    // Compile-time check that the usual discovery -> queue -> features path
+    // needs nothing outside the prelude.
+    fn _workflow() -> Option<DeviceQueueHandle> {
+        let device: FriendlyUsbDevice = list_connected_devices().into_iter().next()?;
+        let _features: Option<DeviceFeatures> = get_device_features_with_fallback(&device).ok();
+        Some(DeviceQueueFactory::spawn_worker(device.unique_id.clone(), device))
+    }
+    assert_eq!(KEEPKEY_VID, 0x2b24);
+}
@@ -0,0 +1,289 @@
+//! Async counterparts of [`Transport`] and [`ProtocolAdapter`](super::ProtocolAdapter).
+//!
+//! rusb and hidapi only have blocking calls, so [`BlockingTransport`] adapts
+//! the existing USB, WebUSB and HID transports by running each read and write
+//! on the blocking I/O pool. Async callers `.await` a device round trip instead
+//! of parking a tokio worker thread (or a `block_in_place`) on it.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use core::time::Duration;
+use log::{debug, info};
+use std::time::Instant;
+
+use super::Transport;
+use crate::blocking_io;
+use crate::failure::DeviceTimeout;
+use crate::messages::Message;
+use crate::protocol_trace::{self, TraceDirection};
+
+/// Answers device requests that arrive mid-exchange (ButtonRequest,
+/// PinMatrixRequest, ...): `Some(reply)` is sent back, `None` ends the
+/// exchange with that message. It runs on the awaiting task, so it must not
+/// block.
+pub type AsyncMessageHandler<'a> = dyn Fn(&Message) -> Result<Option<Message>> + Send + Sync + 'a;
+
+/// Told `(sent, total)` bytes as a message goes out; runs on the blocking I/O
+/// pool, so it must be cheap
+pub type WriteProgress = std::sync::Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// A read that got nothing before its deadline. [`AsyncProtocolAdapter::handle`]
+/// turns it into a [`DeviceTimeout`] naming the message left unanswered.
+#[derive(Debug, thiserror::Error)]
+#[error("No reply from the device within {0:?}")]
+pub struct ReadTimeout(pub Duration);
+
+#[async_trait]
+pub trait AsyncTransport: Send {
+    async fn write(&mut self, msg: &[u8], timeout: Duration) -> Result<usize>;
+    /// Append one framed message to `buf`
+    async fn read(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<()>;
+    async fn reset(&mut self) -> Result<()>;
+    /// Report the bytes of every following write to `progress`, until cleared with `None`
+    fn set_write_progress(&mut self, _progress: Option<WriteProgress>) {}
+}
+
+#[async_trait]
+pub trait AsyncProtocolAdapter: Send {
+    async fn reset(&mut self) -> Result<()>;
+    /// See [`AsyncTransport::set_write_progress`]
+    fn set_write_progress(&mut self, _progress: Option<WriteProgress>) {}
+    async fn send(&mut self, msg: Message) -> Result<()>;
+    async fn handle(&mut self, msg: Message) -> Result<Message>;
+    /// Read one message the device sent unprompted, like a DEBUG_LINK log line
+    async fn recv(&mut self, timeout: Duration) -> Result<Message>;
+
+    /// [`handle`](Self::handle), replying through `handler` until it returns
+    /// `None`; the async form of `ProtocolAdapter::with_handler`
+    async fn handle_with(&mut self, msg: Message, handler: &AsyncMessageHandler<'_>) -> Result<Message> {
+        let mut msg = msg;
+        loop {
+            let response = self.handle(msg).await?;
+            match handler(&response)? {
+                Some(reply) => msg = reply,
+                None => return Ok(response),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T: AsyncTransport> AsyncProtocolAdapter for T {
+    async fn reset(&mut self) -> Result<()> {
+        AsyncTransport::reset(self).await
+    }
+
+    fn set_write_progress(&mut self, progress: Option<WriteProgress>) {
+        AsyncTransport::set_write_progress(self, progress)
+    }
+
+    async fn send(&mut self, msg: Message) -> Result<()> {
+        info!("AsyncProtocolAdapter::send: Sending message type: {:?}", msg.message_type());
+        let mut out_buf = Vec::<u8>::with_capacity(msg.encoded_len());
+        msg.encode(&mut out_buf)?;
+        debug!("AsyncProtocolAdapter::send: Encoded message size: {} bytes", out_buf.len());
+        self.write(&out_buf, msg.write_timeout()).await?;
+        protocol_trace::record(TraceDirection::ToDevice, &msg, &out_buf, None);
+        Ok(())
+    }
+
+    async fn handle(&mut self, msg: Message) -> Result<Message> {
+        let read_timeout = msg.read_timeout();
+        let timed_out = DeviceTimeout::waiting_for(&msg, read_timeout);
+        let sent_at = Instant::now();
+        self.send(msg).await?;
+
+        let mut in_buf = Vec::<u8>::new();
+        match self.read(&mut in_buf, read_timeout).await {
+            Err(e) if e.downcast_ref::<ReadTimeout>().is_some() => return Err(timed_out.into()),
+            result => result?,
+        }
+        let out = Message::decode(&mut in_buf.as_slice()).map_err(|x| anyhow!(x))?;
+        protocol_trace::record(TraceDirection::FromDevice, &out, &in_buf, Some(sent_at.elapsed()));
+        info!("AsyncProtocolAdapter::handle: Received {:?} ({} bytes)", out.message_type(), in_buf.len());
+        Ok(out)
+    }
+
+    async fn recv(&mut self, timeout: Duration) -> Result<Message> {
+        let mut in_buf = Vec::<u8>::new();
+        self.read(&mut in_buf, timeout).await?;
+        let out = Message::decode(&mut in_buf.as_slice()).map_err(|x| anyhow!(x))?;
+        protocol_trace::record(TraceDirection::FromDevice, &out, &in_buf, None);
+        Ok(out)
+    }
+}
+
+/// A blocking [`Transport`] driven from the blocking I/O pool. Each call moves
+/// the transport to a pool thread and back; if a call is cancelled or the pool
+/// fails, the transport is gone and later calls return an error, so the owner
+/// should open a new one.
+pub struct BlockingTransport<T> {
+    inner: Option<T>,
+    write_progress: Option<WriteProgress>,
+}
+
+impl<T> BlockingTransport<T>
+where
+    T: Transport + Send + 'static,
+    T::Error: Send + Sync + 'static,
+{
+    pub fn new(inner: T) -> Self {
+        Self { inner: Some(inner), write_progress: None }
+    }
+
+    async fn run<R, F>(&mut self, op: F) -> Result<R>
+    where
+        F: FnOnce(&mut T) -> Result<R, T::Error> + Send + 'static,
+        R: Send + 'static,
+    {
+        let mut inner = self
+            .inner
+            .take()
+            .ok_or_else(|| anyhow!("Transport was lost by an interrupted call; reopen the device"))?;
+        let (inner, result) = blocking_io::run(move || {
+            let result = op(&mut inner);
+            (inner, result)
+        })
+        .await?;
+        self.inner = Some(inner);
+        Ok(result?)
+    }
+}
+
+#[async_trait]
+impl<T> AsyncTransport for BlockingTransport<T>
+where
+    T: Transport + Send + 'static,
+    T::Error: Send + Sync + 'static,
+{
+    async fn write(&mut self, msg: &[u8], timeout: Duration) -> Result<usize> {
+        let msg = msg.to_vec();
+        match self.write_progress.clone() {
+            Some(progress) => {
+                self.run(move |transport| {
+                    transport.write_with_progress(&msg, timeout, &mut |sent| progress(sent, msg.len()))
+                })
+                .await
+            }
+            None => self.run(move |transport| transport.write(&msg, timeout)).await,
+        }
+    }
+
+    async fn read(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<()> {
+        let read = self
+            .run(move |transport| {
+                let mut read = Vec::new();
+                match transport.read(&mut read, timeout) {
+                    Ok(()) => Ok(Some(read)),
+                    Err(e) if T::is_timeout(&e) => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .await?
+            .ok_or(ReadTimeout(timeout))?;
+        buf.extend_from_slice(&read);
+        Ok(())
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.run(|transport| transport.reset()).await
+    }
+
+    fn set_write_progress(&mut self, progress: Option<WriteProgress>) {
+        self.write_progress = progress;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{Features, GetFeatures, Initialize};
+    use std::collections::VecDeque;
+
+    /// Answers every write with the next canned message
+    struct Scripted {
+        replies: VecDeque<Message>,
+        pending: Option<Vec<u8>>,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("no reply scripted")]
+    struct NoReply;
+
+    impl Transport for Scripted {
+        type Error = NoReply;
+        fn write(&mut self, msg: &[u8], _timeout: Duration) -> Result<usize, NoReply> {
+            let reply = self.replies.pop_front().ok_or(NoReply)?;
+            let mut encoded = Vec::new();
+            reply.encode(&mut encoded).map_err(|_| NoReply)?;
+            self.pending = Some(encoded);
+            Ok(msg.len())
+        }
+        fn read(&mut self, buf: &mut Vec<u8>, _timeout: Duration) -> Result<(), NoReply> {
+            buf.extend(self.pending.take().ok_or(NoReply)?);
+            Ok(())
+        }
+        fn reset(&mut self) -> Result<(), NoReply> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn blocking_transports_answer_through_the_pool() {
+        let replies = VecDeque::from([
+            crate::messages::ButtonRequest::default().into(),
+            Message::Features(Features { label: Some("kk".to_string()), ..Default::default() }),
+        ]);
+        let mut adapter: Box<dyn AsyncProtocolAdapter> =
+            Box::new(BlockingTransport::new(Scripted { replies, pending: None }));
+
+        let ack_buttons = |msg: &Message| -> Result<Option<Message>> {
+            Ok(match msg {
+                Message::ButtonRequest(_) => Some(crate::messages::ButtonAck::default().into()),
+                _ => None,
+            })
+        };
+        match adapter.handle_with(GetFeatures {}.into(), &ack_buttons).await.unwrap() {
+            Message::Features(features) => assert_eq!(features.label.as_deref(), Some("kk")),
+            other => panic!("unexpected {:?}", other.message_type()),
+        }
+        // Out of replies: the transport's own error comes back, and the transport survives it
+        assert!(adapter.handle(Initialize {}.into()).await.is_err());
+        assert!(adapter.reset().await.is_ok());
+    }
+
+    /// Takes every write and never answers
+    struct Silent;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("read timed out")]
+    struct TimedOut;
+
+    impl Transport for Silent {
+        type Error = TimedOut;
+        fn write(&mut self, msg: &[u8], _timeout: Duration) -> Result<usize, TimedOut> {
+            Ok(msg.len())
+        }
+        fn read(&mut self, _buf: &mut Vec<u8>, _timeout: Duration) -> Result<(), TimedOut> {
+            Err(TimedOut)
+        }
+        fn reset(&mut self) -> Result<(), TimedOut> {
+            Ok(())
+        }
+        fn is_timeout(_error: &TimedOut) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn unanswered_reads_say_who_was_waiting() {
+        use crate::failure::TimeoutKind;
+
+        let mut adapter = BlockingTransport::new(Silent);
+        let error = adapter.handle(GetFeatures {}.into()).await.unwrap_err();
+        assert_eq!(DeviceTimeout::find(&error).unwrap().kind, TimeoutKind::DeviceUnresponsive);
+
+        let error = adapter.handle(crate::messages::ButtonAck::default().into()).await.unwrap_err();
+        assert_eq!(DeviceTimeout::find(&error).unwrap().kind, TimeoutKind::UserTookTooLong);
+    }
+}
@@ -0,0 +1,274 @@
+//! Picks and opens the transport (WebUSB, USB or HID) for a discovered device.
+
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
+
+use super::ProtocolAdapter;
+#[cfg(feature = "queue")]
+use super::{AsyncProtocolAdapter, BlockingTransport};
+use crate::friendly_usb::FriendlyUsbDevice;
+
+/// Transport type detection for different KeepKey device modes
+#[derive(Debug, Clone, Copy)]
+enum TransportType {
+    /// Modern WebUSB devices (firmware 7.10.0+) with bulk endpoints
+    WebUsb,
+    /// Traditional USB devices with interrupt endpoints and HID-style protocol
+    TraditionalUsb,
+    /// Legacy devices or fallback mode that only work with HID API
+    HidOnly,
+}
+
+/// A transport opened for a device, before it is boxed for sync or async use
+enum OpenedTransport {
+    WebUsb(crate::transport::WebUsbTransport<rusb::GlobalContext>),
+    Usb(crate::transport::UsbTransport<rusb::GlobalContext>),
+    #[cfg(feature = "hid")]
+    Hid(crate::transport::HidTransport),
+}
+
+/// Create transport with WebUSB/USB/HID auto-detection
+pub(crate) fn create_transport_for_device(device_info: &FriendlyUsbDevice) -> Result<Box<dyn ProtocolAdapter + Send>> {
+    #[cfg(feature = "mock-device")]
+    if super::is_mock_device(device_info) {
+        return Ok(Box::new(super::MockDevice::new()));
+    }
+    Ok(match open_transport_for_device(device_info)? {
+        OpenedTransport::WebUsb(transport) => Box::new(transport),
+        OpenedTransport::Usb(transport) => Box::new(transport),
+        #[cfg(feature = "hid")]
+        OpenedTransport::Hid(transport) => Box::new(transport),
+    })
+}
+
+/// Same detection as [`create_transport_for_device`], with the transport
+/// driven from the blocking I/O pool
+#[cfg(feature = "queue")]
+pub(crate) fn create_async_transport_for_device(device_info: &FriendlyUsbDevice) -> Result<Box<dyn AsyncProtocolAdapter>> {
+    #[cfg(feature = "mock-device")]
+    if super::is_mock_device(device_info) {
+        return Ok(Box::new(BlockingTransport::new(super::MockDevice::new())));
+    }
+    Ok(match open_transport_for_device(device_info)? {
+        OpenedTransport::WebUsb(transport) => Box::new(BlockingTransport::new(transport)),
+        OpenedTransport::Usb(transport) => Box::new(BlockingTransport::new(transport)),
+        #[cfg(feature = "hid")]
+        OpenedTransport::Hid(transport) => Box::new(BlockingTransport::new(transport)),
+    })
+}
+
+/// The normal and DEBUG_LINK interfaces of a device, both driven from the
+/// blocking I/O pool. Fails for release firmware, which has no DEBUG_LINK
+/// interface, and for devices that don't use the USB interrupt transport.
+#[cfg(feature = "queue")]
+pub(crate) fn create_async_debug_link_for_device(
+    device_info: &FriendlyUsbDevice,
+) -> Result<(Box<dyn AsyncProtocolAdapter>, Box<dyn AsyncProtocolAdapter>)> {
+    let devices = crate::features::list_devices();
+    let physical_device = find_physical_device_by_info(device_info, &devices)?;
+    if !matches!(detect_transport_type(&physical_device, device_info)?, TransportType::TraditionalUsb) {
+        return Err(anyhow!("DEBUG_LINK needs the USB interrupt transport"));
+    }
+    let (main, debug) = crate::debug_link::open_usb_debug_link(&physical_device)?;
+    Ok((Box::new(BlockingTransport::new(main)), Box::new(BlockingTransport::new(debug))))
+}
+
+fn open_transport_for_device(device_info: &FriendlyUsbDevice) -> Result<OpenedTransport> {
+    // Find physical device for transport
+    let devices = crate::features::list_devices();
+    let physical_device = find_physical_device_by_info(device_info, &devices)?;
+    
+    // Detect transport type based on device endpoints
+    let transport_type = detect_transport_type(&physical_device, device_info)?;
+    
+    match transport_type {
+        TransportType::WebUsb => {
+            info!("🌐 Detected WebUSB device, using WebUSB transport for {}", device_info.unique_id);
+            info!("🔧 Attempting to create WebUSB transport...");
+            match crate::transport::WebUsbTransport::new(&physical_device, 0) {
+                Ok((transport, _, _)) => {
+                    info!("✅ Successfully created WebUSB transport for device {}", device_info.unique_id);
+                    Ok(OpenedTransport::WebUsb(transport))
+                }
+                Err(webusb_err) => {
+                    error!("❌ WebUSB transport creation failed for device {}: {}", device_info.unique_id, webusb_err);
+                    warn!("⚠️ WebUSB transport failed for device {}: {}, trying HID fallback", device_info.unique_id, webusb_err);
+                    try_hid_fallback(device_info, webusb_err.to_string())
+                }
+            }
+        }
+        TransportType::TraditionalUsb => {
+            info!("🔌 Detected traditional USB device, using interrupt transport for {}", device_info.unique_id);
+            match crate::transport::UsbTransport::new(&physical_device, 0) {
+                Ok((transport, _, _)) => {
+                    info!("✅ Created USB transport for device {}", device_info.unique_id);
+                    Ok(OpenedTransport::Usb(transport))
+                }
+                Err(usb_err) => {
+                    warn!("⚠️ USB transport failed for device {}: {}, trying HID fallback", device_info.unique_id, usb_err);
+                    try_hid_fallback(device_info, usb_err.to_string())
+                }
+            }
+        }
+        TransportType::HidOnly => {
+            info!("🎛️ Device requires HID transport, using HID for {}", device_info.unique_id);
+            try_hid_fallback(device_info, "Device requires HID transport".to_string())
+        }
+    }
+}
+
+/// Detect the appropriate transport type for a device
+fn detect_transport_type(device: &rusb::Device<rusb::GlobalContext>, device_info: &FriendlyUsbDevice) -> Result<TransportType> {
+    info!("🔍 Detecting transport type for device {} (VID: {:04x}, PID: {:04x})", 
+          device_info.unique_id, device_info.vid, device_info.pid);
+    
+    // Legacy devices (PID 0x0001) must use HID on all platforms
+    if device_info.pid == 0x0001 {
+        info!("🎛️ Legacy device (PID 0x0001) detected - using HID transport");
+        return Ok(TransportType::HidOnly);
+    }
+    
+    // Modern devices (PID 0x0002 and newer) should prefer USB transport
+    // PID 0x0002 devices have interrupt endpoints and use USB transport (not WebUSB with bulk endpoints)
+    if device_info.pid == 0x0002 {
+        info!("🔌 Modern KeepKey device (PID 0x0002) detected - preferring USB transport");
+        info!("   📡 Firmware 7.10.0+ devices use USB transport with interrupt endpoints");
+        
+        // On Windows, we'll try USB first and let the fallback mechanism handle FIDO blocklist issues
+        #[cfg(target_os = "windows")]
+        {
+            info!("🪟 Windows detected - will try USB first, HID fallback available if FIDO blocklist blocks access");
+        }
+        
+        return Ok(TransportType::TraditionalUsb);
+    }
+    
+    // For other newer device PIDs, inspect the endpoints
+    match device.active_config_descriptor() {
+        Ok(config_desc) => {
+            info!("📋 Successfully read device config descriptor");
+            
+            // Look at the first interface (index 0) which is what we use
+            if let Some(interface) = config_desc.interfaces().next() {
+                info!("📋 Found interface 0");
+                if let Some(interface_desc) = interface.descriptors().next() {
+                    let endpoints: Vec<_> = interface_desc.endpoint_descriptors().collect();
+                    info!("📋 Found {} endpoints", endpoints.len());
+                    
+                    // Log all endpoints for debugging
+                    for (i, ep) in endpoints.iter().enumerate() {
+                        info!("   Endpoint {}: addr=0x{:02x}, type={:?}, dir={:?}, max_packet={}",
+                              i, ep.address(), ep.transfer_type(), ep.direction(), ep.max_packet_size());
+                    }
+                    
+                    // Check if we have bulk endpoints (WebUSB) or interrupt endpoints (USB)
+                    let has_bulk = endpoints.iter().any(|ep| ep.transfer_type() == rusb::TransferType::Bulk);
+                    let has_interrupt = endpoints.iter().any(|ep| ep.transfer_type() == rusb::TransferType::Interrupt);
+                    
+                    info!("📋 Endpoint analysis: has_bulk={}, has_interrupt={}", has_bulk, has_interrupt);
+                    
+                    if has_bulk {
+                        info!("🌐 Device {} has bulk endpoints - using WebUSB", device_info.unique_id);
+                        return Ok(TransportType::WebUsb);
+                    } else if has_interrupt {
+                        info!("🔌 Device {} has interrupt endpoints - using USB transport", device_info.unique_id);
+                        return Ok(TransportType::TraditionalUsb);
+                    } else {
+                        warn!("⚠️ Device {} has no recognizable endpoints - defaulting to WebUSB", device_info.unique_id);
+                        return Ok(TransportType::WebUsb);
+                    }
+                } else {
+                    warn!("⚠️ Could not get interface descriptor for device {}", device_info.unique_id);
+                }
+            } else {
+                warn!("⚠️ Could not find interface 0 for device {}", device_info.unique_id);
+            }
+        }
+        Err(e) => {
+            warn!("⚠️ Could not read device descriptors for {}: {}, defaulting to WebUSB", device_info.unique_id, e);
+        }
+    }
+    
+    // Default to WebUSB for modern devices, not HID
+    info!("🌐 Defaulting to WebUSB transport for device {}", device_info.unique_id);
+    Ok(TransportType::WebUsb)
+}
+
+/// Try HID transport as fallback
+#[cfg(feature = "hid")]
+fn try_hid_fallback(device_info: &FriendlyUsbDevice, previous_error: String) -> Result<OpenedTransport> {
+    // Check if this is a Windows FIDO blocklist error
+    #[cfg(target_os = "windows")]
+    {
+        let error_lower = previous_error.to_lowercase();
+        if error_lower.contains("access") || error_lower.contains("denied") || 
+           error_lower.contains("permission") || error_lower.contains("0x00000005") {
+            info!("🪟 Windows FIDO blocklist detected - switching to HID transport");
+            info!("   📝 Windows CTAP-HID filter is blocking USB access");
+            info!("   ✅ Using HID transport to bypass FIDO restrictions");
+        }
+    }
+    
+    match crate::transport::HidTransport::new_for_device(device_info.serial_number.as_deref()) {
+        Ok(hid_transport) => {
+            info!("✅ Created HID transport for device {}", device_info.unique_id);
+            Ok(OpenedTransport::Hid(hid_transport))
+        }
+        Err(hid_err) => {
+            Err(anyhow!("Failed with both primary transport ({}) and HID fallback ({})", previous_error, hid_err))
+        }
+    }
+}
+
+#[cfg(not(feature = "hid"))]
+fn try_hid_fallback(device_info: &FriendlyUsbDevice, previous_error: String) -> Result<OpenedTransport> {
+    Err(anyhow!(
+        "{} (device {}; HID fallback not built, enable the `hid` feature)",
+        previous_error,
+        device_info.unique_id
+    ))
+}
+
+/// Find the physical device matching device info (static method)
+fn find_physical_device_by_info(device_info: &FriendlyUsbDevice, devices: &[rusb::Device<rusb::GlobalContext>]) -> Result<rusb::Device<rusb::GlobalContext>> {
+    // An empty serial would match every serial-less KeepKey
+    if let Some(serial) = device_info.serial_number.as_ref().filter(|s| !s.is_empty()) {
+        // Match by serial number (flexible - allows PID change after bootloader update)
+        for device in devices {
+            if let Ok(handle) = device.open() {
+                let timeout = std::time::Duration::from_millis(100);
+                if let Ok(langs) = handle.read_languages(timeout) {
+                    if let Some(lang) = langs.first() {
+                        if let Ok(desc) = device.device_descriptor() {
+                            // Only check VID matches (KeepKey vendor ID)
+                            if desc.vendor_id() == device_info.vid {
+                                if let Ok(device_serial) = handle.read_serial_number_string(*lang, &desc, timeout) {
+                                    if device_serial == *serial {
+                                        // Log if PID changed (happens after bootloader update)
+                                        if desc.product_id() != device_info.pid {
+                                            info!("📝 Device reconnected with different PID: 0x{:04x} -> 0x{:04x}", 
+                                                  device_info.pid, desc.product_id());
+                                        }
+                                        return Ok(device.clone());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    // Serial-less devices: the slot's current bus address (or one spelled out in the id)
+    if let Some((bus, addr)) = crate::device_identity::usb_address(&device_info.unique_id) {
+        for device in devices {
+            if device.bus_number() == bus && device.address() == addr {
+                return Ok(device.clone());
+            }
+        }
+    }
+    
+    Err(anyhow!("Physical device not found for {} (VID: 0x{:04x}, PID: 0x{:04x}, Serial: {:?})", 
+                device_info.unique_id, device_info.vid, device_info.pid, device_info.serial_number))
+}
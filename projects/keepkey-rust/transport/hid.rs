@@ -25,6 +25,9 @@ pub enum HidError {
     Io(#[from] std::io::Error),
     #[error("Other error: {0}")]
     Other(String),
+    /// Nothing arrived before the read deadline
+    #[error("{0}")]
+    Timeout(String),
 }
 
 pub struct HidTransport {
@@ -213,7 +216,16 @@ impl HidTransport {
 impl Transport for HidTransport {
     type Error = HidError;
     
-    fn write(&mut self, msg: &[u8], _timeout: Duration) -> Result<usize, Self::Error> {
+    fn write(&mut self, msg: &[u8], timeout: Duration) -> Result<usize, Self::Error> {
+        self.write_with_progress(msg, timeout, &mut |_| {})
+    }
+    
+    fn write_with_progress(
+        &mut self,
+        msg: &[u8],
+        _timeout: Duration,
+        progress: &mut dyn FnMut(usize),
+    ) -> Result<usize, Self::Error> {
         // The incoming message already has the protocol header from Message::encode
         // Format: [#][#][msg_type(2)][length(4)][data...]
         
@@ -305,6 +317,9 @@ impl Transport for HidTransport {
             .write(&first_packet)
             .map_err(|e| HidError::Other(format!("HID write failed: {}", e)))?;
         
+        // Header plus data sent so far
+        progress(8 + first_chunk_size);
+        
         // Send continuation packets if needed
         let mut sent = first_chunk_size;
         let mut packet_count = 1;
@@ -324,6 +339,7 @@ impl Transport for HidTransport {
             
             sent += chunk_size;
             packet_count += 1;
+            progress(8 + sent);
         }
         
         info!("HID Write: Complete. Sent {} bytes in {} packets", msg.len(), packet_count);
@@ -331,6 +347,10 @@ impl Transport for HidTransport {
         Ok(msg.len())
     }
     
+    fn is_timeout(error: &Self::Error) -> bool {
+        matches!(error, HidError::Timeout(_))
+    }
+
     fn read(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<(), Self::Error> {
         let timeout_ms = timeout.as_millis() as i32;
         
@@ -362,7 +382,7 @@ impl Transport for HidTransport {
                 timeout_ms
             );
             
-            return Err(HidError::Other(helpful_message));
+            return Err(HidError::Timeout(helpful_message));
         }
         
         info!("HID Read: Received first packet ({} bytes)", size);
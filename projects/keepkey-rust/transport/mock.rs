@@ -0,0 +1,363 @@
+//! Simulated KeepKey (`mock-device` feature), for demo mode and for running
+//! applications end to end without hardware.
+//!
+//! It speaks the same framed protobuf protocol as the USB transports and
+//! shows up in [`list_connected_devices`](crate::features::list_connected_devices)
+//! while demo mode is on (see [`DEMO_MODE_ENV`]), so device queue workers,
+//! Features reads and signing loops run unchanged. Keys come from the BIP39
+//! test mnemonic "abandon abandon ... about": addresses and xpubs are real and
+//! match any other wallet loaded with that seed, which is public, so never
+//! fund them. Signing walks through the usual button requests and returns
+//! canned results.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use bitcoin::bip32::{ChildNumber, ExtendedPrivKey, ExtendedPubKey};
+use bitcoin::secp256k1::{All, Secp256k1};
+use bitcoin::{Address, Network, PublicKey};
+use log::debug;
+
+use super::Transport;
+use crate::friendly_usb::{FriendlyUsbDevice, KEEPKEY_VID};
+use crate::messages::{self, ButtonRequestType, InputScriptType, Message};
+
+/// Device id (and serial number) reported by the simulator
+pub const MOCK_DEVICE_ID: &str = "MOCKKEEPKEY000000000000001";
+
+/// Environment variable that lists the simulator as a connected device (`1` or `true`)
+pub const DEMO_MODE_ENV: &str = "KEEPKEY_DEMO_MODE";
+
+// BIP39 seed of "abandon abandon abandon abandon abandon abandon abandon
+// abandon abandon abandon abandon about" with an empty passphrase
+const TEST_SEED_HEX: &str = "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4";
+
+// A minimal well-formed transaction (one null input, one empty output)
+// returned as the signed result of every SignTx
+const CANNED_SIGNED_TX_HEX: &str = "0100000001000000000000000000000000000000000000000000000000000000000000000000000000ffffffff01000000000000000000000000";
+
+/// Syntactically valid DER signature returned for every signed input
+fn canned_signature() -> Vec<u8> {
+    let mut der = vec![0x30, 0x44, 0x02, 0x20];
+    der.extend([0x11; 32]);
+    der.extend([0x02, 0x20]);
+    der.extend([0x22; 32]);
+    der
+}
+
+fn demo_mode_state() -> &'static AtomicBool {
+    static DEMO_MODE: OnceLock<AtomicBool> = OnceLock::new();
+    DEMO_MODE.get_or_init(|| {
+        let on = std::env::var(DEMO_MODE_ENV)
+            .is_ok_and(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "on"));
+        AtomicBool::new(on)
+    })
+}
+
+/// Whether the simulator is listed as a connected device
+pub fn demo_mode() -> bool {
+    demo_mode_state().load(Ordering::Relaxed)
+}
+
+/// Turn demo mode on or off (initially read from [`DEMO_MODE_ENV`])
+pub fn set_demo_mode(on: bool) {
+    demo_mode_state().store(on, Ordering::Relaxed);
+}
+
+pub struct MockDevice {
+    secp: Secp256k1<All>,
+    master: ExtendedPrivKey,
+    responses: VecDeque<Vec<u8>>,
+    /// Button requests still to be acknowledged before `after_buttons` is sent
+    pending_buttons: VecDeque<ButtonRequestType>,
+    after_buttons: Option<Message>,
+}
+
+impl Default for MockDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockDevice {
+    pub fn new() -> Self {
+        let seed = hex::decode(TEST_SEED_HEX).expect("test seed is valid hex");
+        Self {
+            secp: Secp256k1::new(),
+            master: ExtendedPrivKey::new_master(Network::Bitcoin, &seed).expect("test seed is a valid BIP32 seed"),
+            responses: VecDeque::new(),
+            pending_buttons: VecDeque::new(),
+            after_buttons: None,
+        }
+    }
+
+    /// How the simulator appears in device listings
+    pub fn device_info() -> FriendlyUsbDevice {
+        FriendlyUsbDevice::new(
+            MOCK_DEVICE_ID.to_string(),
+            KEEPKEY_VID,
+            0x0002,
+            Some("KeepKey".to_string()),
+            Some("KeepKey Simulator".to_string()),
+            Some(MOCK_DEVICE_ID.to_string()),
+        )
+    }
+
+    pub fn features() -> messages::Features {
+        messages::Features {
+            vendor: Some("keepkey.com".to_string()),
+            major_version: Some(7),
+            minor_version: Some(10),
+            patch_version: Some(0),
+            bootloader_mode: Some(false),
+            device_id: Some(MOCK_DEVICE_ID.to_string()),
+            pin_protection: Some(false),
+            passphrase_protection: Some(false),
+            language: Some("english".to_string()),
+            label: Some("KeepKey Simulator".to_string()),
+            initialized: Some(true),
+            imported: Some(false),
+            pin_cached: Some(false),
+            passphrase_cached: Some(false),
+            model: Some("K1-14AM".to_string()),
+            firmware_variant: Some("Simulator".to_string()),
+            no_backup: Some(false),
+            ..Default::default()
+        }
+    }
+
+    fn failure(code: messages::FailureType, message: impl Into<String>) -> Message {
+        messages::Failure {
+            code: Some(code as i32),
+            message: Some(message.into()),
+        }
+        .into()
+    }
+
+    fn derive(&self, address_n: &[u32]) -> Result<ExtendedPubKey, String> {
+        let path: Vec<ChildNumber> = address_n.iter().map(|&index| ChildNumber::from(index)).collect();
+        let xprv = self.master.derive_priv(&self.secp, &path).map_err(|e| e.to_string())?;
+        Ok(ExtendedPubKey::from_priv(&self.secp, &xprv))
+    }
+
+    fn network(coin_name: Option<&str>) -> Network {
+        match coin_name {
+            Some("Testnet") => Network::Testnet,
+            _ => Network::Bitcoin,
+        }
+    }
+
+    fn address(&self, address_n: &[u32], coin_name: Option<&str>, script_type: Option<i32>) -> Result<String, String> {
+        let xpub = self.derive(address_n)?;
+        let network = Self::network(coin_name);
+        let key = PublicKey::new(xpub.public_key);
+        let address = match script_type.and_then(InputScriptType::from_i32) {
+            None | Some(InputScriptType::Spendaddress) => Address::p2pkh(&key, network),
+            Some(InputScriptType::Spendwitness) => Address::p2wpkh(&key, network).map_err(|e| e.to_string())?,
+            Some(InputScriptType::Spendp2shwitness) => Address::p2shwpkh(&key, network).map_err(|e| e.to_string())?,
+            Some(InputScriptType::Spendtaproot) => {
+                Address::p2tr(&self.secp, xpub.public_key.x_only_public_key().0, None, network)
+            }
+            Some(other) => return Err(format!("Script type {:?} is not supported by the simulator", other)),
+        };
+        Ok(address.to_string())
+    }
+
+    /// Serialized public node, with the SLIP-132 version the firmware uses
+    /// for the script type (xpub, ypub or zpub)
+    fn public_key(&self, address_n: &[u32], coin_name: Option<&str>, script_type: Option<i32>) -> Result<messages::PublicKey, String> {
+        let xpub = self.derive(address_n)?;
+        let testnet = Self::network(coin_name) != Network::Bitcoin;
+        let version: [u8; 4] = match (script_type.and_then(InputScriptType::from_i32), testnet) {
+            (Some(InputScriptType::Spendp2shwitness), false) => [0x04, 0x9d, 0x7c, 0xb2],
+            (Some(InputScriptType::Spendwitness), false) => [0x04, 0xb2, 0x47, 0x46],
+            (Some(InputScriptType::Spendp2shwitness), true) => [0x04, 0x4a, 0x52, 0x62],
+            (Some(InputScriptType::Spendwitness), true) => [0x04, 0x5f, 0x1c, 0xf6],
+            (_, false) => [0x04, 0x88, 0xb2, 0x1e],
+            (_, true) => [0x04, 0x35, 0x87, 0xcf],
+        };
+        let mut encoded = xpub.encode();
+        encoded[..4].copy_from_slice(&version);
+
+        Ok(messages::PublicKey {
+            node: messages::HdNodeType {
+                depth: xpub.depth as u32,
+                fingerprint: u32::from_be_bytes([encoded[5], encoded[6], encoded[7], encoded[8]]),
+                child_num: u32::from(xpub.child_number),
+                chain_code: encoded[13..45].to_vec(),
+                private_key: None,
+                public_key: Some(xpub.public_key.serialize().to_vec()),
+            },
+            xpub: Some(bitcoin::base58::encode_check(&encoded)),
+        })
+    }
+
+    /// Queue the confirmations a real device would ask for, then `result`
+    fn confirm_then(&mut self, buttons: Vec<ButtonRequestType>, result: Message) -> Message {
+        self.pending_buttons = buttons.into();
+        self.after_buttons = Some(result);
+        self.next_button().unwrap_or_else(|| Self::failure(messages::FailureType::FailureOther, "Nothing to confirm"))
+    }
+
+    fn next_button(&mut self) -> Option<Message> {
+        match self.pending_buttons.pop_front() {
+            Some(code) => Some(
+                messages::ButtonRequest {
+                    code: Some(code as i32),
+                    data: None,
+                }
+                .into(),
+            ),
+            None => self.after_buttons.take(),
+        }
+    }
+
+    fn respond(&mut self, request: Message) -> Message {
+        debug!("MockDevice: {:?}", request.message_type());
+        let result = match request {
+            Message::Initialize(_) | Message::GetFeatures(_) => {
+                self.pending_buttons.clear();
+                self.after_buttons = None;
+                Ok(Self::features().into())
+            }
+            Message::Ping(ping) => Ok(messages::Success { message: ping.message }.into()),
+            Message::ClearSession(_) | Message::ApplySettings(_) | Message::ApplyPolicies(_) => {
+                Ok(messages::Success { message: Some("Simulated".to_string()) }.into())
+            }
+            Message::Cancel(_) => {
+                self.pending_buttons.clear();
+                self.after_buttons = None;
+                Ok(Self::failure(messages::FailureType::FailureActionCancelled, "Cancelled"))
+            }
+            Message::ButtonAck(_) => Ok(self
+                .next_button()
+                .unwrap_or_else(|| Self::failure(messages::FailureType::FailureUnexpectedMessage, "Unexpected ButtonAck"))),
+            Message::GetAddress(req) => self
+                .address(&req.address_n, req.coin_name.as_deref(), req.script_type)
+                .map(|address| messages::Address { address }.into()),
+            Message::GetPublicKey(req) => self
+                .public_key(&req.address_n, req.coin_name.as_deref(), req.script_type)
+                .map(Message::from),
+            Message::SignMessage(req) => self
+                .address(&req.address_n, req.coin_name.as_deref(), req.script_type)
+                .map(|address| {
+                    let mut signature = vec![0x1f];
+                    signature.extend([0x33; 64]);
+                    let result = messages::MessageSignature {
+                        address: Some(address),
+                        signature: Some(signature),
+                    };
+                    self.confirm_then(vec![ButtonRequestType::ButtonRequestOther], result.into())
+                }),
+            Message::SignTx(req) => {
+                let result = messages::TxRequest {
+                    request_type: Some(messages::RequestType::Txfinished as i32),
+                    details: None,
+                    serialized: Some(messages::TxRequestSerializedType {
+                        signature_index: Some(0),
+                        signature: Some(canned_signature()),
+                        serialized_tx: Some(hex::decode(CANNED_SIGNED_TX_HEX).expect("canned tx is valid hex")),
+                    }),
+                };
+                let mut buttons = vec![ButtonRequestType::ButtonRequestConfirmOutput; req.outputs_count as usize];
+                buttons.push(ButtonRequestType::ButtonRequestSignTx);
+                Ok(self.confirm_then(buttons, result.into()))
+            }
+            other => Err(format!("{:?} is not supported by the simulator", other.message_type())),
+        };
+        result.unwrap_or_else(|message| Self::failure(messages::FailureType::FailureOther, message))
+    }
+}
+
+impl Transport for MockDevice {
+    type Error = rusb::Error;
+
+    fn write(&mut self, msg: &[u8], _timeout: Duration) -> Result<usize, Self::Error> {
+        let request = Message::decode(&mut &msg[..]).map_err(|_| rusb::Error::Other)?;
+        let response = self.respond(request);
+        let mut out = Vec::with_capacity(response.encoded_len());
+        response.encode(&mut out).map_err(|_| rusb::Error::Other)?;
+        self.responses.push_back(out);
+        Ok(msg.len())
+    }
+
+    fn is_timeout(error: &Self::Error) -> bool {
+        matches!(error, rusb::Error::Timeout)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, _timeout: Duration) -> Result<(), Self::Error> {
+        let response = self.responses.pop_front().ok_or(rusb::Error::Timeout)?;
+        buf.extend_from_slice(&response);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), Self::Error> {
+        self.responses.clear();
+        self.pending_buttons.clear();
+        self.after_buttons = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::ProtocolAdapter;
+
+    const HARDENED: u32 = 0x8000_0000;
+
+    #[test]
+    fn derives_the_test_mnemonic_addresses() {
+        let mut device = MockDevice::new();
+        let request = messages::GetAddress {
+            address_n: vec![84 | HARDENED, HARDENED, HARDENED, 0, 0],
+            coin_name: Some("Bitcoin".to_string()),
+            script_type: Some(InputScriptType::Spendwitness as i32),
+            ..Default::default()
+        };
+        match device.handle(request.into()).unwrap() {
+            Message::Address(address) => assert_eq!(address.address, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"),
+            other => panic!("unexpected response {:?}", other),
+        }
+
+        let request = messages::GetPublicKey {
+            address_n: vec![84 | HARDENED, HARDENED, HARDENED],
+            coin_name: Some("Bitcoin".to_string()),
+            script_type: Some(InputScriptType::Spendwitness as i32),
+            ..Default::default()
+        };
+        match device.handle(request.into()).unwrap() {
+            Message::PublicKey(key) => assert_eq!(
+                key.xpub.as_deref(),
+                Some("zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs")
+            ),
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+
+    #[test]
+    fn signing_asks_for_confirmation_first() {
+        let mut device = MockDevice::new();
+        let sign_tx = messages::SignTx {
+            outputs_count: 2,
+            inputs_count: 1,
+            ..Default::default()
+        };
+        let mut codes = Vec::new();
+        let mut response = device.handle(sign_tx.into()).unwrap();
+        while let Message::ButtonRequest(request) = response {
+            codes.push(request.code.unwrap_or_default());
+            response = device.handle(messages::ButtonAck {}.into()).unwrap();
+        }
+        assert_eq!(codes.len(), 3);
+        match response {
+            Message::TxRequest(request) => {
+                assert_eq!(request.request_type, Some(messages::RequestType::Txfinished as i32));
+                assert!(request.serialized.and_then(|s| s.serialized_tx).is_some());
+            }
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+}
@@ -1,24 +1,76 @@
 pub mod protocol_adapter;
 pub mod usb;
 pub mod webusb;
+#[cfg(feature = "hid")]
 pub mod hid;
+#[cfg(feature = "queue")]
+pub mod async_transport;
+#[cfg(feature = "mock-device")]
+pub mod mock;
+mod factory;
 
 pub use protocol_adapter::*;
 pub use usb::*;
 pub use webusb::*;
+#[cfg(feature = "hid")]
 pub use hid::*;
+#[cfg(feature = "queue")]
+pub use async_transport::*;
+#[cfg(feature = "mock-device")]
+pub use mock::MockDevice;
+pub(crate) use factory::create_transport_for_device;
+#[cfg(feature = "queue")]
+pub(crate) use factory::{create_async_debug_link_for_device, create_async_transport_for_device};
 
+use crate::failure::DeviceFailure;
+use crate::friendly_usb::FriendlyUsbDevice;
 use crate::messages::{self, Message};
 use anyhow::{anyhow, bail, Result};
 use core::time::Duration;
 use std::io::{stdin, stdout, Write};
 use log::info;
 
+/// Whether `device` is the simulator (`mock-device` feature) rather than hardware
+pub fn is_mock_device(device: &FriendlyUsbDevice) -> bool {
+    #[cfg(feature = "mock-device")]
+    {
+        device.unique_id == mock::MOCK_DEVICE_ID
+    }
+    #[cfg(not(feature = "mock-device"))]
+    {
+        let _ = device;
+        false
+    }
+}
+
 pub trait Transport {
     type Error: std::error::Error;
     fn write(&mut self, msg: &[u8], timeout: Duration) -> Result<usize, Self::Error>;
     fn read(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<(), Self::Error>;
     fn reset(&mut self) -> Result<(), Self::Error>;
+
+    /// [`write`](Self::write), calling `progress` with the bytes of `msg` sent
+    /// so far. Transports that write in one go report once at the end.
+    fn write_with_progress(
+        &mut self,
+        msg: &[u8],
+        timeout: Duration,
+        progress: &mut dyn FnMut(usize),
+    ) -> Result<usize, Self::Error> {
+        let written = self.write(msg, timeout)?;
+        progress(written);
+        Ok(written)
+    }
+
+    /// Whether `error` means nothing arrived before the deadline, as opposed
+    /// to the device or the bus failing
+    fn is_timeout(error: &Self::Error) -> bool
+    where
+        Self: Sized,
+    {
+        let _ = error;
+        false
+    }
 }
 
 pub fn standard_message_handler(msg: &Message) -> Result<Option<Message>> {
@@ -66,7 +118,7 @@ pub fn standard_message_handler(msg: &Message) -> Result<Option<Message>> {
             let passphrase = passphrase.trim().to_owned();
             Some(messages::PassphraseAck { passphrase }.into())
         }
-        Message::Failure(x) => bail!("Failure: {}", x.message()),
+        Message::Failure(x) => return Err(DeviceFailure::from(x).into()),
         _ => None,
     })
 }
@@ -109,7 +161,7 @@ pub fn pin_flow_message_handler(msg: &Message) -> Result<Option<Message>> {
             // Don't handle passphrase in PIN flow - let frontend handle it
             None
         }
-        Message::Failure(x) => bail!("Failure: {}", x.message()),
+        Message::Failure(x) => return Err(DeviceFailure::from(x).into()),
         _ => None,
     })
 }
@@ -157,7 +209,7 @@ pub fn recovery_flow_message_handler(msg: &Message) -> Result<Option<Message>> {
             // Don't handle passphrase in recovery flow - let frontend handle it
             None
         }
-        Message::Failure(x) => bail!("Failure: {}", x.message()),
+        Message::Failure(x) => return Err(DeviceFailure::from(x).into()),
         _ => None,
     })
 }
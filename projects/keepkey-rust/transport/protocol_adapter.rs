@@ -1,6 +1,9 @@
 use super::{ProtocolAdapter, Transport};
+use crate::failure::DeviceTimeout;
 use crate::messages::Message;
+use crate::protocol_trace::{self, TraceDirection};
 use anyhow::{anyhow, Result};
+use std::time::Instant;
 
 use log::{info, debug};
 
@@ -25,6 +28,7 @@ where
         debug!("ProtocolAdapter::send: Encoded message size: {} bytes", out_buf.len());
         
         self.write(&out_buf, msg.write_timeout())?;
+        protocol_trace::record(TraceDirection::ToDevice, &msg, &out_buf, None);
 
         Ok(())
     }
@@ -37,15 +41,21 @@ where
         info!("ProtocolAdapter::handle: Processing message type: {:?}", msg.message_type());
         
         let read_timeout = msg.read_timeout();
+        let timed_out = DeviceTimeout::waiting_for(&msg, read_timeout);
+        let sent_at = Instant::now();
         self.send(msg)?;
 
         info!("ProtocolAdapter::handle: Waiting for response (timeout: {:?})...", read_timeout);
         let mut in_buf = Vec::<u8>::new();
-        self.read(&mut in_buf, read_timeout)?;
+        match self.read(&mut in_buf, read_timeout) {
+            Err(e) if T::is_timeout(&e) => return Err(timed_out.into()),
+            result => result?,
+        }
         
         info!("ProtocolAdapter::handle: Received {} bytes response", in_buf.len());
 
         let out = Message::decode(&mut in_buf.as_slice()).map_err(|x| anyhow!(x))?;
+        protocol_trace::record(TraceDirection::FromDevice, &out, &in_buf, Some(sent_at.elapsed()));
         info!("ProtocolAdapter::handle: Decoded response type: {:?}", out.message_type());
         
         // Clean, concise logging with key info
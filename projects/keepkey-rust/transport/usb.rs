@@ -133,8 +133,17 @@ macro_rules! since {
 impl<T: UsbContext> Transport for UsbTransport<T> {
     type Error = rusb::Error;
     fn write(&mut self, msg: &[u8], timeout: Duration) -> Result<usize, Self::Error> {
+        self.write_with_progress(msg, timeout, &mut |_| {})
+    }
+    fn write_with_progress(
+        &mut self,
+        msg: &[u8],
+        timeout: Duration,
+        progress: &mut dyn FnMut(usize),
+    ) -> Result<usize, Self::Error> {
         let started = Instant::now();
         let mut packet = Vec::<u8>::with_capacity(self.out_packet_size);
+        let mut sent = 0;
         for chunk in msg.chunks(self.out_packet_size - 1) {
             packet.clear();
             packet.push(b'?');
@@ -152,9 +161,15 @@ impl<T: UsbContext> Transport for UsbTransport<T> {
             if written_len != packet.len() {
                 return Err(rusb::Error::Other);
             }
+            sent += chunk.len();
+            progress(sent);
         }
         Ok(msg.len())
     }
+    fn is_timeout(error: &Self::Error) -> bool {
+        matches!(error, rusb::Error::Timeout)
+    }
+
     fn read(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<(), Self::Error> {
         let mut packet = Vec::<u8>::with_capacity(self.in_packet_size);
         let started = Instant::now();
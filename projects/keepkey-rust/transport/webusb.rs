@@ -172,6 +172,10 @@ impl<T: UsbContext> Transport for WebUsbTransport<T> {
         Ok(msg.len())
     }
     
+    fn is_timeout(error: &Self::Error) -> bool {
+        matches!(error, rusb::Error::Timeout)
+    }
+
     fn read(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<(), Self::Error> {
         let started = Instant::now();
         
@@ -0,0 +1,154 @@
+use crate::server::address_validation::{validate_address, AddressError};
+use crate::server::{cache::DeviceCache, chain::ChainBackend};
+use crate::transport::ProtocolAdapter;
+use anyhow::{anyhow, Result};
+use bitcoin::{Amount, Denomination, Network};
+use clap::Parser;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+fn parse_btc_amount(s: &str) -> Result<u64, String> {
+    Amount::from_str_in(s, Denomination::Bitcoin)
+        .map(Amount::to_sat)
+        .map_err(|e| format!("invalid BTC amount {:?}: {}", s, e))
+}
+
+/// Wait for a payment to an address and print its txid and confirmations.
+/// Exits non-zero on timeout, so it can gate scripted checkout flows.
+#[derive(Parser, Debug, Clone)]
+pub struct AwaitPayment {
+    /// Address to watch
+    #[clap(short, long)]
+    pub address: String,
+
+    /// Minimum amount in BTC a single transaction must pay to the address
+    #[clap(long, value_parser = parse_btc_amount)]
+    pub amount: Option<u64>,
+
+    /// Wait until the payment has this many confirmations (0 = in the mempool)
+    #[clap(short, long, default_value_t = 0)]
+    pub confirmations: u64,
+
+    /// Give up after this many seconds
+    #[clap(long)]
+    pub timeout: Option<u64>,
+
+    /// Seconds between backend polls
+    #[clap(long, default_value_t = 10)]
+    pub interval: u64,
+
+    /// Esplora base URL; defaults to the `chain_backend_url` setting
+    #[clap(long)]
+    pub backend: Option<String>,
+
+    /// Print the result as a JSON object
+    #[clap(long)]
+    pub json: bool,
+}
+
+impl super::CliCommand for AwaitPayment {
+    fn handle(self, _protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        // Like `watch`, this doesn't talk to the device and is run from main.rs
+        println!("AwaitPayment command should be handled in main.rs with async runtime");
+        Ok(())
+    }
+}
+
+impl AwaitPayment {
+    pub async fn run(self) -> Result<()> {
+        // The backend decides the network, so only a malformed address is fatal
+        match validate_address(&self.address, Network::Bitcoin) {
+            Ok(_) => {}
+            Err(AddressError::WrongNetwork { .. }) => eprintln!("Note: {} is not a mainnet address", self.address),
+            Err(e) => return Err(anyhow!(e)),
+        }
+        let backend = match &self.backend {
+            Some(url) => ChainBackend::new(url.as_str()),
+            None => ChainBackend::from_cache(&DeviceCache::open()?).await?,
+        };
+        let deadline = self.timeout.map(|secs| Instant::now() + Duration::from_secs(secs));
+        let interval = Duration::from_secs(self.interval.max(1));
+
+        // Outputs that already exist don't count as the payment being waited for
+        let existing: HashSet<String> = backend
+            .address_utxos(&self.address)
+            .await?
+            .into_iter()
+            .map(|utxo| utxo.txid)
+            .collect();
+        eprintln!(
+            "Waiting for {} to {} via {} (Ctrl+C to stop)",
+            self.amount
+                .map(|sats| format!("{} BTC", Amount::from_sat(sats).to_string_in(Denomination::Bitcoin)))
+                .unwrap_or_else(|| "a payment".to_string()),
+            self.address,
+            backend.base_url()
+        );
+
+        let mut announced: Option<String> = None;
+        loop {
+            match self.find_payment(&backend, &existing).await {
+                Ok(Some((txid, sats, confirmations))) => {
+                    if confirmations >= self.confirmations {
+                        if self.json {
+                            println!(
+                                "{}",
+                                serde_json::json!({
+                                    "address": self.address,
+                                    "txid": txid,
+                                    "amountSats": sats,
+                                    "confirmations": confirmations,
+                                })
+                            );
+                        } else {
+                            println!("{} {}", txid, confirmations);
+                        }
+                        return Ok(());
+                    }
+                    if announced.as_deref() != Some(txid.as_str()) {
+                        eprintln!("Payment seen: {} ({} sats), waiting for {} confirmation(s)", txid, sats, self.confirmations);
+                        announced = Some(txid);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Backend error, retrying: {}", e),
+            }
+
+            if deadline.map_or(false, |deadline| Instant::now() + interval > deadline) {
+                return Err(anyhow!("no payment to {} within {}s", self.address, self.timeout.unwrap_or_default()));
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Qualifying transaction with the most confirmations, as `(txid, sats paid to the address, confirmations)`
+    async fn find_payment(&self, backend: &ChainBackend, existing: &HashSet<String>) -> Result<Option<(String, u64, u64)>> {
+        let mut paid: HashMap<String, (u64, Option<u64>)> = HashMap::new();
+        for utxo in backend.address_utxos(&self.address).await? {
+            if existing.contains(&utxo.txid) {
+                continue;
+            }
+            let entry = paid.entry(utxo.txid).or_insert((0, None));
+            entry.0 += utxo.value;
+            if utxo.status.confirmed {
+                entry.1 = utxo.status.block_height;
+            }
+        }
+
+        let minimum = self.amount.unwrap_or(1);
+        let best = paid
+            .into_iter()
+            .filter(|(_, (sats, _))| *sats >= minimum)
+            // Most confirmations first, so a waiting script finishes as early as possible
+            .min_by_key(|(_, (_, height))| height.unwrap_or(u64::MAX));
+        let (txid, (sats, height)) = match best {
+            Some(best) => best,
+            None => return Ok(None),
+        };
+        let confirmations = match height {
+            Some(height) => backend.tip_height().await?.saturating_sub(height) + 1,
+            None => 0,
+        };
+        Ok(Some((txid, sats, confirmations)))
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    cli::CliCommand,
+    server::{autostart, instance_lock},
+    transport::ProtocolAdapter,
+};
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use std::time::Duration;
+
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Run the server in the background at login
+#[derive(Debug, Clone, Args)]
+pub struct Daemon {
+    #[clap(subcommand)]
+    command: DaemonCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum DaemonCommand {
+    Install(Install),
+    Status,
+    Uninstall,
+}
+
+/// Start `kkcli server` at login (launchd, systemd or the Startup folder)
+#[derive(Debug, Clone, Args)]
+struct Install {
+    /// port the server listens on
+    #[clap(short, long, default_value_t = 1646)]
+    port: u16,
+}
+
+impl Daemon {
+    pub async fn run(self) -> Result<()> {
+        match self.command {
+            DaemonCommand::Install(x) => {
+                let (manager, path) = autostart::install(x.port)?;
+                println!("Installed {} at {}", manager.name(), path.display());
+                if manager == autostart::ServiceManager::StartupFolder {
+                    println!("The server starts at next login; run `kkcli server` to start it now");
+                }
+                Ok(())
+            }
+            DaemonCommand::Status => status().await,
+            DaemonCommand::Uninstall => {
+                match autostart::uninstall()? {
+                    Some(path) => println!("Removed {}", path.display()),
+                    None => println!("No autostart entry installed"),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn status() -> Result<()> {
+    match autostart::installed()? {
+        Some(path) => println!("Autostart: installed ({})", path.display()),
+        None => println!("Autostart: not installed"),
+    }
+
+    let Some(instance) = instance_lock::running_instance()? else {
+        println!("Server:    not running");
+        return Ok(());
+    };
+    let started = chrono::DateTime::from_timestamp(instance.started_at, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_else(|| instance.started_at.to_string());
+    println!("Server:    running (PID {}, port {}, since {})", instance.pid, instance.port, started);
+
+    let url = format!("http://127.0.0.1:{}/api/health", instance.port);
+    let healthy = match reqwest::Client::builder().timeout(HEALTH_TIMEOUT).build() {
+        Ok(client) => client.get(&url).send().await.is_ok_and(|response| response.status().is_success()),
+        Err(_) => false,
+    };
+    if healthy {
+        println!("Health:    ok ({})", url);
+    } else {
+        // Device setup runs before the port opens, so a fresh server may not answer yet
+        println!("Health:    not answering at {}", url);
+    }
+    Ok(())
+}
+
+impl CliCommand for Daemon {
+    fn handle(self, _: &mut dyn ProtocolAdapter) -> Result<()> {
+        unreachable!();
+    }
+}
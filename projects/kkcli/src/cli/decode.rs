@@ -2,15 +2,54 @@ use super::parsers::HexParser;
 use crate::{cli::CliCommand, messages::Message, transport::ProtocolAdapter};
 use anyhow::{anyhow, Result};
 use clap::Args;
+use serde::Deserialize;
+use std::time::Duration;
 
-/// Decode a raw message
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Decode a raw message, or follow the messages a running vault exchanges
+/// with its devices
 #[derive(Debug, Clone, Args)]
 pub struct Decode {
-    #[clap(required = true, multiple = false, value_parser = HexParser)]
+    #[clap(required_unless_present = "live", multiple = false, value_parser = HexParser)]
     data: Vec<Vec<u8>>,
+
+    /// Print each message as the vault sends or receives it (needs the
+    /// vault's protocol trace turned on)
+    #[clap(long)]
+    live: bool,
+
+    /// Base URL of the vault's API, with --live
+    #[clap(long, default_value = "http://127.0.0.1:1646", requires = "live")]
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProtocolTrace {
+    tracing: bool,
+    entries: Vec<TraceEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TraceEntry {
+    seq: u64,
+    direction: String,
+    message_type: String,
+    len: usize,
+    frame: String,
+    truncated: bool,
+    elapsed_ms: Option<u64>,
 }
 
 impl Decode {
+    pub async fn run(self) -> Result<()> {
+        if self.live {
+            self.follow().await
+        } else {
+            self.handle()
+        }
+    }
+
     pub fn handle(self) -> Result<()> {
         let mut data = self.data[0].clone();
 
@@ -22,6 +61,54 @@ impl Decode {
         println!("{:?}", msg);
         Ok(())
     }
+
+    async fn follow(&self) -> Result<()> {
+        let url = format!("{}/api/protocol-trace", self.url.trim_end_matches('/'));
+        let client = reqwest::Client::new();
+        let mut since = None;
+        let mut warned = false;
+        eprintln!("Following {} (Ctrl+C to stop)", url);
+        loop {
+            let mut request = client.get(&url);
+            if let Some(seq) = since {
+                request = request.query(&[("since", seq)]);
+            }
+            let trace: ProtocolTrace = request
+                .send()
+                .await
+                .map_err(|e| anyhow!("could not reach {} ({}); is the vault running?", url, e))?
+                .error_for_status()?
+                .json()
+                .await?;
+            if !trace.tracing && !warned {
+                eprintln!("Protocol tracing is off in the vault; turn it on in settings or start it with KEEPKEY_PROTOCOL_TRACE=1");
+            }
+            warned = !trace.tracing;
+            for entry in &trace.entries {
+                print_entry(entry);
+                since = Some(entry.seq);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+fn print_entry(entry: &TraceEntry) {
+    let arrow = if entry.direction == "to_device" { "->" } else { "<-" };
+    let elapsed = entry.elapsed_ms.map(|ms| format!(" in {}ms", ms)).unwrap_or_default();
+    println!("{} {} ({} bytes{})", arrow, entry.message_type, entry.len, elapsed);
+
+    let frame = hex::decode(&entry.frame).unwrap_or_default();
+    if frame.is_empty() {
+        println!("   (contents withheld)");
+    } else if entry.truncated {
+        println!("   (first {} of {} bytes) {}", frame.len(), entry.len, entry.frame);
+    } else {
+        match Message::decode(&mut frame.as_slice()) {
+            Ok(msg) => println!("   {:?}", msg),
+            Err(e) => println!("   (undecodable: {}) {}", e, entry.frame),
+        }
+    }
 }
 
 impl CliCommand for Decode {
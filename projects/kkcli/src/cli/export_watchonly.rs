@@ -0,0 +1,90 @@
+use crate::server::cache::DeviceCache;
+use crate::server::watch_only::{self, WatchOnlyFormat};
+use crate::transport::ProtocolAdapter;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::path::PathBuf;
+
+/// Write a watch-only wallet (descriptors and account labels) for Bitcoin
+/// Core, Electrum, Sparrow or as plain JSON, from the xpubs cached by
+/// `kkcli server`; the device doesn't need to be connected
+#[derive(Parser, Debug, Clone)]
+pub struct ExportWatchonly {
+    /// Output format
+    #[clap(value_enum, short, long, default_value = "json")]
+    pub format: WatchOnlyFormat,
+
+    /// Account for single-account formats (electrum, sparrow), e.g. m/84'/0'/0'
+    #[clap(short, long)]
+    pub account: Option<String>,
+
+    /// Device whose xpubs to export; defaults to the first cached device
+    #[clap(long)]
+    pub device_id: Option<String>,
+
+    /// Master key fingerprint (hex) for the descriptors' key origin, if the
+    /// cache doesn't have it
+    #[clap(long)]
+    pub fingerprint: Option<String>,
+
+    /// Unix time Bitcoin Core rescans from (core format); 0 scans the whole chain
+    #[clap(long, default_value_t = 0)]
+    pub rescan_from: u64,
+
+    /// Write to this file instead of stdout
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl super::CliCommand for ExportWatchonly {
+    fn handle(self, _protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        // Reads the cache only and is run from main.rs
+        println!("ExportWatchonly command should be handled in main.rs with async runtime");
+        Ok(())
+    }
+}
+
+impl ExportWatchonly {
+    pub async fn run(self) -> Result<()> {
+        let cache = DeviceCache::open()?;
+        let device_id = match self.device_id.clone() {
+            Some(id) => id,
+            None => cache
+                .get_first_device_from_db()
+                .await?
+                .ok_or_else(|| anyhow!("No device in the cache; start `kkcli server` with the device connected first"))?,
+        };
+
+        let xpubs = cache.get_cached_xpubs(&device_id).await?;
+        let fingerprint = match &self.fingerprint {
+            Some(fp) if fp.len() == 8 && hex::decode(fp).is_ok() => Some(fp.to_ascii_lowercase()),
+            Some(fp) => return Err(anyhow!("Invalid fingerprint {:?}; expected 8 hex characters", fp)),
+            None => watch_only::master_fingerprint(&xpubs),
+        };
+        if fingerprint.is_none() {
+            eprintln!("Note: master fingerprint unknown; descriptors have no key origin (pass --fingerprint to add it)");
+        }
+
+        let label = cache
+            .load_device(&device_id)
+            .await?
+            .and_then(|features| features.label)
+            .filter(|label| !label.trim().is_empty())
+            .unwrap_or_else(|| "KeepKey".to_string());
+        let wallet = watch_only::build_wallet(&label, &xpubs, fingerprint)?;
+        let contents = watch_only::render(&wallet, self.format, self.account.as_deref(), self.rescan_from)?;
+
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, &contents).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+                eprintln!(
+                    "Wrote watch-only wallet with {} account(s) to {}",
+                    if self.format.single_account() { 1 } else { wallet.accounts.len() },
+                    path.display()
+                );
+            }
+            None => print!("{}", contents),
+        }
+        Ok(())
+    }
+}
@@ -1,18 +1,30 @@
+pub mod await_payment;
+pub mod daemon;
 pub mod decode;
+pub mod export_watchonly;
 pub mod list;
 mod macros;
 pub mod parsers;
+pub mod send_many;
 pub mod system;
 pub mod types;
 pub mod utxo;
 pub mod server;
+pub mod test;
+pub mod watch;
 
+use await_payment::*;
+use daemon::*;
 use decode::*;
+use export_watchonly::*;
 use list::*;
+use send_many::*;
 pub(crate) use macros::*;
 use system::*;
 use utxo::*;
 use server::*;
+use test::*;
+use watch::*;
 
 use crate::transport::ProtocolAdapter;
 use anyhow::Result;
@@ -94,6 +106,12 @@ use_cli_subcommands! {
     List,
     Decode,
     Server,
+    Watch,
+    AwaitPayment,
+    SendMany,
+    Daemon,
+    ExportWatchonly,
+    Test,
     Ping,
     GetFeatures,
     ListCoins,
@@ -117,8 +135,11 @@ use_cli_subcommands! {
     DebugLinkFillConfig,
     SignIdentity,
     SignTx,
+    SignPsbt,
     ChangeWipeCode,
     FlashHash,
     FlashWrite,
     SoftReset,
+    Selftest,
+    Attest,
 }
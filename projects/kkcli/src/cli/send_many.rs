@@ -0,0 +1,258 @@
+use crate::server::routes::{SendManyOutput, SendManyRequest};
+use crate::transport::ProtocolAdapter;
+use anyhow::{anyhow, Context, Result};
+use bitcoin::{Amount, Denomination};
+use clap::Parser;
+use serde::Deserialize;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// Pay every row of a CSV file in one transaction through a running
+/// `kkcli server`. The batch is previewed first and only signed once confirmed.
+#[derive(Parser, Debug, Clone)]
+pub struct SendMany {
+    /// Payouts, one per line: address, amount in BTC and an optional label.
+    /// A header line and lines starting with # are skipped.
+    #[clap(long)]
+    pub csv: PathBuf,
+
+    /// Base URL of the server
+    #[clap(short, long, default_value = "http://127.0.0.1:1646")]
+    pub url: String,
+
+    /// API key of a client paired with the `sign` scope
+    #[clap(long)]
+    pub api_key: Option<String>,
+
+    /// Fee rate in sat/vB; defaults to the backend estimate for --conf-target
+    #[clap(long)]
+    pub fee_rate: Option<f64>,
+
+    /// Blocks to confirm within when no fee rate is given
+    #[clap(long)]
+    pub conf_target: Option<u32>,
+
+    /// Script type of the change address (p2wpkh, p2sh-p2wpkh, p2pkh or p2tr)
+    #[clap(long)]
+    pub change_script_type: Option<String>,
+
+    /// Also spend unconfirmed outputs
+    #[clap(long)]
+    pub include_unconfirmed: bool,
+
+    /// Only show the preview
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Sign without asking for confirmation
+    #[clap(short, long)]
+    pub yes: bool,
+
+    /// Sign, but print the transaction instead of broadcasting it
+    #[clap(long)]
+    pub no_broadcast: bool,
+
+    /// Print the server's responses as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Preview {
+    outputs: Vec<PreviewOutput>,
+    change: Option<Change>,
+    fee_rate: f64,
+    vsize: u64,
+    inputs: usize,
+    formatted_total: String,
+    formatted_fee: String,
+    formatted_unit: String,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PreviewOutput {
+    index: usize,
+    label: Option<String>,
+    address: String,
+    formatted_amount: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Change {
+    path: String,
+    formatted_amount: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Sent {
+    serialized_tx: String,
+    txid: Option<String>,
+}
+
+impl super::CliCommand for SendMany {
+    fn handle(self, _protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        // Talks to a running server, which owns the device; run from main.rs
+        println!("SendMany command should be handled in main.rs with async runtime");
+        Ok(())
+    }
+}
+
+impl SendMany {
+    pub async fn run(self) -> Result<()> {
+        let file = std::fs::File::open(&self.csv).with_context(|| format!("could not open {}", self.csv.display()))?;
+        let outputs = read_payouts(std::io::BufReader::new(file))?;
+        let mut request = SendManyRequest {
+            outputs,
+            fee_rate: self.fee_rate,
+            conf_target: self.conf_target,
+            include_unconfirmed: self.include_unconfirmed.then_some(true),
+            change_script_type: self.change_script_type.clone(),
+            broadcast: None,
+        };
+        let client = reqwest::Client::new();
+
+        let preview = self.post(&client, "/api/v2/send-many/preview", &request).await?;
+        if self.json {
+            println!("{}", preview);
+        } else {
+            print_preview(&serde_json::from_value(preview)?);
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+        if !self.yes && !confirm("Sign this transaction on the KeepKey?")? {
+            return Err(anyhow!("cancelled"));
+        }
+
+        request.broadcast = Some(!self.no_broadcast);
+        eprintln!("Confirm the transaction on the device...");
+        let response = self.post(&client, "/api/v2/send-many", &request).await?;
+        if self.json {
+            println!("{}", response);
+            return Ok(());
+        }
+        let sent: Sent = serde_json::from_value(response)?;
+        match sent.txid {
+            Some(txid) => println!("{}", txid),
+            None => println!("{}", sent.serialized_tx),
+        }
+        Ok(())
+    }
+
+    async fn post(&self, client: &reqwest::Client, path: &str, request: &SendManyRequest) -> Result<serde_json::Value> {
+        let url = format!("{}{}", self.url.trim_end_matches('/'), path);
+        let mut builder = client.post(&url).json(request);
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| anyhow!("could not reach {} ({}); is `kkcli server` running?", url, e))?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        if !status.is_success() {
+            let message = body["message"].as_str().or_else(|| body["error"].as_str()).unwrap_or("no details");
+            return Err(anyhow!("{} failed ({}): {}", path, status, message));
+        }
+        Ok(body)
+    }
+}
+
+/// Payouts from `address,amount[,label]` lines, amounts in BTC
+fn read_payouts(reader: impl BufRead) -> Result<Vec<SendManyOutput>> {
+    let mut outputs = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv_line(line);
+        if outputs.is_empty() && fields[0].eq_ignore_ascii_case("address") {
+            continue;
+        }
+        if fields.len() < 2 || fields.len() > 3 {
+            return Err(anyhow!("line {}: expected address,amount[,label]", i + 1));
+        }
+        let amount_sats = Amount::from_str_in(&fields[1], Denomination::Bitcoin)
+            .map_err(|e| anyhow!("line {}: invalid BTC amount {:?}: {}", i + 1, fields[1], e))?
+            .to_sat();
+        outputs.push(SendManyOutput {
+            address: fields[0].clone(),
+            amount_sats,
+            label: fields.get(2).filter(|l| !l.is_empty()).cloned(),
+        });
+    }
+    if outputs.is_empty() {
+        return Err(anyhow!("no payouts in the file"));
+    }
+    Ok(outputs)
+}
+
+/// Fields of one CSV line; double quotes allow commas in a field and `""` is a quote
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+fn print_preview(preview: &Preview) {
+    let unit = if preview.formatted_unit == "btc" { "BTC" } else { preview.formatted_unit.as_str() };
+    println!("{:>4}  {:<24} {:<62} {:>16}", "#", "Label", "Address", "Amount");
+    for output in &preview.outputs {
+        println!(
+            "{:>4}  {:<24} {:<62} {:>16} {}",
+            output.index + 1,
+            output.label.as_deref().unwrap_or("-"),
+            output.address,
+            output.formatted_amount,
+            unit
+        );
+    }
+    match &preview.change {
+        Some(change) => println!("{:>4}  {:<24} {:<62} {:>16} {}", "", "change", change.path, change.formatted_amount, unit),
+        None => println!("      (no change; the remainder goes to the fee)"),
+    }
+    println!(
+        "Total {} {} to {} recipient(s), fee {} {} ({} sat/vB, {} vB, {} input(s))",
+        preview.formatted_total,
+        unit,
+        preview.outputs.len(),
+        preview.formatted_fee,
+        unit,
+        preview.fee_rate,
+        preview.vsize,
+        preview.inputs
+    );
+    for warning in &preview.warnings {
+        println!("Warning: {}", warning);
+    }
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
+}
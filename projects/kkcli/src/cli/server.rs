@@ -17,7 +17,18 @@ pub struct Server {
     #[clap(long)]
     pub daemon: bool,
     
-    // Removed allow_mock field as per Pioneer Guild Guidelines - "NEVER MOCK ANYTHING"
+    /// Serve the full API from a simulated KeepKey (test seed, fixture balances,
+    /// canned signatures); also on when KEEPKEY_DEMO_MODE=1
+    #[clap(long)]
+    pub mock_device: bool,
+    
+    /// Play back a recorded device session (JSON from a support bundle) on the simulated device
+    #[clap(long, value_name = "FILE")]
+    pub replay: Option<std::path::PathBuf>,
+    
+    /// Keep the recorded delays between requests and device answers when replaying
+    #[clap(long)]
+    pub replay_realtime: bool,
 }
 
 impl super::CliCommand for Server {
@@ -43,11 +54,32 @@ impl Server {
             // The main.rs file will handle the actual initialization
         }
         
+        // Fails fast with the other server's PID and port instead of a bind error later
+        let _lock = crate::server::instance_lock::InstanceLock::acquire(self.port)?;
+        
         println!("Starting KeepKey CLI server on port {}", self.port);
         println!("Press Ctrl+C to stop the server");
         
-        // Start the server - no more allow_mock as per Pioneer Guild Guidelines
-        crate::server::start_server(self.port).await?;
+        if let Some(path) = &self.replay {
+            let session = crate::transport::DeviceSession::load(path)?;
+            println!(
+                "Replaying {} recorded frames from {}{}",
+                session.frames.len(),
+                path.display(),
+                session.operation.as_deref().map(|o| format!(" ({})", o)).unwrap_or_default()
+            );
+            let device = crate::transport::ReplayDevice::new(session).with_realtime(self.replay_realtime);
+            crate::server::set_replay_device(device);
+        }
+        
+        let demo_mode = std::env::var("KEEPKEY_DEMO_MODE")
+            .is_ok_and(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "on"));
+        let mock_device = self.mock_device || self.replay.is_some() || demo_mode;
+        if mock_device {
+            println!("⚠️  SIMULATED DEVICE: no KeepKey is used, addresses are from the public test seed");
+        }
+        
+        crate::server::start_server(self.port, mock_device).await?;
         
         Ok(())
     }
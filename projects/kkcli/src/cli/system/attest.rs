@@ -0,0 +1,188 @@
+use crate::{
+    cli::{
+        expect_field, expect_message,
+        parsers::Bip32PathParser,
+        types::Bip32Path,
+        CliCommand,
+    },
+    messages::{self, Message},
+    transport::ProtocolAdapter,
+};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Release manifest shipped with kkcli; its `hashes` section lists every
+/// official bootloader and firmware build
+const RELEASES_MANIFEST: &str = include_str!("../../../firmware/releases.json");
+const ATTESTATION_HEADER: &str = "KeepKey attestation v1";
+
+#[derive(Debug, Default, Deserialize)]
+struct ReleaseHashes {
+    #[serde(default)]
+    bootloader: HashMap<String, String>,
+    #[serde(default)]
+    firmware: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleasesManifest {
+    #[serde(default)]
+    hashes: ReleaseHashes,
+}
+
+/// Measured state of the device, compared against the release manifest
+#[derive(Debug, Clone, Serialize)]
+pub struct AttestationReport {
+    pub timestamp: String,
+    pub device_id: Option<String>,
+    pub model: Option<String>,
+    pub firmware_version: String,
+    pub revision: Option<String>,
+    pub bootloader_hash: Option<String>,
+    /// Official release the bootloader hash belongs to
+    pub bootloader_release: Option<String>,
+    pub firmware_hash: Option<String>,
+    /// Official release the firmware hash belongs to
+    pub firmware_release: Option<String>,
+    /// Both hashes match official releases and the firmware reports the version its hash belongs to
+    pub official: bool,
+    pub nonce: String,
+}
+
+impl AttestationReport {
+    /// Text that gets signed. One `key: value` per line so it stays readable
+    /// when archived and can be checked with any Bitcoin message verifier.
+    pub fn statement(&self) -> String {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+        [
+            ATTESTATION_HEADER.to_string(),
+            format!("timestamp: {}", self.timestamp),
+            format!("device_id: {}", opt(&self.device_id)),
+            format!("model: {}", opt(&self.model)),
+            format!("firmware_version: {}", self.firmware_version),
+            format!("revision: {}", opt(&self.revision)),
+            format!("bootloader_hash: {}", opt(&self.bootloader_hash)),
+            format!("bootloader_release: {}", opt(&self.bootloader_release)),
+            format!("firmware_hash: {}", opt(&self.firmware_hash)),
+            format!("firmware_release: {}", opt(&self.firmware_release)),
+            format!("official: {}", self.official),
+            format!("nonce: {}", self.nonce),
+        ]
+        .join("\n")
+    }
+}
+
+/// The archived attestation: the signed statement plus what signed it
+#[derive(Debug, Clone, Serialize)]
+pub struct Attestation {
+    pub report: AttestationReport,
+    pub statement: String,
+    pub address: String,
+    /// Base64 Bitcoin message signature over `statement`
+    pub signature: String,
+}
+
+fn measure(features: messages::Features) -> Result<AttestationReport> {
+    let manifest: ReleasesManifest = serde_json::from_str(RELEASES_MANIFEST)
+        .map_err(|e| anyhow!("embedded releases.json is invalid: {}", e))?;
+    let release = |map: &HashMap<String, String>, hash: &Option<String>| {
+        hash.as_ref()
+            .and_then(|h| map.get(h))
+            .map(|v| v.trim_start_matches('v').to_string())
+    };
+
+    let firmware_version = format!(
+        "{}.{}.{}",
+        features.major_version.unwrap_or(0),
+        features.minor_version.unwrap_or(0),
+        features.patch_version.unwrap_or(0)
+    );
+    let bootloader_hash = features.bootloader_hash.as_ref().map(hex::encode);
+    let firmware_hash = features.firmware_hash.as_ref().map(hex::encode);
+    let bootloader_release = release(&manifest.hashes.bootloader, &bootloader_hash);
+    let firmware_release = release(&manifest.hashes.firmware, &firmware_hash);
+    let official = bootloader_release.is_some()
+        && firmware_release.as_deref() == Some(firmware_version.as_str());
+
+    let mut nonce = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    Ok(AttestationReport {
+        timestamp: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        device_id: features.device_id,
+        model: features.model,
+        firmware_version,
+        revision: features.revision.map(|r| {
+            std::str::from_utf8(&r).map_or_else(|_| hex::encode(&r), |x| x.to_owned())
+        }),
+        bootloader_hash,
+        bootloader_release,
+        firmware_hash,
+        firmware_release,
+        official,
+        nonce: hex::encode(nonce),
+    })
+}
+
+/// Produce a signed statement of the device's bootloader and firmware hashes
+/// that can be archived as proof it ran official firmware at this time
+#[derive(Debug, Clone, Args)]
+pub struct Attest {
+    /// BIP-32 path of the key that signs the attestation
+    #[clap(short = 'n', long, value_parser = Bip32PathParser, default_value = "m/44'/0'/0'/0/0")]
+    address: Bip32Path,
+    /// write the attestation JSON to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<std::path::PathBuf>,
+}
+
+impl CliCommand for Attest {
+    fn handle(self, protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        let features = expect_message!(
+            Message::Features,
+            protocol_adapter.handle(messages::GetFeatures::default().into())
+        )?;
+        if features.bootloader_mode.unwrap_or(false) {
+            return Err(anyhow!("device is in bootloader mode; attestation needs the firmware to sign"));
+        }
+
+        let report = measure(features)?;
+        if !report.official {
+            eprintln!("WARNING: device hashes do not match an official release; the attestation will say so");
+        }
+
+        let statement = report.statement();
+        let resp = expect_message!(
+            Message::MessageSignature,
+            protocol_adapter.with_standard_handler().handle(
+                messages::SignMessage {
+                    address_n: self.address.into(),
+                    message: statement.clone().into_bytes(),
+                    coin_name: Some("Bitcoin".to_string()),
+                    script_type: None,
+                }
+                .into(),
+            )
+        )?;
+
+        let attestation = Attestation {
+            report,
+            statement,
+            address: expect_field!(resp.address)?.to_string(),
+            signature: base64::encode(expect_field!(resp.signature)?),
+        };
+        let json = serde_json::to_string_pretty(&attestation)?;
+
+        match self.output {
+            Some(path) => {
+                std::fs::write(&path, json)?;
+                println!("Attestation written to {}", path.display());
+            }
+            None => println!("{}", json),
+        }
+        Ok(())
+    }
+}
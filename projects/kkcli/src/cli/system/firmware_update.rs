@@ -1,6 +1,7 @@
 use crate::{
     cli::{expect_success, CliCommand},
     messages,
+    server::progress::{Progress, ProgressOperation, UpdateStage},
     transport::ProtocolAdapter,
 };
 use anyhow::Result;
@@ -19,22 +20,50 @@ pub struct FirmwareUpdate {
 impl CliCommand for FirmwareUpdate {
     fn handle(self, protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
         let payload = std::fs::read(self.file_path)?;
+        let len = payload.len() as u64;
+
+        let step = |stage, current, total| Progress::update(ProgressOperation::FirmwareUpdate, stage, current, total);
+
+        let payload_hash = Sha256::digest(&payload).to_vec();
+        println!(
+            "{}",
+            step(UpdateStage::VerifyingHash, 1, 1)
+                .with_message(format!("SHA-256 {}", hex::encode(&payload_hash)))
+                .render_bar()
+        );
 
         if !self.skip_erase {
-            println!("Erasing firmware...");
+            println!(
+                "{}",
+                step(UpdateStage::Erasing, 1, 1)
+                    .with_message("Erasing firmware...")
+                    .render_bar()
+            );
             expect_success!(protocol_adapter
                 .with_standard_handler()
                 .handle(messages::FirmwareErase::default().into()),)?;
         }
 
-        println!("Uploading firmware...");
+        println!(
+            "{}",
+            step(UpdateStage::Uploading, 0, len)
+                .with_message("Uploading firmware...")
+                .render_bar()
+        );
         expect_success!(protocol_adapter.with_standard_handler().handle(
             messages::FirmwareUpload {
-                payload_hash: Sha256::digest(&payload).to_vec(),
+                payload_hash,
                 payload,
             }
             .into()
         ),)?;
+        println!("{}", step(UpdateStage::Uploading, len, len).render_bar());
+        println!(
+            "{}",
+            step(UpdateStage::Rebooting, 1, 1)
+                .with_message("Device is restarting with the new firmware")
+                .render_bar()
+        );
 
         Ok(())
     }
@@ -19,7 +19,7 @@ pub struct GetPublicKey {
     address: Bip32Path,
     #[clap(long)]
     ecdsa_curve_name: Option<String>,
-    /// Confirm address on device screen
+    /// Show the xpub on the device screen so it can be compared with what the host prints
     #[clap(short = 'd', long, action = SetTrue)]
     show_display: Option<bool>,
     #[clap(short, long)]
@@ -28,8 +28,18 @@ pub struct GetPublicKey {
     script_type: Option<ScriptType>,
 }
 
+/// Last eight characters of an xpub in two groups of four. They cover the
+/// Base58Check checksum, so a swapped key won't match what the device shows.
+pub fn xpub_check_code(xpub: &str) -> String {
+    let chars: Vec<char> = xpub.chars().collect();
+    let tail = &chars[chars.len().saturating_sub(8)..];
+    let (first, second) = tail.split_at(tail.len().saturating_sub(4));
+    format!("{} {}", first.iter().collect::<String>(), second.iter().collect::<String>())
+}
+
 impl CliCommand for GetPublicKey {
     fn handle(self, protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        let shown_on_device = self.show_display.unwrap_or(false);
         let resp = expect_message!(
             Message::PublicKey,
             protocol_adapter.with_standard_handler().handle(
@@ -44,7 +54,16 @@ impl CliCommand for GetPublicKey {
             )
         )?;
 
-        println!("{}", expect_field!(resp.xpub)?);
+        let xpub = expect_field!(resp.xpub)?;
+        println!("{}", xpub);
+
+        // Verification hints go to stderr so scripts can keep capturing stdout
+        eprintln!("  check code: {}", xpub_check_code(xpub));
+        if shown_on_device {
+            eprintln!("Check that the xpub on your KeepKey ends with the same characters before exporting it.");
+        } else {
+            eprintln!("Not verified on the device; run again with --show-display to compare it on screen.");
+        }
 
         Ok(())
     }
@@ -1,6 +1,7 @@
 use crate::{
     cli::{expect_success, parsers::TypedPossibleValuesParser, CliCommand},
     messages::{self, Message},
+    server::progress::{Progress, ProgressOperation},
     transport::ProtocolAdapter,
 };
 use anyhow::Result;
@@ -40,17 +41,30 @@ pub struct RecoveryDevice {
 impl CliCommand for RecoveryDevice {
     fn handle(self, protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
         let mut printed_char_req_msg = false;
+        let word_count = self.word_count as u64;
         expect_success!(protocol_adapter
             .with_standard_handler()
             .with_mut_handler(&mut |msg| match msg {
-                Message::CharacterRequest(messages::CharacterRequest { character_pos, .. }) => {
+                Message::CharacterRequest(messages::CharacterRequest {
+                    word_pos,
+                    character_pos,
+                }) => {
                     if !printed_char_req_msg {
-                        print!(
-                            "Enter your mnemonic using the cipher shown on your device screen: "
+                        println!(
+                            "Enter your mnemonic using the cipher shown on your device screen:"
                         );
-                        stdout().flush().unwrap();
                         printed_char_req_msg = true;
                     }
+                    if *character_pos == 0 {
+                        let word = Progress::new(
+                            ProgressOperation::Recovery,
+                            "word",
+                            *word_pos as u64 + 1,
+                            word_count,
+                        );
+                        print!("\r{}", word.render_bar());
+                        stdout().flush().unwrap();
+                    }
                     Ok(Some((|| -> crossterm::Result<Message> {
                         loop {
                             match crossterm::event::read()? {
@@ -1,4 +1,5 @@
 mod apply_policy;
+mod attest;
 mod apply_settings;
 mod change_pin;
 mod change_wipe_code;
@@ -9,10 +10,12 @@ mod firmware_update;
 pub mod info;
 pub mod initialize;
 mod manufacturing;
+mod selftest;
 mod sign_identity;
 mod wipe_device;
 
 pub use apply_policy::*;
+pub use attest::*;
 pub use apply_settings::*;
 pub use change_pin::*;
 pub use change_wipe_code::*;
@@ -23,5 +26,6 @@ pub use firmware_update::*;
 pub use info::*;
 pub use initialize::*;
 pub use manufacturing::*;
+pub use selftest::*;
 pub use sign_identity::*;
 pub use wipe_device::*;
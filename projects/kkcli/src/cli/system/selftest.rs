@@ -0,0 +1,234 @@
+use crate::{
+    cli::CliCommand,
+    messages::{self, Message},
+    transport::ProtocolAdapter,
+};
+use anyhow::{anyhow, Result};
+use clap::{ArgAction::SetTrue, Args};
+use serde::Serialize;
+use std::time::Instant;
+use utoipa::ToSchema;
+
+const SELFTEST_PING_MESSAGE: &str = "kkcli selftest";
+const SELFTEST_ENTROPY_SIZE: u32 = 32;
+// m/44'/0'/0'/0/0
+const SELFTEST_ADDRESS_PATH: [u32; 5] = [0x8000002c, 0x80000000, 0x80000000, 0, 0];
+// Start of flash; only manufacturing firmware answers FlashHash
+const SELFTEST_FLASH_ADDRESS: u32 = 0x0800_0000;
+const SELFTEST_FLASH_LENGTH: u32 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SelftestStatus {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SelftestStep {
+    pub name: String,
+    pub status: SelftestStatus,
+    pub duration_ms: u64,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SelftestReport {
+    pub passed: bool,
+    pub total_ms: u64,
+    pub steps: Vec<SelftestStep>,
+}
+
+/// Options for the scripted self-test sequence
+#[derive(Debug, Clone, Default)]
+pub struct SelftestOptions {
+    /// Skip steps that require a button press on the device
+    pub no_button: bool,
+}
+
+fn record_step(
+    steps: &mut Vec<SelftestStep>,
+    name: &str,
+    started: Instant,
+    result: Result<Option<String>>,
+) {
+    let (status, detail) = match result {
+        Ok(detail) => (SelftestStatus::Pass, detail),
+        Err(e) => (SelftestStatus::Fail, Some(e.to_string())),
+    };
+    steps.push(SelftestStep {
+        name: name.to_string(),
+        status,
+        duration_ms: started.elapsed().as_millis() as u64,
+        detail,
+    });
+}
+
+fn run_step(
+    steps: &mut Vec<SelftestStep>,
+    name: &str,
+    f: impl FnOnce() -> Result<Option<String>>,
+) {
+    let started = Instant::now();
+    let result = f();
+    record_step(steps, name, started, result);
+}
+
+fn skip_step(steps: &mut Vec<SelftestStep>, name: &str, reason: &str) {
+    steps.push(SelftestStep {
+        name: name.to_string(),
+        status: SelftestStatus::Skipped,
+        duration_ms: 0,
+        detail: Some(reason.to_string()),
+    });
+}
+
+/// Run the self-test sequence (Ping, GetEntropy, GetAddress, FlashHash) and
+/// report pass/fail per step. Steps keep running after a failure so the report
+/// shows everything that is broken, not just the first thing.
+pub fn run_selftest(
+    protocol_adapter: &mut dyn ProtocolAdapter,
+    options: &SelftestOptions,
+) -> SelftestReport {
+    let started = Instant::now();
+    let mut steps = Vec::new();
+
+    if options.no_button {
+        skip_step(&mut steps, "ping", "button steps disabled");
+    } else {
+        run_step(&mut steps, "ping", || {
+            match protocol_adapter.with_standard_handler().handle(
+                messages::Ping {
+                    message: Some(SELFTEST_PING_MESSAGE.to_string()),
+                    button_protection: Some(true),
+                    pin_protection: None,
+                    passphrase_protection: None,
+                    wipe_code_protection: None,
+                }
+                .into(),
+            )? {
+                Message::Success(resp) if resp.message() == SELFTEST_PING_MESSAGE => Ok(None),
+                Message::Success(resp) => Err(anyhow!("ping echoed {:?}", resp.message)),
+                other => Err(anyhow!("unexpected message ({:?})", other.message_type())),
+            }
+        });
+    }
+
+    run_step(&mut steps, "get_entropy", || {
+        match protocol_adapter.with_standard_handler().handle(
+            messages::GetEntropy {
+                size: SELFTEST_ENTROPY_SIZE,
+            }
+            .into(),
+        )? {
+            Message::Entropy(resp) if resp.entropy.len() == SELFTEST_ENTROPY_SIZE as usize => {
+                Ok(Some(format!("{} bytes", resp.entropy.len())))
+            }
+            Message::Entropy(resp) => Err(anyhow!(
+                "expected {} bytes of entropy, got {}",
+                SELFTEST_ENTROPY_SIZE,
+                resp.entropy.len()
+            )),
+            other => Err(anyhow!("unexpected message ({:?})", other.message_type())),
+        }
+    });
+
+    let show_display = !options.no_button;
+    run_step(&mut steps, "get_address", || {
+        match protocol_adapter.with_standard_handler().handle(
+            messages::GetAddress {
+                coin_name: Some("Bitcoin".to_string()),
+                address_n: SELFTEST_ADDRESS_PATH.to_vec(),
+                script_type: Some(messages::InputScriptType::Spendaddress as i32),
+                show_display: Some(show_display),
+                multisig: None,
+            }
+            .into(),
+        )? {
+            Message::Address(resp) if !resp.address.is_empty() => Ok(Some(resp.address)),
+            Message::Address(_) => Err(anyhow!("device returned empty address")),
+            other => Err(anyhow!("unexpected message ({:?})", other.message_type())),
+        }
+    });
+
+    let flash_started = Instant::now();
+    let flash_result = protocol_adapter.handle(
+        messages::FlashHash {
+            address: Some(SELFTEST_FLASH_ADDRESS),
+            length: Some(SELFTEST_FLASH_LENGTH),
+            challenge: None,
+        }
+        .into(),
+    );
+    match flash_result {
+        // Release firmware rejects manufacturing messages; that isn't a failure
+        Ok(Message::Failure(_)) => skip_step(&mut steps, "flash_hash", "not supported by firmware"),
+        Ok(Message::FlashHashResponse(resp)) => {
+            record_step(&mut steps, "flash_hash", flash_started, Ok(resp.data.map(hex::encode)))
+        }
+        Ok(other) => record_step(
+            &mut steps,
+            "flash_hash",
+            flash_started,
+            Err(anyhow!("unexpected message ({:?})", other.message_type())),
+        ),
+        Err(e) => record_step(&mut steps, "flash_hash", flash_started, Err(e)),
+    }
+
+    SelftestReport {
+        passed: steps.iter().all(|s| s.status != SelftestStatus::Fail),
+        total_ms: started.elapsed().as_millis() as u64,
+        steps,
+    }
+}
+
+/// Run a scripted device self-test and report pass/fail per step
+#[derive(Debug, Clone, Args)]
+pub struct Selftest {
+    /// skip steps that need a button press (no display confirmation)
+    #[clap(long, action = SetTrue)]
+    no_button: bool,
+    /// print the report as JSON
+    #[clap(long, action = SetTrue)]
+    json: bool,
+}
+
+impl CliCommand for Selftest {
+    fn handle(self, protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        let report = run_selftest(
+            protocol_adapter,
+            &SelftestOptions {
+                no_button: self.no_button,
+            },
+        );
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            for step in &report.steps {
+                let status = match step.status {
+                    SelftestStatus::Pass => "PASS",
+                    SelftestStatus::Fail => "FAIL",
+                    SelftestStatus::Skipped => "SKIP",
+                };
+                println!(
+                    "{:<4} {:<12} {:>6}ms  {}",
+                    status,
+                    step.name,
+                    step.duration_ms,
+                    step.detail.as_deref().unwrap_or("")
+                );
+            }
+            println!("total {}ms", report.total_ms);
+        }
+
+        if report.passed {
+            Ok(())
+        } else {
+            Err(anyhow!("selftest failed"))
+        }
+    }
+}
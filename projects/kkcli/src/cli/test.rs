@@ -0,0 +1,313 @@
+use crate::{
+    cli::CliCommand,
+    messages::{self, Message},
+    transport::{HidTransport, ProtocolAdapter, UsbTransport},
+};
+use anyhow::{anyhow, Result};
+use clap::{ArgAction::SetTrue, Args, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// Hardware test utilities
+#[derive(Debug, Clone, Args)]
+pub struct Test {
+    #[clap(subcommand)]
+    command: TestCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum TestCommand {
+    Soak(Soak),
+}
+
+impl Test {
+    pub fn handle(self) -> Result<()> {
+        match self.command {
+            TestCommand::Soak(x) => x.run(),
+        }
+    }
+}
+
+impl CliCommand for Test {
+    fn handle(self, _: &mut dyn ProtocolAdapter) -> Result<()> {
+        unreachable!();
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SoakTransport {
+    Usb,
+    Hid,
+    Both,
+}
+
+/// Hammer the device with interleaved GetFeatures/GetAddress/Ping and report
+/// error rates, latency percentiles and reconnect behavior
+#[derive(Debug, Clone, Args)]
+pub struct Soak {
+    /// number of requests to send
+    #[clap(short = 'n', long, default_value_t = 100)]
+    iterations: u32,
+    /// transport(s) to exercise; `both` alternates USB and HID every request
+    #[clap(value_enum, short, long, default_value_t = SoakTransport::Both)]
+    transport: SoakTransport,
+    /// pause between requests, in milliseconds
+    #[clap(long, default_value_t = 0)]
+    delay_ms: u64,
+    /// print the report as JSON
+    #[clap(long, action = SetTrue)]
+    json: bool,
+}
+
+const SOAK_OPS: [&str; 3] = ["GetFeatures", "GetAddress", "Ping"];
+// m/44'/0'/0'/0/0
+const SOAK_ADDRESS_PATH: [u32; 5] = [0x8000002c, 0x80000000, 0x80000000, 0, 0];
+
+#[derive(Debug, Default, Serialize)]
+struct LatencySummary {
+    count: usize,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct SoakStats {
+    requests: u32,
+    errors: u32,
+    error_rate: f64,
+    latency: LatencySummary,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct RecoveryStats {
+    /// transport had to be reopened after an error
+    attempts: u32,
+    /// reopen succeeded
+    recovered: u32,
+    /// slowest successful reopen
+    max_recovery_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct SoakReport {
+    iterations: u32,
+    elapsed_ms: f64,
+    by_operation: BTreeMap<String, SoakStats>,
+    by_transport: BTreeMap<SoakTransport, SoakStats>,
+    recovery: RecoveryStats,
+    errors: BTreeMap<String, u32>,
+}
+
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((pct / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+#[derive(Default)]
+struct Samples {
+    latencies: Vec<f64>,
+    requests: u32,
+    errors: u32,
+}
+
+impl Samples {
+    fn summarize(mut self) -> SoakStats {
+        self.latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        SoakStats {
+            requests: self.requests,
+            errors: self.errors,
+            error_rate: if self.requests == 0 {
+                0.0
+            } else {
+                self.errors as f64 / self.requests as f64
+            },
+            latency: LatencySummary {
+                count: self.latencies.len(),
+                p50_ms: percentile(&self.latencies, 50.0),
+                p90_ms: percentile(&self.latencies, 90.0),
+                p99_ms: percentile(&self.latencies, 99.0),
+                max_ms: self.latencies.last().copied().unwrap_or_default(),
+            },
+        }
+    }
+}
+
+fn open_transport(kind: SoakTransport) -> Result<Box<dyn ProtocolAdapter>> {
+    Ok(match kind {
+        SoakTransport::Usb => {
+            let device = crate::server::try_get_device()?;
+            let (transport, _, _) = UsbTransport::new(&device, 0).map_err(|e| anyhow!(e))?;
+            Box::new(transport)
+        }
+        SoakTransport::Hid => Box::new(HidTransport::new(None)?),
+        SoakTransport::Both => unreachable!(),
+    })
+}
+
+fn soak_request(adapter: &mut dyn ProtocolAdapter, op: &str) -> Result<()> {
+    let msg: Message = match op {
+        "GetFeatures" => messages::GetFeatures {}.into(),
+        "GetAddress" => messages::GetAddress {
+            coin_name: Some("Bitcoin".to_string()),
+            address_n: SOAK_ADDRESS_PATH.to_vec(),
+            script_type: None,
+            show_display: Some(false),
+            multisig: None,
+        }
+        .into(),
+        _ => messages::Ping {
+            message: Some("soak".to_string()),
+            ..Default::default()
+        }
+        .into(),
+    };
+
+    match (op, adapter.handle(msg)?) {
+        ("GetFeatures", Message::Features(_)) => Ok(()),
+        ("GetAddress", Message::Address(_)) => Ok(()),
+        ("Ping", Message::Success(_)) => Ok(()),
+        (_, other) => Err(anyhow!("unexpected message ({:?})", other.message_type())),
+    }
+}
+
+impl Soak {
+    pub fn run(self) -> Result<()> {
+        let started = Instant::now();
+        let mut by_op: BTreeMap<String, Samples> = BTreeMap::new();
+        let mut by_transport: BTreeMap<SoakTransport, Samples> = BTreeMap::new();
+        let mut recovery = RecoveryStats::default();
+        let mut errors: BTreeMap<String, u32> = BTreeMap::new();
+
+        let mut current: Option<(SoakTransport, Box<dyn ProtocolAdapter>)> = None;
+        let mut needs_recovery = false;
+
+        for i in 0..self.iterations {
+            let kind = match self.transport {
+                SoakTransport::Both if i % 2 == 0 => SoakTransport::Usb,
+                SoakTransport::Both => SoakTransport::Hid,
+                other => other,
+            };
+            let op = SOAK_OPS[(i as usize) % SOAK_OPS.len()];
+
+            // USB claims the interface exclusively, so switching means reopening
+            if current.as_ref().map(|(k, _)| *k) != Some(kind) {
+                current = None;
+                let reopen_started = Instant::now();
+                match open_transport(kind) {
+                    Ok(adapter) => {
+                        if needs_recovery {
+                            recovery.recovered += 1;
+                            recovery.max_recovery_ms = recovery
+                                .max_recovery_ms
+                                .max(reopen_started.elapsed().as_secs_f64() * 1000.0);
+                            needs_recovery = false;
+                        }
+                        current = Some((kind, adapter));
+                    }
+                    Err(e) => {
+                        eprintln!("[{}] failed to open {:?}: {}", i, kind, e);
+                        *errors.entry(format!("open {:?}: {}", kind, e)).or_default() += 1;
+                    }
+                }
+            }
+
+            let op_samples = by_op.entry(op.to_string()).or_default();
+            let transport_samples = by_transport.entry(kind).or_default();
+            op_samples.requests += 1;
+            transport_samples.requests += 1;
+
+            let request_started = Instant::now();
+            let result = match current.as_mut() {
+                Some((_, adapter)) => soak_request(adapter.as_mut(), op),
+                None => Err(anyhow!("no transport")),
+            };
+            let elapsed_ms = request_started.elapsed().as_secs_f64() * 1000.0;
+
+            match result {
+                Ok(()) => {
+                    op_samples.latencies.push(elapsed_ms);
+                    transport_samples.latencies.push(elapsed_ms);
+                }
+                Err(e) => {
+                    op_samples.errors += 1;
+                    transport_samples.errors += 1;
+                    *errors.entry(format!("{} via {:?}: {}", op, kind, e)).or_default() += 1;
+                    if !self.json {
+                        eprintln!("[{}] {} via {:?} failed: {}", i, op, kind, e);
+                    }
+                    // Drop the transport so the next request exercises reconnection
+                    if current.take().is_some() {
+                        recovery.attempts += 1;
+                        needs_recovery = true;
+                    }
+                }
+            }
+
+            if self.delay_ms > 0 {
+                std::thread::sleep(Duration::from_millis(self.delay_ms));
+            }
+        }
+
+        let report = SoakReport {
+            iterations: self.iterations,
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+            by_operation: by_op.into_iter().map(|(k, v)| (k, v.summarize())).collect(),
+            by_transport: by_transport.into_iter().map(|(k, v)| (k, v.summarize())).collect(),
+            recovery,
+            errors,
+        };
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print_report(&report);
+        }
+
+        Ok(())
+    }
+}
+
+fn print_report(report: &SoakReport) {
+    let print_stats = |name: &str, s: &SoakStats| {
+        println!(
+            "  {:<12} {:>5} req {:>4} err ({:>5.1}%)  p50 {:>7.1}ms  p90 {:>7.1}ms  p99 {:>7.1}ms  max {:>7.1}ms",
+            name,
+            s.requests,
+            s.errors,
+            s.error_rate * 100.0,
+            s.latency.p50_ms,
+            s.latency.p90_ms,
+            s.latency.p99_ms,
+            s.latency.max_ms,
+        );
+    };
+
+    println!("Soak test: {} iterations in {:.1}s", report.iterations, report.elapsed_ms / 1000.0);
+    println!("By operation:");
+    for (name, stats) in &report.by_operation {
+        print_stats(name, stats);
+    }
+    println!("By transport:");
+    for (kind, stats) in &report.by_transport {
+        print_stats(&format!("{:?}", kind), stats);
+    }
+    println!(
+        "Recovery: {}/{} reconnects succeeded (slowest {:.1}ms)",
+        report.recovery.recovered, report.recovery.attempts, report.recovery.max_recovery_ms
+    );
+    if !report.errors.is_empty() {
+        println!("Errors:");
+        for (err, count) in &report.errors {
+            println!("  {:>4}x {}", count, err);
+        }
+    }
+}
@@ -8,8 +8,12 @@ use crate::{
     messages::{self, Message},
     transport::ProtocolAdapter,
 };
-use anyhow::Result;
-use clap::{ArgAction::SetTrue, Args};
+use anyhow::{anyhow, Result};
+use clap::{ArgAction::SetTrue, Args, ValueEnum};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
 
 /// Get bitcoin address in base58 encoding
 #[derive(Debug, Clone, Args)]
@@ -24,10 +28,84 @@ pub struct GetAddress {
     /// Confirm address on device screen
     #[clap(short = 'd', long, action = SetTrue)]
     show_display: Option<bool>,
+    /// Render the address as a QR code in the terminal
+    #[clap(long, action = SetTrue)]
+    qr: bool,
+    /// Copy the address to the clipboard (best effort)
+    #[clap(long, action = SetTrue)]
+    copy: bool,
+}
+
+/// Print `address` as a QR code. Bech32 addresses are encoded upper-case,
+/// which BIP-173 allows and which fits a smaller code.
+fn print_qr(address: &str) -> Result<()> {
+    let lower = address.to_ascii_lowercase();
+    let data = if lower.starts_with("bc1") || lower.starts_with("tb1") {
+        address.to_ascii_uppercase()
+    } else {
+        address.to_string()
+    };
+    let code = qrcode::QrCode::new(data.as_bytes()).map_err(|e| anyhow!("cannot encode QR code: {}", e))?;
+    // Inverted so it scans on dark terminal backgrounds
+    let rendered = code
+        .render::<qrcode::render::unicode::Dense1x2>()
+        .dark_color(qrcode::render::unicode::Dense1x2::Light)
+        .light_color(qrcode::render::unicode::Dense1x2::Dark)
+        .build();
+    eprintln!("{}", rendered);
+    Ok(())
+}
+
+/// Hand `text` to the first clipboard tool found for this platform
+fn copy_to_clipboard(text: &str) -> Result<&'static str> {
+    let candidates: &[(&'static str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+
+    for (tool, args) in candidates {
+        let mut child = match Command::new(tool)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(tool);
+        }
+    }
+    Err(anyhow!(
+        "no clipboard tool found (tried {})",
+        candidates.iter().map(|(tool, _)| *tool).collect::<Vec<_>>().join(", ")
+    ))
 }
 
 impl CliCommand for GetAddress {
     fn handle(self, protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        let path = self.address.to_string();
+        let coin_name = self.coin_name.clone().unwrap_or_else(|| "Bitcoin".to_string());
+        let script_type = self
+            .script_type
+            .unwrap_or(ScriptType::P2pkh)
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+        let shown_on_device = self.show_display.unwrap_or(false);
+
         let resp = expect_message!(
             Message::Address,
             protocol_adapter.with_standard_handler().handle(
@@ -44,6 +122,27 @@ impl CliCommand for GetAddress {
 
         println!("{}", resp.address);
 
+        // Everything but the address goes to stderr so scripts can keep
+        // capturing stdout
+        eprintln!("  path:        {}", path);
+        eprintln!("  script type: {}", script_type);
+        eprintln!("  coin:        {}", coin_name);
+        if shown_on_device {
+            eprintln!("Check that the address on your KeepKey matches before sharing it.");
+        } else {
+            eprintln!("Not verified on the device; run again with --show-display before receiving large amounts.");
+        }
+
+        if self.qr {
+            print_qr(&resp.address)?;
+        }
+        if self.copy {
+            match copy_to_clipboard(&resp.address) {
+                Ok(tool) => eprintln!("Copied to clipboard ({}).", tool),
+                Err(e) => eprintln!("Could not copy to clipboard: {}", e),
+            }
+        }
+
         Ok(())
     }
 }
@@ -1,9 +1,11 @@
 mod get_address;
 mod sign_message;
+mod sign_psbt;
 mod sign_tx;
 mod verify_message;
 
 pub use get_address::*;
 pub use sign_message::*;
+pub use sign_psbt::*;
 pub use sign_tx::*;
 pub use verify_message::*;
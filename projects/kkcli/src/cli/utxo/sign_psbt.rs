@@ -0,0 +1,57 @@
+use crate::{
+    cli::CliCommand,
+    server::{psbt, sign_bitcoin_tx},
+    transport::ProtocolAdapter,
+};
+use anyhow::{anyhow, Result};
+use bitcoin::bip32::Fingerprint;
+use clap::Args;
+use std::str::FromStr;
+
+/// Sign a BIP-174 PSBT (e.g. exported from Sparrow or Specter)
+///
+/// The PSBT file may be binary or base64. Prints the PSBT with the device's
+/// signatures added and the signed transaction.
+#[derive(Debug, Clone, Args)]
+pub struct SignPsbt {
+    /// Path to the PSBT file
+    file_path: String,
+
+    /// Write the signed PSBT (base64) to this file instead of printing it
+    #[clap(short, long)]
+    output: Option<String>,
+
+    /// Master key fingerprint (hex) to pick this wallet's key when inputs list several derivations
+    #[clap(short, long)]
+    fingerprint: Option<String>,
+}
+
+impl CliCommand for SignPsbt {
+    fn handle(self, protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        let fingerprint = match self.fingerprint.as_deref() {
+            Some(fingerprint) => Some(
+                Fingerprint::from_str(fingerprint)
+                    .map_err(|e| anyhow!("Invalid fingerprint: {}", e))?,
+            ),
+            None => None,
+        };
+        let unsigned = psbt::parse_psbt(&std::fs::read(&self.file_path)?)?;
+        let request = psbt::psbt_sign_request(&unsigned, fingerprint)?;
+
+        let (response, _) = sign_bitcoin_tx(protocol_adapter, &request, &|step| {
+            println!("{}", step.render_bar())
+        })?;
+        let (signed, _) = psbt::merge_signed_tx(unsigned, &response.serialized_tx)?;
+
+        match self.output {
+            Some(path) => {
+                std::fs::write(&path, signed.to_string())?;
+                println!("Signed PSBT written to {}", path);
+            }
+            None => println!("Signed PSBT: {}", signed),
+        }
+        println!("Signed transaction: {}", response.serialized_tx);
+
+        Ok(())
+    }
+}
@@ -27,7 +27,7 @@ pub struct SignTx {
     
     /// Transaction inputs (format: "path:prev_hash:prev_index:amount:script_type")
     /// Example: "m/44'/0'/0'/0/0:abc123...:0:100000:p2pkh"
-    /// Script types: p2pkh, p2sh-p2wpkh, p2wpkh
+    /// Script types: p2pkh, p2sh-p2wpkh, p2wpkh, p2tr (firmware 7.10.0 or later)
     #[clap(short = 'i', long, value_delimiter = ' ', required = true)]
     inputs: Vec<String>,
     
@@ -80,6 +80,7 @@ impl SignTx {
                 "p2pkh" => messages::InputScriptType::Spendaddress,
                 "p2sh-p2wpkh" => messages::InputScriptType::Spendp2shwitness,
                 "p2wpkh" => messages::InputScriptType::Spendwitness,
+                "p2tr" => messages::InputScriptType::Spendtaproot,
                 _ => return Err(anyhow!("Invalid script type: {}", parts[4])),
             };
             
@@ -0,0 +1,160 @@
+use crate::transport::ProtocolAdapter;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::time::Duration;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Print live events from a running `kkcli server`: device status, signing
+/// progress, approvals, transaction confirmations and fee bump advice for
+/// transactions stuck in the mempool
+#[derive(Parser, Debug, Clone)]
+pub struct Watch {
+    /// Base URL of the server
+    #[clap(short, long, default_value = "http://127.0.0.1:1646")]
+    pub url: String,
+
+    /// Print each event as one JSON object per line
+    #[clap(long)]
+    pub json: bool,
+
+    /// Only show events whose type starts with this (e.g. `tx:`, `sign:`); repeatable
+    #[clap(short, long = "event")]
+    pub events: Vec<String>,
+
+    /// Exit when the connection drops instead of reconnecting
+    #[clap(long)]
+    pub no_reconnect: bool,
+}
+
+impl super::CliCommand for Watch {
+    fn handle(self, _protocol_adapter: &mut dyn ProtocolAdapter) -> Result<()> {
+        // Like `server`, this doesn't talk to the device and is run from main.rs
+        println!("Watch command should be handled in main.rs with async runtime");
+        Ok(())
+    }
+}
+
+impl Watch {
+    pub async fn run(self) -> Result<()> {
+        let url = format!("{}/api/v2/events", self.url.trim_end_matches('/'));
+        let client = reqwest::Client::new();
+        loop {
+            match self.stream(&client, &url).await {
+                Ok(()) => eprintln!("Event stream closed by the server"),
+                Err(e) => eprintln!("Event stream error: {}", e),
+            }
+            if self.no_reconnect {
+                return Ok(());
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+            eprintln!("Reconnecting to {}...", url);
+        }
+    }
+
+    async fn stream(&self, client: &reqwest::Client, url: &str) -> Result<()> {
+        let mut response = client
+            .get(url)
+            .header("Accept", "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| anyhow!("could not reach {} ({}); is `kkcli server` running?", url, e))?;
+        if !response.status().is_success() {
+            return Err(anyhow!("{} returned {}", url, response.status()));
+        }
+        eprintln!("Watching {} (Ctrl+C to stop)", url);
+
+        let mut buffer = String::new();
+        let mut data = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some(end) = buffer.find('\n') {
+                let line: String = buffer.drain(..=end).collect();
+                let line = line.trim_end_matches(|c: char| c == '\r' || c == '\n');
+                if line.is_empty() {
+                    // A blank line ends the event
+                    if !data.is_empty() {
+                        self.print_event(&data.join("\n"));
+                        data.clear();
+                    }
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+                }
+                // `event:` repeats the type inside the payload; `:` lines are keep-alives
+            }
+        }
+        Ok(())
+    }
+
+    fn print_event(&self, payload: &str) {
+        let event: serde_json::Value = match serde_json::from_str(payload) {
+            Ok(event) => event,
+            Err(_) => {
+                eprintln!("Ignoring malformed event: {}", payload);
+                return;
+            }
+        };
+        let event_type = event.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
+        if !self.events.is_empty() && !self.events.iter().any(|prefix| event_type.starts_with(prefix.as_str())) {
+            return;
+        }
+
+        if self.json {
+            println!("{}", event);
+        } else {
+            let data = event.get("data").cloned().unwrap_or(serde_json::Value::Null);
+            println!(
+                "{}  {:<20} {}",
+                chrono::Local::now().format("%H:%M:%S"),
+                event_type,
+                summarize(event_type, &data)
+            );
+        }
+    }
+}
+
+/// One-line description of an event for humans; falls back to the raw data
+fn summarize(event_type: &str, data: &serde_json::Value) -> String {
+    let field = |name: &str| data.get(name).map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()));
+    match event_type {
+        "device_status" => match data.get("device").filter(|d| !d.is_null()) {
+            Some(device) => format!(
+                "connected: {}",
+                device.get("name").and_then(|n| n.as_str()).unwrap_or("KeepKey")
+            ),
+            None => "disconnected".to_string(),
+        },
+        "sign:started" | "sign:completed" => format!(
+            "{} ({} input(s), {} output(s))",
+            field("endpoint").unwrap_or_default(),
+            field("inputs").unwrap_or_default(),
+            field("outputs").unwrap_or_default()
+        ),
+        "sign:failed" => format!(
+            "{}: {}",
+            field("endpoint").unwrap_or_default(),
+            field("error").unwrap_or_default()
+        ),
+        "approval:requested" => format!(
+            "{} via {} awaiting approval ({})",
+            field("kind").unwrap_or_default(),
+            field("endpoint").unwrap_or_default(),
+            field("id").unwrap_or_default()
+        ),
+        "approval:resolved" => format!("{} {}", field("id").unwrap_or_default(), field("outcome").unwrap_or_default()),
+        "tx:stuck" => format!(
+            "{} likely stuck at {} sat/vB after {} min; consider {} to {} sat/vB",
+            field("txid").unwrap_or_default(),
+            field("feeRate").unwrap_or_default(),
+            field("pendingMinutes").unwrap_or_default(),
+            if data.get("replaceable").and_then(|r| r.as_bool()) == Some(false) { "a CPFP bump" } else { "an RBF bump" },
+            field("suggestedFeeRate").unwrap_or_default()
+        ),
+        _ if event_type.starts_with("tx:") => match (field("txid"), field("confirmations")) {
+            (Some(txid), Some(confirmations)) => format!("{} ({} confirmation(s))", txid, confirmations),
+            (Some(txid), None) => txid,
+            _ => data.to_string(),
+        },
+        _ => data.to_string(),
+    }
+}
@@ -3,6 +3,10 @@ use serde::Deserialize;
 use semver::Version;
 use anyhow::{Result, anyhow};
 use url::Url; // For joining URLs
+use sha2::{Digest, Sha256};
+use std::io::Read;
+
+use crate::server::progress::{Progress, ProgressOperation, UpdateStage};
 
 #[derive(RustEmbed)]
 #[folder = "firmware/"]
@@ -53,6 +57,8 @@ struct ManifestFile {
 
 const EMBEDDED_LTS_MANIFEST_PATH: &str = "lts_manifest.json"; // Path within firmware/ folder
 // URL for fetching the latest manifest from the network
+/// Signed images start with a 256-byte `KPKY` header; release hashes cover what follows it
+const IMAGE_HEADER_LEN: usize = 256;
 const REMOTE_MANIFEST_URL: &str = "https://raw.githubusercontent.com/keepkey/keepkey-desktop/master/firmware/releases.json";
 
 pub struct FirmwareManager {
@@ -192,15 +198,9 @@ impl FirmwareManager {
             }
             UrlType::HttpAbsolute => {
                 println!("Downloading firmware from absolute URL: {}", info.url);
-                let url = info.url.clone();
-                let response = std::thread::spawn(move || {
-                    reqwest::blocking::get(&url)
-                }).join().unwrap()?;
-                if response.status().is_success() {
-                    Ok(response.bytes()?.to_vec())
-                } else {
-                    Err(anyhow!("Failed to download firmware from {}: HTTP {}", info.url, response.status()))
-                }
+                let image = download(&info.url, self.operation_for(info))?;
+                verify_image_hash(info, &image, self.operation_for(info))?;
+                Ok(image)
             }
             UrlType::HttpRelative => {
                 if let Some(base_url_str) = &self.remote_base_url {
@@ -209,15 +209,9 @@ impl FirmwareManager {
                             match base_url.join(&info.url) {
                                 Ok(full_url) => {
                                     println!("Downloading firmware from constructed URL: {}", full_url.as_str());
-                                    let url_str = full_url.as_str().to_string();
-                                    let response = std::thread::spawn(move || {
-                                        reqwest::blocking::get(&url_str)
-                                    }).join().unwrap()?;
-                                    if response.status().is_success() {
-                                        Ok(response.bytes()?.to_vec())
-                                    } else {
-                                        Err(anyhow!("Failed to download firmware from {}: HTTP {}", full_url.as_str(), response.status()))
-                                    }
+                                    let image = download(full_url.as_str(), self.operation_for(info))?;
+                                    verify_image_hash(info, &image, self.operation_for(info))?;
+                                    Ok(image)
                                 }
                                 Err(e) => Err(anyhow!("Failed to join base URL '{}' with relative path '{}': {}", base_url_str, info.url, e)),
                             }
@@ -231,6 +225,14 @@ impl FirmwareManager {
         }
     }
     
+    /// Whether `info` is the release's bootloader or its firmware, for progress lines
+    fn operation_for(&self, info: &FirmwareInfo) -> ProgressOperation {
+        match &self.releases {
+            Some(releases) if releases.bootloader.url == info.url => ProgressOperation::BootloaderUpdate,
+            _ => ProgressOperation::FirmwareUpdate,
+        }
+    }
+
     pub fn get_latest_firmware_bytes(&self) -> Result<Vec<u8>> {
         let info = self.releases.as_ref()
             .ok_or_else(|| anyhow!("Firmware information not available to get bytes."))?.firmware.clone();
@@ -250,4 +252,62 @@ impl FirmwareManager {
     pub fn get_latest_bootloader_info(&self) -> Option<&FirmwareInfo> {
         self.releases.as_ref().map(|r| &r.bootloader)
     }
-} 
\ No newline at end of file
+}
+
+/// Download an image, printing a `downloading` line every 10%
+fn download(url: &str, operation: ProgressOperation) -> Result<Vec<u8>> {
+    let url = url.to_string();
+    std::thread::spawn(move || -> Result<Vec<u8>> {
+        let mut response = reqwest::blocking::get(&url)?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to download firmware from {}: HTTP {}", url, response.status()));
+        }
+        let total = response.content_length().unwrap_or(0);
+        let mut image = Vec::with_capacity(total as usize);
+        let mut chunk = [0u8; 64 * 1024];
+        let mut last_tenth = None;
+        loop {
+            let read = response.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            image.extend_from_slice(&chunk[..read]);
+            let step = Progress::update(operation, UpdateStage::Downloading, image.len() as u64, total);
+            let tenth = step.fraction().map(|f| (f * 10.0) as u64);
+            if tenth != last_tenth {
+                println!("{}", step.render_bar());
+                last_tenth = tenth;
+            }
+        }
+        Ok(image)
+    })
+    .join()
+    .map_err(|_| anyhow!("Firmware download thread panicked"))?
+}
+
+/// Check a downloaded image against the release hash before it goes anywhere near a device
+fn verify_image_hash(info: &FirmwareInfo, image: &[u8], operation: ProgressOperation) -> Result<()> {
+    let expected = info.hash.trim_start_matches("0x").to_lowercase();
+    if expected.is_empty() {
+        return Ok(());
+    }
+    let body = if image.starts_with(b"KPKY") && image.len() > IMAGE_HEADER_LEN {
+        &image[IMAGE_HEADER_LEN..]
+    } else {
+        image
+    };
+    let actual = hex::encode(Sha256::digest(body));
+    if actual != expected && hex::encode(Sha256::digest(image)) != expected {
+        return Err(anyhow!(
+            "Downloaded image for v{} doesn't match the release hash: expected {}, got {}",
+            info.version, expected, actual
+        ));
+    }
+    println!(
+        "{}",
+        Progress::update(operation, UpdateStage::VerifyingHash, 1, 1)
+            .with_message(format!("SHA-256 {}", actual))
+            .render_bar()
+    );
+    Ok(())
+}
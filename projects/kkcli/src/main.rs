@@ -65,6 +65,26 @@ async fn main() -> Result<()> {
             // Handle server command asynchronously
             return server_cmd.clone().run().await;
         }
+        Subcommand::Watch(watch_cmd) => {
+            // Streams events from a running server; no device needed
+            return watch_cmd.clone().run().await;
+        }
+        Subcommand::Daemon(daemon_cmd) => {
+            // Manages the autostart entry and reads the server's pidfile; no device needed
+            return daemon_cmd.clone().run().await;
+        }
+        Subcommand::ExportWatchonly(export_cmd) => {
+            // Built from cached xpubs; no device needed
+            return export_cmd.clone().run().await;
+        }
+        Subcommand::AwaitPayment(await_cmd) => {
+            // Polls the chain backend; no device needed
+            return await_cmd.clone().run().await;
+        }
+        Subcommand::SendMany(send_cmd) => {
+            // Goes through a running server, which owns the device
+            return send_cmd.clone().run().await;
+        }
         Subcommand::List(_) => {
             for device in list_devices().iter() {
                 let device_desc = device.device_descriptor()?;
@@ -82,6 +102,11 @@ async fn main() -> Result<()> {
             return Ok(());
         }
         Subcommand::Decode(x) => {
+            // Decodes its argument, or with --live polls a running vault; no device needed
+            return x.clone().run().await;
+        }
+        Subcommand::Test(x) => {
+            // Soak tests open and reopen transports themselves
             x.clone().handle()?;
             return Ok(());
         }
@@ -0,0 +1,110 @@
+//! Destination address checks, run before a transaction or payment request
+//! goes anywhere near the device.
+//!
+//! Parsing goes through `bitcoin::Address`, which verifies the Base58Check
+//! checksum of legacy addresses and the bech32 (witness v0) or bech32m
+//! (witness v1+) checksum of segwit ones, including the variant mix-ups that
+//! BIP-350 forbids. The network check then rejects testnet addresses when
+//! spending mainnet coins and the other way round.
+
+use std::str::FromStr;
+
+use bitcoin::address::{Address, AddressType, NetworkUnchecked};
+use bitcoin::Network;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(crate) enum AddressError {
+    #[error("Address is empty")]
+    Empty,
+    #[error("Invalid Bitcoin address {address:?}: {reason}")]
+    Malformed { address: String, reason: String },
+    #[error("Address {address} is not a {expected} address")]
+    WrongNetwork { address: String, expected: Network },
+    /// Parses, but isn't a standard output type (e.g. an unknown witness version)
+    #[error("Address {address} has a non-standard output type")]
+    Unsupported { address: String },
+}
+
+/// An address that parsed, matched the expected network and has a standard type
+pub(crate) struct ValidatedAddress {
+    pub address_type: AddressType,
+}
+
+impl ValidatedAddress {
+    /// Script type name as used for outputs ("p2pkh", "p2sh", "p2wpkh", "p2wsh", "p2tr")
+    pub fn script_type(&self) -> &'static str {
+        match self.address_type {
+            AddressType::P2pkh => "p2pkh",
+            AddressType::P2sh => "p2sh",
+            AddressType::P2wpkh => "p2wpkh",
+            AddressType::P2wsh => "p2wsh",
+            AddressType::P2tr => "p2tr",
+            _ => "unknown",
+        }
+    }
+}
+
+pub(crate) fn validate_address(address: &str, network: Network) -> Result<ValidatedAddress, AddressError> {
+    let trimmed = address.trim();
+    if trimmed.is_empty() {
+        return Err(AddressError::Empty);
+    }
+    if trimmed != address {
+        return Err(AddressError::Malformed {
+            address: address.to_string(),
+            reason: "leading or trailing whitespace".to_string(),
+        });
+    }
+
+    let unchecked = Address::<NetworkUnchecked>::from_str(address).map_err(|e| AddressError::Malformed {
+        address: address.to_string(),
+        reason: e.to_string(),
+    })?;
+    if !unchecked.is_valid_for_network(network) {
+        return Err(AddressError::WrongNetwork { address: address.to_string(), expected: network });
+    }
+    match unchecked.assume_checked().address_type() {
+        Some(address_type) => Ok(ValidatedAddress { address_type }),
+        None => Err(AddressError::Unsupported { address: address.to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_each_mainnet_type() {
+        let cases = [
+            ("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", "p2pkh"),
+            ("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy", "p2sh"),
+            ("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", "p2wpkh"),
+            ("bc1p5d7rjq7g6rdk2yhzks9smlaqtedr4dekq08ge8ztwac72sfr9rusxg3297", "p2tr"),
+        ];
+        for (address, script_type) in cases {
+            let validated = validate_address(address, Network::Bitcoin).unwrap();
+            assert_eq!(validated.script_type(), script_type, "{}", address);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_checksums_and_wrong_networks() {
+        // Last character changed
+        assert!(matches!(
+            validate_address("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3", Network::Bitcoin),
+            Err(AddressError::Malformed { .. })
+        ));
+        // Witness v1 encoded with bech32 instead of bech32m (BIP-350 test vector)
+        assert!(matches!(
+            validate_address("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7k7grplx", Network::Bitcoin),
+            Err(AddressError::Malformed { .. })
+        ));
+        assert!(matches!(
+            validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", Network::Bitcoin),
+            Err(AddressError::WrongNetwork { .. })
+        ));
+        assert!(validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", Network::Testnet).is_ok());
+        assert_eq!(validate_address(" ", Network::Bitcoin).err(), Some(AddressError::Empty));
+    }
+}
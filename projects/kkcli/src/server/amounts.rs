@@ -0,0 +1,283 @@
+//! Locale-aware formatting of bitcoin amounts.
+//!
+//! Money-bearing responses always carry the raw integer satoshi value; the
+//! formatted string next to it is for display only. The locale comes from
+//! config `amount_locale`, or from the request's `Accept-Language` header when
+//! that is `auto`; the unit is the user's denomination preference
+//! (`amount_unit`). Both are echoed back as `amountFormat` so clients know how
+//! the strings were written.
+
+use axum::http::{header, HeaderMap};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use super::cache::DeviceCache;
+
+const DEFAULT_LOCALE: &str = "en-US";
+const NBSP: &str = "\u{a0}";
+
+// Languages writing 1.234,56 and 1 234,56; everything else gets 1,234.56
+const DOT_GROUP_LANGUAGES: &[&str] = &[
+    "de", "es", "it", "nl", "pt", "id", "tr", "da", "el", "ro", "vi", "hr", "sl", "sr",
+];
+const SPACE_GROUP_LANGUAGES: &[&str] = &[
+    "fr", "ru", "pl", "cs", "sk", "sv", "nb", "nn", "no", "fi", "hu", "uk", "bg", "lt", "lv", "et",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AmountUnit {
+    Btc,
+    /// 100 satoshis
+    Bits,
+    Sats,
+}
+
+impl AmountUnit {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "btc" => Some(AmountUnit::Btc),
+            "bits" | "bit" => Some(AmountUnit::Bits),
+            "sats" | "sat" => Some(AmountUnit::Sats),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AmountUnit::Btc => "btc",
+            AmountUnit::Bits => "bits",
+            AmountUnit::Sats => "sats",
+        }
+    }
+
+    fn sats_per_unit(self) -> u64 {
+        match self {
+            AmountUnit::Btc => 100_000_000,
+            AmountUnit::Bits => 100,
+            AmountUnit::Sats => 1,
+        }
+    }
+
+    fn decimals(self) -> usize {
+        match self {
+            AmountUnit::Btc => 8,
+            AmountUnit::Bits => 2,
+            AmountUnit::Sats => 0,
+        }
+    }
+}
+
+/// How formatted amounts in a response were written
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AmountFormat {
+    /// BCP 47 tag the separators were chosen for
+    pub locale: String,
+    pub unit: AmountUnit,
+    pub decimal_separator: String,
+    pub group_separator: String,
+}
+
+/// `de-ch` -> `de-CH`; `None` for anything that isn't a plausible language tag
+pub(crate) fn normalize_tag(tag: &str) -> Option<String> {
+    let mut subtags = tag.trim().split(['-', '_']);
+    let language = subtags.next()?.to_ascii_lowercase();
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mut normalized = language;
+    for subtag in subtags {
+        if subtag.is_empty() || subtag.len() > 8 || !subtag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        normalized.push('-');
+        if subtag.len() == 2 {
+            normalized.push_str(&subtag.to_ascii_uppercase());
+        } else {
+            normalized.push_str(subtag);
+        }
+    }
+    Some(normalized)
+}
+
+/// Highest-weighted language in an `Accept-Language` header
+fn preferred_language(accept_language: &str) -> Option<String> {
+    let mut best: Option<(&str, f32)> = None;
+    for part in accept_language.split(',') {
+        let mut fields = part.split(';');
+        let tag = fields.next().unwrap_or("").trim();
+        let weight = fields
+            .find_map(|f| f.trim().strip_prefix("q="))
+            .map(|q| q.parse::<f32>().unwrap_or(0.0))
+            .unwrap_or(1.0);
+        if tag.is_empty() || tag == "*" || weight <= 0.0 {
+            continue;
+        }
+        if best.map_or(true, |(_, best_weight)| weight > best_weight) {
+            best = Some((tag, weight));
+        }
+    }
+    best.and_then(|(tag, _)| normalize_tag(tag))
+}
+
+impl AmountFormat {
+    /// Separators for `locale`, falling back to en-US for unknown tags
+    pub fn for_locale(locale: &str, unit: AmountUnit) -> Self {
+        let locale = normalize_tag(locale).unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+        let language = locale.split('-').next().unwrap_or_default();
+        let region = locale.split('-').skip(1).find(|s| s.len() == 2).unwrap_or_default();
+
+        let (decimal_separator, group_separator) = if region == "CH" && matches!(language, "de" | "it") {
+            (".", "'")
+        } else if DOT_GROUP_LANGUAGES.contains(&language) {
+            (",", ".")
+        } else if SPACE_GROUP_LANGUAGES.contains(&language) {
+            (",", NBSP)
+        } else {
+            (".", ",")
+        };
+
+        Self {
+            locale,
+            unit,
+            decimal_separator: decimal_separator.to_string(),
+            group_separator: group_separator.to_string(),
+        }
+    }
+
+    /// Write `sats` in this format's unit with all of the unit's decimals
+    pub fn format(&self, sats: i64) -> String {
+        let per_unit = self.unit.sats_per_unit();
+        let abs = sats.unsigned_abs();
+        let whole = (abs / per_unit).to_string();
+
+        let mut out = String::new();
+        if sats < 0 {
+            out.push('-');
+        }
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                out.push_str(&self.group_separator);
+            }
+            out.push(digit);
+        }
+        if self.unit.decimals() > 0 {
+            out.push_str(&self.decimal_separator);
+            out.push_str(&format!("{:0width$}", abs % per_unit, width = self.unit.decimals()));
+        }
+        out
+    }
+}
+
+/// Exact conversion of a decimal BTC string such as `"0.00120000"` to satoshis
+pub(crate) fn btc_to_sats(btc: &str) -> Option<i64> {
+    let btc = btc.trim();
+    let (negative, digits) = match btc.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, btc),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Anything past the 8th decimal must be zero padding
+    let (fraction, excess) = fraction.split_at(fraction.len().min(8));
+    if excess.chars().any(|c| c != '0') {
+        return None;
+    }
+
+    let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let fraction: i64 = format!("{:0<8}", fraction).parse().ok()?;
+    let sats = whole.checked_mul(100_000_000)?.checked_add(fraction)?;
+    Some(if negative { -sats } else { sats })
+}
+
+async fn config_value(cache: &DeviceCache, key: &str) -> Option<String> {
+    match cache.get_config(key).await {
+        Ok(value) => value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()),
+        Err(e) => {
+            warn!("Failed to read {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Preferred denomination for formatted amounts (config `amount_unit`, default BTC)
+pub(crate) async fn amount_unit(cache: &DeviceCache) -> AmountUnit {
+    config_value(cache, "amount_unit")
+        .await
+        .and_then(|unit| AmountUnit::parse(&unit))
+        .unwrap_or(AmountUnit::Btc)
+}
+
+/// Amount format for a request: the preferred unit, in config `amount_locale`
+/// or the client's `Accept-Language` when that is `auto`
+pub(crate) async fn amount_format(cache: &DeviceCache, headers: &HeaderMap) -> AmountFormat {
+    let locale = match config_value(cache, "amount_locale").await {
+        Some(locale) if !locale.eq_ignore_ascii_case("auto") => locale,
+        _ => headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(preferred_language)
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string()),
+    };
+    AmountFormat::for_locale(&locale, amount_unit(cache).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_with_locale_separators() {
+        let en = AmountFormat::for_locale("en-US", AmountUnit::Btc);
+        assert_eq!(en.format(123_456_789_012), "1,234.56789012");
+        assert_eq!(en.format(-120_000), "-0.00120000");
+
+        let de = AmountFormat::for_locale("de-de", AmountUnit::Btc);
+        assert_eq!(de.locale, "de-DE");
+        assert_eq!(de.format(123_456_789_012), "1.234,56789012");
+
+        let fr = AmountFormat::for_locale("fr", AmountUnit::Bits);
+        assert_eq!(fr.format(123_456_789), "1\u{a0}234\u{a0}567,89");
+
+        let ch = AmountFormat::for_locale("de-CH", AmountUnit::Sats);
+        assert_eq!(ch.format(1_234_567), "1'234'567");
+
+        assert_eq!(AmountFormat::for_locale("not a locale", AmountUnit::Btc).locale, DEFAULT_LOCALE);
+    }
+
+    #[test]
+    fn formats_in_preferred_unit() {
+        assert_eq!(AmountUnit::parse(" Sat "), Some(AmountUnit::Sats));
+        assert_eq!(AmountUnit::parse("mbtc"), None);
+        let en = AmountFormat::for_locale("en", AmountUnit::Sats);
+        assert_eq!(en.format(-2_100), "-2,100");
+        let bits = AmountFormat::for_locale("en", AmountUnit::Bits);
+        assert_eq!(bits.format(120_000), "1,200.00");
+    }
+
+    #[test]
+    fn picks_highest_weighted_language() {
+        assert_eq!(preferred_language("fr-CH, fr;q=0.9, en;q=0.8").as_deref(), Some("fr-CH"));
+        assert_eq!(preferred_language("en;q=0.5, de-AT;q=0.9, *").as_deref(), Some("de-AT"));
+        assert_eq!(preferred_language("*, es;q=0").as_deref(), None);
+    }
+
+    #[test]
+    fn parses_btc_strings_exactly() {
+        assert_eq!(btc_to_sats("0.00120000"), Some(120_000));
+        assert_eq!(btc_to_sats("21"), Some(2_100_000_000_000_000));
+        assert_eq!(btc_to_sats("-1.5"), Some(-150_000_000));
+        assert_eq!(btc_to_sats(".1"), Some(10_000_000));
+        assert_eq!(btc_to_sats("0.123456780"), Some(12_345_678));
+        assert_eq!(btc_to_sats("0.123456789"), None);
+        assert_eq!(btc_to_sats("1e-8"), None);
+        assert_eq!(btc_to_sats(""), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! Remote approval for signing requests.
+//!
+//! When enabled for an API key (config `remote_approval:<key>`, falling back to
+//! `remote_approval`), a signing request made over REST is parked here before
+//! the device is touched. An `approval:requested` event describing what will be
+//! signed goes out over `/ws`; the desktop UI answers via
+//! `POST /api/v2/approvals/{id}`. Rejection or timeout fails the request.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{oneshot, Mutex};
+use tokio::time::{timeout, Duration};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::ServerState;
+
+const DEFAULT_APPROVAL_TIMEOUT_SECS: u64 = 120;
+
+/// One destination of a transaction awaiting approval
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalOutput {
+    pub address: String,
+    /// Amount in satoshis
+    pub amount: String,
+    /// What the requester calls this payment, e.g. a payee from a batch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// What the user is asked to approve
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRequest {
+    pub id: String,
+    /// `sign-tx` or `sign-message`
+    pub kind: String,
+    /// Endpoint that created the request
+    pub endpoint: String,
+    /// Last characters of the API key, to tell clients apart
+    pub api_key_hint: Option<String>,
+    /// External destinations (change outputs are not listed)
+    pub outputs: Vec<ApprovalOutput>,
+    /// Sum of `outputs` in satoshis
+    pub total_amount: Option<String>,
+    pub message: Option<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ApprovalOutcome {
+    Approved,
+    Rejected,
+    TimedOut,
+}
+
+struct PendingApproval {
+    request: ApprovalRequest,
+    api_key: Option<String>,
+    respond: oneshot::Sender<bool>,
+}
+
+/// Signing requests waiting for a decision from the UI
+#[derive(Clone, Default)]
+pub struct ApprovalRegistry {
+    pending: Arc<Mutex<HashMap<String, PendingApproval>>>,
+}
+
+impl ApprovalRegistry {
+    pub async fn list(&self) -> Vec<ApprovalRequest> {
+        let mut requests: Vec<ApprovalRequest> = self.pending.lock().await.values().map(|p| p.request.clone()).collect();
+        requests.sort_by_key(|r| r.created_at);
+        requests
+    }
+
+    /// Resolve a pending request. Returns `None` if it doesn't exist (or already
+    /// expired) and `Some(false)` if `api_key` is the key that created it.
+    pub async fn decide(&self, id: &str, approve: bool, api_key: Option<&str>) -> Option<bool> {
+        let mut pending = self.pending.lock().await;
+        let entry = pending.get(id)?;
+        if api_key.is_some() && entry.api_key.as_deref() == api_key {
+            return Some(false);
+        }
+        let entry = pending.remove(id)?;
+        // The requester may have given up already; nothing else to do then
+        let _ = entry.respond.send(approve);
+        Some(true)
+    }
+}
+
+/// Park a signing request until the UI approves or rejects it. Returns
+/// `Approved` immediately when remote approval is not enabled for `api_key`.
+pub(crate) async fn await_remote_approval(
+    state: &ServerState,
+    api_key: Option<&str>,
+    kind: &str,
+    endpoint: &str,
+    outputs: Vec<ApprovalOutput>,
+    message: Option<String>,
+) -> anyhow::Result<ApprovalOutcome> {
+    if !state.cache.get_remote_approval_required(api_key).await? {
+        return Ok(ApprovalOutcome::Approved);
+    }
+
+    let wait = Duration::from_secs(
+        state
+            .cache
+            .get_config("remote_approval_timeout_secs")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_APPROVAL_TIMEOUT_SECS),
+    );
+    let now = chrono::Utc::now().timestamp();
+    let total_amount = if outputs.is_empty() {
+        None
+    } else {
+        Some(outputs.iter().filter_map(|o| o.amount.parse::<u64>().ok()).sum::<u64>().to_string())
+    };
+    let request = ApprovalRequest {
+        id: Uuid::new_v4().to_string(),
+        kind: kind.to_string(),
+        endpoint: endpoint.to_string(),
+        api_key_hint: api_key.map(|k| k.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect()),
+        outputs,
+        total_amount,
+        message,
+        created_at: now,
+        expires_at: now + wait.as_secs() as i64,
+    };
+    let id = request.id.clone();
+
+    let (respond, decision) = oneshot::channel();
+    state.approvals.pending.lock().await.insert(
+        id.clone(),
+        PendingApproval {
+            request: request.clone(),
+            api_key: api_key.map(str::to_string),
+            respond,
+        },
+    );
+    info!("⏳ {} via {} awaiting remote approval ({})", kind, endpoint, id);
+    state.events.emit("approval:requested", serde_json::to_value(&request)?);
+
+    let outcome = match timeout(wait, decision).await {
+        Ok(Ok(true)) => ApprovalOutcome::Approved,
+        Ok(Ok(false)) | Ok(Err(_)) => ApprovalOutcome::Rejected,
+        Err(_) => {
+            state.approvals.pending.lock().await.remove(&id);
+            ApprovalOutcome::TimedOut
+        }
+    };
+
+    if outcome != ApprovalOutcome::Approved {
+        warn!("🚫 Signing request {} not approved: {:?}", id, outcome);
+    }
+    let outcome_name = match outcome {
+        ApprovalOutcome::Approved => "approved",
+        ApprovalOutcome::Rejected => "rejected",
+        ApprovalOutcome::TimedOut => "timed_out",
+    };
+    state.events.emit("approval:resolved", serde_json::json!({ "id": id, "outcome": outcome_name }));
+    Ok(outcome)
+}
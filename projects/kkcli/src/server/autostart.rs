@@ -0,0 +1,249 @@
+//! Start the headless server at login: a launchd agent on macOS, a systemd
+//! user unit on Linux and a Startup folder script on Windows. Each entry runs
+//! this same binary as `server --daemon`; the instance lock keeps it from
+//! clashing with a server started by hand.
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+
+use super::cache::DeviceCache;
+
+const LAUNCHD_LABEL: &str = "com.keepkey.kkcli";
+const SYSTEMD_UNIT: &str = "kkcli.service";
+const STARTUP_SCRIPT: &str = "kkcli.cmd";
+const LOG_FILE: &str = "kkcli-daemon.log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceManager {
+    Launchd,
+    Systemd,
+    StartupFolder,
+}
+
+impl ServiceManager {
+    pub fn current() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(Self::Launchd)
+        } else if cfg!(target_os = "windows") {
+            Ok(Self::StartupFolder)
+        } else if cfg!(target_os = "linux") {
+            Ok(Self::Systemd)
+        } else {
+            Err(anyhow!("Starting the server at login is not supported on this platform"))
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Launchd => "launchd agent",
+            Self::Systemd => "systemd user unit",
+            Self::StartupFolder => "Startup folder script",
+        }
+    }
+
+    pub fn entry_path(self) -> Result<PathBuf> {
+        let path = match self {
+            Self::Launchd => dirs::home_dir()
+                .ok_or_else(|| anyhow!("Could not determine home directory"))?
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", LAUNCHD_LABEL)),
+            Self::Systemd => dirs::config_dir()
+                .ok_or_else(|| anyhow!("Could not determine config directory"))?
+                .join("systemd/user")
+                .join(SYSTEMD_UNIT),
+            // %APPDATA%\Microsoft\Windows\Start Menu\Programs\Startup
+            Self::StartupFolder => dirs::config_dir()
+                .ok_or_else(|| anyhow!("Could not determine AppData directory"))?
+                .join("Microsoft")
+                .join("Windows")
+                .join("Start Menu")
+                .join("Programs")
+                .join("Startup")
+                .join(STARTUP_SCRIPT),
+        };
+        Ok(path)
+    }
+
+    /// Contents of the entry that runs `exe` as a server on `port`
+    pub fn render(self, exe: &Path, port: u16, log_file: &Path) -> String {
+        let exe = exe.display().to_string();
+        match self {
+            Self::Launchd => format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>server</string>
+        <string>--port</string>
+        <string>{port}</string>
+        <string>--daemon</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+                label = LAUNCHD_LABEL,
+                exe = xml_escape(&exe),
+                port = port,
+                log = xml_escape(&log_file.display().to_string()),
+            ),
+            Self::Systemd => format!(
+                "[Unit]\n\
+                 Description=KeepKey CLI server\n\
+                 After=network.target\n\
+                 # A second server exits at once on the instance lock; don't retry forever\n\
+                 StartLimitIntervalSec=120\n\
+                 StartLimitBurst=5\n\
+                 \n\
+                 [Service]\n\
+                 ExecStart={exe} server --port {port} --daemon\n\
+                 Restart=on-failure\n\
+                 RestartSec=5\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy=default.target\n",
+                exe = systemd_quote(&exe),
+                port = port,
+            ),
+            // Output goes to the log; `start /min` keeps a console window out of the way
+            Self::StartupFolder => format!(
+                "@echo off\r\nstart \"kkcli\" /min cmd /c \"\"{exe}\" server --port {port} --daemon >> \"{log}\" 2>&1\"\r\n",
+                exe = exe,
+                port = port,
+                log = log_file.display(),
+            ),
+        }
+    }
+
+    /// Load the entry now rather than at next login
+    fn activate(self, path: &Path) -> Result<()> {
+        match self {
+            Self::Launchd => run("launchctl", &["load", "-w", &path.display().to_string()]),
+            Self::Systemd => {
+                run("systemctl", &["--user", "daemon-reload"])?;
+                run("systemctl", &["--user", "enable", "--now", SYSTEMD_UNIT])
+            }
+            // Takes effect at next login; `kkcli server` starts one now
+            Self::StartupFolder => Ok(()),
+        }
+    }
+
+    fn deactivate(self, path: &Path) -> Result<()> {
+        match self {
+            Self::Launchd => run("launchctl", &["unload", "-w", &path.display().to_string()]),
+            Self::Systemd => run("systemctl", &["--user", "disable", "--now", SYSTEMD_UNIT]),
+            Self::StartupFolder => Ok(()),
+        }
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "`{} {}` failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Quote one ExecStart word; `%` would otherwise be read as a unit specifier
+fn systemd_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%"))
+}
+
+/// Write and load the entry for this platform, replacing any existing one
+pub fn install(port: u16) -> Result<(ServiceManager, PathBuf)> {
+    let manager = ServiceManager::current()?;
+    let path = manager.entry_path()?;
+    let exe = std::env::current_exe().context("Could not locate the kkcli binary")?;
+    let log_file = DeviceCache::get_cache_dir()?.join(LOG_FILE);
+
+    if path.exists() {
+        // Reinstalling picks up a moved binary or a new port
+        if let Err(e) = manager.deactivate(&path) {
+            warn!("Could not unload the previous {}: {}", manager.name(), e);
+        }
+    }
+    for dir in [path.parent(), log_file.parent()].into_iter().flatten() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(&path, manager.render(&exe, port, &log_file))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    manager.activate(&path)?;
+    info!("Installed {} at {}", manager.name(), path.display());
+    Ok((manager, path))
+}
+
+/// Unload and delete the entry; `None` if there wasn't one
+pub fn uninstall() -> Result<Option<PathBuf>> {
+    let manager = ServiceManager::current()?;
+    let path = manager.entry_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    if let Err(e) = manager.deactivate(&path) {
+        warn!("Could not unload {}: {}", manager.name(), e);
+    }
+    std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    if manager == ServiceManager::Systemd {
+        let _ = run("systemctl", &["--user", "daemon-reload"]);
+    }
+    Ok(Some(path))
+}
+
+/// Path of the installed entry, if any
+pub fn installed() -> Result<Option<PathBuf>> {
+    let path = ServiceManager::current()?.entry_path()?;
+    Ok(path.exists().then_some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_quote_paths_with_spaces_and_specials() {
+        let exe = Path::new("/opt/Key Keep/100%/kkcli");
+        let log = Path::new("/home/u/.keepkey/kkcli/kkcli-daemon.log");
+
+        let unit = ServiceManager::Systemd.render(exe, 1646, log);
+        assert!(unit.contains(r#"ExecStart="/opt/Key Keep/100%%/kkcli" server --port 1646 --daemon"#));
+
+        let plist = ServiceManager::Launchd.render(Path::new("/Apps/K&K/kkcli"), 1700, log);
+        assert!(plist.contains("<string>/Apps/K&amp;K/kkcli</string>"));
+        assert!(plist.contains("<string>1700</string>"));
+    }
+}
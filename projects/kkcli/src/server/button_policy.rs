@@ -0,0 +1,161 @@
+//! Host-side confirmation policy.
+//!
+//! Firmware policies (ApplyPolicies) cover what the device itself enforces.
+//! These settings add host-level rules: force on-device display of addresses,
+//! restrict OP_RETURN outputs, and require that a high-fee transaction was
+//! actually confirmed on the device. The signing flow records every
+//! ButtonRequest the device sends and refuses to release a signed transaction
+//! when a required confirmation never happened.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::messages::ButtonRequestType;
+use super::cache::DeviceCache;
+use super::routes::BitcoinSignRequest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OpReturnPolicy {
+    /// Sign OP_RETURN outputs like any other output
+    Allow,
+    /// Only release the signature if the device asked to confirm every output
+    Confirm,
+    /// Refuse to sign transactions with OP_RETURN outputs
+    Deny,
+}
+
+impl OpReturnPolicy {
+    fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "confirm" => Self::Confirm,
+            "deny" => Self::Deny,
+            _ => Self::Allow,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Confirm => "confirm",
+            Self::Deny => "deny",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ButtonPolicy {
+    /// Always show addresses on the device before returning them (bypasses the address cache)
+    pub confirm_address_display: bool,
+    pub op_return: OpReturnPolicy,
+    /// Fees above this many satoshis must be confirmed on the device
+    pub fee_confirm_threshold_sats: Option<u64>,
+}
+
+impl ButtonPolicy {
+    pub async fn load(cache: &DeviceCache) -> Result<Self> {
+        let confirm_address_display = cache
+            .get_config("policy_confirm_address_display")
+            .await?
+            .map(|v| v.trim() == "true")
+            .unwrap_or(false);
+        let op_return = cache
+            .get_config("policy_op_return")
+            .await?
+            .map(|v| OpReturnPolicy::parse(&v))
+            .unwrap_or(OpReturnPolicy::Allow);
+        let fee_confirm_threshold_sats = cache
+            .get_config("policy_fee_confirm_threshold_sats")
+            .await?
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|v| *v > 0);
+        Ok(Self {
+            confirm_address_display,
+            op_return,
+            fee_confirm_threshold_sats,
+        })
+    }
+
+    pub async fn save(&self, cache: &DeviceCache) -> Result<()> {
+        cache
+            .set_config(
+                "policy_confirm_address_display",
+                if self.confirm_address_display { "true" } else { "false" },
+                Some("Always show addresses on the device before returning them"),
+            )
+            .await?;
+        cache
+            .set_config(
+                "policy_op_return",
+                self.op_return.as_str(),
+                Some("OP_RETURN outputs: allow, confirm (on device) or deny"),
+            )
+            .await?;
+        cache
+            .set_config(
+                "policy_fee_confirm_threshold_sats",
+                &self.fee_confirm_threshold_sats.unwrap_or(0).to_string(),
+                Some("Fees above this many satoshis must be confirmed on the device (0 disables)"),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Check a transaction before it reaches the device and work out which
+    /// confirmations the device must ask for
+    pub fn required_confirmations(&self, request: &BitcoinSignRequest) -> Result<RequiredConfirmations> {
+        let op_returns = request.outputs.iter().filter(|o| o.is_op_return()).count();
+        if op_returns > 0 && self.op_return == OpReturnPolicy::Deny {
+            return Err(anyhow!("Policy violation: OP_RETURN outputs are not allowed"));
+        }
+
+        let external_outputs = request.outputs.iter().filter(|o| o.address_n.is_none()).count();
+        let confirm_outputs = if op_returns > 0 && self.op_return == OpReturnPolicy::Confirm {
+            external_outputs
+        } else {
+            0
+        };
+
+        let fee = match self.fee_confirm_threshold_sats {
+            Some(threshold) => {
+                let inputs: u64 = request.inputs.iter().filter_map(|i| i.amount.parse::<u64>().ok()).sum();
+                let outputs: u64 = request.outputs.iter().filter_map(|o| o.amount.parse::<u64>().ok()).sum();
+                inputs.saturating_sub(outputs) > threshold
+            }
+            None => false,
+        };
+
+        Ok(RequiredConfirmations { confirm_outputs, fee })
+    }
+}
+
+/// Confirmations the device must have asked for before a signature is released
+#[derive(Debug, Default)]
+pub struct RequiredConfirmations {
+    confirm_outputs: usize,
+    fee: bool,
+}
+
+impl RequiredConfirmations {
+    /// Verify against the ButtonRequest codes seen while signing
+    pub fn verify(&self, seen: &[i32]) -> Result<()> {
+        let count = |t: ButtonRequestType| seen.iter().filter(|c| **c == t as i32).count();
+
+        if count(ButtonRequestType::ButtonRequestConfirmOutput) < self.confirm_outputs {
+            return Err(anyhow!(
+                "Policy violation: device confirmed {} of {} outputs",
+                count(ButtonRequestType::ButtonRequestConfirmOutput),
+                self.confirm_outputs
+            ));
+        }
+        if self.fee
+            && count(ButtonRequestType::ButtonRequestFeeOverThreshold) == 0
+            && count(ButtonRequestType::ButtonRequestSignTx) == 0
+        {
+            return Err(anyhow!("Policy violation: fee above threshold was not confirmed on the device"));
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,22 @@
 use anyhow::{anyhow, Result};
 use rusqlite::{Connection, params, OptionalExtension};
 use serde::{Serialize, Deserialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use std::path::PathBuf;
 use tracing::{debug, error, info, warn};
 use crate::server::routes;
+use crate::server::wallet::supports_taproot;
+use super::path_templates::{active_paths, same_path, NetworkKind, PathTemplate, ScriptTypeCoverage};
 use tokio;
 
+lazy_static::lazy_static! {
+    /// One handle per database file for the whole process. Every `open` of the
+    /// same file shares its connection and memory cache, so a write made
+    /// through one handle is visible through all of them.
+    static ref OPEN_CACHES: std::sync::Mutex<HashMap<PathBuf, DeviceCache>> = std::sync::Mutex::new(HashMap::new());
+}
+
 #[derive(Clone)]
 pub struct DeviceCache {
     db: Arc<tokio::sync::Mutex<Connection>>,
@@ -110,6 +119,143 @@ pub struct PortfolioSummary {
     pub last_updated: i64,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PortfolioSnapshotEntry {
+    pub snapshot_date: String,
+    pub caip: String,
+    pub pubkey: String,
+    pub symbol: Option<String>,
+    pub balance: String,
+    pub price_usd: String,
+    pub value_usd: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RealizedTx {
+    pub txid: String,
+    pub caip: String,
+    pub amount_sats: i64,
+    pub fee_sats: i64,
+    pub block_height: Option<u64>,
+    pub block_time: i64,
+    pub price_usd: Option<String>,
+}
+
+/// An xpub cached at frontload, with what is needed to derive it again
+#[derive(Clone, Debug)]
+pub struct CachedXpub {
+    pub coin: String,
+    /// Script type the xpub was requested for (`p2pkh`, `p2sh-p2wpkh`, `p2wpkh`)
+    pub script_type: String,
+    pub path: Vec<u32>,
+    pub xpub: String,
+}
+
+/// One account from gap-limit discovery
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredAccount {
+    /// CAIP-2 chain id
+    pub network: String,
+    pub script_type: String,
+    /// Unhardened account index
+    pub account: u32,
+    /// Receive and change addresses with on-chain history
+    pub used_addresses: u32,
+    /// Highest receive index with history
+    pub last_receive: Option<u32>,
+    /// Highest change index with history
+    pub last_change: Option<u32>,
+    /// Unix seconds
+    pub scanned_at: i64,
+}
+
+/// Rows removed when a device is forgotten
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ForgottenDevice {
+    pub addresses: usize,
+    pub xpubs: usize,
+    pub balances: usize,
+    pub transactions: usize,
+}
+
+/// History undone because the blocks it confirmed in were reorged out
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainRollback {
+    /// Realized transactions removed; the next sync adds back those mined again
+    pub realized_txids: Vec<String>,
+    /// Broadcast transactions sent back to confirmation tracking
+    pub broadcast_txids: Vec<String>,
+    /// Cached balances marked stale so they are fetched again
+    pub stale_balances: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DashboardToken {
+    pub id: String,
+    pub label: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+/// A client paired through `POST /auth/pair`, with what it has been doing
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PairedClient {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub image_url: String,
+    /// Last four characters of the API key
+    pub key_hint: String,
+    pub created_at: i64,
+    pub last_seen_at: Option<i64>,
+    pub request_count: u64,
+    /// Scopes the key was granted at pairing
+    pub granted_scopes: Vec<String>,
+    /// Requests broken down by scope, most used first
+    pub scopes: Vec<ClientScopeUsage>,
+    /// When the key expires unless used again; None once expired or revoked, or with expiry off
+    pub expires_at: Option<i64>,
+    pub expired_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientScopeUsage {
+    /// read, address, sign or admin
+    pub scope: String,
+    pub request_count: u64,
+    pub last_used_at: i64,
+}
+
+/// What a presented API key turned out to be
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientKeyStatus {
+    /// Not issued by pairing; left alone
+    Unknown,
+    Active(String),
+    Expired,
+    Revoked,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxHistoryEntry {
+    pub txid: String,
+    pub device_id: Option<String>,
+    pub raw_tx: String,
+    pub spent_outpoints: Vec<String>,
+    pub status: String,
+    pub confirmations: u32,
+    pub block_height: Option<u64>,
+    pub block_hash: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ConfigEntry {
     pub key: String,
@@ -117,6 +263,101 @@ pub struct ConfigEntry {
     pub description: Option<String>,
 }
 
+/// Gap limit when `gap_limit` isn't configured (BIP-44's recommendation)
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+// Upper bound for `gap_limit`; every step is a device call and a backend query
+pub const MAX_GAP_LIMIT: u32 = 200;
+
+/// A host-side table carried in a metadata backup
+struct BackupTable {
+    table: &'static str,
+    columns: &'static [&'static str],
+    /// Columns identifying a row; a restored row matching an existing one is skipped
+    key: &'static [&'static str],
+    /// Which rows to take
+    filter: &'static str,
+}
+
+/// Settings, accounts and pairings: what a new machine can't get back from the
+/// device or the chain. Secrets never live in these tables.
+const BACKUP_TABLES: &[BackupTable] = &[
+    BackupTable {
+        table: "config",
+        columns: &["key", "value", "description"],
+        key: &["key"],
+        filter: "1",
+    },
+    BackupTable {
+        table: "paths",
+        columns: &[
+            "device_id", "note", "blockchain", "symbol", "symbol_swap_kit", "networks", "script_type",
+            "available_script_types", "type", "address_n_list", "address_n_list_master", "curve", "show_display",
+        ],
+        key: &["device_id", "address_n_list"],
+        filter: "device_id IS NOT NULL",
+    },
+    BackupTable {
+        table: "discovered_accounts",
+        columns: &["device_id", "network", "script_type", "account", "used_addresses", "last_receive", "last_change", "scanned_at"],
+        key: &["device_id", "network", "script_type", "account"],
+        filter: "1",
+    },
+    BackupTable {
+        table: "paired_clients",
+        columns: &[
+            "id", "key_hash", "key_hint", "name", "url", "image_url", "created_at", "last_seen_at", "request_count",
+            "expired_at", "revoked_at",
+        ],
+        key: &["id"],
+        filter: "1",
+    },
+    BackupTable {
+        table: "client_scope_usage",
+        columns: &["client_id", "scope", "request_count", "last_used_at"],
+        key: &["client_id", "scope"],
+        filter: "1",
+    },
+    BackupTable {
+        table: "client_scopes",
+        columns: &["client_id", "scope", "granted_at"],
+        key: &["client_id", "scope"],
+        filter: "1",
+    },
+    BackupTable {
+        table: "dashboard_tokens",
+        columns: &["id", "token_hash", "label", "created_at", "last_used_at", "revoked_at"],
+        key: &["id"],
+        filter: "1",
+    },
+];
+
+/// Rows of the backed-up tables, keyed by table name
+pub type HostMetadata = BTreeMap<String, Vec<serde_json::Map<String, serde_json::Value>>>;
+
+fn sql_to_json(value: rusqlite::types::ValueRef<'_>) -> serde_json::Value {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => f.into(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+        ValueRef::Null | ValueRef::Blob(_) => serde_json::Value::Null,
+    }
+}
+
+fn json_to_sql(value: Option<&serde_json::Value>) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match value {
+        Some(serde_json::Value::Bool(b)) => Value::Integer(i64::from(*b)),
+        Some(serde_json::Value::Number(n)) => n
+            .as_i64()
+            .map(Value::Integer)
+            .or_else(|| n.as_f64().map(Value::Real))
+            .unwrap_or(Value::Null),
+        Some(serde_json::Value::String(s)) => Value::Text(s.clone()),
+        _ => Value::Null,
+    }
+}
+
 fn as_string<S>(x: &i64, s: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
@@ -204,9 +445,48 @@ impl DeviceCache {
         }
     }
 
-    /// Open or create the device cache database
+    /// Open or create the device cache database, or return the handle this
+    /// process already has open
     pub fn open() -> Result<Self> {
-        let cache_dir = Self::get_cache_dir()?;
+        Self::open_shared(Self::get_cache_dir()?, false)
+    }
+    
+    /// Separate cache for `--mock-device`, so simulated key material and
+    /// fixture balances never land in the real one. Prices stay off.
+    pub fn open_simulation() -> Result<Self> {
+        Self::open_shared(Self::get_cache_dir()?.join("mock"), true)
+    }
+    
+    /// Simulation cache in `cache_dir` instead of the user's cache directory
+    pub fn open_simulation_in(cache_dir: PathBuf) -> Result<Self> {
+        Self::open_shared(cache_dir, true)
+    }
+    
+    fn open_shared(cache_dir: PathBuf, simulation: bool) -> Result<Self> {
+        // Held across the connect so two first opens can't race to create separate connections
+        let mut open = OPEN_CACHES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(cache) = open.get(&cache_dir) {
+            debug!("Reusing open device cache for {}", cache_dir.display());
+            return Ok(cache.clone());
+        }
+        
+        let conn = Self::connect(cache_dir.clone())?;
+        if simulation {
+            conn.execute("UPDATE config SET value = 'true' WHERE key = 'offline_mode'", [])?;
+        }
+        let cache = Self::from_connection(conn);
+        open.insert(cache_dir, cache.clone());
+        Ok(cache)
+    }
+    
+    fn from_connection(conn: Connection) -> Self {
+        Self {
+            db: Arc::new(tokio::sync::Mutex::new(conn)),
+            memory_cache: Arc::new(RwLock::new(MemoryCache::default())),
+        }
+    }
+    
+    fn connect(cache_dir: PathBuf) -> Result<Connection> {
         info!("🔍 DEBUG: cache_dir resolved to: {}", cache_dir.display());
         std::fs::create_dir_all(&cache_dir)?;
         
@@ -224,14 +504,11 @@ impl DeviceCache {
         let schema = include_str!("schema.sql");
         conn.execute_batch(schema)?;
         
-        Ok(Self {
-            db: Arc::new(tokio::sync::Mutex::new(conn)),
-            memory_cache: Arc::new(RwLock::new(MemoryCache::default())),
-        })
+        Ok(conn)
     }
     
     /// Get the cache directory based on OS
-    fn get_cache_dir() -> Result<PathBuf> {
+    pub(crate) fn get_cache_dir() -> Result<PathBuf> {
         let home_dir = dirs::home_dir()
             .ok_or_else(|| anyhow!("Could not determine home directory"))?;
         
@@ -297,8 +574,11 @@ impl DeviceCache {
         
         drop(db); // Release the lock before doing more complex operations
         
-        // Get all paths from database to check what should be cached
-        let paths = self.get_paths().await?;
+        // Get all paths in use to check what should be cached
+        let paths: Vec<Path> = self.get_active_paths().await?
+            .into_iter()
+            .filter(|path| self.firmware_supports(path))
+            .collect();
         if paths.is_empty() {
             warn!("📂 No paths found in database - device needs frontload to load default paths");
             return Ok(false);
@@ -694,46 +974,33 @@ impl DeviceCache {
         cache.features.clone()
     }
     
-    /// Get currently loaded device ID (with database fallback)
-    /// 
-    /// ✅ FIXED: Now uses database fallback when memory cache is empty
-    /// This fixes the "No device found in cache" errors that occur during startup.
+    /// Get currently loaded device ID from memory.
+    ///
+    /// Never touches the database, so request handlers can call it without
+    /// stalling the runtime. `warm_device_id` fills it in at startup for a
+    /// device known from an earlier session.
     pub fn get_device_id(&self) -> Option<String> {
-        // First try memory cache (fast path)
-        {
-            let cache = self.memory_cache.read().unwrap();
-            if let Some(device_id) = &cache.device_id {
-                return Some(device_id.clone());
-            }
+        let cache = self.memory_cache.read().unwrap();
+        cache.device_id.clone()
+    }
+    
+    /// Load the device ID from the database into memory if nothing is loaded yet
+    pub async fn warm_device_id(&self) -> Result<Option<String>> {
+        if let Some(device_id) = self.get_device_id() {
+            return Ok(Some(device_id));
         }
-        
-        // Memory cache empty - try database fallback (slower but reliable)
-        match self.get_first_device_from_db() {
-            Ok(Some(device_id)) => {
-                info!("💾 Using database fallback for device ID: {}", device_id);
-                
-                // Populate memory cache for next time
-                {
-                    let mut cache = self.memory_cache.write().unwrap();
-                    cache.device_id = Some(device_id.clone());
-                }
-                
-                Some(device_id)
-            },
-            Ok(None) => {
-                debug!("📭 No device found in database");
-                None
-            },
-            Err(e) => {
-                warn!("❌ Database fallback failed: {}", e);
-                None
-            }
+        let device_id = self.get_first_device_from_db().await?;
+        if let Some(device_id) = &device_id {
+            info!("💾 Loaded device ID {} from database", device_id);
+            let mut cache = self.memory_cache.write().unwrap();
+            cache.device_id.get_or_insert_with(|| device_id.clone());
         }
+        Ok(device_id)
     }
     
-    /// Get first device ID from database (fallback method)
-    pub fn get_first_device_from_db(&self) -> Result<Option<String>> {
-        let db = self.db.blocking_lock();
+    /// Get first device ID from database
+    pub async fn get_first_device_from_db(&self) -> Result<Option<String>> {
+        let db = self.db.lock().await;
         
         let device_id: Option<String> = db.query_row(
             "SELECT device_id FROM devices LIMIT 1",
@@ -744,10 +1011,18 @@ impl DeviceCache {
         Ok(device_id)
     }
     
-    /// Clear all caches for a device
+    /// Clear all caches for a device. Addresses, xpubs, balances and the
+    /// portfolio summary go with the device row (ON DELETE CASCADE); the
+    /// tables without a foreign key are cleared explicitly.
     pub async fn clear_device(&self, device_id: &str) -> Result<()> {
-        let db = self.db.lock().await;
-        db.execute("DELETE FROM devices WHERE device_id = ?1", params![device_id])?;
+        let mut db = self.db.lock().await;
+        let tx = db.transaction()?;
+        tx.execute("DELETE FROM paths WHERE device_id = ?1", params![device_id])?;
+        tx.execute("DELETE FROM portfolio_snapshots WHERE device_id = ?1", params![device_id])?;
+        tx.execute("DELETE FROM realized_transactions WHERE device_id = ?1", params![device_id])?;
+        tx.execute("DELETE FROM discovered_accounts WHERE device_id = ?1", params![device_id])?;
+        tx.execute("DELETE FROM devices WHERE device_id = ?1", params![device_id])?;
+        tx.commit()?;
         
         // Clear memory cache if it's the current device
         let mut cache = self.memory_cache.write().unwrap();
@@ -759,6 +1034,41 @@ impl DeviceCache {
         Ok(())
     }
 
+    /// Remove everything cached for a device, including its label and the
+    /// transactions broadcast from it. Returns `None` if nothing was known.
+    pub async fn forget_device(&self, device_id: &str) -> Result<Option<ForgottenDevice>> {
+        let (known, mut forgotten) = {
+            let db = self.db.lock().await;
+            let count = |sql: &str| -> Result<usize> {
+                Ok(db.query_row(sql, params![device_id], |row| row.get::<_, i64>(0))? as usize)
+            };
+            let known = count("SELECT COUNT(*) FROM devices WHERE device_id = ?1")? > 0;
+            let xpubs = count(
+                "SELECT COUNT(*) FROM cached_addresses WHERE device_id = ?1
+                 AND (address LIKE 'xpub%' OR address LIKE 'ypub%' OR address LIKE 'zpub%')"
+            )?;
+            let forgotten = ForgottenDevice {
+                addresses: count("SELECT COUNT(*) FROM cached_addresses WHERE device_id = ?1")? - xpubs,
+                xpubs,
+                balances: count("SELECT COUNT(*) FROM cached_balances WHERE device_id = ?1")?,
+                transactions: 0,
+            };
+            (known, forgotten)
+        };
+
+        self.clear_device(device_id).await?;
+        forgotten.transactions = self
+            .db
+            .lock()
+            .await
+            .execute("DELETE FROM tx_history WHERE device_id = ?1", params![device_id])?;
+
+        if !known && forgotten.transactions == 0 {
+            return Ok(None);
+        }
+        Ok(Some(forgotten))
+    }
+
     /// Get all paths from the database
     pub async fn get_paths(&self) -> Result<Vec<Path>> {
         let db = self.db.lock().await;
@@ -872,6 +1182,16 @@ impl DeviceCache {
     
     /// Add a new path to the database
     pub async fn add_path(&self, path: &Path) -> Result<i64> {
+        self.insert_path(None, path).await
+    }
+
+    /// Add a path only `device_id` uses, such as an account found by
+    /// discovery; it goes when the device's cache is cleared
+    pub async fn add_device_path(&self, device_id: &str, path: &Path) -> Result<i64> {
+        self.insert_path(Some(device_id), path).await
+    }
+
+    async fn insert_path(&self, device_id: Option<&str>, path: &Path) -> Result<i64> {
         let networks_json = serde_json::to_string(&path.networks)?;
         let available_script_types_json = path.available_script_types.as_ref()
             .map(|types| serde_json::to_string(types))
@@ -888,7 +1208,7 @@ impl DeviceCache {
              curve, show_display) 
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             params![
-                None::<String>, // device_id = NULL for global paths
+                device_id, // NULL for global paths
                 path.note,
                 path.blockchain,
                 path.symbol,
@@ -970,6 +1290,144 @@ impl DeviceCache {
         Ok(())
     }
 
+    /// Whether the cached device's firmware can derive `path`. Taproot paths
+    /// are skipped on firmware that predates BIP-86 instead of failing.
+    pub fn firmware_supports(&self, path: &Path) -> bool {
+        if path.script_type != "p2tr" {
+            return true;
+        }
+        match self.get_cached_features() {
+            Some(features) => supports_taproot(features.major_version, features.minor_version, features.patch_version),
+            None => true,
+        }
+    }
+    
+    /// Paths in use: everything in `paths` except what a path template excludes
+    pub async fn get_active_paths(&self) -> Result<Vec<Path>> {
+        let templates = self.get_path_templates().await?;
+        Ok(active_paths(self.get_paths().await?, &templates))
+    }
+
+    // === Path Template Methods ===
+
+    fn path_template_from_row(row: &rusqlite::Row) -> rusqlite::Result<PathTemplate> {
+        let network_kind: String = row.get(2)?;
+        let network_kind = NetworkKind::parse(&network_kind).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text,
+                format!("unknown network kind {}", network_kind).into())
+        })?;
+        let script_types_json: String = row.get(7)?;
+        let script_types: ScriptTypeCoverage = serde_json::from_str(&script_types_json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(7,
+                rusqlite::types::Type::Text, Box::new(e)))?;
+        Ok(PathTemplate {
+            id: row.get(0)?,
+            network: row.get(1)?,
+            network_kind,
+            coin_name: row.get(3)?,
+            symbol: row.get(4)?,
+            coin_type: row.get(5)?,
+            accounts: row.get(6)?,
+            script_types,
+            enabled: row.get(8)?,
+        })
+    }
+
+    pub async fn get_path_templates(&self) -> Result<Vec<PathTemplate>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare(
+            "SELECT id, network, network_kind, coin_name, symbol, coin_type, accounts, script_types, enabled
+             FROM path_templates ORDER BY id"
+        )?;
+        let templates = stmt.query_map([], Self::path_template_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(templates)
+    }
+
+    pub async fn get_path_template(&self, id: i64) -> Result<Option<PathTemplate>> {
+        let db = self.db.lock().await;
+        let template = db.query_row(
+            "SELECT id, network, network_kind, coin_name, symbol, coin_type, accounts, script_types, enabled
+             FROM path_templates WHERE id = ?1",
+            params![id],
+            Self::path_template_from_row,
+        ).optional()?;
+        Ok(template)
+    }
+
+    /// Insert a template; fails if the network already has one
+    pub async fn add_path_template(&self, template: &PathTemplate) -> Result<i64> {
+        let script_types_json = serde_json::to_string(&template.script_types)?;
+        let db = self.db.lock().await;
+        db.execute(
+            "INSERT INTO path_templates
+             (network, network_kind, coin_name, symbol, coin_type, accounts, script_types, enabled)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                template.network,
+                template.network_kind.as_str(),
+                template.coin_name,
+                template.symbol,
+                template.coin_type,
+                template.accounts,
+                script_types_json,
+                template.enabled
+            ],
+        )?;
+        Ok(db.last_insert_rowid())
+    }
+
+    pub async fn update_path_template(&self, id: i64, template: &PathTemplate) -> Result<()> {
+        let script_types_json = serde_json::to_string(&template.script_types)?;
+        let db = self.db.lock().await;
+        let rows_affected = db.execute(
+            "UPDATE path_templates SET network = ?1, network_kind = ?2, coin_name = ?3, symbol = ?4,
+             coin_type = ?5, accounts = ?6, script_types = ?7, enabled = ?8 WHERE id = ?9",
+            params![
+                template.network,
+                template.network_kind.as_str(),
+                template.coin_name,
+                template.symbol,
+                template.coin_type,
+                template.accounts,
+                script_types_json,
+                template.enabled,
+                id
+            ],
+        )?;
+        if rows_affected == 0 {
+            return Err(anyhow!("Path template with ID {} not found", id));
+        }
+        Ok(())
+    }
+
+    pub async fn delete_path_template(&self, id: i64) -> Result<()> {
+        let db = self.db.lock().await;
+        let rows_affected = db.execute("DELETE FROM path_templates WHERE id = ?1", params![id])?;
+        if rows_affected == 0 {
+            return Err(anyhow!("Path template with ID {} not found", id));
+        }
+        Ok(())
+    }
+
+    /// Add the paths enabled templates ask for that aren't in `paths` yet;
+    /// returns how many were added
+    pub async fn ensure_template_paths(&self) -> Result<usize> {
+        let existing = self.get_paths().await?;
+        let mut added = 0;
+        for template in self.get_path_templates().await? {
+            for path in template.expand() {
+                if existing.iter().any(|p| same_path(p, &path)) {
+                    continue;
+                }
+                let id = self.add_path(&path).await?;
+                debug!("Added templated path {} (DB ID: {})", path.note, id);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
     // === Configuration Methods ===
 
     /// Get a configuration value
@@ -1011,128 +1469,1153 @@ impl DeviceCache {
         }
     }
 
-    // === Balance Methods ===
+    /// Get the primary Esplora-compatible chain backend URL from config
+    pub async fn get_chain_backend_url(&self) -> Result<String> {
+        let urls = self.get_chain_backend_urls().await?;
+        urls.into_iter().next().ok_or_else(|| anyhow!("No chain backend configured"))
+    }
 
-    /// Save balances to cache
-    /// 
-    /// 🚨 CRITICAL WARNING: ON DELETE CASCADE DANGER 🚨
-    /// 
-    /// This function previously used "INSERT OR REPLACE" which caused CASCADE deletions!
-    /// The cached_balances table has: FOREIGN KEY (device_id) REFERENCES devices(device_id) ON DELETE CASCADE
-    /// When "INSERT OR REPLACE" encounters a UNIQUE constraint conflict, it:
-    /// 1. DELETES the existing row (triggering CASCADE deletion of ALL related data!)
-    /// 2. INSERTS the new row
-    /// 
-    /// This was the root cause of the cache clearing bug documented in:
-    /// /docs/debugging/resolved_cache_bug_summary_20250609.md
-    /// 
-    /// SOLUTION: Use "INSERT ... ON CONFLICT DO UPDATE" instead, which updates in-place
-    /// without triggering CASCADE deletions.
-    /// 
-    /// ⚠️ NEVER USE "INSERT OR REPLACE" ON TABLES WITH CASCADE FOREIGN KEYS! ⚠️
-    pub async fn save_balances(&self, device_id: &str, balances: &[CachedBalance]) -> Result<()> {
+    /// Get all configured chain backends (comma separated in config), primary first
+    pub async fn get_chain_backend_urls(&self) -> Result<Vec<String>> {
+        let value = match self.get_config("chain_backend_url").await? {
+            Some(value) => value,
+            None => {
+                let default_url = "https://mempool.space/api";
+                self.set_config("chain_backend_url", default_url, Some("Esplora-compatible API used for broadcast and chain queries")).await?;
+                default_url.to_string()
+            }
+        };
+        Ok(value
+            .split(',')
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .collect())
+    }
+
+    /// Get the double-spend policy, preferring an override for the given API key
+    pub async fn get_double_spend_policy(&self, api_key: Option<&str>) -> Result<String> {
+        if let Some(api_key) = api_key {
+            if let Some(policy) = self.get_config(&format!("double_spend_policy:{}", api_key)).await? {
+                return Ok(policy);
+            }
+        }
+        Ok(self.get_config("double_spend_policy").await?.unwrap_or_else(|| "refuse".to_string()))
+    }
+
+    // === Portfolio History Methods ===
+
+    /// Copy the current cached balances into the snapshot for `snapshot_date`
+    pub async fn save_portfolio_snapshot(&self, device_id: &str, snapshot_date: &str, balances: &[CachedBalance]) -> Result<()> {
         let db = self.db.lock().await;
-        let now = chrono::Utc::now().timestamp();
         
         for balance in balances {
-            // 🚨 FIXED: Using INSERT ... ON CONFLICT DO UPDATE instead of INSERT OR REPLACE
-            // to prevent CASCADE deletion of cached data!
             db.execute(
-                "INSERT INTO cached_balances 
-                 (device_id, caip, pubkey, balance, price_usd, value_usd, symbol, network_id, last_updated)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-                 ON CONFLICT(device_id, caip, pubkey) DO UPDATE SET
+                "INSERT INTO portfolio_snapshots
+                 (device_id, snapshot_date, caip, pubkey, symbol, balance, price_usd, value_usd)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(device_id, snapshot_date, caip, pubkey) DO UPDATE SET
+                   symbol = excluded.symbol,
                    balance = excluded.balance,
                    price_usd = excluded.price_usd,
-                   value_usd = excluded.value_usd,
-                   symbol = excluded.symbol,
-                   network_id = excluded.network_id,
-                   last_updated = excluded.last_updated",
+                   value_usd = excluded.value_usd",
                 params![
                     device_id,
+                    snapshot_date,
                     balance.caip,
                     balance.pubkey,
+                    balance.symbol,
                     balance.balance,
                     balance.price_usd,
                     balance.value_usd,
-                    balance.symbol,
-                    balance.network_id,
-                    now
                 ],
             )?;
         }
         
-        // 🚨 FIXED: Clear portfolio summary cache when balances update
-        // This ensures USD values update immediately in the frontend
-        db.execute(
-            "DELETE FROM portfolio_summaries WHERE device_id = ?1",
-            params![device_id],
-        )?;
-        
-        info!("💾 Saved {} balances for device {} and cleared portfolio summary cache", balances.len(), device_id);
+        info!("📸 Saved portfolio snapshot for {} on {} ({} assets)", device_id, snapshot_date, balances.len());
         Ok(())
     }
 
-    /// Get cached balances for a device
-    pub async fn get_cached_balances(&self, device_id: &str) -> Result<Vec<CachedBalance>> {
+    /// Whether a snapshot exists for the device on `snapshot_date`
+    pub async fn has_portfolio_snapshot(&self, device_id: &str, snapshot_date: &str) -> Result<bool> {
+        let db = self.db.lock().await;
+        let count: i64 = db.query_row(
+            "SELECT COUNT(*) FROM portfolio_snapshots WHERE device_id = ?1 AND snapshot_date = ?2",
+            params![device_id, snapshot_date],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Snapshots between two dates (inclusive, `YYYY-MM-DD`), oldest first
+    pub async fn get_portfolio_snapshots(&self, device_id: &str, from_date: &str, to_date: &str) -> Result<Vec<PortfolioSnapshotEntry>> {
         let db = self.db.lock().await;
-        
         let mut stmt = db.prepare(
-            "SELECT id, device_id, caip, pubkey, balance, price_usd, value_usd, 
-             symbol, network_id, last_updated
-             FROM cached_balances WHERE device_id = ?1"
+            "SELECT snapshot_date, caip, pubkey, symbol, balance, price_usd, value_usd
+             FROM portfolio_snapshots
+             WHERE device_id = ?1 AND snapshot_date >= ?2 AND snapshot_date <= ?3
+             ORDER BY snapshot_date, caip, pubkey"
         )?;
-        
-        let rows = stmt.query_map(params![device_id], |row| {
-            Ok(CachedBalance {
-                id: row.get(0)?,
-                device_id: row.get(1)?,
-                caip: row.get(2)?,
-                pubkey: row.get(3)?,
+        let rows = stmt.query_map(params![device_id, from_date, to_date], |row| {
+            Ok(PortfolioSnapshotEntry {
+                snapshot_date: row.get(0)?,
+                caip: row.get(1)?,
+                pubkey: row.get(2)?,
+                symbol: row.get(3)?,
                 balance: row.get(4)?,
                 price_usd: row.get(5)?,
                 value_usd: row.get(6)?,
-                symbol: row.get(7)?,
-                network_id: row.get(8)?,
-                last_updated: row.get(9)?,
             })
         })?;
         
-        let mut balances = Vec::new();
-        for balance in rows {
-            balances.push(balance?);
+        let mut entries = Vec::new();
+        for entry in rows {
+            entries.push(entry?);
         }
-        
-        Ok(balances)
+        Ok(entries)
     }
 
-    /// Check if balances need refresh (older than 1 hour)
-    pub async fn balances_need_refresh(&self, device_id: &str) -> Result<bool> {
+    /// USD price of an asset from the latest snapshot on or before `date`
+    pub async fn get_snapshot_price(&self, device_id: &str, caip: &str, date: &str) -> Result<Option<String>> {
         let db = self.db.lock().await;
-        let one_hour_ago = chrono::Utc::now().timestamp() - 3600; // 1 hour in seconds (was 600 = 10 min)
-        
-        let count: i64 = db.query_row(
-            "SELECT COUNT(*) FROM cached_balances 
-             WHERE device_id = ?1 AND last_updated > ?2",
-            params![device_id, one_hour_ago],
+        let price = db.query_row(
+            "SELECT price_usd FROM portfolio_snapshots
+             WHERE device_id = ?1 AND caip = ?2 AND snapshot_date <= ?3
+             ORDER BY snapshot_date DESC LIMIT 1",
+            params![device_id, caip, date],
             |row| row.get(0),
+        ).optional()?;
+        Ok(price)
+    }
+
+    /// Insert or update a realized transaction. An existing price annotation is kept.
+    pub async fn save_realized_tx(&self, device_id: &str, tx: &RealizedTx) -> Result<()> {
+        let db = self.db.lock().await;
+        db.execute(
+            "INSERT INTO realized_transactions
+             (device_id, txid, caip, amount_sats, fee_sats, block_height, block_time, price_usd)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(device_id, txid, caip) DO UPDATE SET
+               amount_sats = excluded.amount_sats,
+               fee_sats = excluded.fee_sats,
+               block_height = excluded.block_height,
+               block_time = excluded.block_time,
+               price_usd = COALESCE(realized_transactions.price_usd, excluded.price_usd)",
+            params![device_id, tx.txid, tx.caip, tx.amount_sats, tx.fee_sats, tx.block_height, tx.block_time, tx.price_usd],
         )?;
-        
-        // If we have no recent balances, we need refresh
-        let needs_refresh = count == 0;
-        info!("💾 Cache check for device {}: {} fresh balances (< 1h old) → need refresh: {}", 
-            device_id, count, needs_refresh);
-        Ok(needs_refresh)
+        Ok(())
     }
 
-    /// Clear old balances (older than 1 hour)
-    pub async fn clear_old_balances(&self, device_id: &str) -> Result<()> {
+    /// Realized transactions between two unix timestamps (inclusive), oldest first
+    pub async fn get_realized_txs(&self, device_id: &str, from_time: i64, to_time: i64) -> Result<Vec<RealizedTx>> {
         let db = self.db.lock().await;
-        let one_hour_ago = chrono::Utc::now().timestamp() - 3600; // 1 hour in seconds
-        
-        let rows_affected = db.execute(
-            "DELETE FROM cached_balances WHERE device_id = ?1 AND last_updated < ?2",
-            params![device_id, one_hour_ago],
+        let mut stmt = db.prepare(
+            "SELECT txid, caip, amount_sats, fee_sats, block_height, block_time, price_usd
+             FROM realized_transactions
+             WHERE device_id = ?1 AND block_time >= ?2 AND block_time <= ?3
+             ORDER BY block_time, txid"
+        )?;
+        let rows = stmt.query_map(params![device_id, from_time, to_time], |row| {
+            Ok(RealizedTx {
+                txid: row.get(0)?,
+                caip: row.get(1)?,
+                amount_sats: row.get(2)?,
+                fee_sats: row.get(3)?,
+                block_height: row.get(4)?,
+                block_time: row.get(5)?,
+                price_usd: row.get(6)?,
+            })
+        })?;
+        
+        let mut txs = Vec::new();
+        for tx in rows {
+            txs.push(tx?);
+        }
+        Ok(txs)
+    }
+
+    /// Addresses (not xpubs) cached for a device and coin
+    pub async fn get_cached_address_strings(&self, device_id: &str, coin: &str) -> Result<Vec<String>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare(
+            "SELECT DISTINCT address FROM cached_addresses WHERE device_id = ?1 AND coin = ?2"
+        )?;
+        let rows = stmt.query_map(params![device_id, coin], |row| row.get::<_, String>(0))?;
+        
+        let mut addresses = Vec::new();
+        for address in rows {
+            let address = address?;
+            if !address.starts_with("xpub") && !address.starts_with("ypub") && !address.starts_with("zpub") {
+                addresses.push(address);
+            }
+        }
+        Ok(addresses)
+    }
+
+    /// Addresses (not xpubs) cached for a device and coin, keyed by address with their derivation path
+    pub async fn get_cached_address_paths(&self, device_id: &str, coin: &str) -> Result<HashMap<String, Vec<u32>>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare(
+            "SELECT address, derivation_path FROM cached_addresses WHERE device_id = ?1 AND coin = ?2"
+        )?;
+        let rows = stmt.query_map(params![device_id, coin], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        
+        let mut addresses = HashMap::new();
+        for row in rows {
+            let (address, path_json) = row?;
+            if address.starts_with("xpub") || address.starts_with("ypub") || address.starts_with("zpub") {
+                continue;
+            }
+            if let Ok(path) = serde_json::from_str::<Vec<u32>>(&path_json) {
+                addresses.insert(address, path);
+            }
+        }
+        Ok(addresses)
+    }
+
+    /// Xpubs cached for a device (stored with an `_xpub` script type suffix)
+    pub async fn get_cached_xpubs(&self, device_id: &str) -> Result<Vec<CachedXpub>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare(
+            "SELECT coin, script_type, derivation_path, address FROM cached_addresses
+             WHERE device_id = ?1 AND script_type LIKE '%\\_xpub' ESCAPE '\\'"
+        )?;
+        let rows = stmt.query_map(params![device_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        })?;
+        
+        let mut xpubs = Vec::new();
+        for row in rows {
+            let (coin, script_type, path_json, xpub) = row?;
+            let path = match serde_json::from_str::<Vec<u32>>(&path_json) {
+                Ok(path) => path,
+                Err(_) => {
+                    warn!("Skipping cached xpub with unreadable path {:?}", path_json);
+                    continue;
+                }
+            };
+            xpubs.push(CachedXpub {
+                coin,
+                script_type: script_type.trim_end_matches("_xpub").to_string(),
+                path,
+                xpub,
+            });
+        }
+        Ok(xpubs)
+    }
+
+    /// Write a reviewed xpub sync in one transaction: `upserts` are cached as
+    /// given and `removals` deleted. Addresses cached under an account whose
+    /// xpub is replaced or removed were derived from the old key and are
+    /// dropped as well; returns how many.
+    pub async fn apply_xpub_changes(
+        &self,
+        device_id: &str,
+        upserts: &[CachedXpub],
+        removals: &[CachedXpub],
+    ) -> Result<usize> {
+        let now = chrono::Utc::now().timestamp();
+        let mut stale_accounts = Vec::new();
+        let mut dropped = 0;
+        {
+            let mut db = self.db.lock().await;
+            let tx = db.transaction()?;
+            for xpub in upserts {
+                let path_json = serde_json::to_string(&xpub.path)?;
+                let script_type = format!("{}_xpub", xpub.script_type);
+                let previous: Option<String> = tx
+                    .query_row(
+                        "SELECT address FROM cached_addresses
+                         WHERE device_id = ?1 AND coin = ?2 AND script_type = ?3 AND derivation_path = ?4",
+                        params![device_id, xpub.coin, script_type, path_json],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                tx.execute(
+                    "INSERT INTO cached_addresses
+                     (device_id, coin, script_type, derivation_path, address, pubkey, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, NULL, ?6)
+                     ON CONFLICT(device_id, coin, script_type, derivation_path) DO UPDATE SET
+                       address = excluded.address,
+                       created_at = excluded.created_at",
+                    params![device_id, xpub.coin, script_type, path_json, xpub.xpub, now],
+                )?;
+                if previous.is_some_and(|previous| previous != xpub.xpub) {
+                    stale_accounts.push(xpub);
+                }
+            }
+            for xpub in removals {
+                tx.execute(
+                    "DELETE FROM cached_addresses
+                     WHERE device_id = ?1 AND coin = ?2 AND script_type = ?3 AND derivation_path = ?4",
+                    params![device_id, xpub.coin, format!("{}_xpub", xpub.script_type), serde_json::to_string(&xpub.path)?],
+                )?;
+                stale_accounts.push(xpub);
+            }
+            for account in &stale_accounts {
+                // Paths are stored as JSON arrays, so an account's addresses share its prefix
+                let account_json = serde_json::to_string(&account.path)?;
+                let prefix = format!("{},%", account_json.trim_end_matches(']'));
+                dropped += tx.execute(
+                    "DELETE FROM cached_addresses
+                     WHERE device_id = ?1 AND coin = ?2 AND script_type = ?3 AND derivation_path LIKE ?4",
+                    params![device_id, account.coin, account.script_type, prefix],
+                )?;
+            }
+            tx.commit()?;
+        }
+
+        let mut cache = self.memory_cache.write().unwrap();
+        if cache.device_id.as_deref() == Some(device_id) {
+            for xpub in upserts {
+                let key = AddressKey {
+                    coin: xpub.coin.clone(),
+                    script_type: format!("{}_xpub", xpub.script_type),
+                    path: xpub.path.clone(),
+                };
+                cache.addresses.insert(key, CachedAddress { address: xpub.xpub.clone(), pubkey: None });
+            }
+            for xpub in removals {
+                cache.addresses.retain(|key, _| {
+                    !(key.coin == xpub.coin && key.script_type == format!("{}_xpub", xpub.script_type) && key.path == xpub.path)
+                });
+            }
+            for account in &stale_accounts {
+                cache.addresses.retain(|key, _| {
+                    !(key.coin == account.coin
+                        && key.script_type == account.script_type
+                        && key.path.len() > account.path.len()
+                        && key.path.starts_with(&account.path))
+                });
+            }
+        }
+
+        info!(
+            "Synced xpubs for device {}: {} written, {} removed, {} stale address(es) dropped",
+            device_id,
+            upserts.len(),
+            removals.len(),
+            dropped
+        );
+        Ok(dropped)
+    }
+
+    /// Drop everything derived from the device's keys (addresses, xpubs,
+    /// balances, portfolio summary) so the next frontload asks the device
+    /// again. Paths, features and transaction history are kept. Returns the
+    /// number of cached addresses and xpubs removed.
+    pub async fn invalidate_key_material(&self, device_id: &str) -> Result<usize> {
+        let removed = {
+            let mut db = self.db.lock().await;
+            let tx = db.transaction()?;
+            let removed = tx.execute("DELETE FROM cached_addresses WHERE device_id = ?1", params![device_id])?;
+            tx.execute("DELETE FROM cached_balances WHERE device_id = ?1", params![device_id])?;
+            tx.execute("DELETE FROM portfolio_summaries WHERE device_id = ?1", params![device_id])?;
+            tx.commit()?;
+            removed
+        };
+        
+        let mut cache = self.memory_cache.write().unwrap();
+        if cache.device_id.as_deref() == Some(device_id) {
+            cache.addresses.clear();
+        }
+        
+        warn!("Invalidated {} cached addresses/xpubs and balances for device {}", removed, device_id);
+        Ok(removed)
+    }
+
+    // === Dashboard Token Methods ===
+
+    /// Store a new read-only dashboard token by its hash
+    pub async fn create_dashboard_token(&self, id: &str, token_hash: &str, label: &str) -> Result<DashboardToken> {
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        db.execute(
+            "INSERT INTO dashboard_tokens (id, token_hash, label, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, token_hash, label, now],
+        )?;
+        info!("🔑 Created dashboard token {} ({})", id, label);
+        Ok(DashboardToken {
+            id: id.to_string(),
+            label: label.to_string(),
+            created_at: now,
+            last_used_at: None,
+            revoked_at: None,
+        })
+    }
+
+    /// All dashboard tokens, including revoked ones, newest first
+    pub async fn list_dashboard_tokens(&self) -> Result<Vec<DashboardToken>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare(
+            "SELECT id, label, created_at, last_used_at, revoked_at FROM dashboard_tokens ORDER BY created_at DESC"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(DashboardToken {
+                id: row.get(0)?,
+                label: row.get(1)?,
+                created_at: row.get(2)?,
+                last_used_at: row.get(3)?,
+                revoked_at: row.get(4)?,
+            })
+        })?;
+        
+        let mut tokens = Vec::new();
+        for token in rows {
+            tokens.push(token?);
+        }
+        Ok(tokens)
+    }
+
+    /// Revoke a dashboard token. Returns false if no active token has that id.
+    pub async fn revoke_dashboard_token(&self, id: &str) -> Result<bool> {
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        let updated = db.execute(
+            "UPDATE dashboard_tokens SET revoked_at = ?2 WHERE id = ?1 AND revoked_at IS NULL",
+            params![id, now],
+        )?;
+        if updated > 0 {
+            info!("🔑 Revoked dashboard token {}", id);
+        }
+        Ok(updated > 0)
+    }
+
+    /// Look up an active (not revoked) token by hash and record its use
+    pub async fn use_dashboard_token(&self, token_hash: &str) -> Result<Option<String>> {
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        let id: Option<String> = db.query_row(
+            "SELECT id FROM dashboard_tokens WHERE token_hash = ?1 AND revoked_at IS NULL",
+            params![token_hash],
+            |row| row.get(0),
+        ).optional()?;
+        if let Some(id) = &id {
+            db.execute("UPDATE dashboard_tokens SET last_used_at = ?2 WHERE id = ?1", params![id, now])?;
+        }
+        Ok(id)
+    }
+
+    // === Paired Client Methods ===
+
+    /// Store a newly paired client by the hash of its API key, with the scopes it was granted
+    pub async fn create_paired_client(
+        &self,
+        id: &str,
+        key_hash: &str,
+        key_hint: &str,
+        pairing: &routes::PairingInfo,
+        scopes: &[&str],
+    ) -> Result<()> {
+        let mut db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        let tx = db.transaction()?;
+        tx.execute(
+            "INSERT INTO paired_clients (id, key_hash, key_hint, name, url, image_url, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, key_hash, key_hint, pairing.name, pairing.url, pairing.image_url, now],
+        )?;
+        for scope in scopes {
+            tx.execute(
+                "INSERT OR IGNORE INTO client_scopes (client_id, scope, granted_at) VALUES (?1, ?2, ?3)",
+                params![id, scope, now],
+            )?;
+        }
+        tx.commit()?;
+        info!("🔑 Paired client {} ({}) with scopes {}", id, pairing.name, scopes.join(", "));
+        Ok(())
+    }
+
+    /// Scopes granted to a paired client's key
+    pub async fn get_client_scopes(&self, id: &str) -> Result<Vec<String>> {
+        let db = self.db.lock().await;
+        Self::client_scopes(&db, id)
+    }
+
+    fn client_scopes(db: &Connection, id: &str) -> Result<Vec<String>> {
+        let mut stmt = db.prepare("SELECT scope FROM client_scopes WHERE client_id = ?1 ORDER BY scope")?;
+        let scopes = stmt.query_map(params![id], |row| row.get(0))?;
+        Ok(scopes.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Whether signing and administration need a paired key (`api_auth` is anything but `off`)
+    pub async fn api_auth_enforced(&self) -> Result<bool> {
+        Ok(self
+            .get_config("api_auth")
+            .await?
+            .map(|v| v.trim().to_ascii_lowercase() != "off")
+            .unwrap_or(true))
+    }
+
+    /// Days without use after which a paired client's key stops working (0 = never)
+    pub async fn get_client_expiry_days(&self) -> Result<u32> {
+        Ok(self
+            .get_config("client_expiry_days")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(90))
+    }
+
+    /// Mark clients idle for longer than `expiry_days` as expired
+    fn expire_idle_clients(db: &Connection, expiry_days: u32, now: i64) -> Result<usize> {
+        if expiry_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = now - i64::from(expiry_days) * 86_400;
+        let expired = db.execute(
+            "UPDATE paired_clients SET expired_at = ?1
+             WHERE expired_at IS NULL AND revoked_at IS NULL AND COALESCE(last_seen_at, created_at) < ?2",
+            params![now, cutoff],
+        )?;
+        if expired > 0 {
+            info!("🔑 Expired {} paired client(s) unused for {} days", expired, expiry_days);
+        }
+        Ok(expired)
+    }
+
+    /// Check a presented API key and, if it is an active pairing, count the request under `scope`
+    pub async fn use_paired_client(&self, key_hash: &str, scope: &str) -> Result<ClientKeyStatus> {
+        let expiry_days = self.get_client_expiry_days().await?;
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        Self::expire_idle_clients(&db, expiry_days, now)?;
+        let client: Option<(String, Option<i64>, Option<i64>)> = db.query_row(
+            "SELECT id, expired_at, revoked_at FROM paired_clients WHERE key_hash = ?1",
+            params![key_hash],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional()?;
+        let id = match client {
+            None => return Ok(ClientKeyStatus::Unknown),
+            Some((_, _, Some(_))) => return Ok(ClientKeyStatus::Revoked),
+            Some((_, Some(_), None)) => return Ok(ClientKeyStatus::Expired),
+            Some((id, None, None)) => id,
+        };
+        db.execute(
+            "UPDATE paired_clients SET last_seen_at = ?2, request_count = request_count + 1 WHERE id = ?1",
+            params![id, now],
+        )?;
+        db.execute(
+            "INSERT INTO client_scope_usage (client_id, scope, request_count, last_used_at) VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT(client_id, scope) DO UPDATE SET request_count = request_count + 1, last_used_at = ?3",
+            params![id, scope, now],
+        )?;
+        Ok(ClientKeyStatus::Active(id))
+    }
+
+    /// Paired clients with their usage, most recently seen first
+    pub async fn list_paired_clients(&self) -> Result<Vec<PairedClient>> {
+        let expiry_days = self.get_client_expiry_days().await?;
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        Self::expire_idle_clients(&db, expiry_days, now)?;
+
+        let mut stmt = db.prepare(
+            "SELECT id, name, url, image_url, key_hint, created_at, last_seen_at, request_count, expired_at, revoked_at
+             FROM paired_clients ORDER BY COALESCE(last_seen_at, created_at) DESC"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(PairedClient {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                url: row.get(2)?,
+                image_url: row.get(3)?,
+                key_hint: row.get(4)?,
+                created_at: row.get(5)?,
+                last_seen_at: row.get(6)?,
+                request_count: row.get(7)?,
+                granted_scopes: Vec::new(),
+                scopes: Vec::new(),
+                expires_at: None,
+                expired_at: row.get(8)?,
+                revoked_at: row.get(9)?,
+            })
+        })?;
+        let mut clients = Vec::new();
+        for client in rows {
+            clients.push(client?);
+        }
+
+        let mut stmt = db.prepare(
+            "SELECT scope, request_count, last_used_at FROM client_scope_usage
+             WHERE client_id = ?1 ORDER BY request_count DESC, scope"
+        )?;
+        for client in &mut clients {
+            let scopes = stmt.query_map(params![client.id], |row| {
+                Ok(ClientScopeUsage {
+                    scope: row.get(0)?,
+                    request_count: row.get(1)?,
+                    last_used_at: row.get(2)?,
+                })
+            })?;
+            for scope in scopes {
+                client.scopes.push(scope?);
+            }
+            client.granted_scopes = Self::client_scopes(&db, &client.id)?;
+            if expiry_days > 0 && client.expired_at.is_none() && client.revoked_at.is_none() {
+                let idle_since = client.last_seen_at.unwrap_or(client.created_at);
+                client.expires_at = Some(idle_since + i64::from(expiry_days) * 86_400);
+            }
+        }
+        Ok(clients)
+    }
+
+    /// Revoke a paired client's key. Returns false if no active client has that id.
+    pub async fn revoke_paired_client(&self, id: &str) -> Result<bool> {
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        let updated = db.execute(
+            "UPDATE paired_clients SET revoked_at = ?2 WHERE id = ?1 AND revoked_at IS NULL",
+            params![id, now],
+        )?;
+        if updated > 0 {
+            info!("🔑 Revoked paired client {}", id);
+        }
+        Ok(updated > 0)
+    }
+
+    // === Metadata Backup Methods ===
+
+    /// Rows of every table in a metadata backup
+    pub async fn export_host_metadata(&self) -> Result<HostMetadata> {
+        let db = self.db.lock().await;
+        let mut metadata = HostMetadata::new();
+        for table in BACKUP_TABLES {
+            let sql = format!("SELECT {} FROM {} WHERE {}", table.columns.join(", "), table.table, table.filter);
+            let mut stmt = db.prepare(&sql)?;
+            let mut rows = stmt.query([])?;
+            let mut exported = Vec::new();
+            while let Some(row) = rows.next()? {
+                let mut object = serde_json::Map::new();
+                for (i, column) in table.columns.iter().enumerate() {
+                    object.insert(column.to_string(), sql_to_json(row.get_ref(i)?));
+                }
+                exported.push(object);
+            }
+            metadata.insert(table.table.to_string(), exported);
+        }
+        Ok(metadata)
+    }
+
+    /// Merge a metadata backup into this cache. Rows that already exist are
+    /// kept as they are, except settings, which the backup overrides. Returns
+    /// rows written per table.
+    pub async fn import_host_metadata(&self, metadata: &HostMetadata) -> Result<BTreeMap<String, usize>> {
+        let mut db = self.db.lock().await;
+        let tx = db.transaction()?;
+        let mut restored = BTreeMap::new();
+        for table in BACKUP_TABLES {
+            let Some(rows) = metadata.get(table.table) else {
+                continue;
+            };
+            let matches = table.key.iter().map(|c| format!("{} IS ?", c)).collect::<Vec<_>>().join(" AND ");
+            let exists_sql = format!("SELECT 1 FROM {} WHERE {}", table.table, matches);
+            let placeholders = vec!["?"; table.columns.len()].join(", ");
+            let insert_sql = format!("INSERT INTO {} ({}) VALUES ({})", table.table, table.columns.join(", "), placeholders);
+
+            let mut written = 0;
+            for row in rows {
+                let key: Vec<_> = table.key.iter().map(|c| json_to_sql(row.get(*c))).collect();
+                let exists = tx
+                    .query_row(&exists_sql, rusqlite::params_from_iter(&key), |_| Ok(()))
+                    .optional()?
+                    .is_some();
+                if exists && table.table != "config" {
+                    continue;
+                }
+                if exists {
+                    tx.execute(&format!("DELETE FROM {} WHERE {}", table.table, matches), rusqlite::params_from_iter(&key))?;
+                }
+                let values: Vec<_> = table.columns.iter().map(|c| json_to_sql(row.get(*c))).collect();
+                tx.execute(&insert_sql, rusqlite::params_from_iter(&values))?;
+                written += 1;
+            }
+            restored.insert(table.table.to_string(), written);
+        }
+        tx.commit()?;
+        info!("📦 Restored host metadata: {:?}", restored);
+        Ok(restored)
+    }
+
+    // === Fiat Methods ===
+
+    /// Display currency for fiat equivalents (upper-case ISO 4217 code)
+    pub async fn get_fiat_currency(&self) -> Result<String> {
+        Ok(self
+            .get_config("fiat_currency")
+            .await?
+            .map(|c| c.trim().to_ascii_uppercase())
+            .filter(|c| !c.is_empty())
+            .unwrap_or_else(|| "USD".to_string()))
+    }
+
+    /// Exchange rate feed URL (USD base)
+    pub async fn get_fx_rate_url(&self) -> Result<String> {
+        Ok(self
+            .get_config("fx_rate_url")
+            .await?
+            .unwrap_or_else(|| "https://open.er-api.com/v6/latest/USD".to_string()))
+    }
+
+    /// Whether the server should avoid price and exchange rate lookups
+    pub async fn is_offline_mode(&self) -> Result<bool> {
+        Ok(self
+            .get_config("offline_mode")
+            .await?
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(false))
+    }
+
+    /// Cached exchange rate for a currency as `(rate_per_usd, rate_timestamp, fetched_at)`
+    pub async fn get_fx_rate(&self, currency: &str) -> Result<Option<(f64, i64, i64)>> {
+        let db = self.db.lock().await;
+        let rate = db.query_row(
+            "SELECT rate_per_usd, rate_timestamp, fetched_at FROM fx_rates WHERE currency = ?1",
+            params![currency],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional()?;
+        Ok(rate)
+    }
+
+    /// Store exchange rates fetched from `source`
+    pub async fn save_fx_rates(&self, rates: &HashMap<String, f64>, source: &str, rate_timestamp: i64) -> Result<()> {
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        
+        for (currency, rate) in rates {
+            db.execute(
+                "INSERT INTO fx_rates (currency, rate_per_usd, source, rate_timestamp, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(currency) DO UPDATE SET
+                   rate_per_usd = excluded.rate_per_usd,
+                   source = excluded.source,
+                   rate_timestamp = excluded.rate_timestamp,
+                   fetched_at = excluded.fetched_at",
+                params![currency.to_ascii_uppercase(), rate, source, rate_timestamp, now],
+            )?;
+        }
+        
+        debug!("Saved {} exchange rates from {}", rates.len(), source);
+        Ok(())
+    }
+
+    /// Whether REST signing requests need approval from the UI, preferring an override for the given API key
+    pub async fn get_remote_approval_required(&self, api_key: Option<&str>) -> Result<bool> {
+        let mut value = None;
+        if let Some(api_key) = api_key {
+            value = self.get_config(&format!("remote_approval:{}", api_key)).await?;
+        }
+        if value.is_none() {
+            value = self.get_config("remote_approval").await?;
+        }
+        Ok(matches!(value.as_deref().map(str::trim), Some("required") | Some("on") | Some("true")))
+    }
+
+    /// Outpoints (`txid:vout`) the user froze; wallet spends never select them
+    pub async fn get_frozen_outpoints(&self) -> Result<HashSet<String>> {
+        Ok(self
+            .get_config("frozen_outpoints")
+            .await?
+            .unwrap_or_default()
+            .split(',')
+            .map(|outpoint| outpoint.trim().to_string())
+            .filter(|outpoint| !outpoint.is_empty())
+            .collect())
+    }
+
+    /// Replace the set of frozen outpoints
+    pub async fn set_frozen_outpoints(&self, outpoints: &BTreeSet<String>) -> Result<()> {
+        let value = outpoints.iter().cloned().collect::<Vec<_>>().join(",");
+        self.set_config("frozen_outpoints", &value, Some("Comma separated txid:vout outpoints excluded from coin selection")).await
+    }
+
+    // === Transaction History Methods ===
+
+    /// Record a transaction we broadcast
+    pub async fn record_broadcast(&self, txid: &str, device_id: Option<&str>, raw_tx: &str, spent_outpoints: &[String]) -> Result<()> {
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        
+        db.execute(
+            "INSERT INTO tx_history (txid, device_id, raw_tx, spent_outpoints, status, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, 'broadcast', ?5, ?5)
+             ON CONFLICT(txid) DO UPDATE SET updated_at = excluded.updated_at",
+            params![txid, device_id, raw_tx, serde_json::to_string(spent_outpoints)?, now],
+        )?;
+        
+        info!("💾 Recorded broadcast of {} spending {} outpoint(s)", txid, spent_outpoints.len());
+        Ok(())
+    }
+
+    /// Load a transaction from history
+    pub async fn get_tx_history_entry(&self, txid: &str) -> Result<Option<TxHistoryEntry>> {
+        let db = self.db.lock().await;
+        let entry = db.query_row(
+            "SELECT txid, device_id, raw_tx, spent_outpoints, status, confirmations, block_height, block_hash, created_at, updated_at
+             FROM tx_history WHERE txid = ?1",
+            params![txid],
+            Self::tx_history_from_row,
+        ).optional()?;
+        Ok(entry)
+    }
+
+    /// Find earlier broadcasts (other than `txid`) that spend any of the given outpoints.
+    /// Returns `(outpoint, conflicting txid)` pairs.
+    pub async fn find_conflicting_broadcasts(&self, txid: &str, outpoints: &[String]) -> Result<Vec<(String, String)>> {
+        let db = self.db.lock().await;
+        let wanted: HashSet<&str> = outpoints.iter().map(String::as_str).collect();
+        
+        let mut stmt = db.prepare(
+            "SELECT txid, device_id, raw_tx, spent_outpoints, status, confirmations, block_height, block_hash, created_at, updated_at
+             FROM tx_history WHERE txid != ?1"
+        )?;
+        let rows = stmt.query_map(params![txid], Self::tx_history_from_row)?;
+        
+        let mut conflicts = Vec::new();
+        for entry in rows {
+            let entry = entry?;
+            for outpoint in entry.spent_outpoints.iter().filter(|o| wanted.contains(o.as_str())) {
+                conflicts.push((outpoint.clone(), entry.txid.clone()));
+            }
+        }
+        Ok(conflicts)
+    }
+
+    fn tx_history_from_row(row: &rusqlite::Row) -> rusqlite::Result<TxHistoryEntry> {
+        let spent_outpoints: String = row.get(3)?;
+        Ok(TxHistoryEntry {
+            txid: row.get(0)?,
+            device_id: row.get(1)?,
+            raw_tx: row.get(2)?,
+            spent_outpoints: serde_json::from_str(&spent_outpoints).unwrap_or_default(),
+            status: row.get(4)?,
+            confirmations: row.get(5)?,
+            block_height: row.get(6)?,
+            block_hash: row.get(7)?,
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+        })
+    }
+
+    /// Every transaction broadcast for a device, plus those whose signing device is unknown
+    pub async fn get_tx_history(&self, device_id: &str) -> Result<Vec<TxHistoryEntry>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare(
+            "SELECT txid, device_id, raw_tx, spent_outpoints, status, confirmations, block_height, block_hash, created_at, updated_at
+             FROM tx_history WHERE device_id = ?1 OR device_id IS NULL ORDER BY created_at"
+        )?;
+        let rows = stmt.query_map(params![device_id], Self::tx_history_from_row)?;
+        
+        let mut entries = Vec::new();
+        for entry in rows {
+            entries.push(entry?);
+        }
+        Ok(entries)
+    }
+
+    /// Transactions still being tracked towards the confirmation target
+    pub async fn get_tracked_transactions(&self) -> Result<Vec<TxHistoryEntry>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare(
+            "SELECT txid, device_id, raw_tx, spent_outpoints, status, confirmations, block_height, block_hash, created_at, updated_at
+             FROM tx_history WHERE status != 'confirmed' ORDER BY created_at"
+        )?;
+        let rows = stmt.query_map([], Self::tx_history_from_row)?;
+        
+        let mut entries = Vec::new();
+        for entry in rows {
+            entries.push(entry?);
+        }
+        Ok(entries)
+    }
+
+    /// Update confirmation progress for a tracked transaction
+    pub async fn update_tx_confirmations(
+        &self,
+        txid: &str,
+        status: &str,
+        confirmations: u32,
+        block_height: Option<u64>,
+        block_hash: Option<&str>,
+    ) -> Result<()> {
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        db.execute(
+            "UPDATE tx_history SET status = ?2, confirmations = ?3, block_height = ?4, block_hash = ?5, updated_at = ?6
+             WHERE txid = ?1",
+            params![txid, status, confirmations, block_height, block_hash, now],
+        )?;
+        Ok(())
+    }
+
+    // === Chain Block Methods ===
+
+    /// Remember the block at `height` that history was confirmed in. The
+    /// first hash seen for a height is kept, so a reorg can't hide behind a
+    /// later sync.
+    pub async fn record_block(&self, height: u64, hash: &str) -> Result<()> {
+        let db = self.db.lock().await;
+        db.execute(
+            "INSERT OR IGNORE INTO chain_blocks (height, hash) VALUES (?1, ?2)",
+            params![height, hash],
+        )?;
+        Ok(())
+    }
+
+    /// Recorded `(height, hash)` pairs, highest first
+    pub async fn get_recorded_blocks(&self) -> Result<Vec<(u64, String)>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare("SELECT height, hash FROM chain_blocks ORDER BY height DESC")?;
+        let blocks = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(blocks)
+    }
+
+    /// Undo everything confirmed at `fork_height` or above, for every device
+    pub async fn roll_back_chain(&self, fork_height: u64) -> Result<ChainRollback> {
+        let mut db = self.db.lock().await;
+        let tx = db.transaction()?;
+        let txids = |sql: &str| -> Result<Vec<String>> {
+            let mut stmt = tx.prepare(sql)?;
+            let txids = stmt
+                .query_map(params![fork_height], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            Ok(txids)
+        };
+        let mut rollback = ChainRollback {
+            realized_txids: txids("SELECT DISTINCT txid FROM realized_transactions WHERE block_height >= ?1 ORDER BY txid")?,
+            broadcast_txids: txids("SELECT txid FROM tx_history WHERE block_height >= ?1 ORDER BY txid")?,
+            stale_balances: 0,
+        };
+        tx.execute("DELETE FROM realized_transactions WHERE block_height >= ?1", params![fork_height])?;
+        tx.execute(
+            "UPDATE tx_history SET status = 'reorged', confirmations = 0, block_height = NULL, block_hash = NULL,
+             updated_at = ?2 WHERE block_height >= ?1",
+            params![fork_height, chrono::Utc::now().timestamp()],
+        )?;
+        // Balances may count outputs that no longer exist
+        rollback.stale_balances = tx.execute("UPDATE cached_balances SET last_updated = 0", [])?;
+        tx.execute("DELETE FROM portfolio_summaries", [])?;
+        tx.execute("DELETE FROM chain_blocks WHERE height >= ?1", params![fork_height])?;
+        tx.commit()?;
+        Ok(rollback)
+    }
+
+    /// Number of confirmations after which a transaction stops being tracked
+    pub async fn get_confirmation_target(&self) -> Result<u32> {
+        Ok(self
+            .get_config("tx_confirmation_target")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(6))
+    }
+
+    /// Consecutive unused addresses after which discovery stops scanning a chain
+    pub async fn get_gap_limit(&self) -> Result<u32> {
+        Ok(self
+            .get_config("gap_limit")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0 && *n <= MAX_GAP_LIMIT)
+            .unwrap_or(DEFAULT_GAP_LIMIT))
+    }
+
+    /// How long a discovery scan stays fresh before frontload scans again
+    pub async fn get_discovery_interval(&self) -> Result<std::time::Duration> {
+        let hours: u64 = self
+            .get_config("account_discovery_interval_hours")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(24);
+        Ok(std::time::Duration::from_secs(hours * 3600))
+    }
+
+    // === Account Discovery Methods ===
+
+    /// Record what discovery found for one account, replacing the last scan
+    pub async fn save_discovered_account(&self, device_id: &str, account: &DiscoveredAccount) -> Result<()> {
+        let db = self.db.lock().await;
+        db.execute(
+            "INSERT INTO discovered_accounts
+             (device_id, network, script_type, account, used_addresses, last_receive, last_change, scanned_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(device_id, network, script_type, account) DO UPDATE SET
+                used_addresses = excluded.used_addresses,
+                last_receive = excluded.last_receive,
+                last_change = excluded.last_change,
+                scanned_at = excluded.scanned_at",
+            params![
+                device_id,
+                account.network,
+                account.script_type,
+                account.account,
+                account.used_addresses,
+                account.last_receive,
+                account.last_change,
+                account.scanned_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The account map of a device, by network, script type and account
+    pub async fn get_discovered_accounts(&self, device_id: &str) -> Result<Vec<DiscoveredAccount>> {
+        let db = self.db.lock().await;
+        let mut stmt = db.prepare(
+            "SELECT network, script_type, account, used_addresses, last_receive, last_change, scanned_at
+             FROM discovered_accounts WHERE device_id = ?1
+             ORDER BY network, script_type, account",
+        )?;
+        let accounts = stmt
+            .query_map(params![device_id], |row| {
+                Ok(DiscoveredAccount {
+                    network: row.get(0)?,
+                    script_type: row.get(1)?,
+                    account: row.get(2)?,
+                    used_addresses: row.get(3)?,
+                    last_receive: row.get(4)?,
+                    last_change: row.get(5)?,
+                    scanned_at: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(accounts)
+    }
+
+    /// When discovery last ran for a device (Unix seconds)
+    pub async fn last_discovery(&self, device_id: &str) -> Result<Option<i64>> {
+        let db = self.db.lock().await;
+        Ok(db.query_row(
+            "SELECT MAX(scanned_at) FROM discovered_accounts WHERE device_id = ?1",
+            params![device_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    // === Balance Methods ===
+
+    /// Save balances to cache
+    /// 
+    /// 🚨 CRITICAL WARNING: ON DELETE CASCADE DANGER 🚨
+    /// 
+    /// This function previously used "INSERT OR REPLACE" which caused CASCADE deletions!
+    /// The cached_balances table has: FOREIGN KEY (device_id) REFERENCES devices(device_id) ON DELETE CASCADE
+    /// When "INSERT OR REPLACE" encounters a UNIQUE constraint conflict, it:
+    /// 1. DELETES the existing row (triggering CASCADE deletion of ALL related data!)
+    /// 2. INSERTS the new row
+    /// 
+    /// This was the root cause of the cache clearing bug documented in:
+    /// /docs/debugging/resolved_cache_bug_summary_20250609.md
+    /// 
+    /// SOLUTION: Use "INSERT ... ON CONFLICT DO UPDATE" instead, which updates in-place
+    /// without triggering CASCADE deletions.
+    /// 
+    /// ⚠️ NEVER USE "INSERT OR REPLACE" ON TABLES WITH CASCADE FOREIGN KEYS! ⚠️
+    pub async fn save_balances(&self, device_id: &str, balances: &[CachedBalance]) -> Result<()> {
+        let db = self.db.lock().await;
+        let now = chrono::Utc::now().timestamp();
+        
+        for balance in balances {
+            // 🚨 FIXED: Using INSERT ... ON CONFLICT DO UPDATE instead of INSERT OR REPLACE
+            // to prevent CASCADE deletion of cached data!
+            db.execute(
+                "INSERT INTO cached_balances 
+                 (device_id, caip, pubkey, balance, price_usd, value_usd, symbol, network_id, last_updated)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(device_id, caip, pubkey) DO UPDATE SET
+                   balance = excluded.balance,
+                   price_usd = excluded.price_usd,
+                   value_usd = excluded.value_usd,
+                   symbol = excluded.symbol,
+                   network_id = excluded.network_id,
+                   last_updated = excluded.last_updated",
+                params![
+                    device_id,
+                    balance.caip,
+                    balance.pubkey,
+                    balance.balance,
+                    balance.price_usd,
+                    balance.value_usd,
+                    balance.symbol,
+                    balance.network_id,
+                    now
+                ],
+            )?;
+        }
+        
+        // 🚨 FIXED: Clear portfolio summary cache when balances update
+        // This ensures USD values update immediately in the frontend
+        db.execute(
+            "DELETE FROM portfolio_summaries WHERE device_id = ?1",
+            params![device_id],
+        )?;
+        
+        info!("💾 Saved {} balances for device {} and cleared portfolio summary cache", balances.len(), device_id);
+        Ok(())
+    }
+
+    /// Get cached balances for a device
+    pub async fn get_cached_balances(&self, device_id: &str) -> Result<Vec<CachedBalance>> {
+        let db = self.db.lock().await;
+        
+        let mut stmt = db.prepare(
+            "SELECT id, device_id, caip, pubkey, balance, price_usd, value_usd, 
+             symbol, network_id, last_updated
+             FROM cached_balances WHERE device_id = ?1"
+        )?;
+        
+        let rows = stmt.query_map(params![device_id], |row| {
+            Ok(CachedBalance {
+                id: row.get(0)?,
+                device_id: row.get(1)?,
+                caip: row.get(2)?,
+                pubkey: row.get(3)?,
+                balance: row.get(4)?,
+                price_usd: row.get(5)?,
+                value_usd: row.get(6)?,
+                symbol: row.get(7)?,
+                network_id: row.get(8)?,
+                last_updated: row.get(9)?,
+            })
+        })?;
+        
+        let mut balances = Vec::new();
+        for balance in rows {
+            balances.push(balance?);
+        }
+        
+        Ok(balances)
+    }
+
+    /// Check if balances need refresh (older than 1 hour)
+    pub async fn balances_need_refresh(&self, device_id: &str) -> Result<bool> {
+        let db = self.db.lock().await;
+        let one_hour_ago = chrono::Utc::now().timestamp() - 3600; // 1 hour in seconds (was 600 = 10 min)
+        
+        let count: i64 = db.query_row(
+            "SELECT COUNT(*) FROM cached_balances 
+             WHERE device_id = ?1 AND last_updated > ?2",
+            params![device_id, one_hour_ago],
+            |row| row.get(0),
+        )?;
+        
+        // If we have no recent balances, we need refresh
+        let needs_refresh = count == 0;
+        info!("💾 Cache check for device {}: {} fresh balances (< 1h old) → need refresh: {}", 
+            device_id, count, needs_refresh);
+        Ok(needs_refresh)
+    }
+
+    /// Clear old balances (older than 1 hour)
+    pub async fn clear_old_balances(&self, device_id: &str) -> Result<()> {
+        let db = self.db.lock().await;
+        let one_hour_ago = chrono::Utc::now().timestamp() - 3600; // 1 hour in seconds
+        
+        let rows_affected = db.execute(
+            "DELETE FROM cached_balances WHERE device_id = ?1 AND last_updated < ?2",
+            params![device_id, one_hour_ago],
         )?;
         
         if rows_affected > 0 {
@@ -1348,6 +2831,22 @@ mod tests {
         }
     }
 
+    /// Opening the same database twice must hand back the same connection and memory cache
+    #[tokio::test]
+    async fn test_open_shares_one_handle_per_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = DeviceCache::open_shared(temp_dir.path().to_path_buf(), false).unwrap();
+        let second = DeviceCache::open_shared(temp_dir.path().to_path_buf(), false).unwrap();
+        assert!(Arc::ptr_eq(&first.db, &second.db));
+
+        first.save_features(&mock_routes_features(), "shared-device").await.unwrap();
+        assert_eq!(second.get_device_id().as_deref(), Some("shared-device"));
+
+        let other_dir = TempDir::new().unwrap();
+        let other = DeviceCache::open_shared(other_dir.path().to_path_buf(), false).unwrap();
+        assert!(!Arc::ptr_eq(&first.db, &other.db));
+    }
+
     /// Test that reproduces the exact startup cache loading bug scenario
     #[tokio::test] 
     async fn test_startup_cache_loading_bug_reproduction() {
@@ -3,26 +3,209 @@ use tracing::{info, debug, error, warn};
 use hex;
 use serde_json;
 use crate::messages::{self, Message};
-use crate::transport::{UsbTransport, ProtocolAdapter};
+use crate::transport::{DeviceTransport, ProtocolAdapter};
 use crate::server::routes;
-use super::device_cache::{DeviceCache, CachedBalance};
-use rusb::GlobalContext;
+use crate::server::progress::{Progress, ProgressOperation, ProgressSink};
+use crate::server::chain::ChainBackend;
+use super::device_cache::{DeviceCache, CachedBalance, CachedXpub, DiscoveredAccount, Path};
+use super::path_templates::{same_path, NetworkKind};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+/// Result of re-deriving a cached xpub on connect
+#[derive(Debug)]
+pub enum XpubCheck {
+    /// No xpubs cached for this device yet
+    NothingCached,
+    Matched(CachedXpub),
+    /// The device derived something else: the seed or passphrase changed, or
+    /// the cache is corrupt
+    Mismatch { cached: CachedXpub, from_device: String },
+}
+
+/// Which accounts and addresses a frontload run derives. The default is what
+/// startup loads: every active path, first 5 receive addresses.
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontloadScope {
+    /// Account indexes to load, e.g. `[0, 2]`; all accounts when omitted
+    #[serde(default)]
+    pub accounts: Option<Vec<u32>>,
+    /// Script types to load ("p2pkh", "p2sh-p2wpkh", "p2wpkh", ...); all when omitted
+    #[serde(default)]
+    pub script_types: Option<Vec<String>>,
+    /// Addresses per chain, starting at index 0
+    #[serde(default = "default_address_count")]
+    pub address_count: u32,
+    /// Also derive change (chain 1) addresses, not just receive
+    #[serde(default)]
+    pub include_change: bool,
+    /// Run gap-limit account discovery even if the last scan is recent
+    #[serde(default)]
+    pub rediscover: bool,
+}
+
+fn default_address_count() -> u32 {
+    5
+}
+
+// Upper bound for `addressCount`; deeper scans belong to gap-limit discovery
+pub const MAX_FRONTLOAD_ADDRESSES: u32 = 100;
+const HARDENED: u32 = 0x8000_0000;
+
+impl Default for FrontloadScope {
+    fn default() -> Self {
+        Self {
+            accounts: None,
+            script_types: None,
+            address_count: default_address_count(),
+            include_change: false,
+            rediscover: false,
+        }
+    }
+}
+
+impl FrontloadScope {
+    pub fn validate(&self) -> Result<()> {
+        if self.address_count == 0 || self.address_count > MAX_FRONTLOAD_ADDRESSES {
+            return Err(anyhow::anyhow!("addressCount must be between 1 and {}", MAX_FRONTLOAD_ADDRESSES));
+        }
+        if let Some(accounts) = &self.accounts {
+            if accounts.is_empty() {
+                return Err(anyhow::anyhow!("accounts must not be empty; omit it to load every account"));
+            }
+            if let Some(account) = accounts.iter().find(|account| **account >= HARDENED) {
+                return Err(anyhow::anyhow!("Account {} must be unhardened", account));
+            }
+        }
+        if matches!(&self.script_types, Some(script_types) if script_types.is_empty()) {
+            return Err(anyhow::anyhow!("scriptTypes must not be empty; omit it to load every script type"));
+        }
+        Ok(())
+    }
+
+    /// Whether `path` is one of the accounts this scope asks for
+    pub fn includes(&self, path: &Path) -> bool {
+        if let Some(script_types) = &self.script_types {
+            if !script_types.iter().any(|script_type| script_type == &path.script_type) {
+                return false;
+            }
+        }
+        match &self.accounts {
+            Some(accounts) => path
+                .address_n_list
+                .get(2)
+                .map(|account| accounts.contains(&(account & !HARDENED)))
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+
+    fn chains(&self) -> &'static [u32] {
+        if self.include_change { &[0, 1] } else { &[0] }
+    }
+
+    /// Discovery looks at every account, so it only runs for an unrestricted scope
+    fn covers_everything(&self) -> bool {
+        self.accounts.is_none() && self.script_types.is_none()
+    }
+}
+
+// Discovery stops here even if every account so far had history
+const MAX_DISCOVERED_ACCOUNTS: u32 = 100;
+
+/// Walks one chain of an account until `gap_limit` addresses in a row have
+/// no history
+#[derive(Debug)]
+struct GapScan {
+    gap_limit: u32,
+    next: u32,
+    unused_run: u32,
+    used: u32,
+    last_used: Option<u32>,
+}
+
+impl GapScan {
+    fn new(gap_limit: u32) -> Self {
+        Self { gap_limit, next: 0, unused_run: 0, used: 0, last_used: None }
+    }
+
+    fn done(&self) -> bool {
+        self.unused_run >= self.gap_limit
+    }
+
+    /// Note whether address `self.next` has history and move on to the next one
+    fn record(&mut self, has_history: bool) {
+        if has_history {
+            self.used += 1;
+            self.last_used = Some(self.next);
+            self.unused_run = 0;
+        } else {
+            self.unused_run += 1;
+        }
+        self.next += 1;
+    }
+}
+
+/// What a frontload run covered and how much it had to ask the device for
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontloadReport {
+    pub device_id: String,
+    /// Active paths inside the scope
+    pub paths: usize,
+    /// Addresses and xpubs fetched from the device; the rest were already cached
+    pub populated: usize,
+    /// Used accounts that discovery found past the known paths on this run
+    pub discovered_accounts: usize,
+    pub elapsed_ms: u64,
+}
 
 pub struct DeviceFrontloader {
     cache: DeviceCache,
-    transport_arc: Arc<Mutex<Option<UsbTransport<GlobalContext>>>>,
-    device_obj: rusb::Device<GlobalContext>,
+    transport_arc: Arc<Mutex<Option<DeviceTransport>>>,
+    /// Store made-up balances instead of asking Pioneer (simulated device)
+    fixture_balances: bool,
+    scope: FrontloadScope,
+    progress: Option<ProgressSink>,
 }
 
+// Bitcoin mainnet genesis hash prefix used in bip122 CAIPs
+const BTC_CAIP_PREFIX: &str = "bip122:000000000019d6689c085ae165831e93";
+const FIXTURE_BTC_PRICE_USD: &str = "65000.00";
+
 impl DeviceFrontloader {
-    pub fn new(cache: DeviceCache, transport_arc: Arc<Mutex<Option<UsbTransport<GlobalContext>>>>, device_obj: rusb::Device<GlobalContext>) -> Self {
-        Self { cache, transport_arc, device_obj }
+    pub fn new(cache: DeviceCache, transport_arc: Arc<Mutex<Option<DeviceTransport>>>) -> Self {
+        Self { cache, transport_arc, fixture_balances: false, scope: FrontloadScope::default(), progress: None }
+    }
+
+    /// Only derive the accounts and addresses in `scope`
+    pub fn with_scope(mut self, scope: FrontloadScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn with_fixture_balances(mut self) -> Self {
+        self.fixture_balances = true;
+        self
+    }
+
+    /// Report one step per path and one for the balance fetch
+    pub fn with_progress(mut self, progress: ProgressSink) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    fn report(&self, device_id: &str, progress: Progress) {
+        if let Some(sink) = &self.progress {
+            sink(device_id, &progress);
+        }
     }
 
     /// Frontload all device data - but only populate what's missing
-    pub async fn frontload_all(&self) -> Result<()> {
+    pub async fn frontload_all(&self) -> Result<FrontloadReport> {
         info!("🔄 Starting device frontload process...");
         let start_time = std::time::Instant::now();
         
@@ -38,12 +221,19 @@ impl DeviceFrontloader {
         
         // Always ensure all default paths are loaded (not just if database is empty)
         self.ensure_all_default_paths_loaded().await?;
+
+        // Find used accounts past the templated ones before deriving addresses for them
+        let mut discovered_accounts = 0;
+        if self.discovery_due(&device_id).await? {
+            match self.discover_accounts(&device_id).await {
+                Ok(added) => discovered_accounts = added,
+                Err(e) => warn!("⚠️ Account discovery failed, loading the known accounts only: {}", e),
+            }
+        }
         
         // Always check for missing addresses from database paths
         info!("📍 Checking for missing addresses from database paths...");
-        let mut total_addresses = 0;
-        
-        total_addresses += self.populate_missing_addresses(&device_id).await?;
+        let (paths, total_addresses) = self.populate_missing_addresses(&device_id).await?;
         
         // CRITICAL: Fetch balances during frontload - FAIL FAST if Pioneer unavailable
         info!("💰 Fetching balances from Pioneer API during frontload...");
@@ -51,6 +241,7 @@ impl DeviceFrontloader {
             error!("❌ Failed to fetch balances during frontload - FAILING FAST: {}", e);
             return Err(anyhow::anyhow!("Frontload failed: Cannot fetch balances from Pioneer API: {}", e));
         }
+        self.report(&device_id, Progress::new(ProgressOperation::Frontload, "balances", 1, 1));
         
         let elapsed = start_time.elapsed();
         if total_addresses > 0 {
@@ -74,174 +265,141 @@ impl DeviceFrontloader {
             }
         }
         
-        Ok(())
+        Ok(FrontloadReport {
+            device_id,
+            paths,
+            populated: total_addresses,
+            discovered_accounts,
+            elapsed_ms: elapsed.as_millis() as u64,
+        })
     }
     
-    /// Ensure all default paths from JSON are loaded into database
+    /// Ensure the paths the enabled path templates ask for are in the database
     async fn ensure_all_default_paths_loaded(&self) -> Result<()> {
-        info!("📂 Ensuring all default paths from JSON are loaded into database...");
-        
-        // Load and parse the JSON file
-        let json_content = include_str!("../../default-paths.json");
-        let json_data: serde_json::Value = serde_json::from_str(json_content)
-            .map_err(|e| anyhow::anyhow!("Failed to parse default-paths.json: {}", e))?;
-        
-        let paths_array = json_data["paths"].as_array()
-            .ok_or_else(|| anyhow::anyhow!("No 'paths' array found in default-paths.json"))?;
-        
-        info!("📥 Found {} default paths in JSON", paths_array.len());
-        
-        // Get existing paths from database
-        let existing_paths = self.cache.get_paths().await?;
-        info!("📊 Database currently has {} paths", existing_paths.len());
-        
-        // Create a set of existing path notes for quick lookup
-        let existing_notes: std::collections::HashSet<String> = existing_paths
-            .iter()
-            .map(|p| p.note.clone())
-            .collect();
-        
-        let mut loaded_count = 0;
-        let mut skipped_count = 0;
-        
-        // Convert JSON paths to our Path struct and insert missing ones
-        for (index, path_json) in paths_array.iter().enumerate() {
-            let note = path_json.get("note")
-                .and_then(|n| n.as_str())
-                .unwrap_or("Unknown");
-                
-            if existing_notes.contains(note) {
-                debug!("⏭️ Path already exists: {}", note);
-                skipped_count += 1;
-                continue;
-            }
-            
-            match self.parse_and_insert_path(path_json).await {
-                Ok(id) => {
-                    loaded_count += 1;
-                    info!("✅ Loaded missing path {}: {} (DB ID: {})", 
-                        index + 1, note, id);
-                }
-                Err(e) => {
-                    warn!("❌ Failed to load path {}: {} - Error: {}", 
-                        index + 1, note, e);
-                    // Continue loading other paths instead of failing completely
+        info!("📂 Ensuring templated paths are loaded into database...");
+        let added = self.cache.ensure_template_paths().await?;
+        info!("🎯 Path loading complete: {} new paths loaded", added);
+        Ok(())
+    }
+    
+    /// Whether this run should scan for accounts: not for the simulator or a
+    /// narrowed scope, and not again within the configured interval unless
+    /// the scope asks to rediscover
+    async fn discovery_due(&self, device_id: &str) -> Result<bool> {
+        if self.fixture_balances || !self.scope.covers_everything() {
+            return Ok(false);
+        }
+        if self.scope.rediscover {
+            return Ok(true);
+        }
+        let interval = self.cache.get_discovery_interval().await?.as_secs() as i64;
+        Ok(match self.cache.last_discovery(device_id).await? {
+            Some(scanned_at) => chrono::Utc::now().timestamp() - scanned_at >= interval,
+            None => true,
+        })
+    }
+
+    /// BIP-44 account discovery on the mainnet templates: scan account 0, 1,
+    /// ... of each script type until one has no history, checking addresses
+    /// with the chain backend until `gap_limit` in a row are unused. Every
+    /// scanned account goes into the device's account map; used accounts
+    /// that aren't among the paths yet are added for this device. Returns
+    /// how many were added.
+    async fn discover_accounts(&self, device_id: &str) -> Result<usize> {
+        let chain = ChainBackend::from_cache(&self.cache).await?;
+        let gap_limit = self.cache.get_gap_limit().await?;
+        let existing = self.cache.get_paths().await?;
+        info!("🔭 Discovering accounts (gap limit {}) via {}", gap_limit, chain.base_url());
+
+        // The chain backend serves a single network, mainnet unless configured otherwise
+        let templates = self.cache.get_path_templates().await?
+            .into_iter()
+            .filter(|template| template.enabled && template.network_kind == NetworkKind::Mainnet);
+        let mut added = 0;
+        for template in templates {
+            for script_type in template.covered_script_types() {
+                for account in 0..MAX_DISCOVERED_ACCOUNTS {
+                    let path = match template.account_path(script_type, account) {
+                        Some(path) if self.cache.firmware_supports(&path) => path,
+                        _ => break,
+                    };
+                    self.report(
+                        device_id,
+                        Progress::new(ProgressOperation::Frontload, "discovery", account as u64 + 1, 0)
+                            .with_message(path.note.clone()),
+                    );
+                    let found = self.scan_account(device_id, &chain, &template.network, &path, gap_limit).await?;
+                    self.cache.save_discovered_account(device_id, &found).await?;
+                    if found.used_addresses == 0 {
+                        break;
+                    }
+                    if !existing.iter().any(|known| same_path(known, &path)) {
+                        self.cache.add_device_path(device_id, &path).await?;
+                        info!("🔭 Found used account: {} ({} addresses with history)", path.note, found.used_addresses);
+                        added += 1;
+                    }
                 }
             }
         }
-        
-        info!("🎯 Path loading complete: {} new paths loaded, {} already existed", loaded_count, skipped_count);
-        
-        Ok(())
+        Ok(added)
     }
-    
-    /// Parse a JSON path object and insert it into the database
-    async fn parse_and_insert_path(&self, path_json: &serde_json::Value) -> Result<i64> {
-        use super::device_cache::Path;
-        
-        // Extract required fields
-        let note = path_json.get("note")
-            .and_then(|n| n.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Missing 'note' field"))?
-            .to_string();
-        
-        let script_type = path_json.get("script_type")
-            .and_then(|s| s.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Missing 'script_type' field"))?
-            .to_string();
-        
-        let path_type = path_json.get("type")
-            .and_then(|t| t.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Missing 'type' field"))?
-            .to_string();
-        
-        let curve = path_json.get("curve")
-            .and_then(|c| c.as_str())
-            .unwrap_or("secp256k1")
-            .to_string();
-        
-        let show_display = path_json.get("showDisplay")
-            .and_then(|s| s.as_bool())
-            .unwrap_or(false);
-        
-        // Extract address_n_list
-        let address_n_list = path_json.get("addressNList")
-            .and_then(|a| a.as_array())
-            .ok_or_else(|| anyhow::anyhow!("Missing 'addressNList' field"))?
-            .iter()
-            .map(|v| v.as_u64().ok_or_else(|| anyhow::anyhow!("Invalid addressNList value"))
-                     .map(|n| n as u32))
-            .collect::<Result<Vec<u32>, _>>()?;
-        
-        // Extract address_n_list_master  
-        let address_n_list_master = path_json.get("addressNListMaster")
-            .and_then(|a| a.as_array())
-            .ok_or_else(|| anyhow::anyhow!("Missing 'addressNListMaster' field"))?
-            .iter()
-            .map(|v| v.as_u64().ok_or_else(|| anyhow::anyhow!("Invalid addressNListMaster value"))
-                     .map(|n| n as u32))
-            .collect::<Result<Vec<u32>, _>>()?;
-        
-        // Extract networks
-        let networks = path_json.get("networks")
-            .and_then(|n| n.as_array())
-            .ok_or_else(|| anyhow::anyhow!("Missing 'networks' field"))?
-            .iter()
-            .map(|v| v.as_str().ok_or_else(|| anyhow::anyhow!("Invalid networks value"))
-                     .map(|s| s.to_string()))
-            .collect::<Result<Vec<String>, _>>()?;
-        
-        // Extract available_script_types
-        let available_script_types = path_json.get("available_script_types")
-            .and_then(|a| a.as_array())
-            .map(|arr| arr.iter()
-                 .map(|v| v.as_str().unwrap_or("").to_string())
-                 .collect::<Vec<String>>());
-        
-        // Extract optional fields
-        let blockchain = path_json.get("blockchain")
-            .and_then(|b| b.as_str())
-            .map(|s| s.to_string());
-        
-        let symbol = path_json.get("symbol")
-            .and_then(|s| s.as_str())
-            .map(|s| s.to_string());
-        
-        let symbol_swap_kit = path_json.get("symbol_swap_kit")
-            .and_then(|s| s.as_str())
-            .map(|s| s.to_string());
-        
-        // Create Path struct (global paths, no device_id)
-        let path = Path {
-            id: 0, // Will be assigned by database
-            note,
-            blockchain,
-            symbol,
-            symbol_swap_kit,
-            networks,
-            script_type,
-            available_script_types,
-            path_type,
-            address_n_list,
-            address_n_list_master,
-            curve,
-            show_display,
-        };
-        
-        // Insert into database
-        let id = self.cache.add_path(&path).await?;
-        Ok(id)
+
+    /// Walk the receive and change chains of the account at `path`, deriving
+    /// (and caching) addresses the device hasn't given us yet
+    async fn scan_account(
+        &self,
+        device_id: &str,
+        chain: &ChainBackend,
+        network: &str,
+        path: &Path,
+        gap_limit: u32,
+    ) -> Result<DiscoveredAccount> {
+        let (coin_name, script_type) = self.get_coin_info_from_network_and_path(network, &path.script_type, &path.address_n_list)?;
+        let mut scans = [GapScan::new(gap_limit), GapScan::new(gap_limit)];
+        for (change, scan) in scans.iter_mut().enumerate() {
+            while !scan.done() {
+                let mut address_path = path.address_n_list.clone();
+                address_path.extend([change as u32, scan.next]);
+                if self.cache.get_cached_address(&coin_name, &script_type, &address_path).is_none() {
+                    self.get_and_cache_bitcoin_address(device_id, &coin_name, &script_type, &address_path).await?;
+                }
+                let address = self.cache.get_cached_address(&coin_name, &script_type, &address_path)
+                    .ok_or_else(|| anyhow::anyhow!("Device returned no address for {:?}", address_path))?
+                    .address;
+                scan.record(chain.address_tx_count(&address).await? > 0);
+            }
+        }
+        let [receive, change] = scans;
+        Ok(DiscoveredAccount {
+            network: network.to_string(),
+            script_type: path.script_type.clone(),
+            account: path.address_n_list[2] & !HARDENED,
+            used_addresses: receive.used + change.used,
+            last_receive: receive.last_used,
+            last_change: change.last_used,
+            scanned_at: chrono::Utc::now().timestamp(),
+        })
     }
-    
-    /// Populate only missing addresses based on database paths
-    async fn populate_missing_addresses(&self, device_id: &str) -> Result<usize> {
+
+    /// Populate only missing addresses for the database paths in scope,
+    /// returning (paths in scope, addresses and xpubs fetched)
+    async fn populate_missing_addresses(&self, device_id: &str) -> Result<(usize, usize)> {
         let mut count = 0;
         
         // Get paths from database
-        let paths = self.cache.get_paths().await?;
-        debug!("Found {} paths in database", paths.len());
-        
-        for path in paths {
+        let paths: Vec<Path> = self.cache.get_active_paths().await?
+            .into_iter()
+            .filter(|path| self.scope.includes(path) && self.cache.firmware_supports(path))
+            .collect();
+        debug!("Found {} paths in database within frontload scope", paths.len());
+        let path_count = paths.len();
+        
+        for (index, path) in paths.into_iter().enumerate() {
+            self.report(
+                device_id,
+                Progress::new(ProgressOperation::Frontload, "path", index as u64 + 1, path_count as u64)
+                    .with_message(path.note.clone()),
+            );
             // Check which networks this path supports
             for network in &path.networks {
                 // Determine coin name and script type from network and path
@@ -325,37 +483,39 @@ impl DeviceFrontloader {
         }
         
         info!("📍 Populated {} missing addresses and xpubs from database paths", count);
-        Ok(count)
+        Ok((path_count, count))
     }
     
-    /// Generate individual address paths from account path (first 5 addresses: 0-4)
+    /// Generate individual address paths from account path: the first
+    /// `address_count` receive addresses, plus change when the scope asks for it
     fn generate_address_paths(&self, account_path: &[u32]) -> Vec<Vec<u32>> {
         let mut paths = Vec::new();
         
-        // Generate first 5 receiving addresses (0/0 through 0/4)
-        for i in 0..5 {
-            let mut full_path = account_path.to_vec();
-            
-            // Handle different path structures:
-            // - 3 elements: m/purpose'/coin'/account' -> add /change/address_index
-            // - 5 elements: already m/purpose'/coin'/account'/change/address_index -> use as is for first, modify index for others
-            match full_path.len() {
-                3 => {
-                    // Account-level path, add change and address index
-                    full_path.push(0); // change = 0 (receiving)
-                    full_path.push(i); // address_index
-                }
-                5 => {
-                    // Full address path, just change the last index
-                    full_path[4] = i; // change address_index
-                }
-                _ => {
-                    // Unexpected path length, skip
-                    warn!("Unexpected path length {} for account path {:?}", full_path.len(), account_path);
-                    continue;
+        for &chain in self.scope.chains() {
+            for i in 0..self.scope.address_count {
+                let mut full_path = account_path.to_vec();
+                
+                // Handle different path structures:
+                // - 3 elements: m/purpose'/coin'/account' -> add /change/address_index
+                // - 5 elements: already m/purpose'/coin'/account'/change/address_index -> replace change and index
+                match full_path.len() {
+                    3 => {
+                        // Account-level path, add change and address index
+                        full_path.push(chain);
+                        full_path.push(i);
+                    }
+                    5 => {
+                        full_path[3] = chain;
+                        full_path[4] = i;
+                    }
+                    _ => {
+                        // Unexpected path length, skip
+                        warn!("Unexpected path length {} for account path {:?}", full_path.len(), account_path);
+                        continue;
+                    }
                 }
+                paths.push(full_path);
             }
-            paths.push(full_path);
         }
         
         paths
@@ -583,6 +743,7 @@ impl DeviceFrontloader {
             "p2pkh" => msg.script_type = Some(messages::InputScriptType::Spendaddress as i32),
             "p2wpkh" => msg.script_type = Some(messages::InputScriptType::Spendwitness as i32),
             "p2sh-p2wpkh" => msg.script_type = Some(messages::InputScriptType::Spendp2shwitness as i32),
+            "p2tr" => msg.script_type = Some(messages::InputScriptType::Spendtaproot as i32),
             _ => return Err(anyhow::anyhow!("Unknown script type: {}", script_type)),
         }
         
@@ -688,6 +849,63 @@ impl DeviceFrontloader {
         }
     }
     
+    /// Re-derive one randomly chosen cached xpub and compare it with the cache.
+    /// Checking a different one on each connect covers every account over time
+    /// while costing a single device call.
+    pub async fn verify_random_xpub(&self, device_id: &str) -> Result<XpubCheck> {
+        let xpubs = self.cache.get_cached_xpubs(device_id).await?;
+        let cached = {
+            use rand::seq::SliceRandom;
+            match xpubs.choose(&mut rand::thread_rng()) {
+                Some(cached) => cached.clone(),
+                None => return Ok(XpubCheck::NothingCached),
+            }
+        };
+        
+        info!("🔐 Verifying cached {} {} xpub at {:?} against the device", cached.coin, cached.script_type, cached.path);
+        let from_device = self.fetch_xpub(&cached.coin, &cached.script_type, &cached.path).await?;
+        if from_device == cached.xpub {
+            Ok(XpubCheck::Matched(cached))
+        } else {
+            Ok(XpubCheck::Mismatch { cached, from_device })
+        }
+    }
+    
+    /// Ask the device for the xpub of every UTXO account among the active
+    /// paths in scope, without reading or writing the cache. Fails on the
+    /// first account the device can't derive, so callers never mistake a
+    /// partial answer for accounts that went away.
+    pub async fn derive_xpubs(&self, device_id: &str) -> Result<Vec<CachedXpub>> {
+        let paths: Vec<Path> = self.cache.get_active_paths().await?
+            .into_iter()
+            .filter(|path| self.scope.includes(path) && self.cache.firmware_supports(path))
+            .collect();
+        let mut accounts: Vec<(String, String, Vec<u32>)> = Vec::new();
+        for path in &paths {
+            for network in path.networks.iter().filter(|network| network.starts_with("bip122:")) {
+                match self.get_coin_info_from_network_and_path(network, &path.script_type, &path.address_n_list) {
+                    Ok((coin, script_type)) => {
+                        let account = (coin, script_type, path.address_n_list.clone());
+                        if !accounts.contains(&account) {
+                            accounts.push(account);
+                        }
+                    }
+                    Err(e) => debug!("Skipping unsupported UTXO network {}: {}", network, e),
+                }
+            }
+        }
+
+        let total = accounts.len() as u64;
+        let mut xpubs = Vec::with_capacity(accounts.len());
+        for (index, (coin, script_type, path)) in accounts.into_iter().enumerate() {
+            self.report(device_id, Progress::new(ProgressOperation::Frontload, "xpub", index as u64 + 1, total));
+            let xpub = self.fetch_xpub(&coin, &script_type, &path).await
+                .map_err(|e| anyhow::anyhow!("Failed to derive {} {} xpub at {:?}: {}", coin, script_type, path, e))?;
+            xpubs.push(CachedXpub { coin, script_type, path, xpub });
+        }
+        Ok(xpubs)
+    }
+
     /// Get and cache extended public key (xpub) for UTXO networks
     async fn get_and_cache_xpub(
         &self,
@@ -696,8 +914,26 @@ impl DeviceFrontloader {
         script_type: &str,
         path: &[u32],
     ) -> Result<String> {
+        let xpub = self.fetch_xpub(coin_name, script_type, path).await?;
+        
+        // Save xpub to cache (we'll use the address field to store the xpub)
+        self.cache.save_address(
+            device_id,
+            coin_name,
+            &format!("{}_xpub", script_type), // Mark as xpub variant
+            path,
+            &xpub,
+            None,
+        ).await?;
+        
+        info!("✅ Cached {} {} xpub: {}", coin_name, script_type, xpub);
+        Ok(xpub)
+    }
+    
+    /// Ask the device for the extended public key at an account path
+    async fn fetch_xpub(&self, coin_name: &str, script_type: &str, path: &[u32]) -> Result<String> {
         let mut transport_opt_guard = self.transport_arc.lock().await;
-        let transport = transport_opt_guard.as_mut().ok_or_else(|| anyhow::anyhow!("Transport not available in get_and_cache_xpub"))?;
+        let transport = transport_opt_guard.as_mut().ok_or_else(|| anyhow::anyhow!("Transport not available in fetch_xpub"))?;
         
         // Create GetPublicKey message to get xpub
         let mut msg = messages::GetPublicKey::default();
@@ -710,6 +946,7 @@ impl DeviceFrontloader {
             "p2pkh" => msg.script_type = Some(messages::InputScriptType::Spendaddress as i32), // xpub
             "p2wpkh" => msg.script_type = Some(messages::InputScriptType::Spendwitness as i32), // zpub
             "p2sh-p2wpkh" => msg.script_type = Some(messages::InputScriptType::Spendp2shwitness as i32), // ypub
+            "p2tr" => msg.script_type = Some(messages::InputScriptType::Spendtaproot as i32), // xpub
             _ => return Err(anyhow::anyhow!("Unknown script type for xpub: {}", script_type)),
         }
         
@@ -718,20 +955,9 @@ impl DeviceFrontloader {
         
         match response {
             Message::PublicKey(pubkey_msg) => {
-                if let Some(xpub) = &pubkey_msg.xpub {
+                if let Some(xpub) = pubkey_msg.xpub {
                     if !xpub.is_empty() {
-                        // Save xpub to cache (we'll use the address field to store the xpub)
-                        self.cache.save_address(
-                            device_id,
-                            coin_name,
-                            &format!("{}_xpub", script_type), // Mark as xpub variant
-                            path,
-                            xpub,
-                            None,
-                        ).await?;
-                        
-                        info!("✅ Cached {} {} xpub: {}", coin_name, script_type, xpub);
-                        Ok(xpub.clone())
+                        Ok(xpub)
                     } else {
                         Err(anyhow::anyhow!("Empty xpub returned from device"))
                     }
@@ -783,7 +1009,7 @@ impl DeviceFrontloader {
         info!("{}: Using Pioneer server URL: {}", tag, pioneer_url);
         
         // Get all pubkeys for building asset query
-        let paths = self.cache.get_paths().await?;
+        let paths = self.cache.get_active_paths().await?;
         let mut asset_queries = Vec::new();
         
         for path in paths {
@@ -864,6 +1090,13 @@ impl DeviceFrontloader {
             return Err(anyhow::anyhow!("No cached addresses available for balance lookup"));
         }
         
+        if self.fixture_balances {
+            let cached_balances = fixture_balances(device_id, &asset_queries);
+            info!("{}: 🧪 Saving {} fixture balances instead of querying Pioneer", tag, cached_balances.len());
+            self.cache.save_balances(device_id, &cached_balances).await?;
+            return Ok(());
+        }
+        
         info!("{}: Fetching balances for {} assets from {}", tag, asset_queries.len(), pioneer_url);
         
         // Build the full URL
@@ -1084,6 +1317,40 @@ fn caip_to_network_id(caip: &str) -> String {
     }
 }
 
+/// Deterministic balances for a simulated device: 0.01, 0.02, ... BTC on the
+/// bitcoin xpubs in query order, nothing anywhere else
+fn fixture_balances(device_id: &str, asset_queries: &[serde_json::Value]) -> Vec<CachedBalance> {
+    let price: f64 = FIXTURE_BTC_PRICE_USD.parse().unwrap_or_default();
+    let mut btc_accounts = 0u64;
+    asset_queries
+        .iter()
+        .filter_map(|query| {
+            let caip = query.get("caip")?.as_str()?;
+            let pubkey = query.get("pubkey")?.as_str()?;
+            let (sats, price_usd) = if caip.starts_with(BTC_CAIP_PREFIX) {
+                btc_accounts += 1;
+                (btc_accounts * 1_000_000, FIXTURE_BTC_PRICE_USD)
+            } else {
+                (0, "0.00")
+            };
+            let balance = format!("{}.{:08}", sats / 100_000_000, sats % 100_000_000);
+            let value_usd = if price_usd == "0.00" { 0.0 } else { sats as f64 / 100_000_000.0 * price };
+            Some(CachedBalance {
+                id: 0,
+                device_id: device_id.to_string(),
+                caip: caip.to_string(),
+                pubkey: pubkey.to_string(),
+                balance,
+                price_usd: price_usd.to_string(),
+                value_usd: format!("{:.2}", value_usd),
+                symbol: extract_symbol_from_caip(caip),
+                network_id: Some(caip_to_network_id(caip)),
+                last_updated: chrono::Utc::now().timestamp(),
+            })
+        })
+        .collect()
+}
+
 /// Extract symbol from CAIP
 fn extract_symbol_from_caip(caip: &str) -> Option<String> {
     if caip.contains("bip122:000000000019d6689c085ae165831e93") {
@@ -1412,5 +1679,86 @@ mod tests {
         assert!(cache.get_cached_address("Ethereum", "ethereum", &[44, 60, 0, 0, 0]).is_some());
     }
     
+    
+    #[tokio::test]
+    async fn test_frontload_scope_limits_paths_and_addresses() {
+        let cache = create_test_cache().await;
+        let scope = FrontloadScope {
+            accounts: Some(vec![1]),
+            script_types: Some(vec!["p2wpkh".to_string()]),
+            address_count: 2,
+            include_change: true,
+            rediscover: false,
+        };
+        assert!(scope.validate().is_ok());
+        
+        let template = cache.get_path_templates().await.unwrap().into_iter()
+            .find(|t| t.network == BTC_CAIP_PREFIX)
+            .unwrap();
+        let template = crate::server::cache::PathTemplate { accounts: 2, ..template };
+        let in_scope: Vec<_> = template.expand().into_iter().filter(|p| scope.includes(p)).collect();
+        assert_eq!(in_scope.len(), 1);
+        assert_eq!(in_scope[0].address_n_list, vec![84 | HARDENED, HARDENED, 1 | HARDENED]);
+        
+        let frontloader = DeviceFrontloader::new(cache, Arc::new(Mutex::new(None))).with_scope(scope);
+        let account = &in_scope[0].address_n_list;
+        let addresses = frontloader.generate_address_paths(account);
+        assert_eq!(addresses.len(), 4);
+        assert!(addresses.contains(&[account.as_slice(), &[1, 1]].concat()));
+        
+        let too_many = FrontloadScope { address_count: MAX_FRONTLOAD_ADDRESSES + 1, ..FrontloadScope::default() };
+        assert!(too_many.validate().is_err());
+    }
 
-} 
\ No newline at end of file
+    #[test]
+    fn test_gap_scan_stops_after_gap_limit_unused() {
+        let history = [true, false, false, true, false, false, false];
+        let mut scan = GapScan::new(3);
+        let mut checked = 0;
+        while !scan.done() {
+            scan.record(history.get(scan.next as usize).copied().unwrap_or(false));
+            checked += 1;
+        }
+        assert_eq!(checked, 7);
+        assert_eq!(scan.used, 2);
+        assert_eq!(scan.last_used, Some(3));
+
+        let mut empty = GapScan::new(2);
+        while !empty.done() {
+            empty.record(false);
+        }
+        assert_eq!((empty.next, empty.used, empty.last_used), (2, 0, None));
+    }
+
+    #[tokio::test]
+    async fn test_discovered_accounts_are_remembered_per_device() {
+        let cache = create_test_cache().await;
+        let device_id = "discovery-device";
+        let frontloader = DeviceFrontloader::new(cache.clone(), Arc::new(Mutex::new(None)));
+        assert!(frontloader.discovery_due(device_id).await.unwrap());
+
+        let account = DiscoveredAccount {
+            network: BTC_CAIP_PREFIX.to_string(),
+            script_type: "p2wpkh".to_string(),
+            account: 1,
+            used_addresses: 3,
+            last_receive: Some(4),
+            last_change: None,
+            scanned_at: chrono::Utc::now().timestamp(),
+        };
+        cache.save_discovered_account(device_id, &account).await.unwrap();
+        cache.save_discovered_account(device_id, &DiscoveredAccount { used_addresses: 5, ..account.clone() }).await.unwrap();
+        let map = cache.get_discovered_accounts(device_id).await.unwrap();
+        assert_eq!(map, vec![DiscoveredAccount { used_addresses: 5, ..account }]);
+        assert!(cache.get_discovered_accounts("other-device").await.unwrap().is_empty());
+
+        // A fresh scan isn't repeated unless asked for, and a narrowed scope never scans
+        assert!(!frontloader.discovery_due(device_id).await.unwrap());
+        let forced = DeviceFrontloader::new(cache.clone(), Arc::new(Mutex::new(None)))
+            .with_scope(FrontloadScope { rediscover: true, ..FrontloadScope::default() });
+        assert!(forced.discovery_due(device_id).await.unwrap());
+        let narrowed = DeviceFrontloader::new(cache, Arc::new(Mutex::new(None)))
+            .with_scope(FrontloadScope { accounts: Some(vec![0]), rediscover: true, ..FrontloadScope::default() });
+        assert!(!narrowed.discovery_due(device_id).await.unwrap());
+    }
+}
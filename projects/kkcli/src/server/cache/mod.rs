@@ -1,8 +1,10 @@
 pub mod device_cache;
 pub mod frontload;
+pub mod path_templates;
 
-pub use device_cache::{DeviceCache, CachedAddress, CachedFeatures};
-pub use frontload::DeviceFrontloader;
+pub use device_cache::{DeviceCache, CachedAddress, CachedFeatures, CachedXpub, DiscoveredAccount};
+pub use frontload::{DeviceFrontloader, FrontloadReport, FrontloadScope, XpubCheck};
+pub use path_templates::PathTemplate;
 
 #[cfg(test)]
 mod test_helpers {
@@ -40,6 +42,7 @@ mod test_helpers {
 #[cfg(test)]
 mod integration_tests {
     use super::*;
+    use device_cache::{CachedBalance, RealizedTx};
     use tempfile::tempdir;
     use test_helpers::create_test_features;
     
@@ -207,4 +210,342 @@ mod integration_tests {
             assert_eq!(address.unwrap().address, "wal_test_address");
         }
     }
-} 
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_broadcast_history_conflicts() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("tx_history_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+        
+        let spent = vec!["aa:0".to_string(), "bb:1".to_string()];
+        cache.record_broadcast("tx1", None, "00", &spent).await.unwrap();
+        
+        // Re-checking the same transaction is not a conflict
+        assert!(cache.find_conflicting_broadcasts("tx1", &spent).await.unwrap().is_empty());
+        
+        let conflicts = cache.find_conflicting_broadcasts("tx2", &["bb:1".to_string(), "cc:0".to_string()]).await.unwrap();
+        assert_eq!(conflicts, vec![("bb:1".to_string(), "tx1".to_string())]);
+        
+        assert_eq!(cache.get_double_spend_policy(None).await.unwrap(), "refuse");
+        cache.set_config("double_spend_policy:key1", "warn", None).await.unwrap();
+        assert_eq!(cache.get_double_spend_policy(Some("key1")).await.unwrap(), "warn");
+        assert_eq!(cache.get_double_spend_policy(Some("key2")).await.unwrap(), "refuse");
+
+        // Device history includes broadcasts whose signer is unknown
+        cache.record_broadcast("tx2", Some("dev1"), "00", &["cc:0".to_string()]).await.unwrap();
+        cache.record_broadcast("tx3", Some("dev2"), "00", &["dd:0".to_string()]).await.unwrap();
+        let mut history: Vec<String> = cache.get_tx_history("dev1").await.unwrap().into_iter().map(|e| e.txid).collect();
+        history.sort();
+        assert_eq!(history, vec!["tx1".to_string(), "tx2".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_frozen_outpoints_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let cache = create_test_cache_with_path(&temp_dir.path().join("frozen_test.db")).await;
+
+        assert!(cache.get_frozen_outpoints().await.unwrap().is_empty());
+        let frozen: std::collections::BTreeSet<String> = ["aa:0".to_string(), "bb:3".to_string()].into_iter().collect();
+        cache.set_frozen_outpoints(&frozen).await.unwrap();
+        let loaded = cache.get_frozen_outpoints().await.unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert!(loaded.contains("bb:3"));
+    }
+
+    #[tokio::test]
+    async fn test_device_id_snapshot_is_warmed_from_database() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("warm_device_id_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+        cache.save_features(&create_test_features("dev1", "Warm"), "dev1").await.unwrap();
+        assert_eq!(cache.get_device_id().as_deref(), Some("dev1"));
+
+        // A fresh handle knows nothing until warmed; reading it must not touch the database
+        let cache2 = create_test_cache_with_path(&db_path).await;
+        assert_eq!(cache2.get_device_id(), None);
+        assert_eq!(cache2.warm_device_id().await.unwrap().as_deref(), Some("dev1"));
+        assert_eq!(cache2.get_device_id().as_deref(), Some("dev1"));
+    }
+    
+    #[tokio::test]
+    async fn test_portfolio_snapshots_and_realized_txs() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("portfolio_history_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+        let caip = "bip122:000000000019d6689c085ae165831e93/slip44:0";
+        
+        let balance = |price: &str| CachedBalance {
+            id: 0,
+            device_id: "dev1".to_string(),
+            caip: caip.to_string(),
+            pubkey: "xpub1".to_string(),
+            balance: "0.5".to_string(),
+            price_usd: price.to_string(),
+            value_usd: "0.00".to_string(),
+            symbol: Some("BTC".to_string()),
+            network_id: None,
+            last_updated: 0,
+        };
+        cache.save_portfolio_snapshot("dev1", "2024-01-01", &[balance("42000.00")]).await.unwrap();
+        cache.save_portfolio_snapshot("dev1", "2024-01-03", &[balance("44000.00")]).await.unwrap();
+        
+        assert!(cache.has_portfolio_snapshot("dev1", "2024-01-01").await.unwrap());
+        assert!(!cache.has_portfolio_snapshot("dev1", "2024-01-02").await.unwrap());
+        assert_eq!(cache.get_portfolio_snapshots("dev1", "2024-01-01", "2024-01-02").await.unwrap().len(), 1);
+        
+        // Price lookups fall back to the latest earlier snapshot
+        assert_eq!(cache.get_snapshot_price("dev1", caip, "2024-01-02").await.unwrap().as_deref(), Some("42000.00"));
+        assert_eq!(cache.get_snapshot_price("dev1", caip, "2023-12-31").await.unwrap(), None);
+        
+        let tx = RealizedTx {
+            txid: "tx1".to_string(),
+            caip: caip.to_string(),
+            amount_sats: -10_000,
+            fee_sats: 500,
+            block_height: Some(800_000),
+            block_time: 1_704_153_600,
+            price_usd: Some("42000.00".to_string()),
+        };
+        cache.save_realized_tx("dev1", &tx).await.unwrap();
+        // A later sync without a price keeps the original annotation
+        cache.save_realized_tx("dev1", &RealizedTx { price_usd: None, ..tx.clone() }).await.unwrap();
+        
+        let txs = cache.get_realized_txs("dev1", 0, i64::MAX).await.unwrap();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].price_usd.as_deref(), Some("42000.00"));
+    }
+    
+    #[tokio::test]
+    async fn test_roll_back_chain_undoes_orphaned_history() {
+        let temp_dir = tempdir().unwrap();
+        let cache = create_test_cache_with_path(&temp_dir.path().join("reorg_test.db")).await;
+        cache.save_features(&create_test_features("dev1", "Reorg"), "dev1").await.unwrap();
+        let caip = "bip122:000000000019d6689c085ae165831e93/slip44:0";
+        
+        let realized = |txid: &str, height: u64| RealizedTx {
+            txid: txid.to_string(),
+            caip: caip.to_string(),
+            amount_sats: 10_000,
+            fee_sats: 0,
+            block_height: Some(height),
+            block_time: 1_704_153_600,
+            price_usd: None,
+        };
+        cache.save_realized_tx("dev1", &realized("old", 99)).await.unwrap();
+        cache.save_realized_tx("dev1", &realized("orphaned", 101)).await.unwrap();
+        cache.record_broadcast("sent", Some("dev1"), "00", &["aa:0".to_string()]).await.unwrap();
+        cache.update_tx_confirmations("sent", "confirmed", 6, Some(100), Some("hash100")).await.unwrap();
+        cache.save_balances("dev1", &[CachedBalance {
+            id: 0,
+            device_id: "dev1".to_string(),
+            caip: caip.to_string(),
+            pubkey: "xpub1".to_string(),
+            balance: "0.5".to_string(),
+            price_usd: "0.00".to_string(),
+            value_usd: "0.00".to_string(),
+            symbol: Some("BTC".to_string()),
+            network_id: None,
+            last_updated: 0,
+        }]).await.unwrap();
+        assert!(!cache.balances_need_refresh("dev1").await.unwrap());
+        
+        cache.record_block(99, "hash99").await.unwrap();
+        cache.record_block(100, "hash100").await.unwrap();
+        // The first hash seen for a height sticks
+        cache.record_block(100, "other").await.unwrap();
+        assert_eq!(
+            cache.get_recorded_blocks().await.unwrap(),
+            vec![(100, "hash100".to_string()), (99, "hash99".to_string())]
+        );
+        
+        let rollback = cache.roll_back_chain(100).await.unwrap();
+        assert_eq!(rollback.realized_txids, vec!["orphaned".to_string()]);
+        assert_eq!(rollback.broadcast_txids, vec!["sent".to_string()]);
+        assert_eq!(rollback.stale_balances, 1);
+        
+        let txs = cache.get_realized_txs("dev1", 0, i64::MAX).await.unwrap();
+        assert_eq!(txs.iter().map(|tx| tx.txid.as_str()).collect::<Vec<_>>(), vec!["old"]);
+        let sent = cache.get_tx_history_entry("sent").await.unwrap().unwrap();
+        assert_eq!(sent.status, "reorged");
+        assert_eq!(sent.block_hash, None);
+        // Back under confirmation tracking
+        assert!(cache.get_tracked_transactions().await.unwrap().iter().any(|e| e.txid == "sent"));
+        assert!(cache.balances_need_refresh("dev1").await.unwrap());
+        assert_eq!(cache.get_recorded_blocks().await.unwrap(), vec![(99, "hash99".to_string())]);
+    }
+    
+    #[tokio::test]
+    async fn test_dashboard_token_revocation() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("dashboard_token_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+        
+        cache.create_dashboard_token("tok1", "hash1", "Office").await.unwrap();
+        assert_eq!(cache.use_dashboard_token("hash1").await.unwrap().as_deref(), Some("tok1"));
+        assert_eq!(cache.use_dashboard_token("unknown").await.unwrap(), None);
+        assert!(cache.list_dashboard_tokens().await.unwrap()[0].last_used_at.is_some());
+        
+        assert!(cache.revoke_dashboard_token("tok1").await.unwrap());
+        assert!(!cache.revoke_dashboard_token("tok1").await.unwrap());
+        assert_eq!(cache.use_dashboard_token("hash1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_paired_client_usage_by_scope() {
+        use device_cache::ClientKeyStatus;
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("paired_client_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+
+        let pairing = crate::server::routes::PairingInfo {
+            name: "Wallet app".to_string(),
+            url: "https://example.com".to_string(),
+            image_url: String::new(),
+            added_on: None,
+            scopes: None,
+        };
+        cache.create_paired_client("c1", "keyhash", "abcd", &pairing, &["read", "sign"]).await.unwrap();
+        assert_eq!(cache.get_client_scopes("c1").await.unwrap(), vec!["read", "sign"]);
+        for scope in ["read", "sign", "read"] {
+            assert_eq!(cache.use_paired_client("keyhash", scope).await.unwrap(), ClientKeyStatus::Active("c1".to_string()));
+        }
+        assert_eq!(cache.use_paired_client("other", "read").await.unwrap(), ClientKeyStatus::Unknown);
+
+        let client = &cache.list_paired_clients().await.unwrap()[0];
+        assert_eq!(client.request_count, 3);
+        assert_eq!(client.scopes.iter().map(|s| (s.scope.as_str(), s.request_count)).collect::<Vec<_>>(), vec![("read", 2), ("sign", 1)]);
+        assert_eq!(client.expires_at, Some(client.last_seen_at.unwrap() + 90 * 86_400));
+
+        assert!(cache.revoke_paired_client("c1").await.unwrap());
+        assert_eq!(cache.use_paired_client("keyhash", "read").await.unwrap(), ClientKeyStatus::Revoked);
+        assert_eq!(cache.list_paired_clients().await.unwrap()[0].expires_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_host_metadata_restores_into_a_new_cache() {
+        let temp_dir = tempdir().unwrap();
+        let old = create_test_cache_with_path(&temp_dir.path().join("old.db")).await;
+        old.set_config("fiat_currency", "EUR", None).await.unwrap();
+        old.create_dashboard_token("tok1", "hash1", "Office").await.unwrap();
+        let metadata = old.export_host_metadata().await.unwrap();
+
+        let new = create_test_cache_with_path(&temp_dir.path().join("new.db")).await;
+        let restored = new.import_host_metadata(&metadata).await.unwrap();
+        assert_eq!(restored["dashboard_tokens"], 1);
+        assert_eq!(new.get_config("fiat_currency").await.unwrap().as_deref(), Some("EUR"));
+        assert_eq!(new.use_dashboard_token("hash1").await.unwrap().as_deref(), Some("tok1"));
+
+        // Restoring again writes nothing new but settings
+        let again = new.import_host_metadata(&metadata).await.unwrap();
+        assert_eq!(again["dashboard_tokens"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_clear_device_removes_wallet_history() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("clear_device_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+        let caip = "bip122:000000000019d6689c085ae165831e93/slip44:0";
+        
+        let balance = CachedBalance {
+            id: 0,
+            device_id: "dev1".to_string(),
+            caip: caip.to_string(),
+            pubkey: "xpub1".to_string(),
+            balance: "0.5".to_string(),
+            price_usd: "42000.00".to_string(),
+            value_usd: "21000.00".to_string(),
+            symbol: Some("BTC".to_string()),
+            network_id: None,
+            last_updated: 0,
+        };
+        cache.save_portfolio_snapshot("dev1", "2024-01-01", &[balance]).await.unwrap();
+        cache.save_realized_tx("dev1", &RealizedTx {
+            txid: "tx1".to_string(),
+            caip: caip.to_string(),
+            amount_sats: 10_000,
+            fee_sats: 0,
+            block_height: Some(800_000),
+            block_time: 1_704_153_600,
+            price_usd: None,
+        }).await.unwrap();
+        
+        cache.clear_device("dev1").await.unwrap();
+        
+        assert!(!cache.has_portfolio_snapshot("dev1", "2024-01-01").await.unwrap());
+        assert!(cache.get_realized_txs("dev1", 0, i64::MAX).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_forget_device_reports_removed_rows() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("forget_device_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+        
+        let features = create_test_features("dev1", "Forget Me");
+        cache.save_features(&features, "dev1").await.unwrap();
+        cache.save_address("dev1", "Bitcoin", "p2pkh", &[44, 0, 0, 0, 0], "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", None).await.unwrap();
+        cache.save_address("dev1", "Bitcoin", "p2pkh", &[44, 0, 0], "xpub6forgetme", None).await.unwrap();
+        
+        let forgotten = cache.forget_device("dev1").await.unwrap().expect("device was cached");
+        assert_eq!(forgotten.addresses, 1);
+        assert_eq!(forgotten.xpubs, 1);
+        
+        assert!(cache.forget_device("dev1").await.unwrap().is_none());
+        assert!(cache.forget_device("unknown").await.unwrap().is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_invalidate_key_material_keeps_device_and_paths() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("invalidate_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+        
+        let features = create_test_features("dev1", "Check Me");
+        cache.save_features(&features, "dev1").await.unwrap();
+        cache.load_device("dev1").await.unwrap();
+        cache.save_address("dev1", "Bitcoin", "p2wpkh", &[84, 0, 0, 0, 0], "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", None).await.unwrap();
+        cache.save_address("dev1", "Bitcoin", "p2wpkh_xpub", &[84, 0, 0], "zpub6check", None).await.unwrap();
+        
+        let xpubs = cache.get_cached_xpubs("dev1").await.unwrap();
+        assert_eq!(xpubs.len(), 1);
+        assert_eq!(xpubs[0].script_type, "p2wpkh");
+        assert_eq!(xpubs[0].path, vec![84, 0, 0]);
+        
+        assert_eq!(cache.invalidate_key_material("dev1").await.unwrap(), 2);
+        assert!(cache.get_cached_xpubs("dev1").await.unwrap().is_empty());
+        assert!(cache.get_cached_address("Bitcoin", "p2wpkh_xpub", &[84, 0, 0]).is_none());
+        assert!(cache.has_device("dev1").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_path_templates_drive_active_paths() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("path_templates_test.db");
+        let cache = create_test_cache_with_path(&db_path).await;
+        
+        // Seeded: mainnet (4 script types) and testnet (2) enabled, signet disabled
+        assert_eq!(cache.ensure_template_paths().await.unwrap(), 6);
+        assert_eq!(cache.ensure_template_paths().await.unwrap(), 0);
+        let paths = cache.get_active_paths().await.unwrap();
+        let native = paths.iter().find(|p| p.note == "Bitcoin account 0 Native Segwit (Bech32) BIP84").unwrap();
+        assert_eq!(native.path_type, "zpub");
+        assert_eq!(native.address_n_list, vec![0x8000_0054, 0x8000_0000, 0x8000_0000]);
+        let testnet = paths.iter().find(|p| p.note == "Bitcoin Testnet account 0 Native Segwit (Bech32) BIP84").unwrap();
+        assert_eq!(testnet.path_type, "vpub");
+        let taproot = paths.iter().find(|p| p.script_type == "p2tr").unwrap();
+        assert_eq!(taproot.path_type, "xpub");
+        assert_eq!(taproot.address_n_list, vec![0x8000_0056, 0x8000_0000, 0x8000_0000]);
+        
+        // Turning off a script type hides its paths without deleting them
+        let mut mainnet = cache.get_path_templates().await.unwrap().remove(0);
+        mainnet.script_types.p2pkh = false;
+        mainnet.accounts = 2;
+        cache.update_path_template(mainnet.id, &mainnet).await.unwrap();
+        assert_eq!(cache.ensure_template_paths().await.unwrap(), 3);
+        let paths = cache.get_active_paths().await.unwrap();
+        assert!(!paths.iter().any(|p| p.script_type == "p2pkh" && p.networks[0] == mainnet.network));
+        assert!(paths.iter().any(|p| p.note == "Bitcoin account 1 segwit (p2sh-p2wpkh) BIP49"));
+        assert_eq!(cache.get_paths().await.unwrap().len(), 9);
+    }
+}
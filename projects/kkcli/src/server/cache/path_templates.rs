@@ -0,0 +1,195 @@
+//! Per-network templates for the account paths the wallet derives.
+//!
+//! A template says which script types are covered on a UTXO network and how
+//! many accounts to derive. Frontload expands enabled templates into rows of
+//! `paths`; pubkey listing and balance lookups skip paths whose template is
+//! disabled or no longer covers their script type. Paths added by hand for
+//! networks without a template are left alone.
+
+use serde::{Deserialize, Serialize};
+
+use super::device_cache::Path;
+
+const HARDENED: u32 = 0x8000_0000;
+// Upper bound for `accounts`, so a typo doesn't queue thousands of device calls
+pub const MAX_TEMPLATE_ACCOUNTS: u32 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkKind {
+    Mainnet,
+    Testnet,
+    Signet,
+}
+
+impl NetworkKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mainnet" => Some(NetworkKind::Mainnet),
+            "testnet" => Some(NetworkKind::Testnet),
+            "signet" => Some(NetworkKind::Signet),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NetworkKind::Mainnet => "mainnet",
+            NetworkKind::Testnet => "testnet",
+            NetworkKind::Signet => "signet",
+        }
+    }
+}
+
+/// Which script types a template derives accounts for
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptTypeCoverage {
+    #[serde(default)]
+    pub p2pkh: bool,
+    #[serde(rename = "p2sh-p2wpkh", default)]
+    pub p2sh_p2wpkh: bool,
+    #[serde(default)]
+    pub p2wpkh: bool,
+    #[serde(default)]
+    pub p2tr: bool,
+}
+
+impl ScriptTypeCoverage {
+    pub fn covers(&self, script_type: &str) -> bool {
+        match script_type {
+            "p2pkh" => self.p2pkh,
+            "p2sh-p2wpkh" => self.p2sh_p2wpkh,
+            "p2wpkh" => self.p2wpkh,
+            "p2tr" => self.p2tr,
+            _ => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !(self.p2pkh || self.p2sh_p2wpkh || self.p2wpkh || self.p2tr)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathTemplate {
+    #[serde(serialize_with = "crate::server::cache::device_cache::as_string", default)]
+    pub id: i64,
+    /// CAIP-2 chain id, e.g. `bip122:000000000019d6689c085ae165831e93`
+    pub network: String,
+    pub network_kind: NetworkKind,
+    /// Name used in path notes ("Bitcoin", "Bitcoin Testnet", ...)
+    pub coin_name: String,
+    pub symbol: String,
+    /// SLIP-44 coin type, unhardened
+    pub coin_type: u32,
+    /// Accounts 0..accounts are derived for every covered script type
+    pub accounts: u32,
+    pub script_types: ScriptTypeCoverage,
+    pub enabled: bool,
+}
+
+// (script type, BIP purpose, note suffix, key prefix on mainnet, key prefix on test networks)
+const SCRIPT_TYPES: &[(&str, u32, &str, &str, &str)] = &[
+    ("p2pkh", 44, "legacy (p2pkh)", "xpub", "tpub"),
+    ("p2sh-p2wpkh", 49, "segwit (p2sh-p2wpkh) BIP49", "ypub", "upub"),
+    ("p2wpkh", 84, "Native Segwit (Bech32) BIP84", "zpub", "vpub"),
+    // SLIP-132 has no taproot version; BIP-86 keys are plain xpubs
+    ("p2tr", 86, "Taproot (Bech32m) BIP86", "xpub", "tpub"),
+];
+
+impl PathTemplate {
+    /// Problems that would make the template expand to nothing sensible
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.network.starts_with("bip122:") {
+            return Err(format!("Path templates are for bip122 networks, got {}", self.network));
+        }
+        if self.coin_name.trim().is_empty() || self.symbol.trim().is_empty() {
+            return Err("coinName and symbol are required".to_string());
+        }
+        if self.coin_type >= HARDENED {
+            return Err(format!("coinType {} must be unhardened", self.coin_type));
+        }
+        if self.accounts == 0 || self.accounts > MAX_TEMPLATE_ACCOUNTS {
+            return Err(format!("accounts must be between 1 and {}", MAX_TEMPLATE_ACCOUNTS));
+        }
+        if self.enabled && self.script_types.is_empty() {
+            return Err("An enabled template must cover at least one script type".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether paths of `script_type` on this template's network are in use
+    pub fn covers(&self, script_type: &str) -> bool {
+        self.enabled && self.script_types.covers(script_type)
+    }
+
+    /// The account paths this template asks for; empty when disabled
+    pub fn expand(&self) -> Vec<Path> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        self.covered_script_types()
+            .flat_map(|script_type| (0..self.accounts).filter_map(move |account| self.account_path(script_type, account)))
+            .collect()
+    }
+
+    /// Script types the template covers, in BIP purpose order
+    pub fn covered_script_types(&self) -> impl Iterator<Item = &'static str> + '_ {
+        SCRIPT_TYPES
+            .iter()
+            .map(|&(script_type, ..)| script_type)
+            .filter(|script_type| self.script_types.covers(script_type))
+    }
+
+    /// Path of `account` for `script_type` on this template's network, also
+    /// past `accounts`; `None` for an unknown script type
+    pub fn account_path(&self, script_type: &str, account: u32) -> Option<Path> {
+        let &(script_type, purpose, suffix, mainnet_prefix, test_prefix) =
+            SCRIPT_TYPES.iter().find(|(name, ..)| *name == script_type)?;
+        let address_n_list = vec![purpose | HARDENED, self.coin_type | HARDENED, account | HARDENED];
+        let mut address_n_list_master = address_n_list.clone();
+        address_n_list_master.extend([0, 0]);
+        Some(Path {
+            id: 0,
+            note: format!("{} account {} {}", self.coin_name, account, suffix),
+            blockchain: Some("bitcoin".to_string()),
+            symbol: Some(self.symbol.clone()),
+            symbol_swap_kit: Some(self.symbol.clone()),
+            networks: vec![self.network.clone()],
+            script_type: script_type.to_string(),
+            available_script_types: Some(SCRIPT_TYPES.iter().map(|(name, ..)| name.to_string()).collect()),
+            path_type: match self.network_kind {
+                NetworkKind::Mainnet => mainnet_prefix,
+                NetworkKind::Testnet | NetworkKind::Signet => test_prefix,
+            }
+            .to_string(),
+            address_n_list,
+            address_n_list_master,
+            curve: "secp256k1".to_string(),
+            show_display: false,
+        })
+    }
+}
+
+/// Drop paths that a template for one of their networks doesn't cover
+pub fn active_paths(paths: Vec<Path>, templates: &[PathTemplate]) -> Vec<Path> {
+    paths
+        .into_iter()
+        .filter(|path| {
+            path.networks.iter().all(|network| {
+                templates
+                    .iter()
+                    .filter(|template| &template.network == network)
+                    .all(|template| template.covers(&path.script_type))
+            })
+        })
+        .collect()
+}
+
+/// Same derivation on the same network, whatever the note says
+pub fn same_path(a: &Path, b: &Path) -> bool {
+    a.script_type == b.script_type
+        && a.address_n_list == b.address_n_list
+        && a.networks.iter().any(|network| b.networks.contains(network))
+}
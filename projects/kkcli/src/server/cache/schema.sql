@@ -50,6 +50,31 @@ CREATE TABLE IF NOT EXISTS paths (
     -- Note: No foreign key constraint to allow global paths with NULL device_id
 );
 
+-- Path templates - which script types and how many accounts are derived on each
+-- UTXO network. Frontload expands enabled templates into rows of `paths`.
+CREATE TABLE IF NOT EXISTS path_templates (
+    id            INTEGER PRIMARY KEY,
+    network       TEXT NOT NULL UNIQUE, -- CAIP-2 chain id
+    network_kind  TEXT NOT NULL, -- mainnet, testnet, signet
+    coin_name     TEXT NOT NULL,
+    symbol        TEXT NOT NULL,
+    coin_type     INTEGER NOT NULL, -- SLIP-44, unhardened
+    accounts      INTEGER NOT NULL DEFAULT 1,
+    script_types  TEXT NOT NULL, -- JSON object of script type coverage flags
+    enabled       BOOLEAN NOT NULL DEFAULT 1
+);
+
+INSERT OR IGNORE INTO path_templates (network, network_kind, coin_name, symbol, coin_type, accounts, script_types, enabled) VALUES
+('bip122:000000000019d6689c085ae165831e93', 'mainnet', 'Bitcoin', 'BTC', 0, 1, '{"p2pkh":true,"p2sh-p2wpkh":true,"p2wpkh":true,"p2tr":true}', 1),
+('bip122:000000000933ea01ad0ee984209779ba', 'testnet', 'Bitcoin Testnet', 'TEST', 1, 1, '{"p2pkh":true,"p2sh-p2wpkh":false,"p2wpkh":true,"p2tr":false}', 1),
+('bip122:00000008819873e925422c1ff0f99f7c', 'signet', 'Bitcoin Signet', 'sBTC', 1, 1, '{"p2pkh":false,"p2sh-p2wpkh":false,"p2wpkh":true,"p2tr":false}', 0);
+
+-- Templates saved before taproot support: cover p2tr on mainnet, leave a saved choice alone
+UPDATE path_templates SET script_types = json_set(script_types, '$.p2tr', json('true'))
+WHERE network = 'bip122:000000000019d6689c085ae165831e93' AND json_extract(script_types, '$.p2tr') IS NULL;
+UPDATE path_templates SET script_types = json_set(script_types, '$.p2tr', json('false'))
+WHERE json_extract(script_types, '$.p2tr') IS NULL;
+
 -- Cached addresses table - derived addresses for each device/path combination
 CREATE TABLE IF NOT EXISTS cached_addresses (
     id               INTEGER PRIMARY KEY,
@@ -99,6 +124,121 @@ CREATE TABLE IF NOT EXISTS config (
     updated_at  INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
 );
 
+-- Transaction history - transactions broadcast through this server
+CREATE TABLE IF NOT EXISTS tx_history (
+    txid            TEXT PRIMARY KEY,
+    device_id       TEXT, -- NULL when the signing device is unknown
+    raw_tx          TEXT NOT NULL,
+    spent_outpoints TEXT NOT NULL, -- JSON array of "txid:vout"
+    status          TEXT NOT NULL DEFAULT 'broadcast', -- broadcast, confirming, confirmed, reorged
+    confirmations   INTEGER NOT NULL DEFAULT 0,
+    block_height    INTEGER,
+    block_hash      TEXT,
+    created_at      INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    updated_at      INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
+-- Daily portfolio snapshots - one row per asset per day, copied from cached_balances
+CREATE TABLE IF NOT EXISTS portfolio_snapshots (
+    id              INTEGER PRIMARY KEY,
+    device_id       TEXT NOT NULL,
+    snapshot_date   TEXT NOT NULL, -- YYYY-MM-DD (UTC)
+    caip            TEXT NOT NULL,
+    pubkey          TEXT NOT NULL,
+    symbol          TEXT,
+    balance         TEXT NOT NULL,
+    price_usd       TEXT NOT NULL DEFAULT '0.00',
+    value_usd       TEXT NOT NULL DEFAULT '0.00',
+    created_at      INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    UNIQUE(device_id, snapshot_date, caip, pubkey)
+);
+
+-- Realized transactions - confirmed on-chain movements for the device's addresses
+CREATE TABLE IF NOT EXISTS realized_transactions (
+    device_id       TEXT NOT NULL,
+    txid            TEXT NOT NULL,
+    caip            TEXT NOT NULL,
+    amount_sats     INTEGER NOT NULL, -- net change for the wallet, negative for sends
+    fee_sats        INTEGER NOT NULL DEFAULT 0, -- only set when the wallet paid the fee
+    block_height    INTEGER,
+    block_time      INTEGER NOT NULL,
+    price_usd       TEXT, -- price on the day of the transaction, NULL if unknown
+    PRIMARY KEY (device_id, txid, caip)
+);
+
+-- Blocks the cached history confirmed in, with the hash first seen at each
+-- height. A different hash at the backend means the block was reorged out.
+CREATE TABLE IF NOT EXISTS chain_blocks (
+    height          INTEGER PRIMARY KEY,
+    hash            TEXT NOT NULL,
+    recorded_at     INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
+-- Read-only dashboard tokens - only the SHA-256 of the token is stored
+CREATE TABLE IF NOT EXISTS dashboard_tokens (
+    id              TEXT PRIMARY KEY,
+    token_hash      TEXT NOT NULL UNIQUE,
+    label           TEXT NOT NULL,
+    created_at      INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    last_used_at    INTEGER,
+    revoked_at      INTEGER
+);
+
+-- Clients paired through POST /auth/pair - only the SHA-256 of the API key is stored
+CREATE TABLE IF NOT EXISTS paired_clients (
+    id              TEXT PRIMARY KEY,
+    key_hash        TEXT NOT NULL UNIQUE,
+    key_hint        TEXT NOT NULL, -- last four characters of the key
+    name            TEXT NOT NULL,
+    url             TEXT NOT NULL,
+    image_url       TEXT NOT NULL,
+    created_at      INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    last_seen_at    INTEGER,
+    request_count   INTEGER NOT NULL DEFAULT 0,
+    expired_at      INTEGER, -- unused for longer than client_expiry_days
+    revoked_at      INTEGER
+);
+
+-- Requests per paired client and scope (read, address, sign, admin)
+CREATE TABLE IF NOT EXISTS client_scope_usage (
+    client_id       TEXT NOT NULL,
+    scope           TEXT NOT NULL,
+    request_count   INTEGER NOT NULL DEFAULT 0,
+    last_used_at    INTEGER NOT NULL,
+    PRIMARY KEY (client_id, scope)
+);
+
+-- Scopes granted to a paired client's API key (read, address, sign, admin)
+CREATE TABLE IF NOT EXISTS client_scopes (
+    client_id       TEXT NOT NULL,
+    scope           TEXT NOT NULL,
+    granted_at      INTEGER NOT NULL,
+    PRIMARY KEY (client_id, scope)
+);
+
+-- Fiat exchange rates - USD to display currency, refreshed from fx_rate_url
+CREATE TABLE IF NOT EXISTS fx_rates (
+    currency        TEXT PRIMARY KEY, -- ISO 4217 code, e.g. EUR
+    rate_per_usd    REAL NOT NULL,
+    source          TEXT NOT NULL,
+    rate_timestamp  INTEGER NOT NULL, -- when the source published the rate
+    fetched_at      INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
+-- Accounts found by gap-limit discovery, per device and script type. Used
+-- accounts past the templated ones also get a device-specific row in `paths`.
+CREATE TABLE IF NOT EXISTS discovered_accounts (
+    device_id       TEXT NOT NULL,
+    network         TEXT NOT NULL, -- CAIP-2 chain id
+    script_type     TEXT NOT NULL,
+    account         INTEGER NOT NULL, -- unhardened
+    used_addresses  INTEGER NOT NULL DEFAULT 0, -- receive and change addresses with history
+    last_receive    INTEGER, -- highest receive index with history, NULL if none
+    last_change     INTEGER, -- highest change index with history, NULL if none
+    scanned_at      INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    PRIMARY KEY (device_id, network, script_type, account)
+);
+
 -- Indexes for performance
 CREATE INDEX IF NOT EXISTS idx_devices_device_id ON devices(device_id);
 CREATE INDEX IF NOT EXISTS idx_networks_chain_id ON networks(chain_id_caip2);
@@ -109,9 +249,32 @@ CREATE INDEX IF NOT EXISTS idx_cached_addresses_lookup ON cached_addresses(devic
 CREATE INDEX IF NOT EXISTS idx_cached_balances_device_id ON cached_balances(device_id);
 CREATE INDEX IF NOT EXISTS idx_cached_balances_last_updated ON cached_balances(last_updated);
 CREATE INDEX IF NOT EXISTS idx_portfolio_device_id ON portfolio_summaries(device_id);
+CREATE INDEX IF NOT EXISTS idx_tx_history_created_at ON tx_history(created_at);
+CREATE INDEX IF NOT EXISTS idx_tx_history_status ON tx_history(status);
+CREATE INDEX IF NOT EXISTS idx_portfolio_snapshots_date ON portfolio_snapshots(device_id, snapshot_date);
+CREATE INDEX IF NOT EXISTS idx_realized_transactions_time ON realized_transactions(device_id, block_time);
 
 -- Insert default configuration values
 INSERT OR IGNORE INTO config (key, value, description) VALUES 
 ('pioneer_server_url', 'https://pioneers.dev', 'Pioneer API server URL for balance and price data'),
 ('cache_ttl_minutes', '10', 'How many minutes to cache balance data before refreshing'),
-('auto_discover_assets', 'true', 'Whether to automatically discover new assets for connected devices'); 
\ No newline at end of file
+('auto_discover_assets', 'true', 'Whether to automatically discover new assets for connected devices'),
+('chain_backend_url', 'https://mempool.space/api', 'Esplora-compatible API(s) used for broadcast and chain queries; comma separated, primary first'),
+('double_spend_policy', 'refuse', 'What to do when a broadcast conflicts with a known spend: refuse, warn or allow'),
+('tx_confirmation_target', '6', 'Confirmations after which a broadcast transaction is considered final'),
+('gap_limit', '20', 'Consecutive unused addresses after which account discovery stops scanning a chain'),
+('account_discovery_interval_hours', '24', 'Hours between gap-limit account discovery scans on frontload (0 scans on every frontload)'),
+('fiat_currency', 'USD', 'Display currency for fiat equivalents (ISO 4217 code)'),
+('fx_rate_url', 'https://open.er-api.com/v6/latest/USD', 'Exchange rate feed used to convert USD prices to the display currency'),
+('amount_unit', 'btc', 'Denomination for formatted amounts in API responses: btc, bits or sats'),
+('amount_locale', 'auto', 'Locale for formatted amounts in API responses (BCP 47 tag such as de-DE), or auto to follow the Accept-Language header'),
+('offline_mode', 'false', 'When true, no price or exchange rate requests are made and fiat values are omitted'),
+('remote_approval', 'off', 'Require approval from the desktop UI before REST signing requests reach the device: off or required (override per key with remote_approval:<api key>)'),
+('remote_approval_timeout_secs', '120', 'Seconds to wait for a remote approval decision before failing the signing request'),
+('api_auth', 'sensitive', 'Which requests need a paired API key granted their scope: sensitive (signing and device administration) or off'),
+('client_expiry_days', '90', 'API keys of paired clients unused for this many days stop working until the client pairs again (0 disables)'),
+('pin_entry', 'local', 'Where device PIN prompts are answered: local (stdin) or remote (GET/POST /api/v2/pin with the PIN entry token)'),
+('pin_entry_timeout_secs', '120', 'Seconds to wait for a remote PIN entry before failing the device call'),
+('policy_confirm_address_display', 'false', 'Always show addresses on the device before returning them'),
+('policy_op_return', 'allow', 'OP_RETURN outputs: allow, confirm (on device) or deny'),
+('policy_fee_confirm_threshold_sats', '0', 'Fees above this many satoshis must be confirmed on the device (0 disables)'); 
\ No newline at end of file
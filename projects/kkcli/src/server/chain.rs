@@ -0,0 +1,290 @@
+//! Client for the configured Esplora-compatible chain backend (mempool.space,
+//! blockstream.info or a self-hosted esplora/electrs).
+
+use anyhow::{anyhow, Result};
+use bitcoin::consensus::encode::deserialize;
+use serde::Deserialize;
+use tracing::debug;
+
+use super::cache::DeviceCache;
+
+/// Confirmed transactions per page on Esplora's address history endpoints
+const ESPLORA_PAGE_SIZE: usize = 25;
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct TxStatus {
+    pub confirmed: bool,
+    pub block_height: Option<u64>,
+    pub block_hash: Option<String>,
+    pub block_time: Option<u64>,
+}
+
+/// Best block as reported by a backend
+#[derive(Debug, Clone)]
+pub(crate) struct ChainTip {
+    pub height: u64,
+    pub hash: String,
+    pub median_time: Option<u64>,
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockHeader {
+    height: u64,
+    timestamp: Option<u64>,
+    mediantime: Option<u64>,
+}
+
+/// Spending status of a single output
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Outspend {
+    pub spent: bool,
+    pub txid: Option<String>,
+    pub vin: Option<u32>,
+    pub status: Option<TxStatus>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct EsploraPrevout {
+    pub scriptpubkey_address: Option<String>,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct EsploraVin {
+    pub prevout: Option<EsploraPrevout>,
+}
+
+/// Transaction as returned by the address history endpoints
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct EsploraTx {
+    pub txid: String,
+    pub vin: Vec<EsploraVin>,
+    pub vout: Vec<EsploraPrevout>,
+    pub fee: Option<u64>,
+    /// Weight units; vsize is a quarter of this, rounded up
+    pub weight: Option<u64>,
+    pub status: TxStatus,
+}
+
+/// Unspent output as returned by `/address/{address}/utxo`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct EsploraUtxo {
+    pub txid: String,
+    pub vout: u32,
+    pub value: u64,
+    pub status: TxStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddressTxCounts {
+    tx_count: u64,
+}
+
+/// Summary from `/address/{address}`
+#[derive(Debug, Deserialize)]
+struct AddressStats {
+    chain_stats: AddressTxCounts,
+    mempool_stats: AddressTxCounts,
+}
+
+/// Mempool summary from `/mempool`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct MempoolStats {
+    pub count: u64,
+    pub vsize: u64,
+    pub total_fee: u64,
+    /// `[fee_rate, vsize]` pairs, highest fee rate first. Each entry covers the
+    /// transactions paying at least `fee_rate` but less than the previous entry.
+    pub fee_histogram: Vec<(f64, u64)>,
+}
+
+pub(crate) struct ChainBackend {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl ChainBackend {
+    pub(crate) fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Backend configured in the cache's `chain_backend_url` setting
+    pub(crate) async fn from_cache(cache: &DeviceCache) -> Result<Self> {
+        Ok(Self::new(cache.get_chain_backend_url().await?))
+    }
+
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn get_text(&self, path: &str) -> Result<String> {
+        let url = format!("{}{}", self.base_url, path);
+        debug!("GET {}", url);
+        let response = self.client.get(&url).header("User-Agent", "kkcli/1.0").send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!("Chain backend returned {} for {}: {}", status, url, body));
+        }
+        Ok(body)
+    }
+
+    /// Whether `txid:vout` has been spent, and by which transaction
+    pub(crate) async fn outspend(&self, txid: &str, vout: u32) -> Result<Outspend> {
+        let body = self.get_text(&format!("/tx/{}/outspend/{}", txid, vout)).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Height of the current chain tip
+    pub(crate) async fn tip_height(&self) -> Result<u64> {
+        let body = self.get_text("/blocks/tip/height").await?;
+        body.trim().parse().map_err(|e| anyhow!("Invalid tip height {:?}: {}", body, e))
+    }
+
+    /// Current chain tip with its median time past
+    pub(crate) async fn tip(&self) -> Result<ChainTip> {
+        let hash = self.get_text("/blocks/tip/hash").await?.trim().to_string();
+        let header: BlockHeader = serde_json::from_str(&self.get_text(&format!("/block/{}", hash)).await?)?;
+        Ok(ChainTip {
+            height: header.height,
+            hash,
+            median_time: header.mediantime,
+            timestamp: header.timestamp,
+        })
+    }
+
+    /// Hash of the block the backend has at `height` in its best chain
+    pub(crate) async fn block_hash_at(&self, height: u64) -> Result<String> {
+        Ok(self.get_text(&format!("/block-height/{}", height)).await?.trim().to_string())
+    }
+
+    /// Confirmation status of a transaction; `None` if the backend doesn't know it
+    pub(crate) async fn tx_status(&self, txid: &str) -> Result<Option<TxStatus>> {
+        let url = format!("{}/tx/{}/status", self.base_url, txid);
+        debug!("GET {}", url);
+        let response = self.client.get(&url).header("User-Agent", "kkcli/1.0").send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!("Chain backend returned {} for {}: {}", status, url, body));
+        }
+        Ok(Some(serde_json::from_str(&body)?))
+    }
+
+    /// Confirmed transaction history for an address, newest first. Esplora pages
+    /// confirmed history 25 at a time, keyed by the last txid seen.
+    pub(crate) async fn address_txs(&self, address: &str) -> Result<Vec<EsploraTx>> {
+        let mut txs: Vec<EsploraTx> = Vec::new();
+        loop {
+            let path = match txs.last() {
+                Some(last) => format!("/address/{}/txs/chain/{}", address, last.txid),
+                None => format!("/address/{}/txs/chain", address),
+            };
+            let page: Vec<EsploraTx> = serde_json::from_str(&self.get_text(&path).await?)?;
+            let page_len = page.len();
+            txs.extend(page);
+            if page_len < ESPLORA_PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(txs)
+    }
+
+    /// Transactions that ever touched an address, confirmed or in the mempool
+    pub(crate) async fn address_tx_count(&self, address: &str) -> Result<u64> {
+        let stats: AddressStats = serde_json::from_str(&self.get_text(&format!("/address/{}", address)).await?)?;
+        Ok(stats.chain_stats.tx_count + stats.mempool_stats.tx_count)
+    }
+
+    /// Unspent outputs of an address, confirmed and unconfirmed
+    pub(crate) async fn address_utxos(&self, address: &str) -> Result<Vec<EsploraUtxo>> {
+        let body = self.get_text(&format!("/address/{}/utxo", address)).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Inputs, outputs, fee and weight of a transaction
+    pub(crate) async fn tx(&self, txid: &str) -> Result<EsploraTx> {
+        Ok(serde_json::from_str(&self.get_text(&format!("/tx/{}", txid)).await?)?)
+    }
+
+    /// Raw hex of a transaction
+    pub(crate) async fn tx_hex(&self, txid: &str) -> Result<String> {
+        Ok(self.get_text(&format!("/tx/{}/hex", txid)).await?.trim().to_string())
+    }
+
+    /// Current mempool size and fee-rate histogram
+    pub(crate) async fn mempool(&self) -> Result<MempoolStats> {
+        Ok(serde_json::from_str(&self.get_text("/mempool").await?)?)
+    }
+
+    /// Fee rate (sat/vB) per confirmation target in blocks, lowest target first
+    pub(crate) async fn fee_estimates(&self) -> Result<Vec<(u32, f64)>> {
+        let estimates: std::collections::HashMap<String, f64> =
+            serde_json::from_str(&self.get_text("/fee-estimates").await?)?;
+        let mut estimates: Vec<(u32, f64)> = estimates
+            .into_iter()
+            .filter_map(|(target, rate)| target.parse::<u32>().ok().map(|t| (t, rate)))
+            .collect();
+        estimates.sort_by_key(|(target, _)| *target);
+        Ok(estimates)
+    }
+
+    /// Fee rate (sat/vB) needed to confirm within `target_blocks`, taking the
+    /// closest target the backend reports at or below it
+    pub(crate) async fn fee_rate(&self, target_blocks: u32) -> Result<f64> {
+        self.fee_estimates()
+            .await?
+            .into_iter()
+            .filter(|(target, _)| *target <= target_blocks)
+            .max_by_key(|(target, _)| *target)
+            .map(|(_, rate)| rate)
+            .ok_or_else(|| anyhow!("Chain backend returned no fee estimate for {} blocks", target_blocks))
+    }
+
+    /// Submit a raw transaction; returns the txid reported by the backend
+    pub(crate) async fn broadcast(&self, raw_tx_hex: &str) -> Result<String> {
+        let url = format!("{}/tx", self.base_url);
+        debug!("POST {}", url);
+        let response = self
+            .client
+            .post(&url)
+            .header("User-Agent", "kkcli/1.0")
+            .header("Content-Type", "text/plain")
+            .body(raw_tx_hex.to_string())
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!("Chain backend rejected transaction ({}): {}", status, body));
+        }
+        Ok(body.trim().to_string())
+    }
+}
+
+/// Decode a raw transaction and return its txid and the outpoints it spends
+/// (formatted as `txid:vout`)
+pub(crate) fn decode_spent_outpoints(raw_tx_hex: &str) -> Result<(String, Vec<String>)> {
+    let bytes = hex::decode(raw_tx_hex.trim()).map_err(|e| anyhow!("Invalid transaction hex: {}", e))?;
+    let tx: bitcoin::Transaction = deserialize(&bytes).map_err(|e| anyhow!("Invalid transaction: {}", e))?;
+    let outpoints = tx
+        .input
+        .iter()
+        .map(|input| format!("{}:{}", input.previous_output.txid, input.previous_output.vout))
+        .collect();
+    Ok((tx.txid().to_string(), outpoints))
+}
+
+/// Whether a raw transaction opts in to replace-by-fee (BIP125)
+pub(crate) fn signals_rbf(raw_tx_hex: &str) -> Result<bool> {
+    let bytes = hex::decode(raw_tx_hex.trim()).map_err(|e| anyhow!("Invalid transaction hex: {}", e))?;
+    let tx: bitcoin::Transaction = deserialize(&bytes).map_err(|e| anyhow!("Invalid transaction: {}", e))?;
+    Ok(tx.input.iter().any(|input| input.sequence.is_rbf()))
+}
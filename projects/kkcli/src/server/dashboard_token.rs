@@ -0,0 +1,84 @@
+//! Read-only dashboard tokens.
+//!
+//! A dashboard token (`kkro_...`) lets a status dashboard or mobile companion
+//! read the portfolio summary and derive receive addresses, and nothing else.
+//! Requests presenting one are checked here before routing; requests without
+//! a dashboard token are unaffected.
+
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use tracing::{error, warn};
+
+use super::cache::DeviceCache;
+
+pub(crate) const DASHBOARD_TOKEN_PREFIX: &str = "kkro_";
+
+/// Endpoints a dashboard token may call
+const DASHBOARD_SCOPE: &[(Method, &str)] = &[
+    (Method::GET, "/api/health"),
+    (Method::GET, "/v2/portfolio/summary"),
+    (Method::POST, "/addresses/utxo"),
+    (Method::GET, "/api/v2/response-signing/key"),
+];
+
+/// New random token; only its hash is stored
+pub(crate) fn generate_dashboard_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", DASHBOARD_TOKEN_PREFIX, hex::encode(bytes))
+}
+
+pub(crate) fn hash_dashboard_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn in_dashboard_scope(method: &Method, path: &str) -> bool {
+    // CORS preflight carries no credentials
+    *method == Method::OPTIONS || DASHBOARD_SCOPE.iter().any(|(m, p)| m == method && *p == path)
+}
+
+fn reject(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+pub(crate) async fn dashboard_token_guard(
+    State(cache): State<DeviceCache>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let token = req
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .filter(|t| t.starts_with(DASHBOARD_TOKEN_PREFIX))
+        .map(str::to_string);
+
+    let token = match token {
+        Some(token) => token,
+        None => return next.run(req).await,
+    };
+
+    match cache.use_dashboard_token(&hash_dashboard_token(&token)).await {
+        Ok(Some(id)) => {
+            if in_dashboard_scope(req.method(), req.uri().path()) {
+                next.run(req).await
+            } else {
+                warn!("🔒 Dashboard token {} denied {} {}", id, req.method(), req.uri().path());
+                reject(StatusCode::FORBIDDEN, "Dashboard tokens are read-only and cannot access this endpoint")
+            }
+        }
+        Ok(None) => reject(StatusCode::UNAUTHORIZED, "Invalid or revoked dashboard token"),
+        Err(e) => {
+            error!("Failed to check dashboard token: {}", e);
+            reject(StatusCode::INTERNAL_SERVER_ERROR, "Failed to check dashboard token")
+        }
+    }
+}
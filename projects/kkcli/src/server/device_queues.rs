@@ -0,0 +1,70 @@
+//! One queue per physical KeepKey.
+//!
+//! A device call holds its device's lock for the whole exchange, so two
+//! requests never interleave messages on one USB connection. Locks are kept per
+//! device: with two KeepKeys plugged in, one can sign while the other derives
+//! addresses, and requests only wait for others on the same device.
+//!
+//! Requests pick a device with `device_id`, the USB serial number (the same as
+//! `Features.device_id`). Without one they go to the device the server started
+//! with, whose lock is `ServerState::device_mutex`, so code that only knows
+//! about that device still queues behind requests naming it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::cache::DeviceCache;
+
+#[derive(Clone)]
+pub struct DeviceQueues {
+    primary: Arc<Mutex<()>>,
+    others: Arc<std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+}
+
+impl DeviceQueues {
+    /// Queues around `primary`, the lock of the device the server started with
+    pub fn new(primary: Arc<Mutex<()>>) -> Self {
+        Self {
+            primary,
+            others: Arc::default(),
+        }
+    }
+
+    /// The lock for `device_id`; the primary device's when `None` or when it
+    /// names the device in the cache
+    pub fn lock_for(&self, cache: &DeviceCache, device_id: Option<&str>) -> Arc<Mutex<()>> {
+        match device_id {
+            Some(id) if cache.get_device_id().as_deref() != Some(id) => Arc::clone(
+                self.others
+                    .lock()
+                    .unwrap()
+                    .entry(id.to_string())
+                    .or_default(),
+            ),
+            _ => Arc::clone(&self.primary),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn devices_queue_independently() {
+        let dir = tempdir().unwrap();
+        let cache = DeviceCache::open_simulation_in(dir.path().to_path_buf()).unwrap();
+        let primary = Arc::new(Mutex::new(()));
+        let queues = DeviceQueues::new(Arc::clone(&primary));
+
+        let _signing = primary.lock().await;
+        assert!(queues.lock_for(&cache, None).try_lock().is_err());
+
+        let second = queues.lock_for(&cache, Some("SECOND"));
+        let _deriving = second.try_lock().expect("another device is not blocked by the primary");
+        assert!(queues.lock_for(&cache, Some("SECOND")).try_lock().is_err());
+        assert!(queues.lock_for(&cache, Some("THIRD")).try_lock().is_ok());
+    }
+}
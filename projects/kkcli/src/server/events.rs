@@ -0,0 +1,45 @@
+//! In-process event bus. Background jobs publish here and every WebSocket
+//! client receives the events.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+const EVENT_CHANNEL_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerEvent {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: serde_json::Value,
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<ServerEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_CHANNEL_SIZE);
+        Self { tx }
+    }
+}
+
+impl EventBus {
+    pub fn emit(&self, event_type: &str, data: serde_json::Value) {
+        // No subscribers just means nobody is listening right now
+        let receivers = self
+            .tx
+            .send(ServerEvent {
+                event_type: event_type.to_string(),
+                data,
+            })
+            .unwrap_or(0);
+        debug!("📣 {} delivered to {} subscriber(s)", event_type, receivers);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.tx.subscribe()
+    }
+}
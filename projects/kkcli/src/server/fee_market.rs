@@ -0,0 +1,147 @@
+//! Mempool fee market for fee choosers: the backend's fee-rate histogram
+//! grouped into bands, each with an estimated confirmation time.
+//!
+//! Snapshots are shared by every client. The backend is asked at most once per
+//! `FEE_MARKET_TTL`, and after a failed fetch not again for
+//! `FEE_MARKET_RETRY`; in between the last good snapshot is served, marked
+//! stale once it has outlived the TTL.
+
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use super::chain::{ChainBackend, MempoolStats};
+use super::routes::{FeeBand, FeeEstimate, MempoolFeesResponse};
+
+const FEE_MARKET_TTL: Duration = Duration::from_secs(30);
+const FEE_MARKET_RETRY: Duration = Duration::from_secs(10);
+/// Block space available to ordinary transactions per block, in vbytes
+const BLOCK_VSIZE: u64 = 1_000_000;
+const BLOCK_INTERVAL_MINUTES: u64 = 10;
+
+#[derive(Default)]
+struct FeeMarketState {
+    snapshot: Option<(Instant, MempoolFeesResponse)>,
+    last_failure: Option<Instant>,
+}
+
+#[derive(Clone, Default)]
+pub struct FeeMarketCache {
+    inner: Arc<Mutex<FeeMarketState>>,
+}
+
+impl FeeMarketCache {
+    /// Current snapshot, fetched from `backend` if the cached one has expired.
+    /// The lock is held while fetching so concurrent callers share one request.
+    pub(crate) async fn get(&self, backend: &ChainBackend) -> Result<MempoolFeesResponse> {
+        let mut state = self.inner.lock().await;
+        if let Some((fetched, snapshot)) = &state.snapshot {
+            if fetched.elapsed() < FEE_MARKET_TTL {
+                return Ok(snapshot.clone());
+            }
+        }
+
+        let retry_allowed = match state.last_failure {
+            Some(failed) => failed.elapsed() >= FEE_MARKET_RETRY,
+            None => true,
+        };
+        if retry_allowed {
+            match fetch(backend).await {
+                Ok(snapshot) => {
+                    state.last_failure = None;
+                    state.snapshot = Some((Instant::now(), snapshot.clone()));
+                    return Ok(snapshot);
+                }
+                Err(e) => {
+                    warn!("Failed to refresh mempool fees from {}: {}", backend.base_url(), e);
+                    state.last_failure = Some(Instant::now());
+                    if state.snapshot.is_none() {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        match &state.snapshot {
+            Some((_, snapshot)) => Ok(MempoolFeesResponse { stale: true, ..snapshot.clone() }),
+            None => Err(anyhow!("Mempool fees unavailable; retrying in {}s", FEE_MARKET_RETRY.as_secs())),
+        }
+    }
+}
+
+async fn fetch(backend: &ChainBackend) -> Result<MempoolFeesResponse> {
+    let (mempool, estimates) = tokio::try_join!(backend.mempool(), backend.fee_estimates())?;
+    Ok(MempoolFeesResponse {
+        count: mempool.count,
+        vsize: mempool.vsize,
+        total_fee: mempool.total_fee,
+        bands: fee_bands(&mempool),
+        estimates: estimates
+            .into_iter()
+            .map(|(target_blocks, fee_rate)| FeeEstimate { target_blocks, fee_rate })
+            .collect(),
+        backend: backend.base_url().to_string(),
+        fetched_at: chrono::Utc::now().timestamp(),
+        stale: false,
+    })
+}
+
+/// Histogram entries as bands, assuming miners take the highest fee rates
+/// first and each block has room for `BLOCK_VSIZE` vbytes
+fn fee_bands(mempool: &MempoolStats) -> Vec<FeeBand> {
+    let mut histogram = mempool.fee_histogram.clone();
+    histogram.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut ahead = 0u64;
+    let mut max_fee_rate = None;
+    histogram
+        .into_iter()
+        .map(|(min_fee_rate, vsize)| {
+            // A transaction joining the bottom of this band waits behind it all
+            ahead += vsize;
+            let estimated_blocks = ahead.div_ceil(BLOCK_VSIZE).max(1);
+            let band = FeeBand {
+                min_fee_rate,
+                max_fee_rate,
+                vsize,
+                vsize_ahead: ahead,
+                estimated_blocks,
+                estimated_minutes: estimated_blocks * BLOCK_INTERVAL_MINUTES,
+            };
+            max_fee_rate = Some(min_fee_rate);
+            band
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bands_accumulate_vsize_into_block_estimates() {
+        let mempool = MempoolStats {
+            count: 3000,
+            vsize: 2_600_000,
+            total_fee: 10_000_000,
+            fee_histogram: vec![(5.0, 1_500_000), (20.0, 600_000), (2.0, 500_000)],
+        };
+        let bands = fee_bands(&mempool);
+
+        let summary: Vec<_> = bands
+            .iter()
+            .map(|b| (b.min_fee_rate, b.max_fee_rate, b.vsize_ahead, b.estimated_blocks))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (20.0, None, 600_000, 1),
+                (5.0, Some(20.0), 2_100_000, 3),
+                (2.0, Some(5.0), 2_600_000, 3),
+            ]
+        );
+        assert_eq!(bands[1].estimated_minutes, 30);
+    }
+}
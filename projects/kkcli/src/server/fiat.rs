@@ -0,0 +1,147 @@
+//! Fiat equivalents in the configured display currency.
+//!
+//! Prices come from the Pioneer feed in USD (`price_usd`/`value_usd` on cached
+//! balances) and are converted with a USD-based exchange rate cached in
+//! `fx_rates`. In offline mode, or when no rate is available, no fiat values
+//! are produced and callers omit the field.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use super::cache::DeviceCache;
+
+/// Cached exchange rates older than this are refreshed
+const FX_RATE_MAX_AGE_SECS: i64 = 60 * 60;
+
+/// A value in the display currency
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FiatValue {
+    /// ISO 4217 display currency
+    pub currency: String,
+    /// Unit price in the display currency, when the value is for a single asset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    pub value: String,
+    /// When the underlying USD price was fetched (unix seconds)
+    pub rate_timestamp: i64,
+    /// When the USD exchange rate was published; absent for USD
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fx_timestamp: Option<i64>,
+}
+
+/// Conversion from USD to the display currency
+#[derive(Debug, Clone)]
+pub(crate) struct FiatContext {
+    pub currency: String,
+    pub rate_per_usd: f64,
+    pub fx_timestamp: Option<i64>,
+}
+
+impl FiatContext {
+    fn usd() -> Self {
+        Self {
+            currency: "USD".to_string(),
+            rate_per_usd: 1.0,
+            fx_timestamp: None,
+        }
+    }
+
+    /// Convert a USD amount (as stored in the cache) to the display currency
+    pub fn convert_usd(&self, usd: &str) -> Option<String> {
+        let usd: f64 = usd.trim().parse().ok()?;
+        Some(format!("{:.2}", usd * self.rate_per_usd))
+    }
+
+    /// Fiat equivalent of a single asset balance
+    pub fn balance_value(&self, price_usd: &str, value_usd: &str, price_timestamp: i64) -> Option<FiatValue> {
+        Some(FiatValue {
+            currency: self.currency.clone(),
+            price: self.convert_usd(price_usd),
+            value: self.convert_usd(value_usd)?,
+            rate_timestamp: price_timestamp,
+            fx_timestamp: self.fx_timestamp,
+        })
+    }
+
+    /// Fiat equivalent of an aggregate USD total
+    pub fn total_value(&self, total_usd: &str, price_timestamp: i64) -> Option<FiatValue> {
+        Some(FiatValue {
+            currency: self.currency.clone(),
+            price: None,
+            value: self.convert_usd(total_usd)?,
+            rate_timestamp: price_timestamp,
+            fx_timestamp: self.fx_timestamp,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FxRateFeed {
+    rates: HashMap<String, f64>,
+    time_last_update_unix: Option<i64>,
+}
+
+/// Resolve the conversion for the configured display currency.
+///
+/// Returns `None` in offline mode or when no exchange rate can be found; a
+/// stale cached rate is used if refreshing fails.
+pub(crate) async fn fiat_context(cache: &DeviceCache) -> Option<FiatContext> {
+    match resolve_fiat_context(cache).await {
+        Ok(context) => context,
+        Err(e) => {
+            warn!("Fiat conversion unavailable: {}", e);
+            None
+        }
+    }
+}
+
+async fn resolve_fiat_context(cache: &DeviceCache) -> Result<Option<FiatContext>> {
+    if cache.is_offline_mode().await? {
+        debug!("Offline mode - omitting fiat values");
+        return Ok(None);
+    }
+
+    let currency = cache.get_fiat_currency().await?;
+    if currency == "USD" {
+        return Ok(Some(FiatContext::usd()));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let cached = cache.get_fx_rate(&currency).await?;
+    if let Some((rate, rate_timestamp, fetched_at)) = cached {
+        if now - fetched_at < FX_RATE_MAX_AGE_SECS {
+            return Ok(Some(FiatContext { currency, rate_per_usd: rate, fx_timestamp: Some(rate_timestamp) }));
+        }
+    }
+
+    if let Err(e) = refresh_fx_rates(cache).await {
+        warn!("Failed to refresh exchange rates: {}", e);
+    }
+
+    Ok(cache
+        .get_fx_rate(&currency)
+        .await?
+        .map(|(rate, rate_timestamp, _)| FiatContext { currency, rate_per_usd: rate, fx_timestamp: Some(rate_timestamp) }))
+}
+
+async fn refresh_fx_rates(cache: &DeviceCache) -> Result<()> {
+    let url = cache.get_fx_rate_url().await?;
+    debug!("GET {}", url);
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("User-Agent", "kkcli/1.0")
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Exchange rate feed returned {}", response.status()));
+    }
+
+    let feed: FxRateFeed = response.json().await?;
+    let rate_timestamp = feed.time_last_update_unix.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    cache.save_fx_rates(&feed.rates, &url, rate_timestamp).await
+}
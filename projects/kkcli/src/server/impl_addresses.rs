@@ -1,30 +1,52 @@
 use anyhow::Result;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 use tokio::time::{timeout, Duration};
 use tracing::{info, error, warn};
 use hex;
 
-use crate::transport::{UsbTransport, ProtocolAdapter};
+use crate::transport::ProtocolAdapter;
 use crate::messages::{self, Message};
 use crate::server::routes;
+use crate::server::button_policy::ButtonPolicy;
+use crate::server::wallet::require_taproot;
 use crate::server::cache::DeviceCache;
-use crate::server::{DEVICE_OPERATION_TIMEOUT, try_get_device, try_get_device_with_retry};
+use crate::server::device_queues::DeviceQueues;
+use crate::server::{DEVICE_OPERATION_TIMEOUT, open_device_transport_for, try_get_device_with_retry};
 
 // Enhanced UTXO address generation - using cache!
 pub(crate) async fn generate_utxo_address_impl(
     request: routes::UtxoAddressRequest,
     cache: &DeviceCache,
-    device_mutex: Arc<Mutex<()>>,
+    queues: &DeviceQueues,
 ) -> Result<routes::UtxoAddressResponse> {
     info!("🚀 Checking cache for UTXO address: coin={}, script_type={:?}, path={:?}", 
         request.coin, request.script_type, request.address_n);
     
+    // Policy may require every address to be shown on the device
+    let mut request = request;
+    if ButtonPolicy::load(cache).await?.confirm_address_display {
+        request.show_display = Some(true);
+    }
+    
     // Map script type to our internal format
     let script_type = request.script_type.as_deref().unwrap_or("p2pkh");
+    let input_script_type = match script_type {
+        "p2pkh" => messages::InputScriptType::Spendaddress,
+        "p2wpkh" => messages::InputScriptType::Spendwitness,
+        "p2sh-p2wpkh" => messages::InputScriptType::Spendp2shwitness,
+        "p2tr" => messages::InputScriptType::Spendtaproot,
+        // Any other name would be cached as that type with the wrong address
+        other => return Err(anyhow::anyhow!("Unsupported script type '{}', expected p2pkh, p2sh-p2wpkh, p2wpkh or p2tr", other)),
+    };
     
-    // Check cache first
-    if let Some(cached_address) = cache.get_cached_address(&request.coin, script_type, &request.address_n) {
+    // The cache holds the primary device's addresses; another device is always asked
+    let primary = request.device_id.is_none() || request.device_id == cache.get_device_id();
+
+    // Check cache first (a cached address was never shown on the device)
+    if !primary {
+        info!("🔎 Address for device {:?}, skipping cache", request.device_id);
+    } else if request.show_display == Some(true) {
+        info!("🔎 Address display required, skipping cache");
+    } else if let Some(cached_address) = cache.get_cached_address(&request.coin, script_type, &request.address_n) {
         info!("✨ Found cached address: {}", cached_address.address);
         return Ok(routes::UtxoAddressResponse {
             address: cached_address.address,
@@ -35,30 +57,24 @@ pub(crate) async fn generate_utxo_address_impl(
     // Not in cache - fetch from device with mutex protection
     info!("💫 Address not in cache, fetching from device...");
     
-    // Acquire device mutex to prevent concurrent access
+    // Acquire this device's lock; other devices stay free
+    let device_mutex = queues.lock_for(cache, request.device_id.as_deref());
     let _lock = device_mutex.lock().await;
     info!("🔒 Device mutex acquired for UTXO address generation");
     
-    // Get the USB device
-    let device = try_get_device()?;
-    
     // Wrap device communication in timeout
     let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
-        let (mut transport, _config_descriptor, _handle) = UsbTransport::new(&device, 0)?;
+        let mut transport = open_device_transport_for(request.device_id.as_deref())?;
+        if input_script_type == messages::InputScriptType::Spendtaproot {
+            require_taproot(&mut transport)?;
+        }
         
         // Create GetAddress message
         let mut msg = messages::GetAddress::default();
         msg.address_n = request.address_n.clone();
         msg.coin_name = Some(request.coin.clone());
         msg.show_display = request.show_display;
-        
-        // Set script type
-        match script_type {
-            "p2pkh" => msg.script_type = Some(messages::InputScriptType::Spendaddress as i32),
-            "p2wpkh" => msg.script_type = Some(messages::InputScriptType::Spendwitness as i32),
-            "p2sh-p2wpkh" => msg.script_type = Some(messages::InputScriptType::Spendp2shwitness as i32),
-            _ => msg.script_type = Some(messages::InputScriptType::Spendaddress as i32), // Default to p2pkh
-        }
+        msg.script_type = Some(input_script_type as i32);
         
         info!("Sending GetAddress message to device for {} with path: {:?}", request.coin, request.address_n);
         
@@ -69,8 +85,8 @@ pub(crate) async fn generate_utxo_address_impl(
         match response {
             Message::Address(addr_msg) => {
                 if !addr_msg.address.is_empty() {
-                    // Cache the address for future use
-                    if let Some(device_id) = cache.get_device_id() {
+                    // Cache the address for future use (the primary device's only)
+                    if let Some(device_id) = cache.get_device_id().filter(|_| primary) {
                         if let Err(e) = cache.save_address(
                             &device_id,
                             &request.coin,
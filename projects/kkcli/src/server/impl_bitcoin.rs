@@ -4,10 +4,13 @@ use tracing::{info, error, warn};
 use hex;
 use std::collections::HashMap;
 
-use crate::transport::{UsbTransport, ProtocolAdapter};
+use crate::transport::ProtocolAdapter;
 use crate::messages::{self, Message};
+use crate::server::button_policy::ButtonPolicy;
+use crate::server::progress::{signing_step, Progress};
 use crate::server::routes;
-use crate::server::{DEVICE_OPERATION_TIMEOUT, try_get_device, ServerState};
+use crate::server::wallet::require_taproot;
+use crate::server::{DEVICE_OPERATION_TIMEOUT, open_device_transport_for, ServerState};
 
 // Bitcoin transaction signing implementation
 pub(crate) async fn bitcoin_sign_tx_impl(state: &ServerState, request: routes::BitcoinSignRequest) -> Result<routes::BitcoinSignResponse> {
@@ -270,9 +273,9 @@ pub(crate) async fn bitcoin_sign_tx_impl(state: &ServerState, request: routes::B
                                         address: output.address.clone(),
                                         address_n: output.address_n.clone().unwrap_or_default(),
                                         amount: output.amount.parse()?,
-                                        script_type: parse_bitcoin_output_script_type(&output.script_type)? as i32,
+                                        script_type: output_script_type(output)? as i32,
                                         multisig: None,
-                                        op_return_data: None,
+                                        op_return_data: op_return_bytes(output)?,
                                         address_type: None,
                                         decred_script_version: None,
                                     };
@@ -605,6 +608,33 @@ fn parse_bitcoin_input_script_type(script_type: &str) -> Result<messages::InputS
     }
 }
 
+/// Largest OP_RETURN payload Bitcoin Core relays as standard
+pub(crate) const MAX_OP_RETURN_BYTES: usize = 80;
+
+/// Payload of an OP_RETURN output, or None for any other output
+pub(crate) fn op_return_bytes(output: &routes::BitcoinOutput) -> Result<Option<Vec<u8>>> {
+    if !output.is_op_return() {
+        return Ok(None);
+    }
+    let data = output
+        .op_return_data
+        .as_deref()
+        .ok_or_else(|| anyhow!("OP_RETURN output has no op_return_data"))?;
+    let bytes = hex::decode(data).map_err(|e| anyhow!("Invalid op_return_data hex: {}", e))?;
+    if bytes.len() > MAX_OP_RETURN_BYTES {
+        return Err(anyhow!("op_return_data is {} bytes; at most {} are allowed", bytes.len(), MAX_OP_RETURN_BYTES));
+    }
+    Ok(Some(bytes))
+}
+
+/// Device script type for an output, OP_RETURN included
+pub(crate) fn output_script_type(output: &routes::BitcoinOutput) -> Result<messages::OutputScriptType> {
+    if output.is_op_return() {
+        return Ok(messages::OutputScriptType::Paytoopreturn);
+    }
+    parse_bitcoin_output_script_type(&output.script_type)
+}
+
 // Helper function to parse Bitcoin output script type
 fn parse_bitcoin_output_script_type(script_type: &str) -> Result<messages::OutputScriptType> {
     match script_type.to_lowercase().as_str() {
@@ -897,10 +927,44 @@ fn parse_tx_output_from_hex(hex_str: &str, output_index: usize) -> Result<(u64,
     Err(anyhow::anyhow!("Failed to parse output"))
 }
 
-// Bitcoin message signing
-pub(crate) async fn bitcoin_sign_message_impl(_request: routes::BitcoinSignMessageRequest) -> Result<routes::BitcoinSignMessageResponse> {
-    error!("Bitcoin sign message not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
+// Bitcoin message signing, on a fresh connection like transaction signing
+pub(crate) async fn bitcoin_sign_message_impl(request: routes::BitcoinSignMessageRequest) -> Result<routes::BitcoinSignMessageResponse> {
+    // The signature header records the address type, which the device takes from the script type
+    let script_type = match request.address_n.first().map(|p| p & 0x7fff_ffff) {
+        Some(49) => messages::InputScriptType::Spendp2shwitness,
+        Some(84) => messages::InputScriptType::Spendwitness,
+        _ => messages::InputScriptType::Spendaddress,
+    };
+    
+    let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
+        let mut transport = open_device_transport_for(request.device_id.as_deref())?;
+        let response = transport.with_standard_handler().handle(
+            messages::SignMessage {
+                address_n: request.address_n.clone(),
+                message: request.message.clone().into_bytes(),
+                coin_name: Some(request.coin.clone().unwrap_or_else(|| "Bitcoin".to_string())),
+                script_type: Some(script_type as i32),
+            }
+            .into(),
+        )?;
+        
+        match response {
+            Message::MessageSignature(signature) => Ok(routes::BitcoinSignMessageResponse {
+                address: signature.address.unwrap_or_default(),
+                signature: base64::encode(signature.signature.unwrap_or_default()),
+            }),
+            Message::Failure(failure) => Err(anyhow!("Device failure: {:?}", failure)),
+            _ => Err(anyhow!("Unexpected response: {:?}", response)),
+        }
+    }).await;
+    
+    match result {
+        Ok(response) => response,
+        Err(_) => {
+            error!("Message signing timed out");
+            Err(anyhow!("Device operation timed out"))
+        }
+    }
 }
 
 // Bitcoin message verification
@@ -912,25 +976,46 @@ pub(crate) async fn bitcoin_verify_message_impl(_request: routes::BitcoinVerifyM
 // Add this new implementation that creates a fresh connection
 pub async fn bitcoin_sign_tx_fresh_impl(
     request: routes::BitcoinSignRequest,
+    policy: &ButtonPolicy,
+) -> Result<routes::BitcoinSignResponse> {
+    bitcoin_sign_tx_with_progress(request, policy, &|_| {}).await
+}
+
+/// `bitcoin_sign_tx_fresh_impl`, reporting each input and output the device asks for
+pub async fn bitcoin_sign_tx_with_progress(
+    request: routes::BitcoinSignRequest,
+    policy: &ButtonPolicy,
+    progress: &(dyn Fn(Progress) + Send + Sync),
 ) -> Result<routes::BitcoinSignResponse> {
     info!("🚀 Starting Bitcoin transaction signing with FRESH connection");
     info!("📋 Request: {} inputs, {} outputs", request.inputs.len(), request.outputs.len());
     
-    // Create a fresh USB connection (like the CLI does)
-    // Get USB device first
-    let device = try_get_device()?;
-    
-    // Create new transport (exactly like CLI does)
-    let (transport, _config_descriptor, _handle) = UsbTransport::new(&device, 0)?;
+    // Refuse disallowed transactions before touching the device
+    let required_confirmations = policy.required_confirmations(&request)?;
     
-    info!("✅ Created fresh USB connection");
+    // Create a fresh connection (like the CLI does) to the requested device
+    let mut transport = open_device_transport_for(request.device_id.as_deref())?;
     
-    // Create mutable transport for signing
-    let mut transport = transport;
-    
-    // Now run the same signing logic as the regular implementation
-    // but using our fresh protocol_adapter
+    info!("✅ Created fresh device connection");
     
+    let (response, button_requests) = sign_bitcoin_tx(&mut transport, &request, progress)?;
+    required_confirmations.verify(&button_requests)?;
+    Ok(response)
+}
+
+/// Run the SignTx exchange for `request` on an open connection. Returns the
+/// signed transaction and the ButtonRequest codes the device sent, for the
+/// caller's confirmation policy.
+pub fn sign_bitcoin_tx(
+    transport: &mut dyn ProtocolAdapter,
+    request: &routes::BitcoinSignRequest,
+    progress: &dyn Fn(Progress),
+) -> Result<(routes::BitcoinSignResponse, Vec<i32>)> {
+    // Older firmware would reject taproot inputs halfway through the exchange
+    if request.inputs.iter().any(|input| input.script_type == "p2tr") {
+        require_taproot(transport)?;
+    }
+
     // Build transaction metadata map
     let mut tx_map = HashMap::new();
     
@@ -976,6 +1061,7 @@ pub async fn bitcoin_sign_tx_fresh_impl(
             "p2pkh" => messages::InputScriptType::Spendaddress,
             "p2sh-p2wpkh" => messages::InputScriptType::Spendp2shwitness,
             "p2wpkh" => messages::InputScriptType::Spendwitness,
+            "p2tr" => messages::InputScriptType::Spendtaproot,
             _ => messages::InputScriptType::Spendaddress,
         };
         
@@ -984,7 +1070,7 @@ pub async fn bitcoin_sign_tx_fresh_impl(
             prev_hash: hex::decode(&input.prev_hash)?,
             prev_index: input.prev_index,
             script_sig: None,
-            sequence: Some(0xffffffff),
+            sequence: Some(input.sequence.unwrap_or(0xffffffff)),
             script_type: Some(script_type as i32),
             multisig: None,
             amount: Some(input.amount.parse::<u64>()?),
@@ -996,9 +1082,12 @@ pub async fn bitcoin_sign_tx_fresh_impl(
     let mut new_tx_outputs = Vec::new();
     for output in &request.outputs {
         let script_type = match output.script_type.as_str() {
+            _ if output.is_op_return() => messages::OutputScriptType::Paytoopreturn,
             "p2pkh" => messages::OutputScriptType::Paytoaddress,
             "p2sh" => messages::OutputScriptType::Paytoscripthash,
             "p2wpkh" => messages::OutputScriptType::Paytowitness,
+            "p2sh-p2wpkh" => messages::OutputScriptType::Paytop2shwitness,
+            "p2tr" => messages::OutputScriptType::Paytotaproot,
             _ => messages::OutputScriptType::Paytoaddress,
         };
         
@@ -1008,15 +1097,17 @@ pub async fn bitcoin_sign_tx_fresh_impl(
             amount: output.amount.parse::<u64>()?,
             script_type: script_type as i32,
             multisig: None,
-            op_return_data: None,
+            op_return_data: op_return_bytes(output)?,
             address_type: Some(messages::OutputAddressType::Spend as i32),
             decred_script_version: None,
         });
     }
     
+    let version = request.version.unwrap_or(1);
+    let lock_time = request.lock_time.unwrap_or(0);
     let unsigned_tx = messages::TransactionType {
-        version: Some(1),
-        lock_time: Some(0),
+        version: Some(version),
+        lock_time: Some(lock_time),
         inputs_cnt: Some(request.inputs.len() as u32),
         outputs_cnt: Some(request.outputs.len() as u32),
         inputs: new_tx_inputs,
@@ -1037,8 +1128,8 @@ pub async fn bitcoin_sign_tx_fresh_impl(
         outputs_count: request.outputs.len() as u32,
         inputs_count: request.inputs.len() as u32,
         coin_name: Some("Bitcoin".to_string()),
-        version: Some(1),
-        lock_time: Some(0),
+        version: Some(version),
+        lock_time: Some(lock_time),
         expiry: None,
         overwintered: None,
         version_group_id: None,
@@ -1051,14 +1142,26 @@ pub async fn bitcoin_sign_tx_fresh_impl(
     let mut current_message = Message::SignTx(sign_tx);
     let mut signatures = Vec::new();
     let mut serialized_tx_parts = Vec::new();
+    let mut button_requests: Vec<i32> = Vec::new();
+    let counts = (request.inputs.len() as u64, request.outputs.len() as u64);
     
     loop {
+        // Record ButtonRequest codes so the confirmation policy can be checked
+        let mut record_buttons = |msg: &Message| {
+            if let Message::ButtonRequest(req) = msg {
+                button_requests.push(req.code.unwrap_or_default());
+            }
+            crate::transport::standard_message_handler(msg)
+        };
         let response = transport
-            .with_standard_handler()
+            .with_mut_handler(&mut record_buttons)
             .handle(current_message)?;
         
         match response {
             Message::TxRequest(tx_req) => {
+                if let Some(step) = signing_step(&tx_req, counts.0, counts.1) {
+                    progress(step);
+                }
                 // Handle the transaction request (same logic as regular implementation)
                 match handle_tx_request_for_fresh(tx_req, &tx_map, &mut signatures, &mut serialized_tx_parts) {
                     Ok(Some(next_msg)) => current_message = next_msg,
@@ -1073,10 +1176,11 @@ pub async fn bitcoin_sign_tx_fresh_impl(
                         info!("   Signatures: {}", signatures.len());
                         info!("   Serialized TX: {} bytes", serialized_tx.len());
                         
-                        return Ok(routes::BitcoinSignResponse {
+                        let response = routes::BitcoinSignResponse {
                             signatures: signatures.into_iter().map(|(_, sig)| sig).collect(),
                             serialized_tx: hex::encode(serialized_tx),
-                        });
+                        };
+                        return Ok((response, button_requests));
                     }
                     Err(e) => return Err(e),
                 }
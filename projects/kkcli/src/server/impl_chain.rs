@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Result};
+use std::time::Instant;
+use tracing::{info, warn};
+
+use crate::server::chain::{decode_spent_outpoints, ChainBackend};
+use crate::server::routes;
+use crate::server::ServerState;
+
+/// Check the outpoints spent by `raw_tx` against our own broadcast history and
+/// the chain backend. Conflicts are spends of the same outpoint by a different
+/// transaction.
+pub(crate) async fn find_spend_conflicts(
+    state: &ServerState,
+    backend: &ChainBackend,
+    txid: &str,
+    outpoints: &[String],
+) -> Result<Vec<routes::SpendConflict>> {
+    let mut conflicts: Vec<routes::SpendConflict> = state
+        .cache
+        .find_conflicting_broadcasts(txid, outpoints)
+        .await?
+        .into_iter()
+        .map(|(outpoint, conflicting_txid)| routes::SpendConflict {
+            outpoint,
+            conflicting_txid,
+            source: routes::ConflictSource::History,
+        })
+        .collect();
+
+    for outpoint in outpoints {
+        let (prev_txid, vout) = outpoint
+            .rsplit_once(':')
+            .and_then(|(t, v)| v.parse::<u32>().ok().map(|v| (t, v)))
+            .ok_or_else(|| anyhow!("Malformed outpoint {}", outpoint))?;
+
+        let outspend = backend.outspend(prev_txid, vout).await?;
+        match outspend.txid {
+            Some(spender) if outspend.spent && spender != txid => {
+                if !conflicts.iter().any(|c| &c.outpoint == outpoint && c.conflicting_txid == spender) {
+                    conflicts.push(routes::SpendConflict {
+                        outpoint: outpoint.clone(),
+                        conflicting_txid: spender,
+                        source: routes::ConflictSource::Backend,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(conflicts)
+}
+
+/// Broadcast a signed transaction after checking for double spends.
+///
+/// With the `refuse` policy a conflicting transaction is not broadcast and the
+/// response has `broadcast: false`; `warn` broadcasts anyway and reports the
+/// conflicts; `allow` skips the check.
+pub(crate) async fn broadcast_tx_impl(
+    state: &ServerState,
+    request: routes::BroadcastRequest,
+    api_key: Option<&str>,
+) -> Result<routes::BroadcastResponse> {
+    let (txid, outpoints) = decode_spent_outpoints(&request.raw_tx)?;
+    let backend = ChainBackend::from_cache(&state.cache).await?;
+    let policy = routes::DoubleSpendPolicy::parse(&state.cache.get_double_spend_policy(api_key).await?);
+
+    info!("📡 Broadcast request for {} ({} inputs) via {} (policy {:?})", txid, outpoints.len(), backend.base_url(), policy);
+
+    let conflicts = if policy == routes::DoubleSpendPolicy::Allow {
+        Vec::new()
+    } else {
+        find_spend_conflicts(state, &backend, &txid, &outpoints).await?
+    };
+
+    if !conflicts.is_empty() {
+        for conflict in &conflicts {
+            warn!(
+                "⚠️ {} spends {} which is already spent by {} ({:?})",
+                txid, conflict.outpoint, conflict.conflicting_txid, conflict.source
+            );
+        }
+        if policy == routes::DoubleSpendPolicy::Refuse {
+            return Ok(routes::BroadcastResponse {
+                txid,
+                broadcast: false,
+                policy,
+                conflicts,
+            });
+        }
+    }
+
+    let backend_txid = backend.broadcast(&request.raw_tx).await?;
+    if backend_txid != txid {
+        warn!("Backend reported txid {} for transaction {}", backend_txid, txid);
+    }
+
+    state
+        .cache
+        .record_broadcast(&txid, request.device_id.as_deref(), &request.raw_tx, &outpoints)
+        .await?;
+
+    Ok(routes::BroadcastResponse {
+        txid,
+        broadcast: true,
+        policy,
+        conflicts,
+    })
+}
+
+/// Query every configured backend for its tip. The first backend that answers
+/// (in configured order) is authoritative; the others are compared against it.
+pub(crate) async fn chain_tip_impl(state: &ServerState) -> Result<routes::ChainTipResponse> {
+    let urls = state.cache.get_chain_backend_urls().await?;
+    if urls.is_empty() {
+        return Err(anyhow!("No chain backend configured"));
+    }
+
+    let results = futures::future::join_all(urls.iter().map(|url| async move {
+        let started = Instant::now();
+        let tip = ChainBackend::new(url.clone()).tip().await;
+        (url.clone(), tip, started.elapsed().as_millis() as u64)
+    }))
+    .await;
+
+    let primary = results
+        .iter()
+        .find_map(|(url, tip, _)| tip.as_ref().ok().map(|tip| (url.clone(), tip.clone())))
+        .ok_or_else(|| {
+            let errors: Vec<String> = results
+                .iter()
+                .filter_map(|(url, tip, _)| tip.as_ref().err().map(|e| format!("{}: {}", url, e)))
+                .collect();
+            anyhow!("No chain backend reachable ({})", errors.join("; "))
+        })?;
+    let (primary_url, primary_tip) = primary;
+
+    let backends: Vec<routes::BackendTipStatus> = results
+        .into_iter()
+        .map(|(url, tip, latency_ms)| match tip {
+            Ok(tip) => routes::BackendTipStatus {
+                agrees: tip.hash == primary_tip.hash,
+                height_delta: Some(tip.height as i64 - primary_tip.height as i64),
+                url,
+                height: Some(tip.height),
+                hash: Some(tip.hash),
+                latency_ms,
+                error: None,
+            },
+            Err(e) => routes::BackendTipStatus {
+                url,
+                height: None,
+                hash: None,
+                agrees: false,
+                height_delta: None,
+                latency_ms,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect();
+
+    let agreement = if backends.len() == 1 {
+        routes::BackendAgreement::Single
+    } else if backends.iter().all(|b| b.agrees) {
+        routes::BackendAgreement::Agree
+    } else {
+        warn!("⚠️ Chain backends disagree on tip: {:?}", backends);
+        routes::BackendAgreement::Disagree
+    };
+
+    Ok(routes::ChainTipResponse {
+        height: primary_tip.height,
+        hash: primary_tip.hash,
+        median_time: primary_tip.median_time,
+        timestamp: primary_tip.timestamp,
+        backend: primary_url,
+        agreement,
+        backends,
+    })
+}
+
+/// Mempool fee bands and backend estimates, from the shared fee market cache
+pub(crate) async fn mempool_fees_impl(state: &ServerState) -> Result<routes::MempoolFeesResponse> {
+    let backend = ChainBackend::from_cache(&state.cache).await?;
+    state.fee_market.get(&backend).await
+}
@@ -11,32 +11,29 @@ use rusb::{Device, GlobalContext};
 use crate::transport::UsbTransport;
 use crate::server::routes;
 use crate::server::cache::DeviceCache;
-use crate::server::{DEVICE_IDS, DEVICE_OPERATION_TIMEOUT, try_get_device};
+use crate::server::{DEVICE_IDS, DEVICE_OPERATION_TIMEOUT, simulated_device, try_get_device};
 
 // Device status implementation
 pub(crate) async fn get_device_status_impl() -> Result<routes::DeviceStatus> {
-    match try_get_device() {
-        Ok(_device) => {
-            Ok(routes::DeviceStatus {
-                connected: true,
-                device: Some(routes::DeviceInfo {
-                    device_id: "kkcli-device".to_string(),
-                    name: "KeepKey via CLI".to_string(),
-                    vendor_id: 0x2B24,
-                    product_id: 0x0001,
-                    manufacturer: Some("ShapeShift".to_string()),
-                    product: Some("KeepKey".to_string()),
-                    serial_number: None,
-                    is_keepkey: true,
-                }),
-            })
-        }
-        Err(_) => {
-            Ok(routes::DeviceStatus {
-                connected: false,
-                device: None,
-            })
-        }
+    if simulated_device() || try_get_device().is_ok() {
+        Ok(routes::DeviceStatus {
+            connected: true,
+            device: Some(routes::DeviceInfo {
+                device_id: "kkcli-device".to_string(),
+                name: "KeepKey via CLI".to_string(),
+                vendor_id: 0x2B24,
+                product_id: 0x0001,
+                manufacturer: Some("ShapeShift".to_string()),
+                product: Some("KeepKey".to_string()),
+                serial_number: None,
+                is_keepkey: true,
+            }),
+        })
+    } else {
+        Ok(routes::DeviceStatus {
+            connected: false,
+            device: None,
+        })
     }
 }
 
@@ -117,6 +114,51 @@ pub(crate) async fn get_device_features_impl(State(server_state): State<Arc<Serv
     }
 }
 
+// Self-test needs time for the user to confirm Ping and the address on screen
+const SELFTEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+// Device self-test implementation
+pub(crate) async fn device_selftest_impl(
+    server_state: Arc<ServerState>,
+    device_id: String,
+    request: routes::SelftestRequest,
+) -> Result<crate::cli::system::SelftestReport> {
+    if let Some(known_id) = server_state.cache.get_device_id() {
+        if device_id != known_id {
+            return Err(anyhow::anyhow!("No KeepKey device found with id {}", device_id));
+        }
+    }
+
+    info!("🧪 Running self-test on device {}", device_id);
+
+    let options = crate::cli::system::SelftestOptions {
+        no_button: request.no_button.unwrap_or(false),
+    };
+
+    let result = timeout(SELFTEST_TIMEOUT, async {
+        let _lock = server_state.device_mutex.lock().await;
+        let mut transport_guard = server_state.active_transport.lock().await;
+        if let Some(transport) = transport_guard.as_mut() {
+            Ok(crate::cli::system::run_selftest(transport, &options))
+        } else {
+            error!("Device transport not available for self-test.");
+            Err(anyhow::anyhow!("Device not connected or transport not initialized"))
+        }
+    }).await;
+
+    match result {
+        Ok(Ok(report)) => {
+            info!("🧪 Self-test finished: passed={} in {}ms", report.passed, report.total_ms);
+            Ok(report)
+        }
+        Ok(Err(e)) => Err(e),
+        Err(_) => {
+            error!("Self-test timed out.");
+            Err(anyhow::anyhow!("Device operation timed out"))
+        }
+    }
+}
+
 // SDK compatible features implementation - now using cache!
 pub(crate) async fn get_features_sdk_compatible(cache: &DeviceCache) -> Result<routes::Features> {
     // First try to get from cache
@@ -198,4 +240,58 @@ pub(crate) fn list_devices() -> Box<[Device<GlobalContext>]> {
             vec![].into_boxed_slice()
         }
     }
-} 
\ No newline at end of file
+} 
+/// Remove every trace of a device from the local cache (for selling a device
+/// or rotating seeds). The device doesn't need to be connected.
+pub(crate) async fn forget_device_impl(
+    server_state: &ServerState,
+    device_id: &str,
+) -> Result<Option<routes::ForgetDeviceResponse>> {
+    let forgotten = match server_state.cache.forget_device(device_id).await? {
+        Some(forgotten) => forgotten,
+        None => return Ok(None),
+    };
+    server_state.events.emit("device:forgotten", serde_json::json!({ "deviceId": device_id }));
+    Ok(Some(routes::ForgetDeviceResponse {
+        device_id: device_id.to_string(),
+        addresses: forgotten.addresses,
+        xpubs: forgotten.xpubs,
+        balances: forgotten.balances,
+        transactions: forgotten.transactions,
+    }))
+}
+
+/// Derive the accounts in `scope` on the connected device and refresh balances.
+/// Addresses that are already cached aren't asked for again.
+pub(crate) async fn frontload_impl(
+    server_state: &ServerState,
+    scope: crate::server::cache::FrontloadScope,
+) -> Result<crate::server::cache::FrontloadReport> {
+    if !simulated_device() && try_get_device().is_err() {
+        return Err(anyhow::anyhow!("No KeepKey device found"));
+    }
+
+    let mut frontloader = crate::server::cache::DeviceFrontloader::new(
+        server_state.cache.clone(),
+        Arc::clone(&server_state.active_transport),
+    )
+    .with_scope(scope)
+    .with_progress(crate::server::progress::event_sink(server_state.events.clone()));
+    if simulated_device() {
+        frontloader = frontloader.with_fixture_balances();
+    }
+
+    let _operation = server_state
+        .wallet_locks
+        .acquire(crate::server::wallet_locks::WalletOperation::Frontload, [])
+        .await?;
+    let report = {
+        let _lock = server_state.device_mutex.lock().await;
+        frontloader.frontload_all().await?
+    };
+    server_state.events.emit(
+        "device:frontloaded",
+        serde_json::json!({ "deviceId": report.device_id, "populated": report.populated }),
+    );
+    Ok(report)
+}
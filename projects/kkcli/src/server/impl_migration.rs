@@ -0,0 +1,293 @@
+//! Balances per script type and migration to a newer one.
+//!
+//! Ownership comes from the cached addresses; UTXOs come from the chain
+//! backend. The migration helper only plans: it returns unsigned sign
+//! requests that consolidate older outputs into one address of the target
+//! type, which the caller then signs and broadcasts like any other transaction.
+
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use tracing::{info, warn};
+
+use crate::server::amounts::AmountFormat;
+use crate::server::chain::ChainBackend;
+use crate::server::routes;
+use crate::server::wallet::{
+    load_wallet, script_type_index, WalletState, WalletUtxo, DUST_LIMIT_SATS, SCRIPT_TYPES, TX_OVERHEAD_VBYTES,
+};
+use crate::server::ServerState;
+
+const HARDENED: u32 = 0x8000_0000;
+const SATS_PER_BTC: f64 = 100_000_000.0;
+const DEFAULT_TARGET_SCRIPT_TYPE: &str = "p2wpkh";
+const DEFAULT_MAX_INPUTS: usize = 100;
+const FEE_TARGET_BLOCKS: u32 = 6;
+
+fn account_label(path: &[u32]) -> String {
+    match path {
+        [purpose, coin, account, ..] => format!(
+            "m/{}'/{}'/{}'",
+            purpose & !HARDENED,
+            coin & !HARDENED,
+            account & !HARDENED
+        ),
+        _ => "m".to_string(),
+    }
+}
+
+fn format_btc(sats: u64) -> String {
+    format!("{:.8}", sats as f64 / SATS_PER_BTC)
+}
+
+/// Bitcoin balance of the cached addresses, split by script type
+pub(crate) async fn script_type_balances_impl(
+    state: &ServerState,
+    format: &AmountFormat,
+) -> Result<routes::ScriptTypeBalancesResponse> {
+    let chain = ChainBackend::from_cache(&state.cache).await?;
+    let wallet = load_wallet(state, &chain).await?;
+
+    let script_types: Vec<routes::ScriptTypeBalance> = SCRIPT_TYPES
+        .iter()
+        .enumerate()
+        .map(|(index, info)| {
+            let utxos: Vec<&WalletUtxo> = wallet.utxos.iter().filter(|u| u.script_type == index).collect();
+            let balance_sats = utxos.iter().map(|u| u.utxo.value).sum();
+            routes::ScriptTypeBalance {
+                script_type: info.name.to_string(),
+                label: info.label.to_string(),
+                addresses: wallet.addresses.iter().filter(|(_, _, t)| *t == index).count(),
+                utxos: utxos.len(),
+                balance_sats,
+                balance: format_btc(balance_sats),
+                formatted_balance: format.format(balance_sats as i64),
+                formatted_unit: format.unit,
+            }
+        })
+        .collect();
+
+    let total_sats: u64 = script_types.iter().map(|t| t.balance_sats).sum();
+    Ok(routes::ScriptTypeBalancesResponse {
+        device_id: wallet.device_id,
+        total_sats,
+        formatted_total: format.format(total_sats as i64),
+        script_types,
+        amount_format: format.clone(),
+    })
+}
+
+/// First cached receive address of the target type in `account` that has
+/// never been used and wasn't already picked for another transaction
+async fn pick_destination(
+    chain: &ChainBackend,
+    wallet: &WalletState,
+    target: usize,
+    account: u32,
+    taken: &mut HashSet<Vec<u32>>,
+) -> Result<Vec<u32>> {
+    let mut candidates: Vec<&(String, Vec<u32>, usize)> = wallet
+        .addresses
+        .iter()
+        .filter(|(_, path, t)| *t == target && path.len() == 5 && path[2] == account && path[3] == 0)
+        .filter(|(_, path, _)| !taken.contains(path))
+        .collect();
+    candidates.sort_by_key(|(_, path, _)| path[4]);
+
+    for (address, path, _) in candidates {
+        if wallet.utxos.iter().any(|u| u.path == *path) {
+            continue;
+        }
+        if chain.address_txs(address).await?.is_empty() {
+            taken.insert(path.clone());
+            return Ok(path.clone());
+        }
+    }
+    Err(anyhow!(
+        "No cached unused {} receive address for account {}; derive one first or pass targetAddressN",
+        SCRIPT_TYPES[target].label,
+        account & !HARDENED
+    ))
+}
+
+/// Plan consolidation transactions moving older script types to the target type
+pub(crate) async fn migration_plan_impl(
+    state: &ServerState,
+    request: routes::MigrationRequest,
+    format: &AmountFormat,
+) -> Result<routes::MigrationPlan> {
+    let target_name = request.target_script_type.as_deref().unwrap_or(DEFAULT_TARGET_SCRIPT_TYPE);
+    let target = match script_type_index(target_name) {
+        Some(index) if index >= script_type_index(DEFAULT_TARGET_SCRIPT_TYPE).unwrap_or(0) => index,
+        _ => return Err(anyhow!("Invalid target script type '{}', expected p2wpkh or p2tr", target_name)),
+    };
+    let max_inputs = request.max_inputs.unwrap_or(DEFAULT_MAX_INPUTS);
+    if max_inputs == 0 {
+        return Err(anyhow!("Invalid maxInputs: must be at least 1"));
+    }
+    if let Some(path) = &request.target_address_n {
+        if path.first().map(|p| p & !HARDENED) != Some(SCRIPT_TYPES[target].purpose) {
+            return Err(anyhow!(
+                "Invalid targetAddressN: {} addresses use purpose {}'",
+                SCRIPT_TYPES[target].name,
+                SCRIPT_TYPES[target].purpose
+            ));
+        }
+    }
+
+    let chain = ChainBackend::from_cache(&state.cache).await?;
+    let fee_rate = match request.fee_rate {
+        Some(rate) => rate,
+        None => chain.fee_rate(FEE_TARGET_BLOCKS).await?,
+    };
+    if !fee_rate.is_finite() || fee_rate <= 0.0 {
+        return Err(anyhow!("Invalid fee rate {}", fee_rate));
+    }
+    let per_account = request.per_account.unwrap_or(true);
+    let per_script_type = request.per_script_type.unwrap_or(false);
+
+    let wallet = load_wallet(state, &chain).await?;
+    let frozen = state.cache.get_frozen_outpoints().await?;
+    let mut warnings = Vec::new();
+    let (mut skipped_dust_utxos, mut skipped_dust_sats) = (0, 0);
+
+    // Group the spendable outputs the way the privacy options ask for
+    let mut groups: BTreeMap<(Option<u32>, Option<usize>), Vec<&WalletUtxo>> = BTreeMap::new();
+    let mut unconfirmed = 0;
+    for utxo in wallet.utxos.iter().filter(|u| u.script_type < target) {
+        if frozen.contains(&format!("{}:{}", utxo.utxo.txid, utxo.utxo.vout)) {
+            continue;
+        }
+        if !utxo.utxo.status.confirmed {
+            unconfirmed += 1;
+            continue;
+        }
+        let spend_fee = (SCRIPT_TYPES[utxo.script_type].input_vbytes as f64 * fee_rate).ceil() as u64;
+        if utxo.utxo.value <= spend_fee {
+            skipped_dust_utxos += 1;
+            skipped_dust_sats += utxo.utxo.value;
+            continue;
+        }
+        let account = if per_account { utxo.path.get(2).copied() } else { None };
+        let script_type = if per_script_type { Some(utxo.script_type) } else { None };
+        groups.entry((account, script_type)).or_default().push(utxo);
+    }
+    if unconfirmed > 0 {
+        warnings.push(format!("{} unconfirmed output(s) left out; plan again once they confirm", unconfirmed));
+    }
+
+    let mut taken = HashSet::new();
+    let mut prev_txs: HashMap<String, String> = HashMap::new();
+    let mut transactions = Vec::new();
+    for ((account, _), mut utxos) in groups {
+        utxos.sort_by_key(|u| std::cmp::Reverse(u.utxo.value));
+        for chunk in utxos.chunks(max_inputs) {
+            let input_sats: u64 = chunk.iter().map(|u| u.utxo.value).sum();
+            let estimated_vsize = TX_OVERHEAD_VBYTES
+                + chunk.iter().map(|u| SCRIPT_TYPES[u.script_type].input_vbytes).sum::<u64>()
+                + SCRIPT_TYPES[target].output_vbytes;
+            let fee_sats = (estimated_vsize as f64 * fee_rate).ceil() as u64;
+            if input_sats < fee_sats + DUST_LIMIT_SATS {
+                skipped_dust_utxos += chunk.len();
+                skipped_dust_sats += input_sats;
+                continue;
+            }
+
+            let source_accounts: BTreeSet<String> = chunk.iter().map(|u| account_label(&u.path)).collect();
+            let account_numbers: BTreeSet<u32> = chunk.iter().filter_map(|u| u.path.get(2).copied()).collect();
+            if account_numbers.len() > 1 {
+                warnings.push(format!(
+                    "Transaction {} merges accounts {}, linking them on-chain",
+                    transactions.len() + 1,
+                    source_accounts.iter().cloned().collect::<Vec<_>>().join(", ")
+                ));
+            }
+            let destination_path = match &request.target_address_n {
+                Some(path) => path.clone(),
+                None => {
+                    let account = account.or_else(|| account_numbers.iter().next().copied()).unwrap_or(HARDENED);
+                    pick_destination(&chain, &wallet, target, account, &mut taken).await?
+                }
+            };
+
+            let mut inputs = Vec::new();
+            for u in chunk {
+                let hex = match prev_txs.get(&u.utxo.txid) {
+                    Some(hex) => hex.clone(),
+                    None => {
+                        let hex = chain.tx_hex(&u.utxo.txid).await?;
+                        prev_txs.insert(u.utxo.txid.clone(), hex.clone());
+                        hex
+                    }
+                };
+                inputs.push(routes::BitcoinInput {
+                    address_n: u.path.clone(),
+                    prev_hash: u.utxo.txid.clone(),
+                    prev_index: u.utxo.vout,
+                    amount: u.utxo.value.to_string(),
+                    script_type: SCRIPT_TYPES[u.script_type].name.to_string(),
+                    hex: Some(hex),
+                    sequence: None,
+                });
+            }
+            let output_sats = input_sats - fee_sats;
+
+            transactions.push(routes::MigrationTransaction {
+                source_accounts: source_accounts.into_iter().collect(),
+                source_script_types: chunk
+                    .iter()
+                    .map(|u| SCRIPT_TYPES[u.script_type].name)
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                input_count: chunk.len(),
+                input_sats,
+                fee_sats,
+                output_sats,
+                formatted_input: format.format(input_sats as i64),
+                formatted_fee: format.format(fee_sats as i64),
+                formatted_output: format.format(output_sats as i64),
+                formatted_unit: format.unit,
+                estimated_vsize,
+                destination_path: destination_path.clone(),
+                sign_request: routes::BitcoinSignRequest {
+                    tx_hex: String::new(),
+                    inputs,
+                    outputs: vec![routes::BitcoinOutput {
+                        address: None,
+                        address_n: Some(destination_path),
+                        amount: output_sats.to_string(),
+                        script_type: SCRIPT_TYPES[target].name.to_string(),
+                        op_return_data: None,
+                    }],
+                    version: None,
+                    lock_time: None,
+                    device_id: None,
+                },
+            });
+        }
+    }
+
+    if request.target_address_n.is_some() && transactions.len() > 1 {
+        warnings.push("All transactions pay the same targetAddressN, which reuses that address".to_string());
+    }
+    if transactions.iter().any(|t| t.input_count > 1) {
+        warnings.push("Consolidating links every input of a transaction to one owner on-chain".to_string());
+    }
+    if skipped_dust_utxos > 0 {
+        warn!("Migration leaves {} output(s) ({} sats) that cost more to spend than they hold", skipped_dust_utxos, skipped_dust_sats);
+    }
+    info!("Planned {} migration transaction(s) to {}", transactions.len(), SCRIPT_TYPES[target].name);
+
+    Ok(routes::MigrationPlan {
+        device_id: wallet.device_id,
+        target_script_type: SCRIPT_TYPES[target].name.to_string(),
+        fee_rate,
+        transactions,
+        skipped_dust_utxos,
+        skipped_dust_sats,
+        formatted_skipped_dust: format.format(skipped_dust_sats as i64),
+        warnings,
+        amount_format: format.clone(),
+    })
+}
@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Result};
+use chrono::TimeZone;
+use std::collections::BTreeMap;
+
+use crate::server::amounts::{btc_to_sats, AmountFormat};
+use crate::server::routes;
+use crate::server::ServerState;
+
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+fn parse_date(value: &str) -> Result<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid date '{}', expected YYYY-MM-DD", value))
+}
+
+fn format_btc(sats: i64) -> String {
+    format!("{:.8}", sats as f64 / SATS_PER_BTC)
+}
+
+/// Snapshots and realized transactions for the current device between `from`
+/// and `to` (inclusive, UTC days). Defaults to the last year.
+pub(crate) async fn portfolio_history_impl(
+    state: &ServerState,
+    from: Option<&str>,
+    to: Option<&str>,
+    format: &AmountFormat,
+) -> Result<routes::PortfolioHistoryResponse> {
+    let device_id = state
+        .cache
+        .get_device_id()
+        .ok_or_else(|| anyhow!("No device available"))?;
+
+    let today = chrono::Utc::now().date_naive();
+    let to = match to {
+        Some(to) => parse_date(to)?,
+        None => today,
+    };
+    let from = match from {
+        Some(from) => parse_date(from)?,
+        None => to - chrono::Duration::days(365),
+    };
+    if from > to {
+        return Err(anyhow!("Invalid date range: {} is after {}", from, to));
+    }
+    let (from_str, to_str) = (from.format("%Y-%m-%d").to_string(), to.format("%Y-%m-%d").to_string());
+
+    let mut days: BTreeMap<String, Vec<routes::SnapshotAsset>> = BTreeMap::new();
+    for entry in state.cache.get_portfolio_snapshots(&device_id, &from_str, &to_str).await? {
+        let balance_sats = btc_to_sats(&entry.balance);
+        days.entry(entry.snapshot_date).or_default().push(routes::SnapshotAsset {
+            caip: entry.caip,
+            pubkey: entry.pubkey,
+            symbol: entry.symbol,
+            balance: entry.balance,
+            balance_sats,
+            formatted_balance: balance_sats.map(|sats| format.format(sats)),
+            formatted_unit: format.unit,
+            price_usd: entry.price_usd,
+            value_usd: entry.value_usd,
+        });
+    }
+    let snapshots = days
+        .into_iter()
+        .map(|(date, assets)| {
+            let total: f64 = assets.iter().filter_map(|a| a.value_usd.parse::<f64>().ok()).sum();
+            routes::DailySnapshot {
+                date,
+                total_value_usd: format!("{:.2}", total),
+                assets,
+            }
+        })
+        .collect();
+
+    let from_time = from.and_hms_opt(0, 0, 0).map(|t| chrono::Utc.from_utc_datetime(&t).timestamp()).unwrap_or(0);
+    let to_time = to.and_hms_opt(23, 59, 59).map(|t| chrono::Utc.from_utc_datetime(&t).timestamp()).unwrap_or(i64::MAX);
+    let transactions = state
+        .cache
+        .get_realized_txs(&device_id, from_time, to_time)
+        .await?
+        .into_iter()
+        .map(|tx| {
+            let kind = if tx.amount_sats > 0 {
+                "receive"
+            } else if tx.amount_sats < 0 {
+                "send"
+            } else {
+                "self"
+            };
+            let value_usd = tx
+                .price_usd
+                .as_deref()
+                .and_then(|p| p.parse::<f64>().ok())
+                .map(|price| format!("{:.2}", price * tx.amount_sats as f64 / SATS_PER_BTC));
+            routes::RealizedTransaction {
+                txid: tx.txid,
+                caip: tx.caip,
+                kind: kind.to_string(),
+                amount: format_btc(tx.amount_sats),
+                fee: format_btc(tx.fee_sats),
+                amount_sats: tx.amount_sats,
+                fee_sats: tx.fee_sats,
+                formatted_amount: format.format(tx.amount_sats),
+                formatted_fee: format.format(tx.fee_sats),
+                formatted_unit: format.unit,
+                block_height: tx.block_height,
+                block_time: tx.block_time,
+                price_usd: tx.price_usd,
+                value_usd,
+            }
+        })
+        .collect();
+
+    Ok(routes::PortfolioHistoryResponse {
+        device_id,
+        from: from_str,
+        to: to_str,
+        snapshots,
+        transactions,
+        amount_format: format.clone(),
+    })
+}
+
+/// Realized transactions as CSV, one row per transaction, in the column layout
+/// most tax tools accept for generic imports
+pub(crate) fn portfolio_history_csv(history: &routes::PortfolioHistoryResponse) -> String {
+    let mut csv = String::from("Date,Type,Asset,Amount,Fee,Fee Asset,Price USD,Value USD,Txid\n");
+    for tx in &history.transactions {
+        let date = chrono::Utc
+            .timestamp_opt(tx.block_time, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+            .unwrap_or_default();
+        let kind = match tx.kind.as_str() {
+            "receive" => "Receive",
+            "send" => "Send",
+            _ => "Fee",
+        };
+        // Amounts are unsigned in the export; the Type column carries direction
+        let amount = tx.amount.trim_start_matches('-');
+        csv.push_str(&format!(
+            "{},{},BTC,{},{},BTC,{},{},{}\n",
+            date,
+            kind,
+            amount,
+            tx.fee,
+            tx.price_usd.as_deref().unwrap_or(""),
+            tx.value_usd.as_deref().map(|v| v.trim_start_matches('-')).unwrap_or(""),
+            tx.txid,
+        ));
+    }
+    csv
+}
@@ -0,0 +1,212 @@
+//! Privacy report over the locally cached transaction history.
+//!
+//! Nothing here talks to the network: the transactions come from `tx_history`
+//! (everything broadcast through the server) and ownership from the cached
+//! addresses and their derivation paths. Inputs are attributed to wallet
+//! addresses through the public key they reveal.
+
+use anyhow::{anyhow, Result};
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::{Address, Network, PublicKey, Transaction, TxIn};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tracing::warn;
+
+use crate::server::routes::{self, PrivacyIssue, PrivacyIssueKind};
+use crate::server::ServerState;
+
+const HARDENED: u32 = 0x8000_0000;
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// Payments that are a multiple of 0.001 BTC look typed in by hand
+const ROUND_AMOUNT_SATS: u64 = 100_000;
+
+const ADDRESS_REUSE_PENALTY: u32 = 10;
+const LINKED_ACCOUNTS_PENALTY: u32 = 15;
+const ROUND_CHANGE_PENALTY: u32 = 5;
+/// No single kind of issue can take more than this off the score
+const MAX_PENALTY_PER_KIND: u32 = 40;
+
+/// `m/purpose'/coin'/account'` for a derivation path
+fn account_of(path: &[u32]) -> Option<String> {
+    if path.len() < 3 {
+        return None;
+    }
+    Some(format!(
+        "m/{}'/{}'/{}'",
+        path[0] & !HARDENED,
+        path[1] & !HARDENED,
+        path[2] & !HARDENED
+    ))
+}
+
+/// Public key revealed by an input, from its witness or scriptSig
+fn input_pubkey(input: &TxIn) -> Option<PublicKey> {
+    if input.witness.len() == 2 {
+        return input.witness.last().and_then(|key| PublicKey::from_slice(key).ok());
+    }
+    match input.script_sig.instructions().filter_map(|i| i.ok()).last() {
+        Some(Instruction::PushBytes(bytes)) => PublicKey::from_slice(bytes.as_bytes()).ok(),
+        _ => None,
+    }
+}
+
+/// Wallet address that funded an input. The revealed key is tried as every
+/// single-key address type the wallet uses.
+fn input_owner<'a>(input: &TxIn, owned: &'a HashMap<String, Vec<u32>>) -> Option<&'a str> {
+    let pubkey = input_pubkey(input)?;
+    [
+        Some(Address::p2pkh(&pubkey, Network::Bitcoin)),
+        Address::p2wpkh(&pubkey, Network::Bitcoin).ok(),
+        Address::p2shwpkh(&pubkey, Network::Bitcoin).ok(),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|address| owned.get_key_value(&address.to_string()).map(|(a, _)| a.as_str()))
+}
+
+fn format_btc(sats: u64) -> String {
+    format!("{:.8}", sats as f64 / SATS_PER_BTC)
+}
+
+fn analyze(device_id: &str, owned: &HashMap<String, Vec<u32>>, txs: &[Transaction]) -> routes::PrivacyReport {
+    // Outpoints each wallet address received, and the transactions it showed up in
+    let mut received: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    let mut seen_in: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    let mut issues = Vec::new();
+    let mut analyzed = 0;
+
+    for tx in txs {
+        let txid = tx.txid().to_string();
+
+        let mut spent_from = BTreeSet::new();
+        for input in &tx.input {
+            if let Some(address) = input_owner(input, owned) {
+                spent_from.insert(address);
+                received.entry(address).or_default().insert(input.previous_output.to_string());
+                seen_in.entry(address).or_default().insert(txid.clone());
+            }
+        }
+
+        let mut change = Vec::new();
+        let mut payments = Vec::new();
+        for (vout, output) in tx.output.iter().enumerate() {
+            let address = match Address::from_script(&output.script_pubkey, Network::Bitcoin) {
+                Ok(address) => address.to_string(),
+                // OP_RETURN and other non-standard outputs
+                Err(_) => continue,
+            };
+            match owned.get_key_value(&address) {
+                Some((address, _)) => {
+                    received.entry(address.as_str()).or_default().insert(format!("{}:{}", txid, vout));
+                    seen_in.entry(address.as_str()).or_default().insert(txid.clone());
+                    change.push(output.value);
+                }
+                None => payments.push(output.value),
+            }
+        }
+
+        if spent_from.is_empty() && change.is_empty() {
+            continue;
+        }
+        analyzed += 1;
+
+        let accounts: BTreeSet<String> = spent_from.iter().filter_map(|a| account_of(&owned[*a])).collect();
+        if accounts.len() > 1 {
+            issues.push(PrivacyIssue {
+                kind: PrivacyIssueKind::LinkedAccounts,
+                description: format!(
+                    "Transaction spends coins from {} accounts together, linking them on-chain",
+                    accounts.len()
+                ),
+                txids: vec![txid.clone()],
+                addresses: spent_from.iter().map(|a| a.to_string()).collect(),
+                accounts: accounts.into_iter().collect(),
+            });
+        }
+
+        // Only outgoing transactions have change to give away
+        let round = |sats: &u64| *sats > 0 && sats % ROUND_AMOUNT_SATS == 0;
+        if !spent_from.is_empty()
+            && !payments.is_empty()
+            && payments.iter().all(round)
+            && change.iter().any(|c| !round(c))
+        {
+            issues.push(PrivacyIssue {
+                kind: PrivacyIssueKind::RoundNumberChange,
+                description: format!(
+                    "Payment of {} BTC is a round amount, so the {} BTC output is easily identified as change",
+                    payments.iter().map(|p| format_btc(*p)).collect::<Vec<_>>().join(" + "),
+                    change.iter().map(|c| format_btc(*c)).collect::<Vec<_>>().join(" + ")
+                ),
+                txids: vec![txid],
+                addresses: Vec::new(),
+                accounts: Vec::new(),
+            });
+        }
+    }
+
+    for (address, outpoints) in &received {
+        if outpoints.len() > 1 {
+            issues.push(PrivacyIssue {
+                kind: PrivacyIssueKind::AddressReuse,
+                description: format!("Address {} received {} times", address, outpoints.len()),
+                txids: seen_in.get(address).map(|t| t.iter().cloned().collect()).unwrap_or_default(),
+                addresses: vec![address.to_string()],
+                accounts: account_of(&owned[*address]).into_iter().collect(),
+            });
+        }
+    }
+
+    let count = |kind| issues.iter().filter(|i| i.kind == kind).count() as u32;
+    let (reused, linked, round) = (
+        count(PrivacyIssueKind::AddressReuse),
+        count(PrivacyIssueKind::LinkedAccounts),
+        count(PrivacyIssueKind::RoundNumberChange),
+    );
+    let penalty = (reused * ADDRESS_REUSE_PENALTY).min(MAX_PENALTY_PER_KIND)
+        + (linked * LINKED_ACCOUNTS_PENALTY).min(MAX_PENALTY_PER_KIND)
+        + (round * ROUND_CHANGE_PENALTY).min(MAX_PENALTY_PER_KIND);
+
+    let mut suggestions = Vec::new();
+    if reused > 0 {
+        suggestions.push("Use a fresh receive address for every payment; avoid sharing one address publicly".to_string());
+    }
+    if linked > 0 {
+        suggestions.push("Spend from one account at a time; use coin control instead of consolidating across accounts".to_string());
+    }
+    if round > 0 {
+        suggestions.push("Avoid round payment amounts, or send the whole UTXO so there is no change output".to_string());
+    }
+
+    routes::PrivacyReport {
+        device_id: device_id.to_string(),
+        score: 100u32.saturating_sub(penalty) as u8,
+        transactions_analyzed: analyzed,
+        addresses_analyzed: owned.len(),
+        issues,
+        suggestions,
+    }
+}
+
+/// Analyse every cached transaction of the current device
+pub(crate) async fn privacy_report_impl(state: &ServerState) -> Result<routes::PrivacyReport> {
+    let device_id = state
+        .cache
+        .get_device_id()
+        .ok_or_else(|| anyhow!("No device available"))?;
+
+    let owned = state.cache.get_cached_address_paths(&device_id, "Bitcoin").await?;
+    let mut txs = Vec::new();
+    for entry in state.cache.get_tx_history(&device_id).await? {
+        let decoded = hex::decode(entry.raw_tx.trim())
+            .map_err(|e| anyhow!("{}", e))
+            .and_then(|bytes| deserialize::<Transaction>(&bytes).map_err(|e| anyhow!("{}", e)));
+        match decoded {
+            Ok(tx) => txs.push(tx),
+            Err(e) => warn!("Skipping undecodable transaction {} in privacy report: {}", entry.txid, e),
+        }
+    }
+
+    Ok(analyze(&device_id, &owned, &txs))
+}
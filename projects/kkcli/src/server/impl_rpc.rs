@@ -0,0 +1,388 @@
+//! Bitcoin Core-style wallet RPCs on top of the REST services.
+//!
+//! Only the wallet calls tools usually depend on are mapped. Amounts are BTC
+//! numbers and wallet errors use Core's error codes, so clients written
+//! against `bitcoind` can point at `/rpc` with few changes.
+
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::server::address_validation::validate_address;
+use crate::server::approvals::{await_remote_approval, ApprovalOutcome, ApprovalOutput};
+use crate::server::button_policy::ButtonPolicy;
+use crate::server::chain::ChainBackend;
+use crate::server::routes;
+use crate::server::wallet::{
+    load_wallet, next_address_path, script_type_index, script_type_of_address, DUST_LIMIT_SATS, SCRIPT_TYPES,
+    TX_OVERHEAD_VBYTES,
+};
+use crate::server::ServerState;
+
+const SATS_PER_BTC: f64 = 100_000_000.0;
+const DEFAULT_CONF_TARGET: u32 = 6;
+/// Script type of the change output of `sendtoaddress`
+const CHANGE_SCRIPT_TYPE: &str = "p2wpkh";
+
+// JSON-RPC 2.0 error codes
+pub(crate) const RPC_PARSE_ERROR: i64 = -32700;
+pub(crate) const RPC_INVALID_REQUEST: i64 = -32600;
+const RPC_METHOD_NOT_FOUND: i64 = -32601;
+// Bitcoin Core wallet error codes
+const RPC_TYPE_ERROR: i64 = -3;
+const RPC_WALLET_ERROR: i64 = -4;
+const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+const RPC_WALLET_INSUFFICIENT_FUNDS: i64 = -6;
+const RPC_INVALID_PARAMETER: i64 = -8;
+const RPC_WALLET_NOT_FOUND: i64 = -18;
+const RPC_VERIFY_REJECTED: i64 = -26;
+
+#[derive(Debug)]
+pub(crate) struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub(crate) fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        json!({ "code": self.code, "message": self.message })
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        let message = e.to_string();
+        if message.starts_with("No device") || message.contains("No KeepKey device found") {
+            Self::new(RPC_WALLET_NOT_FOUND, message)
+        } else {
+            Self::new(RPC_WALLET_ERROR, message)
+        }
+    }
+}
+
+/// Positional (`[a, b]`) or named (`{"name": a}`) parameter; `null` counts as absent
+fn param<'a>(params: &'a Value, index: usize, name: &str) -> Option<&'a Value> {
+    match params {
+        Value::Array(values) => values.get(index),
+        Value::Object(values) => values.get(name),
+        _ => None,
+    }
+    .filter(|v| !v.is_null())
+}
+
+fn param_str<'a>(params: &'a Value, index: usize, name: &str) -> Result<Option<&'a str>, RpcError> {
+    match param(params, index, name) {
+        None => Ok(None),
+        Some(value) => value
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| RpcError::new(RPC_TYPE_ERROR, format!("{} must be a string", name))),
+    }
+}
+
+fn param_f64(params: &Value, index: usize, name: &str) -> Result<Option<f64>, RpcError> {
+    match param(params, index, name) {
+        None => Ok(None),
+        // bitcoin-cli sends numbers, some clients send strings
+        Some(Value::String(s)) => s
+            .parse()
+            .map(Some)
+            .map_err(|_| RpcError::new(RPC_TYPE_ERROR, format!("{} must be a number", name))),
+        Some(value) => value
+            .as_f64()
+            .map(Some)
+            .ok_or_else(|| RpcError::new(RPC_TYPE_ERROR, format!("{} must be a number", name))),
+    }
+}
+
+fn param_bool(params: &Value, index: usize, name: &str) -> Result<Option<bool>, RpcError> {
+    match param(params, index, name) {
+        None => Ok(None),
+        Some(value) => value
+            .as_bool()
+            .map(Some)
+            .ok_or_else(|| RpcError::new(RPC_TYPE_ERROR, format!("{} must be a boolean", name))),
+    }
+}
+
+fn required<T>(value: Option<T>, name: &str) -> Result<T, RpcError> {
+    value.ok_or_else(|| RpcError::new(RPC_INVALID_PARAMETER, format!("Missing required parameter {}", name)))
+}
+
+/// Run one RPC method. `api_key` selects the same per-key policies as REST.
+pub(crate) async fn rpc_call_impl(
+    state: &ServerState,
+    method: &str,
+    params: &Value,
+    api_key: Option<&str>,
+) -> Result<Value, RpcError> {
+    info!("RPC {}", method);
+    match method {
+        "getnewaddress" => rpc_getnewaddress(state, params).await,
+        "listunspent" => rpc_listunspent(state, params).await,
+        "sendtoaddress" => rpc_sendtoaddress(state, params, api_key).await,
+        "signmessage" => rpc_signmessage(state, params, api_key).await,
+        _ => Err(RpcError::new(RPC_METHOD_NOT_FOUND, "Method not found")),
+    }
+}
+
+/// getnewaddress ( "label" "address_type" ) - the label is accepted but not stored
+async fn rpc_getnewaddress(state: &ServerState, params: &Value) -> Result<Value, RpcError> {
+    let script_type = match param_str(params, 1, "address_type")?.unwrap_or("bech32") {
+        "legacy" => "p2pkh",
+        "p2sh-segwit" => "p2sh-p2wpkh",
+        "bech32" => "p2wpkh",
+        other => {
+            return Err(RpcError::new(
+                RPC_INVALID_PARAMETER,
+                format!("Unknown or unsupported address type '{}'", other),
+            ))
+        }
+    };
+    let script_type_index = script_type_index(script_type).unwrap_or_default();
+    let address_n = next_address_path(state, script_type_index, 0).await?;
+
+    let response = crate::server::generate_utxo_address_impl(
+        routes::UtxoAddressRequest {
+            address_n,
+            coin: "Bitcoin".to_string(),
+            script_type: Some(script_type.to_string()),
+            show_display: None,
+            device_id: None,
+        },
+        &state.cache,
+        &state.device_queues,
+    )
+    .await?;
+    Ok(json!(response.address))
+}
+
+/// listunspent ( minconf maxconf ["address",...] )
+async fn rpc_listunspent(state: &ServerState, params: &Value) -> Result<Value, RpcError> {
+    let minconf = param_f64(params, 0, "minconf")?.unwrap_or(1.0) as u64;
+    let maxconf = param_f64(params, 1, "maxconf")?.map(|v| v as u64).unwrap_or(9_999_999);
+    let addresses: Option<Vec<&str>> = match param(params, 2, "addresses") {
+        None => None,
+        Some(Value::Array(values)) => Some(values.iter().filter_map(Value::as_str).collect()),
+        Some(_) => return Err(RpcError::new(RPC_TYPE_ERROR, "addresses must be an array")),
+    };
+
+    let chain = ChainBackend::from_cache(&state.cache).await?;
+    let wallet = load_wallet(state, &chain).await?;
+    let tip = chain.tip_height().await?;
+
+    let unspent: Vec<Value> = wallet
+        .utxos
+        .iter()
+        .filter(|u| addresses.as_ref().map_or(true, |a| a.contains(&u.address.as_str())))
+        .filter_map(|u| {
+            let confirmations = match (u.utxo.status.confirmed, u.utxo.status.block_height) {
+                (true, Some(height)) => tip.saturating_sub(height) + 1,
+                _ => 0,
+            };
+            if confirmations < minconf || confirmations > maxconf {
+                return None;
+            }
+            Some(json!({
+                "txid": u.utxo.txid,
+                "vout": u.utxo.vout,
+                "address": u.address,
+                "amount": u.utxo.value as f64 / SATS_PER_BTC,
+                "confirmations": confirmations,
+                "spendable": true,
+                "solvable": true,
+                "safe": confirmations > 0,
+            }))
+        })
+        .collect();
+    Ok(Value::Array(unspent))
+}
+
+/// sendtoaddress "address" amount ( "comment" "comment_to" subtractfeefromamount
+/// replaceable conf_target "estimate_mode" avoid_reuse fee_rate )
+///
+/// Selects confirmed coins largest first, signs on the device (after remote
+/// approval when enabled) and broadcasts. Comments and replaceability are
+/// accepted but ignored.
+async fn rpc_sendtoaddress(state: &ServerState, params: &Value, api_key: Option<&str>) -> Result<Value, RpcError> {
+    let address = required(param_str(params, 0, "address")?, "address")?;
+    let amount = required(param_f64(params, 1, "amount")?, "amount")?;
+    let subtract_fee = param_bool(params, 4, "subtractfeefromamount")?.unwrap_or(false);
+    let conf_target = param_f64(params, 6, "conf_target")?.map(|v| v as u32).unwrap_or(DEFAULT_CONF_TARGET);
+    let fee_rate = param_f64(params, 9, "fee_rate")?;
+
+    if let Err(e) = validate_address(address, bitcoin::Network::Bitcoin) {
+        return Err(RpcError::new(RPC_INVALID_ADDRESS_OR_KEY, format!("Invalid Bitcoin address: {}", e)));
+    }
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err(RpcError::new(RPC_TYPE_ERROR, "Amount out of range"));
+    }
+    let amount_sats = (amount * SATS_PER_BTC).round() as u64;
+
+    let chain = ChainBackend::from_cache(&state.cache).await?;
+    let fee_rate = match fee_rate {
+        Some(rate) if rate.is_finite() && rate > 0.0 => rate,
+        Some(_) => return Err(RpcError::new(RPC_INVALID_PARAMETER, "Invalid fee_rate")),
+        None => chain.fee_rate(conf_target).await?,
+    };
+    let mut wallet = load_wallet(state, &chain).await?;
+    let frozen = state.cache.get_frozen_outpoints().await?;
+    wallet
+        .utxos
+        .retain(|u| u.utxo.status.confirmed && !frozen.contains(&format!("{}:{}", u.utxo.txid, u.utxo.vout)));
+    wallet.utxos.sort_by_key(|u| std::cmp::Reverse(u.utxo.value));
+
+    // Largest first until the amount and the fee (with a change output) are covered
+    let recipient_vbytes = script_type_of_address(address).map_or(34, |t| SCRIPT_TYPES[t].output_vbytes);
+    let change_index = script_type_index(CHANGE_SCRIPT_TYPE).unwrap_or_default();
+    let mut selected = Vec::new();
+    let (mut input_sats, mut input_vbytes) = (0u64, 0u64);
+    let mut plan = None;
+    for utxo in &wallet.utxos {
+        selected.push(utxo);
+        input_sats += utxo.utxo.value;
+        input_vbytes += SCRIPT_TYPES[utxo.script_type].input_vbytes;
+        let vsize = TX_OVERHEAD_VBYTES + input_vbytes + recipient_vbytes + SCRIPT_TYPES[change_index].output_vbytes;
+        let fee = (vsize as f64 * fee_rate).ceil() as u64;
+        let needed = if subtract_fee { amount_sats } else { amount_sats + fee };
+        if input_sats >= needed {
+            let recipient_sats = if subtract_fee { amount_sats.saturating_sub(fee) } else { amount_sats };
+            if recipient_sats < DUST_LIMIT_SATS {
+                return Err(RpcError::new(RPC_TYPE_ERROR, "Transaction amount too small to pay the fee"));
+            }
+            plan = Some((recipient_sats, input_sats - needed));
+            break;
+        }
+    }
+    let (recipient_sats, change_sats) = match plan {
+        Some(plan) => plan,
+        None => return Err(RpcError::new(RPC_WALLET_INSUFFICIENT_FUNDS, "Insufficient funds")),
+    };
+
+    let mut outputs = vec![routes::BitcoinOutput {
+        address: Some(address.to_string()),
+        address_n: None,
+        amount: recipient_sats.to_string(),
+        script_type: script_type_of_address(address).map_or("p2pkh", |t| SCRIPT_TYPES[t].name).to_string(),
+        op_return_data: None,
+    }];
+    // Change below the dust limit is left to the miners
+    if change_sats >= DUST_LIMIT_SATS {
+        let change_path = next_address_path(state, change_index, 1).await?;
+        // Derive it once so the cache (and the next call) knows it is used
+        crate::server::generate_utxo_address_impl(
+            routes::UtxoAddressRequest {
+                address_n: change_path.clone(),
+                coin: "Bitcoin".to_string(),
+                script_type: Some(CHANGE_SCRIPT_TYPE.to_string()),
+                show_display: None,
+                device_id: None,
+            },
+            &state.cache,
+            &state.device_queues,
+        )
+        .await?;
+        outputs.push(routes::BitcoinOutput {
+            address: None,
+            address_n: Some(change_path),
+            amount: change_sats.to_string(),
+            script_type: CHANGE_SCRIPT_TYPE.to_string(),
+            op_return_data: None,
+        });
+    }
+
+    let mut inputs = Vec::new();
+    for utxo in &selected {
+        inputs.push(routes::BitcoinInput {
+            address_n: utxo.path.clone(),
+            prev_hash: utxo.utxo.txid.clone(),
+            prev_index: utxo.utxo.vout,
+            amount: utxo.utxo.value.to_string(),
+            script_type: SCRIPT_TYPES[utxo.script_type].name.to_string(),
+            hex: Some(chain.tx_hex(&utxo.utxo.txid).await?),
+            sequence: None,
+        });
+    }
+    let request = routes::BitcoinSignRequest {
+        tx_hex: String::new(),
+        inputs,
+        outputs,
+        version: None,
+        lock_time: None,
+        device_id: None,
+    };
+
+    let approval_outputs = vec![ApprovalOutput {
+        address: address.to_string(),
+        amount: recipient_sats.to_string(),
+        label: None,
+    }];
+    require_approval(state, api_key, "sign-tx", approval_outputs, None).await?;
+
+    let policy = ButtonPolicy::load(&state.cache).await?;
+    let signed = crate::server::bitcoin_sign_tx_fresh_impl(request, &policy).await?;
+    let broadcast = crate::server::broadcast_tx_impl(
+        state,
+        routes::BroadcastRequest {
+            raw_tx: signed.serialized_tx,
+            device_id: Some(wallet.device_id),
+        },
+        api_key,
+    )
+    .await?;
+    if !broadcast.broadcast {
+        return Err(RpcError::new(
+            RPC_VERIFY_REJECTED,
+            format!("Transaction {} conflicts with an earlier broadcast and was not sent", broadcast.txid),
+        ));
+    }
+    Ok(json!(broadcast.txid))
+}
+
+/// signmessage "address" "message"
+async fn rpc_signmessage(state: &ServerState, params: &Value, api_key: Option<&str>) -> Result<Value, RpcError> {
+    let address = required(param_str(params, 0, "address")?, "address")?;
+    let message = required(param_str(params, 1, "message")?, "message")?;
+
+    if address.starts_with("bc1p") {
+        return Err(RpcError::new(RPC_TYPE_ERROR, "Address does not refer to key"));
+    }
+    let device_id = state.cache.get_device_id().ok_or_else(|| RpcError::new(RPC_WALLET_NOT_FOUND, "No device available"))?;
+    let address_n = state
+        .cache
+        .get_cached_address_paths(&device_id, "Bitcoin")
+        .await?
+        .remove(address)
+        .ok_or_else(|| RpcError::new(RPC_WALLET_ERROR, "Private key not available"))?;
+
+    require_approval(state, api_key, "sign-message", Vec::new(), Some(message.to_string())).await?;
+
+    let signed = crate::server::bitcoin_sign_message_impl(routes::BitcoinSignMessageRequest {
+        address_n,
+        message: message.to_string(),
+        coin: Some("Bitcoin".to_string()),
+        device_id: None,
+    })
+    .await?;
+    Ok(json!(signed.signature))
+}
+
+async fn require_approval(
+    state: &ServerState,
+    api_key: Option<&str>,
+    kind: &str,
+    outputs: Vec<ApprovalOutput>,
+    message: Option<String>,
+) -> Result<(), RpcError> {
+    match await_remote_approval(state, api_key, kind, "/rpc", outputs, message).await? {
+        ApprovalOutcome::Approved => Ok(()),
+        ApprovalOutcome::Rejected => Err(RpcError::new(RPC_WALLET_ERROR, "Signing request rejected")),
+        ApprovalOutcome::TimedOut => Err(RpcError::new(RPC_WALLET_ERROR, "Signing request was not approved in time")),
+    }
+}
@@ -0,0 +1,439 @@
+//! Paying several recipients in one transaction.
+//!
+//! A batch names up to [`MAX_OUTPUTS`] destinations, each with an amount and an
+//! optional label that is shown in the preview and the approval prompt but
+//! never leaves this machine. Coins are picked largest first from the cached
+//! UTXOs, leaving out frozen ones, unconfirmed ones (unless asked for) and
+//! those worth less than the fee to spend them. What is left over goes to the
+//! next change address of the chosen script type, or to the fee when it would
+//! be dust.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use tracing::info;
+
+use crate::cli::types::Bip32Path;
+use crate::server::address_validation::validate_address;
+use crate::server::amounts::AmountFormat;
+use crate::server::approvals::{await_remote_approval, ApprovalOutcome, ApprovalOutput};
+use crate::server::button_policy::ButtonPolicy;
+use crate::server::chain::ChainBackend;
+use crate::server::routes;
+use crate::server::tx_size::{estimate_size, fee_for, input_weight};
+use crate::server::wallet::{load_wallet, next_address_path, script_type_index, WalletUtxo, DUST_LIMIT_SATS, SCRIPT_TYPES};
+use crate::server::wallet_locks::WalletOperation;
+use crate::server::ServerState;
+
+const DEFAULT_CONF_TARGET: u32 = 6;
+const DEFAULT_CHANGE_SCRIPT_TYPE: &str = "p2wpkh";
+/// Most destinations in one batch; the device asks to confirm each of them
+pub(crate) const MAX_OUTPUTS: usize = 100;
+/// 21 million BTC
+const MAX_MONEY_SATS: u64 = 21_000_000 * 100_000_000;
+const MAX_LABEL_CHARS: usize = 64;
+
+/// Coins picked for a batch and where the rest of them goes
+#[derive(Debug)]
+struct Selection {
+    spent: Vec<WalletUtxo>,
+    fee_sats: u64,
+    /// 0 when the remainder was too small for an output and went to the fee
+    change_sats: u64,
+    vsize: u64,
+}
+
+/// Check every destination and return their output script types and total,
+/// before any coins are looked at
+fn validate_outputs(outputs: &[routes::SendManyOutput]) -> Result<(Vec<&'static str>, u64)> {
+    if outputs.is_empty() {
+        return Err(anyhow!("Invalid outputs: give at least one destination"));
+    }
+    if outputs.len() > MAX_OUTPUTS {
+        return Err(anyhow!(
+            "Invalid outputs: at most {} destinations per transaction, got {}",
+            MAX_OUTPUTS,
+            outputs.len()
+        ));
+    }
+
+    let mut script_types = Vec::with_capacity(outputs.len());
+    let mut total: u64 = 0;
+    for (i, output) in outputs.iter().enumerate() {
+        let number = i + 1;
+        let validated = validate_address(&output.address, bitcoin::Network::Bitcoin)
+            .map_err(|e| anyhow!("Invalid output {}: {}", number, e))?;
+        if output.amount_sats < DUST_LIMIT_SATS {
+            return Err(anyhow!(
+                "Invalid output {}: {} sats is below the {} sat dust limit",
+                number,
+                output.amount_sats,
+                DUST_LIMIT_SATS
+            ));
+        }
+        if output.label.as_ref().map_or(false, |l| l.chars().count() > MAX_LABEL_CHARS) {
+            return Err(anyhow!("Invalid output {}: label is longer than {} characters", number, MAX_LABEL_CHARS));
+        }
+        total = total
+            .checked_add(output.amount_sats)
+            .filter(|total| *total <= MAX_MONEY_SATS)
+            .ok_or_else(|| anyhow!("Invalid outputs: total is more than 21,000,000 BTC"))?;
+        script_types.push(validated.script_type());
+    }
+    Ok((script_types, total))
+}
+
+/// Add coins largest first until they pay `total` to `outputs` plus the fee,
+/// with a change output of `change_type` when the remainder is worth one
+fn select_coins(
+    mut candidates: Vec<WalletUtxo>,
+    outputs: &[&str],
+    total: u64,
+    change_type: &str,
+    fee_rate: f64,
+) -> Result<Selection> {
+    candidates.sort_by_key(|u| std::cmp::Reverse(u.utxo.value));
+    let with_change: Vec<(&str, u64)> = outputs
+        .iter()
+        .map(|t| (*t, 1))
+        .chain(std::iter::once((change_type, 1)))
+        .collect();
+    let without_change = &with_change[..outputs.len()];
+
+    let mut spent = Vec::new();
+    let mut input_sats: u64 = 0;
+    for utxo in candidates {
+        input_sats += utxo.utxo.value;
+        spent.push(utxo);
+        let inputs: Vec<(&str, u64)> = spent.iter().map(|u| (SCRIPT_TYPES[u.script_type].name, 1)).collect();
+
+        let size = estimate_size(&inputs, &with_change)?;
+        let fee_sats = fee_for(size.vsize, fee_rate);
+        if input_sats >= total + fee_sats + DUST_LIMIT_SATS {
+            return Ok(Selection {
+                change_sats: input_sats - total - fee_sats,
+                fee_sats,
+                vsize: size.vsize,
+                spent,
+            });
+        }
+        let size = estimate_size(&inputs, without_change)?;
+        if input_sats >= total + fee_for(size.vsize, fee_rate) {
+            // Too little left for a change output; the miners get it
+            return Ok(Selection {
+                fee_sats: input_sats - total,
+                change_sats: 0,
+                vsize: size.vsize,
+                spent,
+            });
+        }
+    }
+    Err(anyhow!(
+        "Insufficient funds: {} sats spendable, {} sats to send plus the fee",
+        input_sats,
+        total
+    ))
+}
+
+/// The coins a batch spends and its preview
+async fn plan_send_many(
+    state: &ServerState,
+    chain: &ChainBackend,
+    request: &routes::SendManyRequest,
+    format: &AmountFormat,
+) -> Result<(Selection, routes::SendManyPreview)> {
+    let (output_types, total_sats) = validate_outputs(&request.outputs)?;
+    let change_name = request.change_script_type.as_deref().unwrap_or(DEFAULT_CHANGE_SCRIPT_TYPE);
+    let change_type = script_type_index(change_name).ok_or_else(|| {
+        anyhow!("Invalid change script type '{}', expected p2pkh, p2sh-p2wpkh, p2wpkh or p2tr", change_name)
+    })?;
+    let fee_rate = match request.fee_rate {
+        Some(rate) => rate,
+        None => chain.fee_rate(request.conf_target.unwrap_or(DEFAULT_CONF_TARGET)).await?,
+    };
+    if !fee_rate.is_finite() || fee_rate <= 0.0 {
+        return Err(anyhow!("Invalid fee rate {}", fee_rate));
+    }
+    let include_unconfirmed = request.include_unconfirmed.unwrap_or(false);
+
+    let frozen = state.cache.get_frozen_outpoints().await?;
+    let wallet = load_wallet(state, chain).await?;
+    let own_addresses: HashSet<&str> = wallet.addresses.iter().map(|(a, _, _)| a.as_str()).collect();
+    let mut warnings = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, output) in request.outputs.iter().enumerate() {
+        if !seen.insert(output.address.as_str()) {
+            warnings.push(format!("Output {} pays {} again", i + 1, output.address));
+        }
+        if own_addresses.contains(output.address.as_str()) {
+            warnings.push(format!("Output {} pays {}, an address of this wallet", i + 1, output.address));
+        }
+    }
+
+    let candidates: Vec<WalletUtxo> = wallet
+        .utxos
+        .into_iter()
+        .filter(|u| !frozen.contains(&format!("{}:{}", u.utxo.txid, u.utxo.vout)))
+        .filter(|u| u.utxo.status.confirmed || include_unconfirmed)
+        .filter(|u| {
+            let spend_fee = fee_for(input_weight(SCRIPT_TYPES[u.script_type].name).unwrap_or_default().div_ceil(4), fee_rate);
+            u.utxo.value > spend_fee
+        })
+        .collect();
+    let selection = select_coins(candidates, &output_types, total_sats, SCRIPT_TYPES[change_type].name, fee_rate)?;
+
+    let change = if selection.change_sats > 0 {
+        let address_n = next_address_path(state, change_type, 1).await?;
+        Some(routes::SendManyChange {
+            path: format!("m{}", Bip32Path::from(address_n.clone())),
+            address_n,
+            script_type: SCRIPT_TYPES[change_type].name.to_string(),
+            address: None,
+            amount_sats: selection.change_sats,
+            formatted_amount: format.format(selection.change_sats as i64),
+        })
+    } else {
+        None
+    };
+    let preview = routes::SendManyPreview {
+        device_id: wallet.device_id,
+        outputs: request
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| routes::SendManyPreviewOutput {
+                index,
+                label: output.label.clone(),
+                address: output.address.clone(),
+                amount_sats: output.amount_sats,
+                formatted_amount: format.format(output.amount_sats as i64),
+            })
+            .collect(),
+        change,
+        total_sats,
+        fee_sats: selection.fee_sats,
+        fee_rate,
+        vsize: selection.vsize,
+        inputs: selection.spent.len(),
+        input_sats: selection.spent.iter().map(|u| u.utxo.value).sum(),
+        formatted_total: format.format(total_sats as i64),
+        formatted_fee: format.format(selection.fee_sats as i64),
+        formatted_unit: format.unit,
+        warnings,
+        amount_format: format.clone(),
+    };
+    Ok((selection, preview))
+}
+
+/// What a batch would spend, pay and cost, without touching the device
+pub(crate) async fn send_many_preview_impl(
+    state: &ServerState,
+    request: routes::SendManyRequest,
+    format: &AmountFormat,
+) -> Result<routes::SendManyPreview> {
+    let chain = ChainBackend::from_cache(&state.cache).await?;
+    let (_, preview) = plan_send_many(state, &chain, &request, format).await?;
+    Ok(preview)
+}
+
+/// Build the batch, sign it on the device (after remote approval when enabled)
+/// and, unless `broadcast` is false, broadcast it
+pub(crate) async fn send_many_impl(
+    state: &ServerState,
+    request: routes::SendManyRequest,
+    format: &AmountFormat,
+    api_key: Option<&str>,
+) -> Result<routes::SendManyResponse> {
+    let chain = ChainBackend::from_cache(&state.cache).await?;
+    let (selection, mut preview) = plan_send_many(state, &chain, &request, format).await?;
+    let _operation = state
+        .wallet_locks
+        .acquire(
+            WalletOperation::Sign,
+            selection.spent.iter().map(|u| format!("{}:{}", u.utxo.txid, u.utxo.vout)),
+        )
+        .await?;
+
+    let mut inputs = Vec::with_capacity(selection.spent.len());
+    for utxo in &selection.spent {
+        inputs.push(routes::BitcoinInput {
+            address_n: utxo.path.clone(),
+            prev_hash: utxo.utxo.txid.clone(),
+            prev_index: utxo.utxo.vout,
+            amount: utxo.utxo.value.to_string(),
+            script_type: SCRIPT_TYPES[utxo.script_type].name.to_string(),
+            hex: Some(chain.tx_hex(&utxo.utxo.txid).await?),
+            sequence: None,
+        });
+    }
+    let mut outputs: Vec<routes::BitcoinOutput> = preview
+        .outputs
+        .iter()
+        .map(|output| routes::BitcoinOutput {
+            address: Some(output.address.clone()),
+            address_n: None,
+            amount: output.amount_sats.to_string(),
+            script_type: crate::server::wallet::script_type_of_address(&output.address)
+                .map_or("p2pkh", |t| SCRIPT_TYPES[t].name)
+                .to_string(),
+            op_return_data: None,
+        })
+        .collect();
+
+    let approval_outputs = preview
+        .outputs
+        .iter()
+        .map(|output| ApprovalOutput {
+            address: output.address.clone(),
+            amount: output.amount_sats.to_string(),
+            label: output.label.clone(),
+        })
+        .collect();
+    match await_remote_approval(state, api_key, "sign-tx", "/api/v2/send-many", approval_outputs, None).await? {
+        ApprovalOutcome::Approved => {}
+        ApprovalOutcome::Rejected => return Err(anyhow!("Signing request rejected")),
+        ApprovalOutcome::TimedOut => return Err(anyhow!("Signing request was not approved in time")),
+    }
+
+    if let Some(change) = preview.change.as_mut() {
+        // Derive and cache the change address so the wallet finds the coins coming back
+        let derived = crate::server::generate_utxo_address_impl(
+            routes::UtxoAddressRequest {
+                address_n: change.address_n.clone(),
+                coin: "Bitcoin".to_string(),
+                script_type: Some(change.script_type.clone()),
+                show_display: None,
+                device_id: None,
+            },
+            &state.cache,
+            &state.device_queues,
+        )
+        .await?;
+        change.address = Some(derived.address);
+        outputs.push(routes::BitcoinOutput {
+            address: None,
+            address_n: Some(change.address_n.clone()),
+            amount: change.amount_sats.to_string(),
+            script_type: change.script_type.clone(),
+            op_return_data: None,
+        });
+    }
+
+    info!(
+        "💸 Sending {} sats to {} output(s) from {} input(s), {} sat fee",
+        preview.total_sats,
+        preview.outputs.len(),
+        preview.inputs,
+        preview.fee_sats
+    );
+    let policy = ButtonPolicy::load(&state.cache).await?;
+    let signed = {
+        let _device = state.device_queues.lock_for(&state.cache, None).lock_owned().await;
+        crate::server::bitcoin_sign_tx_fresh_impl(
+            routes::BitcoinSignRequest {
+                tx_hex: String::new(),
+                inputs,
+                outputs,
+                version: None,
+                lock_time: None,
+                device_id: None,
+            },
+            &policy,
+        )
+        .await?
+    };
+
+    if !request.broadcast.unwrap_or(true) {
+        return Ok(routes::SendManyResponse {
+            preview,
+            serialized_tx: signed.serialized_tx,
+            txid: None,
+            broadcast: None,
+        });
+    }
+    let broadcast = crate::server::broadcast_tx_impl(
+        state,
+        routes::BroadcastRequest {
+            raw_tx: signed.serialized_tx.clone(),
+            device_id: Some(preview.device_id.clone()),
+        },
+        api_key,
+    )
+    .await?;
+    Ok(routes::SendManyResponse {
+        preview,
+        serialized_tx: signed.serialized_tx,
+        txid: broadcast.broadcast.then(|| broadcast.txid.clone()),
+        broadcast: Some(broadcast),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::chain::{EsploraUtxo, TxStatus};
+
+    const DESTINATION: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+    fn output(amount_sats: u64, label: Option<&str>) -> routes::SendManyOutput {
+        routes::SendManyOutput {
+            address: DESTINATION.to_string(),
+            amount_sats,
+            label: label.map(str::to_string),
+        }
+    }
+
+    fn utxo(value: u64) -> WalletUtxo {
+        WalletUtxo {
+            address: "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu".to_string(),
+            path: vec![84 | 0x8000_0000, 0x8000_0000, 0x8000_0000, 0, 0],
+            script_type: 2,
+            utxo: EsploraUtxo {
+                txid: format!("{:064x}", value),
+                vout: 0,
+                value,
+                status: TxStatus { confirmed: true, block_height: None, block_hash: None, block_time: None },
+            },
+        }
+    }
+
+    #[test]
+    fn outputs_are_checked_against_limits() {
+        let (types, total) = validate_outputs(&[output(10_000, Some("Alice")), output(20_000, None)]).unwrap();
+        assert_eq!(types, vec!["p2wpkh", "p2wpkh"]);
+        assert_eq!(total, 30_000);
+
+        assert!(validate_outputs(&[]).is_err());
+        assert!(validate_outputs(&vec![output(10_000, None); MAX_OUTPUTS + 1]).is_err());
+        assert!(validate_outputs(&[output(DUST_LIMIT_SATS - 1, None)]).is_err());
+        assert!(validate_outputs(&[output(10_000, Some(&"x".repeat(MAX_LABEL_CHARS + 1)))]).is_err());
+        assert!(validate_outputs(&[output(MAX_MONEY_SATS, None), output(10_000, None)]).is_err());
+
+        let mut bad = output(10_000, None);
+        bad.address = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx".to_string();
+        let error = validate_outputs(&[output(10_000, None), bad]).unwrap_err().to_string();
+        assert!(error.starts_with("Invalid output 2"), "{}", error);
+    }
+
+    #[test]
+    fn coins_are_selected_largest_first_with_change() {
+        let selection = select_coins(vec![utxo(5_000), utxo(200_000), utxo(50_000)], &["p2wpkh", "p2wpkh"], 100_000, "p2wpkh", 2.0).unwrap();
+        assert_eq!(selection.spent.len(), 1);
+        assert_eq!(selection.spent[0].utxo.value, 200_000);
+        assert_eq!(selection.fee_sats, fee_for(selection.vsize, 2.0));
+        assert_eq!(selection.change_sats, 200_000 - 100_000 - selection.fee_sats);
+
+        let selection = select_coins(vec![utxo(60_000), utxo(50_000)], &["p2wpkh"], 100_000, "p2wpkh", 2.0).unwrap();
+        assert_eq!(selection.spent.len(), 2);
+        assert!(selection.change_sats >= DUST_LIMIT_SATS);
+    }
+
+    #[test]
+    fn dust_change_goes_to_the_fee() {
+        let selection = select_coins(vec![utxo(100_400)], &["p2wpkh"], 100_000, "p2wpkh", 2.0).unwrap();
+        assert_eq!(selection.change_sats, 0);
+        assert_eq!(selection.fee_sats, 400);
+
+        let error = select_coins(vec![utxo(100_100)], &["p2wpkh"], 100_000, "p2wpkh", 2.0).unwrap_err();
+        assert!(error.to_string().starts_with("Insufficient funds"), "{}", error);
+    }
+}
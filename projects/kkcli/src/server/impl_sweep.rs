@@ -0,0 +1,306 @@
+//! Sending everything: how much a sweep to one destination would deliver, and
+//! the one-call flow that builds, signs and broadcasts it.
+//!
+//! A sweep spends every cached UTXO except frozen ones, unconfirmed ones
+//! (unless asked for) and those worth less than the fee to spend them, into a
+//! single output with no change. It can be narrowed to one address or to one
+//! derivation path (an account such as `m/44'/0'/0'`, or a single address
+//! path), e.g. to empty an old legacy account or a paper wallet's address.
+
+use anyhow::{anyhow, Result};
+use tracing::info;
+
+use crate::server::address_validation::validate_address;
+use crate::server::approvals::{await_remote_approval, ApprovalOutcome, ApprovalOutput};
+use crate::server::button_policy::ButtonPolicy;
+use crate::server::chain::ChainBackend;
+use crate::server::routes;
+use crate::server::tx_size::{estimate_size, fee_for, input_weight};
+use crate::cli::types::Bip32Path;
+use crate::server::wallet::{load_wallet, WalletState, WalletUtxo, DUST_LIMIT_SATS, SCRIPT_TYPES};
+use crate::server::ServerState;
+
+const HARDENED: u32 = 0x8000_0000;
+const DEFAULT_CONF_TARGET: u32 = 6;
+/// purpose / coin type / account / change / index
+const ADDRESS_PATH_DEPTH: usize = 5;
+
+/// Which of the wallet's UTXOs a sweep may spend
+enum SweepSource {
+    Wallet,
+    Address(String),
+    /// Every address at or below this path
+    Path(Vec<u32>),
+}
+
+impl SweepSource {
+    fn from_request(request: &routes::MaxSendRequest) -> Result<Self> {
+        match (&request.source_address, &request.source_path) {
+            (None, None) => Ok(Self::Wallet),
+            (Some(address), None) => {
+                validate_address(address, bitcoin::Network::Bitcoin).map_err(|e| anyhow!("Invalid source address: {}", e))?;
+                Ok(Self::Address(address.clone()))
+            }
+            (None, Some(path)) => {
+                let path: Vec<u32> = path
+                    .parse::<Bip32Path>()
+                    .map_err(|e| anyhow!("Invalid source path {:?}: {}", path, e.to_string().trim()))?
+                    .into();
+                if path.len() < 3 || path.len() > ADDRESS_PATH_DEPTH {
+                    return Err(anyhow!("Invalid source path: give an account (m/44'/0'/0') or an address path"));
+                }
+                Ok(Self::Path(path))
+            }
+            (Some(_), Some(_)) => Err(anyhow!("Invalid source: give sourceAddress or sourcePath, not both")),
+        }
+    }
+
+    fn includes(&self, utxo: &WalletUtxo) -> bool {
+        match self {
+            Self::Wallet => true,
+            Self::Address(address) => &utxo.address == address,
+            Self::Path(prefix) => utxo.path.starts_with(prefix),
+        }
+    }
+
+    fn describe(&self) -> Option<String> {
+        match self {
+            Self::Wallet => None,
+            Self::Address(address) => Some(address.clone()),
+            Self::Path(path) => Some(format!("m{}", Bip32Path::from(path.clone()))),
+        }
+    }
+}
+
+/// Load the wallet, first deriving (and caching) the address of a full address
+/// path the cache doesn't know yet, so its funds can be found
+async fn load_wallet_for(state: &ServerState, chain: &ChainBackend, source: &SweepSource) -> Result<WalletState> {
+    let wallet = load_wallet(state, chain).await?;
+    match source {
+        SweepSource::Address(address) if !wallet.addresses.iter().any(|(a, _, _)| a == address) => Err(anyhow!(
+            "Invalid source address: {} is not a cached address of this device; sweep by its derivation path instead",
+            address
+        )),
+        SweepSource::Path(path) if path.len() == ADDRESS_PATH_DEPTH && !wallet.addresses.iter().any(|(_, p, _)| p == path) => {
+            let script_type = SCRIPT_TYPES
+                .iter()
+                .find(|t| t.purpose | HARDENED == path[0])
+                .ok_or_else(|| anyhow!("Invalid source path: unknown purpose {}'", path[0] & !HARDENED))?;
+            crate::server::generate_utxo_address_impl(
+                routes::UtxoAddressRequest {
+                    address_n: path.clone(),
+                    coin: "Bitcoin".to_string(),
+                    script_type: Some(script_type.name.to_string()),
+                    show_display: None,
+                    device_id: None,
+                },
+                &state.cache,
+                &state.device_queues,
+            )
+            .await?;
+            load_wallet(state, chain).await
+        }
+        _ => Ok(wallet),
+    }
+}
+
+/// The UTXOs a sweep spends and what it would deliver
+async fn plan_sweep(
+    state: &ServerState,
+    chain: &ChainBackend,
+    request: &routes::MaxSendRequest,
+) -> Result<(Vec<WalletUtxo>, routes::MaxSendResponse)> {
+    let destination_type = validate_address(&request.destination, bitcoin::Network::Bitcoin)
+        .map_err(|e| anyhow!("Invalid destination: {}", e))?
+        .script_type();
+    let fee_rate = match request.fee_rate {
+        Some(rate) => rate,
+        None => chain.fee_rate(request.conf_target.unwrap_or(DEFAULT_CONF_TARGET)).await?,
+    };
+    if !fee_rate.is_finite() || fee_rate <= 0.0 {
+        return Err(anyhow!("Invalid fee rate {}", fee_rate));
+    }
+    let include_unconfirmed = request.include_unconfirmed.unwrap_or(false);
+    let source = SweepSource::from_request(request)?;
+
+    let frozen = state.cache.get_frozen_outpoints().await?;
+    let wallet = load_wallet_for(state, chain, &source).await?;
+    let (mut frozen_utxos, mut unconfirmed_utxos, mut uneconomical_utxos) = (0, 0, 0);
+    let mut spent = Vec::new();
+    for utxo in wallet.utxos.into_iter().filter(|u| source.includes(u)) {
+        let script_type = SCRIPT_TYPES[utxo.script_type].name;
+        // Fee for this input alone, rounded up to whole vbytes
+        let spend_fee = fee_for(input_weight(script_type).unwrap_or_default().div_ceil(4), fee_rate);
+        if frozen.contains(&format!("{}:{}", utxo.utxo.txid, utxo.utxo.vout)) {
+            frozen_utxos += 1;
+        } else if !utxo.utxo.status.confirmed && !include_unconfirmed {
+            unconfirmed_utxos += 1;
+        } else if utxo.utxo.value <= spend_fee {
+            uneconomical_utxos += 1;
+        } else {
+            spent.push(utxo);
+        }
+    }
+    if spent.is_empty() {
+        let from = source.describe().map(|s| format!(" in {}", s)).unwrap_or_default();
+        return Err(anyhow!("Insufficient funds: no spendable UTXOs{} at {} sat/vB", from, fee_rate));
+    }
+
+    let inputs: Vec<(&str, u64)> = spent.iter().map(|u| (SCRIPT_TYPES[u.script_type].name, 1)).collect();
+    let size = estimate_size(&inputs, &[(destination_type, 1)])?;
+    let fee_sats = fee_for(size.vsize, fee_rate);
+    let input_sats: u64 = spent.iter().map(|u| u.utxo.value).sum();
+    let amount_sats = input_sats.saturating_sub(fee_sats);
+    if amount_sats < DUST_LIMIT_SATS {
+        return Err(anyhow!("Insufficient funds: {} sats left after a {} sat fee", amount_sats, fee_sats));
+    }
+
+    let summary = routes::MaxSendResponse {
+        device_id: wallet.device_id,
+        destination: request.destination.clone(),
+        source: source.describe(),
+        fee_rate,
+        amount_sats,
+        fee_sats,
+        vsize: size.vsize,
+        inputs: spent.len(),
+        input_sats,
+        frozen_utxos,
+        unconfirmed_utxos,
+        uneconomical_utxos,
+    };
+    Ok((spent, summary))
+}
+
+/// Largest amount that can be sent to the destination at the fee rate
+pub(crate) async fn max_send_impl(state: &ServerState, request: routes::MaxSendRequest) -> Result<routes::MaxSendResponse> {
+    let chain = ChainBackend::from_cache(&state.cache).await?;
+    let (_, summary) = plan_sweep(state, &chain, &request).await?;
+    Ok(summary)
+}
+
+/// Build the sweep, sign it on the device (after remote approval when enabled)
+/// and, unless `broadcast` is false, broadcast it
+pub(crate) async fn sweep_impl(
+    state: &ServerState,
+    request: routes::SweepRequest,
+    api_key: Option<&str>,
+) -> Result<routes::SweepResponse> {
+    let chain = ChainBackend::from_cache(&state.cache).await?;
+    let (spent, plan) = plan_sweep(state, &chain, &request.plan).await?;
+
+    let mut inputs = Vec::with_capacity(spent.len());
+    for utxo in &spent {
+        inputs.push(routes::BitcoinInput {
+            address_n: utxo.path.clone(),
+            prev_hash: utxo.utxo.txid.clone(),
+            prev_index: utxo.utxo.vout,
+            amount: utxo.utxo.value.to_string(),
+            script_type: SCRIPT_TYPES[utxo.script_type].name.to_string(),
+            hex: Some(chain.tx_hex(&utxo.utxo.txid).await?),
+            sequence: None,
+        });
+    }
+    let outputs = vec![routes::BitcoinOutput {
+        address: Some(plan.destination.clone()),
+        address_n: None,
+        amount: plan.amount_sats.to_string(),
+        script_type: crate::server::wallet::script_type_of_address(&plan.destination)
+            .map_or("p2pkh", |t| SCRIPT_TYPES[t].name)
+            .to_string(),
+        op_return_data: None,
+    }];
+
+    let approval_outputs = vec![ApprovalOutput {
+        address: plan.destination.clone(),
+        amount: plan.amount_sats.to_string(),
+        label: None,
+    }];
+    match await_remote_approval(state, api_key, "sign-tx", "/api/v2/sweep", approval_outputs, None).await? {
+        ApprovalOutcome::Approved => {}
+        ApprovalOutcome::Rejected => return Err(anyhow!("Signing request rejected")),
+        ApprovalOutcome::TimedOut => return Err(anyhow!("Signing request was not approved in time")),
+    }
+
+    info!("🧹 Sweeping {} input(s), {} sats to {}", plan.inputs, plan.amount_sats, plan.destination);
+    let policy = ButtonPolicy::load(&state.cache).await?;
+    let signed = crate::server::bitcoin_sign_tx_fresh_impl(
+        routes::BitcoinSignRequest {
+            tx_hex: String::new(),
+            inputs,
+            outputs,
+            version: None,
+            lock_time: None,
+            device_id: None,
+        },
+        &policy,
+    )
+    .await?;
+
+    if !request.broadcast.unwrap_or(true) {
+        return Ok(routes::SweepResponse {
+            plan,
+            serialized_tx: signed.serialized_tx,
+            txid: None,
+            broadcast: None,
+        });
+    }
+    let broadcast = crate::server::broadcast_tx_impl(
+        state,
+        routes::BroadcastRequest {
+            raw_tx: signed.serialized_tx.clone(),
+            device_id: Some(plan.device_id.clone()),
+        },
+        api_key,
+    )
+    .await?;
+    Ok(routes::SweepResponse {
+        plan,
+        serialized_tx: signed.serialized_tx,
+        txid: broadcast.broadcast.then(|| broadcast.txid.clone()),
+        broadcast: Some(broadcast),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::chain::{EsploraUtxo, TxStatus};
+
+    fn request(source_address: Option<&str>, source_path: Option<&str>) -> routes::MaxSendRequest {
+        routes::MaxSendRequest {
+            destination: "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".to_string(),
+            fee_rate: Some(1.0),
+            conf_target: None,
+            include_unconfirmed: None,
+            source_address: source_address.map(str::to_string),
+            source_path: source_path.map(str::to_string),
+        }
+    }
+
+    fn utxo_at(path: Vec<u32>) -> WalletUtxo {
+        WalletUtxo {
+            address: "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2".to_string(),
+            path,
+            script_type: 0,
+            utxo: EsploraUtxo {
+                txid: "00".repeat(32),
+                vout: 0,
+                value: 10_000,
+                status: TxStatus { confirmed: true, block_height: None, block_hash: None, block_time: None },
+            },
+        }
+    }
+
+    #[test]
+    fn source_path_selects_an_account() {
+        let source = SweepSource::from_request(&request(None, Some("m/44'/0'/0'"))).unwrap();
+        assert_eq!(source.describe().as_deref(), Some("m/44'/0'/0'"));
+        assert!(source.includes(&utxo_at(vec![44 | HARDENED, HARDENED, HARDENED, 1, 7])));
+        assert!(!source.includes(&utxo_at(vec![44 | HARDENED, HARDENED, 1 | HARDENED, 0, 0])));
+
+        assert!(SweepSource::from_request(&request(None, Some("m/44'"))).is_err());
+        assert!(SweepSource::from_request(&request(Some("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"), Some("m/44'/0'/0'"))).is_err());
+        assert!(matches!(SweepSource::from_request(&request(None, None)), Ok(SweepSource::Wallet)));
+    }
+}
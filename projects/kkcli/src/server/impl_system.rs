@@ -4,6 +4,7 @@ use std::sync::Arc;
 use tokio::time::timeout;
 
 use crate::server::{DEVICE_OPERATION_TIMEOUT, routes, ServerState};
+use crate::server::progress::{Progress, ProgressOperation, UpdateStage};
 use crate::messages::{self, Message as KkMessage, ApplySettings, ChangePin, WipeDevice, RecoveryDevice, ResetDevice, LoadDevice, FirmwareErase, FirmwareUpload, Failure as ProtosFailure, MessageType as ProtosMessageType, PolicyType as ProtosPolicyType, ApplyPolicies as ProtosApplyPolicies};
 use crate::transport::{ProtocolAdapter, UsbTransport}; // UsbTransport for type, ProtocolAdapter for .call()
 
@@ -170,11 +171,27 @@ pub(crate) async fn system_change_pin_impl(
     }
 }
 
-pub(crate) async fn system_wipe_device_impl(server_state: Arc<ServerState>) -> Result<()> {
+/// Phrase a client must send back verbatim before the device is wiped
+pub(crate) const WIPE_CONFIRMATION_PHRASE: &str = "wipe my keepkey";
+
+pub(crate) async fn system_wipe_device_impl(server_state: Arc<ServerState>, confirmation: &str) -> Result<()> {
+    if confirmation.trim() != WIPE_CONFIRMATION_PHRASE {
+        return Err(anyhow::anyhow!("Confirmation phrase mismatch: type \"{}\" to wipe the device", WIPE_CONFIRMATION_PHRASE));
+    }
+    let pending = server_state.approvals.list().await;
+    if !pending.is_empty() {
+        return Err(anyhow::anyhow!("Signing in progress: {} request(s) awaiting approval", pending.len()));
+    }
+
     info!("Wiping device");
+    let device_id = server_state.cache.get_device_id();
 
     let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
-        let mut transport_guard = server_state.active_transport.lock().await;
+        // A held transport means a signing (or other device) operation is running
+        let mut transport_guard = server_state
+            .active_transport
+            .try_lock()
+            .map_err(|_| anyhow::anyhow!("Signing in progress: the device is busy"))?;
         if let Some(transport) = transport_guard.as_mut() {
             let wipe_device_msg = WipeDevice {};
 
@@ -205,13 +222,23 @@ pub(crate) async fn system_wipe_device_impl(server_state: Arc<ServerState>) -> R
     }).await;
 
     match result {
-        Ok(Ok(_)) => Ok(()),
-        Ok(Err(e)) => Err(e),
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => return Err(e),
         Err(_) => {
             error!("Wipe device timed out.");
-            Err(anyhow::anyhow!("Device operation timed out"))
+            return Err(anyhow::anyhow!("Device operation timed out"));
+        }
+    }
+
+    // The seed is gone, so every cached address, xpub and balance is stale
+    if let Some(device_id) = &device_id {
+        if let Err(e) = server_state.cache.clear_device(device_id).await {
+            warn!("Device wiped but clearing its cached data failed: {}", e);
         }
     }
+    server_state.response_signer.clear().await;
+    server_state.events.emit("device:wiped", serde_json::json!({ "deviceId": device_id }));
+    Ok(())
 }
 
 pub(crate) async fn system_recovery_device_impl(
@@ -237,8 +264,26 @@ pub(crate) async fn system_recovery_device_impl(
                 dry_run: request.dry_run,
             };
 
-            // RecoveryDevice is interactive
-            let response = transport.with_standard_handler().handle(recovery_device_msg.into()).map_err(|e| {
+            // RecoveryDevice is interactive; each word the device asks for is a progress step
+            let device_id = server_state.cache.get_device_id().unwrap_or_default();
+            let word_count = request.word_count as u64;
+            let mut words_asked = 0;
+            let mut report_words = |msg: &KkMessage| {
+                let word = match msg {
+                    KkMessage::WordRequest(_) => {
+                        words_asked += 1;
+                        Some(words_asked)
+                    }
+                    KkMessage::CharacterRequest(req) if req.character_pos == 0 => Some(req.word_pos as u64 + 1),
+                    _ => None,
+                };
+                if let Some(word) = word {
+                    let step = Progress::new(ProgressOperation::Recovery, "word", word, word_count);
+                    crate::server::progress::emit(&server_state.events, &device_id, &step);
+                }
+                crate::transport::standard_message_handler(msg)
+            };
+            let response = transport.with_mut_handler(&mut report_words).handle(recovery_device_msg.into()).map_err(|e| {
                 error!("Error sending RecoveryDevice: {:?}", e);
                 anyhow::anyhow!("Failed to send RecoveryDevice: {}", e)
             })?;
@@ -475,6 +520,7 @@ pub(crate) async fn system_firmware_erase_impl(server_state: Arc<ServerState>) -
             match response {
                 KkMessage::Success(success_msg) => {
                     info!("Successfully initiated firmware erase: {:?}", success_msg.message);
+                    report_firmware_progress(&server_state, Progress::update(ProgressOperation::FirmwareUpdate, UpdateStage::Erasing, 1, 1));
                     Ok(())
                 }
                 KkMessage::Failure(failure_msg) => {
@@ -504,6 +550,11 @@ pub(crate) async fn system_firmware_erase_impl(server_state: Arc<ServerState>) -
 
 const FIRMWARE_CHUNK_SIZE: usize = 1024; // Define a reasonable chunk size
 
+fn report_firmware_progress(server_state: &ServerState, progress: Progress) {
+    let device_id = server_state.cache.get_device_id().unwrap_or_default();
+    crate::server::progress::emit(&server_state.events, &device_id, &progress);
+}
+
 pub(crate) async fn system_firmware_upload_impl(
     server_state: Arc<ServerState>,
     request: routes::FirmwareUploadRequest,
@@ -517,8 +568,9 @@ pub(crate) async fn system_firmware_upload_impl(
             // For this example, assuming erase is a separate preceding step or handled by bootloader.
 
             // Send FirmwareUpload messages in chunks
+            let chunk_count = request.firmware.len().div_ceil(FIRMWARE_CHUNK_SIZE);
             for (i, chunk) in request.firmware.chunks(FIRMWARE_CHUNK_SIZE).enumerate() {
-                info!("Uploading firmware chunk {}/{}", i + 1, (request.firmware.len() + FIRMWARE_CHUNK_SIZE - 1) / FIRMWARE_CHUNK_SIZE);
+                info!("Uploading firmware chunk {}/{}", i + 1, chunk_count);
                 let firmware_upload_msg = FirmwareUpload {
                     payload: chunk.to_vec().into(),
                     payload_hash: Vec::new(), // Field expects Vec<u8>, not Option<Vec<u8>>
@@ -536,6 +588,10 @@ pub(crate) async fn system_firmware_upload_impl(
                 match response {
                     KkMessage::Success(success_msg) => {
                         info!("Successfully sent firmware chunk {}: {:?}", i + 1, success_msg.message);
+                        report_firmware_progress(
+                            &server_state,
+                            Progress::update(ProgressOperation::FirmwareUpdate, UpdateStage::Uploading, i as u64 + 1, chunk_count as u64),
+                        );
                         // Continue to next chunk
                     }
                     KkMessage::Failure(failure_msg) => {
@@ -554,6 +610,7 @@ pub(crate) async fn system_firmware_upload_impl(
             // After all chunks are sent, the device might perform verification and then reboot.
             // The final success might be implicit if no errors, or a final Success message.
             info!("All firmware chunks sent successfully.");
+            report_firmware_progress(&server_state, Progress::new(ProgressOperation::FirmwareUpdate, "finished", 1, 1));
             Ok(())
         } else {
             error!("Device transport not available for FirmwareUpload.");
@@ -572,29 +629,166 @@ pub(crate) async fn system_firmware_upload_impl(
 }
 
 // Debug implementations
-pub(crate) async fn debug_link_state_impl() -> Result<routes::DebugLinkState> {
-    error!("Debug link state not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
+pub(crate) async fn debug_link_state_impl(server_state: Arc<ServerState>) -> Result<routes::DebugLinkState> {
+    let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
+        let mut debug_guard = server_state.debug_transport.lock().await;
+        if let Some(debug_transport) = debug_guard.as_mut() {
+            match debug_transport.handle(messages::DebugLinkGetState {}.into())? {
+                KkMessage::DebugLinkState(state) => Ok(routes::DebugLinkState {
+                    layout: state.layout.map(hex::encode),
+                    pin: state.pin,
+                    matrix: state.matrix,
+                    mnemonic: state.mnemonic,
+                    node: state.node.and_then(|n| n.public_key).map(hex::encode),
+                    passphrase_protection: state.passphrase_protection.unwrap_or_default(),
+                    reset_word: state.reset_word,
+                    reset_entropy: state.reset_entropy.map(hex::encode),
+                    recovery_fake_word: state.recovery_fake_word,
+                    recovery_word_pos: state.recovery_word_pos,
+                }),
+                unexpected_msg => Err(anyhow::anyhow!("Unexpected response type from device: {:?}", unexpected_msg.message_type())),
+            }
+        } else {
+            error!("DEBUG_LINK interface not available.");
+            Err(anyhow::anyhow!("DEBUG_LINK not available - device is not running debug firmware"))
+        }
+    }).await;
+
+    match result {
+        Ok(r) => r,
+        Err(_) => {
+            error!("Debug link state timed out.");
+            Err(anyhow::anyhow!("Device operation timed out"))
+        }
+    }
 }
 
-pub(crate) async fn debug_fill_config_impl(_request: routes::DebugFillConfig) -> Result<()> {
-    error!("Debug fill config not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
+pub(crate) async fn debug_fill_config_impl(server_state: Arc<ServerState>, _request: routes::DebugFillConfig) -> Result<()> {
+    let mut debug_guard = server_state.debug_transport.lock().await;
+    if let Some(debug_transport) = debug_guard.as_mut() {
+        // DebugLinkFillConfig has no payload and no response
+        debug_transport.send(messages::DebugLinkFillConfig {}.into())?;
+        info!("Sent DebugLinkFillConfig");
+        Ok(())
+    } else {
+        error!("DEBUG_LINK interface not available.");
+        Err(anyhow::anyhow!("DEBUG_LINK not available - device is not running debug firmware"))
+    }
 }
 
 // Manufacturing implementations
-pub(crate) async fn manufacturing_get_hash_impl() -> Result<String> {
-    error!("Manufacturing get hash not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
+pub(crate) async fn manufacturing_get_hash_impl(
+    server_state: Arc<ServerState>,
+    address: u32,
+    length: u32,
+    challenge: Option<Vec<u8>>,
+) -> Result<String> {
+    info!("Requesting flash hash: address=0x{:08x}, length={}", address, length);
+
+    let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
+        let _lock = server_state.device_mutex.lock().await;
+        let mut transport_guard = server_state.active_transport.lock().await;
+        if let Some(transport) = transport_guard.as_mut() {
+            let flash_hash_msg = messages::FlashHash {
+                address: Some(address),
+                length: Some(length),
+                challenge,
+            };
+
+            match transport.handle(flash_hash_msg.into())? {
+                KkMessage::FlashHashResponse(resp) => resp
+                    .data
+                    .map(hex::encode)
+                    .ok_or_else(|| anyhow::anyhow!("FlashHashResponse did not include data")),
+                KkMessage::Failure(failure_msg) => {
+                    // Release firmware rejects the manufacturing message family
+                    error!("FlashHash failed: {:?}", failure_msg.message);
+                    Err(anyhow::anyhow!("Device returned failure (manufacturing firmware required?): {:?}", failure_msg.message))
+                }
+                unexpected_msg => Err(anyhow::anyhow!("Unexpected response type from device: {:?}", unexpected_msg.message_type())),
+            }
+        } else {
+            error!("Device transport not available for FlashHash.");
+            Err(anyhow::anyhow!("Device not connected or transport not initialized"))
+        }
+    }).await;
+
+    match result {
+        Ok(r) => r,
+        Err(_) => {
+            error!("FlashHash timed out.");
+            Err(anyhow::anyhow!("Device operation timed out"))
+        }
+    }
 }
 
-pub(crate) async fn manufacturing_model_prefix_impl() -> Result<String> {
-    error!("Manufacturing model prefix not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
+/// Model family of a hardware model string, e.g. `K1-14AM` -> `K1-14`.
+fn model_prefix(model: &str) -> &str {
+    match model.rfind(|c: char| c.is_ascii_digit()) {
+        Some(idx) => &model[..=idx],
+        None => model,
+    }
+}
+
+pub(crate) async fn manufacturing_model_prefix_impl(server_state: Arc<ServerState>) -> Result<String> {
+    let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
+        let _lock = server_state.device_mutex.lock().await;
+        let mut transport_guard = server_state.active_transport.lock().await;
+        if let Some(transport) = transport_guard.as_mut() {
+            match transport.handle(messages::GetFeatures {}.into())? {
+                KkMessage::Features(features) => features
+                    .model
+                    .as_deref()
+                    .map(|m| model_prefix(m).to_string())
+                    .ok_or_else(|| anyhow::anyhow!("Device did not report a hardware model")),
+                unexpected_msg => Err(anyhow::anyhow!("Unexpected response type from device: {:?}", unexpected_msg.message_type())),
+            }
+        } else {
+            error!("Device transport not available for model prefix.");
+            Err(anyhow::anyhow!("Device not connected or transport not initialized"))
+        }
+    }).await;
+
+    match result {
+        Ok(r) => r,
+        Err(_) => {
+            error!("Model prefix request timed out.");
+            Err(anyhow::anyhow!("Device operation timed out"))
+        }
+    }
 }
 
 // Raw message implementation
 pub(crate) async fn raw_message_impl(_body: axum::body::Bytes) -> Result<axum::body::Bytes> {
     error!("Raw message not implemented");
     Err(anyhow::anyhow!("Not implemented"))
-} 
\ No newline at end of file
+} 
+/// Firmware policies as reported in the device's Features
+pub(crate) async fn firmware_policies_impl(server_state: &ServerState) -> Result<Vec<routes::Policy>> {
+    let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
+        let mut transport_guard = server_state.active_transport.lock().await;
+        let transport = transport_guard
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Device not connected or transport not initialized"))?;
+
+        match transport.with_standard_handler().handle(messages::GetFeatures {}.into())? {
+            KkMessage::Features(features) => Ok(features
+                .policies
+                .into_iter()
+                .filter_map(|p| {
+                    p.policy_name.map(|policy_name| routes::Policy {
+                        policy_name,
+                        enabled: p.enabled.unwrap_or(false),
+                    })
+                })
+                .collect()),
+            other => Err(anyhow::anyhow!("Unexpected response to GetFeatures: {:?}", other.message_type())),
+        }
+    })
+    .await;
+
+    match result {
+        Ok(policies) => policies,
+        Err(_) => Err(anyhow::anyhow!("Device operation timed out")),
+    }
+}
@@ -0,0 +1,148 @@
+//! Keeps to one server per user. Two servers would fight over the USB
+//! interface, and whichever lost the port would exit with a bind error that
+//! says nothing about the other one.
+//!
+//! The running server records itself in `kkcli.pid` in the cache directory.
+//! The file is created exclusively, so of two servers starting together only
+//! one gets it; a record whose process is gone (killed, crashed, Ctrl+C) is
+//! treated as stale and replaced.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use tracing::{info, warn};
+
+use super::cache::DeviceCache;
+
+const PID_FILE: &str = "kkcli.pid";
+
+/// What the running server wrote to the pidfile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    pub pid: u32,
+    pub port: u16,
+    /// Unix seconds
+    pub started_at: i64,
+}
+
+pub fn pid_file_path() -> Result<PathBuf> {
+    Ok(DeviceCache::get_cache_dir()?.join(PID_FILE))
+}
+
+/// The server recorded in the pidfile, if its process is still alive
+pub fn running_instance() -> Result<Option<InstanceInfo>> {
+    let path = pid_file_path()?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    // An unreadable record can only be left over from an interrupted write
+    Ok(serde_json::from_str::<InstanceInfo>(&contents)
+        .ok()
+        .filter(|info| process_alive(info.pid)))
+}
+
+/// Held for the life of the server; dropping it removes the pidfile
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl InstanceLock {
+    /// Claim the pidfile and check `port` is free, or explain who has them
+    pub fn acquire(port: u16) -> Result<Self> {
+        let path = pid_file_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        // Second pass only happens after clearing a stale record
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let lock = Self { path: path.clone(), pid: std::process::id() };
+                    // Checked while holding the pidfile, so a failure here is
+                    // something other than kkcli; dropping `lock` releases it
+                    check_port_free(port)?;
+                    let info = InstanceInfo {
+                        pid: lock.pid,
+                        port,
+                        started_at: chrono::Utc::now().timestamp(),
+                    };
+                    file.write_all(serde_json::to_string(&info)?.as_bytes())
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    info!("🔒 Server lock taken: {} (PID {})", path.display(), lock.pid);
+                    return Ok(lock);
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => match running_instance()? {
+                    Some(other) => {
+                        return Err(anyhow!(
+                            "kkcli server is already running (PID {}, port {}); stop it first or use `kkcli daemon status`",
+                            other.pid,
+                            other.port
+                        ))
+                    }
+                    None => {
+                        warn!("Removing stale server lock {}", path.display());
+                        std::fs::remove_file(&path)
+                            .with_context(|| format!("Failed to remove stale {}", path.display()))?;
+                    }
+                },
+                Err(e) => return Err(e).with_context(|| format!("Failed to create {}", path.display())),
+            }
+        }
+        Err(anyhow!("Could not take the server lock at {}", path.display()))
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // A stale-lock cleanup by another server may have replaced the record
+        let recorded = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<InstanceInfo>(&contents).ok());
+        if !matches!(recorded, Some(info) if info.pid != self.pid) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn check_port_free(port: u16) -> Result<()> {
+    match TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port))) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::AddrInUse => Err(anyhow!(
+            "Port {} is already in use by another program; stop it or pick another with --port",
+            port
+        )),
+        Err(e) => Err(e).with_context(|| format!("Failed to check port {}", port)),
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // Signal 0 checks the process exists without touching it
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+        .unwrap_or(false)
+}
@@ -0,0 +1,205 @@
+//! Encrypted backups of host-side metadata.
+//!
+//! Settings, device-specific and discovered accounts, paired clients and
+//! dashboard tokens live only in this machine's cache. A backup carries them
+//! to a new machine: the rows are serialized to JSON and sealed with
+//! AES-256-GCM under a key the device derives with CipherKeyValue, so only the
+//! same seed can open it. The backup holds no keys; key material never leaves
+//! the device.
+//!
+//! Backups go to a local file, or to a URL with a plain HTTP `PUT` (a WebDAV
+//! collection, or a pre-signed S3 URL), and restores read them back the same
+//! way. Restoring merges: rows already in the cache are kept, except settings,
+//! which the backup overrides.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::info;
+use utoipa::ToSchema;
+
+use super::cache::device_cache::HostMetadata;
+use super::response_signing::derive_device_key;
+use super::ServerState;
+
+pub const BACKUP_FORMAT: &str = "keepkey-metadata-backup";
+const BACKUP_VERSION: u32 = 1;
+
+// m/10016'/1', next to the response signing key's m/10016'/0'
+const KEY_PATH: [u32; 2] = [0x8000_0000 | 10016, 0x8000_0000 | 1];
+const KEY_NAME: &str = "KeepKey metadata backup key";
+
+/// What is written to the file or URL
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupEnvelope {
+    pub format: String,
+    pub version: u32,
+    pub created_at: i64,
+    /// 12-byte AES-GCM nonce, hex
+    pub nonce: String,
+    /// AES-256-GCM of the metadata JSON, base64
+    pub ciphertext: String,
+}
+
+/// Where a backup is written to or read from; give `file` or `url`
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupLocation {
+    /// Path of a local file
+    pub file: Option<String>,
+    /// WebDAV or pre-signed S3 URL; written with PUT, read with GET
+    pub url: Option<String>,
+    /// Sent as the `Authorization` header with `url`
+    pub authorization: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupReport {
+    /// The file path or URL written
+    pub location: String,
+    pub created_at: i64,
+    pub bytes: usize,
+    /// Rows backed up per table
+    pub rows: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub location: String,
+    /// When the backup was made
+    pub created_at: i64,
+    /// Rows written per table; rows already present are not counted
+    pub restored: BTreeMap<String, usize>,
+}
+
+impl BackupLocation {
+    fn describe(&self) -> Result<String> {
+        match (&self.file, &self.url) {
+            (Some(file), None) => Ok(file.clone()),
+            (None, Some(url)) if url.starts_with("https://") || url.starts_with("http://") => Ok(url.clone()),
+            (None, Some(url)) => bail!("Backup URL must be http(s): {}", url),
+            _ => bail!("Give either a file or a url for the backup"),
+        }
+    }
+
+    async fn write(&self, bytes: Vec<u8>) -> Result<()> {
+        if let Some(file) = &self.file {
+            return Ok(tokio::fs::write(file, bytes).await?);
+        }
+        let mut request = reqwest::Client::new().put(self.url.as_deref().unwrap_or_default()).body(bytes);
+        if let Some(authorization) = &self.authorization {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    async fn read(&self) -> Result<Vec<u8>> {
+        if let Some(file) = &self.file {
+            return Ok(tokio::fs::read(file).await?);
+        }
+        let mut request = reqwest::Client::new().get(self.url.as_deref().unwrap_or_default());
+        if let Some(authorization) = &self.authorization {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+        Ok(request.send().await?.error_for_status()?.bytes().await?.to_vec())
+    }
+}
+
+pub(crate) fn seal(key: &[u8; 32], metadata: &HostMetadata, created_at: i64) -> Result<BackupEnvelope> {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let plaintext = serde_json::to_vec(metadata)?;
+    let ciphertext = Aes256Gcm::new(key.into())
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| anyhow!("Failed to encrypt the backup"))?;
+    Ok(BackupEnvelope {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at,
+        nonce: hex::encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+pub(crate) fn open(key: &[u8; 32], envelope: &BackupEnvelope) -> Result<HostMetadata> {
+    if envelope.format != BACKUP_FORMAT {
+        bail!("Not a KeepKey metadata backup");
+    }
+    if envelope.version > BACKUP_VERSION {
+        bail!("Backup version {} is newer than this server understands", envelope.version);
+    }
+    let nonce = hex::decode(&envelope.nonce)?;
+    if nonce.len() != 12 {
+        bail!("Malformed backup nonce");
+    }
+    let ciphertext = BASE64.decode(&envelope.ciphertext)?;
+    let plaintext = Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow!("Backup can't be opened with this device: it was made with a different seed, or is damaged"))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// Encrypt the cache's host metadata with the connected device's key and
+/// write it to `location`
+pub(crate) async fn create_backup(state: &ServerState, location: &BackupLocation) -> Result<BackupReport> {
+    let described = location.describe()?;
+    let metadata = state.cache.export_host_metadata().await?;
+    let key = {
+        let _lock = state.device_mutex.lock().await;
+        derive_device_key(state, &KEY_PATH, KEY_NAME).await?
+    };
+    let created_at = chrono::Utc::now().timestamp();
+    let bytes = serde_json::to_vec_pretty(&seal(&key, &metadata, created_at)?)?;
+    let size = bytes.len();
+    location.write(bytes).await?;
+    info!("📦 Metadata backup written to {} ({} bytes)", described, size);
+    Ok(BackupReport {
+        location: described,
+        created_at,
+        bytes: size,
+        rows: metadata.iter().map(|(table, rows)| (table.clone(), rows.len())).collect(),
+    })
+}
+
+/// Read a backup from `location`, open it with the connected device's key and
+/// merge it into the cache
+pub(crate) async fn restore_backup(state: &ServerState, location: &BackupLocation) -> Result<RestoreReport> {
+    let described = location.describe()?;
+    let bytes = location.read().await?;
+    let envelope: BackupEnvelope =
+        serde_json::from_slice(&bytes).map_err(|e| anyhow!("Not a KeepKey metadata backup: {}", e))?;
+    let key = {
+        let _lock = state.device_mutex.lock().await;
+        derive_device_key(state, &KEY_PATH, KEY_NAME).await?
+    };
+    let metadata = open(&key, &envelope)?;
+    let restored = state.cache.import_host_metadata(&metadata).await?;
+    info!("📦 Metadata backup from {} restored", described);
+    Ok(RestoreReport { location: described, created_at: envelope.created_at, restored })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backups_only_open_with_the_same_key() {
+        let mut metadata = HostMetadata::new();
+        let mut row = serde_json::Map::new();
+        row.insert("key".into(), "fiat_currency".into());
+        row.insert("value".into(), "EUR".into());
+        metadata.insert("config".into(), vec![row]);
+
+        let envelope = seal(&[7u8; 32], &metadata, 1_700_000_000).unwrap();
+        assert_eq!(open(&[7u8; 32], &envelope).unwrap(), metadata);
+        assert!(open(&[8u8; 32], &envelope).unwrap_err().to_string().contains("different seed"));
+    }
+}
@@ -1,11 +1,44 @@
 pub mod routes;
 pub mod cache;
+pub mod events;
+pub mod approvals;
+pub mod pin_entry;
+pub mod fee_market;
+pub mod progress;
+pub mod psbt;
+pub mod response_signing;
+pub mod instance_lock;
+pub mod autostart;
+pub mod watch_only;
+pub mod xpub_sync;
+pub mod wallet_locks;
+pub mod device_queues;
+pub mod metadata_backup;
 
 // Implementation modules
 mod impl_device;
 mod impl_addresses;
 mod impl_bitcoin;
 mod impl_system;
+mod impl_chain;
+mod impl_portfolio;
+mod impl_privacy;
+mod impl_migration;
+mod impl_rpc;
+mod impl_sweep;
+mod impl_send_many;
+pub(crate) mod chain;
+pub(crate) mod address_validation;
+pub(crate) mod tx_size;
+mod wallet;
+mod fiat;
+mod amounts;
+mod dashboard_token;
+mod paired_clients;
+mod button_policy;
+mod tx_tracker;
+mod portfolio_history;
+mod reorg;
 mod server_init;
 mod v2_endpoints;
 
@@ -21,7 +54,8 @@ use prost::Message as ProstMessage;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::any::type_name;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 use tokio::sync::Mutex;
 use tokio::time::{timeout, Duration};
 use tower_http::cors::CorsLayer;
@@ -33,7 +67,7 @@ use rusb::{Device, GlobalContext};
 use base64;
 use hex;
 
-use crate::transport::{UsbTransport, ProtocolAdapter};
+use crate::transport::{DeviceTransport, MockDevice, ReplayDevice, UsbTransport, ProtocolAdapter};
 use crate::messages::{self, Message};
 use self::cache::{DeviceCache, DeviceFrontloader};
 
@@ -42,22 +76,95 @@ pub(crate) use impl_device::*;
 pub(crate) use impl_addresses::*;
 pub(crate) use impl_bitcoin::*;
 pub(crate) use impl_system::*;
+pub(crate) use impl_chain::*;
+pub(crate) use impl_portfolio::*;
+pub(crate) use impl_privacy::*;
+pub(crate) use impl_migration::*;
+pub(crate) use impl_rpc::*;
+pub(crate) use impl_sweep::*;
+pub(crate) use impl_send_many::*;
 
 // Export server initialization function
-pub use server_init::start_server;
+pub use server_init::{build_router, simulated_server_state, start_server};
 
 // Server state for sharing across handlers
 #[derive(Clone)]
 pub struct ServerState {
     pub cache: DeviceCache,
-    pub device_mutex: Arc<Mutex<()>>, // Prevents concurrent device access
-    pub active_transport: Arc<Mutex<Option<UsbTransport<GlobalContext>>>>, // Holds the active, shared USB transport
+    pub device_mutex: Arc<Mutex<()>>, // Prevents concurrent access to the device the server started with
+    pub device_queues: device_queues::DeviceQueues, // Per-device locks, so several KeepKeys work at once
+    pub active_transport: Arc<Mutex<Option<DeviceTransport>>>, // Holds the active, shared device transport
+    pub debug_transport: Arc<Mutex<Option<UsbTransport<GlobalContext>>>>, // DEBUG_LINK interface, only on debug firmware
+    pub events: events::EventBus, // Pushed to WebSocket clients
+    pub approvals: approvals::ApprovalRegistry, // Signing requests waiting for remote approval
+    pub pin_entry: pin_entry::PinEntryBroker, // PIN matrix requests answered over REST
+    pub fee_market: fee_market::FeeMarketCache, // Mempool fee snapshots shared by all clients
+    pub response_signer: response_signing::ResponseSigner, // Device-derived key for signed responses
+    pub xpub_sync: xpub_sync::XpubSyncPlans, // Xpub diffs waiting for the caller to apply them
+    pub wallet_locks: wallet_locks::WalletLocks, // Long-running operations that must not interleave
 }
 
 // Constants
 pub(crate) const DEVICE_IDS: &[(u16, u16)] = &[(0x2b24, 0x0001), (0x2b24, 0x0002)];
 pub(crate) const DEVICE_OPERATION_TIMEOUT: Duration = Duration::from_secs(30);
 
+// Set by `kkcli server --mock-device`: device calls go to the simulator instead of USB
+static SIMULATED_DEVICE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_simulated_device(enabled: bool) {
+    SIMULATED_DEVICE.store(enabled, Ordering::SeqCst);
+}
+
+pub(crate) fn simulated_device() -> bool {
+    SIMULATED_DEVICE.load(Ordering::SeqCst)
+}
+
+// Set by `kkcli server --replay`: the simulator plays back a recorded session
+static REPLAY_DEVICE: RwLock<Option<Arc<std::sync::Mutex<ReplayDevice>>>> = RwLock::new(None);
+
+/// Play back `device` from every simulated connection; implies simulation mode
+pub(crate) fn set_replay_device(device: ReplayDevice) {
+    *REPLAY_DEVICE.write().unwrap() = Some(Arc::new(std::sync::Mutex::new(device)));
+    set_simulated_device(true);
+}
+
+/// The simulator's side of a connection, continuing the loaded replay if there is one
+pub(crate) fn simulated_transport() -> DeviceTransport {
+    match REPLAY_DEVICE.read().unwrap().as_ref() {
+        Some(replay) => DeviceTransport::Replay(Arc::clone(replay)),
+        None => DeviceTransport::Mock(MockDevice::new()),
+    }
+}
+
+/// A fresh connection for a one-off device call: the USB KeepKey, or the simulator
+pub(crate) fn open_device_transport() -> Result<DeviceTransport> {
+    if simulated_device() {
+        return Ok(simulated_transport());
+    }
+    let device = try_get_device()?;
+    let (transport, _config_descriptor, _handle) = UsbTransport::new(&device, 0)?;
+    Ok(DeviceTransport::Usb(transport))
+}
+
+/// `open_device_transport` for the KeepKey whose USB serial number is
+/// `device_id`; any KeepKey when `None`. The simulator stands in for every id.
+pub(crate) fn open_device_transport_for(device_id: Option<&str>) -> Result<DeviceTransport> {
+    let device_id = match device_id {
+        Some(device_id) if !simulated_device() => device_id,
+        _ => return open_device_transport(),
+    };
+    for device in list_devices().iter() {
+        let serial = device.device_descriptor().ok().and_then(|desc| {
+            device.open().ok().and_then(|handle| handle.read_serial_number_string_ascii(&desc).ok())
+        });
+        if serial.as_deref() == Some(device_id) {
+            let (transport, _config_descriptor, _handle) = UsbTransport::new(device, 0)?;
+            return Ok(DeviceTransport::Usb(transport));
+        }
+    }
+    Err(anyhow::anyhow!("No KeepKey device found with id {}", device_id))
+}
+
 // API Documentation
 #[derive(OpenApi)]
 #[openapi(
@@ -71,6 +178,46 @@ pub(crate) const DEVICE_OPERATION_TIMEOUT: Duration = Duration::from_secs(30);
         routes::system_get_features,
         routes::system_ping,
         routes::generate_utxo_address,
+        routes::bitcoin_sign_psbt,
+        routes::device_selftest,
+        routes::forget_device,
+        routes::frontload_device,
+        routes::discovered_accounts,
+        routes::running_operations,
+        routes::plan_xpub_sync,
+        routes::apply_xpub_sync,
+        routes::discard_xpub_sync,
+        routes::broadcast_transaction,
+        routes::get_chain_tip,
+        routes::get_mempool_fees,
+        routes::estimate_transaction_size,
+        routes::max_send,
+        routes::sweep_wallet,
+        routes::send_many_preview,
+        routes::send_many,
+        routes::get_frozen_outpoints,
+        routes::put_frozen_outpoints,
+        routes::get_portfolio_history,
+        routes::get_privacy_report,
+        routes::get_script_type_balances,
+        routes::plan_script_type_migration,
+        routes::json_rpc,
+        routes::create_dashboard_token,
+        routes::list_dashboard_tokens,
+        routes::revoke_dashboard_token,
+        routes::list_paired_clients,
+        routes::revoke_paired_client,
+        routes::create_metadata_backup,
+        routes::restore_metadata_backup,
+        routes::list_pending_approvals,
+        routes::decide_approval,
+        routes::get_pending_pin,
+        routes::submit_pin,
+        routes::get_policies,
+        routes::get_response_signing_key,
+        routes::put_policies,
+        routes::start_session_recording,
+        routes::stop_session_recording,
     ),
     components(schemas(
         routes::Features,
@@ -79,12 +226,95 @@ pub(crate) const DEVICE_OPERATION_TIMEOUT: Duration = Duration::from_secs(30);
         routes::PingResponse,
         routes::UtxoAddressRequest,
         routes::UtxoAddressResponse,
+        routes::BitcoinSignPsbtRequest,
+        routes::BitcoinSignPsbtResponse,
         routes::AddressResponse,
+        routes::SelftestRequest,
+        routes::ForgetDeviceResponse,
+        cache::FrontloadScope,
+        cache::FrontloadReport,
+        cache::DiscoveredAccount,
+        cache::device_cache::PairedClient,
+        cache::device_cache::ClientScopeUsage,
+        metadata_backup::BackupLocation,
+        metadata_backup::BackupReport,
+        metadata_backup::RestoreReport,
+        wallet_locks::HeldOperation,
+        wallet_locks::WalletOperation,
+        xpub_sync::XpubSyncPlan,
+        xpub_sync::XpubChange,
+        xpub_sync::XpubChangeKind,
+        xpub_sync::XpubSyncResult,
+        crate::cli::system::SelftestReport,
+        crate::cli::system::SelftestStep,
+        crate::cli::system::SelftestStatus,
+        routes::BroadcastRequest,
+        routes::BroadcastResponse,
+        routes::SpendConflict,
+        routes::ConflictSource,
+        routes::DoubleSpendPolicy,
+        routes::ChainTipResponse,
+        routes::BackendTipStatus,
+        routes::BackendAgreement,
+        routes::MempoolFeesResponse,
+        routes::FeeBand,
+        routes::FeeEstimate,
+        routes::TxEstimateRequest,
+        routes::TxEstimateResponse,
+        routes::TxShapeEntry,
+        routes::MaxSendRequest,
+        routes::MaxSendResponse,
+        routes::SweepRequest,
+        routes::SweepResponse,
+        routes::SendManyOutput,
+        routes::SendManyRequest,
+        routes::SendManyPreviewOutput,
+        routes::SendManyChange,
+        routes::SendManyPreview,
+        routes::SendManyResponse,
+        routes::FrozenOutpoints,
+        routes::PortfolioHistoryResponse,
+        routes::DailySnapshot,
+        routes::SnapshotAsset,
+        routes::RealizedTransaction,
+        routes::PrivacyReport,
+        routes::PrivacyIssue,
+        routes::PrivacyIssueKind,
+        routes::ScriptTypeBalancesResponse,
+        routes::ScriptTypeBalance,
+        routes::MigrationRequest,
+        routes::MigrationPlan,
+        routes::MigrationTransaction,
+        amounts::AmountFormat,
+        amounts::AmountUnit,
+        routes::CreateDashboardTokenRequest,
+        routes::CreateDashboardTokenResponse,
+        routes::DashboardTokenInfo,
+        routes::ApprovalDecision,
+        routes::PinSubmission,
+        routes::SessionRecordingStart,
+        routes::SessionRecordingSaved,
+        crate::transport::DeviceSession,
+        crate::transport::session::RecordedFrame,
+        crate::transport::session::FrameDirection,
+        routes::PolicyResponse,
+        routes::ResponseSigningKey,
+        crate::server::button_policy::ButtonPolicy,
+        crate::server::button_policy::OpReturnPolicy,
+        crate::server::approvals::ApprovalRequest,
+        crate::server::approvals::ApprovalOutput,
+        crate::server::pin_entry::PinRequest,
     )),
     tags(
         (name = "device", description = "Device management endpoints"),
         (name = "addresses", description = "Address generation endpoints"),
         (name = "system", description = "System endpoints"),
+        (name = "chain", description = "Broadcast and chain state endpoints"),
+        (name = "portfolio", description = "Portfolio history and tax export endpoints"),
+        (name = "auth", description = "Pairing and access token endpoints"),
+        (name = "approvals", description = "Remote approval of REST signing requests"),
+        (name = "pin", description = "Remote PIN matrix entry for headless servers"),
+        (name = "debug", description = "Device session recording and DEBUG_LINK endpoints"),
     )
 )]
 struct ApiDoc;
@@ -271,37 +501,32 @@ pub(crate) fn list_devices() -> Box<[Device<GlobalContext>]> {
 
 // Implementation functions that can be reused by both REST and MCP endpoints
 pub(crate) async fn get_device_status_impl() -> Result<routes::DeviceStatus> {
-    match try_get_device() {
-        Ok(_device) => {
-            Ok(routes::DeviceStatus {
-                connected: true,
-                device: Some(routes::DeviceInfo {
-                    device_id: "kkcli-device".to_string(),
-                    name: "KeepKey via CLI".to_string(),
-                    vendor_id: 0x2B24,
-                    product_id: 0x0001,
-                    manufacturer: Some("ShapeShift".to_string()),
-                    product: Some("KeepKey".to_string()),
-                    serial_number: None,
-                    is_keepkey: true,
-                }),
-            })
-        }
-        Err(_) => {
-            Ok(routes::DeviceStatus {
-                connected: false,
-                device: None,
-            })
-        }
+    if simulated_device() || try_get_device().is_ok() {
+        Ok(routes::DeviceStatus {
+            connected: true,
+            device: Some(routes::DeviceInfo {
+                device_id: "kkcli-device".to_string(),
+                name: "KeepKey via CLI".to_string(),
+                vendor_id: 0x2B24,
+                product_id: 0x0001,
+                manufacturer: Some("ShapeShift".to_string()),
+                product: Some("KeepKey".to_string()),
+                serial_number: None,
+                is_keepkey: true,
+            }),
+        })
+    } else {
+        Ok(routes::DeviceStatus {
+            connected: false,
+            device: None,
+        })
     }
 }
 
 pub(crate) async fn get_device_features_impl() -> Result<routes::KeepKeyFeatures> {
-    let device = try_get_device()?;
-    
     // Wrap device communication in timeout
     let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
-        let (_transport, _config_descriptor, _handle) = UsbTransport::new(&device, 0)?;
+        let _transport = open_device_transport()?;
         
         // Get features from the device (simplified)
         // In a real implementation, you would call the actual GetFeatures command
@@ -402,111 +627,6 @@ pub(crate) async fn list_usb_devices_impl() -> Result<Vec<routes::UsbDeviceInfo>
     Ok(device_list)
 }
 
-// Enhanced UTXO address generation - using cache!
-pub(crate) async fn generate_utxo_address_impl(
-    request: routes::UtxoAddressRequest,
-    cache: &DeviceCache,
-    device_mutex: Arc<Mutex<()>>,
-) -> Result<routes::UtxoAddressResponse> {
-    info!("🚀 Checking cache for UTXO address: coin={}, script_type={:?}, path={:?}", 
-        request.coin, request.script_type, request.address_n);
-    
-    // Map script type to our internal format
-    let script_type = request.script_type.as_deref().unwrap_or("p2pkh");
-    
-    // Check cache first
-    if let Some(cached_address) = cache.get_cached_address(&request.coin, script_type, &request.address_n) {
-        info!("✨ Found cached address: {}", cached_address.address);
-        return Ok(routes::UtxoAddressResponse {
-            address: cached_address.address,
-            address_n: request.address_n,
-        });
-    }
-    
-    // Not in cache - fetch from device with mutex protection
-    info!("💫 Address not in cache, fetching from device...");
-    
-    // Acquire device mutex to prevent concurrent access
-    let _lock = device_mutex.lock().await;
-    info!("🔒 Device mutex acquired for UTXO address generation");
-    
-    // Get the USB device
-    let device = try_get_device()?;
-    
-    // Wrap device communication in timeout
-    let result = timeout(DEVICE_OPERATION_TIMEOUT, async {
-        let (mut transport, _config_descriptor, _handle) = UsbTransport::new(&device, 0)?;
-        
-        // Create GetAddress message
-        let mut msg = messages::GetAddress::default();
-        msg.address_n = request.address_n.clone();
-        msg.coin_name = Some(request.coin.clone());
-        msg.show_display = request.show_display;
-        
-        // Set script type
-        match script_type {
-            "p2pkh" => msg.script_type = Some(messages::InputScriptType::Spendaddress as i32),
-            "p2wpkh" => msg.script_type = Some(messages::InputScriptType::Spendwitness as i32),
-            "p2sh-p2wpkh" => msg.script_type = Some(messages::InputScriptType::Spendp2shwitness as i32),
-            _ => msg.script_type = Some(messages::InputScriptType::Spendaddress as i32), // Default to p2pkh
-        }
-        
-        info!("Sending GetAddress message to device for {} with path: {:?}", request.coin, request.address_n);
-        
-        // Send the message and wait for response
-        let response = transport.with_standard_handler().handle(msg.into())?;
-        
-        // Extract the address from the response
-        match response {
-            Message::Address(addr_msg) => {
-                if !addr_msg.address.is_empty() {
-                    // Cache the address for future use
-                    if let Some(device_id) = cache.get_device_id() {
-                        if let Err(e) = cache.save_address(
-                            &device_id,
-                            &request.coin,
-                            script_type,
-                            &request.address_n,
-                            &addr_msg.address,
-                            None,
-                        ).await {
-                            warn!("Failed to cache address: {}", e);
-                        } else {
-                            info!("💾 Cached new address for future use");
-                        }
-                    }
-                    
-                    Ok(routes::UtxoAddressResponse {
-                        address: addr_msg.address,
-                        address_n: request.address_n,
-                    })
-                } else {
-                    Err(anyhow::anyhow!("Device returned empty address"))
-                }
-            }
-            _ => {
-                error!("Unexpected response type from device: {:?}", response);
-                Err(anyhow::anyhow!("Unexpected response from device"))
-            }
-        }
-    }).await;
-    
-    match result {
-        Ok(Ok(response)) => {
-            info!("✅ Got address from device: {}", response.address);
-            Ok(response)
-        }
-        Ok(Err(e)) => {
-            error!("Device communication failed: {}", e);
-            Err(e)
-        }
-        Err(_) => {
-            error!("Device communication timed out");
-            Err(anyhow::anyhow!("Device operation timed out"))
-        }
-    }
-}
-
 // Removed: Osmosis sign LP remove (Cosmos) implementation - not supported in Bitcoin-only build.
 
 // Removed: Osmosis sign delegate (Cosmos) implementation - not supported in Bitcoin-only build.
@@ -648,9 +768,13 @@ pub(crate) async fn bitcoin_sign_tx_impl(request: routes::BitcoinSignRequest) ->
                                 address: output.address.clone(),
                                 address_n: output.address_n.clone().unwrap_or_default(),
                                 amount: output.amount.parse()?,
-                                script_type: parse_bitcoin_output_script_type(&output.script_type)? as i32,
+                                script_type: (if output.is_op_return() {
+                                    messages::OutputScriptType::Paytoopreturn
+                                } else {
+                                    parse_bitcoin_output_script_type(&output.script_type)?
+                                }) as i32,
                                 multisig: None,
-                                op_return_data: None,
+                                op_return_data: impl_bitcoin::op_return_bytes(output)?,
                                 address_type: None,
                                 decred_script_version: None,
                             };
@@ -914,11 +1038,6 @@ pub(crate) async fn system_change_pin_impl(_request: routes::ChangePinRequest) -
     Err(anyhow::anyhow!("Not implemented"))
 }
 
-pub(crate) async fn system_wipe_device_impl() -> anyhow::Result<()> {
-    error!("Wipe device not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
-}
-
 pub(crate) async fn system_recovery_device_impl(_request: routes::RecoveryDeviceRequest) -> anyhow::Result<()> {
     error!("Recovery device not implemented");
     Err(anyhow::anyhow!("Not implemented"))
@@ -949,28 +1068,6 @@ pub(crate) async fn system_firmware_upload_impl(_request: routes::FirmwareUpload
     Err(anyhow::anyhow!("Not implemented"))
 }
 
-// Debug implementations
-pub(crate) async fn debug_link_state_impl() -> anyhow::Result<routes::DebugLinkState> {
-    error!("Debug link state not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
-}
-
-pub(crate) async fn debug_fill_config_impl(_request: routes::DebugFillConfig) -> anyhow::Result<()> {
-    error!("Debug fill config not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
-}
-
-// Manufacturing implementations
-pub(crate) async fn manufacturing_get_hash_impl() -> anyhow::Result<String> {
-    error!("Manufacturing get hash not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
-}
-
-pub(crate) async fn manufacturing_model_prefix_impl() -> anyhow::Result<String> {
-    error!("Manufacturing model prefix not implemented");
-    Err(anyhow::anyhow!("Not implemented"))
-}
-
 // Raw message implementation
 pub(crate) async fn raw_message_impl(_body: axum::body::Bytes) -> anyhow::Result<axum::body::Bytes> {
     error!("Raw message not implemented");
@@ -0,0 +1,234 @@
+//! API keys, their scopes, and usage tracking for paired clients.
+//!
+//! `POST /auth/pair` issues an API key and stores its hash with the scopes the
+//! client asked for: `read`, `address`, `sign` or `admin`. Granting `sign` or
+//! `admin` takes a button press on the device, so a process on this machine
+//! can't hand itself a signing key. Every request is classified into one of
+//! those scopes; a paired key may only make requests within its grants.
+//!
+//! With `api_auth` set to `sensitive` (the default), signing and device
+//! administration (wipe, recovery, firmware, raw messages, key management)
+//! need a paired key granted that scope. Reads and address derivation stay
+//! open to keyless clients. `off` lets any request through, as before pairing
+//! carried scopes.
+//!
+//! Requests are also counted per client and scope, and keys left unused for
+//! `client_expiry_days` stop working until the client pairs again.
+//! `GET /api/v2/clients` shows what each integration has been doing.
+
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use tokio::time::timeout;
+use tracing::{error, info, warn};
+
+use crate::messages::{self, Message};
+use crate::transport::ProtocolAdapter;
+use super::cache::device_cache::ClientKeyStatus;
+use super::cache::DeviceCache;
+use super::dashboard_token::DASHBOARD_TOKEN_PREFIX;
+use super::{ServerState, DEVICE_OPERATION_TIMEOUT};
+
+/// Every scope a key can be granted
+pub(crate) const SCOPES: &[&str] = &["read", "address", "sign", "admin"];
+
+/// Granted when a pairing request names no scopes
+pub(crate) const DEFAULT_SCOPES: &[&str] = &["read", "address", "sign"];
+
+pub(crate) fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Last four characters, enough to tell keys apart in a list
+pub(crate) fn api_key_hint(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    chars[chars.len().saturating_sub(4)..].iter().collect()
+}
+
+/// Scopes that need a paired key under `api_auth = sensitive`, and a button
+/// press on the device to grant
+pub(crate) fn is_sensitive(scope: &str) -> bool {
+    matches!(scope, "sign" | "admin")
+}
+
+/// The scopes to grant for a pairing request, in [`SCOPES`] order; `read` is always included
+pub(crate) fn granted_scopes(requested: Option<&[String]>) -> Result<Vec<&'static str>, String> {
+    let requested: Vec<&str> = match requested {
+        Some(requested) => requested.iter().map(|s| s.trim()).collect(),
+        None => DEFAULT_SCOPES.to_vec(),
+    };
+    if let Some(unknown) = requested.iter().find(|s| !SCOPES.contains(s)) {
+        return Err(format!("Unknown scope {:?}; expected one of {}", unknown, SCOPES.join(", ")));
+    }
+    Ok(SCOPES
+        .iter()
+        .copied()
+        .filter(|scope| *scope == "read" || requested.contains(scope))
+        .collect())
+}
+
+/// What kind of access a request needs: `read`, `address`, `sign` or `admin`
+pub(crate) fn request_scope(method: &Method, path: &str) -> &'static str {
+    let signs = (path.contains("/sign") && !path.contains("response-signing"))
+        || path == "/api/v2/sweep"
+        || path == "/api/v2/send-many"
+        || path == "/api/v2/tx/broadcast"
+        // wallet RPC methods include signing
+        || path == "/rpc";
+    let administers = (path.contains("/system/") && !path.ends_with("/get-features") && !path.ends_with("/ping"))
+        || path.contains("/debug/")
+        || path == "/raw"
+        || path == "/api/v1/raw-message"
+        || path.starts_with("/api/v2/clients")
+        || path.starts_with("/api/v2/dashboard-tokens")
+        || path.starts_with("/api/v2/metadata-backup")
+        || (*method == Method::DELETE && path.starts_with("/api/v2/"))
+        || (*method == Method::PUT && path.starts_with("/api/v2/"));
+    if signs {
+        "sign"
+    } else if administers {
+        "admin"
+    } else if path.contains("/address") || path.contains("/xpub") {
+        "address"
+    } else {
+        "read"
+    }
+}
+
+fn reject(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Ask the user to confirm on the device that `name` may use `scopes`
+pub(crate) async fn confirm_pairing_on_device(state: &ServerState, name: &str, scopes: &[&str]) -> Result<()> {
+    let name: String = name.chars().take(32).collect();
+    let prompt = format!("Pair {}? Allows: {}", name, scopes.join(", "));
+    let _lock = state.device_mutex.lock().await;
+    timeout(DEVICE_OPERATION_TIMEOUT, async {
+        let mut transport_guard = state.active_transport.lock().await;
+        let transport = transport_guard
+            .as_mut()
+            .ok_or_else(|| anyhow!("Device not connected or transport not initialized"))?;
+        let ping = messages::Ping {
+            message: Some(prompt),
+            button_protection: Some(true),
+            pin_protection: None,
+            passphrase_protection: None,
+            wipe_code_protection: None,
+        };
+        match transport.with_standard_handler().handle(ping.into())? {
+            Message::Success(_) => Ok(()),
+            Message::Failure(failure) => Err(PairingDeclined(failure.message.unwrap_or_default()).into()),
+            other => Err(anyhow!("Unexpected response to Ping: {:?}", other.message_type())),
+        }
+    })
+    .await
+    .map_err(|_| anyhow!("Timed out waiting for the pairing to be confirmed on the device"))?
+}
+
+/// The user rejected a pairing on the device
+#[derive(Debug, thiserror::Error)]
+#[error("Pairing declined on the device: {0}")]
+pub(crate) struct PairingDeclined(String);
+
+pub(crate) async fn authorize_api_keys(
+    State(cache): State<DeviceCache>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // CORS preflight carries no credentials
+    if *req.method() == Method::OPTIONS {
+        return next.run(req).await;
+    }
+
+    let key = req
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .filter(|k| !k.starts_with(DASHBOARD_TOKEN_PREFIX))
+        .map(str::to_string);
+    let scope = request_scope(req.method(), req.uri().path());
+
+    let status = match &key {
+        Some(key) => cache.use_paired_client(&hash_api_key(key), scope).await,
+        None => Ok(ClientKeyStatus::Unknown),
+    };
+    let granted = match status {
+        Ok(ClientKeyStatus::Active(id)) => cache.get_client_scopes(&id).await.map(Some),
+        Ok(ClientKeyStatus::Unknown) => Ok(None),
+        Ok(ClientKeyStatus::Expired) => {
+            let hint = key.as_deref().map(api_key_hint).unwrap_or_default();
+            warn!("🔒 Expired API key ...{} denied {} {}", hint, req.method(), req.uri().path());
+            return reject(StatusCode::UNAUTHORIZED, "API key expired after going unused; pair again");
+        }
+        Ok(ClientKeyStatus::Revoked) => return reject(StatusCode::UNAUTHORIZED, "API key revoked; pair again"),
+        Err(e) => Err(e),
+    };
+
+    match granted {
+        Ok(Some(granted)) if granted.iter().any(|g| g == scope) => next.run(req).await,
+        Ok(Some(_)) => {
+            let hint = key.as_deref().map(api_key_hint).unwrap_or_default();
+            warn!("🔒 API key ...{} lacks the {} scope for {} {}", hint, scope, req.method(), req.uri().path());
+            reject(StatusCode::FORBIDDEN, &format!("API key was not granted the {} scope; pair again asking for it", scope))
+        }
+        Ok(None) if !is_sensitive(scope) => next.run(req).await,
+        Ok(None) => match cache.api_auth_enforced().await {
+            Ok(false) => next.run(req).await,
+            Ok(true) => {
+                info!("🔒 {} {} needs an API key with the {} scope", req.method(), req.uri().path(), scope);
+                reject(
+                    StatusCode::UNAUTHORIZED,
+                    &format!("This endpoint needs an API key with the {} scope; pair with POST /auth/pair", scope),
+                )
+            }
+            Err(e) => {
+                error!("Failed to read api_auth: {}", e);
+                reject(StatusCode::INTERNAL_SERVER_ERROR, "Failed to check API key")
+            }
+        },
+        Err(e) => {
+            error!("Failed to check API key: {}", e);
+            reject(StatusCode::INTERNAL_SERVER_ERROR, "Failed to check API key")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_grouped_by_scope() {
+        assert_eq!(request_scope(&Method::POST, "/bitcoin/sign-tx"), "sign");
+        assert_eq!(request_scope(&Method::POST, "/utxo/sign-transaction"), "sign");
+        assert_eq!(request_scope(&Method::GET, "/api/v2/response-signing/key"), "read");
+        assert_eq!(request_scope(&Method::POST, "/system/info/wipe-device"), "admin");
+        assert_eq!(request_scope(&Method::POST, "/api/v1/system/wipe-device"), "admin");
+        assert_eq!(request_scope(&Method::POST, "/raw"), "admin");
+        assert_eq!(request_scope(&Method::GET, "/system/info/get-features"), "read");
+        assert_eq!(request_scope(&Method::POST, "/rpc"), "sign");
+        assert_eq!(request_scope(&Method::POST, "/api/v2/send-many"), "sign");
+        assert_eq!(request_scope(&Method::POST, "/api/v2/send-many/preview"), "read");
+        assert_eq!(request_scope(&Method::DELETE, "/api/v2/xpubs/sync/abc"), "admin");
+        assert_eq!(request_scope(&Method::POST, "/addresses/utxo"), "address");
+        assert_eq!(request_scope(&Method::GET, "/v2/portfolio/summary"), "read");
+        assert_eq!(api_key_hint("0123-abcd"), "abcd");
+    }
+
+    #[test]
+    fn pairing_grants_read_plus_what_was_asked() {
+        assert_eq!(granted_scopes(None).unwrap(), vec!["read", "address", "sign"]);
+        let asked = vec!["admin".to_string(), "address".to_string()];
+        assert_eq!(granted_scopes(Some(asked.as_slice())).unwrap(), vec!["read", "address", "admin"]);
+        assert!(granted_scopes(Some(&["spend".to_string()][..])).is_err());
+        assert!(is_sensitive("sign") && !is_sensitive("address"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Remote PIN entry for headless servers.
+//!
+//! With config `pin_entry` set to `remote`, a PinMatrixRequest from the device
+//! is parked here instead of prompting on stdin. A `pin:requested` event goes
+//! out over `/ws`; an admin UI fetches it from `GET /api/v2/pin`, shows the
+//! 3x3 grid and submits the positions the user clicked (the same cipher-mapped
+//! digits the desktop app sends) to `POST /api/v2/pin/{id}`. The PIN itself
+//! never leaves the device; positions only make sense against the scrambled
+//! matrix on its screen.
+//!
+//! Both routes require `Authorization: Bearer kkpin_...`. The token is printed
+//! once when remote entry is first enabled and only its hash is stored
+//! (`pin_entry_token_hash`); clear that key to issue a new one.
+
+use anyhow::{anyhow, Result};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::cache::DeviceCache;
+use super::events::EventBus;
+use crate::messages::PinMatrixRequestType;
+
+pub(crate) const PIN_TOKEN_PREFIX: &str = "kkpin_";
+const PIN_TOKEN_HASH_KEY: &str = "pin_entry_token_hash";
+const DEFAULT_PIN_TIMEOUT_SECS: u64 = 120;
+// KeepKey PINs are at most nine digits, each a position 1-9 on the matrix
+const MAX_PIN_LENGTH: usize = 9;
+
+/// The PIN matrix the device is currently showing
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PinRequest {
+    pub id: String,
+    /// `current`, `new_first` or `new_second`
+    pub kind: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+struct PendingPin {
+    request: PinRequest,
+    respond: mpsc::Sender<String>,
+}
+
+/// Hands PinMatrixRequests from the transport to the REST API.
+///
+/// Uses std sync primitives because the transport's message handler is
+/// synchronous and blocks the device thread while it waits.
+#[derive(Clone)]
+pub struct PinEntryBroker {
+    pending: Arc<Mutex<Option<PendingPin>>>,
+    events: EventBus,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum PinSubmitError {
+    NotFound,
+    Invalid(String),
+}
+
+fn kind_name(kind: PinMatrixRequestType) -> &'static str {
+    match kind {
+        PinMatrixRequestType::Current => "current",
+        PinMatrixRequestType::NewFirst => "new_first",
+        PinMatrixRequestType::NewSecond => "new_second",
+    }
+}
+
+fn validate_positions(positions: &str) -> Result<(), String> {
+    if positions.is_empty() || positions.len() > MAX_PIN_LENGTH {
+        return Err(format!("PIN must be 1 to {} positions", MAX_PIN_LENGTH));
+    }
+    if !positions.chars().all(|c| ('1'..='9').contains(&c)) {
+        return Err("PIN positions must be digits 1-9".to_string());
+    }
+    Ok(())
+}
+
+impl PinEntryBroker {
+    pub fn new(events: EventBus) -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(None)),
+            events,
+        }
+    }
+
+    pub fn active(&self) -> Option<PinRequest> {
+        self.pending.lock().unwrap().as_ref().map(|p| p.request.clone())
+    }
+
+    /// Pass the clicked positions for request `id` to the waiting device call
+    pub(crate) fn submit(&self, id: &str, positions: &str) -> Result<(), PinSubmitError> {
+        validate_positions(positions).map_err(PinSubmitError::Invalid)?;
+        let mut pending = self.pending.lock().unwrap();
+        match pending.as_ref() {
+            Some(p) if p.request.id == id => {}
+            _ => return Err(PinSubmitError::NotFound),
+        }
+        let entry = pending.take().ok_or(PinSubmitError::NotFound)?;
+        // The device call may have timed out in the meantime
+        entry.respond.send(positions.to_string()).map_err(|_| PinSubmitError::NotFound)
+    }
+
+    /// Block until positions are submitted for a PinMatrixRequest of `kind`
+    pub(crate) fn wait(&self, kind: PinMatrixRequestType, wait: Duration) -> Result<String> {
+        let now = chrono::Utc::now().timestamp();
+        let request = PinRequest {
+            id: Uuid::new_v4().to_string(),
+            kind: kind_name(kind).to_string(),
+            created_at: now,
+            expires_at: now + wait.as_secs() as i64,
+        };
+        let id = request.id.clone();
+
+        let (respond, positions) = mpsc::channel();
+        // A newer request from the device replaces any stale one
+        *self.pending.lock().unwrap() = Some(PendingPin {
+            request: request.clone(),
+            respond,
+        });
+        info!("🔢 PIN ({}) awaiting remote entry ({})", request.kind, id);
+        self.events.emit("pin:requested", serde_json::to_value(&request)?);
+
+        let result = positions.recv_timeout(wait);
+        let outcome = match &result {
+            Ok(_) => "submitted",
+            Err(RecvTimeoutError::Timeout) => "timed_out",
+            Err(RecvTimeoutError::Disconnected) => "cancelled",
+        };
+        {
+            let mut pending = self.pending.lock().unwrap();
+            if pending.as_ref().map(|p| p.request.id == id).unwrap_or(false) {
+                *pending = None;
+            }
+        }
+        self.events.emit("pin:resolved", serde_json::json!({ "id": id, "outcome": outcome }));
+
+        result.map_err(|_| {
+            warn!("PIN request {} {}", id, outcome);
+            anyhow!("PIN entry {}", outcome.replace('_', " "))
+        })
+    }
+}
+
+fn hash_pin_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub(crate) async fn remote_pin_enabled(cache: &DeviceCache) -> Result<bool> {
+    Ok(cache.get_config("pin_entry").await?.as_deref() == Some("remote"))
+}
+
+/// Whether `token` is the current remote PIN entry token
+pub(crate) async fn check_pin_token(cache: &DeviceCache, token: &str) -> Result<bool> {
+    if !token.starts_with(PIN_TOKEN_PREFIX) {
+        return Ok(false);
+    }
+    Ok(cache.get_config(PIN_TOKEN_HASH_KEY).await?.as_deref() == Some(hash_pin_token(token).as_str()))
+}
+
+/// Route PIN prompts from the transport to `broker`, issuing a token if none exists yet
+pub(crate) async fn enable_remote_pin_entry(cache: &DeviceCache, broker: &PinEntryBroker) -> Result<()> {
+    if cache.get_config(PIN_TOKEN_HASH_KEY).await?.is_none() {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = format!("{}{}", PIN_TOKEN_PREFIX, hex::encode(bytes));
+        cache
+            .set_config(PIN_TOKEN_HASH_KEY, &hash_pin_token(&token), Some("SHA-256 of the bearer token for /api/v2/pin"))
+            .await?;
+        println!("Remote PIN entry token (shown once, store it now): {}", token);
+    }
+
+    let wait = Duration::from_secs(
+        cache
+            .get_config("pin_entry_timeout_secs")
+            .await?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_PIN_TIMEOUT_SECS),
+    );
+    let broker = broker.clone();
+    crate::transport::set_pin_prompt(Some(Arc::new(move |kind| broker.wait(kind, wait))));
+    info!("🔢 PIN entry is remote: GET /api/v2/pin, POST /api/v2/pin/{{id}}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn submitted_positions_reach_the_waiting_call() {
+        let broker = PinEntryBroker::new(EventBus::default());
+        let waiter = {
+            let broker = broker.clone();
+            std::thread::spawn(move || broker.wait(PinMatrixRequestType::Current, Duration::from_secs(5)))
+        };
+
+        let request = loop {
+            if let Some(request) = broker.active() {
+                break request;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(request.kind, "current");
+        assert_eq!(broker.submit(&request.id, "12a"), Err(PinSubmitError::Invalid("PIN positions must be digits 1-9".to_string())));
+        assert_eq!(broker.submit("other", "1234"), Err(PinSubmitError::NotFound));
+        assert_eq!(broker.submit(&request.id, "7415"), Ok(()));
+
+        assert_eq!(waiter.join().unwrap().unwrap(), "7415");
+        assert!(broker.active().is_none());
+    }
+
+    #[test]
+    fn unanswered_request_times_out() {
+        let broker = PinEntryBroker::new(EventBus::default());
+        let err = broker.wait(PinMatrixRequestType::NewFirst, Duration::from_millis(20)).unwrap_err();
+        assert_eq!(err.to_string(), "PIN entry timed out");
+        assert!(broker.active().is_none());
+    }
+}
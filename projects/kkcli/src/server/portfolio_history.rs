@@ -0,0 +1,148 @@
+//! Daily portfolio snapshots and realized transaction history.
+//!
+//! Once per UTC day the cached balances are copied into `portfolio_snapshots`.
+//! Confirmed transactions touching the device's Bitcoin addresses are pulled
+//! from the chain backend into `realized_transactions`, annotated with the USD
+//! price from the snapshot on (or before) the day they confirmed. The blocks
+//! they confirmed in are recorded so `reorg` can undo them if orphaned.
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use tracing::{debug, info, warn};
+
+use super::cache::device_cache::RealizedTx;
+use super::chain::{ChainBackend, EsploraTx};
+use super::ServerState;
+
+const PORTFOLIO_HISTORY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub(crate) const BITCOIN_CAIP: &str = "bip122:000000000019d6689c085ae165831e93/slip44:0";
+
+pub(crate) fn spawn_portfolio_history(state: Arc<ServerState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(PORTFOLIO_HISTORY_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run_once(&state).await {
+                warn!("Portfolio history update failed: {}", e);
+            }
+        }
+    })
+}
+
+async fn run_once(state: &ServerState) -> Result<()> {
+    let device_id = match state.cache.get_device_id() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    if !state.cache.has_portfolio_snapshot(&device_id, &today).await? {
+        let balances = state.cache.get_cached_balances(&device_id).await?;
+        if !balances.is_empty() {
+            state.cache.save_portfolio_snapshot(&device_id, &today, &balances).await?;
+        }
+    }
+
+    if state.cache.is_offline_mode().await? {
+        debug!("Offline mode - skipping realized transaction sync");
+        return Ok(());
+    }
+    sync_realized_transactions(state, &device_id).await
+}
+
+/// Fetch confirmed history for the device's Bitcoin addresses and store the
+/// wallet's net change per transaction
+pub(crate) async fn sync_realized_transactions(state: &ServerState, device_id: &str) -> Result<()> {
+    let addresses = state.cache.get_cached_address_strings(device_id, "Bitcoin").await?;
+    if addresses.is_empty() {
+        return Ok(());
+    }
+
+    let backend = ChainBackend::from_cache(&state.cache).await?;
+    let mut txs: HashMap<String, EsploraTx> = HashMap::new();
+    for address in &addresses {
+        match backend.address_txs(address).await {
+            Ok(history) => {
+                for tx in history {
+                    txs.entry(tx.txid.clone()).or_insert(tx);
+                }
+            }
+            Err(e) => warn!("Failed to fetch history for {}: {}", address, e),
+        }
+    }
+
+    let ours: HashSet<&str> = addresses.iter().map(String::as_str).collect();
+    let mut saved = 0;
+    for tx in txs.values() {
+        let block_time = match (tx.status.confirmed, tx.status.block_time) {
+            (true, Some(time)) => time as i64,
+            _ => continue,
+        };
+        let (amount_sats, fee_sats) = net_wallet_change(tx, &ours);
+        if amount_sats == 0 && fee_sats == 0 {
+            continue;
+        }
+
+        let price_usd = state.cache.get_snapshot_price(device_id, BITCOIN_CAIP, &utc_date(block_time)).await?;
+
+        state
+            .cache
+            .save_realized_tx(
+                device_id,
+                &RealizedTx {
+                    txid: tx.txid.clone(),
+                    caip: BITCOIN_CAIP.to_string(),
+                    amount_sats,
+                    fee_sats,
+                    block_height: tx.status.block_height,
+                    block_time,
+                    price_usd,
+                },
+            )
+            .await?;
+        if let (Some(height), Some(hash)) = (tx.status.block_height, &tx.status.block_hash) {
+            state.cache.record_block(height, hash).await?;
+        }
+        saved += 1;
+    }
+
+    info!("📒 Synced {} realized transaction(s) across {} address(es)", saved, addresses.len());
+    Ok(())
+}
+
+/// `YYYY-MM-DD` (UTC) for a unix timestamp
+pub(crate) fn utc_date(timestamp: i64) -> String {
+    use chrono::TimeZone;
+    chrono::Utc
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Net change in sats for the wallet, and the fee if the wallet funded the transaction
+fn net_wallet_change(tx: &EsploraTx, ours: &HashSet<&str>) -> (i64, i64) {
+    let is_ours = |address: &Option<String>| address.as_deref().map_or(false, |a| ours.contains(a));
+
+    let spent: u64 = tx
+        .vin
+        .iter()
+        .filter_map(|vin| vin.prevout.as_ref())
+        .filter(|prevout| is_ours(&prevout.scriptpubkey_address))
+        .map(|prevout| prevout.value)
+        .sum();
+    let received: u64 = tx
+        .vout
+        .iter()
+        .filter(|vout| is_ours(&vout.scriptpubkey_address))
+        .map(|vout| vout.value)
+        .sum();
+
+    let fee = if spent > 0 { tx.fee.unwrap_or(0) as i64 } else { 0 };
+    // The fee is reported separately, so it is not part of the amount sent
+    let amount = received as i64 - spent as i64 + fee;
+    (amount, fee)
+}
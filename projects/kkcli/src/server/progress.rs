@@ -0,0 +1,191 @@
+//! Progress of long device operations (frontload, firmware updates, signing,
+//! recovery).
+//!
+//! Same JSON shape as keepkey-rust's `progress::Progress`, so vault and kkcli
+//! clients read `device:progress` frames the same way. The server publishes
+//! them on the event bus; CLI commands draw them as text bars.
+
+use crate::messages;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::events::EventBus;
+
+pub const PROGRESS_EVENT: &str = "device:progress";
+const BAR_WIDTH: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressOperation {
+    Frontload,
+    FirmwareUpdate,
+    BootloaderUpdate,
+    Signing,
+    Recovery,
+}
+
+/// Stage of a firmware or bootloader update, as in keepkey-rust.
+/// `downloading` and `uploading` count bytes; the others are single steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStage {
+    Downloading,
+    VerifyingHash,
+    Erasing,
+    Uploading,
+    Rebooting,
+    Verifying,
+    Finished,
+    Failed,
+}
+
+impl UpdateStage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UpdateStage::Downloading => "downloading",
+            UpdateStage::VerifyingHash => "verifying_hash",
+            UpdateStage::Erasing => "erasing",
+            UpdateStage::Uploading => "uploading",
+            UpdateStage::Rebooting => "rebooting",
+            UpdateStage::Verifying => "verifying",
+            UpdateStage::Finished => "finished",
+            UpdateStage::Failed => "failed",
+        }
+    }
+}
+
+/// One step of an operation. `current` counts up to `total`; a `total` of 0
+/// means the number of steps isn't known.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    pub operation: ProgressOperation,
+    /// Operation-specific step name, e.g. `path`, `uploading`, `input`, `word`
+    pub stage: String,
+    pub current: u64,
+    pub total: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl Progress {
+    pub fn new(operation: ProgressOperation, stage: impl Into<String>, current: u64, total: u64) -> Self {
+        Self { operation, stage: stage.into(), current, total, message: None }
+    }
+
+    /// A firmware or bootloader update step
+    pub fn update(operation: ProgressOperation, stage: UpdateStage, current: u64, total: u64) -> Self {
+        Self::new(operation, stage.as_str(), current, total)
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Completed share between 0 and 1, if the total is known
+    pub fn fraction(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.current.min(self.total) as f64 / self.total as f64)
+    }
+
+    /// One-line text bar, e.g. `uploading [######------] 12/24`
+    pub fn render_bar(&self) -> String {
+        let counts = if self.total > 0 {
+            format!("{}/{}", self.current, self.total)
+        } else {
+            self.current.to_string()
+        };
+        let filled = self.fraction().map_or(0, |f| (f * BAR_WIDTH as f64).round() as usize);
+        let mut line = format!("{} [{}{}] {}", self.stage, "#".repeat(filled), "-".repeat(BAR_WIDTH - filled), counts);
+        if let Some(message) = &self.message {
+            line.push(' ');
+            line.push_str(message);
+        }
+        line
+    }
+}
+
+/// Receives progress for a device; the first argument is the device id
+pub type ProgressSink = Arc<dyn Fn(&str, &Progress) + Send + Sync>;
+
+/// Publish progress as `device:progress` frames for WebSocket and SSE clients
+pub fn event_sink(events: EventBus) -> ProgressSink {
+    Arc::new(move |device_id, progress| emit(&events, device_id, progress))
+}
+
+pub fn emit(events: &EventBus, device_id: &str, progress: &Progress) {
+    let mut data = serde_json::to_value(progress).unwrap_or_default();
+    data["device_id"] = serde_json::Value::String(device_id.to_string());
+    events.emit(PROGRESS_EVENT, data);
+}
+
+/// Signing step for a TxRequest about the transaction being signed. Requests
+/// about previous transactions aren't steps and give `None`.
+pub fn signing_step(tx_req: &messages::TxRequest, inputs: u64, outputs: u64) -> Option<Progress> {
+    let details = tx_req.details.as_ref();
+    if details.and_then(|d| d.tx_hash.as_ref()).is_some() {
+        return None;
+    }
+    let index = details.and_then(|d| d.request_index).unwrap_or(0) as u64;
+    match tx_req.request_type {
+        Some(rt) if rt == messages::RequestType::Txinput as i32 => {
+            Some(Progress::new(ProgressOperation::Signing, "input", index + 1, inputs))
+        }
+        Some(rt) if rt == messages::RequestType::Txoutput as i32 => {
+            Some(Progress::new(ProgressOperation::Signing, "output", index + 1, outputs))
+        }
+        Some(rt) if rt == messages::RequestType::Txfinished as i32 => {
+            Some(Progress::new(ProgressOperation::Signing, "finished", 1, 1))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_match_the_library_shape() {
+        let events = EventBus::default();
+        let mut rx = events.subscribe();
+        emit(&events, "dev1", &Progress::update(ProgressOperation::FirmwareUpdate, UpdateStage::Uploading, 3, 4));
+
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.event_type, PROGRESS_EVENT);
+        assert_eq!(
+            event.data,
+            serde_json::json!({
+                "device_id": "dev1",
+                "operation": "firmware_update",
+                "stage": "uploading",
+                "current": 3,
+                "total": 4,
+            })
+        );
+        assert_eq!(
+            Progress::new(ProgressOperation::Frontload, "path", 1, 4).render_bar(),
+            format!("path [{}{}] 1/4", "#".repeat(6), "-".repeat(18))
+        );
+    }
+
+    #[test]
+    fn update_stages_use_the_library_names() {
+        for stage in [UpdateStage::Downloading, UpdateStage::VerifyingHash, UpdateStage::Rebooting, UpdateStage::Failed] {
+            assert_eq!(serde_json::to_value(stage).unwrap(), serde_json::json!(stage.as_str()));
+        }
+        assert_eq!(UpdateStage::VerifyingHash.as_str(), "verifying_hash");
+    }
+
+    #[test]
+    fn only_steps_of_the_signed_transaction_count() {
+        let request = |request_type: messages::RequestType, tx_hash: Option<Vec<u8>>| messages::TxRequest {
+            request_type: Some(request_type as i32),
+            details: Some(messages::TxRequestDetailsType { request_index: Some(1), tx_hash, ..Default::default() }),
+            serialized: None,
+        };
+        let step = signing_step(&request(messages::RequestType::Txinput, None), 2, 1).unwrap();
+        assert_eq!((step.stage.as_str(), step.current, step.total), ("input", 2, 2));
+        assert!(signing_step(&request(messages::RequestType::Txinput, Some(vec![1])), 2, 1).is_none());
+        assert!(signing_step(&request(messages::RequestType::Txmeta, None), 2, 1).is_none());
+    }
+}
@@ -0,0 +1,223 @@
+//! BIP-174 PSBT signing for `kkcli sign-psbt` and `POST /bitcoin/sign-psbt`.
+//!
+//! A PSBT is turned into an ordinary [`BitcoinSignRequest`], so it goes
+//! through the same approval, confirmation policy and SignTx flow as any
+//! other transaction. The device's signed transaction is then merged back
+//! into the PSBT as partial signatures. The mapping follows keepkey-rust's
+//! `psbt` module.
+
+use anyhow::{anyhow, Result};
+use bitcoin::bip32::{Fingerprint, KeySource};
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::psbt::{Input, Psbt};
+use bitcoin::{Address, Network, Script, Transaction, TxOut};
+use std::str::FromStr;
+
+use super::routes::{BitcoinInput, BitcoinOutput, BitcoinSignRequest};
+
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+/// Read a PSBT in binary form or as base64 text
+pub fn parse_psbt(data: &[u8]) -> Result<Psbt> {
+    if data.starts_with(PSBT_MAGIC) {
+        return Psbt::deserialize(data).map_err(|e| anyhow!("Invalid PSBT: {}", e));
+    }
+    let text = std::str::from_utf8(data).map_err(|_| anyhow!("Invalid PSBT: neither binary nor base64"))?;
+    Psbt::from_str(text.trim()).map_err(|e| anyhow!("Invalid PSBT: {}", e))
+}
+
+/// The sign request for every input and output of `psbt`. `fingerprint`
+/// picks this wallet's key when an input lists several derivations.
+pub fn psbt_sign_request(psbt: &Psbt, fingerprint: Option<Fingerprint>) -> Result<BitcoinSignRequest> {
+    let tx = &psbt.unsigned_tx;
+    let mut inputs = Vec::with_capacity(tx.input.len());
+    for (index, (txin, input)) in tx.input.iter().zip(&psbt.inputs).enumerate() {
+        let spent = spent_output(txin.previous_output.vout, input)
+            .ok_or_else(|| anyhow!("Invalid PSBT: input {} has neither a witness nor a non-witness UTXO", index))?;
+        let (_, path) = pick_derivation(input.bip32_derivation.values(), fingerprint)
+            .map_err(|e| anyhow!("Invalid PSBT: input {} {}", index, e))?;
+        let script_type = if spent.script_pubkey.is_p2pkh() {
+            "p2pkh"
+        } else if spent.script_pubkey.is_v0_p2wpkh() {
+            "p2wpkh"
+        } else if spent.script_pubkey.is_p2sh() && input.redeem_script.as_ref().is_some_and(|s| s.is_v0_p2wpkh()) {
+            "p2sh-p2wpkh"
+        } else {
+            return Err(anyhow!("Unsupported PSBT: input {} spends an unsupported script type", index));
+        };
+        inputs.push(BitcoinInput {
+            address_n: path.into_iter().map(|child| u32::from(*child)).collect(),
+            prev_hash: txin.previous_output.txid.to_string(),
+            prev_index: txin.previous_output.vout,
+            amount: spent.value.to_string(),
+            script_type: script_type.to_string(),
+            hex: input.non_witness_utxo.as_ref().map(|prev| hex::encode(serialize(prev))),
+            sequence: Some(txin.sequence.0),
+        });
+    }
+
+    let mut outputs = Vec::with_capacity(tx.output.len());
+    for (index, (txout, output)) in tx.output.iter().zip(&psbt.outputs).enumerate() {
+        let script = &txout.script_pubkey;
+        let change_type = if script.is_p2pkh() {
+            Some("p2pkh")
+        } else if script.is_v0_p2wpkh() {
+            Some("p2wpkh")
+        } else if script.is_p2sh() && output.redeem_script.as_ref().is_some_and(|s| s.is_v0_p2wpkh()) {
+            Some("p2sh-p2wpkh")
+        } else {
+            None
+        };
+        let change = pick_derivation(output.bip32_derivation.values(), fingerprint).ok();
+        let mapped = match (change, change_type) {
+            (Some((_, path)), Some(script_type)) => BitcoinOutput {
+                address: None,
+                address_n: Some(path.into_iter().map(|child| u32::from(*child)).collect()),
+                amount: txout.value.to_string(),
+                script_type: script_type.to_string(),
+                op_return_data: None,
+            },
+            _ if script.is_op_return() => BitcoinOutput {
+                address: None,
+                address_n: None,
+                amount: txout.value.to_string(),
+                script_type: "op_return".to_string(),
+                op_return_data: Some(hex::encode(script_pushes(script).concat())),
+            },
+            _ => {
+                let address = Address::from_script(script, Network::Bitcoin)
+                    .map_err(|_| anyhow!("Unsupported PSBT: output {} has a script with no address", index))?;
+                BitcoinOutput {
+                    address: Some(address.to_string()),
+                    address_n: None,
+                    amount: txout.value.to_string(),
+                    script_type: "p2pkh".to_string(),
+                    op_return_data: None,
+                }
+            }
+        };
+        outputs.push(mapped);
+    }
+
+    Ok(BitcoinSignRequest {
+        tx_hex: hex::encode(serialize(tx)),
+        inputs,
+        outputs,
+        version: Some(tx.version as u32),
+        lock_time: Some(tx.lock_time.to_consensus_u32()),
+        device_id: None,
+    })
+}
+
+/// Add the signatures in the device's signed transaction to `psbt` as
+/// partial signatures, returning the transaction as well
+pub fn merge_signed_tx(mut psbt: Psbt, serialized_tx_hex: &str) -> Result<(Psbt, Transaction)> {
+    let tx: Transaction = deserialize(&hex::decode(serialized_tx_hex)?)
+        .map_err(|e| anyhow!("Device returned an unreadable transaction: {}", e))?;
+    if tx.txid() != psbt.unsigned_tx.txid() {
+        return Err(anyhow!("Device signed a different transaction than the PSBT describes"));
+    }
+    for (index, txin) in tx.input.iter().enumerate() {
+        // Witness spends carry [signature, pubkey]; P2PKH carries them as script_sig pushes
+        let pushes = if txin.witness.is_empty() { script_pushes(&txin.script_sig) } else { txin.witness.to_vec() };
+        let [signature, pubkey] = <[Vec<u8>; 2]>::try_from(pushes)
+            .map_err(|_| anyhow!("Device left input {} unsigned", index))?;
+        let signature = bitcoin::ecdsa::Signature::from_slice(&signature)
+            .map_err(|e| anyhow!("Input {} signature: {}", index, e))?;
+        let pubkey = bitcoin::PublicKey::from_slice(&pubkey).map_err(|e| anyhow!("Input {} pubkey: {}", index, e))?;
+        psbt.inputs[index].partial_sigs.insert(pubkey, signature);
+    }
+    Ok((psbt, tx))
+}
+
+fn spent_output(vout: u32, input: &Input) -> Option<TxOut> {
+    input
+        .witness_utxo
+        .clone()
+        .or_else(|| input.non_witness_utxo.as_ref().and_then(|tx| tx.output.get(vout as usize).cloned()))
+}
+
+fn pick_derivation<'a>(
+    derivations: impl Iterator<Item = &'a KeySource>,
+    fingerprint: Option<Fingerprint>,
+) -> Result<&'a KeySource> {
+    let mut candidates = derivations.filter(|(fp, _)| fingerprint.is_none_or(|wanted| *fp == wanted));
+    let first = candidates.next().ok_or_else(|| anyhow!("has no BIP-32 derivation for this wallet"))?;
+    if candidates.next().is_some() {
+        return Err(anyhow!("has several BIP-32 derivations; pass the wallet's master fingerprint"));
+    }
+    Ok(first)
+}
+
+fn script_pushes(script: &Script) -> Vec<Vec<u8>> {
+    script
+        .instructions()
+        .filter_map(|instruction| match instruction {
+            Ok(Instruction::PushBytes(bytes)) => Some(bytes.as_bytes().to_vec()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::bip32::DerivationPath;
+    use bitcoin::blockdata::locktime::absolute::LockTime;
+    use bitcoin::{OutPoint, ScriptBuf, Sequence, TxIn};
+
+    #[test]
+    fn maps_a_psbt_to_a_sign_request() {
+        let key = bitcoin::PublicKey::from_str("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+        let script = ScriptBuf::new_v0_p2wpkh(&key.wpubkey_hash().unwrap());
+        let recipient = Address::from_str("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq").unwrap().assume_checked();
+        let unsigned = Transaction {
+            version: 2,
+            lock_time: LockTime::from_consensus(800_000),
+            input: vec![TxIn {
+                previous_output: OutPoint::from_str(
+                    "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b:1",
+                )
+                .unwrap(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                ..Default::default()
+            }],
+            output: vec![
+                TxOut { value: 30_000, script_pubkey: recipient.script_pubkey() },
+                TxOut { value: 19_000, script_pubkey: script.clone() },
+            ],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(unsigned).unwrap();
+        let path = DerivationPath::from_str("m/84'/0'/0'/1/3").unwrap();
+        psbt.inputs[0].witness_utxo = Some(TxOut { value: 50_000, script_pubkey: script });
+        psbt.inputs[0].bip32_derivation.insert(key.inner, (Fingerprint::default(), path.clone()));
+        psbt.outputs[1].bip32_derivation.insert(key.inner, (Fingerprint::default(), path));
+
+        let request = psbt_sign_request(&parse_psbt(psbt.to_string().as_bytes()).unwrap(), None).unwrap();
+        assert_eq!((request.version, request.lock_time), (Some(2), Some(800_000)));
+        let input = &request.inputs[0];
+        assert_eq!(input.prev_hash, "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+        assert_eq!((input.script_type.as_str(), input.amount.as_str()), ("p2wpkh", "50000"));
+        assert_eq!(input.sequence, Some(0xffff_fffd));
+        assert_eq!(request.outputs[0].address.as_deref(), Some("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"));
+        assert_eq!(request.outputs[1].address_n, Some(vec![0x8000_0054, 0x8000_0000, 0x8000_0000, 1, 3]));
+
+        assert!(merge_signed_tx(psbt, &request.tx_hex).is_err());
+    }
+
+    #[test]
+    fn maps_op_return_outputs_to_their_payload() {
+        let unsigned = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![],
+            output: vec![TxOut { value: 0, script_pubkey: ScriptBuf::from_hex("6a0568656c6c6f").unwrap() }],
+        };
+        let psbt = Psbt::from_unsigned_tx(unsigned).unwrap();
+        let request = psbt_sign_request(&psbt, None).unwrap();
+        let output = &request.outputs[0];
+        assert!(output.is_op_return());
+        assert_eq!(output.op_return_data.as_deref(), Some(hex::encode(b"hello").as_str()));
+    }
+}
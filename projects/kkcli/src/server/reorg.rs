@@ -0,0 +1,93 @@
+//! Keeps cached chain history on the backend's best chain.
+//!
+//! `tx_tracker` and `portfolio_history` record the block each confirmation was
+//! seen in. This job compares those blocks with what the backend now has at
+//! the same heights; when they differ (or the tip dropped below them) the
+//! history from the fork point up is rolled back, balances are marked stale
+//! and a `chain:reorg` event is published. The realized history is then
+//! synced again so transactions re-mined on the new chain come back.
+
+use anyhow::Result;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use tracing::{debug, warn};
+
+use super::chain::ChainBackend;
+use super::portfolio_history;
+use super::ServerState;
+
+const REORG_WATCH_INTERVAL: Duration = Duration::from_secs(2 * 60);
+
+pub(crate) fn spawn_reorg_watch(state: Arc<ServerState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(REORG_WATCH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = check_once(&state).await {
+                warn!("Reorg check failed: {}", e);
+            }
+        }
+    })
+}
+
+async fn check_once(state: &ServerState) -> Result<()> {
+    if state.cache.is_offline_mode().await? {
+        return Ok(());
+    }
+    let recorded = state.cache.get_recorded_blocks().await?;
+    if recorded.is_empty() {
+        return Ok(());
+    }
+
+    let backend = ChainBackend::from_cache(&state.cache).await?;
+    let stale = stale_blocks(&backend, &recorded).await?;
+    let fork_height = match stale.last() {
+        Some((height, _)) => *height,
+        None => {
+            debug!("{} recorded block(s) still on the best chain", recorded.len());
+            return Ok(());
+        }
+    };
+
+    let rollback = state.cache.roll_back_chain(fork_height).await?;
+    warn!(
+        "🔀 Reorg from height {}: {} block(s) orphaned, {} realized and {} broadcast transaction(s) rolled back",
+        fork_height,
+        stale.len(),
+        rollback.realized_txids.len(),
+        rollback.broadcast_txids.len()
+    );
+    state.events.emit(
+        "chain:reorg",
+        json!({
+            "forkHeight": fork_height,
+            "staleBlocks": stale
+                .iter()
+                .map(|(height, hash)| json!({ "height": height, "hash": hash }))
+                .collect::<Vec<_>>(),
+            "realizedTxids": rollback.realized_txids,
+            "broadcastTxids": rollback.broadcast_txids,
+            "staleBalances": rollback.stale_balances,
+        }),
+    );
+
+    if let Some(device_id) = state.cache.get_device_id() {
+        portfolio_history::sync_realized_transactions(state, &device_id).await?;
+    }
+    Ok(())
+}
+
+/// Recorded blocks (highest first) the backend no longer has, stopping at the
+/// first one still on its best chain
+async fn stale_blocks(backend: &ChainBackend, recorded: &[(u64, String)]) -> Result<Vec<(u64, String)>> {
+    let tip = backend.tip_height().await?;
+    let mut stale = Vec::new();
+    for (height, hash) in recorded {
+        if *height <= tip && backend.block_hash_at(*height).await? == *hash {
+            break;
+        }
+        stale.push((*height, hash.clone()));
+    }
+    Ok(stale)
+}
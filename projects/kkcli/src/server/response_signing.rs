@@ -0,0 +1,271 @@
+//! Signed responses for clients that don't trust the local path to the daemon.
+//!
+//! A process on the host sitting between this server and an app (a proxy, or
+//! something squatting on the port while the server restarts) could rewrite a
+//! receive address or xpub in transit. A client that sends `X-KeepKey-Sign: 1`
+//! with an address or public key request gets an ECDSA signature over the
+//! response in `X-KeepKey-Signature`, made with the key in
+//! `X-KeepKey-Signing-Key`.
+//!
+//! The signed message is SHA-256 of
+//! `keepkey-response-v1\n<METHOD> <path>\n<canonical JSON body>`, where the
+//! canonical JSON has object keys sorted bytewise and no whitespace. The
+//! signature is the 64-byte compact form in hex.
+//!
+//! The signing key is never written anywhere: it comes from CipherKeyValue on
+//! the device at a fixed path and key name, so it only exists while the device
+//! is connected and unlocked, and a given seed always yields the same key.
+//! Clients pin it from `GET /api/v2/response-signing/key` once and refuse
+//! responses signed by anything else.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bitcoin::secp256k1::{ecdsa::Signature, Message as SecpMessage, PublicKey, Secp256k1, SecretKey};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+use crate::messages::{self, Message};
+use crate::transport::ProtocolAdapter;
+use super::{ServerState, DEVICE_OPERATION_TIMEOUT};
+
+/// Request header opting in to a signed response
+pub(crate) const SIGN_HEADER: &str = "x-keepkey-sign";
+pub(crate) const SIGNATURE_HEADER: &str = "x-keepkey-signature";
+pub(crate) const SIGNING_KEY_HEADER: &str = "x-keepkey-signing-key";
+
+pub(crate) const MESSAGE_PREFIX: &str = "keepkey-response-v1";
+pub(crate) const ALGORITHM: &str = "ecdsa-secp256k1-sha256";
+
+// m/10016'/0', the SLIP-0011 purpose for CipherKeyValue
+const KEY_PATH: [u32; 2] = [0x8000_0000 | 10016, 0x8000_0000];
+const KEY_NAME: &str = "KeepKey response signing key";
+// Fixed plaintext; only the ciphertext's determinism matters
+const KEY_PLAINTEXT: [u8; 32] = [0u8; 32];
+
+/// Responses that carry data a client would act on without checking it on
+/// the device: receive addresses and xpubs
+pub(crate) const SIGNED_ENDPOINTS: &[(Method, &str)] = &[
+    (Method::POST, "/addresses/utxo"),
+    (Method::POST, "/api/v1/utxo/address"),
+    (Method::POST, "/system/info/get-public-key"),
+];
+
+/// Signing key derived from the connected device, kept in memory only
+#[derive(Clone, Default)]
+pub struct ResponseSigner {
+    key: Arc<Mutex<Option<DerivedKey>>>,
+}
+
+struct DerivedKey {
+    device_id: Option<String>,
+    secret: SecretKey,
+}
+
+impl ResponseSigner {
+    /// The current device's key, asking the device for it if it changed
+    pub(crate) async fn secret_key(&self, state: &ServerState) -> Result<SecretKey> {
+        let device_id = state.cache.get_device_id();
+        let mut key = self.key.lock().await;
+        if let Some(derived) = key.as_ref().filter(|k| k.device_id == device_id) {
+            return Ok(derived.secret);
+        }
+
+        let secret = SecretKey::from_slice(&derive_device_key(state, &KEY_PATH, KEY_NAME).await?)?;
+        info!("🔏 Response signing key ready for device {:?}", device_id);
+        *key = Some(DerivedKey { device_id, secret });
+        Ok(secret)
+    }
+
+    pub(crate) async fn public_key(&self, state: &ServerState) -> Result<PublicKey> {
+        let secret = self.secret_key(state).await?;
+        Ok(PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret))
+    }
+
+    /// Hex signature and public key for a response body
+    pub(crate) async fn sign(&self, state: &ServerState, method: &Method, path: &str, body: &Value) -> Result<(String, String)> {
+        let secret = self.secret_key(state).await?;
+        let secp = Secp256k1::signing_only();
+        let digest = SecpMessage::from_slice(&signing_digest(method, path, body))?;
+        let signature = secp.sign_ecdsa(&digest, &secret);
+        Ok((
+            hex::encode(signature.serialize_compact()),
+            PublicKey::from_secret_key(&secp, &secret).to_string(),
+        ))
+    }
+
+    /// Forget the key, e.g. when the device is wiped or swapped
+    pub(crate) async fn clear(&self) {
+        *self.key.lock().await = None;
+    }
+}
+
+/// SHA-256 of the device's CipherKeyValue encryption of a fixed block under
+/// `key_name` at `path`: the same 32 bytes for a given seed, never stored
+pub(crate) async fn derive_device_key(state: &ServerState, path: &[u32], key_name: &str) -> Result<[u8; 32]> {
+    let ciphered = timeout(DEVICE_OPERATION_TIMEOUT, async {
+        let mut transport_guard = state.active_transport.lock().await;
+        let transport = transport_guard
+            .as_mut()
+            .ok_or_else(|| anyhow!("Device not connected or transport not initialized"))?;
+        let request = messages::CipherKeyValue {
+            address_n: path.to_vec(),
+            key: Some(key_name.to_string()),
+            value: Some(KEY_PLAINTEXT.to_vec()),
+            encrypt: Some(true),
+            ask_on_encrypt: Some(false),
+            ask_on_decrypt: Some(false),
+            iv: None,
+        };
+        match transport.with_standard_handler().handle(request.into())? {
+            Message::CipheredKeyValue(resp) => resp.value.ok_or_else(|| anyhow!("Device returned no key material")),
+            Message::Failure(failure) => Err(anyhow!("Device refused key derivation: {}", failure.message.unwrap_or_default())),
+            other => Err(anyhow!("Unexpected response to CipherKeyValue: {:?}", other.message_type())),
+        }
+    })
+    .await
+    .map_err(|_| anyhow!("Device timed out deriving {:?}", key_name))??;
+
+    Ok(Sha256::digest(ciphered).into())
+}
+
+/// Object keys sorted bytewise, no insignificant whitespace
+pub(crate) fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+pub(crate) fn signing_digest(method: &Method, path: &str, body: &Value) -> [u8; 32] {
+    let message = format!("{}\n{} {}\n{}", MESSAGE_PREFIX, method, path, canonical_json(body));
+    Sha256::digest(message.as_bytes()).into()
+}
+
+/// Check a signature the way a client would
+pub fn verify(public_key: &str, signature: &str, method: &Method, path: &str, body: &Value) -> Result<()> {
+    let public_key: PublicKey = public_key.parse()?;
+    let signature = Signature::from_compact(&hex::decode(signature)?)?;
+    let digest = SecpMessage::from_slice(&signing_digest(method, path, body))?;
+    Secp256k1::verification_only().verify_ecdsa(&digest, &signature, &public_key)?;
+    Ok(())
+}
+
+fn wants_signature(req: &Request) -> bool {
+    req.headers()
+        .get(SIGN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+fn reject(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Sign successful responses from [`SIGNED_ENDPOINTS`] for clients that ask.
+/// A client that asked never gets an unsigned answer back: if the device
+/// can't sign, the request fails.
+pub(crate) async fn sign_responses(State(state): State<Arc<ServerState>>, req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    if !wants_signature(&req) || !SIGNED_ENDPOINTS.iter().any(|(m, p)| *m == method && *p == path) {
+        return next.run(req).await;
+    }
+
+    let response = next.run(req).await;
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return reject(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read response: {}", e)),
+    };
+    let value: Value = match serde_json::from_slice(&bytes) {
+        Ok(value) => value,
+        Err(e) => return reject(StatusCode::INTERNAL_SERVER_ERROR, format!("Response is not JSON: {}", e)),
+    };
+
+    match state.response_signer.sign(&state, &method, &path, &value).await {
+        Ok((signature, public_key)) => {
+            for (name, value) in [(SIGNATURE_HEADER, signature), (SIGNING_KEY_HEADER, public_key)] {
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    parts.headers.insert(name, value);
+                }
+            }
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(e) => {
+            warn!("🔏 Could not sign {} {}: {}", method, path, e);
+            reject(StatusCode::SERVICE_UNAVAILABLE, format!("Response signing unavailable: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_json_sorts_keys_and_strips_whitespace() {
+        let body: Value = serde_json::from_str(r#"{ "b": [1, {"z": true, "a": null}], "a": "x\"y" }"#).unwrap();
+        assert_eq!(canonical_json(&body), r#"{"a":"x\"y","b":[1,{"a":null,"z":true}]}"#);
+    }
+
+    #[test]
+    fn signatures_verify_only_for_the_signed_request() {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let public_key = PublicKey::from_secret_key(&secp, &secret).to_string();
+        let body = serde_json::json!({ "address": "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", "addressN": [2147483732u32, 2147483648u32, 2147483648u32, 0, 0] });
+
+        let digest = SecpMessage::from_slice(&signing_digest(&Method::POST, "/addresses/utxo", &body)).unwrap();
+        let signature = hex::encode(secp.sign_ecdsa(&digest, &secret).serialize_compact());
+
+        assert!(verify(&public_key, &signature, &Method::POST, "/addresses/utxo", &body).is_ok());
+        assert!(verify(&public_key, &signature, &Method::POST, "/system/info/get-public-key", &body).is_err());
+        let mut tampered = body.clone();
+        tampered["address"] = Value::String("bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string());
+        assert!(verify(&public_key, &signature, &Method::POST, "/addresses/utxo", &tampered).is_err());
+    }
+}
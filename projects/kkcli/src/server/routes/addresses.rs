@@ -19,10 +19,13 @@ pub struct UtxoAddressRequest {
     pub address_n: Vec<u32>,
     /// Coin name (e.g., "Bitcoin")
     pub coin: String,
-    /// Script type (e.g., "p2sh-p2wpkh")
+    /// Script type: `p2pkh` (default), `p2sh-p2wpkh`, `p2wpkh` or `p2tr`
     pub script_type: Option<String>,
     /// Whether to show on device display
     pub show_display: Option<bool>,
+    /// KeepKey to ask, by device id; the server's device when omitted
+    #[serde(default)]
+    pub device_id: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -43,6 +46,7 @@ pub struct UtxoAddressResponse {
     request_body = UtxoAddressRequest,
     responses(
         (status = 200, description = "Address generated successfully", body = UtxoAddressResponse),
+        (status = 400, description = "Unknown script type, or p2tr on firmware without taproot"),
         (status = 404, description = "No KeepKey device found"),
         (status = 500, description = "Internal server error")
     ),
@@ -55,7 +59,7 @@ pub async fn generate_utxo_address(
     info!("UTXO address generation request: coin={}, script_type={:?}, path={:?}", 
         request.coin, request.script_type, request.address_n);
     
-    match crate::server::generate_utxo_address_impl(request, &state.cache, state.device_mutex.clone()).await {
+    match crate::server::generate_utxo_address_impl(request, &state.cache, &state.device_queues).await {
         Ok(response) => {
             info!("Generated address: {}", response.address);
             Ok(Json(response))
@@ -64,6 +68,8 @@ pub async fn generate_utxo_address(
             error!("Failed to generate address: {}", e);
             if e.to_string().contains("No KeepKey device found") {
                 Err(StatusCode::NOT_FOUND)
+            } else if e.to_string().starts_with("Unsupported script type") || e.to_string().starts_with("Taproot requires") {
+                Err(StatusCode::BAD_REQUEST)
             } else {
                 Err(StatusCode::INTERNAL_SERVER_ERROR)
             }
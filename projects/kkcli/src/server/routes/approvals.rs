@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use std::sync::Arc;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use tracing::info;
+
+use crate::server::approvals::ApprovalRequest;
+use crate::server::ServerState;
+use super::chain::api_key_from_headers;
+use super::common::ApiError;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalDecision {
+    pub approve: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/approvals",
+    responses(
+        (status = 200, description = "Signing requests waiting for approval", body = [ApprovalRequest])
+    ),
+    tag = "approvals"
+)]
+pub async fn list_pending_approvals(
+    State(state): State<Arc<ServerState>>,
+) -> Json<Vec<ApprovalRequest>> {
+    Json(state.approvals.list().await)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/approvals/{id}",
+    params(("id" = String, Path, description = "Approval request id")),
+    request_body = ApprovalDecision,
+    responses(
+        (status = 204, description = "Decision recorded"),
+        (status = 403, description = "A client cannot approve its own signing request"),
+        (status = 404, description = "No pending request with this id")
+    ),
+    tag = "approvals"
+)]
+pub async fn decide_approval(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(decision): Json<ApprovalDecision>,
+) -> Result<StatusCode, ApiError> {
+    match state.approvals.decide(&id, decision.approve, api_key_from_headers(&headers)).await {
+        Some(true) => {
+            info!("Signing request {} {}", id, if decision.approve { "approved" } else { "rejected" });
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Some(false) => Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "A client cannot approve its own signing request",
+        )),
+        None => Err(ApiError::not_found(format!("No pending approval {}", id))),
+    }
+}
@@ -1,16 +1,21 @@
 use axum::{
-    extract::State,
+    extract::{Path, State},
     http::StatusCode,
     Json,
 };
 use std::sync::Arc;
 use serde::{Serialize, Deserialize};
 use utoipa::ToSchema;
-use tracing::info;
+use tracing::{error, info};
 use chrono::Utc;
 use uuid::Uuid;
 
+use crate::server::cache::device_cache::PairedClient;
+use crate::server::paired_clients::{
+    api_key_hint, confirm_pairing_on_device, granted_scopes, hash_api_key, is_sensitive, PairingDeclined,
+};
 use crate::server::ServerState;
+use super::common::ApiError;
 
 #[derive(Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -23,12 +28,18 @@ pub struct PairingInfo {
     pub image_url: String,
     /// When this pairing was added (optional)
     pub added_on: Option<u64>,
+    /// Scopes to grant the key: read, address, sign, admin. Defaults to read,
+    /// address and sign; sign and admin must be confirmed on the device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
 }
 
 #[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthResponse {
     pub api_key: String,
+    /// Scopes the key was granted
+    pub scopes: Vec<String>,
 }
 
 #[utoipa::path(
@@ -53,6 +64,7 @@ pub async fn auth_verify(
         url: "http://localhost:1646".to_string(),
         image_url: "https://github.com/BitHighlander/keepkey-desktop/raw/master/electron/icon.png".to_string(),
         added_on: Some(Utc::now().timestamp() as u64),
+        scopes: None,
     }))
 }
 
@@ -62,26 +74,91 @@ pub async fn auth_verify(
     request_body = PairingInfo,
     responses(
         (status = 200, description = "Pairing successful", body = AuthResponse),
-        (status = 403, description = "Pairing request rejected")
+        (status = 400, description = "Unknown scope requested"),
+        (status = 403, description = "Pairing declined on the device")
     ),
     tag = "auth"
 )]
 pub async fn auth_pair(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
     Json(pairing_info): Json<PairingInfo>,
-) -> Result<Json<AuthResponse>, StatusCode> {
+) -> Result<Json<AuthResponse>, ApiError> {
     info!("Pairing request from: {} ({})", pairing_info.name, pairing_info.url);
-    
-    // Generate a new API key for this pairing
+
+    let scopes = granted_scopes(pairing_info.scopes.as_deref())
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+
+    // Signing and administration are only granted with a button press on the device
+    if scopes.iter().any(|scope| is_sensitive(scope)) {
+        if let Err(e) = confirm_pairing_on_device(&state, &pairing_info.name, &scopes).await {
+            if e.downcast_ref::<PairingDeclined>().is_some() {
+                info!("Pairing with {} declined on the device", pairing_info.name);
+                return Err(ApiError::new(StatusCode::FORBIDDEN, e.to_string()));
+            }
+            error!("Failed to confirm pairing with {}: {}", pairing_info.name, e);
+            return Err(ApiError::internal_error(e.to_string()));
+        }
+    }
+
+    // Generate a new API key for this pairing; only its hash is kept
     let api_key = Uuid::new_v4().to_string();
-    
+    let id = Uuid::new_v4().to_string();
+    if let Err(e) = state
+        .cache
+        .create_paired_client(&id, &hash_api_key(&api_key), &api_key_hint(&api_key), &pairing_info, &scopes)
+        .await
+    {
+        error!("Failed to record pairing for {}: {}", pairing_info.name, e);
+        return Err(ApiError::internal_error(e.to_string()));
+    }
     info!("Generated new API key for {}", pairing_info.name);
-    
-    // In a real implementation, you would:
-    // 1. Show a pairing prompt on the device
-    // 2. Wait for user confirmation
-    // 3. Store the pairing info and API key
-    // 4. Only return success if user approved
-    
-    Ok(Json(AuthResponse { api_key }))
-} 
\ No newline at end of file
+
+    Ok(Json(AuthResponse {
+        api_key,
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/clients",
+    responses(
+        (status = 200, description = "Paired clients with request counts, last activity and scopes used", body = [PairedClient])
+    ),
+    tag = "auth"
+)]
+pub async fn list_paired_clients(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Vec<PairedClient>>, ApiError> {
+    match state.cache.list_paired_clients().await {
+        Ok(clients) => Ok(Json(clients)),
+        Err(e) => {
+            error!("Failed to list paired clients: {}", e);
+            Err(ApiError::internal_error(e.to_string()))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v2/clients/{id}",
+    params(("id" = String, Path, description = "Client id")),
+    responses(
+        (status = 204, description = "Client's API key revoked"),
+        (status = 404, description = "No active client with this id")
+    ),
+    tag = "auth"
+)]
+pub async fn revoke_paired_client(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.cache.revoke_paired_client(&id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::not_found(format!("No active paired client {}", id))),
+        Err(e) => {
+            error!("Failed to revoke paired client: {}", e);
+            Err(ApiError::internal_error(e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::server::metadata_backup::{BackupLocation, BackupReport, RestoreReport};
+use crate::server::ServerState;
+use super::common::ApiError;
+
+fn backup_error(e: anyhow::Error) -> ApiError {
+    let message = e.to_string();
+    if message.contains("No KeepKey device") || message.contains("Device not connected") {
+        ApiError::not_found(message)
+    } else if message.starts_with("Give either") || message.starts_with("Backup URL") {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    } else if message.contains("different seed") || message.contains("metadata backup") || message.starts_with("Backup version") {
+        ApiError::unprocessable_entity(message)
+    } else {
+        ApiError::internal_error(message)
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/metadata-backup",
+    request_body = BackupLocation,
+    responses(
+        (status = 200, description = "Settings, accounts and pairings encrypted with the device's key and written", body = BackupReport),
+        (status = 400, description = "Neither or both of file and url given"),
+        (status = 404, description = "No KeepKey device connected to derive the key"),
+        (status = 500, description = "Writing the backup failed")
+    ),
+    tag = "system"
+)]
+pub async fn create_metadata_backup(
+    State(state): State<Arc<ServerState>>,
+    Json(location): Json<BackupLocation>,
+) -> Result<Json<BackupReport>, ApiError> {
+    crate::server::metadata_backup::create_backup(&state, &location)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Metadata backup failed: {}", e);
+            backup_error(e)
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/metadata-backup/restore",
+    request_body = BackupLocation,
+    responses(
+        (status = 200, description = "Backup opened with the device's key and merged into the cache", body = RestoreReport),
+        (status = 400, description = "Neither or both of file and url given"),
+        (status = 404, description = "No KeepKey device connected to derive the key"),
+        (status = 422, description = "Not a backup, or made with a different seed"),
+        (status = 500, description = "Reading the backup failed")
+    ),
+    tag = "system"
+)]
+pub async fn restore_metadata_backup(
+    State(state): State<Arc<ServerState>>,
+    Json(location): Json<BackupLocation>,
+) -> Result<Json<RestoreReport>, ApiError> {
+    crate::server::metadata_backup::restore_backup(&state, &location)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Metadata restore failed: {}", e);
+            backup_error(e)
+        })
+}
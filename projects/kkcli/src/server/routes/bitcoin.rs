@@ -1,18 +1,25 @@
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
+use std::str::FromStr;
 use std::sync::Arc;
 use serde::{Serialize, Deserialize};
 use utoipa::ToSchema;
-use tracing::{info, error, warn};
+use tracing::{info, error};
 use serde_json;
 use hex;
 use anyhow;
 
+use crate::server::address_validation::validate_address;
+use crate::server::approvals::{await_remote_approval, ApprovalOutcome, ApprovalOutput};
+use crate::server::button_policy::ButtonPolicy;
+use crate::server::wallet_locks::{WalletLock, WalletOperation};
 use crate::server::ServerState;
+use tokio::sync::OwnedMutexGuard;
+use super::chain::api_key_from_headers;
 use super::common::ApiError;
 
 // Helper type to handle amounts that can be either strings or numbers
@@ -38,6 +45,15 @@ pub struct BitcoinSignRequest {
     pub tx_hex: String,
     pub inputs: Vec<BitcoinInput>,
     pub outputs: Vec<BitcoinOutput>,
+    /// Transaction version; 1 when omitted
+    #[serde(default)]
+    pub version: Option<u32>,
+    /// Transaction nLockTime; 0 when omitted
+    #[serde(default)]
+    pub lock_time: Option<u32>,
+    /// KeepKey to sign with, by device id; the server's device when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, ToSchema)]
@@ -48,6 +64,9 @@ pub struct BitcoinInput {
     pub amount: String,
     pub script_type: String,
     pub hex: Option<String>, // Optional previous transaction hex
+    /// nSequence; 0xffffffff (final, no RBF) when omitted
+    #[serde(default)]
+    pub sequence: Option<u32>,
 }
 
 #[derive(Deserialize, Serialize, Debug, ToSchema)]
@@ -56,6 +75,15 @@ pub struct BitcoinOutput {
     pub address_n: Option<Vec<u32>>,
     pub amount: String,
     pub script_type: String,
+    /// Hex payload (at most 80 bytes) of an OP_RETURN output; address and path are ignored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op_return_data: Option<String>,
+}
+
+impl BitcoinOutput {
+    pub fn is_op_return(&self) -> bool {
+        self.op_return_data.is_some() || self.script_type == "op_return"
+    }
 }
 
 #[derive(Serialize, ToSchema)]
@@ -64,12 +92,35 @@ pub struct BitcoinSignResponse {
     pub serialized_tx: String,    // Hex-encoded serialized transaction
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct BitcoinSignPsbtRequest {
+    /// BIP-174 PSBT, base64-encoded
+    pub psbt: String,
+    /// Master key fingerprint (hex) to pick this wallet's key when inputs list several derivations
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// KeepKey to sign with, by device id; the server's device when omitted
+    #[serde(default)]
+    pub device_id: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BitcoinSignPsbtResponse {
+    /// The PSBT with the device's partial signatures added, base64-encoded
+    pub psbt: String,
+    /// Hex-encoded signed transaction, ready to broadcast
+    pub signed_tx: String,
+}
+
 // Bitcoin message signing
 #[derive(Deserialize, ToSchema)]
 pub struct BitcoinSignMessageRequest {
     pub address_n: Vec<u32>,
     pub message: String,
     pub coin: Option<String>,
+    /// KeepKey to sign with, by device id; the server's device when omitted
+    #[serde(default)]
+    pub device_id: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -101,8 +152,12 @@ pub struct UtxoSignTransactionRequest {
     pub outputs: Vec<UtxoOutput>,
     pub version: Option<u32>,
     pub locktime: Option<u32>,
+    /// Hex payload (at most 80 bytes) added as a zero-value OP_RETURN output
     pub op_return_data: Option<String>,
     pub vault_address: Option<String>,
+    /// KeepKey to sign with, by device id; the server's device when omitted
+    #[serde(default)]
+    pub device_id: Option<String>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -168,6 +223,76 @@ pub struct UtxoSignTransactionResponse {
     pub serialized_tx: String,    // Hex-encoded serialized transaction
 }
 
+/// External destinations of a transaction, as shown in an approval prompt
+fn approval_outputs(outputs: &[BitcoinOutput]) -> Vec<ApprovalOutput> {
+    outputs
+        .iter()
+        .filter(|o| o.address_n.is_none())
+        .filter_map(|o| {
+            let address = match &o.op_return_data {
+                Some(data) => format!("OP_RETURN {}", data),
+                None => o.address.clone()?,
+            };
+            Some(ApprovalOutput { address, amount: o.amount.clone(), label: None })
+        })
+        .collect()
+}
+
+/// Hold a signing request until it is approved from the UI, when remote
+/// approval is enabled for the caller's API key
+async fn require_remote_approval(
+    state: &ServerState,
+    headers: &HeaderMap,
+    kind: &str,
+    endpoint: &str,
+    outputs: Vec<ApprovalOutput>,
+    message: Option<String>,
+) -> Result<(), ApiError> {
+    match await_remote_approval(state, api_key_from_headers(headers), kind, endpoint, outputs, message).await {
+        Ok(ApprovalOutcome::Approved) => Ok(()),
+        Ok(ApprovalOutcome::Rejected) => Err(ApiError::new(StatusCode::FORBIDDEN, "Signing request rejected")),
+        Ok(ApprovalOutcome::TimedOut) => Err(ApiError::new(
+            StatusCode::REQUEST_TIMEOUT,
+            "Signing request was not approved in time",
+        )),
+        Err(e) => {
+            error!("Remote approval failed: {}", e);
+            Err(ApiError::internal_error(format!("Remote approval failed: {}", e)))
+        }
+    }
+}
+
+/// Publish signing progress (`sign:started`, `sign:completed`, `sign:failed`) for event stream clients
+fn emit_sign_progress(state: &ServerState, event_type: &str, endpoint: &str, counts: (usize, usize), error: Option<String>) {
+    state.events.emit(
+        event_type,
+        serde_json::json!({
+            "endpoint": endpoint,
+            "inputs": counts.0,
+            "outputs": counts.1,
+            "error": error,
+        }),
+    );
+}
+
+/// Forward per-input and per-output signing steps as `device:progress` frames
+fn step_reporter(state: &ServerState) -> impl Fn(crate::server::progress::Progress) + Send + Sync {
+    let events = state.events.clone();
+    let device_id = state.cache.get_device_id().unwrap_or_default();
+    move |step| crate::server::progress::emit(&events, &device_id, &step)
+}
+
+/// Keep other signing requests off `inputs` until the returned lock drops
+async fn lock_inputs(state: &ServerState, inputs: &[BitcoinInput]) -> Result<WalletLock, ApiError> {
+    let outpoints = inputs.iter().map(|input| format!("{}:{}", input.prev_hash, input.prev_index));
+    Ok(state.wallet_locks.acquire(WalletOperation::Sign, outpoints).await?)
+}
+
+/// Wait until the device `device_id` names is free; other devices keep working
+async fn lock_device(state: &ServerState, device_id: Option<&str>) -> OwnedMutexGuard<()> {
+    state.device_queues.lock_for(&state.cache, device_id).lock_owned().await
+}
+
 // Route handlers for Bitcoin
 #[utoipa::path(
     post,
@@ -175,31 +300,138 @@ pub struct UtxoSignTransactionResponse {
     request_body = BitcoinSignRequest,
     responses(
         (status = 200, description = "Transaction signed successfully", body = BitcoinSignResponse),
+        (status = 400, description = "Malformed or non-mainnet output address, OP_RETURN data over 80 bytes, or taproot inputs on firmware without taproot"),
+        (status = 403, description = "Rejected in the remote approval prompt"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 408, description = "Remote approval timed out"),
+        (status = 409, description = "Another signing request is spending the same inputs, or a firmware update or wipe is running"),
         (status = 500, description = "Internal server error")
     ),
     tag = "bitcoin"
 )]
 pub async fn bitcoin_sign_tx(
-    State(_state): State<Arc<ServerState>>, // Don't use the shared state anymore
+    State(state): State<Arc<ServerState>>, // Approval and events only; signing opens a fresh connection
+    headers: HeaderMap,
     Json(request): Json<BitcoinSignRequest>,
-) -> Result<Json<BitcoinSignResponse>, StatusCode> {
+) -> Result<Json<BitcoinSignResponse>, ApiError> {
     info!("Bitcoin transaction signing request");
+    for (idx, output) in request.outputs.iter().enumerate() {
+        if output.is_op_return() {
+            if let Err(e) = crate::server::impl_bitcoin::op_return_bytes(output) {
+                error!("Rejecting output {}: {}", idx, e);
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Output {}: {}", idx, e)));
+            }
+        } else if let Some(address) = &output.address {
+            if let Err(e) = validate_address(address, bitcoin::Network::Bitcoin) {
+                error!("Rejecting output {}: {}", idx, e);
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Output {}: {}", idx, e)));
+            }
+        }
+    }
+    require_remote_approval(&state, &headers, "sign-tx", "/bitcoin/sign-tx", approval_outputs(&request.outputs), None)
+        .await?;
+    let _operation = lock_inputs(&state, &request.inputs).await?;
+    let _device = lock_device(&state, request.device_id.as_deref()).await;
     info!("🔄 Using FRESH connection approach for better reliability");
     
     // Use the FRESH implementation that creates a new connection for each request
-    match crate::server::impl_bitcoin::bitcoin_sign_tx_fresh_impl(request).await {
+    let policy = ButtonPolicy::load(&state.cache)
+        .await
+        .map_err(|e| ApiError::internal_error(format!("Failed to load confirmation policy: {}", e)))?;
+    
+    let counts = (request.inputs.len(), request.outputs.len());
+    emit_sign_progress(&state, "sign:started", "/bitcoin/sign-tx", counts, None);
+    let report_step = step_reporter(&state);
+    match crate::server::impl_bitcoin::bitcoin_sign_tx_with_progress(request, &policy, &report_step).await {
         Ok(response) => {
             info!("Transaction signed successfully with fresh connection");
+            emit_sign_progress(&state, "sign:completed", "/bitcoin/sign-tx", counts, None);
             Ok(Json(response))
         }
         Err(e) => {
             error!("Failed to sign transaction: {}", e);
-            if e.to_string().contains("No KeepKey device found") {
-                Err(StatusCode::NOT_FOUND)
+            emit_sign_progress(&state, "sign:failed", "/bitcoin/sign-tx", counts, Some(e.to_string()));
+            let status = if e.to_string().contains("No KeepKey device found") {
+                StatusCode::NOT_FOUND
+            } else if e.to_string().starts_with("Policy violation") {
+                StatusCode::FORBIDDEN
+            } else if e.to_string().starts_with("Taproot requires") {
+                StatusCode::BAD_REQUEST
             } else {
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            Err(ApiError::new(status, e.to_string()))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/bitcoin/sign-psbt",
+    request_body = BitcoinSignPsbtRequest,
+    responses(
+        (status = 200, description = "PSBT signed", body = BitcoinSignPsbtResponse),
+        (status = 400, description = "Malformed PSBT or an input/output the device can't sign"),
+        (status = 403, description = "Rejected in the remote approval prompt"),
+        (status = 404, description = "No KeepKey device found"),
+        (status = 408, description = "Remote approval timed out"),
+        (status = 409, description = "Another signing request is spending the same inputs, or a firmware update or wipe is running"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "bitcoin"
+)]
+pub async fn bitcoin_sign_psbt(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<BitcoinSignPsbtRequest>,
+) -> Result<Json<BitcoinSignPsbtResponse>, ApiError> {
+    info!("Bitcoin PSBT signing request");
+    let fingerprint = match request.fingerprint.as_deref().map(bitcoin::bip32::Fingerprint::from_str) {
+        Some(Ok(fingerprint)) => Some(fingerprint),
+        Some(Err(e)) => return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid fingerprint: {}", e))),
+        None => None,
+    };
+    let psbt = crate::server::psbt::parse_psbt(request.psbt.as_bytes())
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+    let mut sign_request = crate::server::psbt::psbt_sign_request(&psbt, fingerprint)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+    sign_request.device_id = request.device_id;
+    require_remote_approval(&state, &headers, "sign-psbt", "/bitcoin/sign-psbt", approval_outputs(&sign_request.outputs), None)
+        .await?;
+    let _operation = lock_inputs(&state, &sign_request.inputs).await?;
+    let _device = lock_device(&state, sign_request.device_id.as_deref()).await;
+
+    let policy = ButtonPolicy::load(&state.cache)
+        .await
+        .map_err(|e| ApiError::internal_error(format!("Failed to load confirmation policy: {}", e)))?;
+    let counts = (sign_request.inputs.len(), sign_request.outputs.len());
+    emit_sign_progress(&state, "sign:started", "/bitcoin/sign-psbt", counts, None);
+    let report_step = step_reporter(&state);
+    let signed = crate::server::impl_bitcoin::bitcoin_sign_tx_with_progress(sign_request, &policy, &report_step)
+        .await
+        .and_then(|response| crate::server::psbt::merge_signed_tx(psbt, &response.serialized_tx));
+    match signed {
+        Ok((psbt, tx)) => {
+            info!("PSBT signed successfully");
+            emit_sign_progress(&state, "sign:completed", "/bitcoin/sign-psbt", counts, None);
+            Ok(Json(BitcoinSignPsbtResponse {
+                psbt: psbt.to_string(),
+                signed_tx: bitcoin::consensus::encode::serialize_hex(&tx),
+            }))
+        }
+        Err(e) => {
+            error!("Failed to sign PSBT: {}", e);
+            emit_sign_progress(&state, "sign:failed", "/bitcoin/sign-psbt", counts, Some(e.to_string()));
+            let status = if e.to_string().contains("No KeepKey device found") {
+                StatusCode::NOT_FOUND
+            } else if e.to_string().starts_with("Policy violation") {
+                StatusCode::FORBIDDEN
+            } else if e.to_string().starts_with("Taproot requires") {
+                StatusCode::BAD_REQUEST
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            Err(ApiError::new(status, e.to_string()))
         }
     }
 }
@@ -210,16 +442,30 @@ pub async fn bitcoin_sign_tx(
     request_body = BitcoinSignMessageRequest,
     responses(
         (status = 200, description = "Message signed successfully", body = BitcoinSignMessageResponse),
+        (status = 403, description = "Rejected in the remote approval prompt"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 408, description = "Remote approval timed out"),
         (status = 500, description = "Internal server error")
     ),
     tag = "bitcoin"
 )]
 pub async fn bitcoin_sign_message(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
     Json(request): Json<BitcoinSignMessageRequest>,
 ) -> Result<Json<BitcoinSignMessageResponse>, StatusCode> {
     info!("Bitcoin message signing request");
+    require_remote_approval(
+        &state,
+        &headers,
+        "sign-message",
+        "/bitcoin/sign-message",
+        Vec::new(),
+        Some(request.message.clone()),
+    )
+    .await
+    .map_err(|e| e.status)?;
+    let _device = lock_device(&state, request.device_id.as_deref()).await;
     
     match crate::server::impl_bitcoin::bitcoin_sign_message_impl(request).await {
         Ok(response) => {
@@ -272,14 +518,18 @@ pub async fn bitcoin_verify_message(
     request_body = UtxoSignTransactionRequest,
     responses(
         (status = 200, description = "Transaction signed successfully", body = UtxoSignTransactionResponse),
+        (status = 403, description = "Rejected in the remote approval prompt"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 408, description = "Remote approval timed out"),
+        (status = 409, description = "Another signing request is spending the same inputs, or a firmware update or wipe is running"),
         (status = 422, description = "Invalid request data"),
         (status = 500, description = "Internal server error")
     ),
     tag = "utxo"
 )]
 pub async fn utxo_sign_transaction(
-    State(_state): State<Arc<ServerState>>, // Don't use the shared state anymore
+    State(state): State<Arc<ServerState>>, // Approval and events only; signing opens a fresh connection
+    headers: HeaderMap,
     Json(request): Json<UtxoSignTransactionRequest>,
 ) -> Result<impl IntoResponse, impl IntoResponse> {
     info!("UTXO transaction signing request for {}", request.coin);
@@ -317,24 +567,19 @@ pub async fn utxo_sign_transaction(
             amount: input.amount.as_string(),
             script_type: input.script_type.clone(),
             hex: Some(prev_tx_hex),
+            sequence: None,
         });
     }
     
     let mut outputs = Vec::new();
-    for output in request.outputs {
-        // Detect script type based on address format
-        let script_type = if output.address.starts_with("bc1q") {
-            "p2wpkh".to_string()  // Native SegWit (bech32)
-        } else if output.address.starts_with("bc1p") {
-            "p2tr".to_string()    // Taproot (bech32m)
-        } else if output.address.starts_with("3") {
-            "p2sh".to_string()    // P2SH (could be p2sh-p2wpkh)
-        } else if output.address.starts_with("1") {
-            "p2pkh".to_string()   // Legacy P2PKH
-        } else {
-            // Default to p2pkh for unknown formats
-            warn!("Unknown address format for {}, defaulting to p2pkh", output.address);
-            "p2pkh".to_string()
+    for (idx, output) in request.outputs.into_iter().enumerate() {
+        // Signing is always for mainnet Bitcoin; the script type comes from the parsed address
+        let script_type = match validate_address(&output.address, bitcoin::Network::Bitcoin) {
+            Ok(validated) => validated.script_type().to_string(),
+            Err(e) => {
+                error!("Rejecting output {}: {}", idx, e);
+                return Err(ApiError::unprocessable_entity(format!("Output {}: {}", idx, e)));
+            }
         };
         
         outputs.push(crate::server::routes::bitcoin::BitcoinOutput {
@@ -342,13 +587,31 @@ pub async fn utxo_sign_transaction(
             address_n: None,  // SDK sends addresses, not derivation paths for outputs
             amount: output.amount.as_string(),
             script_type,
+            op_return_data: None,
         });
     }
     
+    if let Some(data) = request.op_return_data {
+        let output = crate::server::routes::bitcoin::BitcoinOutput {
+            address: None,
+            address_n: None,
+            amount: "0".to_string(),
+            script_type: "op_return".to_string(),
+            op_return_data: Some(data),
+        };
+        if let Err(e) = crate::server::impl_bitcoin::op_return_bytes(&output) {
+            return Err(ApiError::unprocessable_entity(format!("opReturnData: {}", e)));
+        }
+        outputs.push(output);
+    }
+    
     let bitcoin_request = crate::server::routes::bitcoin::BitcoinSignRequest {
         tx_hex: "".to_string(), // Not used in our implementation
         inputs,
         outputs,
+        version: None,
+        lock_time: None,
+        device_id: request.device_id,
     };
 
     // Log the request as pretty JSON for debugging
@@ -357,18 +620,47 @@ pub async fn utxo_sign_transaction(
         Err(_) => info!("🔍 Bitcoin request: {:?}", bitcoin_request),
     }
     
+    if let Err(e) = require_remote_approval(
+        &state,
+        &headers,
+        "sign-tx",
+        "/utxo/sign-transaction",
+        approval_outputs(&bitcoin_request.outputs),
+        None,
+    )
+    .await
+    {
+        return Err(e);
+    }
+    let _operation = lock_inputs(&state, &bitcoin_request.inputs).await?;
+    let _device = lock_device(&state, bitcoin_request.device_id.as_deref()).await;
+    
     // Use the FRESH implementation that creates a new connection for each request
-    match crate::server::impl_bitcoin::bitcoin_sign_tx_fresh_impl(bitcoin_request).await {
+    let policy = match ButtonPolicy::load(&state.cache).await {
+        Ok(policy) => policy,
+        Err(e) => return Err(ApiError::internal_error(format!("Failed to load confirmation policy: {}", e))),
+    };
+    
+    let counts = (bitcoin_request.inputs.len(), bitcoin_request.outputs.len());
+    emit_sign_progress(&state, "sign:started", "/utxo/sign-transaction", counts, None);
+    let report_step = step_reporter(&state);
+    match crate::server::impl_bitcoin::bitcoin_sign_tx_with_progress(bitcoin_request, &policy, &report_step).await {
         Ok(response) => {
             info!("Transaction signed successfully with fresh connection");
+            emit_sign_progress(&state, "sign:completed", "/utxo/sign-transaction", counts, None);
             Ok(Json(UtxoSignTransactionResponse {
                 serialized_tx: response.serialized_tx,
             }))
         }
         Err(e) => {
             error!("Failed to sign transaction: {}", e);
+            emit_sign_progress(&state, "sign:failed", "/utxo/sign-transaction", counts, Some(e.to_string()));
             if e.to_string().contains("No KeepKey device found") {
                 Err(ApiError::not_found("No KeepKey device found"))
+            } else if e.to_string().starts_with("Policy violation") {
+                Err(ApiError::new(StatusCode::FORBIDDEN, e.to_string()))
+            } else if e.to_string().starts_with("Taproot requires") {
+                Err(ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))
             } else {
                 Err(ApiError::internal_error(
                     format!("Failed to sign transaction: {}", e)
@@ -0,0 +1,301 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use tracing::{info, error};
+
+use crate::server::ServerState;
+use super::common::ApiError;
+
+/// What to do when a broadcast spends an outpoint that is already spent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DoubleSpendPolicy {
+    /// Don't broadcast; report the conflicting txids
+    Refuse,
+    /// Broadcast, but report the conflicting txids
+    Warn,
+    /// Skip the conflict check
+    Allow,
+}
+
+impl DoubleSpendPolicy {
+    /// Parse a config value; anything unrecognized falls back to `Refuse`
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "warn" => Self::Warn,
+            "allow" => Self::Allow,
+            _ => Self::Refuse,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictSource {
+    /// One of our own earlier broadcasts
+    History,
+    /// Seen by the chain backend (mempool or confirmed)
+    Backend,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendConflict {
+    /// Outpoint spent by both transactions, as `txid:vout`
+    pub outpoint: String,
+    pub conflicting_txid: String,
+    pub source: ConflictSource,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastRequest {
+    /// Signed transaction, hex encoded
+    pub raw_tx: String,
+    /// Device that signed the transaction, recorded in tx history
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastResponse {
+    pub txid: String,
+    pub broadcast: bool,
+    pub policy: DoubleSpendPolicy,
+    pub conflicts: Vec<SpendConflict>,
+}
+
+/// API key from `Authorization: Bearer <key>`, used to pick per-key policies
+pub(crate) fn api_key_from_headers(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/tx/broadcast",
+    request_body = BroadcastRequest,
+    responses(
+        (status = 200, description = "Transaction broadcast", body = BroadcastResponse),
+        (status = 409, description = "Transaction conflicts with a known spend and the policy is refuse"),
+        (status = 422, description = "Invalid transaction"),
+        (status = 502, description = "Chain backend error")
+    ),
+    tag = "chain"
+)]
+pub async fn broadcast_transaction(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<BroadcastRequest>,
+) -> Result<Json<BroadcastResponse>, ApiError> {
+    info!("Broadcast transaction request");
+
+    match crate::server::broadcast_tx_impl(&state, request, api_key_from_headers(&headers)).await {
+        Ok(response) if !response.broadcast => {
+            let details = serde_json::to_value(&response).unwrap_or_default();
+            Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Transaction {} conflicts with an existing spend", response.txid),
+            )
+            .with_details(details))
+        }
+        Ok(response) => {
+            info!("Broadcast {}", response.txid);
+            Ok(Json(response))
+        }
+        Err(e) => {
+            error!("Failed to broadcast transaction: {}", e);
+            let message = e.to_string();
+            if message.starts_with("Invalid transaction") {
+                Err(ApiError::unprocessable_entity(message))
+            } else {
+                Err(ApiError::new(StatusCode::BAD_GATEWAY, message))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendAgreement {
+    /// Only one backend configured
+    Single,
+    /// Every reachable backend reports the same tip
+    Agree,
+    /// At least one backend is unreachable or on a different tip
+    Disagree,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendTipStatus {
+    pub url: String,
+    pub height: Option<u64>,
+    pub hash: Option<String>,
+    /// Same tip hash as the authoritative backend
+    pub agrees: bool,
+    /// Height difference from the authoritative backend
+    pub height_delta: Option<i64>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainTipResponse {
+    pub height: u64,
+    pub hash: String,
+    /// Median time past of the tip block (BIP113), the reference for timelocks
+    pub median_time: Option<u64>,
+    pub timestamp: Option<u64>,
+    /// Backend the tip was taken from
+    pub backend: String,
+    pub agreement: BackendAgreement,
+    pub backends: Vec<BackendTipStatus>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/chain/tip",
+    responses(
+        (status = 200, description = "Current chain tip and backend health", body = ChainTipResponse),
+        (status = 502, description = "No chain backend reachable")
+    ),
+    tag = "chain"
+)]
+pub async fn get_chain_tip(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<ChainTipResponse>, ApiError> {
+    match crate::server::chain_tip_impl(&state).await {
+        Ok(tip) => Ok(Json(tip)),
+        Err(e) => {
+            error!("Failed to get chain tip: {}", e);
+            Err(ApiError::new(StatusCode::BAD_GATEWAY, e.to_string()))
+        }
+    }
+}
+
+/// One histogram bucket of the mempool
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeBand {
+    /// Lowest fee rate in the band (sat/vB)
+    pub min_fee_rate: f64,
+    /// Upper bound of the band; absent for the top band
+    pub max_fee_rate: Option<f64>,
+    /// Transactions in this band, in vbytes
+    pub vsize: u64,
+    /// Mempool vbytes paying at least `minFeeRate`, this band included
+    pub vsize_ahead: u64,
+    /// Blocks until a transaction at `minFeeRate` confirms, if nothing better arrives
+    pub estimated_blocks: u64,
+    pub estimated_minutes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeEstimate {
+    pub target_blocks: u32,
+    /// sat/vB
+    pub fee_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolFeesResponse {
+    /// Transactions in the mempool
+    pub count: u64,
+    pub vsize: u64,
+    /// Sum of fees in the mempool, in satoshis
+    pub total_fee: u64,
+    /// Highest fee rate first
+    pub bands: Vec<FeeBand>,
+    /// Backend fee estimates by confirmation target
+    pub estimates: Vec<FeeEstimate>,
+    pub backend: String,
+    /// When the backend was queried (unix seconds)
+    pub fetched_at: i64,
+    /// Served from cache because the backend could not be refreshed
+    pub stale: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/fees/mempool",
+    responses(
+        (status = 200, description = "Mempool fee histogram with estimated confirmation times", body = MempoolFeesResponse),
+        (status = 502, description = "Chain backend unreachable and nothing cached")
+    ),
+    tag = "chain"
+)]
+pub async fn get_mempool_fees(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<MempoolFeesResponse>, ApiError> {
+    match crate::server::mempool_fees_impl(&state).await {
+        Ok(fees) => Ok(Json(fees)),
+        Err(e) => {
+            error!("Failed to get mempool fees: {}", e);
+            Err(ApiError::new(StatusCode::BAD_GATEWAY, e.to_string()))
+        }
+    }
+}
+
+fn default_count() -> u64 {
+    1
+}
+
+/// Inputs or outputs of one script type. Give either `scriptType` or, for
+/// outputs, the destination `address` to take the type from.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TxShapeEntry {
+    /// Inputs: `p2pkh`, `p2sh-p2wpkh`, `p2wpkh` or `p2tr`; outputs also `p2sh` and `p2wsh`
+    pub script_type: Option<String>,
+    pub address: Option<String>,
+    #[serde(default = "default_count")]
+    pub count: u64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TxEstimateRequest {
+    pub inputs: Vec<TxShapeEntry>,
+    pub outputs: Vec<TxShapeEntry>,
+    /// sat/vB; when set the response includes the fee
+    pub fee_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TxEstimateResponse {
+    pub weight: u64,
+    pub vsize: u64,
+    pub fee_rate: Option<f64>,
+    /// Fee in satoshis at `feeRate`, rounded up
+    pub fee: Option<u64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/tx/estimate",
+    request_body = TxEstimateRequest,
+    responses(
+        (status = 200, description = "Expected weight, vsize and fee", body = TxEstimateResponse),
+        (status = 400, description = "Unknown script type, invalid address or empty side")
+    ),
+    tag = "chain"
+)]
+pub async fn estimate_transaction_size(
+    Json(request): Json<TxEstimateRequest>,
+) -> Result<Json<TxEstimateResponse>, ApiError> {
+    crate::server::tx_size::estimate_tx(&request)
+        .map(Json)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))
+}
@@ -25,6 +25,10 @@ pub struct AddressResponse {
 #[derive(Serialize, ToSchema)]
 pub struct PublicKeyResponse {
     pub xpub: String,
+    /// Last eight characters of the xpub, for comparing with the device screen
+    pub check_code: String,
+    /// The device displayed the xpub for the user to compare
+    pub shown_on_device: bool,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -137,6 +141,7 @@ impl IntoResponse for ApiError {
             error: match self.status {
                 StatusCode::UNPROCESSABLE_ENTITY => "unprocessable_entity",
                 StatusCode::NOT_FOUND => "not_found",
+                StatusCode::CONFLICT => "conflict",
                 StatusCode::INTERNAL_SERVER_ERROR => "internal_server_error",
                 _ => "error",
             }.to_string(),
@@ -0,0 +1,130 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use tracing::{info, error};
+use uuid::Uuid;
+
+use crate::server::ServerState;
+use super::common::ApiError;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDashboardTokenRequest {
+    /// Where the token will be used, e.g. "Office dashboard"
+    pub label: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardTokenInfo {
+    pub id: String,
+    pub label: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDashboardTokenResponse {
+    /// Bearer token; shown only once
+    pub token: String,
+    #[serde(flatten)]
+    pub info: DashboardTokenInfo,
+}
+
+impl From<crate::server::cache::device_cache::DashboardToken> for DashboardTokenInfo {
+    fn from(token: crate::server::cache::device_cache::DashboardToken) -> Self {
+        Self {
+            id: token.id,
+            label: token.label,
+            created_at: token.created_at,
+            last_used_at: token.last_used_at,
+            revoked_at: token.revoked_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/dashboard-tokens",
+    request_body = CreateDashboardTokenRequest,
+    responses(
+        (status = 200, description = "Read-only token created", body = CreateDashboardTokenResponse),
+        (status = 400, description = "Missing label")
+    ),
+    tag = "auth"
+)]
+pub async fn create_dashboard_token(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<CreateDashboardTokenRequest>,
+) -> Result<Json<CreateDashboardTokenResponse>, ApiError> {
+    let label = request.label.trim();
+    if label.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "label is required"));
+    }
+
+    let token = crate::server::dashboard_token::generate_dashboard_token();
+    let hash = crate::server::dashboard_token::hash_dashboard_token(&token);
+    let id = Uuid::new_v4().to_string();
+
+    match state.cache.create_dashboard_token(&id, &hash, label).await {
+        Ok(created) => {
+            info!("Created read-only dashboard token for {}", label);
+            Ok(Json(CreateDashboardTokenResponse { token, info: created.into() }))
+        }
+        Err(e) => {
+            error!("Failed to create dashboard token: {}", e);
+            Err(ApiError::internal_error(e.to_string()))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/dashboard-tokens",
+    responses(
+        (status = 200, description = "Dashboard tokens (without secrets)", body = [DashboardTokenInfo])
+    ),
+    tag = "auth"
+)]
+pub async fn list_dashboard_tokens(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<Vec<DashboardTokenInfo>>, ApiError> {
+    match state.cache.list_dashboard_tokens().await {
+        Ok(tokens) => Ok(Json(tokens.into_iter().map(Into::into).collect())),
+        Err(e) => {
+            error!("Failed to list dashboard tokens: {}", e);
+            Err(ApiError::internal_error(e.to_string()))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v2/dashboard-tokens/{id}",
+    params(("id" = String, Path, description = "Token id")),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 404, description = "No active token with this id")
+    ),
+    tag = "auth"
+)]
+pub async fn revoke_dashboard_token(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.cache.revoke_dashboard_token(&id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::not_found(format!("No active dashboard token {}", id))),
+        Err(e) => {
+            error!("Failed to revoke dashboard token: {}", e);
+            Err(ApiError::internal_error(e.to_string()))
+        }
+    }
+}
@@ -8,7 +8,11 @@ use serde::{Serialize, Deserialize};
 use utoipa::ToSchema;
 use tracing::{info, error};
 
+use crate::server::cache::DeviceCache;
 use crate::server::ServerState;
+use crate::transport::session;
+use crate::transport::DeviceSession;
+use super::common::ApiError;
 
 // Debug structures
 #[derive(Serialize, ToSchema)]
@@ -42,18 +46,18 @@ pub struct DebugFillConfig {
     tag = "debug"
 )]
 pub async fn debug_link_state(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
 ) -> Result<Json<DebugLinkState>, StatusCode> {
     info!("Debug link state request");
     
-    match crate::server::debug_link_state_impl().await {
+    match crate::server::debug_link_state_impl(state).await {
         Ok(state) => {
             info!("Retrieved debug link state");
             Ok(Json(state))
         }
         Err(e) => {
             error!("Failed to get debug link state: {}", e);
-            if e.to_string().contains("No KeepKey device found") {
+            if e.to_string().contains("No KeepKey device found") || e.to_string().contains("DEBUG_LINK not available") {
                 Err(StatusCode::NOT_FOUND)
             } else {
                 Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -74,23 +78,82 @@ pub async fn debug_link_state(
     tag = "debug"
 )]
 pub async fn debug_fill_config(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
     Json(request): Json<DebugFillConfig>,
 ) -> Result<StatusCode, StatusCode> {
     info!("Debug fill config request");
     
-    match crate::server::debug_fill_config_impl(request).await {
+    match crate::server::debug_fill_config_impl(state, request).await {
         Ok(_) => {
             info!("Config filled successfully");
             Ok(StatusCode::OK)
         }
         Err(e) => {
             error!("Failed to fill config: {}", e);
-            if e.to_string().contains("No KeepKey device found") {
+            if e.to_string().contains("No KeepKey device found") || e.to_string().contains("DEBUG_LINK not available") {
                 Err(StatusCode::NOT_FOUND)
             } else {
                 Err(StatusCode::INTERNAL_SERVER_ERROR)
             }
         }
     }
-} 
\ No newline at end of file
+} 
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRecordingStart {
+    /// What is about to be attempted, e.g. "sign 3-input tx"
+    pub operation: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRecordingSaved {
+    /// Where the recording was written; attach it to a support bundle
+    pub path: String,
+    pub session: DeviceSession,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/debug/recording",
+    request_body = SessionRecordingStart,
+    responses(
+        (status = 204, description = "Device traffic is being recorded"),
+        (status = 409, description = "A recording is already running")
+    ),
+    tag = "debug"
+)]
+pub async fn start_session_recording(
+    Json(request): Json<SessionRecordingStart>,
+) -> Result<StatusCode, ApiError> {
+    if session::start_recording(request.operation) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(StatusCode::CONFLICT, "A device session recording is already running"))
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v2/debug/recording",
+    responses(
+        (status = 200, description = "Recording stopped and saved", body = SessionRecordingSaved),
+        (status = 404, description = "No recording running")
+    ),
+    tag = "debug"
+)]
+pub async fn stop_session_recording() -> Result<Json<SessionRecordingSaved>, ApiError> {
+    let session = session::stop_recording().ok_or_else(|| ApiError::not_found("No device session recording is running"))?;
+
+    let file_name = format!("session-{}.json", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"));
+    let path = DeviceCache::get_cache_dir()
+        .map(|dir| dir.join("recordings").join(file_name))
+        .and_then(|path| session.save(&path).map(|_| path))
+        .map_err(|e| {
+            error!("Failed to save device session recording: {}", e);
+            ApiError::internal_error(format!("Failed to save device session recording: {}", e))
+        })?;
+    info!("Device session recording saved to {}", path.display());
+
+    Ok(Json(SessionRecordingSaved { path: path.display().to_string(), session }))
+}
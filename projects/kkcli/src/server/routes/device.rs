@@ -1,5 +1,5 @@
 use axum::{
-    extract::State,
+    extract::{Path, State},
     http::StatusCode,
     Json,
 };
@@ -9,6 +9,8 @@ use utoipa::ToSchema;
 use tracing::{info, error};
 
 use crate::server::ServerState;
+use crate::server::wallet_locks::{HeldOperation, WalletBusy};
+use super::common::ApiError;
 
 #[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -262,4 +264,256 @@ pub async fn get_features_sdk_handler(
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
-} 
\ No newline at end of file
+} 
+#[derive(Deserialize, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SelftestRequest {
+    /// Skip steps that need a button press on the device
+    pub no_button: Option<bool>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/device/{id}/selftest",
+    params(
+        ("id" = String, Path, description = "Device ID")
+    ),
+    request_body = SelftestRequest,
+    responses(
+        (status = 200, description = "Self-test report with per-step results", body = crate::cli::system::SelftestReport),
+        (status = 404, description = "No KeepKey device found"),
+        (status = 408, description = "Self-test timed out"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "device"
+)]
+pub async fn device_selftest(
+    State(state): State<Arc<ServerState>>,
+    Path(device_id): Path<String>,
+    request: Option<Json<SelftestRequest>>,
+) -> Result<Json<crate::cli::system::SelftestReport>, StatusCode> {
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+
+    match crate::server::device_selftest_impl(state, device_id, request).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Self-test failed to run: {}", e);
+            let msg = e.to_string();
+            if msg.contains("No KeepKey device found") {
+                Err(StatusCode::NOT_FOUND)
+            } else if msg.contains("timed out") {
+                Err(StatusCode::REQUEST_TIMEOUT)
+            } else {
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgetDeviceResponse {
+    pub device_id: String,
+    /// Cached receive/change addresses removed
+    pub addresses: usize,
+    pub xpubs: usize,
+    pub balances: usize,
+    /// Broadcast transactions removed from the local history
+    pub transactions: usize,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v2/device/{id}",
+    params(
+        ("id" = String, Path, description = "Device ID")
+    ),
+    responses(
+        (status = 200, description = "All cached data for the device was removed", body = ForgetDeviceResponse),
+        (status = 404, description = "Nothing is cached for this device"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "device"
+)]
+pub async fn forget_device(
+    State(state): State<Arc<ServerState>>,
+    Path(device_id): Path<String>,
+) -> Result<Json<ForgetDeviceResponse>, StatusCode> {
+    match crate::server::forget_device_impl(&state, &device_id).await {
+        Ok(Some(response)) => {
+            info!(
+                "🧹 Forgot device {}: {} address(es), {} xpub(s), {} balance(s), {} transaction(s)",
+                device_id, response.addresses, response.xpubs, response.balances, response.transactions
+            );
+            Ok(Json(response))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to forget device {}: {}", device_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/frontload",
+    request_body = crate::server::cache::FrontloadScope,
+    responses(
+        (status = 200, description = "Accounts in scope are cached", body = crate::server::cache::FrontloadReport),
+        (status = 400, description = "Invalid scope"),
+        (status = 404, description = "No KeepKey device found"),
+        (status = 409, description = "Blocked by another wallet operation"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "device"
+)]
+pub async fn frontload_device(
+    State(state): State<Arc<ServerState>>,
+    request: Option<Json<crate::server::cache::FrontloadScope>>,
+) -> Result<Json<crate::server::cache::FrontloadReport>, ApiError> {
+    let scope = request.map(|Json(scope)| scope).unwrap_or_default();
+    if let Err(e) = scope.validate() {
+        error!("Rejected frontload scope: {}", e);
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, e.to_string()));
+    }
+
+    match crate::server::frontload_impl(&state, scope).await {
+        Ok(report) => {
+            info!(
+                "🔄 Frontloaded {} path(s) for {}: {} fetched from device in {}ms",
+                report.paths, report.device_id, report.populated, report.elapsed_ms
+            );
+            Ok(Json(report))
+        }
+        Err(e) => {
+            error!("Frontload failed: {}", e);
+            Err(device_operation_error(e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/device/{id}/accounts",
+    params(("id" = String, Path, description = "Device id")),
+    responses(
+        (status = 200, description = "Accounts gap-limit discovery scanned, used or not", body = Vec<crate::server::cache::DiscoveredAccount>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "device"
+)]
+pub async fn discovered_accounts(
+    State(state): State<Arc<ServerState>>,
+    Path(device_id): Path<String>,
+) -> Result<Json<Vec<crate::server::cache::DiscoveredAccount>>, StatusCode> {
+    state.cache.get_discovered_accounts(&device_id).await.map(Json).map_err(|e| {
+        error!("Failed to read discovered accounts for {}: {}", device_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/xpubs/sync",
+    responses(
+        (status = 200, description = "Xpubs derived by the device compared with the cache; nothing written yet", body = crate::server::xpub_sync::XpubSyncPlan),
+        (status = 404, description = "No KeepKey device found"),
+        (status = 409, description = "Blocked by another wallet operation"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "device"
+)]
+pub async fn plan_xpub_sync(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<crate::server::xpub_sync::XpubSyncPlan>, ApiError> {
+    match crate::server::xpub_sync::plan_xpub_sync(&state).await {
+        Ok(plan) => Ok(Json(plan)),
+        Err(e) => {
+            error!("Xpub sync failed: {}", e);
+            Err(device_operation_error(e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/xpubs/sync/{id}/apply",
+    params(
+        ("id" = String, Path, description = "Plan ID from POST /api/v2/xpubs/sync")
+    ),
+    responses(
+        (status = 200, description = "The plan's changes were written to the cache", body = crate::server::xpub_sync::XpubSyncResult),
+        (status = 404, description = "No such plan, or it expired"),
+        (status = 409, description = "The cache changed since the plan was made, or another wallet operation is running"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "device"
+)]
+pub async fn apply_xpub_sync(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> Result<Json<crate::server::xpub_sync::XpubSyncResult>, ApiError> {
+    match crate::server::xpub_sync::apply_xpub_sync(&state, &id).await {
+        Ok(Some(result)) => {
+            info!(
+                "🔁 Applied xpub sync {} for {}: {} added, {} changed, {} removed, {} address(es) dropped",
+                result.id, result.device_id, result.added, result.changed, result.removed, result.addresses_dropped
+            );
+            Ok(Json(result))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("No xpub sync plan {}", id))),
+        Err(e) => {
+            error!("Failed to apply xpub sync {}: {}", id, e);
+            if e.to_string().contains("changed since") {
+                Err(ApiError::new(StatusCode::CONFLICT, e.to_string()))
+            } else {
+                Err(device_operation_error(e))
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v2/xpubs/sync/{id}",
+    params(
+        ("id" = String, Path, description = "Plan ID from POST /api/v2/xpubs/sync")
+    ),
+    responses(
+        (status = 204, description = "Plan discarded without writing anything"),
+        (status = 404, description = "No such plan")
+    ),
+    tag = "device"
+)]
+pub async fn discard_xpub_sync(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> StatusCode {
+    if state.xpub_sync.discard(&id).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/operations",
+    responses(
+        (status = 200, description = "Wallet operations running now; conflicting requests wait for these", body = Vec<HeldOperation>)
+    ),
+    tag = "device"
+)]
+pub async fn running_operations(State(state): State<Arc<ServerState>>) -> Json<Vec<HeldOperation>> {
+    Json(state.wallet_locks.running())
+}
+
+/// 409 naming the blocking operation, 404 without a device, 500 otherwise
+pub(crate) fn device_operation_error(e: anyhow::Error) -> ApiError {
+    match e.downcast::<WalletBusy>() {
+        Ok(busy) => busy.into(),
+        Err(e) if e.to_string().contains("No KeepKey device found") => ApiError::not_found(e.to_string()),
+        Err(e) => ApiError::internal_error(e.to_string()),
+    }
+}
@@ -1,15 +1,19 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
-    Json,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json, Router,
 };
 use std::sync::Arc;
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
 use utoipa::ToSchema;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 
 use crate::server::ServerState;
 
+/// Environment variable holding the key that grants the `manufacturing` scope.
+/// When unset, manufacturing endpoints reject every request.
+pub const MANUFACTURING_KEY_ENV: &str = "KKCLI_MANUFACTURING_KEY";
+
 // Manufacturing structures
 #[derive(Serialize, ToSchema)]
 pub struct ManufacturingHash {
@@ -21,23 +25,91 @@ pub struct ModelPrefix {
     pub prefix: String,
 }
 
+#[derive(Deserialize, Default, ToSchema)]
+pub struct ManufacturingHashRequest {
+    /// Flash address to start hashing at
+    pub address: Option<u32>,
+    /// Number of bytes to hash
+    pub length: Option<u32>,
+    /// Optional hex-encoded challenge mixed into the hash
+    pub challenge: Option<String>,
+}
+
+/// Check that the request carries the manufacturing key as a bearer token
+fn require_manufacturing_scope(headers: &HeaderMap) -> Result<(), StatusCode> {
+    let expected = match std::env::var(MANUFACTURING_KEY_ENV) {
+        Ok(key) if !key.is_empty() => key,
+        _ => {
+            warn!("Manufacturing request rejected: {} is not set", MANUFACTURING_KEY_ENV);
+            return Err(StatusCode::FORBIDDEN);
+        }
+    };
+
+    let provided = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    if provided == Some(expected.as_str()) {
+        Ok(())
+    } else {
+        warn!("Manufacturing request rejected: missing or invalid manufacturing key");
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Manufacturing routes; empty unless built with the `manufacturing` feature
+#[cfg(feature = "manufacturing")]
+pub fn manufacturing_router() -> Router<Arc<ServerState>> {
+    use axum::routing::{get, post};
+
+    Router::new()
+        .route("/system/manufacturing/get-hash", post(manufacturing_get_hash))
+        .route("/api/v1/manufacturing/get-hash", get(manufacturing_get_hash))
+        .route("/system/manufacturing/model-prefix", post(manufacturing_model_prefix))
+        .route("/api/v1/manufacturing/model-prefix", get(manufacturing_model_prefix))
+}
+
+#[cfg(not(feature = "manufacturing"))]
+pub fn manufacturing_router() -> Router<Arc<ServerState>> {
+    Router::new()
+}
+
 // Route handlers for Manufacturing
 #[utoipa::path(
     post,
     path = "/system/manufacturing/get-hash",
+    request_body = ManufacturingHashRequest,
     responses(
         (status = 200, description = "Manufacturing hash", body = ManufacturingHash),
+        (status = 400, description = "Missing or invalid flash range"),
+        (status = 403, description = "Manufacturing scope required"),
         (status = 404, description = "No KeepKey device found"),
         (status = 500, description = "Internal server error")
     ),
     tag = "manufacturing"
 )]
 pub async fn manufacturing_get_hash(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Query(query): Query<ManufacturingHashRequest>,
+    body: Option<Json<ManufacturingHashRequest>>,
 ) -> Result<Json<ManufacturingHash>, StatusCode> {
     info!("Manufacturing get hash request");
-    
-    match crate::server::manufacturing_get_hash_impl().await {
+    require_manufacturing_scope(&headers)?;
+
+    // JSON body wins over query parameters (POST vs GET clients)
+    let request = body.map(|Json(b)| b).unwrap_or(query);
+    let (address, length) = match (request.address, request.length) {
+        (Some(address), Some(length)) if length > 0 => (address, length),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let challenge = match request.challenge.as_deref() {
+        Some(c) => Some(hex::decode(c.trim_start_matches("0x")).map_err(|_| StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    match crate::server::manufacturing_get_hash_impl(state, address, length, challenge).await {
         Ok(hash) => {
             info!("Retrieved manufacturing hash");
             Ok(Json(ManufacturingHash { hash }))
@@ -58,17 +130,20 @@ pub async fn manufacturing_get_hash(
     path = "/system/manufacturing/model-prefix",
     responses(
         (status = 200, description = "Model prefix", body = ModelPrefix),
+        (status = 403, description = "Manufacturing scope required"),
         (status = 404, description = "No KeepKey device found"),
         (status = 500, description = "Internal server error")
     ),
     tag = "manufacturing"
 )]
 pub async fn manufacturing_model_prefix(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
 ) -> Result<Json<ModelPrefix>, StatusCode> {
     info!("Manufacturing model prefix request");
-    
-    match crate::server::manufacturing_model_prefix_impl().await {
+    require_manufacturing_scope(&headers)?;
+
+    match crate::server::manufacturing_model_prefix_impl(state).await {
         Ok(prefix) => {
             info!("Retrieved model prefix");
             Ok(Json(ModelPrefix { prefix }))
@@ -82,4 +157,4 @@ pub async fn manufacturing_model_prefix(
             }
         }
     }
-} 
\ No newline at end of file
+}
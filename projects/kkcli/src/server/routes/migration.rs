@@ -0,0 +1,153 @@
+use axum::{extract::State, http::{HeaderMap, StatusCode}, Json};
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use tracing::{info, error};
+
+use crate::server::amounts::{amount_format, AmountFormat, AmountUnit};
+use crate::server::ServerState;
+use super::bitcoin::BitcoinSignRequest;
+use super::common::ApiError;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptTypeBalance {
+    /// `p2pkh`, `p2sh-p2wpkh`, `p2wpkh` or `p2tr`
+    pub script_type: String,
+    /// `legacy`, `nested-segwit`, `native-segwit` or `taproot`
+    pub label: String,
+    pub addresses: usize,
+    pub utxos: usize,
+    pub balance_sats: u64,
+    /// Balance in BTC
+    pub balance: String,
+    /// `balanceSats` in the response's amount format
+    pub formatted_balance: String,
+    pub formatted_unit: AmountUnit,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptTypeBalancesResponse {
+    pub device_id: String,
+    pub total_sats: u64,
+    pub formatted_total: String,
+    pub script_types: Vec<ScriptTypeBalance>,
+    pub amount_format: AmountFormat,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationRequest {
+    /// Script type to move funds to: `p2wpkh` (default) or `p2tr`
+    pub target_script_type: Option<String>,
+    /// Fee rate in sat/vB; defaults to the backend's 6-block estimate
+    pub fee_rate: Option<f64>,
+    /// Build one transaction per account so accounts are never linked (default true)
+    pub per_account: Option<bool>,
+    /// Also keep each source script type in its own transaction (default false)
+    pub per_script_type: Option<bool>,
+    /// Most inputs per transaction (default 100)
+    pub max_inputs: Option<usize>,
+    /// Destination path; defaults to an unused cached receive address of the target type in the same account
+    pub target_address_n: Option<Vec<u32>>,
+}
+
+/// One consolidation transaction, ready for `POST /api/v1/bitcoin/tx`
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationTransaction {
+    /// Account the inputs come from, as `m/purpose'/coin'/account'`
+    pub source_accounts: Vec<String>,
+    pub source_script_types: Vec<String>,
+    pub input_count: usize,
+    pub input_sats: u64,
+    pub fee_sats: u64,
+    pub output_sats: u64,
+    pub formatted_input: String,
+    pub formatted_fee: String,
+    pub formatted_output: String,
+    pub formatted_unit: AmountUnit,
+    pub estimated_vsize: u64,
+    pub destination_path: Vec<u32>,
+    pub sign_request: BitcoinSignRequest,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationPlan {
+    pub device_id: String,
+    pub target_script_type: String,
+    pub fee_rate: f64,
+    pub transactions: Vec<MigrationTransaction>,
+    /// UTXOs left behind because spending them costs more than they hold
+    pub skipped_dust_utxos: usize,
+    pub skipped_dust_sats: u64,
+    pub formatted_skipped_dust: String,
+    pub warnings: Vec<String>,
+    pub amount_format: AmountFormat,
+}
+
+fn map_migration_error(e: anyhow::Error) -> ApiError {
+    error!("Script type migration failed: {}", e);
+    let message = e.to_string();
+    if message.starts_with("No device") {
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, message)
+    } else if message.starts_with("Invalid") || message.starts_with("No cached") {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    } else if message.starts_with("Chain backend") {
+        ApiError::new(StatusCode::BAD_GATEWAY, message)
+    } else {
+        ApiError::internal_error(message)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/balances/script-types",
+    responses(
+        (status = 200, description = "Bitcoin balance of the cached addresses, per script type", body = ScriptTypeBalancesResponse),
+        (status = 502, description = "Chain backend unavailable"),
+        (status = 503, description = "No device available")
+    ),
+    tag = "portfolio"
+)]
+pub async fn get_script_type_balances(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+) -> Result<Json<ScriptTypeBalancesResponse>, ApiError> {
+    let format = amount_format(&state.cache, &headers).await;
+    let balances = crate::server::script_type_balances_impl(&state, &format)
+        .await
+        .map_err(map_migration_error)?;
+    info!("Balances by script type for {}: {} sats", balances.device_id, balances.total_sats);
+    Ok(Json(balances))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/migrate/plan",
+    request_body = MigrationRequest,
+    responses(
+        (status = 200, description = "Unsigned consolidation transactions moving funds to the target script type", body = MigrationPlan),
+        (status = 400, description = "Invalid options or no destination address"),
+        (status = 502, description = "Chain backend unavailable"),
+        (status = 503, description = "No device available")
+    ),
+    tag = "portfolio"
+)]
+pub async fn plan_script_type_migration(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<MigrationRequest>,
+) -> Result<Json<MigrationPlan>, ApiError> {
+    let format = amount_format(&state.cache, &headers).await;
+    let plan = crate::server::migration_plan_impl(&state, request, &format)
+        .await
+        .map_err(map_migration_error)?;
+    info!(
+        "Migration plan to {} for {}: {} transaction(s) at {} sat/vB",
+        plan.target_script_type, plan.device_id, plan.transactions.len(), plan.fee_rate
+    );
+    Ok(Json(plan))
+}
@@ -6,10 +6,23 @@ pub mod system;
 pub mod system_management;
 pub mod addresses;
 pub mod bitcoin;
+pub mod approvals;
+pub mod chain;
+pub mod sweep;
+pub mod send_many;
+pub mod dashboard;
+pub mod portfolio;
+pub mod privacy;
+pub mod migration;
+pub mod rpc;
 pub mod debug;
 pub mod manufacturing;
+pub mod policy;
+pub mod response_signing;
+pub mod pin;
 pub mod raw;
 pub mod websocket;
+pub mod backup;
 
 
 
@@ -21,9 +34,22 @@ pub use system::*;
 pub use system_management::*;
 pub use addresses::*;
 pub use bitcoin::*;
+pub use approvals::*;
+pub use chain::*;
+pub use sweep::*;
+pub use send_many::*;
+pub use dashboard::*;
+pub use portfolio::*;
+pub use privacy::*;
+pub use migration::*;
+pub use rpc::*;
 pub use debug::*;
 pub use manufacturing::*;
+pub use policy::*;
+pub use response_signing::*;
+pub use pin::*;
 pub use raw::*;
 pub use websocket::*;
+pub use backup::*;
 
  
\ No newline at end of file
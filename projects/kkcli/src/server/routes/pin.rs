@@ -0,0 +1,93 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use std::sync::Arc;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use tracing::{error, info};
+
+use crate::server::pin_entry::{check_pin_token, remote_pin_enabled, PinRequest, PinSubmitError};
+use crate::server::ServerState;
+use super::common::ApiError;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PinSubmission {
+    /// Positions clicked on the 3x3 grid, 1 (bottom left) to 9 (top right),
+    /// as mapped by the matrix the device is showing
+    pub positions: String,
+}
+
+async fn require_pin_token(state: &ServerState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let internal = |e: anyhow::Error| {
+        error!("Failed to check PIN entry token: {}", e);
+        ApiError::internal_error("Failed to check PIN entry token")
+    };
+    if !remote_pin_enabled(&state.cache).await.map_err(internal)? {
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "Remote PIN entry is disabled (config pin_entry)"));
+    }
+    let token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match token {
+        Some(token) if check_pin_token(&state.cache, token).await.map_err(internal)? => Ok(()),
+        _ => Err(ApiError::new(StatusCode::UNAUTHORIZED, "A valid PIN entry token is required")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/pin",
+    responses(
+        (status = 200, description = "PIN matrix the device is waiting on", body = PinRequest),
+        (status = 401, description = "Missing or invalid PIN entry token"),
+        (status = 403, description = "Remote PIN entry is disabled"),
+        (status = 404, description = "No PIN entry pending")
+    ),
+    tag = "pin"
+)]
+pub async fn get_pending_pin(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+) -> Result<Json<PinRequest>, ApiError> {
+    require_pin_token(&state, &headers).await?;
+    state
+        .pin_entry
+        .active()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("No PIN entry pending"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/pin/{id}",
+    params(("id" = String, Path, description = "PIN request id")),
+    request_body = PinSubmission,
+    responses(
+        (status = 204, description = "Positions passed to the device"),
+        (status = 400, description = "Positions are not 1-9 digits"),
+        (status = 401, description = "Missing or invalid PIN entry token"),
+        (status = 403, description = "Remote PIN entry is disabled"),
+        (status = 404, description = "No pending PIN request with this id")
+    ),
+    tag = "pin"
+)]
+pub async fn submit_pin(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(submission): Json<PinSubmission>,
+) -> Result<StatusCode, ApiError> {
+    require_pin_token(&state, &headers).await?;
+    match state.pin_entry.submit(&id, &submission.positions) {
+        Ok(()) => {
+            info!("PIN positions submitted for {}", id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(PinSubmitError::Invalid(message)) => Err(ApiError::new(StatusCode::BAD_REQUEST, message)),
+        Err(PinSubmitError::NotFound) => Err(ApiError::not_found(format!("No pending PIN request {}", id))),
+    }
+}
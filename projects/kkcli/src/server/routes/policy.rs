@@ -0,0 +1,68 @@
+use axum::{
+    extract::State,
+    Json,
+};
+use std::sync::Arc;
+use serde::Serialize;
+use utoipa::ToSchema;
+use tracing::{info, warn};
+
+use crate::server::button_policy::ButtonPolicy;
+use crate::server::ServerState;
+use super::common::ApiError;
+use super::device::Policy;
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyResponse {
+    /// Policies enforced by the firmware; `None` when no device is connected.
+    /// Change them with `POST /system/info/apply-policy`.
+    pub firmware: Option<Vec<Policy>>,
+    /// Confirmation rules enforced by this server
+    pub host: ButtonPolicy,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/policies",
+    responses(
+        (status = 200, description = "Firmware and host confirmation policies", body = PolicyResponse)
+    ),
+    tag = "system"
+)]
+pub async fn get_policies(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<PolicyResponse>, ApiError> {
+    let host = ButtonPolicy::load(&state.cache)
+        .await
+        .map_err(|e| ApiError::internal_error(e.to_string()))?;
+    let firmware = match crate::server::firmware_policies_impl(&state).await {
+        Ok(policies) => Some(policies),
+        Err(e) => {
+            warn!("Firmware policies unavailable: {}", e);
+            None
+        }
+    };
+    Ok(Json(PolicyResponse { firmware, host }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v2/policies",
+    request_body = ButtonPolicy,
+    responses(
+        (status = 200, description = "Host policy updated", body = PolicyResponse)
+    ),
+    tag = "system"
+)]
+pub async fn put_policies(
+    State(state): State<Arc<ServerState>>,
+    Json(policy): Json<ButtonPolicy>,
+) -> Result<Json<PolicyResponse>, ApiError> {
+    policy
+        .save(&state.cache)
+        .await
+        .map_err(|e| ApiError::internal_error(e.to_string()))?;
+    info!("Updated host confirmation policy: {:?}", policy);
+    get_policies(State(state)).await
+}
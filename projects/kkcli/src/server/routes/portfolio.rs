@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
+use tracing::{info, error};
+
+use crate::server::amounts::{amount_format, AmountFormat, AmountUnit};
+use crate::server::ServerState;
+use super::common::ApiError;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PortfolioHistoryQuery {
+    /// First day to include (`YYYY-MM-DD`, UTC); defaults to one year ago
+    pub from: Option<String>,
+    /// Last day to include (`YYYY-MM-DD`, UTC); defaults to today
+    pub to: Option<String>,
+    /// `json` (default) or `csv`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotAsset {
+    pub caip: String,
+    pub pubkey: String,
+    pub symbol: Option<String>,
+    pub balance: String,
+    /// Exact balance in satoshis, when `balance` is a BTC amount
+    pub balance_sats: Option<i64>,
+    /// `balance` in the response's amount format
+    pub formatted_balance: Option<String>,
+    pub formatted_unit: AmountUnit,
+    pub price_usd: String,
+    pub value_usd: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DailySnapshot {
+    /// `YYYY-MM-DD` (UTC)
+    pub date: String,
+    pub total_value_usd: String,
+    pub assets: Vec<SnapshotAsset>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RealizedTransaction {
+    pub txid: String,
+    pub caip: String,
+    /// `receive`, `send` or `self` (only the fee left the wallet)
+    pub kind: String,
+    /// Net change for the wallet in BTC, negative for sends, excluding the fee
+    pub amount: String,
+    /// Fee in BTC
+    pub fee: String,
+    pub amount_sats: i64,
+    pub fee_sats: i64,
+    pub formatted_amount: String,
+    pub formatted_fee: String,
+    pub formatted_unit: AmountUnit,
+    pub block_height: Option<u64>,
+    pub block_time: i64,
+    /// USD price on the day the transaction confirmed, if a snapshot exists
+    pub price_usd: Option<String>,
+    pub value_usd: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PortfolioHistoryResponse {
+    pub device_id: String,
+    pub from: String,
+    pub to: String,
+    pub snapshots: Vec<DailySnapshot>,
+    pub transactions: Vec<RealizedTransaction>,
+    pub amount_format: AmountFormat,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/portfolio/history",
+    params(PortfolioHistoryQuery),
+    responses(
+        (status = 200, description = "Daily snapshots and realized transactions (JSON, or CSV with format=csv)", body = PortfolioHistoryResponse),
+        (status = 400, description = "Invalid date range or format"),
+        (status = 503, description = "No device available")
+    ),
+    tag = "portfolio"
+)]
+pub async fn get_portfolio_history(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<PortfolioHistoryQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let csv = match query.format.as_deref().unwrap_or("json") {
+        "json" => false,
+        "csv" => true,
+        other => return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Unsupported format '{}'", other))),
+    };
+
+    let format = amount_format(&state.cache, &headers).await;
+    let history = crate::server::portfolio_history_impl(&state, query.from.as_deref(), query.to.as_deref(), &format)
+        .await
+        .map_err(|e| {
+            error!("Failed to get portfolio history: {}", e);
+            let message = e.to_string();
+            if message.starts_with("No device") {
+                ApiError::new(StatusCode::SERVICE_UNAVAILABLE, message)
+            } else if message.starts_with("Invalid date") {
+                ApiError::new(StatusCode::BAD_REQUEST, message)
+            } else {
+                ApiError::internal_error(message)
+            }
+        })?;
+
+    info!(
+        "Portfolio history {}..{}: {} snapshot(s), {} transaction(s)",
+        history.from, history.to, history.snapshots.len(), history.transactions.len()
+    );
+
+    if csv {
+        let filename = format!("keepkey-transactions-{}-{}.csv", history.from, history.to);
+        Ok((
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+            ],
+            crate::server::portfolio_history_csv(&history),
+        )
+            .into_response())
+    } else {
+        Ok(Json(history).into_response())
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{extract::State, http::StatusCode, Json};
+use std::sync::Arc;
+use serde::Serialize;
+use utoipa::ToSchema;
+use tracing::{info, error};
+
+use crate::server::ServerState;
+use super::common::ApiError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyIssueKind {
+    /// The same address received or spent in more than one transaction
+    AddressReuse,
+    /// One transaction spent coins from several accounts, linking them on-chain
+    LinkedAccounts,
+    /// A round payment amount next to a non-round output gives away which output is change
+    RoundNumberChange,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacyIssue {
+    pub kind: PrivacyIssueKind,
+    pub description: String,
+    /// Transactions the issue was found in
+    pub txids: Vec<String>,
+    /// Wallet addresses involved
+    pub addresses: Vec<String>,
+    /// Accounts involved, as `m/purpose'/coin'/account'`
+    pub accounts: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivacyReport {
+    pub device_id: String,
+    /// 0 (everything linkable) to 100 (nothing found)
+    pub score: u8,
+    pub transactions_analyzed: usize,
+    pub addresses_analyzed: usize,
+    pub issues: Vec<PrivacyIssue>,
+    pub suggestions: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/privacy/report",
+    responses(
+        (status = 200, description = "Privacy analysis of the cached transaction history", body = PrivacyReport),
+        (status = 503, description = "No device available")
+    ),
+    tag = "portfolio"
+)]
+pub async fn get_privacy_report(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<PrivacyReport>, ApiError> {
+    let report = crate::server::privacy_report_impl(&state)
+        .await
+        .map_err(|e| {
+            error!("Failed to build privacy report: {}", e);
+            let message = e.to_string();
+            if message.starts_with("No device") {
+                ApiError::new(StatusCode::SERVICE_UNAVAILABLE, message)
+            } else {
+                ApiError::internal_error(message)
+            }
+        })?;
+
+    info!(
+        "Privacy report for {}: score {} from {} transaction(s), {} issue(s)",
+        report.device_id, report.score, report.transactions_analyzed, report.issues.len()
+    );
+    Ok(Json(report))
+}
@@ -0,0 +1,50 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use std::sync::Arc;
+use serde::Serialize;
+use utoipa::ToSchema;
+use tracing::warn;
+
+use crate::server::response_signing::{ALGORITHM, MESSAGE_PREFIX, SIGNED_ENDPOINTS};
+use crate::server::ServerState;
+use super::common::ApiError;
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseSigningKey {
+    /// Compressed secp256k1 public key in hex; pin it and reject other keys
+    pub public_key: String,
+    pub algorithm: String,
+    /// First line of every signed message
+    pub message_prefix: String,
+    /// Endpoints that sign their response when sent `X-KeepKey-Sign: 1`
+    pub signed_endpoints: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/response-signing/key",
+    responses(
+        (status = 200, description = "Public key that signs address and xpub responses", body = ResponseSigningKey),
+        (status = 503, description = "Device not connected or locked")
+    ),
+    tag = "auth"
+)]
+pub async fn get_response_signing_key(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<ResponseSigningKey>, ApiError> {
+    let public_key = state.response_signer.public_key(&state).await.map_err(|e| {
+        warn!("🔏 Response signing key unavailable: {}", e);
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, e.to_string())
+    })?;
+
+    Ok(Json(ResponseSigningKey {
+        public_key: public_key.to_string(),
+        algorithm: ALGORITHM.to_string(),
+        message_prefix: MESSAGE_PREFIX.to_string(),
+        signed_endpoints: SIGNED_ENDPOINTS.iter().map(|(method, path)| format!("{} {}", method, path)).collect(),
+    }))
+}
@@ -0,0 +1,95 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::server::{ServerState, RpcError, RPC_INVALID_REQUEST, RPC_PARSE_ERROR};
+use super::chain::api_key_from_headers;
+
+/// Response object in the envelope of the caller's protocol version
+fn envelope(v2: bool, id: Value, outcome: Result<Value, RpcError>) -> Value {
+    match (v2, outcome) {
+        (true, Ok(result)) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        (true, Err(e)) => json!({ "jsonrpc": "2.0", "error": e.to_json(), "id": id }),
+        // Bitcoin Core's 1.0 style always carries both fields
+        (false, Ok(result)) => json!({ "result": result, "error": null, "id": id }),
+        (false, Err(e)) => json!({ "result": null, "error": e.to_json(), "id": id }),
+    }
+}
+
+/// Handle one request object; `None` for JSON-RPC 2.0 notifications
+async fn handle_call(state: &ServerState, call: &Value, api_key: Option<&str>) -> Option<Value> {
+    let v2 = call.get("jsonrpc").and_then(Value::as_str) == Some("2.0");
+    let id = call.get("id").cloned();
+    let method = match call.get("method").and_then(Value::as_str) {
+        Some(method) => method,
+        None => {
+            return Some(envelope(
+                v2,
+                id.unwrap_or(Value::Null),
+                Err(RpcError::new(RPC_INVALID_REQUEST, "Missing method")),
+            ))
+        }
+    };
+    let params = call.get("params").cloned().unwrap_or_else(|| json!([]));
+
+    let outcome = crate::server::rpc_call_impl(state, method, &params, api_key).await;
+    if let Err(e) = &outcome {
+        warn!("RPC {} failed: {} ({})", method, e.message, e.code);
+    }
+    match id {
+        None if v2 => None,
+        id => Some(envelope(v2, id.unwrap_or(Value::Null), outcome)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/rpc",
+    request_body(content = String, description = "JSON-RPC request or batch: getnewaddress, listunspent, sendtoaddress, signmessage", content_type = "application/json"),
+    responses(
+        (status = 200, description = "JSON-RPC response; errors use Bitcoin Core's codes"),
+        (status = 204, description = "Only notifications were sent")
+    ),
+    tag = "bitcoin"
+)]
+pub async fn json_rpc(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let api_key = api_key_from_headers(&headers);
+    let request: Value = match serde_json::from_str(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            let error = RpcError::new(RPC_PARSE_ERROR, format!("Parse error: {}", e));
+            return Json(envelope(true, Value::Null, Err(error))).into_response();
+        }
+    };
+
+    let response = match &request {
+        Value::Array(calls) if calls.is_empty() => Some(envelope(
+            true,
+            Value::Null,
+            Err(RpcError::new(RPC_INVALID_REQUEST, "Empty batch")),
+        )),
+        Value::Array(calls) => {
+            let mut responses = Vec::new();
+            for call in calls {
+                responses.extend(handle_call(&state, call, api_key).await);
+            }
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        call => handle_call(&state, call, api_key).await,
+    };
+
+    match response {
+        Some(response) => Json(response).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
@@ -0,0 +1,190 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use tracing::{info, error};
+
+use crate::server::amounts::{amount_format, AmountFormat, AmountUnit};
+use crate::server::ServerState;
+use super::chain::{api_key_from_headers, BroadcastResponse};
+use super::common::ApiError;
+
+/// One recipient of a batched send
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SendManyOutput {
+    pub address: String,
+    pub amount_sats: u64,
+    /// Shown in the preview and the approval prompt, e.g. who is being paid; never broadcast
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SendManyRequest {
+    /// Destinations in transaction order, at most 100
+    pub outputs: Vec<SendManyOutput>,
+    /// sat/vB; defaults to the backend estimate for `confTarget`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_rate: Option<f64>,
+    /// Blocks to confirm within when no fee rate is given (default 6)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conf_target: Option<u32>,
+    /// Also spend unconfirmed outputs (default false)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_unconfirmed: Option<bool>,
+    /// Script type of the change address: `p2wpkh` (default), `p2sh-p2wpkh`, `p2pkh` or `p2tr`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_script_type: Option<String>,
+    /// Broadcast after signing (default true); otherwise only the signed transaction is returned.
+    /// Ignored by the preview.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broadcast: Option<bool>,
+}
+
+/// One row of the preview, in transaction order
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SendManyPreviewOutput {
+    /// Output index in the transaction
+    pub index: usize,
+    pub label: Option<String>,
+    pub address: String,
+    pub amount_sats: u64,
+    /// `amountSats` in the response's amount format
+    pub formatted_amount: String,
+}
+
+/// Change paid back to the wallet, after the destinations
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SendManyChange {
+    /// Derivation path, e.g. `m/84'/0'/0'/1/3`
+    pub path: String,
+    pub address_n: Vec<u32>,
+    pub script_type: String,
+    /// Known once the address is derived for signing; absent in a preview
+    pub address: Option<String>,
+    pub amount_sats: u64,
+    pub formatted_amount: String,
+}
+
+/// What a batch spends, pays and costs
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SendManyPreview {
+    pub device_id: String,
+    pub outputs: Vec<SendManyPreviewOutput>,
+    /// Absent when the remainder was too small for an output and went to the fee
+    pub change: Option<SendManyChange>,
+    /// Sum of `outputs`
+    pub total_sats: u64,
+    pub fee_sats: u64,
+    pub fee_rate: f64,
+    pub vsize: u64,
+    pub inputs: usize,
+    pub input_sats: u64,
+    pub formatted_total: String,
+    pub formatted_fee: String,
+    pub formatted_unit: AmountUnit,
+    /// Worth a second look before signing, e.g. an address paid twice
+    pub warnings: Vec<String>,
+    pub amount_format: AmountFormat,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SendManyResponse {
+    pub preview: SendManyPreview,
+    pub serialized_tx: String,
+    /// Set once the transaction is broadcast
+    pub txid: Option<String>,
+    pub broadcast: Option<BroadcastResponse>,
+}
+
+fn map_send_many_error(e: anyhow::Error) -> ApiError {
+    error!("Batched send failed: {}", e);
+    let message = e.to_string();
+    if message.starts_with("No device") {
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, message)
+    } else if message.starts_with("No KeepKey device found") {
+        ApiError::not_found(message)
+    } else if message.starts_with("Invalid") {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    } else if message.starts_with("Insufficient funds") {
+        ApiError::unprocessable_entity(message)
+    } else if message.starts_with("Signing request rejected") || message.starts_with("Policy violation") {
+        ApiError::new(StatusCode::FORBIDDEN, message)
+    } else if message.starts_with("Signing request was not approved") {
+        ApiError::new(StatusCode::REQUEST_TIMEOUT, message)
+    } else if message.starts_with("Blocked by") {
+        ApiError::new(StatusCode::CONFLICT, message)
+    } else if message.starts_with("Chain backend") {
+        ApiError::new(StatusCode::BAD_GATEWAY, message)
+    } else {
+        ApiError::internal_error(message)
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/send-many/preview",
+    request_body = SendManyRequest,
+    responses(
+        (status = 200, description = "Coins, fee and change a batched send would use, with each labelled destination", body = SendManyPreview),
+        (status = 400, description = "Invalid destination, amount, label or fee rate, or too many outputs"),
+        (status = 422, description = "Not enough spendable funds for the outputs and the fee"),
+        (status = 502, description = "Chain backend unavailable"),
+        (status = 503, description = "No device available")
+    ),
+    tag = "bitcoin"
+)]
+pub async fn send_many_preview(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<SendManyRequest>,
+) -> Result<Json<SendManyPreview>, ApiError> {
+    let format = amount_format(&state.cache, &headers).await;
+    let preview = crate::server::send_many_preview_impl(&state, request, &format)
+        .await
+        .map_err(map_send_many_error)?;
+    info!("Batched send preview: {} output(s), {} sats, {} sat fee", preview.outputs.len(), preview.total_sats, preview.fee_sats);
+    Ok(Json(preview))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/send-many",
+    request_body = SendManyRequest,
+    responses(
+        (status = 200, description = "Batched send signed and, unless disabled, broadcast", body = SendManyResponse),
+        (status = 400, description = "Invalid destination, amount, label or fee rate, or too many outputs"),
+        (status = 403, description = "Rejected in the remote approval prompt or by policy"),
+        (status = 408, description = "Remote approval timed out"),
+        (status = 409, description = "Signed, but not broadcast because it conflicts with a known spend; or blocked by another wallet operation"),
+        (status = 422, description = "Not enough spendable funds for the outputs and the fee"),
+        (status = 502, description = "Chain backend unavailable")
+    ),
+    tag = "bitcoin"
+)]
+pub async fn send_many(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<SendManyRequest>,
+) -> Result<Json<SendManyResponse>, ApiError> {
+    let format = amount_format(&state.cache, &headers).await;
+    let response = crate::server::send_many_impl(&state, request, &format, api_key_from_headers(&headers))
+        .await
+        .map_err(map_send_many_error)?;
+    if matches!(&response.broadcast, Some(broadcast) if !broadcast.broadcast) {
+        let details = serde_json::to_value(&response).unwrap_or_default();
+        return Err(ApiError::new(StatusCode::CONFLICT, "Batched send conflicts with an existing spend and was not broadcast")
+            .with_details(details));
+    }
+    Ok(Json(response))
+}
@@ -0,0 +1,206 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use tracing::{info, error};
+
+use crate::server::ServerState;
+use crate::server::wallet_locks::WalletOperation;
+use super::chain::{api_key_from_headers, BroadcastResponse};
+use super::common::ApiError;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaxSendRequest {
+    pub destination: String,
+    /// sat/vB; defaults to the backend estimate for `confTarget`
+    pub fee_rate: Option<f64>,
+    /// Blocks to confirm within when no fee rate is given (default 6)
+    pub conf_target: Option<u32>,
+    /// Also spend unconfirmed outputs (default false)
+    pub include_unconfirmed: Option<bool>,
+    /// Only spend the UTXOs of this cached address
+    pub source_address: Option<String>,
+    /// Only spend UTXOs at or below this path, e.g. `m/44'/0'/0'` for a whole
+    /// account. A full address path is derived on the device if not cached yet.
+    pub source_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaxSendResponse {
+    pub device_id: String,
+    pub destination: String,
+    /// Address or path the UTXOs were restricted to; absent for the whole wallet
+    pub source: Option<String>,
+    pub fee_rate: f64,
+    /// What the destination receives: every spendable input minus the fee
+    pub amount_sats: u64,
+    pub fee_sats: u64,
+    pub vsize: u64,
+    pub inputs: usize,
+    pub input_sats: u64,
+    /// Left out because they are frozen
+    pub frozen_utxos: usize,
+    /// Left out because they are unconfirmed and `includeUnconfirmed` is off
+    pub unconfirmed_utxos: usize,
+    /// Left out because spending them costs more than they hold
+    pub uneconomical_utxos: usize,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepRequest {
+    #[serde(flatten)]
+    pub plan: MaxSendRequest,
+    /// Broadcast after signing (default true); otherwise only the signed transaction is returned
+    pub broadcast: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepResponse {
+    pub plan: MaxSendResponse,
+    pub serialized_tx: String,
+    /// Set once the transaction is broadcast
+    pub txid: Option<String>,
+    pub broadcast: Option<BroadcastResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FrozenOutpoints {
+    /// `txid:vout` outpoints that coin selection and sweeps never spend
+    pub outpoints: BTreeSet<String>,
+}
+
+fn map_sweep_error(e: anyhow::Error) -> ApiError {
+    error!("Sweep failed: {}", e);
+    let message = e.to_string();
+    if message.starts_with("No device") {
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, message)
+    } else if message.starts_with("No KeepKey device found") {
+        ApiError::not_found(message)
+    } else if message.starts_with("Invalid") {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    } else if message.starts_with("Insufficient funds") {
+        ApiError::unprocessable_entity(message)
+    } else if message.starts_with("Signing request rejected") || message.starts_with("Policy violation") {
+        ApiError::new(StatusCode::FORBIDDEN, message)
+    } else if message.starts_with("Signing request was not approved") {
+        ApiError::new(StatusCode::REQUEST_TIMEOUT, message)
+    } else if message.starts_with("Chain backend") {
+        ApiError::new(StatusCode::BAD_GATEWAY, message)
+    } else {
+        ApiError::internal_error(message)
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/send/max",
+    request_body = MaxSendRequest,
+    responses(
+        (status = 200, description = "Maximum amount the destination can receive from the wallet, or from one address or path", body = MaxSendResponse),
+        (status = 400, description = "Invalid destination or fee rate"),
+        (status = 422, description = "Nothing left after fees"),
+        (status = 502, description = "Chain backend unavailable"),
+        (status = 503, description = "No device available")
+    ),
+    tag = "bitcoin"
+)]
+pub async fn max_send(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<MaxSendRequest>,
+) -> Result<Json<MaxSendResponse>, ApiError> {
+    let plan = crate::server::max_send_impl(&state, request).await.map_err(map_sweep_error)?;
+    info!("Max send to {}: {} sats at {} sat/vB", plan.destination, plan.amount_sats, plan.fee_rate);
+    Ok(Json(plan))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/sweep",
+    request_body = SweepRequest,
+    responses(
+        (status = 200, description = "Sweep of the wallet, or of one address or path, signed and, unless disabled, broadcast", body = SweepResponse),
+        (status = 400, description = "Invalid destination, source or fee rate"),
+        (status = 403, description = "Rejected in the remote approval prompt or by policy"),
+        (status = 408, description = "Remote approval timed out"),
+        (status = 409, description = "Signed, but not broadcast because it conflicts with a known spend; or blocked by another wallet operation"),
+        (status = 422, description = "Nothing left after fees"),
+        (status = 502, description = "Chain backend unavailable")
+    ),
+    tag = "bitcoin"
+)]
+pub async fn sweep_wallet(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<SweepRequest>,
+) -> Result<Json<SweepResponse>, ApiError> {
+    let _operation = state.wallet_locks.acquire(WalletOperation::Sweep, []).await?;
+    let response = crate::server::sweep_impl(&state, request, api_key_from_headers(&headers))
+        .await
+        .map_err(map_sweep_error)?;
+    if matches!(&response.broadcast, Some(broadcast) if !broadcast.broadcast) {
+        let details = serde_json::to_value(&response).unwrap_or_default();
+        return Err(ApiError::new(StatusCode::CONFLICT, "Sweep conflicts with an existing spend and was not broadcast")
+            .with_details(details));
+    }
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/utxos/frozen",
+    responses(
+        (status = 200, description = "Frozen outpoints", body = FrozenOutpoints)
+    ),
+    tag = "bitcoin"
+)]
+pub async fn get_frozen_outpoints(
+    State(state): State<Arc<ServerState>>,
+) -> Result<Json<FrozenOutpoints>, ApiError> {
+    let outpoints = state
+        .cache
+        .get_frozen_outpoints()
+        .await
+        .map_err(|e| ApiError::internal_error(e.to_string()))?;
+    Ok(Json(FrozenOutpoints { outpoints: outpoints.into_iter().collect() }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v2/utxos/frozen",
+    request_body = FrozenOutpoints,
+    responses(
+        (status = 200, description = "Frozen outpoints replaced", body = FrozenOutpoints),
+        (status = 400, description = "Malformed outpoint")
+    ),
+    tag = "bitcoin"
+)]
+pub async fn put_frozen_outpoints(
+    State(state): State<Arc<ServerState>>,
+    Json(frozen): Json<FrozenOutpoints>,
+) -> Result<Json<FrozenOutpoints>, ApiError> {
+    for outpoint in &frozen.outpoints {
+        let valid = outpoint
+            .rsplit_once(':')
+            .map_or(false, |(txid, vout)| txid.len() == 64 && hex::decode(txid).is_ok() && vout.parse::<u32>().is_ok());
+        if !valid {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid outpoint {:?}, expected txid:vout", outpoint)));
+        }
+    }
+    state
+        .cache
+        .set_frozen_outpoints(&frozen.outpoints)
+        .await
+        .map_err(|e| ApiError::internal_error(e.to_string()))?;
+    info!("{} outpoint(s) frozen", frozen.outpoints.len());
+    Ok(Json(frozen))
+}
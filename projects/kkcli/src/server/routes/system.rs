@@ -162,6 +162,7 @@ pub async fn system_get_public_key(
         get_public_key_msg.address_n = request.address_n.clone();
         get_public_key_msg.ecdsa_curve_name = request.ecdsa_curve_name;
         get_public_key_msg.show_display = request.show_display;
+        let shown_on_device = request.show_display.unwrap_or(false);
         get_public_key_msg.coin_name = request.coin_name;
 
         // Set script type if provided
@@ -170,6 +171,7 @@ pub async fn system_get_public_key(
                 "p2pkh" => get_public_key_msg.script_type = Some(messages::InputScriptType::Spendaddress as i32),
                 "p2wpkh" => get_public_key_msg.script_type = Some(messages::InputScriptType::Spendwitness as i32),
                 "p2sh-p2wpkh" => get_public_key_msg.script_type = Some(messages::InputScriptType::Spendp2shwitness as i32),
+                "p2tr" => get_public_key_msg.script_type = Some(messages::InputScriptType::Spendtaproot as i32),
                 _ => get_public_key_msg.script_type = Some(messages::InputScriptType::Spendaddress as i32),
             }
         }
@@ -186,7 +188,9 @@ pub async fn system_get_public_key(
                 info!("✅ Received public key from device");
                 
                 Ok(PublicKeyResponse {
+                    check_code: crate::cli::system::xpub_check_code(&xpub),
                     xpub,
+                    shown_on_device,
                 })
             }
             unexpected_msg => {
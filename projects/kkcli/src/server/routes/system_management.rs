@@ -9,6 +9,8 @@ use utoipa::ToSchema;
 use tracing::{info, error};
 
 use crate::server::ServerState;
+use super::common::ApiError;
+use crate::server::wallet_locks::WalletOperation;
 
 // System management structures
 #[derive(Deserialize, ToSchema)]
@@ -30,6 +32,12 @@ pub struct ChangePinRequest {
     pub remove: Option<bool>,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct WipeDeviceRequest {
+    /// Must be the phrase `wipe my keepkey`, typed by the user
+    pub confirmation: String,
+}
+
 #[derive(Deserialize, ToSchema)]
 pub struct RecoveryDeviceRequest {
     pub word_count: u32,
@@ -170,29 +178,39 @@ pub async fn system_change_pin(
 #[utoipa::path(
     post,
     path = "/system/info/wipe-device",
+    request_body = WipeDeviceRequest,
     responses(
-        (status = 200, description = "Device wiped successfully"),
+        (status = 200, description = "Device wiped and its cached data cleared"),
+        (status = 400, description = "Confirmation phrase missing or wrong"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 409, description = "A signing request or another wallet operation is in progress"),
         (status = 500, description = "Internal server error")
     ),
     tag = "system"
 )]
 pub async fn system_wipe_device(
-    State(_state): State<Arc<ServerState>>,
-) -> Result<StatusCode, StatusCode> {
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<WipeDeviceRequest>,
+) -> Result<StatusCode, ApiError> {
     info!("Wipe device request");
+    let _operation = state.wallet_locks.acquire(WalletOperation::Wipe, []).await?;
     
-    match crate::server::system_wipe_device_impl().await {
+    match crate::server::system_wipe_device_impl(state, &request.confirmation).await {
         Ok(_) => {
             info!("Device wiped successfully");
             Ok(StatusCode::OK)
         }
         Err(e) => {
             error!("Failed to wipe device: {}", e);
-            if e.to_string().contains("No KeepKey device found") {
-                Err(StatusCode::NOT_FOUND)
+            let message = e.to_string();
+            if message.starts_with("Confirmation phrase") {
+                Err(ApiError::new(StatusCode::BAD_REQUEST, message))
+            } else if message.starts_with("Signing in progress") {
+                Err(ApiError::new(StatusCode::CONFLICT, message))
+            } else if message.contains("No KeepKey device found") || message.contains("Device not connected") {
+                Err(ApiError::not_found(message))
             } else {
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
+                Err(ApiError::internal_error(message))
             }
         }
     }
@@ -205,15 +223,17 @@ pub async fn system_wipe_device(
     responses(
         (status = 200, description = "Recovery initiated"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 409, description = "Blocked by another wallet operation"),
         (status = 500, description = "Internal server error")
     ),
     tag = "system"
 )]
 pub async fn system_recovery_device(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
     Json(request): Json<RecoveryDeviceRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     info!("Recovery device request: word_count={}", request.word_count);
+    let _operation = state.wallet_locks.acquire(WalletOperation::DeviceSetup, []).await?;
     
     match crate::server::system_recovery_device_impl(request).await {
         Ok(_) => {
@@ -223,9 +243,9 @@ pub async fn system_recovery_device(
         Err(e) => {
             error!("Failed to initiate recovery: {}", e);
             if e.to_string().contains("No KeepKey device found") {
-                Err(StatusCode::NOT_FOUND)
+                Err(ApiError::not_found(e.to_string()))
             } else {
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
+                Err(ApiError::internal_error(e.to_string()))
             }
         }
     }
@@ -238,15 +258,17 @@ pub async fn system_recovery_device(
     responses(
         (status = 200, description = "Device reset initiated"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 409, description = "Blocked by another wallet operation"),
         (status = 500, description = "Internal server error")
     ),
     tag = "system"
 )]
 pub async fn system_reset_device(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
     Json(request): Json<ResetDeviceRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     info!("Reset device request");
+    let _operation = state.wallet_locks.acquire(WalletOperation::DeviceSetup, []).await?;
     
     match crate::server::system_reset_device_impl(request).await {
         Ok(_) => {
@@ -256,9 +278,9 @@ pub async fn system_reset_device(
         Err(e) => {
             error!("Failed to reset device: {}", e);
             if e.to_string().contains("No KeepKey device found") {
-                Err(StatusCode::NOT_FOUND)
+                Err(ApiError::not_found(e.to_string()))
             } else {
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
+                Err(ApiError::internal_error(e.to_string()))
             }
         }
     }
@@ -271,15 +293,17 @@ pub async fn system_reset_device(
     responses(
         (status = 200, description = "Device loaded successfully"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 409, description = "Blocked by another wallet operation"),
         (status = 500, description = "Internal server error")
     ),
     tag = "system"
 )]
 pub async fn system_load_device(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
     Json(request): Json<LoadDeviceRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     info!("Load device request");
+    let _operation = state.wallet_locks.acquire(WalletOperation::DeviceSetup, []).await?;
     
     match crate::server::system_load_device_impl(request).await {
         Ok(_) => {
@@ -289,9 +313,9 @@ pub async fn system_load_device(
         Err(e) => {
             error!("Failed to load device: {}", e);
             if e.to_string().contains("No KeepKey device found") {
-                Err(StatusCode::NOT_FOUND)
+                Err(ApiError::not_found(e.to_string()))
             } else {
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
+                Err(ApiError::internal_error(e.to_string()))
             }
         }
     }
@@ -334,14 +358,16 @@ pub async fn system_backup_device(
     responses(
         (status = 200, description = "Firmware erased successfully"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 409, description = "Blocked by another wallet operation"),
         (status = 500, description = "Internal server error")
     ),
     tag = "system"
 )]
 pub async fn system_firmware_erase(
-    State(_state): State<Arc<ServerState>>,
-) -> Result<StatusCode, StatusCode> {
+    State(state): State<Arc<ServerState>>,
+) -> Result<StatusCode, ApiError> {
     info!("Firmware erase request");
+    let _operation = state.wallet_locks.acquire(WalletOperation::FirmwareUpdate, []).await?;
     
     match crate::server::system_firmware_erase_impl().await {
         Ok(_) => {
@@ -351,9 +377,9 @@ pub async fn system_firmware_erase(
         Err(e) => {
             error!("Failed to erase firmware: {}", e);
             if e.to_string().contains("No KeepKey device found") {
-                Err(StatusCode::NOT_FOUND)
+                Err(ApiError::not_found(e.to_string()))
             } else {
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
+                Err(ApiError::internal_error(e.to_string()))
             }
         }
     }
@@ -366,15 +392,17 @@ pub async fn system_firmware_erase(
     responses(
         (status = 200, description = "Firmware uploaded successfully"),
         (status = 404, description = "No KeepKey device found"),
+        (status = 409, description = "Blocked by another wallet operation"),
         (status = 500, description = "Internal server error")
     ),
     tag = "system"
 )]
 pub async fn system_firmware_upload(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
     Json(request): Json<FirmwareUploadRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     info!("Firmware upload request: {} bytes", request.firmware.len());
+    let _operation = state.wallet_locks.acquire(WalletOperation::FirmwareUpdate, []).await?;
     
     match crate::server::system_firmware_upload_impl(request).await {
         Ok(_) => {
@@ -384,9 +412,9 @@ pub async fn system_firmware_upload(
         Err(e) => {
             error!("Failed to upload firmware: {}", e);
             if e.to_string().contains("No KeepKey device found") {
-                Err(StatusCode::NOT_FOUND)
+                Err(ApiError::not_found(e.to_string()))
             } else {
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
+                Err(ApiError::internal_error(e.to_string()))
             }
         }
     }
@@ -1,9 +1,9 @@
 use axum::{
     extract::{State, WebSocketUpgrade, ws::{WebSocket, Message}},
-    response::Response,
+    response::{sse::{Event, KeepAlive, Sse}, Response},
 };
 use std::sync::Arc;
-use futures::{sink::SinkExt, stream::StreamExt};
+use futures::{sink::SinkExt, stream::{self, Stream, StreamExt}};
 use serde::Serialize;
 use serde_json::json;
 use tracing::{info, error};
@@ -27,7 +27,54 @@ pub async fn ws_handler(
     ws.on_upgrade(move |socket| handle_socket(socket, state))
 }
 
-async fn handle_socket(socket: WebSocket, _state: Arc<ServerState>) {
+/// Server-sent events carrying the same events as `/ws`, for clients that only
+/// listen (`kkcli watch`, curl). Device status is sent when it changes rather
+/// than on every poll.
+pub async fn sse_handler(
+    State(state): State<Arc<ServerState>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    info!("Event stream client connected");
+    let initial = (state.events.subscribe(), interval(Duration::from_secs(5)), None::<serde_json::Value>);
+
+    let events = stream::unfold(initial, |(mut events, mut ticker, mut last_status)| async move {
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        let sse = Event::default().event(event.event_type.clone()).json_data(&event);
+                        return Some((sse, (events, ticker, last_status)));
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("Event stream client lagged, dropped {} event(s)", skipped);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                },
+                _ = ticker.tick() => {
+                    let status = match crate::server::get_device_status_impl().await {
+                        Ok(status) => json!(status),
+                        Err(e) => {
+                            error!("Failed to get device status: {}", e);
+                            continue;
+                        }
+                    };
+                    if last_status.as_ref() != Some(&status) {
+                        let event = DeviceEvent {
+                            event_type: "device_status".to_string(),
+                            data: status.clone(),
+                        };
+                        last_status = Some(status);
+                        let sse = Event::default().event("device_status").json_data(&event);
+                        return Some((sse, (events, ticker, last_status)));
+                    }
+                }
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+async fn handle_socket(socket: WebSocket, state: Arc<ServerState>) {
     let (mut sender, mut receiver) = socket.split();
     
     info!("WebSocket connection established");
@@ -82,6 +129,25 @@ async fn handle_socket(socket: WebSocket, _state: Arc<ServerState>) {
         }
     });
     
+    // Forward server events (transaction tracking etc.) to this client
+    let events_tx = tx.clone();
+    let mut events = state.events.subscribe();
+    let events_task = tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if events_tx.send(Message::Text(serde_json::to_string(&event).unwrap())).is_err() {
+                        break;
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    error!("WebSocket client lagged, dropped {} event(s)", skipped);
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+    
     // Spawn task to forward messages from channel to WebSocket
     let mut send_task = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
@@ -126,6 +192,7 @@ async fn handle_socket(socket: WebSocket, _state: Arc<ServerState>) {
     
     // Clean up
     status_task.abort();
+    events_task.abort();
     info!("WebSocket handler terminated");
 }
 
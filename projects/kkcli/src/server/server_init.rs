@@ -2,9 +2,9 @@ use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{timeout, Duration};
-use tracing::{info, error};
+use tracing::{debug, info, error, warn};
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 
@@ -20,11 +20,10 @@ use utoipa_swagger_ui::SwaggerUi;
 use std::net::SocketAddr;
 use hex;
 
-use crate::transport::{UsbTransport, ProtocolAdapter};
+use crate::transport::{DeviceTransport, UsbTransport, ProtocolAdapter};
 use crate::messages::{self, Message};
-use super::cache::{DeviceCache, DeviceFrontloader};
+use super::cache::{DeviceCache, DeviceFrontloader, XpubCheck};
 use super::ServerState;
-use super::try_get_device;
 use super::v2_endpoints;
 
 /// Attempt to cleanup and reset any stuck USB devices
@@ -72,17 +71,28 @@ async fn cleanup_stuck_usb_devices() {
     info!("✅ USB cleanup attempt completed");
 }
 
-/// Start the KeepKey CLI HTTP server
-pub async fn start_server(port: u16) -> Result<()> {
+/// Start the KeepKey CLI HTTP server. With `mock_device` every device call is
+/// answered by a simulated KeepKey holding the public test seed.
+pub async fn start_server(port: u16, mock_device: bool) -> Result<()> {
     info!("🚀 Starting KeepKey CLI server initialization...");
     
-    // 0. First, try to cleanup any stuck USB devices from previous sessions
-    cleanup_stuck_usb_devices().await;
+    if mock_device {
+        super::set_simulated_device(true);
+        warn!("🧪🧪🧪 SIMULATED DEVICE MODE 🧪🧪🧪");
+        warn!("🧪 No KeepKey is used: addresses come from the public test seed,");
+        warn!("🧪 balances are fixtures and signatures are canned. NEVER send funds to these addresses.");
+    } else {
+        // 0. First, try to cleanup any stuck USB devices from previous sessions
+        cleanup_stuck_usb_devices().await;
+    }
     
-    // 1. Open device cache database
-    let cache = DeviceCache::open()?;
-    let cache_for_v2 = cache.clone(); // Clone for v2 endpoints
+    // 1. Open device cache database (a separate one when simulating, so fixtures never mix with real data)
+    let cache = if mock_device { DeviceCache::open_simulation()? } else { DeviceCache::open()? };
     info!("✅ Device cache database opened");
+    // Handlers read the device ID from memory only; seed it from the last session
+    if let Err(e) = cache.warm_device_id().await {
+        warn!("Failed to load device ID from cache: {}", e);
+    }
 
     // Prepare to hold features outside the match
     let mut features = None;
@@ -92,21 +102,37 @@ pub async fn start_server(port: u16) -> Result<()> {
         
     // Variables for device state
     let device_id: String;
-    let shared_active_transport: Arc<Mutex<Option<UsbTransport<GlobalContext>>>>;
+    let shared_active_transport: Arc<Mutex<Option<DeviceTransport>>>;
+    let shared_debug_transport: Arc<Mutex<Option<UsbTransport<GlobalContext>>>>;
         
         // Try to connect to physical device
-        let usb_device = match super::try_get_device() {
-            Ok(device_obj) => device_obj,
-            Err(e) => {
-                error!("✖ No KeepKey device found: {}", e);
-                return Err(anyhow::anyhow!("No KeepKey device found: {}", e));
+        let usb_device = if mock_device {
+            None
+        } else {
+            match super::try_get_device() {
+                Ok(device_obj) => Some(device_obj),
+                Err(e) => {
+                    error!("✖ No KeepKey device found: {}", e);
+                    return Err(anyhow::anyhow!("No KeepKey device found: {}", e));
+                }
             }
         };
         
         // 3. Test device communication BEFORE proceeding and establish the persistent transport
         info!("🧪 Testing device communication and establishing persistent transport...");
         let result = timeout(Duration::from_secs(5), async {
-            let (mut transport, _config_descriptor, _handle) = UsbTransport::new(&usb_device, 0)?;
+            let (mut transport, debug_transport) = match &usb_device {
+                Some(usb_device) => {
+                    let (transport, config_descriptor, handle) = UsbTransport::new(usb_device, 0)?;
+                    // DEBUG_LINK firmware exposes a second interface; absent on release builds
+                    let debug_transport = UsbTransport::new_from_descriptor_and_handle(&config_descriptor, handle, 1).ok();
+                    if debug_transport.is_some() {
+                        info!("🐞 DEBUG_LINK interface available");
+                    }
+                    (DeviceTransport::Usb(transport), debug_transport)
+                }
+                None => (super::simulated_transport(), None),
+            };
             let get_features_msg = messages::GetFeatures {};
             
             let response = transport.with_standard_handler().handle(get_features_msg.into())?;
@@ -123,7 +149,7 @@ pub async fn start_server(port: u16) -> Result<()> {
                     info!("   Device ID: {}", device_id_str);
                     info!("   Label: {}", label);
                     
-                    Ok((device_id_str, features_msg, transport)) // Return transport here
+                    Ok((device_id_str, features_msg, transport, debug_transport)) // Return transport here
                 }
                 _ => Err(anyhow::anyhow!("Unexpected response from device"))
             }
@@ -131,12 +157,13 @@ pub async fn start_server(port: u16) -> Result<()> {
         
         // Handle various timeouts and response scenarios
         match result {
-            Ok(Ok((device_id_result, features_msg, mut transport))) => {
+            Ok(Ok((device_id_result, features_msg, mut transport, debug_transport))) => {
                 device_id = device_id_result;
                 
                 // Set up the shared transport
                 let transport_mutex = Arc::new(Mutex::new(Some(transport)));
                 shared_active_transport = Arc::clone(&transport_mutex);
+                shared_debug_transport = Arc::new(Mutex::new(debug_transport));
                 
                 // Convert protobuf Features to routes::Features and save to cache
                 let routes_features = super::routes::Features {
@@ -208,13 +235,41 @@ pub async fn start_server(port: u16) -> Result<()> {
         false
     };
     
-    if device_exists && has_addresses {
+    let mut needs_frontload = !(device_exists && has_addresses);
+    if !needs_frontload {
         info!("📂 Device found in cache with addresses - loading from cache...");
         if let Some(cached_features) = cache.load_device(&device_id).await? {
             let elapsed = chrono::Utc::now().timestamp() - cached_features.last_seen;
             info!("✅ Device data loaded from cache (last seen: {} seconds ago)", elapsed);
         }
-    } else {
+        
+        // Spot-check the cache against the device so a changed seed or a
+        // corrupt cache is caught now rather than as wrong receive addresses
+        let verifier = DeviceFrontloader::new(cache.clone(), Arc::clone(&shared_active_transport));
+        match verifier.verify_random_xpub(&device_id).await {
+            Ok(XpubCheck::Matched(checked)) => {
+                info!("✅ Cached {} {} xpub matches the device", checked.coin, checked.script_type);
+            }
+            Ok(XpubCheck::NothingCached) => {
+                debug!("No cached xpubs to verify for device {}", device_id);
+            }
+            Ok(XpubCheck::Mismatch { cached, from_device }) => {
+                error!("🚨🚨🚨 CACHED KEYS DO NOT MATCH THIS KEEPKEY 🚨🚨🚨");
+                error!("🚨 {} {} xpub at {:?}", cached.coin, cached.script_type, cached.path);
+                error!("🚨   cached: {}", cached.xpub);
+                error!("🚨   device: {}", from_device);
+                error!("🚨 The seed or passphrase changed, or the cache is corrupt.");
+                error!("🚨 Discarding cached addresses and balances and reloading them from the device.");
+                cache.invalidate_key_material(&device_id).await?;
+                needs_frontload = true;
+            }
+            Err(e) => {
+                warn!("⚠️ Could not verify cached xpub against the device: {}", e);
+            }
+        }
+    }
+    
+    if needs_frontload {
         // Frontload device information - either new device OR existing device with no addresses
         if device_exists {
             info!("🔄 Device exists but has no cached addresses - forcing frontload...");
@@ -223,30 +278,23 @@ pub async fn start_server(port: u16) -> Result<()> {
         }
         info!("⏳ This may take 30-60 seconds on first run...");
         
-        // We need the device object for frontloading
-        let device_obj_result = try_get_device();
-        
-        // Check if we got a device or need to handle error
-        if let Err(e) = &device_obj_result {
-            error!("❌ Failed to get device for frontloading: {}", e);
-            return Err(anyhow::anyhow!("Failed to get device for frontloading: {}", e));
+        // Pass the shared transport to DeviceFrontloader
+        // No event clients exist yet, so startup progress goes to the log
+        let mut frontloader = DeviceFrontloader::new(cache.clone(), Arc::clone(&shared_active_transport))
+            .with_progress(Arc::new(|_, progress| info!("⏳ Frontload {}", progress.render_bar())));
+        if mock_device {
+            frontloader = frontloader.with_fixture_balances();
         }
         
-        // Only continue with frontloading if we have a valid device
-        if let Ok(device_obj) = device_obj_result {
-            // Pass the shared transport to DeviceFrontloader
-            let frontloader = DeviceFrontloader::new(cache.clone(), Arc::clone(&shared_active_transport), device_obj);
-            
-            // This blocks until all data is loaded - MUST succeed before starting server
-            match frontloader.frontload_all().await {
-                Ok(_) => {
-                    info!("✅ Device data frontloaded and cached successfully");
-                }
-                Err(e) => {
-                    error!("❌ Failed to frontload device data: {}", e);
-                    error!("❌ Cannot start server without working device communication");
-                    return Err(anyhow::anyhow!("Device frontloading failed: {}", e));
-                }
+        // This blocks until all data is loaded - MUST succeed before starting server
+        match frontloader.frontload_all().await {
+            Ok(_) => {
+                info!("✅ Device data frontloaded and cached successfully");
+            }
+            Err(e) => {
+                error!("❌ Failed to frontload device data: {}", e);
+                error!("❌ Cannot start server without working device communication");
+                return Err(anyhow::anyhow!("Device frontloading failed: {}", e));
             }
         }
     }
@@ -295,7 +343,84 @@ pub async fn start_server(port: u16) -> Result<()> {
     // 6. ONLY NOW start the REST server with confirmed working device
     info!("🌐 Device confirmed working - starting REST API server on port {}", port);
     
-    // Create API documentation
+    let state = server_state(cache, shared_active_transport, shared_debug_transport);
+    
+    // Headless servers can take PINs from an admin UI instead of stdin
+    if super::pin_entry::remote_pin_enabled(&state.cache).await? {
+        super::pin_entry::enable_remote_pin_entry(&state.cache, &state.pin_entry).await?;
+    }
+    
+    // Follow broadcast transactions until they are final
+    super::tx_tracker::spawn_tx_tracker(Arc::clone(&state));
+    
+    // Daily portfolio snapshots and realized transaction history
+    super::portfolio_history::spawn_portfolio_history(Arc::clone(&state));
+    
+    // Undo cached history confirmed in blocks that get reorged out
+    super::reorg::spawn_reorg_watch(Arc::clone(&state));
+    
+    let app = build_router(Arc::clone(&state));
+    
+    // Start listening - bind to localhost only for security
+    // Using 127.0.0.1 instead of 0.0.0.0 to avoid exposing wallet to the network
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    info!("🚀 Starting KeepKey CLI server on {} (localhost only)", addr);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Server documentation and endpoints:");
+    info!("  - REST API: http://localhost:{}/api", port);
+    info!("  - API Documentation: http://localhost:{}/docs", port);
+    info!("  - OpenAPI Spec: http://localhost:{}/api-docs/openapi.json", port);
+    info!("  - Legacy Swagger: http://localhost:{}/spec/swagger.json", port);
+    info!("  - Authentication: http://localhost:{}/auth/pair", port);
+    info!("  - Events (WebSocket): ws://localhost:{}/ws", port);
+    info!("  - Events (SSE): http://localhost:{}/api/v2/events", port);
+    info!("  - JSON-RPC (Core-style wallet calls): http://localhost:{}/rpc", port);
+
+    // Start the server
+    axum::serve(listener, app).await?;
+    
+    Ok(())
+}
+
+fn server_state(
+    cache: DeviceCache,
+    active_transport: Arc<Mutex<Option<DeviceTransport>>>,
+    debug_transport: Arc<Mutex<Option<UsbTransport<GlobalContext>>>>,
+) -> Arc<ServerState> {
+    let events = super::events::EventBus::default();
+    let device_mutex = Arc::new(Mutex::new(()));
+    Arc::new(ServerState {
+        cache,
+        device_queues: super::device_queues::DeviceQueues::new(Arc::clone(&device_mutex)),
+        device_mutex,
+        active_transport,
+        debug_transport,
+        pin_entry: super::pin_entry::PinEntryBroker::new(events.clone()),
+        events,
+        approvals: super::approvals::ApprovalRegistry::default(),
+        fee_market: super::fee_market::FeeMarketCache::default(),
+        response_signer: super::response_signing::ResponseSigner::default(),
+        xpub_sync: super::xpub_sync::XpubSyncPlans::default(),
+        wallet_locks: super::wallet_locks::WalletLocks::default(),
+    })
+}
+
+/// State for a server backed by the device simulator, with its cache in
+/// `cache_dir` and fixture balances already frontloaded. Background jobs are
+/// not started. Used by the API tests to run the router in-process.
+pub async fn simulated_server_state(cache_dir: std::path::PathBuf) -> Result<Arc<ServerState>> {
+    super::set_simulated_device(true);
+    let cache = DeviceCache::open_simulation_in(cache_dir)?;
+    let active_transport = Arc::new(Mutex::new(Some(super::simulated_transport())));
+    DeviceFrontloader::new(cache.clone(), Arc::clone(&active_transport))
+        .with_fixture_balances()
+        .frontload_all()
+        .await?;
+    Ok(server_state(cache, active_transport, Arc::new(Mutex::new(None))))
+}
+
+/// Every REST, WebSocket and docs route, with the v2 API under `/v2`
+pub fn build_router(state: Arc<ServerState>) -> Router {
     #[derive(OpenApi)]
     #[openapi(
         paths(
@@ -311,6 +436,46 @@ pub async fn start_server(port: u16) -> Result<()> {
             
             
             super::routes::bitcoin::utxo_sign_transaction,
+            super::routes::bitcoin::bitcoin_sign_psbt,
+            super::routes::device_selftest,
+            super::routes::forget_device,
+            super::routes::frontload_device,
+            super::routes::discovered_accounts,
+            super::routes::running_operations,
+            super::routes::plan_xpub_sync,
+            super::routes::apply_xpub_sync,
+            super::routes::discard_xpub_sync,
+            super::routes::broadcast_transaction,
+            super::routes::get_chain_tip,
+            super::routes::get_mempool_fees,
+            super::routes::estimate_transaction_size,
+            super::routes::max_send,
+            super::routes::sweep_wallet,
+            super::routes::send_many_preview,
+            super::routes::send_many,
+            super::routes::get_frozen_outpoints,
+            super::routes::put_frozen_outpoints,
+            super::routes::get_portfolio_history,
+            super::routes::get_privacy_report,
+            super::routes::get_script_type_balances,
+            super::routes::plan_script_type_migration,
+            super::routes::json_rpc,
+            super::routes::create_dashboard_token,
+            super::routes::list_dashboard_tokens,
+            super::routes::revoke_dashboard_token,
+            super::routes::list_paired_clients,
+            super::routes::revoke_paired_client,
+            super::routes::create_metadata_backup,
+            super::routes::restore_metadata_backup,
+            super::routes::list_pending_approvals,
+            super::routes::decide_approval,
+            super::routes::get_pending_pin,
+            super::routes::submit_pin,
+            super::routes::get_policies,
+            super::routes::get_response_signing_key,
+            super::routes::put_policies,
+            super::routes::start_session_recording,
+            super::routes::stop_session_recording,
             
             
         ),
@@ -325,6 +490,83 @@ pub async fn start_server(port: u16) -> Result<()> {
             super::routes::PingResponse,
             super::routes::UtxoAddressRequest,
             super::routes::UtxoAddressResponse,
+            super::routes::BitcoinSignPsbtRequest,
+            super::routes::BitcoinSignPsbtResponse,
+            super::routes::SelftestRequest,
+            super::routes::ForgetDeviceResponse,
+            super::cache::FrontloadScope,
+            super::cache::FrontloadReport,
+            super::cache::DiscoveredAccount,
+            super::cache::device_cache::PairedClient,
+            super::cache::device_cache::ClientScopeUsage,
+            super::metadata_backup::BackupLocation,
+            super::metadata_backup::BackupReport,
+            super::metadata_backup::RestoreReport,
+            super::wallet_locks::HeldOperation,
+            super::wallet_locks::WalletOperation,
+            super::xpub_sync::XpubSyncPlan,
+            super::xpub_sync::XpubChange,
+            super::xpub_sync::XpubChangeKind,
+            super::xpub_sync::XpubSyncResult,
+            crate::cli::system::SelftestReport,
+            crate::cli::system::SelftestStep,
+            crate::cli::system::SelftestStatus,
+            super::routes::BroadcastRequest,
+            super::routes::BroadcastResponse,
+            super::routes::SpendConflict,
+            super::routes::ConflictSource,
+            super::routes::DoubleSpendPolicy,
+            super::routes::ChainTipResponse,
+            super::routes::BackendTipStatus,
+            super::routes::BackendAgreement,
+            super::routes::MempoolFeesResponse,
+            super::routes::FeeBand,
+            super::routes::FeeEstimate,
+            super::routes::TxEstimateRequest,
+            super::routes::TxEstimateResponse,
+            super::routes::TxShapeEntry,
+            super::routes::MaxSendRequest,
+            super::routes::MaxSendResponse,
+            super::routes::SweepRequest,
+            super::routes::SweepResponse,
+            super::routes::SendManyOutput,
+            super::routes::SendManyRequest,
+            super::routes::SendManyPreviewOutput,
+            super::routes::SendManyChange,
+            super::routes::SendManyPreview,
+            super::routes::SendManyResponse,
+            super::routes::FrozenOutpoints,
+            super::routes::PortfolioHistoryResponse,
+            super::routes::DailySnapshot,
+            super::routes::SnapshotAsset,
+            super::routes::RealizedTransaction,
+            super::routes::PrivacyReport,
+            super::routes::PrivacyIssue,
+            super::routes::PrivacyIssueKind,
+            super::routes::ScriptTypeBalancesResponse,
+            super::routes::ScriptTypeBalance,
+            super::routes::MigrationRequest,
+            super::routes::MigrationPlan,
+            super::routes::MigrationTransaction,
+            super::amounts::AmountFormat,
+            super::amounts::AmountUnit,
+            super::routes::CreateDashboardTokenRequest,
+            super::routes::CreateDashboardTokenResponse,
+            super::routes::DashboardTokenInfo,
+            super::routes::ApprovalDecision,
+            super::routes::PinSubmission,
+            super::routes::SessionRecordingStart,
+            super::routes::SessionRecordingSaved,
+            crate::transport::DeviceSession,
+            crate::transport::session::RecordedFrame,
+            crate::transport::session::FrameDirection,
+            super::routes::PolicyResponse,
+            super::routes::ResponseSigningKey,
+            super::button_policy::ButtonPolicy,
+            super::button_policy::OpReturnPolicy,
+            super::approvals::ApprovalRequest,
+            super::approvals::ApprovalOutput,
+            super::pin_entry::PinRequest,
 
 
             // Use only types that exist in the mayachain routes
@@ -342,6 +584,12 @@ pub async fn start_server(port: u16) -> Result<()> {
             (name = "system", description = "System health and status endpoints"),
             (name = "device", description = "Device management and information endpoints"),
             (name = "addresses", description = "Address generation endpoints"),
+            (name = "chain", description = "Broadcast and chain state endpoints"),
+            (name = "portfolio", description = "Portfolio history and tax export endpoints"),
+            (name = "auth", description = "Pairing and access token endpoints"),
+            (name = "approvals", description = "Remote approval of REST signing requests"),
+            (name = "pin", description = "Remote PIN matrix entry for headless servers"),
+            (name = "debug", description = "Device session recording and DEBUG_LINK endpoints"),
             
 
             
@@ -355,14 +603,16 @@ pub async fn start_server(port: u16) -> Result<()> {
     )]
     struct ApiDoc;
 
-    // Create the router with cache state
-    let state = ServerState {
-        cache,
-        device_mutex: Arc::new(Mutex::new(())),
-        active_transport: shared_active_transport,
-    };
+    // --- V2 API endpoints ---
+    // Create API router for v2 endpoints using the unified device cache
+    let v2_router = v2_endpoints::v2_router(Arc::new(state.cache.clone()))
+        // Apply middlewares to v2 router as well to ensure logging 
+        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(super::log_request))
+        .layer(CorsLayer::permissive());
     
-    // Build the application with all routes
+    let dashboard_token_cache = state.cache.clone();
+    let paired_client_cache = state.cache.clone();
     let app = Router::new()
     // Health endpoint
     .route("/api/health", get(super::routes::health_check))
@@ -390,6 +640,7 @@ pub async fn start_server(port: u16) -> Result<()> {
         .route("/api/v1/bitcoin/tx", post(super::routes::bitcoin::bitcoin_sign_tx))
         .route("/api/v1/bitcoin/sign-message", post(super::routes::bitcoin::bitcoin_sign_message))
         .route("/api/v1/bitcoin/verify-message", post(super::routes::bitcoin::bitcoin_verify_message))
+        .route("/api/v1/bitcoin/sign-psbt", post(super::routes::bitcoin::bitcoin_sign_psbt))
         .route("/api/v1/utxo/tx", post(super::routes::bitcoin::utxo_sign_transaction))
         .route("/utxo/sign-transaction", post(super::routes::bitcoin::utxo_sign_transaction))
 
@@ -421,12 +672,50 @@ pub async fn start_server(port: u16) -> Result<()> {
         .route("/api/v1/debug/link-state", get(super::routes::debug::debug_link_state))
         .route("/system/debug/fill-config", post(super::routes::debug::debug_fill_config))
         .route("/api/v1/debug/fill-config", post(super::routes::debug::debug_fill_config))
+        .route("/api/v2/debug/recording", post(super::routes::start_session_recording).delete(super::routes::stop_session_recording))
+        
+        // Manufacturing endpoints (only with the `manufacturing` feature)
+        .merge(super::routes::manufacturing::manufacturing_router())
         
-        // Manufacturing endpoints
-        .route("/system/manufacturing/get-hash", post(super::routes::manufacturing::manufacturing_get_hash))
-        .route("/api/v1/manufacturing/get-hash", get(super::routes::manufacturing::manufacturing_get_hash))
-        .route("/system/manufacturing/model-prefix", post(super::routes::manufacturing::manufacturing_model_prefix))
-        .route("/api/v1/manufacturing/model-prefix", get(super::routes::manufacturing::manufacturing_model_prefix))
+        // Device self-test
+        .route("/api/v2/device/:id/selftest", post(super::routes::device_selftest))
+        .route("/api/v2/device/:id", delete(super::routes::forget_device))
+        .route("/api/v2/frontload", post(super::routes::frontload_device))
+        .route("/api/v2/device/:id/accounts", get(super::routes::discovered_accounts))
+        .route("/api/v2/operations", get(super::routes::running_operations))
+        .route("/api/v2/xpubs/sync", post(super::routes::plan_xpub_sync))
+        .route("/api/v2/xpubs/sync/:id", delete(super::routes::discard_xpub_sync))
+        .route("/api/v2/xpubs/sync/:id/apply", post(super::routes::apply_xpub_sync))
+        
+        // Broadcast with double-spend protection
+        .route("/api/v2/tx/broadcast", post(super::routes::broadcast_transaction))
+        .route("/api/v2/chain/tip", get(super::routes::get_chain_tip))
+        .route("/api/v2/fees/mempool", get(super::routes::get_mempool_fees))
+        .route("/api/v2/tx/estimate", post(super::routes::estimate_transaction_size))
+        .route("/api/v2/send/max", post(super::routes::max_send))
+        .route("/api/v2/sweep", post(super::routes::sweep_wallet))
+        .route("/api/v2/send-many", post(super::routes::send_many))
+        .route("/api/v2/send-many/preview", post(super::routes::send_many_preview))
+        .route("/api/v2/utxos/frozen", get(super::routes::get_frozen_outpoints).put(super::routes::put_frozen_outpoints))
+        .route("/api/v2/portfolio/history", get(super::routes::get_portfolio_history))
+        .route("/api/v2/privacy/report", get(super::routes::get_privacy_report))
+        .route("/api/v2/balances/script-types", get(super::routes::get_script_type_balances))
+        .route("/api/v2/migrate/plan", post(super::routes::plan_script_type_migration))
+        .route("/api/v2/dashboard-tokens", get(super::routes::list_dashboard_tokens).post(super::routes::create_dashboard_token))
+        .route("/api/v2/dashboard-tokens/:id", delete(super::routes::revoke_dashboard_token))
+        .route("/api/v2/clients", get(super::routes::list_paired_clients))
+        .route("/api/v2/clients/:id", delete(super::routes::revoke_paired_client))
+        .route("/api/v2/metadata-backup", post(super::routes::create_metadata_backup))
+        .route("/api/v2/metadata-backup/restore", post(super::routes::restore_metadata_backup))
+        .route("/api/v2/approvals", get(super::routes::list_pending_approvals))
+        .route("/api/v2/approvals/:id", post(super::routes::decide_approval))
+        .route("/api/v2/pin", get(super::routes::get_pending_pin))
+        .route("/api/v2/pin/:id", post(super::routes::submit_pin))
+        .route("/api/v2/policies", get(super::routes::get_policies).put(super::routes::put_policies))
+        .route("/api/v2/response-signing/key", get(super::routes::get_response_signing_key))
+        
+        // Bitcoin Core-style wallet RPC
+        .route("/rpc", post(super::routes::json_rpc))
         
         // Raw message endpoint
         .route("/api/v1/raw-message", post(super::routes::raw::raw_message))
@@ -435,42 +724,33 @@ pub async fn start_server(port: u16) -> Result<()> {
         // Legacy Swagger compatibility route
         .route("/spec/swagger.json", get(super::get_swagger_spec))
         
+        // Device status and server events (tx:confirmed, tx:reorged, tx:stuck, chain:reorg, ...)
+        .route("/ws", get(super::routes::websocket::ws_handler))
+        .route("/api/v2/events", get(super::routes::websocket::sse_handler))
+        
         // Apply middlewares
+        .layer(middleware::from_fn_with_state(Arc::clone(&state), super::response_signing::sign_responses))
         .layer(TraceLayer::new_for_http())
         .layer(middleware::from_fn(super::log_request))
         .layer(
             CorsLayer::permissive()
         )
-        .with_state(Arc::new(state))
+        .with_state(state)
         // Add OpenAPI docs
         .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()));
-    
-    // Start listening - bind to localhost only for security
-    // Using 127.0.0.1 instead of 0.0.0.0 to avoid exposing wallet to the network
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    info!("🚀 Starting KeepKey CLI server on {} (localhost only)", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    info!("Server documentation and endpoints:");
-    info!("  - REST API: http://localhost:{}/api", port);
-    info!("  - API Documentation: http://localhost:{}/docs", port);
-    info!("  - OpenAPI Spec: http://localhost:{}/api-docs/openapi.json", port);
-    info!("  - Legacy Swagger: http://localhost:{}/spec/swagger.json", port);
-    info!("  - Authentication: http://localhost:{}/auth/pair", port);
-    
-    // --- V2 API endpoints ---
-    // Create API router for v2 endpoints using the unified device cache
-    let v2_router = v2_endpoints::v2_router(Arc::new(cache_for_v2))
-        // Apply middlewares to v2 router as well to ensure logging 
-        .layer(TraceLayer::new_for_http())
-        .layer(middleware::from_fn(super::log_request))
-        .layer(CorsLayer::permissive());
-    
+
     // Add the v2_router under /v2
     let app = app.nest("/v2", v2_router);
-
-    // Start the server
-    axum::serve(listener, app).await?;
     
-    Ok(())
-}
+    // Check API keys against their scopes, count requests per paired client, and require a key for sensitive requests
+    let app = app.layer(middleware::from_fn_with_state(
+        paired_client_cache,
+        super::paired_clients::authorize_api_keys,
+    ));
 
+    // Restrict requests carrying a read-only dashboard token to its scope
+    app.layer(middleware::from_fn_with_state(
+        dashboard_token_cache,
+        super::dashboard_token::dashboard_token_guard,
+    ))
+}
@@ -0,0 +1,148 @@
+//! Transaction size estimates from the shape of a transaction alone: how many
+//! inputs and outputs of each script type it has. Nothing is looked up, so UIs
+//! can price a transaction before any coins are selected.
+//!
+//! Sizes are counted in weight units as BIP-141 defines them. Signatures are
+//! assumed to be 72 bytes (DER with sighash), the high end of what the device
+//! produces, so estimates err on the side of a slightly higher fee.
+
+use anyhow::{anyhow, Result};
+use bitcoin::Network;
+
+use super::address_validation::validate_address;
+use super::routes::{TxEstimateRequest, TxEstimateResponse, TxShapeEntry};
+
+/// Version and locktime
+const BASE_WEIGHT: u64 = 8 * 4;
+/// Segwit marker and flag bytes, witness data so counted once each
+const SEGWIT_MARKER_WEIGHT: u64 = 2;
+
+/// Weight of one input spending `script_type`
+pub(crate) fn input_weight(script_type: &str) -> Option<u64> {
+    // Outpoint, scriptSig length and sequence: 41 bytes before any scriptSig
+    match script_type {
+        "p2pkh" => Some((41 + 107) * 4),
+        // scriptSig pushes the 22 byte witness program; witness is count + sig + pubkey
+        "p2sh-p2wpkh" => Some((41 + 23) * 4 + 1 + 73 + 34),
+        "p2wpkh" => Some(41 * 4 + 1 + 73 + 34),
+        // Key path spend with the default sighash: count + 64 byte signature
+        "p2tr" => Some(41 * 4 + 1 + 65),
+        _ => None,
+    }
+}
+
+/// Weight of one output paying to `script_type`
+pub(crate) fn output_weight(script_type: &str) -> Option<u64> {
+    let script_len = match script_type {
+        "p2pkh" => 25,
+        "p2sh" | "p2sh-p2wpkh" => 23,
+        "p2wpkh" => 22,
+        "p2wsh" | "p2tr" => 34,
+        _ => return None,
+    };
+    // Amount and script length prefix
+    Some((8 + 1 + script_len) * 4)
+}
+
+fn varint_len(n: u64) -> u64 {
+    match n {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TxSize {
+    pub weight: u64,
+    pub vsize: u64,
+}
+
+/// Size of a transaction with the given `(script type, count)` inputs and outputs
+pub(crate) fn estimate_size(inputs: &[(&str, u64)], outputs: &[(&str, u64)]) -> Result<TxSize> {
+    let input_count: u64 = inputs.iter().map(|(_, count)| count).sum();
+    let output_count: u64 = outputs.iter().map(|(_, count)| count).sum();
+    if input_count == 0 || output_count == 0 {
+        return Err(anyhow!("A transaction needs at least one input and one output"));
+    }
+
+    let mut weight = BASE_WEIGHT + (varint_len(input_count) + varint_len(output_count)) * 4;
+    for (script_type, count) in inputs {
+        let input = input_weight(script_type).ok_or_else(|| anyhow!("Unsupported input script type {:?}", script_type))?;
+        weight += input * count;
+    }
+    for (script_type, count) in outputs {
+        let output = output_weight(script_type).ok_or_else(|| anyhow!("Unsupported output script type {:?}", script_type))?;
+        weight += output * count;
+    }
+
+    let legacy_inputs: u64 = inputs.iter().filter(|(t, _)| *t == "p2pkh").map(|(_, count)| count).sum();
+    if legacy_inputs < input_count {
+        // Legacy inputs still carry an empty witness (one zero byte) in a segwit transaction
+        weight += SEGWIT_MARKER_WEIGHT + legacy_inputs;
+    }
+
+    Ok(TxSize { weight, vsize: weight.div_ceil(4) })
+}
+
+/// Fee in satoshis for `vsize` at `fee_rate` sat/vB, rounded up
+pub(crate) fn fee_for(vsize: u64, fee_rate: f64) -> u64 {
+    (vsize as f64 * fee_rate).ceil() as u64
+}
+
+fn shape_entry(entry: &TxShapeEntry, side: &str) -> Result<(String, u64)> {
+    let script_type = match (&entry.script_type, &entry.address) {
+        (Some(script_type), None) => script_type.to_ascii_lowercase(),
+        (None, Some(address)) => validate_address(address, Network::Bitcoin)?.script_type().to_string(),
+        _ => return Err(anyhow!("Each {} needs exactly one of scriptType or address", side)),
+    };
+    Ok((script_type, entry.count))
+}
+
+/// Answer for `POST /api/v2/tx/estimate`
+pub(crate) fn estimate_tx(request: &TxEstimateRequest) -> Result<TxEstimateResponse> {
+    if let Some(fee_rate) = request.fee_rate {
+        if !fee_rate.is_finite() || fee_rate < 0.0 {
+            return Err(anyhow!("Invalid fee rate {}", fee_rate));
+        }
+    }
+    let inputs = request.inputs.iter().map(|e| shape_entry(e, "input")).collect::<Result<Vec<_>>>()?;
+    let outputs = request.outputs.iter().map(|e| shape_entry(e, "output")).collect::<Result<Vec<_>>>()?;
+    let as_refs = |entries: &[(String, u64)]| entries.iter().map(|(t, c)| (t.as_str(), *c)).collect::<Vec<_>>();
+
+    let size = estimate_size(&as_refs(&inputs), &as_refs(&outputs))?;
+    Ok(TxEstimateResponse {
+        weight: size.weight,
+        vsize: size.vsize,
+        fee_rate: request.fee_rate,
+        fee: request.fee_rate.map(|rate| fee_for(size.vsize, rate)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_well_known_sizes() {
+        // Classic one-in, two-out legacy payment
+        let legacy = estimate_size(&[("p2pkh", 1)], &[("p2pkh", 2)]).unwrap();
+        assert_eq!(legacy, TxSize { weight: 904, vsize: 226 });
+
+        let segwit = estimate_size(&[("p2wpkh", 1)], &[("p2wpkh", 2)]).unwrap();
+        assert_eq!(segwit.vsize, 141);
+        assert_eq!(fee_for(segwit.vsize, 2.5), 353);
+
+        // Mixing in a legacy input adds the marker and its empty witness
+        let mixed = estimate_size(&[("p2wpkh", 1), ("p2pkh", 1)], &[("p2tr", 1)]).unwrap();
+        assert_eq!(mixed.weight, 40 + 272 + 592 + 172 + 2 + 1);
+    }
+
+    #[test]
+    fn rejects_unknown_types_and_empty_sides() {
+        assert!(estimate_size(&[("p2wsh", 1)], &[("p2wpkh", 1)]).is_err());
+        assert!(estimate_size(&[("p2wpkh", 1)], &[]).is_err());
+        assert!(estimate_size(&[("p2wpkh", 0)], &[("p2wpkh", 1)]).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! Follows broadcast transactions until they reach the configured number of
+//! confirmations, updating `tx_history` and publishing `tx:confirmed` /
+//! `tx:reorged` events (plus an optional webhook POST).
+//!
+//! Transactions still in the mempool after `STUCK_AFTER_SECS` are compared
+//! with the current fee estimates; one paying less than what confirms within
+//! `EXPECTED_TARGET_BLOCKS` gets a `tx:stuck` event suggesting a fee rate to
+//! bump it to.
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::time::{interval, Duration};
+use tracing::{debug, error, info, warn};
+
+use super::cache::device_cache::TxHistoryEntry;
+use super::chain::{self, ChainBackend};
+use super::routes::FeeEstimate;
+use super::ServerState;
+
+const TX_TRACKER_INTERVAL: Duration = Duration::from_secs(60);
+const TX_WEBHOOK_CONFIG_KEY: &str = "tx_webhook_url";
+// Give a transaction a couple of blocks before judging its fee
+const STUCK_AFTER_SECS: i64 = 30 * 60;
+/// Confirmation target, in blocks, a pending transaction is expected to keep up with
+const EXPECTED_TARGET_BLOCKS: u32 = 6;
+/// Target the suggested bump aims for
+const BUMP_TARGET_BLOCKS: u32 = 2;
+/// BIP125 replacements must raise the feerate by at least this much (sat/vB)
+const INCREMENTAL_RELAY_FEE: u64 = 1;
+// Report a stuck transaction again only once the suggestion has risen this much (percent)
+const READVISE_INCREASE_PERCENT: u64 = 25;
+
+/// Suggested rate last published per txid
+fn advised() -> &'static Mutex<HashMap<String, u64>> {
+    static ADVISED: OnceLock<Mutex<HashMap<String, u64>>> = OnceLock::new();
+    ADVISED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub(crate) fn spawn_tx_tracker(state: Arc<ServerState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(TX_TRACKER_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = poll_once(&state).await {
+                warn!("Transaction tracker poll failed: {}", e);
+            }
+        }
+    })
+}
+
+async fn poll_once(state: &ServerState) -> Result<()> {
+    let tracked = state.cache.get_tracked_transactions().await?;
+    if tracked.is_empty() {
+        return Ok(());
+    }
+
+    let backend = ChainBackend::from_cache(&state.cache).await?;
+    let target = state.cache.get_confirmation_target().await?;
+    let tip = backend.tip_height().await?;
+    debug!("Tracking {} transaction(s) at tip {}", tracked.len(), tip);
+
+    let now = chrono::Utc::now().timestamp();
+    let mut estimates = None;
+
+    for entry in tracked {
+        let status = match backend.tx_status(&entry.txid).await {
+            Ok(status) => status,
+            Err(e) => {
+                warn!("Failed to get status for {}: {}", entry.txid, e);
+                continue;
+            }
+        };
+
+        let (confirmations, block_height, block_hash) = match status {
+            Some(s) if s.confirmed => {
+                let height = s.block_height.unwrap_or(tip);
+                (tip.saturating_sub(height) as u32 + 1, Some(height), s.block_hash)
+            }
+            _ => (0, None, None),
+        };
+
+        let reorged = match (&entry.block_hash, &block_hash) {
+            // Was in a block, now back in the mempool (or gone)
+            (Some(_), None) => true,
+            // Re-mined into a different block
+            (Some(old), Some(new)) => old != new,
+            _ => false,
+        };
+
+        let new_status = if confirmations >= target {
+            "confirmed"
+        } else if reorged && confirmations == 0 {
+            "reorged"
+        } else if confirmations > 0 {
+            "confirming"
+        } else {
+            entry.status.as_str()
+        };
+
+        state
+            .cache
+            .update_tx_confirmations(&entry.txid, new_status, confirmations, block_height, block_hash.as_deref())
+            .await?;
+        if let (Some(height), Some(hash)) = (block_height, &block_hash) {
+            state.cache.record_block(height, hash).await?;
+        }
+
+        if reorged {
+            warn!(
+                "🔀 {} left block {:?}; now {} confirmation(s)",
+                entry.txid, entry.block_hash, confirmations
+            );
+            publish(state, "tx:reorged", &entry, confirmations, block_height, block_hash.as_deref()).await;
+        }
+        if new_status == "confirmed" {
+            info!("✅ {} reached {} confirmations", entry.txid, confirmations);
+            publish(state, "tx:confirmed", &entry, confirmations, block_height, block_hash.as_deref()).await;
+        }
+
+        if confirmations > 0 {
+            advised().lock().unwrap_or_else(|e| e.into_inner()).remove(&entry.txid);
+        } else if now - entry.created_at >= STUCK_AFTER_SECS {
+            // Fetched once per poll, and only when something has been waiting
+            if estimates.is_none() {
+                estimates = Some(state.fee_market.get(&backend).await.map(|fees| fees.estimates));
+            }
+            match estimates.as_ref().expect("fetched above") {
+                Ok(estimates) => {
+                    if let Err(e) = check_fee(state, &backend, &entry, now, estimates).await {
+                        warn!("Failed to check the fee of {}: {}", entry.txid, e);
+                    }
+                }
+                Err(e) => debug!("No fee estimates to check {} against: {}", entry.txid, e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Rate from the estimate with the closest target at or below `target_blocks`
+fn estimate_for(estimates: &[FeeEstimate], target_blocks: u32) -> Option<u64> {
+    estimates
+        .iter()
+        .filter(|e| e.target_blocks <= target_blocks)
+        .max_by_key(|e| e.target_blocks)
+        .map(|e| e.fee_rate.ceil() as u64)
+}
+
+/// `(expected, suggested)` sat/vB if a pending transaction paying `fee_rate` looks stuck
+fn assess_fee(fee_rate: u64, estimates: &[FeeEstimate]) -> Option<(u64, u64)> {
+    let expected = estimate_for(estimates, EXPECTED_TARGET_BLOCKS)?;
+    if fee_rate >= expected {
+        return None;
+    }
+    let suggested = estimate_for(estimates, BUMP_TARGET_BLOCKS)
+        .unwrap_or(expected)
+        .max(fee_rate + INCREMENTAL_RELAY_FEE);
+    Some((expected, suggested))
+}
+
+async fn check_fee(
+    state: &ServerState,
+    backend: &ChainBackend,
+    entry: &TxHistoryEntry,
+    now: i64,
+    estimates: &[FeeEstimate],
+) -> Result<()> {
+    let tx = backend.tx(&entry.txid).await?;
+    let (fee, weight) = tx
+        .fee
+        .zip(tx.weight)
+        .ok_or_else(|| anyhow!("backend reported no fee or weight"))?;
+    let fee_rate = fee.div_ceil(weight.div_ceil(4).max(1));
+    let Some((expected, suggested)) = assess_fee(fee_rate, estimates) else {
+        return Ok(());
+    };
+
+    {
+        let mut advised = advised().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(last) = advised.get(&entry.txid) {
+            if suggested * 100 < last * (100 + READVISE_INCREASE_PERCENT) {
+                return Ok(());
+            }
+        }
+        advised.insert(entry.txid.clone(), suggested);
+    }
+
+    let replaceable = chain::signals_rbf(&entry.raw_tx).unwrap_or(false);
+    let pending_minutes = (now - entry.created_at) / 60;
+    warn!(
+        "⏳ {} pays {} sat/vB after {} min, below the {} sat/vB estimate; suggest bumping to {} sat/vB",
+        entry.txid, fee_rate, pending_minutes, expected, suggested
+    );
+    let data = json!({
+        "txid": entry.txid,
+        "deviceId": entry.device_id,
+        "feeRate": fee_rate,
+        "expectedFeeRate": expected,
+        "suggestedFeeRate": suggested,
+        "pendingMinutes": pending_minutes,
+        "replaceable": replaceable,
+    });
+    state.events.emit("tx:stuck", data.clone());
+    post_webhook(state, "tx:stuck", &entry.txid, data).await;
+    Ok(())
+}
+
+async fn publish(
+    state: &ServerState,
+    event_type: &str,
+    entry: &TxHistoryEntry,
+    confirmations: u32,
+    block_height: Option<u64>,
+    block_hash: Option<&str>,
+) {
+    let data = json!({
+        "txid": entry.txid,
+        "deviceId": entry.device_id,
+        "confirmations": confirmations,
+        "blockHeight": block_height,
+        "blockHash": block_hash,
+        "previousBlockHash": entry.block_hash,
+    });
+    state.events.emit(event_type, data.clone());
+    post_webhook(state, event_type, &entry.txid, data).await;
+}
+
+async fn post_webhook(state: &ServerState, event_type: &str, txid: &str, data: serde_json::Value) {
+    let webhook_url = match state.cache.get_config(TX_WEBHOOK_CONFIG_KEY).await {
+        Ok(Some(url)) if !url.is_empty() => url,
+        Ok(_) => return,
+        Err(e) => {
+            error!("Failed to read {}: {}", TX_WEBHOOK_CONFIG_KEY, e);
+            return;
+        }
+    };
+
+    let payload = json!({ "type": event_type, "data": data });
+    match reqwest::Client::new().post(&webhook_url).json(&payload).send().await {
+        Ok(response) if response.status().is_success() => debug!("Posted {} webhook for {}", event_type, txid),
+        Ok(response) => warn!("{} webhook returned {}", event_type, response.status()),
+        Err(e) => warn!("Failed to post {} webhook: {}", event_type, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn underpaying_transactions_get_a_bump_suggestion() {
+        let estimates: Vec<FeeEstimate> = [(1, 40.0), (2, 30.5), (6, 12.0), (144, 2.0)]
+            .into_iter()
+            .map(|(target_blocks, fee_rate)| FeeEstimate { target_blocks, fee_rate })
+            .collect();
+        assert_eq!(assess_fee(12, &estimates), None);
+        assert_eq!(assess_fee(3, &estimates), Some((12, 31)));
+
+        // The suggestion always outbids the original
+        let low = [FeeEstimate { target_blocks: 2, fee_rate: 8.0 }, FeeEstimate { target_blocks: 6, fee_rate: 12.0 }];
+        assert_eq!(assess_fee(11, &low), Some((12, 12)));
+        assert_eq!(assess_fee(3, &estimates[3..]), None);
+    }
+}
@@ -1,11 +1,14 @@
 use axum::{
     extract::{State, Path as AxumPath, Query},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Json},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use crate::server::cache::device_cache::{DeviceCache, Network, Path, CachedBalance, PortfolioSummary};
+use crate::server::cache::PathTemplate;
+use crate::server::amounts::{amount_format, amount_unit, btc_to_sats, normalize_tag, AmountUnit};
+use crate::server::fiat::{fiat_context, FiatValue};
 use std::sync::Arc;
 use std::collections::HashMap;
 use tracing::{info, error, debug, warn};
@@ -222,6 +225,105 @@ pub async fn delete_path(State(cache): State<Arc<DeviceCache>>, AxumPath(id): Ax
     }
 }
 
+// Path template endpoints - which accounts and script types each network derives
+
+pub async fn get_path_templates(State(cache): State<Arc<DeviceCache>>) -> impl IntoResponse {
+    match cache.get_path_templates().await {
+        Ok(templates) => (StatusCode::OK, Json(templates)).into_response(),
+        Err(e) => {
+            error!("Failed to get path templates: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get path templates: {}", e)).into_response()
+        }
+    }
+}
+
+pub async fn get_path_template(State(cache): State<Arc<DeviceCache>>, AxumPath(id): AxumPath<i64>) -> impl IntoResponse {
+    match cache.get_path_template(id).await {
+        Ok(Some(template)) => (StatusCode::OK, Json(template)).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, format!("Path template with ID {} not found", id)).into_response(),
+        Err(e) => {
+            error!("Failed to get path template {}: {}", id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get path template: {}", e)).into_response()
+        }
+    }
+}
+
+/// Add the paths a changed template now asks for; frontload derives their keys
+async fn apply_path_templates(cache: &DeviceCache) {
+    match cache.ensure_template_paths().await {
+        Ok(added) if added > 0 => info!("Added {} templated paths; they are derived on the next frontload", added),
+        Ok(_) => {}
+        Err(e) => warn!("Failed to add templated paths: {}", e),
+    }
+}
+
+pub async fn post_path_template(State(cache): State<Arc<DeviceCache>>, Json(template): Json<PathTemplate>) -> impl IntoResponse {
+    if let Err(message) = template.validate() {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    match cache.get_path_templates().await {
+        Ok(existing) if existing.iter().any(|t| t.network == template.network) => {
+            return (StatusCode::CONFLICT, format!("Network {} already has a path template", template.network)).into_response();
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to get path templates: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get path templates: {}", e)).into_response();
+        }
+    }
+
+    match cache.add_path_template(&template).await {
+        Ok(id) => {
+            info!("Added path template for {} with ID {}", template.network, id);
+            apply_path_templates(&cache).await;
+            (StatusCode::CREATED, format!("{{ \"id\": {} }}", id)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to add path template: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to add path template: {}", e)).into_response()
+        }
+    }
+}
+
+pub async fn put_path_template(State(cache): State<Arc<DeviceCache>>, AxumPath(id): AxumPath<i64>, Json(template): Json<PathTemplate>) -> impl IntoResponse {
+    if let Err(message) = template.validate() {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+
+    match cache.update_path_template(id, &template).await {
+        Ok(_) => {
+            info!("Updated path template {} for {}", id, template.network);
+            apply_path_templates(&cache).await;
+            (StatusCode::OK, format!("Path template with ID {} updated", id)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to update path template: {}", e);
+            if e.to_string().contains("not found") {
+                (StatusCode::NOT_FOUND, format!("Path template with ID {} not found", id)).into_response()
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update path template: {}", e)).into_response()
+            }
+        }
+    }
+}
+
+pub async fn delete_path_template(State(cache): State<Arc<DeviceCache>>, AxumPath(id): AxumPath<i64>) -> impl IntoResponse {
+    match cache.delete_path_template(id).await {
+        Ok(_) => {
+            info!("Deleted path template with ID {}", id);
+            (StatusCode::OK, format!("Path template with ID {} deleted", id)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to delete path template: {}", e);
+            if e.to_string().contains("not found") {
+                (StatusCode::NOT_FOUND, format!("Path template with ID {} not found", id)).into_response()
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete path template: {}", e)).into_response()
+            }
+        }
+    }
+}
+
 /// Query parameters for filtering pubkeys by network
 #[derive(Debug, Deserialize)]
 pub struct GetPubkeysQuery {
@@ -263,7 +365,7 @@ pub async fn get_pubkeys(
     };
     
     // Get paths from database, filtered by network if specified
-    let paths = match cache.get_paths().await {
+    let paths = match cache.get_active_paths().await {
         Ok(all_paths) => {
             if let Some(network_filter) = &params.network {
                 all_paths.into_iter()
@@ -415,12 +517,63 @@ pub struct BalanceResponse {
     pub caip: String,
     pub pubkey: String,
     pub balance: String,
+    /// Exact balance in satoshis, when `balance` is a BTC amount
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance_sats: Option<i64>,
+    /// `balance` with the separators of the request's locale
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatted_balance: Option<String>,
+    /// Unit of `formatted_balance`; `balance` itself is always in whole coins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatted_unit: Option<AmountUnit>,
     pub price_usd: String,
     pub value_usd: String,
     pub symbol: Option<String>,
     pub network_id: Option<String>,
     pub last_updated: i64,
     pub age: String, // Human readable age like "2 minutes ago"
+    /// Value in the display currency; omitted in offline mode or without a rate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fiat: Option<FiatValue>,
+}
+
+/// Portfolio summary with its fiat equivalent
+#[derive(Debug, Serialize)]
+pub struct PortfolioSummaryResponse {
+    #[serde(flatten)]
+    pub summary: PortfolioSummary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fiat: Option<FiatValue>,
+}
+
+/// Fiat display settings
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FiatSettings {
+    pub currency: String,
+    pub offline_mode: bool,
+}
+
+/// Partial update of fiat display settings
+#[derive(Debug, Deserialize)]
+pub struct FiatSettingsUpdate {
+    pub currency: Option<String>,
+    pub offline_mode: Option<bool>,
+}
+
+/// Denomination and locale for formatted amounts
+#[derive(Debug, Serialize)]
+pub struct AmountSettings {
+    pub unit: AmountUnit,
+    /// BCP 47 tag, or `auto` to follow each request's Accept-Language
+    pub locale: String,
+}
+
+/// Partial update of amount display settings
+#[derive(Debug, Deserialize)]
+pub struct AmountSettingsUpdate {
+    /// `btc`, `bits` or `sats`
+    pub unit: Option<String>,
+    pub locale: Option<String>,
 }
 
 /// Portfolio balance request (for POST endpoint)
@@ -434,6 +587,7 @@ pub struct PortfolioBalanceRequest {
 pub async fn get_balances(
     State(cache): State<Arc<DeviceCache>>,
     Query(params): Query<GetBalancesQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let tag = "get_balances";
     debug!("{}: Getting balances with params: {:?}", tag, params);
@@ -451,11 +605,12 @@ pub async fn get_balances(
     
     // Check if balances need refresh or force refresh is requested
     let force_refresh = params.force_refresh.unwrap_or(false);
+    let offline = cache.is_offline_mode().await.unwrap_or(false);
     let needs_refresh = match cache.balances_need_refresh(&device_id).await {
-        Ok(needs) => needs || force_refresh,
+        Ok(needs) => !offline && (needs || force_refresh),
         Err(e) => {
             error!("{}: Error checking refresh status: {}", tag, e);
-            true // Default to refresh on error
+            !offline // Default to refresh on error
         }
     };
     
@@ -480,6 +635,9 @@ pub async fn get_balances(
         }
     };
     
+    let fiat = fiat_context(&cache).await;
+    let format = amount_format(&cache, &headers).await;
+    
     // Filter by network if specified
     let filtered_balances: Vec<BalanceResponse> = balances.into_iter()
         .filter(|balance| {
@@ -490,16 +648,23 @@ pub async fn get_balances(
                 true
             }
         })
-        .map(|balance| BalanceResponse {
-            caip: balance.caip,
-            pubkey: balance.pubkey,
-            balance: balance.balance,
-            price_usd: balance.price_usd,
-            value_usd: balance.value_usd,
-            symbol: balance.symbol,
-            network_id: balance.network_id,
-            last_updated: balance.last_updated,
-            age: format_age(balance.last_updated),
+        .map(|balance| {
+            let balance_sats = btc_to_sats(&balance.balance);
+            BalanceResponse {
+                fiat: fiat.as_ref().and_then(|f| f.balance_value(&balance.price_usd, &balance.value_usd, balance.last_updated)),
+                caip: balance.caip,
+                pubkey: balance.pubkey,
+                balance: balance.balance,
+                balance_sats,
+                formatted_balance: balance_sats.map(|sats| format.format(sats)),
+                formatted_unit: balance_sats.map(|_| format.unit),
+                price_usd: balance.price_usd,
+                value_usd: balance.value_usd,
+                symbol: balance.symbol,
+                network_id: balance.network_id,
+                last_updated: balance.last_updated,
+                age: format_age(balance.last_updated),
+            }
         })
         .collect();
     
@@ -510,6 +675,7 @@ pub async fn get_balances(
 /// Get portfolio balances for specific caip/pubkey pairs
 pub async fn post_portfolio_balances(
     State(cache): State<Arc<DeviceCache>>,
+    headers: HeaderMap,
     Json(requests): Json<Vec<PortfolioBalanceRequest>>,
 ) -> impl IntoResponse {
     let tag = "post_portfolio_balances";
@@ -527,11 +693,12 @@ pub async fn post_portfolio_balances(
     };
     
     // Check if balances need refresh
+    let offline = cache.is_offline_mode().await.unwrap_or(false);
     let needs_refresh = match cache.balances_need_refresh(&device_id).await {
-        Ok(needs) => needs,
+        Ok(needs) => !offline && needs,
         Err(e) => {
             error!("{}: Error checking refresh status: {}", tag, e);
-            true
+            !offline
         }
     };
     
@@ -561,20 +728,30 @@ pub async fn post_portfolio_balances(
         .map(|balance| ((balance.caip.clone(), balance.pubkey.clone()), balance))
         .collect();
     
+    let fiat = fiat_context(&cache).await;
+    let format = amount_format(&cache, &headers).await;
+    
     // Build response for each request
     let mut responses = Vec::new();
     for request in requests {
         if let Some(cached_balance) = balance_map.get(&(request.caip.clone(), request.pubkey.clone())) {
+            let balance_sats = btc_to_sats(&cached_balance.balance);
             responses.push(BalanceResponse {
                 caip: cached_balance.caip.clone(),
                 pubkey: cached_balance.pubkey.clone(),
                 balance: cached_balance.balance.clone(),
+                balance_sats,
+                formatted_balance: balance_sats.map(|sats| format.format(sats)),
+                formatted_unit: balance_sats.map(|_| format.unit),
                 price_usd: cached_balance.price_usd.clone(),
                 value_usd: cached_balance.value_usd.clone(),
                 symbol: cached_balance.symbol.clone(),
                 network_id: cached_balance.network_id.clone(),
                 last_updated: cached_balance.last_updated,
                 age: format_age(cached_balance.last_updated),
+                fiat: fiat.as_ref().and_then(|f| {
+                    f.balance_value(&cached_balance.price_usd, &cached_balance.value_usd, cached_balance.last_updated)
+                }),
             });
         } else {
             // Return zero balance for missing data (no fallbacks)
@@ -582,12 +759,16 @@ pub async fn post_portfolio_balances(
                 caip: request.caip,
                 pubkey: request.pubkey,
                 balance: "0.00000000".to_string(),
+                balance_sats: Some(0),
+                formatted_balance: Some(format.format(0)),
+                formatted_unit: Some(format.unit),
                 price_usd: "0.00".to_string(),
                 value_usd: "0.00".to_string(),
                 symbol: None,
                 network_id: None,
                 last_updated: chrono::Utc::now().timestamp(),
                 age: format_age(chrono::Utc::now().timestamp()),
+                fiat: None,
             });
         }
     }
@@ -611,8 +792,14 @@ pub async fn get_portfolio_summary(State(cache): State<Arc<DeviceCache>>) -> imp
         }
     };
     
+    let fiat = fiat_context(&cache).await;
+    let with_fiat = |summary: PortfolioSummary| PortfolioSummaryResponse {
+        fiat: fiat.as_ref().and_then(|f| f.total_value(&summary.total_value_usd, summary.last_updated)),
+        summary,
+    };
+    
     match cache.get_portfolio_summary(&device_id).await {
-        Ok(Some(summary)) => Json(summary).into_response(),
+        Ok(Some(summary)) => Json(with_fiat(summary)).into_response(),
         Ok(None) => {
             // Generate summary from current balances
             match cache.get_cached_balances(&device_id).await {
@@ -643,7 +830,7 @@ pub async fn get_portfolio_summary(State(cache): State<Arc<DeviceCache>>) -> imp
                         warn!("{}: Failed to save portfolio summary: {}", tag, e);
                     }
                     
-                    Json(summary).into_response()
+                    Json(with_fiat(summary)).into_response()
                 }
                 Err(e) => {
                     error!("{}: Failed to get balances for summary: {}", tag, e);
@@ -662,6 +849,114 @@ pub async fn get_portfolio_summary(State(cache): State<Arc<DeviceCache>>) -> imp
     }
 }
 
+// === Fiat Settings Endpoints ===
+
+/// Get the fiat display currency and offline mode
+pub async fn get_fiat_settings(State(cache): State<Arc<DeviceCache>>) -> impl IntoResponse {
+    let settings = async {
+        Ok::<_, anyhow::Error>(FiatSettings {
+            currency: cache.get_fiat_currency().await?,
+            offline_mode: cache.is_offline_mode().await?,
+        })
+    };
+    match settings.await {
+        Ok(settings) => Json(settings).into_response(),
+        Err(e) => {
+            error!("Failed to get fiat settings: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Failed to get fiat settings"
+            }))).into_response()
+        }
+    }
+}
+
+/// Update the fiat display currency and/or offline mode
+pub async fn put_fiat_settings(
+    State(cache): State<Arc<DeviceCache>>,
+    Json(update): Json<FiatSettingsUpdate>,
+) -> impl IntoResponse {
+    if let Some(currency) = &update.currency {
+        let currency = currency.trim().to_ascii_uppercase();
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": format!("Invalid currency code '{}'", currency)
+            }))).into_response();
+        }
+        if let Err(e) = cache.set_config("fiat_currency", &currency, Some("Display currency for fiat equivalents (ISO 4217 code)")).await {
+            error!("Failed to set fiat currency: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+        info!("💱 Fiat display currency set to {}", currency);
+    }
+    if let Some(offline_mode) = update.offline_mode {
+        let value = if offline_mode { "true" } else { "false" };
+        if let Err(e) = cache.set_config("offline_mode", value, Some("When true, no price or exchange rate requests are made and fiat values are omitted")).await {
+            error!("Failed to set offline mode: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+        info!("Offline mode {}", if offline_mode { "enabled" } else { "disabled" });
+    }
+    get_fiat_settings(State(cache)).await.into_response()
+}
+
+// === Amount Settings Endpoints ===
+
+/// Get the preferred denomination and locale for formatted amounts
+pub async fn get_amount_settings(State(cache): State<Arc<DeviceCache>>) -> impl IntoResponse {
+    let locale = match cache.get_config("amount_locale").await {
+        Ok(locale) => locale.unwrap_or_else(|| "auto".to_string()),
+        Err(e) => {
+            error!("Failed to get amount settings: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": "Failed to get amount settings"
+            }))).into_response();
+        }
+    };
+    Json(AmountSettings { unit: amount_unit(&cache).await, locale }).into_response()
+}
+
+/// Update the preferred denomination and/or locale for formatted amounts
+pub async fn put_amount_settings(
+    State(cache): State<Arc<DeviceCache>>,
+    Json(update): Json<AmountSettingsUpdate>,
+) -> impl IntoResponse {
+    if let Some(unit) = &update.unit {
+        let unit = match AmountUnit::parse(unit) {
+            Some(unit) => unit,
+            None => {
+                return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                    "error": format!("Invalid unit '{}', expected btc, bits or sats", unit)
+                }))).into_response();
+            }
+        };
+        if let Err(e) = cache.set_config("amount_unit", unit.as_str(), Some("Denomination for formatted amounts in API responses: btc, bits or sats")).await {
+            error!("Failed to set amount unit: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+        info!("Amount unit set to {}", unit.as_str());
+    }
+    if let Some(locale) = &update.locale {
+        let locale = if locale.trim().eq_ignore_ascii_case("auto") {
+            "auto".to_string()
+        } else {
+            match normalize_tag(locale) {
+                Some(locale) => locale,
+                None => {
+                    return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                        "error": format!("Invalid locale '{}'", locale)
+                    }))).into_response();
+                }
+            }
+        };
+        if let Err(e) = cache.set_config("amount_locale", &locale, Some("Locale for formatted amounts in API responses (BCP 47 tag such as de-DE), or auto to follow the Accept-Language header")).await {
+            error!("Failed to set amount locale: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+        info!("Amount locale set to {}", locale);
+    }
+    get_amount_settings(State(cache)).await.into_response()
+}
+
 /// Fetch balances from Pioneer API and cache them
 async fn refresh_balances_from_pioneer(cache: &DeviceCache, device_id: &str) -> Result<()> {
     let tag = "refresh_balances_from_pioneer";
@@ -671,7 +966,7 @@ async fn refresh_balances_from_pioneer(cache: &DeviceCache, device_id: &str) ->
     info!("{}: Using Pioneer server URL: {}", tag, pioneer_url);
     
     // Get all pubkeys for building asset query
-    let paths = cache.get_paths().await?;
+    let paths = cache.get_active_paths().await?;
     let mut asset_queries = Vec::new();
     
     for path in paths {
@@ -996,9 +1291,13 @@ pub fn v2_router(cache: Arc<DeviceCache>) -> axum::Router {
         .route("/networks", get(get_networks).post(post_network))
         .route("/paths", get(get_paths).post(post_path))
         .route("/paths/:id", get(get_path).put(put_path).delete(delete_path))
+        .route("/paths/templates", get(get_path_templates).post(post_path_template))
+        .route("/paths/templates/:id", get(get_path_template).put(put_path_template).delete(delete_path_template))
         .route("/pubkeys", get(get_pubkeys))
         .route("/balances", get(get_balances))
         .route("/portfolio", post(post_portfolio_balances))
         .route("/portfolio/summary", get(get_portfolio_summary))
+        .route("/settings/fiat", get(get_fiat_settings).put(put_fiat_settings))
+        .route("/settings/amounts", get(get_amount_settings).put(put_amount_settings))
         .with_state(cache)
 }
@@ -0,0 +1,146 @@
+//! The device's Bitcoin wallet as seen from the cache and the chain backend:
+//! which script types exist, which cached addresses belong to which type, and
+//! the UTXOs sitting on them.
+
+use anyhow::{anyhow, Result};
+use bitcoin::Network;
+
+use crate::messages::{self, Message};
+use crate::transport::ProtocolAdapter;
+
+use super::address_validation::validate_address;
+use super::chain::{ChainBackend, EsploraUtxo};
+use super::ServerState;
+
+const HARDENED: u32 = 0x8000_0000;
+
+/// Smallest output relayed by default policy
+pub(crate) const DUST_LIMIT_SATS: u64 = 546;
+/// Version, locktime, input/output counts and the segwit marker
+pub(crate) const TX_OVERHEAD_VBYTES: u64 = 11;
+
+pub(crate) struct ScriptTypeInfo {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub purpose: u32,
+    pub input_vbytes: u64,
+    pub output_vbytes: u64,
+}
+
+/// Oldest first; a migration only moves funds towards later entries
+pub(crate) const SCRIPT_TYPES: &[ScriptTypeInfo] = &[
+    ScriptTypeInfo { name: "p2pkh", label: "legacy", purpose: 44, input_vbytes: 148, output_vbytes: 34 },
+    ScriptTypeInfo { name: "p2sh-p2wpkh", label: "nested-segwit", purpose: 49, input_vbytes: 91, output_vbytes: 32 },
+    ScriptTypeInfo { name: "p2wpkh", label: "native-segwit", purpose: 84, input_vbytes: 68, output_vbytes: 31 },
+    ScriptTypeInfo { name: "p2tr", label: "taproot", purpose: 86, input_vbytes: 58, output_vbytes: 43 },
+];
+
+pub(crate) fn script_type_index(name: &str) -> Option<usize> {
+    SCRIPT_TYPES.iter().position(|t| t.name == name)
+}
+
+/// Script type of a valid mainnet address. P2SH is assumed to wrap P2WPKH,
+/// the only P2SH type the wallet derives.
+pub(crate) fn script_type_of_address(address: &str) -> Option<usize> {
+    let validated = validate_address(address, Network::Bitcoin).ok()?;
+    let name = match validated.script_type() {
+        "p2sh" => "p2sh-p2wpkh",
+        name => name,
+    };
+    script_type_index(name)
+}
+
+pub(crate) struct WalletUtxo {
+    pub address: String,
+    pub path: Vec<u32>,
+    pub script_type: usize,
+    pub utxo: EsploraUtxo,
+}
+
+pub(crate) struct WalletState {
+    pub device_id: String,
+    /// Cached addresses with their derivation path and script type
+    pub addresses: Vec<(String, Vec<u32>, usize)>,
+    pub utxos: Vec<WalletUtxo>,
+}
+
+/// Cached Bitcoin addresses of the current device and their UTXOs
+pub(crate) async fn load_wallet(state: &ServerState, chain: &ChainBackend) -> Result<WalletState> {
+    let device_id = state
+        .cache
+        .get_device_id()
+        .ok_or_else(|| anyhow!("No device available"))?;
+
+    let mut addresses = Vec::new();
+    let mut utxos = Vec::new();
+    for (address, path) in state.cache.get_cached_address_paths(&device_id, "Bitcoin").await? {
+        let script_type = match script_type_of_address(&address) {
+            Some(script_type) => script_type,
+            None => continue,
+        };
+        for utxo in chain.address_utxos(&address).await? {
+            utxos.push(WalletUtxo {
+                address: address.clone(),
+                path: path.clone(),
+                script_type,
+                utxo,
+            });
+        }
+        addresses.push((address, path, script_type));
+    }
+    Ok(WalletState { device_id, addresses, utxos })
+}
+
+/// Path of the next receive (`change = 0`) or change (`change = 1`) address in
+/// account 0 of a script type: one past the highest index in the cache
+pub(crate) async fn next_address_path(state: &ServerState, script_type: usize, change: u32) -> Result<Vec<u32>> {
+    let device_id = state
+        .cache
+        .get_device_id()
+        .ok_or_else(|| anyhow!("No device available"))?;
+    let prefix = [SCRIPT_TYPES[script_type].purpose | HARDENED, HARDENED, HARDENED, change];
+    let next = state
+        .cache
+        .get_cached_address_paths(&device_id, "Bitcoin")
+        .await?
+        .values()
+        .filter(|path| path.len() == 5 && path[..4] == prefix)
+        .map(|path| path[4] + 1)
+        .max()
+        .unwrap_or(0);
+    Ok([&prefix[..], &[next]].concat())
+}
+
+/// First firmware that derives BIP-86 keys and signs taproot inputs
+pub(crate) const TAPROOT_MIN_FIRMWARE: (u32, u32, u32) = (7, 10, 0);
+
+/// Whether firmware `major.minor.patch` handles taproot. A version the device
+/// didn't report is given the benefit of the doubt; the device refuses what
+/// it can't do anyway.
+pub(crate) fn supports_taproot(major: Option<u32>, minor: Option<u32>, patch: Option<u32>) -> bool {
+    match (major, minor, patch) {
+        (Some(major), Some(minor), Some(patch)) => (major, minor, patch) >= TAPROOT_MIN_FIRMWARE,
+        _ => true,
+    }
+}
+
+/// Ask the device for its firmware version and fail unless it handles taproot
+pub(crate) fn require_taproot(transport: &mut dyn ProtocolAdapter) -> Result<()> {
+    let features = match transport.with_standard_handler().handle(messages::GetFeatures {}.into())? {
+        Message::Features(features) => features,
+        other => return Err(anyhow!("Unexpected response to GetFeatures: {:?}", other.message_type())),
+    };
+    if supports_taproot(features.major_version, features.minor_version, features.patch_version) {
+        return Ok(());
+    }
+    let (major, minor, patch) = TAPROOT_MIN_FIRMWARE;
+    Err(anyhow!(
+        "Taproot requires firmware {}.{}.{} or later; the device runs {}.{}.{}",
+        major,
+        minor,
+        patch,
+        features.major_version.unwrap_or_default(),
+        features.minor_version.unwrap_or_default(),
+        features.patch_version.unwrap_or_default(),
+    ))
+}
@@ -0,0 +1,247 @@
+//! Advisory locks on long-running wallet operations.
+//!
+//! `device_mutex` keeps two device calls from interleaving, but a frontload or
+//! a firmware update is many calls long, and two signing requests can each
+//! select the same UTXOs. Such operations hold a [`WalletLock`] for their whole
+//! run. One that conflicts with a running operation waits for it to finish (up
+//! to [`LOCK_WAIT`]) and otherwise fails with [`WalletBusy`], naming the
+//! operation in the way.
+//!
+//! Firmware updates, wipes and device setup exclude everything else; frontloads
+//! and xpub syncs exclude each other; signing requests only exclude those
+//! spending one of the same outpoints. A sweep spends every coin and so
+//! excludes all signing.
+
+use axum::http::StatusCode;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::time::{timeout_at, Duration, Instant};
+use tracing::info;
+use utoipa::ToSchema;
+
+use super::routes::ApiError;
+
+/// How long a conflicting operation waits before giving up
+pub const LOCK_WAIT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletOperation {
+    FirmwareUpdate,
+    Wipe,
+    /// Recovery, reset or loading a seed
+    DeviceSetup,
+    Frontload,
+    XpubSync,
+    Sign,
+    Sweep,
+}
+
+impl WalletOperation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::FirmwareUpdate => "firmware_update",
+            Self::Wipe => "wipe",
+            Self::DeviceSetup => "device_setup",
+            Self::Frontload => "frontload",
+            Self::XpubSync => "xpub_sync",
+            Self::Sign => "sign",
+            Self::Sweep => "sweep",
+        }
+    }
+
+    fn exclusive(self) -> bool {
+        matches!(self, Self::FirmwareUpdate | Self::Wipe | Self::DeviceSetup)
+    }
+}
+
+/// An operation currently holding a wallet lock
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HeldOperation {
+    pub id: u64,
+    pub operation: WalletOperation,
+    /// Outpoints (`txid:vout`) being spent; empty unless signing
+    pub outpoints: Vec<String>,
+    /// Unix seconds
+    pub started_at: i64,
+}
+
+impl HeldOperation {
+    fn conflicts_with(&self, other: &HeldOperation) -> bool {
+        use WalletOperation::*;
+        if self.operation.exclusive() || other.operation.exclusive() {
+            return true;
+        }
+        match (self.operation, other.operation) {
+            (Frontload | XpubSync, Frontload | XpubSync) => true,
+            (Sweep, Sign | Sweep) | (Sign, Sweep) => true,
+            (Sign, Sign) => self
+                .outpoints
+                .iter()
+                .any(|outpoint| other.outpoints.contains(outpoint)),
+            _ => false,
+        }
+    }
+}
+
+/// A conflicting operation did not finish within [`LOCK_WAIT`]
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Blocked by {} (running for {running_secs}s); try again when it finishes", blocked_by.as_str())]
+pub struct WalletBusy {
+    pub blocked_by: WalletOperation,
+    pub running_secs: i64,
+}
+
+impl From<WalletBusy> for ApiError {
+    fn from(busy: WalletBusy) -> Self {
+        ApiError::new(StatusCode::CONFLICT, busy.to_string()).with_details(serde_json::json!({
+            "blockedBy": busy.blocked_by,
+            "runningSecs": busy.running_secs,
+        }))
+    }
+}
+
+#[derive(Default)]
+struct LocksInner {
+    held: Mutex<Vec<HeldOperation>>,
+    released: Notify,
+    next_id: AtomicU64,
+}
+
+#[derive(Clone, Default)]
+pub struct WalletLocks {
+    inner: Arc<LocksInner>,
+}
+
+impl WalletLocks {
+    /// Take the lock for `operation`, waiting up to [`LOCK_WAIT`] for
+    /// conflicting operations to finish
+    pub async fn acquire(
+        &self,
+        operation: WalletOperation,
+        outpoints: impl IntoIterator<Item = String>,
+    ) -> Result<WalletLock, WalletBusy> {
+        self.acquire_within(operation, outpoints, LOCK_WAIT).await
+    }
+
+    pub async fn acquire_within(
+        &self,
+        operation: WalletOperation,
+        outpoints: impl IntoIterator<Item = String>,
+        wait: Duration,
+    ) -> Result<WalletLock, WalletBusy> {
+        let mut wanted = HeldOperation {
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+            operation,
+            outpoints: outpoints.into_iter().collect::<BTreeSet<_>>().into_iter().collect(),
+            started_at: 0,
+        };
+        let deadline = Instant::now() + wait;
+        let mut announced = false;
+        loop {
+            // Registered before checking, so a release in between still wakes us
+            let released = self.inner.released.notified();
+            let busy = {
+                let mut held = self.inner.held.lock().unwrap();
+                let now = chrono::Utc::now().timestamp();
+                match held.iter().find(|running| running.conflicts_with(&wanted)) {
+                    Some(running) => WalletBusy {
+                        blocked_by: running.operation,
+                        running_secs: now - running.started_at,
+                    },
+                    None => {
+                        wanted.started_at = now;
+                        let id = wanted.id;
+                        held.push(wanted);
+                        return Ok(WalletLock { locks: self.clone(), id });
+                    }
+                }
+            };
+            if !announced {
+                info!("⏳ {} waits for {}", operation.as_str(), busy.blocked_by.as_str());
+                announced = true;
+            }
+            if timeout_at(deadline, released).await.is_err() {
+                return Err(busy);
+            }
+        }
+    }
+
+    /// Operations holding a lock right now
+    pub fn running(&self) -> Vec<HeldOperation> {
+        self.inner.held.lock().unwrap().clone()
+    }
+
+    fn release(&self, id: u64) {
+        self.inner.held.lock().unwrap().retain(|held| held.id != id);
+        self.inner.released.notify_waiters();
+    }
+}
+
+/// Held for the duration of an operation; dropping it lets waiters proceed
+pub struct WalletLock {
+    locks: WalletLocks,
+    id: u64,
+}
+
+impl Drop for WalletLock {
+    fn drop(&mut self) {
+        self.locks.release(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: Duration = Duration::from_millis(20);
+
+    fn outpoints(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn signs_only_conflict_on_shared_outpoints() {
+        let locks = WalletLocks::default();
+        let _first = locks.acquire(WalletOperation::Sign, outpoints(&["aa:0", "bb:1"])).await.unwrap();
+        let _frontload = locks.acquire_within(WalletOperation::Frontload, [], SHORT).await.unwrap();
+        let _other = locks
+            .acquire_within(WalletOperation::Sign, outpoints(&["cc:0"]), SHORT)
+            .await
+            .unwrap();
+        let busy = locks
+            .acquire_within(WalletOperation::Sign, outpoints(&["bb:1"]), SHORT)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(busy.blocked_by, WalletOperation::Sign);
+        assert!(locks.acquire_within(WalletOperation::Sweep, [], SHORT).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn firmware_update_waits_for_frontload_to_finish() {
+        let locks = WalletLocks::default();
+        let frontload = locks.acquire(WalletOperation::Frontload, []).await.unwrap();
+        let busy = locks
+            .acquire_within(WalletOperation::FirmwareUpdate, [], SHORT)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(busy.blocked_by, WalletOperation::Frontload);
+
+        let waiter = {
+            let locks = locks.clone();
+            tokio::spawn(async move {
+                locks.acquire(WalletOperation::FirmwareUpdate, []).await.map(|_| ())
+            })
+        };
+        tokio::time::sleep(SHORT).await;
+        drop(frontload);
+        assert!(waiter.await.unwrap().is_ok());
+        assert!(locks.running().is_empty());
+    }
+}
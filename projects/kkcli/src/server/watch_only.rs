@@ -0,0 +1,319 @@
+//! Watch-only wallet files from the xpubs in the device cache, so exporting
+//! doesn't need the device plugged in.
+//!
+//! Formats:
+//! - `core`: the JSON array Bitcoin Core's `importdescriptors` takes, a
+//!   receive and a change descriptor per account
+//! - `electrum`: an Electrum wallet file for one account; Sparrow opens these too
+//! - `sparrow`: one account as a BIP-389 `<0;1>` multipath descriptor
+//! - `json`: every account with its descriptors, plus BIP-329 labels
+
+use anyhow::{anyhow, Result};
+use bitcoin::base58;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::cache::CachedXpub;
+
+const HARDENED: u32 = 0x8000_0000;
+
+// BIP-380 descriptor checksum alphabets
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+// Extended public key version bytes (BIP-32 and SLIP-132)
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+const YPUB_VERSION: [u8; 4] = [0x04, 0x9d, 0x7c, 0xb2];
+const ZPUB_VERSION: [u8; 4] = [0x04, 0xb2, 0x47, 0x46];
+
+// Addresses Core derives ahead for each descriptor
+const CORE_RANGE_END: u32 = 999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WatchOnlyFormat {
+    Core,
+    Electrum,
+    Sparrow,
+    Json,
+}
+
+impl WatchOnlyFormat {
+    /// Electrum and Sparrow hold one account per wallet
+    pub fn single_account(self) -> bool {
+        matches!(self, Self::Electrum | Self::Sparrow)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchOnlyAccount {
+    pub label: String,
+    pub script_type: String,
+    /// `m/84'/0'/0'`
+    pub path: String,
+    pub xpub: String,
+    /// ypub/zpub form, for wallets that don't read descriptors
+    pub slip132_xpub: String,
+    pub receive_descriptor: String,
+    pub change_descriptor: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchOnlyWallet {
+    /// Master key fingerprint, when known; descriptors carry no key origin without it
+    pub fingerprint: Option<String>,
+    pub label: String,
+    pub accounts: Vec<WatchOnlyAccount>,
+}
+
+fn polymod(c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7_ffff_ffff) << 5) ^ val;
+    for (bit, generator) in [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd].iter().enumerate() {
+        if (c0 >> bit) & 1 == 1 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// Eight character checksum appended after `#`
+pub fn descriptor_checksum(descriptor: &str) -> Result<String> {
+    let mut c = 1u64;
+    let mut class = 0u64;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let pos = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| anyhow!("Invalid descriptor character {:?}", ch))? as u64;
+        c = polymod(c, pos & 31);
+        class = class * 3 + (pos >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+
+    Ok((0..8).map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char).collect())
+}
+
+/// Same key with other version bytes (xpub <-> ypub/zpub)
+fn with_version(key: &str, version: [u8; 4]) -> Result<String> {
+    let mut data = base58::decode_check(key).map_err(|e| anyhow!("Invalid extended key {}: {}", key, e))?;
+    if data.len() != 78 {
+        return Err(anyhow!("Unexpected extended key length {}", data.len()));
+    }
+    data[..4].copy_from_slice(&version);
+    Ok(base58::encode_check(&data))
+}
+
+/// Master fingerprint from any depth-1 xpub, whose parent is the master key
+pub fn master_fingerprint(xpubs: &[CachedXpub]) -> Option<String> {
+    xpubs.iter().find(|x| x.path.len() == 1).and_then(|x| {
+        let data = base58::decode_check(&x.xpub).ok()?;
+        // version (4) | depth (1) | parent fingerprint (4) | ...
+        (data.len() == 78 && data[4] == 1).then(|| hex::encode(&data[5..9]))
+    })
+}
+
+pub(crate) fn format_path(path: &[u32], hardened_marker: &str) -> String {
+    path.iter()
+        .map(|i| if i & HARDENED != 0 { format!("{}{}", i & !HARDENED, hardened_marker) } else { i.to_string() })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn script_label(script_type: &str) -> Option<&'static str> {
+    match script_type {
+        "p2pkh" => Some("Legacy"),
+        "p2sh-p2wpkh" => Some("SegWit"),
+        "p2wpkh" => Some("Native SegWit"),
+        "p2tr" => Some("Taproot"),
+        _ => None,
+    }
+}
+
+/// `branch` is a number or a multipath group like `<0;1>`
+fn descriptor(fingerprint: Option<&str>, account: &WatchOnlyAccount, path: &[u32], branch: &str) -> Result<String> {
+    let origin = fingerprint
+        .map(|fp| format!("[{}/{}]", fp, format_path(path, "h")))
+        .unwrap_or_default();
+    let key = format!("{}{}/{}/*", origin, account.xpub, branch);
+    let descriptor = match account.script_type.as_str() {
+        "p2pkh" => format!("pkh({})", key),
+        "p2sh-p2wpkh" => format!("sh(wpkh({}))", key),
+        "p2wpkh" => format!("wpkh({})", key),
+        "p2tr" => format!("tr({})", key),
+        other => return Err(anyhow!("Unsupported script type: {}", other)),
+    };
+    Ok(format!("{}#{}", descriptor, descriptor_checksum(&descriptor)?))
+}
+
+/// Bitcoin accounts from the cache, with descriptors
+pub fn build_wallet(label: &str, xpubs: &[CachedXpub], fingerprint: Option<String>) -> Result<WatchOnlyWallet> {
+    let mut accounts = Vec::new();
+    for cached in xpubs.iter().filter(|x| x.coin == "Bitcoin" && x.path.len() == 3) {
+        let Some(name) = script_label(&cached.script_type) else { continue };
+        let xpub = with_version(&cached.xpub, XPUB_VERSION)?;
+        let slip132_xpub = match cached.script_type.as_str() {
+            "p2wpkh" => with_version(&xpub, ZPUB_VERSION)?,
+            "p2sh-p2wpkh" => with_version(&xpub, YPUB_VERSION)?,
+            // SLIP-132 defines no taproot version
+            _ => xpub.clone(),
+        };
+        let mut account = WatchOnlyAccount {
+            label: format!("{} {} #{}", label, name, (cached.path[2] & !HARDENED) + 1),
+            script_type: cached.script_type.clone(),
+            path: format!("m/{}", format_path(&cached.path, "'")),
+            xpub,
+            slip132_xpub,
+            receive_descriptor: String::new(),
+            change_descriptor: String::new(),
+        };
+        account.receive_descriptor = descriptor(fingerprint.as_deref(), &account, &cached.path, "0")?;
+        account.change_descriptor = descriptor(fingerprint.as_deref(), &account, &cached.path, "1")?;
+        accounts.push(account);
+    }
+    if accounts.is_empty() {
+        return Err(anyhow!("No Bitcoin account xpubs are cached; start `kkcli server` with the device connected first"));
+    }
+    accounts.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(WatchOnlyWallet { fingerprint, label: label.to_string(), accounts })
+}
+
+/// File contents for `format`. Single-account formats use `account` (a path
+/// like `m/84'/0'/0'`), defaulting to the first native segwit account.
+pub fn render(wallet: &WatchOnlyWallet, format: WatchOnlyFormat, account: Option<&str>, timestamp: u64) -> Result<String> {
+    let selected = if format.single_account() {
+        let found = match account {
+            Some(path) => wallet.accounts.iter().find(|a| a.path == path.replace('h', "'")),
+            None => wallet
+                .accounts
+                .iter()
+                .find(|a| a.script_type == "p2wpkh")
+                .or_else(|| wallet.accounts.first()),
+        };
+        Some(found.ok_or_else(|| anyhow!("No cached account {}", account.unwrap_or_default()))?)
+    } else {
+        None
+    };
+
+    let value = match (format, selected) {
+        (WatchOnlyFormat::Core, _) => Value::Array(
+            wallet
+                .accounts
+                .iter()
+                .flat_map(|a| [(&a.receive_descriptor, false), (&a.change_descriptor, true)])
+                .map(|(desc, internal)| {
+                    json!({
+                        "desc": desc,
+                        "active": true,
+                        "internal": internal,
+                        "range": [0, CORE_RANGE_END],
+                        "timestamp": timestamp,
+                    })
+                })
+                .collect(),
+        ),
+        (WatchOnlyFormat::Electrum, Some(a)) => json!({
+            "keystore": {
+                "type": "bip32",
+                "xpub": a.slip132_xpub,
+                "derivation": a.path,
+                "root_fingerprint": wallet.fingerprint,
+                "label": a.label,
+            },
+            "wallet_type": "standard",
+            "use_encryption": false,
+            "seed_version": 17,
+        }),
+        (WatchOnlyFormat::Sparrow, Some(a)) => {
+            // Multipath: Sparrow takes receive and change from one descriptor
+            let path: Vec<u32> = a
+                .path
+                .trim_start_matches("m/")
+                .split('/')
+                .map(|p| p.trim_end_matches('\'').parse::<u32>().map(|i| i | HARDENED))
+                .collect::<Result<_, _>>()?;
+            return Ok(format!("# {}\n{}\n", a.label, descriptor(wallet.fingerprint.as_deref(), a, &path, "<0;1>")?));
+        }
+        (WatchOnlyFormat::Json, _) => json!({
+            "fingerprint": wallet.fingerprint,
+            "label": wallet.label,
+            "accounts": wallet.accounts,
+            "labels": bip329_labels(wallet),
+        }),
+        (_, None) => unreachable!("single-account formats always select an account"),
+    };
+    Ok(serde_json::to_string_pretty(&value)? + "\n")
+}
+
+/// BIP-329 records naming each account's xpub
+fn bip329_labels(wallet: &WatchOnlyWallet) -> Vec<Value> {
+    wallet
+        .accounts
+        .iter()
+        .map(|a| {
+            let mut record = json!({ "type": "xpub", "ref": a.xpub, "label": a.label });
+            if let Some(fingerprint) = &wallet.fingerprint {
+                record["origin"] = json!(format!("[{}/{}]", fingerprint, a.path.trim_start_matches("m/").replace('\'', "h")));
+            }
+            record
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP-84 test vector account key (m/84'/0'/0' of the "abandon ... about" seed)
+    const ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+    fn cached() -> Vec<CachedXpub> {
+        vec![CachedXpub {
+            coin: "Bitcoin".to_string(),
+            script_type: "p2wpkh".to_string(),
+            path: vec![HARDENED | 84, HARDENED, HARDENED],
+            xpub: ZPUB.to_string(),
+        }]
+    }
+
+    #[test]
+    fn descriptors_use_plain_xpubs_with_key_origin() {
+        let wallet = build_wallet("KeepKey", &cached(), Some("73c5da0a".to_string())).unwrap();
+        let account = &wallet.accounts[0];
+        assert!(account.xpub.starts_with("xpub"));
+        assert_eq!(account.slip132_xpub, ZPUB);
+        assert!(account.receive_descriptor.starts_with(&format!("wpkh([73c5da0a/84h/0h/0h]{}/0/*)#", account.xpub)));
+        assert!(account.change_descriptor.contains("/1/*)#"));
+
+        let core: Value = serde_json::from_str(&render(&wallet, WatchOnlyFormat::Core, None, 0).unwrap()).unwrap();
+        assert_eq!(core.as_array().unwrap().len(), 2);
+        assert_eq!(core[1]["internal"], json!(true));
+
+        let sparrow = render(&wallet, WatchOnlyFormat::Sparrow, Some("m/84h/0h/0h"), 0).unwrap();
+        assert!(sparrow.contains("/<0;1>/*)#"));
+        assert!(render(&wallet, WatchOnlyFormat::Electrum, Some("m/44'/0'/0'"), 0).is_err());
+    }
+
+    #[test]
+    fn taproot_accounts_get_tr_descriptors_and_plain_xpubs() {
+        let mut xpubs = cached();
+        xpubs[0].script_type = "p2tr".to_string();
+        xpubs[0].path = vec![HARDENED | 86, HARDENED, HARDENED];
+        let wallet = build_wallet("KeepKey", &xpubs, Some("73c5da0a".to_string())).unwrap();
+        let account = &wallet.accounts[0];
+        assert_eq!(account.label, "KeepKey Taproot #1");
+        assert_eq!(account.slip132_xpub, account.xpub);
+        assert!(account.receive_descriptor.starts_with(&format!("tr([73c5da0a/86h/0h/0h]{}/0/*)#", account.xpub)));
+    }
+}
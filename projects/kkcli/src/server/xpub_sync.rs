@@ -0,0 +1,265 @@
+//! Reviewable xpub re-sync.
+//!
+//! `POST /api/v2/xpubs/sync` re-derives every active account's xpub on the
+//! device and compares them with the cache without writing anything. The
+//! result is a plan listing what would be added, changed and removed; nothing
+//! is written until the caller confirms it with
+//! `POST /api/v2/xpubs/sync/{id}/apply`. A plan is refused if the cache moved
+//! on since it was made, and forgotten after `PLAN_TTL_SECS`.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::cache::{CachedXpub, DeviceFrontloader};
+use super::wallet_locks::WalletOperation;
+use super::watch_only::format_path;
+use super::ServerState;
+
+const PLAN_TTL_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum XpubChangeKind {
+    /// Derived by the device but not cached
+    Added,
+    /// Cached, but the device derives something else
+    Changed,
+    /// Cached for an account that is no longer active
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct XpubChange {
+    pub kind: XpubChangeKind,
+    pub coin: String,
+    pub script_type: String,
+    /// Account path, e.g. `m/84'/0'/0'`
+    pub path: String,
+    #[serde(skip)]
+    pub address_n: Vec<u32>,
+    /// What the cache holds now
+    pub cached: Option<String>,
+    /// What the device derived
+    pub device: Option<String>,
+}
+
+/// The difference between the cache and the device, waiting to be applied
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct XpubSyncPlan {
+    pub id: String,
+    pub device_id: String,
+    pub changes: Vec<XpubChange>,
+    /// Accounts whose cached xpub matches the device
+    pub unchanged: usize,
+    pub created_at: i64,
+    /// Applying after this fails; request a new plan
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct XpubSyncResult {
+    pub id: String,
+    pub device_id: String,
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    /// Cached addresses of changed or removed accounts, dropped so they are
+    /// derived again from the new key
+    pub addresses_dropped: usize,
+}
+
+/// Plans computed but not yet applied or discarded
+#[derive(Clone, Default)]
+pub struct XpubSyncPlans {
+    pending: Arc<Mutex<HashMap<String, XpubSyncPlan>>>,
+}
+
+impl XpubSyncPlans {
+    async fn insert(&self, plan: XpubSyncPlan) {
+        let now = chrono::Utc::now().timestamp();
+        let mut pending = self.pending.lock().await;
+        pending.retain(|_, plan| plan.expires_at > now);
+        pending.insert(plan.id.clone(), plan);
+    }
+
+    /// Take a plan out for applying; `None` if it doesn't exist or expired
+    async fn take(&self, id: &str) -> Option<XpubSyncPlan> {
+        let plan = self.pending.lock().await.remove(id)?;
+        (plan.expires_at > chrono::Utc::now().timestamp()).then_some(plan)
+    }
+
+    pub async fn discard(&self, id: &str) -> bool {
+        self.pending.lock().await.remove(id).is_some()
+    }
+}
+
+fn change(kind: XpubChangeKind, xpub: &CachedXpub, cached: Option<&str>, device: Option<&str>) -> XpubChange {
+    XpubChange {
+        kind,
+        coin: xpub.coin.clone(),
+        script_type: xpub.script_type.clone(),
+        path: format!("m/{}", format_path(&xpub.path, "'")),
+        address_n: xpub.path.clone(),
+        cached: cached.map(str::to_string),
+        device: device.map(str::to_string),
+    }
+}
+
+/// Compare the cache with what the device derived; returns the changes and
+/// the number of matching accounts
+fn diff(cached: &[CachedXpub], derived: &[CachedXpub]) -> (Vec<XpubChange>, usize) {
+    let same_account = |a: &CachedXpub, b: &CachedXpub| a.coin == b.coin && a.script_type == b.script_type && a.path == b.path;
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+    for from_device in derived {
+        match cached.iter().find(|c| same_account(c, from_device)) {
+            Some(c) if c.xpub == from_device.xpub => unchanged += 1,
+            Some(c) => changes.push(change(XpubChangeKind::Changed, from_device, Some(&c.xpub), Some(&from_device.xpub))),
+            None => changes.push(change(XpubChangeKind::Added, from_device, None, Some(&from_device.xpub))),
+        }
+    }
+    for c in cached {
+        if !derived.iter().any(|d| same_account(c, d)) {
+            changes.push(change(XpubChangeKind::Removed, c, Some(&c.xpub), None));
+        }
+    }
+    (changes, unchanged)
+}
+
+/// Derive every account's xpub on the connected device and diff it against the cache
+pub(crate) async fn plan_xpub_sync(state: &ServerState) -> Result<XpubSyncPlan> {
+    if !super::simulated_device() && super::try_get_device().is_err() {
+        return Err(anyhow!("No KeepKey device found"));
+    }
+    let device_id = state.cache.get_device_id().ok_or_else(|| anyhow!("No KeepKey device found"))?;
+
+    let frontloader = DeviceFrontloader::new(state.cache.clone(), Arc::clone(&state.active_transport))
+        .with_progress(super::progress::event_sink(state.events.clone()));
+    let _operation = state.wallet_locks.acquire(WalletOperation::XpubSync, []).await?;
+    let derived = {
+        let _lock = state.device_mutex.lock().await;
+        frontloader.derive_xpubs(&device_id).await?
+    };
+    let cached = state.cache.get_cached_xpubs(&device_id).await?;
+    let (changes, unchanged) = diff(&cached, &derived);
+
+    let now = chrono::Utc::now().timestamp();
+    let plan = XpubSyncPlan {
+        id: Uuid::new_v4().to_string(),
+        device_id,
+        changes,
+        unchanged,
+        created_at: now,
+        expires_at: now + PLAN_TTL_SECS,
+    };
+    info!(
+        "🔁 Xpub sync plan {} for {}: {} change(s), {} unchanged",
+        plan.id,
+        plan.device_id,
+        plan.changes.len(),
+        plan.unchanged
+    );
+    state.xpub_sync.insert(plan.clone()).await;
+    Ok(plan)
+}
+
+/// Write a plan the caller reviewed. `Ok(None)` if it doesn't exist or expired.
+pub(crate) async fn apply_xpub_sync(state: &ServerState, id: &str) -> Result<Option<XpubSyncResult>> {
+    let Some(plan) = state.xpub_sync.take(id).await else {
+        return Ok(None);
+    };
+    let _operation = state.wallet_locks.acquire(WalletOperation::XpubSync, []).await?;
+
+    // The plan says what the cache held; applying over something else would
+    // overwrite changes nobody reviewed
+    let current = state.cache.get_cached_xpubs(&plan.device_id).await?;
+    for change in &plan.changes {
+        let now = current
+            .iter()
+            .find(|c| c.coin == change.coin && c.script_type == change.script_type && c.path == change.address_n)
+            .map(|c| c.xpub.as_str());
+        if now != change.cached.as_deref() {
+            warn!("Xpub sync plan {} is out of date at {} {}", id, change.script_type, change.path);
+            return Err(anyhow!("The cache changed since plan {} was made; request a new one", id));
+        }
+    }
+
+    let to_xpub = |change: &XpubChange, xpub: &Option<String>| CachedXpub {
+        coin: change.coin.clone(),
+        script_type: change.script_type.clone(),
+        path: change.address_n.clone(),
+        xpub: xpub.clone().unwrap_or_default(),
+    };
+    let upserts: Vec<CachedXpub> = plan
+        .changes
+        .iter()
+        .filter(|c| c.kind != XpubChangeKind::Removed)
+        .map(|c| to_xpub(c, &c.device))
+        .collect();
+    let removals: Vec<CachedXpub> = plan
+        .changes
+        .iter()
+        .filter(|c| c.kind == XpubChangeKind::Removed)
+        .map(|c| to_xpub(c, &c.cached))
+        .collect();
+    let addresses_dropped = state.cache.apply_xpub_changes(&plan.device_id, &upserts, &removals).await?;
+
+    let count = |kind| plan.changes.iter().filter(|c| c.kind == kind).count();
+    let result = XpubSyncResult {
+        id: plan.id,
+        device_id: plan.device_id,
+        added: count(XpubChangeKind::Added),
+        changed: count(XpubChangeKind::Changed),
+        removed: count(XpubChangeKind::Removed),
+        addresses_dropped,
+    };
+    state.events.emit("xpubs:synced", serde_json::to_value(&result)?);
+    Ok(Some(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HARDENED: u32 = 0x8000_0000;
+
+    fn xpub(script_type: &str, purpose: u32, xpub: &str) -> CachedXpub {
+        CachedXpub {
+            coin: "Bitcoin".to_string(),
+            script_type: script_type.to_string(),
+            path: vec![purpose | HARDENED, HARDENED, HARDENED],
+            xpub: xpub.to_string(),
+        }
+    }
+
+    #[test]
+    fn diff_reports_additions_changes_and_removals() {
+        let cached = [xpub("p2pkh", 44, "xpub-a"), xpub("p2wpkh", 84, "zpub-old"), xpub("p2sh-p2wpkh", 49, "ypub-gone")];
+        let derived = [xpub("p2pkh", 44, "xpub-a"), xpub("p2wpkh", 84, "zpub-new"), xpub("p2tr", 86, "xpub-tr")];
+
+        let (changes, unchanged) = diff(&cached, &derived);
+        assert_eq!(unchanged, 1);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| (c.kind, c.path.as_str(), c.cached.as_deref(), c.device.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (XpubChangeKind::Changed, "m/84'/0'/0'", Some("zpub-old"), Some("zpub-new")),
+                (XpubChangeKind::Added, "m/86'/0'/0'", None, Some("xpub-tr")),
+                (XpubChangeKind::Removed, "m/49'/0'/0'", Some("ypub-gone"), None),
+            ]
+        );
+        assert!(diff(&derived, &derived).0.is_empty());
+    }
+}
@@ -1,15 +1,20 @@
 pub mod protocol_adapter;
 pub mod usb;
 pub mod hid;
+pub mod mock;
+pub mod session;
 
 pub use protocol_adapter::*;
 pub use usb::*;
 pub use hid::*;
+pub use mock::MockDevice;
+pub use session::{DeviceSession, ReplayDevice};
 
 use crate::messages::{self, Message};
 use anyhow::{anyhow, bail, Result};
 use core::time::Duration;
 use std::io::{stdin, stdout, Write};
+use std::sync::{Arc, RwLock};
 use tracing::{info, debug};
 
 pub trait Transport {
@@ -19,6 +24,56 @@ pub trait Transport {
     fn reset(&mut self) -> Result<(), Self::Error>;
 }
 
+/// The server's device connection: a USB KeepKey, or the simulator with
+/// `--mock-device` (playing back a recorded session with `--replay`).
+/// Traffic is captured while a session recording is running.
+pub enum DeviceTransport {
+    Usb(UsbTransport<rusb::GlobalContext>),
+    Mock(MockDevice),
+    /// Shared so every connection the server opens continues the same playback
+    Replay(Arc<std::sync::Mutex<ReplayDevice>>),
+}
+
+impl Transport for DeviceTransport {
+    type Error = rusb::Error;
+    fn write(&mut self, msg: &[u8], timeout: Duration) -> Result<usize, Self::Error> {
+        let result = match self {
+            DeviceTransport::Usb(transport) => transport.write(msg, timeout),
+            DeviceTransport::Mock(device) => device.write(msg, timeout),
+            DeviceTransport::Replay(device) => device.lock().map_err(|_| rusb::Error::Other)?.write(msg, timeout),
+        };
+        session::record(session::FrameDirection::ToDevice, result.as_ref().map(|_| msg));
+        result
+    }
+    fn read(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<(), Self::Error> {
+        let start = buf.len();
+        let result = match self {
+            DeviceTransport::Usb(transport) => transport.read(buf, timeout),
+            DeviceTransport::Mock(device) => device.read(buf, timeout),
+            DeviceTransport::Replay(device) => device.lock().map_err(|_| rusb::Error::Other)?.read(buf, timeout),
+        };
+        session::record(session::FrameDirection::FromDevice, result.as_ref().map(|_| &buf[start..]));
+        result
+    }
+    fn reset(&mut self) -> Result<(), Self::Error> {
+        match self {
+            DeviceTransport::Usb(transport) => Transport::reset(transport),
+            DeviceTransport::Mock(device) => Transport::reset(device),
+            DeviceTransport::Replay(device) => Transport::reset(&mut *device.lock().map_err(|_| rusb::Error::Other)?),
+        }
+    }
+}
+
+/// Answers a PinMatrixRequest with the clicked matrix positions
+pub type PinPrompt = dyn Fn(messages::PinMatrixRequestType) -> Result<String> + Send + Sync;
+
+static PIN_PROMPT: RwLock<Option<Arc<PinPrompt>>> = RwLock::new(None);
+
+/// Take PINs from `prompt` instead of stdin (`None` restores the stdin prompt)
+pub fn set_pin_prompt(prompt: Option<Arc<PinPrompt>>) {
+    *PIN_PROMPT.write().unwrap() = prompt;
+}
+
 pub fn standard_message_handler(msg: &Message) -> Result<Option<Message>> {
     info!("StandardHandler: Processing message type: {:?}", msg.message_type());
     
@@ -31,30 +86,27 @@ pub fn standard_message_handler(msg: &Message) -> Result<Option<Message>> {
             Some(ack.into())
         }
         Message::PinMatrixRequest(x) => {
-            match x.r#type {
-                Some(t) => match messages::PinMatrixRequestType::from_i32(t)
-                    .ok_or_else(|| anyhow!("unrecognized PinMatrixRequestType ({})", t))?
-                {
-                    messages::PinMatrixRequestType::Current => {
-                        eprint!("Enter current PIN: ")
-                    }
-                    messages::PinMatrixRequestType::NewFirst => eprint!("Enter new PIN: "),
-                    messages::PinMatrixRequestType::NewSecond => {
-                        eprint!("Re-enter new PIN: ")
-                    }
-                },
+            let kind = match x.r#type {
+                Some(t) => messages::PinMatrixRequestType::from_i32(t)
+                    .ok_or_else(|| anyhow!("unrecognized PinMatrixRequestType ({})", t))?,
                 None => bail!("expected PinMatrixRequestType"),
-            }
-            stdout().flush().unwrap();
-            let mut pin = String::new();
-            stdin().read_line(&mut pin)?;
-            let pin = pin.trim();
-            Some(
-                messages::PinMatrixAck {
-                    pin: pin.to_owned(),
+            };
+            let prompt = PIN_PROMPT.read().unwrap().clone();
+            let pin = match prompt {
+                Some(prompt) => prompt(kind)?,
+                None => {
+                    match kind {
+                        messages::PinMatrixRequestType::Current => eprint!("Enter current PIN: "),
+                        messages::PinMatrixRequestType::NewFirst => eprint!("Enter new PIN: "),
+                        messages::PinMatrixRequestType::NewSecond => eprint!("Re-enter new PIN: "),
+                    }
+                    stdout().flush().unwrap();
+                    let mut pin = String::new();
+                    stdin().read_line(&mut pin)?;
+                    pin.trim().to_owned()
                 }
-                .into(),
-            )
+            };
+            Some(messages::PinMatrixAck { pin }.into())
         }
         Message::PassphraseRequest(_) => {
             eprint!("Enter BIP-39 passphrase: ");
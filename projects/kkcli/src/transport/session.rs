@@ -0,0 +1,378 @@
+//! Recording and replay of device conversations.
+//!
+//! While a recording is active every frame the server's device transport
+//! writes or reads is kept with its offset from the start of the recording,
+//! and the result can be saved as JSON and attached to a support bundle.
+//! PINs, passphrases, recovery words and entropy the host sends are replaced
+//! by their message type; addresses and xpubs the device returns are kept, as
+//! replay needs them.
+//!
+//! [`ReplayDevice`] is the simulator with a recording loaded: when the host
+//! sends the next request of the recorded session, it answers with the
+//! recorded responses (and errors, and optionally delays), so a user's failing
+//! signing or recovery flow runs again on a maintainer's machine. Requests
+//! that aren't next in the recording, such as the server's own startup
+//! traffic, are answered by the simulator and playback picks up again at the
+//! next one that is.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use super::{MockDevice, Transport};
+use crate::messages::Message;
+
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+// Enough for a large multi-input signing; older frames are dropped past this
+const MAX_RECORDED_FRAMES: usize = 20_000;
+// Host messages whose contents must never leave the user's machine
+const REDACTED_MESSAGES: &[&str] = &["PinMatrixAck", "PassphraseAck", "WordAck", "CharacterAck", "LoadDevice", "EntropyAck"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameDirection {
+    ToDevice,
+    FromDevice,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedFrame {
+    /// Milliseconds since the recording started
+    pub at_ms: u64,
+    pub direction: FrameDirection,
+    pub message_type: String,
+    /// Framed message as hex; empty for failed reads and redacted messages
+    pub data: String,
+    #[serde(default)]
+    pub redacted: bool,
+    /// Transport error instead of a frame (`Timeout`, `Pipe`, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceSession {
+    pub version: u32,
+    pub recorded_at: String,
+    /// What the user was doing, as given when the recording was started
+    pub operation: Option<String>,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl DeviceSession {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read device session {}", path.display()))?;
+        let session: DeviceSession = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse device session {}", path.display()))?;
+        if session.version > SESSION_FORMAT_VERSION {
+            anyhow::bail!("Device session format {} is newer than this kkcli supports", session.version);
+        }
+        Ok(session)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write device session {}", path.display()))
+    }
+}
+
+fn message_type_of(frame: &[u8]) -> String {
+    match Message::decode(&mut &frame[..]) {
+        Ok(message) => format!("{:?}", message.message_type()),
+        Err(_) => "Unknown".to_string(),
+    }
+}
+
+struct ActiveRecording {
+    started: Instant,
+    session: DeviceSession,
+}
+
+static RECORDING: Mutex<Option<ActiveRecording>> = Mutex::new(None);
+
+fn recording() -> std::sync::MutexGuard<'static, Option<ActiveRecording>> {
+    RECORDING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Start recording device traffic; `false` if a recording is already running
+pub fn start_recording(operation: Option<String>) -> bool {
+    let mut active = recording();
+    if active.is_some() {
+        return false;
+    }
+    info!("⏺️ Recording device session{}", operation.as_deref().map(|o| format!(" for {}", o)).unwrap_or_default());
+    *active = Some(ActiveRecording {
+        started: Instant::now(),
+        session: DeviceSession {
+            version: SESSION_FORMAT_VERSION,
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            operation,
+            frames: Vec::new(),
+        },
+    });
+    true
+}
+
+/// Stop recording and hand back what was captured
+pub fn stop_recording() -> Option<DeviceSession> {
+    let session = recording().take().map(|active| active.session);
+    if let Some(session) = &session {
+        info!("⏹️ Device session recording stopped ({} frames)", session.frames.len());
+    }
+    session
+}
+
+pub fn is_recording() -> bool {
+    recording().is_some()
+}
+
+/// Called by the server transport for every frame it writes or reads
+pub(crate) fn record<E: std::fmt::Debug>(direction: FrameDirection, frame: Result<&[u8], &E>) {
+    let mut guard = recording();
+    let active = match guard.as_mut() {
+        Some(active) => active,
+        None => return,
+    };
+
+    let recorded = match frame {
+        Ok(frame) => {
+            let message_type = message_type_of(frame);
+            let redacted = direction == FrameDirection::ToDevice && REDACTED_MESSAGES.contains(&message_type.as_str());
+            RecordedFrame {
+                at_ms: active.started.elapsed().as_millis() as u64,
+                direction,
+                data: if redacted { String::new() } else { hex::encode(frame) },
+                message_type,
+                redacted,
+                error: None,
+            }
+        }
+        Err(e) => RecordedFrame {
+            at_ms: active.started.elapsed().as_millis() as u64,
+            direction,
+            message_type: String::new(),
+            data: String::new(),
+            redacted: false,
+            error: Some(format!("{:?}", e)),
+        },
+    };
+
+    let frames = &mut active.session.frames;
+    if frames.len() >= MAX_RECORDED_FRAMES {
+        frames.remove(0);
+    }
+    frames.push(recorded);
+}
+
+/// A recorded response waiting to be read, with the delay it took originally
+struct PendingResponse {
+    delay: Duration,
+    frame: RecordedFrame,
+}
+
+/// Simulator that plays back a recorded session (see the module docs)
+pub struct ReplayDevice {
+    simulator: MockDevice,
+    remaining: VecDeque<RecordedFrame>,
+    responses: VecDeque<PendingResponse>,
+    /// Sleep for the recorded gaps between request and response
+    realtime: bool,
+    replayed: usize,
+}
+
+impl ReplayDevice {
+    pub fn new(session: DeviceSession) -> Self {
+        Self {
+            simulator: MockDevice::new(),
+            remaining: session.frames.into(),
+            responses: VecDeque::new(),
+            realtime: false,
+            replayed: 0,
+        }
+    }
+
+    pub fn with_realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    /// Recorded frames not yet played back
+    pub fn remaining(&self) -> usize {
+        self.remaining.len() + self.responses.len()
+    }
+
+    fn matches(frame: &RecordedFrame, msg: &[u8]) -> bool {
+        if frame.direction != FrameDirection::ToDevice || frame.error.is_some() {
+            return false;
+        }
+        if frame.redacted {
+            frame.message_type == message_type_of(msg)
+        } else {
+            frame.data.eq_ignore_ascii_case(&hex::encode(msg))
+        }
+    }
+
+    /// Queue the device's recorded answers to the request just matched
+    fn queue_responses(&mut self, request_at_ms: u64) {
+        let mut previous_ms = request_at_ms;
+        while self.remaining.front().map_or(false, |f| f.direction == FrameDirection::FromDevice) {
+            let frame = self.remaining.pop_front().expect("front was checked");
+            let delay = Duration::from_millis(frame.at_ms.saturating_sub(previous_ms));
+            previous_ms = frame.at_ms;
+            self.responses.push_back(PendingResponse { delay, frame });
+        }
+    }
+}
+
+fn replay_error(error: &str) -> rusb::Error {
+    match error {
+        "Timeout" => rusb::Error::Timeout,
+        "Pipe" => rusb::Error::Pipe,
+        "NoDevice" => rusb::Error::NoDevice,
+        "Io" => rusb::Error::Io,
+        _ => rusb::Error::Other,
+    }
+}
+
+impl Transport for ReplayDevice {
+    type Error = rusb::Error;
+
+    fn write(&mut self, msg: &[u8], timeout: Duration) -> Result<usize, Self::Error> {
+        // A write that failed in the recording fails again once replay is underway
+        let replaying = self.replayed > 0;
+        let failed_write = self
+            .remaining
+            .front()
+            .filter(|f| f.direction == FrameDirection::ToDevice)
+            .and_then(|f| f.error.clone());
+        if let (true, Some(error)) = (replaying, failed_write) {
+            self.remaining.pop_front();
+            return Err(replay_error(&error));
+        }
+
+        if self.remaining.front().map_or(false, |f| Self::matches(f, msg)) {
+            let frame = self.remaining.pop_front().expect("front was checked");
+            info!("▶️ Replaying recorded answer to {} (frame {})", frame.message_type, self.replayed);
+            self.replayed += 1;
+            self.responses.clear();
+            self.queue_responses(frame.at_ms);
+            return Ok(msg.len());
+        }
+
+        if replaying && !self.remaining.is_empty() {
+            let expected = self.remaining.front().map(|f| f.message_type.clone()).unwrap_or_default();
+            warn!(
+                "⚠️ {} is not next in the recording (expected {}); answered by the simulator",
+                message_type_of(msg),
+                expected
+            );
+        }
+        self.simulator.write(msg, timeout)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, timeout: Duration) -> Result<(), Self::Error> {
+        let response = match self.responses.pop_front() {
+            Some(response) => response,
+            None => return self.simulator.read(buf, timeout),
+        };
+        if self.realtime {
+            std::thread::sleep(response.delay.min(timeout));
+        }
+        if let Some(error) = &response.frame.error {
+            return Err(replay_error(error));
+        }
+        let frame = hex::decode(&response.frame.data).map_err(|_| rusb::Error::Other)?;
+        buf.extend_from_slice(&frame);
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), Self::Error> {
+        self.responses.clear();
+        Transport::reset(&mut self.simulator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages;
+
+    fn encode(message: Message) -> Vec<u8> {
+        let mut out = Vec::new();
+        message.encode(&mut out).unwrap();
+        out
+    }
+
+    fn frame(at_ms: u64, direction: FrameDirection, message: Message) -> RecordedFrame {
+        let data = encode(message);
+        RecordedFrame { at_ms, direction, message_type: message_type_of(&data), data: hex::encode(data), redacted: false, error: None }
+    }
+
+    #[test]
+    fn replays_recorded_answers_around_simulator_traffic() {
+        let ping = messages::Ping { message: Some("hello".to_string()), ..Default::default() };
+        let failure = messages::Failure { code: Some(messages::FailureType::FailureOther as i32), message: Some("boom".to_string()) };
+        let session = DeviceSession {
+            version: SESSION_FORMAT_VERSION,
+            recorded_at: String::new(),
+            operation: None,
+            frames: vec![
+                frame(0, FrameDirection::ToDevice, ping.clone().into()),
+                frame(15, FrameDirection::FromDevice, failure.into()),
+                RecordedFrame {
+                    at_ms: 20,
+                    direction: FrameDirection::ToDevice,
+                    message_type: "PinMatrixAck".to_string(),
+                    data: String::new(),
+                    redacted: true,
+                    error: None,
+                },
+                RecordedFrame {
+                    at_ms: 5020,
+                    direction: FrameDirection::FromDevice,
+                    message_type: String::new(),
+                    data: String::new(),
+                    redacted: false,
+                    error: Some("Timeout".to_string()),
+                },
+            ],
+        };
+        let mut device = ReplayDevice::new(session);
+        let mut buf = Vec::new();
+
+        // The recorded Failure comes back where the simulator would have answered Success
+        device.write(&encode(ping.clone().into()), Duration::from_secs(1)).unwrap();
+        device.read(&mut buf, Duration::from_secs(1)).unwrap();
+        assert!(matches!(Message::decode(&mut buf.as_slice()).unwrap(), Message::Failure(_)));
+
+        // Requests that aren't next go to the simulator without losing our place
+        buf.clear();
+        let other = messages::Ping { message: Some("other".to_string()), ..Default::default() };
+        device.write(&encode(other.into()), Duration::from_secs(1)).unwrap();
+        device.read(&mut buf, Duration::from_secs(1)).unwrap();
+        assert!(matches!(Message::decode(&mut buf.as_slice()).unwrap(), Message::Success(_)));
+
+        // Redacted requests match on type alone
+        let pin = messages::PinMatrixAck { pin: "1234".to_string() };
+        device.write(&encode(pin.into()), Duration::from_secs(1)).unwrap();
+        assert_eq!(device.read(&mut Vec::new(), Duration::from_secs(1)), Err(rusb::Error::Timeout));
+        assert_eq!(device.remaining(), 0);
+
+        buf.clear();
+        device.write(&encode(ping.into()), Duration::from_secs(1)).unwrap();
+        device.read(&mut buf, Duration::from_secs(1)).unwrap();
+        assert!(matches!(Message::decode(&mut buf.as_slice()).unwrap(), Message::Success(_)));
+    }
+}
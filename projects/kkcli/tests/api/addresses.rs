@@ -0,0 +1,40 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+use crate::{TestServer, FIRST_RECEIVE_ADDRESS};
+
+const FIRST_RECEIVE_PATH: [u32; 5] = [0x8000_0054, 0x8000_0000, 0x8000_0000, 0, 0];
+
+#[tokio::test]
+async fn derives_receive_addresses_from_the_simulator() {
+    let server = TestServer::start().await;
+    let request = json!({ "address_n": FIRST_RECEIVE_PATH, "coin": "Bitcoin", "script_type": "p2wpkh" });
+
+    let (status, body) = server.post("/api/v1/utxo/address", request.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["address"], FIRST_RECEIVE_ADDRESS);
+    assert_eq!(body["address_n"], json!(FIRST_RECEIVE_PATH));
+
+    // The legacy route is the same handler
+    let (status, body) = server.post("/addresses/utxo", request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["address"], FIRST_RECEIVE_ADDRESS);
+}
+
+#[tokio::test]
+async fn derives_bip86_taproot_addresses() {
+    let server = TestServer::start().await;
+    // BIP-86 test vector: first receive address of the simulator's seed
+    let path = [0x8000_0056u32, 0x8000_0000, 0x8000_0000, 0, 0];
+    let (status, body) = server
+        .post("/addresses/utxo", json!({ "address_n": path, "coin": "Bitcoin", "script_type": "p2tr" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["address"], "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr");
+
+    // Unknown script types used to fall back to a p2pkh address
+    let (status, _) = server
+        .post("/addresses/utxo", json!({ "address_n": path, "coin": "Bitcoin", "script_type": "p2wsh" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,112 @@
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use crate::TestServer;
+
+#[tokio::test]
+async fn pairing_issues_an_api_key() {
+    let server = TestServer::start().await;
+
+    let (status, body) = server
+        .post("/auth/pair", json!({ "name": "API tests", "url": "http://localhost", "imageUrl": "" }))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body["apiKey"].as_str().unwrap().is_empty());
+    assert_eq!(body["scopes"], json!(["read", "address", "sign"]));
+
+    let (status, body) = server
+        .post("/auth/pair", json!({ "name": "API tests", "url": "http://localhost", "imageUrl": "", "scopes": ["spend"] }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("spend"));
+}
+
+#[tokio::test]
+async fn sensitive_endpoints_need_a_key_with_their_scope() {
+    let server = TestServer::start().await;
+    let sign_message = json!({ "address_n": [0x8000_0054u32, 0x8000_0000u32, 0x8000_0000u32, 0, 0], "message": "hi" });
+
+    // Reads and addresses stay open; signing and wipes need a paired key
+    let (status, _) = server.get("/v2/portfolio/summary").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = server.post("/api/v1/bitcoin/sign-message", sign_message.clone()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = server
+        .request(Method::POST, "/api/v1/bitcoin/sign-message", Some("never-paired"), Some(sign_message.clone()))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = server.post("/system/info/wipe-device", json!({ "confirmation": "wipe my keepkey" })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let reader = server.pair(&["read"]).await;
+    let (status, body) = server
+        .request(Method::POST, "/api/v1/bitcoin/sign-message", Some(&reader), Some(sign_message.clone()))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(body["error"].as_str().unwrap().contains("sign"));
+
+    let signer = server.pair(&["sign"]).await;
+    let (status, _) = server
+        .request(Method::POST, "/api/v1/bitcoin/sign-message", Some(&signer), Some(sign_message.clone()))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = server
+        .request(Method::POST, "/system/info/wipe-device", Some(&signer), Some(json!({ "confirmation": "wipe my keepkey" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, clients) = server.request(Method::GET, "/api/v2/clients", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let granted: Vec<_> = clients.as_array().unwrap().iter().map(|c| c["grantedScopes"].clone()).collect();
+    assert!(granted.contains(&json!(["read", "sign"])));
+
+    // With api_auth off, keyless clients are trusted as before
+    server.state.cache.set_config("api_auth", "off", None).await.unwrap();
+    let (status, _) = server.post("/api/v1/bitcoin/sign-message", sign_message).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn dashboard_tokens_are_limited_to_their_scope() {
+    let server = TestServer::start().await;
+    let admin = server.pair(&["admin"]).await;
+
+    let (status, created) = server
+        .request(Method::POST, "/api/v2/dashboard-tokens", Some(&admin), Some(json!({ "label": "Office dashboard" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let token = created["token"].as_str().unwrap().to_string();
+    let id = created["id"].as_str().unwrap().to_string();
+    assert!(token.starts_with("kkro_"));
+    assert_eq!(created["label"], "Office dashboard");
+
+    let (status, _) = server.request(Method::GET, "/api/health", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let address_request = json!({ "address_n": [0x8000_0054u32, 0x8000_0000u32, 0x8000_0000u32, 0, 0], "coin": "Bitcoin", "script_type": "p2wpkh" });
+    let (status, _) = server
+        .request(Method::POST, "/addresses/utxo", Some(&token), Some(address_request))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Anything that could sign or change settings is refused before routing
+    let (status, body) = server
+        .request(Method::POST, "/api/v1/bitcoin/sign-message", Some(&token), Some(json!({ "address_n": [], "message": "hi" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(body["error"].is_string());
+    let (status, _) = server
+        .request(Method::POST, "/api/v2/dashboard-tokens", Some(&token), Some(json!({ "label": "escalated" })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = server.request(Method::GET, "/api/health", Some("kkro_not-a-token"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = server.request(Method::DELETE, &format!("/api/v2/dashboard-tokens/{}", id), Some(&admin), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = server.request(Method::GET, "/api/health", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = server.request(Method::DELETE, &format!("/api/v2/dashboard-tokens/{}", id), Some(&admin), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "not_found");
+}
@@ -0,0 +1,27 @@
+use axum::http::StatusCode;
+
+use crate::TestServer;
+
+#[tokio::test]
+async fn health_and_device_context() {
+    let server = TestServer::start().await;
+
+    let (status, body) = server.get("/api/health").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ok");
+    assert_eq!(body["service"], "KeepKey CLI API");
+
+    // Frontloading recorded the simulator as the current device
+    let device_id = server.state.cache.get_device_id().expect("device id after frontload");
+    let (status, features) = server.get("/system/info/get-features").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(features["device_id"].is_string());
+
+    let (status, pubkeys) = server.get("/v2/pubkeys").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!pubkeys.as_array().unwrap().is_empty(), "no pubkeys cached for {}", device_id);
+
+    let (status, summary) = server.get("/v2/portfolio/summary").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(summary.is_object());
+}
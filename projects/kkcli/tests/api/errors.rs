@@ -0,0 +1,55 @@
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use serde_json::json;
+
+use crate::TestServer;
+
+#[tokio::test]
+async fn bad_requests_map_to_client_errors() {
+    let server = TestServer::start().await;
+
+    let malformed = Request::builder()
+        .method(Method::POST)
+        .uri("/api/v1/utxo/address")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{\"address_n\": ["))
+        .unwrap();
+    let (status, _) = server.send(malformed).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let missing_field = json!({ "address_n": [0x8000_0054u32, 0x8000_0000u32, 0x8000_0000u32, 0, 0] });
+    let (status, _) = server.post("/api/v1/utxo/address", missing_field).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let not_json = Request::builder()
+        .method(Method::POST)
+        .uri("/api/v2/approvals/anything")
+        .body(Body::from("approve=true"))
+        .unwrap();
+    let (status, _) = server.send(not_json).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let (status, _) = server.get("/api/v2/no-such-endpoint").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn api_errors_carry_a_kind_and_message() {
+    let server = TestServer::start().await;
+    let admin = server.pair(&["admin"]).await;
+
+    let (status, body) = server
+        .request(Method::POST, "/api/v2/dashboard-tokens", Some(&admin), Some(json!({ "label": "  " })))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "error");
+    assert_eq!(body["message"], "label is required");
+
+    let (status, body) = server.post("/api/v2/approvals/unknown", json!({ "approve": true })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "not_found");
+    assert!(body["message"].as_str().unwrap().contains("unknown"));
+
+    let (status, _) = server.request(Method::DELETE, "/api/v2/xpubs/sync/unknown", Some(&admin), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::TestServer;
+
+type Socket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn next_event(socket: &mut Socket) -> Value {
+    loop {
+        match socket.next().await.expect("socket closed").expect("socket error") {
+            Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn websocket_clients_receive_server_events() {
+    let server = TestServer::start().await;
+    let addr = server.listen().await;
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+    let hello = next_event(&mut socket).await;
+    assert_eq!(hello["type"], "connected");
+
+    // The handler subscribes to the event bus just after greeting, so keep
+    // publishing until the event comes through
+    let data = json!({ "txid": "ab".repeat(32), "confirmations": 1 });
+    let received = tokio::time::timeout(Duration::from_secs(10), async {
+        let mut publish = tokio::time::interval(Duration::from_millis(50));
+        loop {
+            tokio::select! {
+                _ = publish.tick() => server.state.events.emit("tx:confirmed", data.clone()),
+                event = next_event(&mut socket) => if event["type"] == "tx:confirmed" {
+                    return event;
+                },
+            }
+        }
+    })
+    .await
+    .expect("tx:confirmed never reached the WebSocket client");
+    assert_eq!(received["data"], data);
+}
+
+#[tokio::test]
+async fn approval_requests_are_published() {
+    let server = TestServer::start().await;
+    server.state.cache.set_config("remote_approval", "true", None).await.unwrap();
+    let mut events = server.state.events.subscribe();
+
+    let signer = server.clone();
+    let job = tokio::spawn(async move {
+        let request = json!({ "address_n": [0x8000_0054u32, 0x8000_0000u32, 0x8000_0000u32, 0, 0], "message": "notify me" });
+        signer.post("/api/v1/bitcoin/sign-message", request).await
+    });
+
+    let requested = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let event = events.recv().await.unwrap();
+            if event.event_type == "approval:requested" {
+                return event.data;
+            }
+        }
+    })
+    .await
+    .expect("no approval:requested event");
+    assert_eq!(requested["message"], "notify me");
+
+    let uri = format!("/api/v2/approvals/{}", requested["id"].as_str().unwrap());
+    let (status, _) = server.post(&uri, json!({ "approve": false })).await;
+    assert_eq!(status, axum::http::StatusCode::NO_CONTENT);
+    job.await.unwrap();
+}
@@ -0,0 +1,102 @@
+//! REST API tests. Every test boots the full router in-process against the
+//! device simulator (public test seed, fixture balances) with a cache in a
+//! temporary directory, so no KeepKey, network or running server is needed.
+//!
+//! Run with `cargo test --test api`.
+
+mod addresses;
+mod auth;
+mod context;
+mod errors;
+mod events;
+mod signing;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use kkcli::server::{build_router, simulated_server_state, ServerState};
+use serde_json::Value;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+/// First receive address of the test seed, m/84'/0'/0'/0/0
+pub const FIRST_RECEIVE_ADDRESS: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+
+#[derive(Clone)]
+pub struct TestServer {
+    pub state: Arc<ServerState>,
+    router: Router,
+    _cache_dir: Arc<TempDir>,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        let cache_dir = tempfile::tempdir().expect("temporary cache directory");
+        let state = simulated_server_state(cache_dir.path().to_path_buf())
+            .await
+            .expect("simulated server state");
+        Self {
+            router: build_router(Arc::clone(&state)),
+            state,
+            _cache_dir: Arc::new(cache_dir),
+        }
+    }
+
+    /// Send one request through the router. The body is parsed as JSON when it
+    /// is JSON, returned as a string when it isn't, and `Null` when empty.
+    pub async fn request(&self, method: Method, uri: &str, bearer: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = bearer {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        self.send(request).await
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> (StatusCode, Value) {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+        };
+        (status, body)
+    }
+
+    pub async fn get(&self, uri: &str) -> (StatusCode, Value) {
+        self.request(Method::GET, uri, None, None).await
+    }
+
+    pub async fn post(&self, uri: &str, body: Value) -> (StatusCode, Value) {
+        self.request(Method::POST, uri, None, Some(body)).await
+    }
+
+    /// Pair a client granted `scopes` and return its API key
+    pub async fn pair(&self, scopes: &[&str]) -> String {
+        let (status, body) = self
+            .post("/auth/pair", serde_json::json!({ "name": "API tests", "url": "http://localhost", "imageUrl": "", "scopes": scopes }))
+            .await;
+        assert_eq!(status, StatusCode::OK, "pairing failed: {}", body);
+        body["apiKey"].as_str().unwrap().to_string()
+    }
+
+    /// Serve the router on an ephemeral localhost port, for WebSocket clients
+    pub async fn listen(&self) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = self.router.clone();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        addr
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use crate::{TestServer, FIRST_RECEIVE_ADDRESS};
+
+const APPROVER: &str = "approver-api-key";
+
+/// Wait until exactly one signing request is parked for approval
+async fn pending_approval(server: &TestServer) -> Value {
+    for _ in 0..100 {
+        let (status, body) = server.get("/api/v2/approvals").await;
+        assert_eq!(status, StatusCode::OK);
+        if let Some(request) = body.as_array().unwrap().first() {
+            return request.clone();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("signing request was never parked for approval");
+}
+
+fn sign_message_in_background(server: &TestServer, api_key: &str) -> tokio::task::JoinHandle<(StatusCode, Value)> {
+    let server = server.clone();
+    let api_key = api_key.to_string();
+    tokio::spawn(async move {
+        let request = json!({ "address_n": [0x8000_0054u32, 0x8000_0000u32, 0x8000_0000u32, 0, 0], "message": "hello keepkey" });
+        server
+            .request(Method::POST, "/api/v1/bitcoin/sign-message", Some(&api_key), Some(request))
+            .await
+    })
+}
+
+#[tokio::test]
+async fn signs_messages_without_remote_approval() {
+    let server = TestServer::start().await;
+
+    let (status, approvals) = server.get("/api/v2/approvals").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(approvals, json!([]));
+
+    let requester = server.pair(&["sign"]).await;
+    let (status, body) = sign_message_in_background(&server, &requester).await.unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["address"], FIRST_RECEIVE_ADDRESS);
+    assert!(!body["signature"].as_str().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn approved_signing_jobs_reach_the_device() {
+    let server = TestServer::start().await;
+    server.state.cache.set_config("remote_approval", "true", None).await.unwrap();
+
+    let requester = server.pair(&["sign"]).await;
+    let job = sign_message_in_background(&server, &requester);
+    let request = pending_approval(&server).await;
+    assert_eq!(request["kind"], "sign-message");
+    assert_eq!(request["message"], "hello keepkey");
+    assert_eq!(request["apiKeyHint"], requester[requester.len() - 4..]);
+    let uri = format!("/api/v2/approvals/{}", request["id"].as_str().unwrap());
+
+    // The client that asked for the signature can't approve it
+    let (status, _) = server.request(Method::POST, &uri, Some(&requester), Some(json!({ "approve": true }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = server.request(Method::POST, &uri, Some(APPROVER), Some(json!({ "approve": true }))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, body) = job.await.unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["address"], FIRST_RECEIVE_ADDRESS);
+
+    // Decided requests are gone
+    let (status, body) = server.request(Method::POST, &uri, Some(APPROVER), Some(json!({ "approve": true }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "not_found");
+}
+
+#[tokio::test]
+async fn rejected_signing_jobs_fail_with_forbidden() {
+    let server = TestServer::start().await;
+    server.state.cache.set_config("remote_approval", "true", None).await.unwrap();
+
+    let requester = server.pair(&["sign"]).await;
+    let job = sign_message_in_background(&server, &requester);
+    let request = pending_approval(&server).await;
+    let uri = format!("/api/v2/approvals/{}", request["id"].as_str().unwrap());
+
+    let (status, _) = server.request(Method::POST, &uri, None, Some(json!({ "approve": false }))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = job.await.unwrap();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, approvals) = server.get("/api/v2/approvals").await;
+    assert_eq!(approvals, json!([]));
+}
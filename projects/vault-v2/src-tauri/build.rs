@@ -1,3 +1,25 @@
+use std::process::Command;
+
 fn main() {
+    // Reported by /api/version so support can tell exactly which code a user runs
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=VAULT_GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=VAULT_BUILD_TARGET={}", std::env::var("TARGET").unwrap_or_default());
+    println!("cargo:rustc-env=VAULT_BUILD_PROFILE={}", std::env::var("PROFILE").unwrap_or_default());
+    println!(
+        "cargo:rustc-env=VAULT_RELEASE_CHANNEL={}",
+        std::env::var("KEEPKEY_RELEASE_CHANNEL").unwrap_or_else(|_| "dev".to_string())
+    );
+    println!("cargo:rerun-if-env-changed=KEEPKEY_RELEASE_CHANNEL");
+    println!("cargo:rerun-if-changed=../../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../../.git/refs/heads");
+
     tauri_build::build()
 }
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use keepkey_rust::derivation_path::DerivationPath;
+use keepkey_rust::device_queue::DeviceQueueHandle;
+
+/// Single-sig accounts the vault uses, as (account path, script type)
+pub const ACCOUNTS: [(&str, &str); 3] = [
+    ("m/44'/0'/0'", "p2pkh"),
+    ("m/49'/0'/0'", "p2sh-p2wpkh"),
+    ("m/84'/0'/0'", "p2wpkh"),
+];
+
+pub fn account_name(script_type: &str) -> &'static str {
+    match script_type {
+        "p2pkh" => "Legacy",
+        "p2sh-p2wpkh" => "SegWit",
+        "p2wpkh" => "Native SegWit",
+        _ => "Unknown",
+    }
+}
+
+/// Cached queue handle for `device_id`, spawning a worker if there isn't one yet
+pub(crate) fn queue_handle(device_id: &str, manager: &mut HashMap<String, DeviceQueueHandle>) -> Result<DeviceQueueHandle, String> {
+    if let Some(handle) = manager.get(device_id) {
+        return Ok(handle.clone());
+    }
+    let devices = keepkey_rust::features::list_connected_devices();
+    let device_info = devices
+        .iter()
+        .find(|d| d.unique_id == device_id)
+        .ok_or_else(|| format!("Device {} not found", device_id))?;
+    let handle = keepkey_rust::device_queue::DeviceQueueFactory::spawn_worker(device_id.to_string(), device_info.clone());
+    manager.insert(device_id.to_string(), handle.clone());
+    Ok(handle)
+}
+
+/// Plain `xpub` for `path` as reported by the device. With `show_display` the
+/// device also puts it on screen and waits for the user to confirm.
+pub(crate) async fn device_xpub(handle: &DeviceQueueHandle, path: &str, show_display: bool) -> Result<String, String> {
+    let get_public_key = keepkey_rust::messages::Message::GetPublicKey(keepkey_rust::messages::GetPublicKey {
+        address_n: path.parse::<DerivationPath>().map_err(|e| e.to_string())?.into_vec(),
+        coin_name: Some("Bitcoin".to_string()),
+        ecdsa_curve_name: Some("secp256k1".to_string()),
+        show_display: Some(show_display),
+        ..Default::default()
+    });
+
+    match handle
+        .send_raw(get_public_key, show_display)
+        .await
+        .map_err(|e| format!("Failed to get xpub for {}: {}", path, e))?
+    {
+        keepkey_rust::messages::Message::PublicKey(public_key) => match public_key.xpub {
+            Some(xpub) if !xpub.is_empty() => Ok(xpub),
+            _ => Err(format!("Device returned empty xpub for {}", path)),
+        },
+        keepkey_rust::messages::Message::Failure(failure) => {
+            Err(format!("Device returned error: {}", failure.message.unwrap_or_default()))
+        }
+        _ => Err("Unexpected response from device for xpub request".to_string()),
+    }
+}
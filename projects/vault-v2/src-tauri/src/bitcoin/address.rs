@@ -0,0 +1,163 @@
+//! Destination address validation for sends.
+//!
+//! Legacy addresses are checked with Base58Check, segwit ones with bech32
+//! (witness v0) or bech32m (v1+), as BIP-173 and BIP-350 specify. Testnet and
+//! regtest addresses are rejected: the vault only spends mainnet coins.
+
+use base58::FromBase58;
+use sha2::{Digest, Sha256};
+
+const MAINNET_HRP: &str = "bc";
+const TEST_HRPS: &[&str] = &["tb", "bcrt"];
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressKind {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+}
+
+/// Kind of a mainnet address, or why it can't be paid to
+pub fn validate_address(address: &str) -> Result<AddressKind, String> {
+    if address.is_empty() {
+        return Err("Destination address is required".to_string());
+    }
+    if address.trim() != address {
+        return Err("Destination address has leading or trailing spaces".to_string());
+    }
+    let lower = address.to_ascii_lowercase();
+    if lower.starts_with("bc1") || TEST_HRPS.iter().any(|hrp| lower.starts_with(&format!("{}1", hrp))) {
+        validate_segwit(address)
+    } else {
+        validate_base58(address)
+    }
+}
+
+fn validate_base58(address: &str) -> Result<AddressKind, String> {
+    let data = address
+        .from_base58()
+        .map_err(|_| format!("{} is not a valid Bitcoin address", address))?;
+    if data.len() != 25 {
+        return Err(format!("{} is not a valid Bitcoin address", address));
+    }
+    let (payload, checksum) = data.split_at(21);
+    let hash = Sha256::digest(Sha256::digest(payload));
+    if &hash[..4] != checksum {
+        return Err(format!("{} has a bad checksum; check it for typos", address));
+    }
+    match payload[0] {
+        0x00 => Ok(AddressKind::P2pkh),
+        0x05 => Ok(AddressKind::P2sh),
+        0x6f | 0xc4 => Err(format!("{} is a testnet address", address)),
+        _ => Err(format!("{} is not a Bitcoin address", address)),
+    }
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+    let mut chk = 1u32;
+    for &value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn validate_segwit(address: &str) -> Result<AddressKind, String> {
+    let invalid = || format!("{} is not a valid Bitcoin address", address);
+    if address.len() > 90 || (address.to_ascii_lowercase() != address && address.to_ascii_uppercase() != address) {
+        return Err(invalid());
+    }
+    let lower = address.to_ascii_lowercase();
+    let separator = lower.rfind('1').ok_or_else(invalid)?;
+    let (hrp, data) = (&lower[..separator], &lower[separator + 1..]);
+    if TEST_HRPS.contains(&hrp) {
+        return Err(format!("{} is a testnet address", address));
+    }
+    if hrp != MAINNET_HRP || data.len() < 6 {
+        return Err(invalid());
+    }
+    let values = data
+        .bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|&x| x == c).map(|p| p as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(invalid)?;
+
+    let mut expanded: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    expanded.push(0);
+    expanded.extend(hrp.bytes().map(|c| c & 31));
+    expanded.extend(&values);
+    let constant = bech32_polymod(&expanded);
+
+    let (version, program) = values[..values.len() - 6].split_first().ok_or_else(invalid)?;
+    // v0 uses bech32, everything later bech32m (BIP-350)
+    let expected_constant = if *version == 0 { BECH32_CONST } else { BECH32M_CONST };
+    if constant != expected_constant {
+        return Err(format!("{} has a bad checksum; check it for typos", address));
+    }
+
+    // Regroup 5-bit values into bytes; leftover bits must be zero padding
+    let mut bytes = Vec::with_capacity(program.len() * 5 / 8);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for &value in program {
+        acc = (acc << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || (acc & ((1 << bits) - 1)) != 0 {
+        return Err(invalid());
+    }
+
+    match (*version, bytes.len()) {
+        (0, 20) => Ok(AddressKind::P2wpkh),
+        (0, 32) => Ok(AddressKind::P2wsh),
+        (1, 32) => Ok(AddressKind::P2tr),
+        (0, _) | (1..=16, 0..=1) | (1..=16, 41..) | (17.., _) => Err(invalid()),
+        _ => Err(format!("{} uses a witness version the vault can't send to yet", address)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_mainnet_addresses() {
+        assert_eq!(validate_address("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"), Ok(AddressKind::P2pkh));
+        assert_eq!(validate_address("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"), Ok(AddressKind::P2sh));
+        assert_eq!(validate_address("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"), Ok(AddressKind::P2wpkh));
+        assert_eq!(
+            validate_address("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3"),
+            Ok(AddressKind::P2wsh)
+        );
+        assert_eq!(
+            validate_address("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"),
+            Ok(AddressKind::P2tr)
+        );
+    }
+
+    #[test]
+    fn rejects_typos_testnet_and_wrong_checksum_variant() {
+        assert!(validate_address("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3").is_err());
+        assert!(validate_address("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5").is_err());
+        assert!(validate_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").unwrap_err().contains("testnet"));
+        assert!(validate_address("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn").unwrap_err().contains("testnet"));
+        // v1 program with a bech32 (not bech32m) checksum
+        assert!(validate_address("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7k7grplx").is_err());
+        // Mixed case
+        assert!(validate_address("bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").is_err());
+    }
+}
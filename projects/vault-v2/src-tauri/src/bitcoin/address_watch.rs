@@ -0,0 +1,354 @@
+//! Short-lived watches on receive addresses, for integrations.
+//!
+//! A swap service or merchant plugin registers one address with a TTL and
+//! optionally a webhook, and hears when a payment to it is first seen and when
+//! it confirms, without being able to read anything else in the wallet. Each
+//! watch is visible only to the REST client that registered it and lives in
+//! memory: it is meant to outlast a checkout, not a restart.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+use utoipa::ToSchema;
+
+use super::pioneer::{self, AddressTx};
+
+/// Emitted to the frontend for every watch transition
+pub const WATCH_EVENT: &str = "address-watch:event";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_TTL_SECS: u64 = 60 * 60;
+const MIN_TTL_SECS: u64 = 60;
+const MAX_TTL_SECS: u64 = 24 * 60 * 60;
+const MAX_CONFIRMATIONS: u32 = 6;
+// One integration must not be able to make the watcher hammer the explorers
+const MAX_WATCHES_PER_CLIENT: usize = 20;
+// Finished watches stay readable for a client that missed the webhook
+const FINISHED_RETENTION_SECS: u64 = 10 * 60;
+// Block timestamps may run up to two hours behind real time
+const BLOCK_TIME_SLACK_SECS: u64 = 2 * 60 * 60;
+
+static WATCHES: Lazy<Mutex<HashMap<String, AddressWatch>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchStatus {
+    Watching,
+    /// A payment is in the mempool or has fewer confirmations than required
+    Seen,
+    Confirmed,
+    /// The TTL ran out first, even if a payment was seen but not yet confirmed
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressWatch {
+    pub id: String,
+    pub address: String,
+    pub label: Option<String>,
+    pub status: WatchStatus,
+    pub required_confirmations: u32,
+    /// Payment the watch matched, once seen
+    pub txid: Option<String>,
+    pub amount_sats: Option<u64>,
+    pub confirmations: u32,
+    pub callback_url: Option<String>,
+    /// Unix seconds
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub first_seen_at: Option<u64>,
+    #[serde(skip)]
+    client: String,
+    #[serde(skip)]
+    finished_at: Option<u64>,
+}
+
+impl AddressWatch {
+    fn is_active(&self) -> bool {
+        matches!(self.status, WatchStatus::Watching | WatchStatus::Seen)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchRequest {
+    pub address: String,
+    /// Defaults to an hour; at most a day
+    pub ttl_seconds: Option<u64>,
+    /// Confirmations before the watch completes; defaults to 1, at most 6
+    pub confirmations: Option<u32>,
+    /// Receives each event as a JSON POST
+    pub callback_url: Option<String>,
+    pub label: Option<String>,
+}
+
+/// Payload of [`WATCH_EVENT`] and of webhook POSTs
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchEvent {
+    /// `seen`, `confirmed` or `expired`
+    pub event: WatchStatus,
+    pub watch: AddressWatch,
+}
+
+fn now_secs() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
+pub fn register(client: &str, request: WatchRequest) -> Result<AddressWatch, String> {
+    let address = request.address.trim();
+    super::address::validate_address(address)?;
+    // Explorers report bech32 addresses in lowercase
+    let address = if address.to_ascii_lowercase().starts_with("bc1") {
+        address.to_ascii_lowercase()
+    } else {
+        address.to_string()
+    };
+
+    let ttl = request.ttl_seconds.unwrap_or(DEFAULT_TTL_SECS);
+    if !(MIN_TTL_SECS..=MAX_TTL_SECS).contains(&ttl) {
+        return Err(format!("TTL must be between {} and {} seconds", MIN_TTL_SECS, MAX_TTL_SECS));
+    }
+    let confirmations = request.confirmations.unwrap_or(1);
+    if !(1..=MAX_CONFIRMATIONS).contains(&confirmations) {
+        return Err(format!("Confirmations must be between 1 and {}", MAX_CONFIRMATIONS));
+    }
+    if let Some(callback) = &request.callback_url {
+        let url = url::Url::parse(callback).map_err(|e| format!("Invalid callback URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("Callback URL must be http or https".to_string());
+        }
+    }
+
+    let now = now_secs();
+    let watch = AddressWatch {
+        id: uuid::Uuid::new_v4().to_string(),
+        address,
+        label: request.label.filter(|l| !l.trim().is_empty()),
+        status: WatchStatus::Watching,
+        required_confirmations: confirmations,
+        txid: None,
+        amount_sats: None,
+        confirmations: 0,
+        callback_url: request.callback_url,
+        created_at: now,
+        expires_at: now + ttl,
+        first_seen_at: None,
+        client: client.to_string(),
+        finished_at: None,
+    };
+
+    let mut watches = WATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    if watches.values().filter(|w| w.client == client && w.is_active()).count() >= MAX_WATCHES_PER_CLIENT {
+        return Err(format!("At most {} active watches per client", MAX_WATCHES_PER_CLIENT));
+    }
+    watches.insert(watch.id.clone(), watch.clone());
+    log::info!("Watching {} for {} until {} ({})", watch.address, client, watch.expires_at, watch.id);
+    Ok(watch)
+}
+
+pub fn list(client: &str) -> Vec<AddressWatch> {
+    let watches = WATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut own: Vec<AddressWatch> = watches.values().filter(|w| w.client == client).cloned().collect();
+    own.sort_by_key(|w| w.created_at);
+    own
+}
+
+/// Another client's watch is reported as missing
+pub fn get(client: &str, id: &str) -> Option<AddressWatch> {
+    let watches = WATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    watches.get(id).filter(|w| w.client == client).cloned()
+}
+
+pub fn cancel(client: &str, id: &str) -> bool {
+    let mut watches = WATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    match watches.get(id) {
+        Some(watch) if watch.client == client => watches.remove(id).is_some(),
+        _ => false,
+    }
+}
+
+/// Advance `watch` given the address's transactions; returns the events it passed through
+fn apply(watch: &mut AddressWatch, txs: &[AddressTx], tip: Option<u64>, now: u64) -> Vec<WatchStatus> {
+    let mut events = Vec::new();
+
+    if watch.status == WatchStatus::Watching {
+        let since = watch.created_at.saturating_sub(BLOCK_TIME_SLACK_SECS);
+        // Explorers list newest first; the earliest new payment is the one the watch was for
+        let payment = txs.iter().rev().find_map(|tx| {
+            let paid: u64 = tx
+                .vout
+                .iter()
+                .filter(|out| out.scriptpubkey_address.as_deref() == Some(watch.address.as_str()))
+                .map(|out| out.value)
+                .sum();
+            let new = !tx.status.confirmed || tx.status.block_time.is_some_and(|t| t >= since);
+            (paid > 0 && new).then(|| (tx.txid.clone(), paid))
+        });
+        if let Some((txid, amount)) = payment {
+            watch.txid = Some(txid);
+            watch.amount_sats = Some(amount);
+            watch.first_seen_at = Some(now);
+            watch.status = WatchStatus::Seen;
+            events.push(WatchStatus::Seen);
+        }
+    }
+
+    if watch.status == WatchStatus::Seen {
+        let confirmed_at = txs
+            .iter()
+            .find(|tx| Some(&tx.txid) == watch.txid.as_ref())
+            .and_then(|tx| tx.status.block_height.filter(|_| tx.status.confirmed));
+        if let (Some(height), Some(tip)) = (confirmed_at, tip) {
+            watch.confirmations = tip.saturating_sub(height).saturating_add(1) as u32;
+        }
+        if watch.confirmations >= watch.required_confirmations {
+            watch.status = WatchStatus::Confirmed;
+            watch.finished_at = Some(now);
+            events.push(WatchStatus::Confirmed);
+        }
+    }
+
+    if watch.is_active() && now >= watch.expires_at {
+        watch.status = WatchStatus::Expired;
+        watch.finished_at = Some(now);
+        events.push(WatchStatus::Expired);
+    }
+    events
+}
+
+/// Check every active watch once and return the events to deliver
+async fn poll() -> Vec<WatchEvent> {
+    let now = now_secs();
+    let addresses: Vec<String> = {
+        let mut watches = WATCHES.lock().unwrap_or_else(|e| e.into_inner());
+        watches.retain(|_, w| w.finished_at.is_none_or(|at| now < at + FINISHED_RETENTION_SECS));
+        let mut addresses: Vec<String> = watches.values().filter(|w| w.is_active()).map(|w| w.address.clone()).collect();
+        addresses.sort();
+        addresses.dedup();
+        addresses
+    };
+    if addresses.is_empty() {
+        return Vec::new();
+    }
+
+    let tip = match pioneer::tip_height().await {
+        Ok(height) => Some(height),
+        Err(e) => {
+            log::warn!("Address watch: {}", e);
+            None
+        }
+    };
+    let mut history = HashMap::new();
+    for address in addresses {
+        match pioneer::address_transactions(&address).await {
+            Ok(txs) => {
+                history.insert(address, txs);
+            }
+            Err(e) => log::warn!("Address watch: {}", e),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut watches = WATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    for watch in watches.values_mut().filter(|w| w.is_active()) {
+        // A failed lookup still lets the watch expire on time
+        let txs = history.get(&watch.address).map(Vec::as_slice).unwrap_or_default();
+        for event in apply(watch, txs, tip, now) {
+            events.push(WatchEvent { event, watch: watch.clone() });
+        }
+    }
+    events
+}
+
+async fn deliver(app: &AppHandle, event: &WatchEvent) {
+    log::info!("Address watch {} {:?} for {}", event.watch.id, event.event, event.watch.address);
+    let payload = serde_json::to_value(event).unwrap_or_default();
+    let _ = crate::commands::emit_or_queue_event(app, WATCH_EVENT, payload).await;
+
+    let Some(url) = &event.watch.callback_url else { return };
+    // Best effort: the client can always GET the watch for its final state
+    let result = match reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build() {
+        Ok(client) => client.post(url).json(event).send().await.and_then(|r| r.error_for_status()).map(drop),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        log::warn!("Address watch webhook to {} failed: {}", url, e);
+    }
+}
+
+/// Background loop; spawned once at startup
+pub async fn run(app: AppHandle) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        for event in poll().await {
+            deliver(&app, &event).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::pioneer::{AddressTxOutput, AddressTxStatus};
+
+    const ADDRESS: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+    fn tx(txid: &str, value: u64, block: Option<(u64, u64)>) -> AddressTx {
+        AddressTx {
+            txid: txid.to_string(),
+            vout: vec![AddressTxOutput { scriptpubkey_address: Some(ADDRESS.to_string()), value }],
+            status: AddressTxStatus {
+                confirmed: block.is_some(),
+                block_height: block.map(|(height, _)| height),
+                block_time: block.map(|(_, time)| time),
+            },
+        }
+    }
+
+    #[test]
+    fn payment_is_seen_then_confirmed_and_old_history_ignored() {
+        let created = 1_700_000_000;
+        let mut watch = register(
+            "test",
+            WatchRequest {
+                address: ADDRESS.to_string(),
+                ttl_seconds: Some(3600),
+                confirmations: Some(2),
+                callback_url: None,
+                label: None,
+            },
+        )
+        .unwrap();
+        watch.created_at = created;
+        watch.expires_at = created + 3600;
+
+        // Paid to the address a month before the watch: not this payment
+        let old = tx("old", 5_000, Some((800_000, created - 30 * 86_400)));
+        assert!(apply(&mut watch, std::slice::from_ref(&old), Some(810_000), created + 30).is_empty());
+
+        let pending = tx("new", 42_000, None);
+        let events = apply(&mut watch, &[pending, old.clone()], Some(810_000), created + 60);
+        assert_eq!(events, vec![WatchStatus::Seen]);
+        assert_eq!(watch.txid.as_deref(), Some("new"));
+        assert_eq!(watch.amount_sats, Some(42_000));
+
+        let mined = tx("new", 42_000, Some((810_001, created + 600)));
+        assert!(apply(&mut watch, &[mined.clone(), old.clone()], Some(810_001), created + 700).is_empty());
+        assert_eq!(watch.confirmations, 1);
+        let events = apply(&mut watch, &[mined, old], Some(810_002), created + 1300);
+        assert_eq!(events, vec![WatchStatus::Confirmed]);
+
+        let mut other = watch.clone();
+        other.status = WatchStatus::Watching;
+        other.txid = None;
+        assert_eq!(apply(&mut other, &[], None, created + 3600), vec![WatchStatus::Expired]);
+        assert!(cancel("test", &watch.id));
+    }
+}
@@ -0,0 +1,259 @@
+//! Broadcast a signed transaction through whichever provider takes it.
+//!
+//! Providers are tried in order: the Electrum server from preferences (a
+//! Fulcrum or electrs on the user's own node), the configured mempool
+//! instance, the public mempool.space and Blockstream. The first one that
+//! accepts the transaction wins. A node refusing the transaction itself stops
+//! the fallback, since the others would refuse it too and there is no point
+//! handing it to more third parties.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use utoipa::ToSchema;
+
+use super::chain_providers::{self, MempoolClient, DEFAULT_MEMPOOL_URL};
+
+pub const BLOCKSTREAM_URL: &str = "https://blockstream.info/api";
+const ELECTRUM_URL_KEY: &str = "electrum_url";
+const ELECTRUM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Which provider accepted a broadcast
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderBroadcast {
+    pub txid: String,
+    /// `electrum`, `mempool` or `blockstream`
+    pub provider: String,
+    /// Server or API root the transaction went to
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Provider {
+    /// `host:port` of a plain-TCP Electrum server
+    Electrum(String),
+    /// Esplora REST API (mempool.space and Blockstream share it)
+    Esplora { name: &'static str, url: String },
+}
+
+impl Provider {
+    fn name(&self) -> &'static str {
+        match self {
+            Provider::Electrum(_) => "electrum",
+            Provider::Esplora { name, .. } => name,
+        }
+    }
+
+    fn url(&self) -> String {
+        match self {
+            Provider::Electrum(addr) => format!("tcp://{}", addr),
+            Provider::Esplora { url, .. } => url.clone(),
+        }
+    }
+
+    async fn broadcast(&self, raw_tx: &str) -> Result<String, String> {
+        match self {
+            Provider::Electrum(addr) => electrum_broadcast(addr, raw_tx).await,
+            Provider::Esplora { url, .. } => MempoolClient::new(url)?.broadcast(raw_tx).await,
+        }
+    }
+}
+
+/// Providers in the order they are tried
+fn providers(electrum_url: Option<&str>, mempool_url: &str) -> Result<Vec<Provider>, String> {
+    let mut providers = Vec::new();
+    if let Some(url) = electrum_url {
+        providers.push(Provider::Electrum(electrum_address(url)?));
+    }
+    let mempool_url = MempoolClient::new(mempool_url)?.base_url().to_string();
+    for url in [mempool_url.as_str(), DEFAULT_MEMPOOL_URL] {
+        let provider = Provider::Esplora { name: "mempool", url: url.to_string() };
+        if !providers.contains(&provider) {
+            providers.push(provider);
+        }
+    }
+    providers.push(Provider::Esplora { name: "blockstream", url: BLOCKSTREAM_URL.to_string() });
+    Ok(providers)
+}
+
+/// Try each provider until one accepts `raw_tx`. Shared by the Tauri command and the REST route.
+pub async fn broadcast_with_fallback(raw_tx: &str) -> Result<ProviderBroadcast, String> {
+    let raw_tx = raw_tx.trim();
+    chain_providers::validate_raw_tx(raw_tx)?;
+
+    let mut failures = Vec::new();
+    for provider in providers(electrum_url()?.as_deref(), &chain_providers::mempool_url()?)? {
+        match provider.broadcast(raw_tx).await {
+            Ok(txid) => {
+                log::info!("📡 Broadcast {} via {} ({})", txid, provider.name(), provider.url());
+                return Ok(ProviderBroadcast { txid, provider: provider.name().to_string(), url: provider.url() });
+            }
+            Err(e) if e.starts_with("Broadcast rejected") => return Err(e),
+            Err(e) => {
+                log::warn!("Broadcast via {} failed, trying the next provider: {}", provider.url(), e);
+                failures.push(format!("{}: {}", provider.url(), e));
+            }
+        }
+    }
+    Err(format!("Transaction broadcast failed on every provider: {}", failures.join("; ")))
+}
+
+/// `host:port` from `tcp://host:port` or a bare `host:port`
+fn electrum_address(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    if url.starts_with("ssl://") || url.starts_with("tls://") {
+        return Err("Electrum over SSL isn't supported; use the server's TCP port".to_string());
+    }
+    let addr = url.strip_prefix("tcp://").unwrap_or(url).trim_end_matches('/');
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(addr.to_string()),
+        _ => Err(format!("Invalid Electrum server: {} (expected tcp://host:port)", url)),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ElectrumResponse {
+    result: Option<serde_json::Value>,
+    error: Option<ElectrumError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ElectrumError {
+    message: String,
+}
+
+/// txid from a `blockchain.transaction.broadcast` reply
+fn electrum_txid(line: &str) -> Result<String, String> {
+    let response: ElectrumResponse =
+        serde_json::from_str(line).map_err(|e| format!("Invalid Electrum response: {}", e))?;
+    if let Some(error) = response.error {
+        return Err(format!("Broadcast rejected: {}", error.message));
+    }
+    response
+        .result
+        .as_ref()
+        .and_then(|r| r.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| format!("Electrum response missing txid: {}", line))
+}
+
+async fn electrum_broadcast(addr: &str, raw_tx: &str) -> Result<String, String> {
+    let exchange = async {
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| format!("Failed to connect to Electrum server {}: {}", addr, e))?;
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "blockchain.transaction.broadcast",
+            "params": [raw_tx],
+        });
+        stream
+            .write_all(format!("{}\n", request).as_bytes())
+            .await
+            .map_err(|e| format!("Transaction broadcast failed: {}", e))?;
+
+        let mut line = String::new();
+        BufReader::new(stream)
+            .read_line(&mut line)
+            .await
+            .map_err(|e| format!("Transaction broadcast failed: {}", e))?;
+        electrum_txid(&line)
+    };
+    tokio::time::timeout(ELECTRUM_TIMEOUT, exchange)
+        .await
+        .map_err(|_| format!("Electrum server {} timed out", addr))?
+}
+
+/// Configured Electrum server, if any
+pub fn electrum_url() -> Result<Option<String>, String> {
+    let config = crate::commands::load_config()?;
+    Ok(config
+        .get(ELECTRUM_URL_KEY)
+        .and_then(|v| v.as_str())
+        .filter(|url| !url.trim().is_empty())
+        .map(str::to_string))
+}
+
+/// Broadcast through an Electrum server first, or stop with `None`
+pub fn set_electrum_url(url: Option<&str>) -> Result<Option<String>, String> {
+    match url.map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => {
+            let url = format!("tcp://{}", electrum_address(url)?);
+            let mut config = crate::commands::load_config()?;
+            if let Some(obj) = config.as_object_mut() {
+                obj.insert(ELECTRUM_URL_KEY.to_string(), serde_json::json!(url));
+            }
+            crate::commands::save_config(&config)?;
+            log::info!("Electrum server set to {}", url);
+            Ok(Some(url))
+        }
+        None => {
+            crate::commands::remove_preference(ELECTRUM_URL_KEY)?;
+            Ok(None)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn broadcast_transaction(raw_tx: String) -> Result<ProviderBroadcast, String> {
+    broadcast_with_fallback(&raw_tx).await
+}
+
+#[tauri::command]
+pub async fn get_electrum_url() -> Result<Option<String>, String> {
+    electrum_url()
+}
+
+#[tauri::command]
+pub async fn set_electrum_url_preference(url: Option<String>) -> Result<Option<String>, String> {
+    set_electrum_url(url.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn providers_are_tried_in_order_without_duplicates() {
+        let names = |providers: Vec<Provider>| providers.iter().map(Provider::url).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(providers(None, DEFAULT_MEMPOOL_URL).unwrap()),
+            vec![DEFAULT_MEMPOOL_URL.to_string(), BLOCKSTREAM_URL.to_string()]
+        );
+        assert_eq!(
+            names(providers(Some("tcp://umbrel.local:50001"), "http://umbrel.local:3006/api/").unwrap()),
+            vec![
+                "tcp://umbrel.local:50001".to_string(),
+                "http://umbrel.local:3006/api".to_string(),
+                DEFAULT_MEMPOOL_URL.to_string(),
+                BLOCKSTREAM_URL.to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn electrum_servers_are_checked() {
+        assert_eq!(electrum_address("tcp://node.local:50001").unwrap(), "node.local:50001");
+        assert_eq!(electrum_address("10.0.0.2:50001").unwrap(), "10.0.0.2:50001");
+        assert!(electrum_address("ssl://electrum.blockstream.info:50002").is_err());
+        assert!(electrum_address("node.local").is_err());
+    }
+
+    #[test]
+    fn electrum_replies_parse() {
+        let txid = "ab".repeat(32);
+        assert_eq!(
+            electrum_txid(&format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, txid)).unwrap(),
+            txid
+        );
+        let rejected = electrum_txid(
+            r#"{"jsonrpc":"2.0","error":{"code":1,"message":"min relay fee not met"},"id":1}"#,
+        )
+        .unwrap_err();
+        assert_eq!(rejected, "Broadcast rejected: min relay fee not met");
+    }
+}
@@ -0,0 +1,349 @@
+//! Chain data straight from a mempool.space instance.
+//!
+//! Fee percentiles, broadcasts, confirmation status and the block height come
+//! from mempool.space's REST API. The base URL is a preference so a
+//! self-hosted instance (Umbrel, Start9, a node at home) can be used instead of
+//! the public one. Send presets prefer these fees and fall back to Pioneer's.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+use super::pioneer::{self, FeeRates};
+
+pub const DEFAULT_MEMPOOL_URL: &str = "https://mempool.space/api";
+const MEMPOOL_URL_KEY: &str = "mempool_url";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BROADCAST_TIMEOUT: Duration = Duration::from_secs(30);
+// The next few projected blocks are all a fee picker needs
+const PROJECTED_BLOCKS: usize = 3;
+
+/// sat/vB recommendations from `/v1/fees/recommended`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendedFees {
+    /// Next block
+    pub fastest_fee: f64,
+    /// Within three blocks
+    pub half_hour_fee: f64,
+    /// Within six blocks
+    pub hour_fee: f64,
+    pub economy_fee: f64,
+    /// Lowest rate the instance's mempool accepts
+    pub minimum_fee: f64,
+}
+
+/// Block the mempool would fill next, from `/v1/fees/mempool-blocks`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectedBlock {
+    #[serde(rename = "blockVSize")]
+    pub block_vsize: f64,
+    pub n_tx: u64,
+    pub median_fee: f64,
+    /// sat/vB at evenly spaced percentiles, lowest first
+    pub fee_range: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeePercentiles {
+    /// Instance the figures came from
+    pub source: String,
+    pub recommended: RecommendedFees,
+    /// Upcoming blocks, next block first
+    pub projected_blocks: Vec<ProjectedBlock>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TxConfirmation {
+    pub txid: String,
+    pub confirmed: bool,
+    /// 0 while in the mempool
+    pub confirmations: u64,
+    pub block_height: Option<u64>,
+    pub block_hash: Option<String>,
+    /// Unix seconds
+    pub block_time: Option<u64>,
+    pub tip_height: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+    block_hash: Option<String>,
+    block_time: Option<u64>,
+}
+
+/// Client for one mempool.space instance
+#[derive(Debug, Clone)]
+pub struct MempoolClient {
+    base_url: String,
+}
+
+impl MempoolClient {
+    /// `base_url` is the API root, e.g. `https://mempool.space/api`
+    pub fn new(base_url: &str) -> Result<Self, String> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        let url = url::Url::parse(base_url).map_err(|e| format!("Invalid mempool URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("Mempool URL must be http or https".to_string());
+        }
+        Ok(Self { base_url: base_url.to_string() })
+    }
+
+    /// The instance chosen in preferences, or mempool.space
+    pub fn from_config() -> Result<Self, String> {
+        Self::new(&mempool_url()?)
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn get_text(&self, path: &str) -> Result<String, String> {
+        let url = format!("{}{}", self.base_url, path);
+        client(REQUEST_TIMEOUT)?
+            .get(&url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?
+            .text()
+            .await
+            .map_err(|e| format!("Invalid response from {}: {}", url, e))
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        let body = self.get_text(path).await?;
+        serde_json::from_str(&body).map_err(|e| format!("Invalid response from {}{}: {}", self.base_url, path, e))
+    }
+
+    pub async fn fee_percentiles(&self) -> Result<FeePercentiles, String> {
+        let recommended = self.get_json("/v1/fees/recommended").await?;
+        let mut projected_blocks: Vec<ProjectedBlock> = self.get_json("/v1/fees/mempool-blocks").await?;
+        projected_blocks.truncate(PROJECTED_BLOCKS);
+        Ok(FeePercentiles {
+            source: self.base_url.clone(),
+            recommended,
+            projected_blocks,
+        })
+    }
+
+    /// Height of the best block
+    pub async fn tip_height(&self) -> Result<u64, String> {
+        let body = self.get_text("/blocks/tip/height").await?;
+        body.trim()
+            .parse()
+            .map_err(|e| format!("{} returned an invalid height: {}", self.base_url, e))
+    }
+
+    pub async fn confirmation(&self, txid: &str) -> Result<TxConfirmation, String> {
+        let status: TxStatus = self.get_json(&format!("/tx/{}/status", txid)).await?;
+        let tip_height = self.tip_height().await?;
+        let confirmations = match (status.confirmed, status.block_height) {
+            (true, Some(height)) => tip_height.saturating_sub(height) + 1,
+            _ => 0,
+        };
+        Ok(TxConfirmation {
+            txid: txid.to_string(),
+            confirmed: status.confirmed,
+            confirmations,
+            block_height: status.block_height,
+            block_hash: status.block_hash,
+            block_time: status.block_time,
+            tip_height,
+        })
+    }
+
+    /// Submit a signed transaction; returns its txid
+    pub async fn broadcast(&self, raw_tx: &str) -> Result<String, String> {
+        let url = format!("{}/tx", self.base_url);
+        let resp = client(BROADCAST_TIMEOUT)?
+            .post(&url)
+            .body(raw_tx.to_string())
+            .send()
+            .await
+            .map_err(|e| format!("Transaction broadcast failed: {}", e))?;
+
+        let status = resp.status();
+        let body = resp
+            .text()
+            .await
+            .map_err(|e| format!("Transaction broadcast failed: {}", e))?;
+        if status.is_client_error() {
+            // The node refused the transaction itself (bad signature, fee too low, ...)
+            return Err(format!("Broadcast rejected: {}", body.trim()));
+        }
+        if !status.is_success() {
+            return Err(format!("Transaction broadcast failed ({}): {}", status, body));
+        }
+        Ok(body.trim().to_string())
+    }
+}
+
+fn client(timeout: Duration) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// Configured API root, defaulting to the public mempool.space
+pub fn mempool_url() -> Result<String, String> {
+    let config = crate::commands::load_config()?;
+    Ok(config
+        .get(MEMPOOL_URL_KEY)
+        .and_then(|v| v.as_str())
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or(DEFAULT_MEMPOOL_URL)
+        .to_string())
+}
+
+/// Point at a self-hosted instance, or back at mempool.space with `None`
+pub fn set_mempool_url(url: Option<&str>) -> Result<String, String> {
+    match url.map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => {
+            let client = MempoolClient::new(url)?;
+            let mut config = crate::commands::load_config()?;
+            if let Some(obj) = config.as_object_mut() {
+                obj.insert(MEMPOOL_URL_KEY.to_string(), serde_json::json!(client.base_url()));
+            }
+            crate::commands::save_config(&config)?;
+            log::info!("Mempool instance set to {}", client.base_url());
+            Ok(client.base_url().to_string())
+        }
+        None => {
+            crate::commands::remove_preference(MEMPOOL_URL_KEY)?;
+            Ok(DEFAULT_MEMPOOL_URL.to_string())
+        }
+    }
+}
+
+/// Send presets from the mempool instance, or from Pioneer when it is unreachable
+pub async fn fee_rates() -> Result<FeeRates, String> {
+    let mempool = MempoolClient::from_config()?;
+    match mempool.fee_percentiles().await {
+        Ok(fees) => Ok(fees.recommended.into()),
+        Err(e) => {
+            log::warn!("Mempool fees unavailable, asking Pioneer: {}", e);
+            pioneer::fee_rates().await
+        }
+    }
+}
+
+impl From<RecommendedFees> for FeeRates {
+    fn from(fees: RecommendedFees) -> Self {
+        FeeRates {
+            slow: Some(fees.hour_fee),
+            average: Some(fees.half_hour_fee),
+            fast: None,
+            fastest: Some(fees.fastest_fee),
+        }
+    }
+}
+
+/// 64 hex characters
+pub fn validate_txid(txid: &str) -> Result<(), String> {
+    if txid.len() == 64 && txid.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(format!("Invalid txid: {}", txid))
+    }
+}
+
+pub(crate) fn validate_raw_tx(raw_tx: &str) -> Result<(), String> {
+    if raw_tx.is_empty() || raw_tx.len() % 2 != 0 || !raw_tx.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("Transaction must be non-empty hex".to_string());
+    }
+    Ok(())
+}
+
+/// Broadcast through the configured instance. Shared by the Tauri command and the REST route.
+pub async fn broadcast(raw_tx: &str) -> Result<String, String> {
+    let raw_tx = raw_tx.trim();
+    validate_raw_tx(raw_tx)?;
+    let txid = MempoolClient::from_config()?.broadcast(raw_tx).await?;
+    log::info!("📡 Broadcast {} via mempool", txid);
+    Ok(txid)
+}
+
+/// Confirmation status of `txid`. Shared by the Tauri command and the REST route.
+pub async fn confirmation(txid: &str) -> Result<TxConfirmation, String> {
+    validate_txid(txid)?;
+    MempoolClient::from_config()?.confirmation(txid).await
+}
+
+#[tauri::command]
+pub async fn get_fee_rates() -> Result<FeePercentiles, String> {
+    MempoolClient::from_config()?.fee_percentiles().await
+}
+
+#[tauri::command]
+pub async fn get_block_height() -> Result<u64, String> {
+    MempoolClient::from_config()?.tip_height().await
+}
+
+#[tauri::command]
+pub async fn get_transaction_confirmation(txid: String) -> Result<TxConfirmation, String> {
+    confirmation(txid.trim()).await
+}
+
+#[tauri::command]
+pub async fn broadcast_raw_transaction(raw_tx: String) -> Result<String, String> {
+    broadcast(&raw_tx).await
+}
+
+#[tauri::command]
+pub async fn get_mempool_url() -> Result<String, String> {
+    mempool_url()
+}
+
+#[tauri::command]
+pub async fn set_mempool_url_preference(url: Option<String>) -> Result<String, String> {
+    set_mempool_url(url.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_urls_are_normalized_and_checked() {
+        assert_eq!(
+            MempoolClient::new(" http://umbrel.local:3006/api/ ").unwrap().base_url(),
+            "http://umbrel.local:3006/api"
+        );
+        assert!(MempoolClient::new("ftp://mempool.space/api").is_err());
+        assert!(MempoolClient::new("mempool.space").is_err());
+    }
+
+    #[test]
+    fn mempool_responses_parse() {
+        let recommended: RecommendedFees = serde_json::from_str(
+            r#"{"fastestFee":12,"halfHourFee":9,"hourFee":7,"economyFee":3,"minimumFee":1}"#,
+        )
+        .unwrap();
+        let rates = FeeRates::from(recommended);
+        assert_eq!((rates.slow, rates.average, rates.fastest), (Some(7.0), Some(9.0), Some(12.0)));
+
+        let blocks: Vec<ProjectedBlock> = serde_json::from_str(
+            r#"[{"blockSize":1500000,"blockVSize":997000.5,"nTx":3100,"totalFees":21000000,"medianFee":10.2,"feeRange":[8,9,10,12,15,30,200]}]"#,
+        )
+        .unwrap();
+        assert_eq!(blocks[0].n_tx, 3100);
+        assert_eq!(blocks[0].fee_range.len(), 7);
+    }
+
+    #[test]
+    fn txids_and_raw_transactions_are_validated() {
+        assert!(validate_txid(&"ab".repeat(32)).is_ok());
+        assert!(validate_txid("abc").is_err());
+        assert!(validate_raw_tx("0200").is_ok());
+        assert!(validate_raw_tx("020").is_err());
+        assert!(validate_raw_tx("zz").is_err());
+    }
+}
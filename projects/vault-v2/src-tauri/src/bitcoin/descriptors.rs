@@ -0,0 +1,419 @@
+use base58::FromBase58;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use tauri::State;
+
+use keepkey_rust::device_queue::DeviceQueueHandle;
+
+use super::accounts::{account_name, device_xpub, queue_handle, ACCOUNTS};
+use crate::commands::DeviceQueueManager;
+
+// BIP-380 descriptor checksum alphabets
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+// Addresses Bitcoin Core derives ahead for each imported descriptor
+const CORE_RANGE_END: u32 = 999;
+
+// Any depth-1 key carries the master fingerprint as its parent fingerprint
+pub(crate) const FINGERPRINT_PATH: &str = "m/44'";
+
+/// Watch-only view of one account
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountDescriptor {
+    pub name: String,
+    pub script_type: String,
+    pub path: String,
+    pub xpub: String,
+    /// ypub/zpub form, for wallets that don't read descriptors
+    pub slip132_xpub: String,
+    /// Last eight characters of `xpub`, to compare with the device screen
+    pub check_code: String,
+    pub receive_descriptor: String,
+    pub change_descriptor: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qr_svg: Option<String>,
+}
+
+/// Wallet file written by `export_watch_only_wallet`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchOnlyFormat {
+    /// `importdescriptors` JSON array, receive and change for every account
+    Core,
+    /// Electrum wallet file for one account; Sparrow opens these too
+    Electrum,
+    /// One account as a BIP-389 `<0;1>` multipath descriptor
+    Sparrow,
+    /// Every account with its descriptors, plus BIP-329 labels
+    Json,
+}
+
+impl WatchOnlyFormat {
+    fn single_account(self) -> bool {
+        matches!(self, Self::Electrum | Self::Sparrow)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Core => "core",
+            Self::Electrum => "electrum",
+            Self::Sparrow => "sparrow",
+            Self::Json => "json",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        if self == Self::Sparrow { "txt" } else { "json" }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DescriptorExport {
+    pub fingerprint: String,
+    /// Every account xpub was shown on the device and confirmed by the user
+    pub shown_on_device: bool,
+    pub accounts: Vec<AccountDescriptor>,
+}
+
+fn polymod(c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7_ffff_ffff) << 5) ^ val;
+    for (bit, generator) in [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd].iter().enumerate() {
+        if (c0 >> bit) & 1 == 1 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// Eight character checksum appended after `#`
+pub fn descriptor_checksum(descriptor: &str) -> Result<String, String> {
+    let mut c = 1u64;
+    let mut class = 0u64;
+    let mut class_count = 0;
+    for ch in descriptor.chars() {
+        let pos = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| format!("Invalid descriptor character {:?}", ch))? as u64;
+        c = polymod(c, pos & 31);
+        class = class * 3 + (pos >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+
+    Ok((0..8).map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char).collect())
+}
+
+fn with_checksum(descriptor: String) -> Result<String, String> {
+    let checksum = descriptor_checksum(&descriptor)?;
+    Ok(format!("{}#{}", descriptor, checksum))
+}
+
+/// `m/84'/0'/0'` -> `84h/0h/0h`, the key-origin form
+fn origin_path(path: &str) -> String {
+    path.trim_start_matches("m/").replace('\'', "h")
+}
+
+pub(crate) fn master_fingerprint(depth_one_xpub: &str) -> Result<String, String> {
+    let data = depth_one_xpub
+        .from_base58()
+        .map_err(|_| "Invalid base58 encoding".to_string())?;
+    if data.len() != 82 {
+        return Err(format!("Unexpected xpub length {}", data.len()));
+    }
+    // version (4) | depth (1) | parent fingerprint (4) | ...
+    Ok(hex::encode(&data[5..9]))
+}
+
+/// Master fingerprint of the device's seed, as 8 hex characters
+pub(crate) async fn device_fingerprint(handle: &DeviceQueueHandle) -> Result<String, String> {
+    master_fingerprint(&device_xpub(handle, FINGERPRINT_PATH, false).await?)
+}
+
+pub fn account_descriptor(fingerprint: &str, path: &str, script_type: &str, xpub: &str, branch: u32) -> Result<String, String> {
+    branch_descriptor(fingerprint, path, script_type, xpub, &branch.to_string())
+}
+
+/// `branch` is a child number or a multipath group like `<0;1>`
+fn branch_descriptor(fingerprint: &str, path: &str, script_type: &str, xpub: &str, branch: &str) -> Result<String, String> {
+    let key = format!("[{}/{}]{}/{}/*", fingerprint, origin_path(path), xpub, branch);
+    let descriptor = match script_type {
+        "p2pkh" => format!("pkh({})", key),
+        "p2sh-p2wpkh" => format!("sh(wpkh({}))", key),
+        "p2wpkh" => format!("wpkh({})", key),
+        other => return Err(format!("Unsupported script type: {}", other)),
+    };
+    with_checksum(descriptor)
+}
+
+/// Last eight characters of an xpub in two groups of four; they include the
+/// Base58Check checksum, so a key swapped by the host won't match the screen
+pub fn xpub_check_code(xpub: &str) -> String {
+    let chars: Vec<char> = xpub.chars().collect();
+    let tail = &chars[chars.len().saturating_sub(8)..];
+    let (first, second) = tail.split_at(tail.len().saturating_sub(4));
+    format!("{} {}", first.iter().collect::<String>(), second.iter().collect::<String>())
+}
+
+fn qr_svg(data: &str) -> Result<String, String> {
+    let code = qrcode::QrCode::new(data.as_bytes()).map_err(|e| format!("Failed to build QR code: {}", e))?;
+    Ok(code
+        .render::<qrcode::render::svg::Color>()
+        .min_dimensions(240, 240)
+        .build())
+}
+
+async fn collect_descriptors(
+    device_id: &str,
+    queue_manager: &DeviceQueueManager,
+    with_qr: bool,
+    show_on_device: bool,
+) -> Result<DescriptorExport, String> {
+    if crate::commands::is_device_in_pin_flow(device_id) {
+        return Err("Device is currently in PIN entry mode. Please complete PIN entry first.".to_string());
+    }
+    let handle = {
+        let mut manager = queue_manager.lock().await;
+        queue_handle(device_id, &mut manager)?
+    };
+
+    let fingerprint = device_fingerprint(&handle).await?;
+    let mut accounts = Vec::with_capacity(ACCOUNTS.len());
+    for (path, script_type) in ACCOUNTS {
+        let xpub = device_xpub(&handle, path, show_on_device).await?;
+        let slip132_xpub = crate::slip132::convert_xpub_prefix(&xpub, script_type)?;
+        accounts.push(AccountDescriptor {
+            name: account_name(script_type).to_string(),
+            script_type: script_type.to_string(),
+            path: path.to_string(),
+            receive_descriptor: account_descriptor(&fingerprint, path, script_type, &xpub, 0)?,
+            change_descriptor: account_descriptor(&fingerprint, path, script_type, &xpub, 1)?,
+            qr_svg: if with_qr { Some(qr_svg(&slip132_xpub)?) } else { None },
+            check_code: xpub_check_code(&xpub),
+            xpub,
+            slip132_xpub,
+        });
+    }
+
+    Ok(DescriptorExport { fingerprint, shown_on_device: show_on_device, accounts })
+}
+
+/// Account descriptors and xpubs with a QR code per account, for display.
+/// With `show_on_device` each account xpub is also shown on the KeepKey.
+#[tauri::command]
+pub async fn get_account_descriptors(
+    device_id: String,
+    show_on_device: Option<bool>,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<DescriptorExport, String> {
+    collect_descriptors(&device_id, &queue_manager, true, show_on_device.unwrap_or(false)).await
+}
+
+/// Write account descriptors and xpubs as JSON, returning the file path.
+/// Without `file_path` the file goes to the Downloads folder.
+#[tauri::command]
+pub async fn export_account_descriptors(
+    device_id: String,
+    file_path: Option<String>,
+    show_on_device: Option<bool>,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<String, String> {
+    let export = collect_descriptors(&device_id, &queue_manager, false, show_on_device.unwrap_or(false)).await?;
+
+    let path = match file_path {
+        Some(path) => PathBuf::from(path),
+        None => dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .ok_or_else(|| "Could not find a Downloads or home directory".to_string())?
+            .join(format!("keepkey-{}-accounts.json", export.fingerprint)),
+    };
+    let json = serde_json::to_string_pretty(&export).map_err(|e| format!("Failed to serialize export: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    log::info!("Exported {} account descriptors to {}", export.accounts.len(), path.display());
+    Ok(path.display().to_string())
+}
+
+/// File contents for `format`. Single-account formats use `account` (a path
+/// like `m/84'/0'/0'`), defaulting to native segwit.
+pub fn render_watch_only(
+    export: &DescriptorExport,
+    format: WatchOnlyFormat,
+    account: Option<&str>,
+    timestamp: u64,
+) -> Result<String, String> {
+    let label = |a: &AccountDescriptor| format!("KeepKey {}", a.name);
+    let selected = if format.single_account() {
+        let found = match account {
+            Some(path) => export.accounts.iter().find(|a| a.path == path.replace('h', "'")),
+            None => export
+                .accounts
+                .iter()
+                .find(|a| a.script_type == "p2wpkh")
+                .or_else(|| export.accounts.first()),
+        };
+        Some(found.ok_or_else(|| format!("No account {}", account.unwrap_or_default()))?)
+    } else {
+        None
+    };
+
+    let value = match (format, selected) {
+        (WatchOnlyFormat::Core, _) => Value::Array(
+            export
+                .accounts
+                .iter()
+                .flat_map(|a| [(&a.receive_descriptor, false), (&a.change_descriptor, true)])
+                .map(|(desc, internal)| {
+                    json!({
+                        "desc": desc,
+                        "active": true,
+                        "internal": internal,
+                        "range": [0, CORE_RANGE_END],
+                        "timestamp": timestamp,
+                    })
+                })
+                .collect(),
+        ),
+        (WatchOnlyFormat::Electrum, Some(a)) => json!({
+            "keystore": {
+                "type": "bip32",
+                "xpub": a.slip132_xpub,
+                "derivation": a.path,
+                "root_fingerprint": export.fingerprint,
+                "label": label(a),
+            },
+            "wallet_type": "standard",
+            "use_encryption": false,
+            "seed_version": 17,
+        }),
+        (WatchOnlyFormat::Sparrow, Some(a)) => {
+            let descriptor = branch_descriptor(&export.fingerprint, &a.path, &a.script_type, &a.xpub, "<0;1>")?;
+            return Ok(format!("# {}\n{}\n", label(a), descriptor));
+        }
+        (WatchOnlyFormat::Json, _) => json!({
+            "fingerprint": export.fingerprint,
+            "shown_on_device": export.shown_on_device,
+            "accounts": export.accounts,
+            // BIP-329 records naming each account's xpub
+            "labels": export
+                .accounts
+                .iter()
+                .map(|a| json!({
+                    "type": "xpub",
+                    "ref": a.xpub,
+                    "label": label(a),
+                    "origin": format!("[{}/{}]", export.fingerprint, origin_path(&a.path)),
+                }))
+                .collect::<Vec<_>>(),
+        }),
+        (_, None) => unreachable!("single-account formats always select an account"),
+    };
+    serde_json::to_string_pretty(&value)
+        .map(|json| json + "\n")
+        .map_err(|e| format!("Failed to serialize wallet: {}", e))
+}
+
+/// Write a watch-only wallet for Bitcoin Core, Electrum, Sparrow or as
+/// generic JSON, returning the file path. Without `file_path` the file goes to
+/// the Downloads folder.
+#[tauri::command]
+pub async fn export_watch_only_wallet(
+    device_id: String,
+    format: WatchOnlyFormat,
+    account: Option<String>,
+    file_path: Option<String>,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<String, String> {
+    let export = collect_descriptors(&device_id, &queue_manager, false, false).await?;
+    // Rescan from genesis: the seed may be older than this device
+    let contents = render_watch_only(&export, format, account.as_deref(), 0)?;
+
+    let path = match file_path {
+        Some(path) => PathBuf::from(path),
+        None => dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .ok_or_else(|| "Could not find a Downloads or home directory".to_string())?
+            .join(format!("keepkey-{}-watch-only-{}.{}", export.fingerprint, format.name(), format.extension())),
+    };
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    log::info!("Exported {} watch-only wallet to {}", format.name(), path.display());
+    Ok(path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_matches_bip380_vectors() {
+        assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert_eq!(
+            with_checksum(
+                "wpkh([d34db33f/84h/0h/0h]xpub6DJ2dNUysrn5Vt36jH2KLBT2i1auw1tTSSomg8PhqNiUtx8QX2SvC9nrHu81fT41fvDUnhMjEzQgXnQjKEu3oaqMSzhSrHMxyyoEAmUHQbY/0/*)".to_string()
+            )
+            .unwrap(),
+            "wpkh([d34db33f/84h/0h/0h]xpub6DJ2dNUysrn5Vt36jH2KLBT2i1auw1tTSSomg8PhqNiUtx8QX2SvC9nrHu81fT41fvDUnhMjEzQgXnQjKEu3oaqMSzhSrHMxyyoEAmUHQbY/0/*)#cjjspncu"
+        );
+    }
+
+    #[test]
+    fn script_types_map_to_descriptor_wrappers() {
+        let xpub = "xpub6DJ2dNUysrn5Vt36jH2KLBT2i1auw1tTSSomg8PhqNiUtx8QX2SvC9nrHu81fT41fvDUnhMjEzQgXnQjKEu3oaqMSzhSrHMxyyoEAmUHQbY";
+        let legacy = account_descriptor("d34db33f", "m/44'/0'/0'", "p2pkh", xpub, 1).unwrap();
+        assert!(legacy.starts_with("pkh([d34db33f/44h/0h/0h]xpub6DJ2"));
+        assert!(legacy.contains("/1/*)#"));
+
+        let nested = account_descriptor("d34db33f", "m/49'/0'/0'", "p2sh-p2wpkh", xpub, 0).unwrap();
+        assert!(nested.starts_with("sh(wpkh([d34db33f/49h/0h/0h]"));
+    }
+
+    #[test]
+    fn watch_only_formats() {
+        let xpub = "xpub6DJ2dNUysrn5Vt36jH2KLBT2i1auw1tTSSomg8PhqNiUtx8QX2SvC9nrHu81fT41fvDUnhMjEzQgXnQjKEu3oaqMSzhSrHMxyyoEAmUHQbY";
+        let export = DescriptorExport {
+            fingerprint: "d34db33f".to_string(),
+            shown_on_device: false,
+            accounts: vec![AccountDescriptor {
+                name: "Native SegWit".to_string(),
+                script_type: "p2wpkh".to_string(),
+                path: "m/84'/0'/0'".to_string(),
+                xpub: xpub.to_string(),
+                slip132_xpub: xpub.to_string(),
+                check_code: xpub_check_code(xpub),
+                receive_descriptor: account_descriptor("d34db33f", "m/84'/0'/0'", "p2wpkh", xpub, 0).unwrap(),
+                change_descriptor: account_descriptor("d34db33f", "m/84'/0'/0'", "p2wpkh", xpub, 1).unwrap(),
+                qr_svg: None,
+            }],
+        };
+
+        let core: Value = serde_json::from_str(&render_watch_only(&export, WatchOnlyFormat::Core, None, 0).unwrap()).unwrap();
+        assert_eq!(core.as_array().unwrap().len(), 2);
+        assert_eq!(core[0]["desc"], json!(export.accounts[0].receive_descriptor));
+        assert_eq!(core[1]["internal"], json!(true));
+
+        let sparrow = render_watch_only(&export, WatchOnlyFormat::Sparrow, Some("m/84h/0h/0h"), 0).unwrap();
+        assert!(sparrow.contains("wpkh([d34db33f/84h/0h/0h]xpub6DJ2"));
+        assert!(sparrow.contains("/<0;1>/*)#"));
+        assert!(render_watch_only(&export, WatchOnlyFormat::Electrum, Some("m/44'/0'/0'"), 0).is_err());
+    }
+
+    #[test]
+    fn check_code_is_the_xpub_tail() {
+        let xpub = "xpub6DJ2dNUysrn5Vt36jH2KLBT2i1auw1tTSSomg8PhqNiUtx8QX2SvC9nrHu81fT41fvDUnhMjEzQgXnQjKEu3oaqMSzhSrHMxyyoEAmUHQbY";
+        assert_eq!(xpub_check_code(xpub), "EAmU HQbY");
+    }
+}
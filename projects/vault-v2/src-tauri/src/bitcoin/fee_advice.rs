@@ -0,0 +1,199 @@
+//! Fee advice for our own sends that sit unconfirmed.
+//!
+//! Every few minutes the sends recorded for fee bumping (see [`super::rbf`])
+//! are looked up on the network. One that has waited a while and pays less
+//! than the mempool currently asks for a confirmation within the hour is
+//! likely stuck: the user gets a notification suggesting a fee rate to bump it
+//! to, and the frontend a [`STUCK_EVENT`] it can offer the bump from.
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use super::pioneer;
+use super::rbf::{self, INCREMENTAL_RELAY_FEE};
+use crate::notifications::{self, NotificationKind};
+
+/// Emitted to the frontend with a [`FeeAdvice`] payload
+pub const STUCK_EVENT: &str = "wallet:transaction-stuck";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// Give a send a couple of blocks before judging it
+const STUCK_AFTER_SECS: i64 = 30 * 60;
+/// Confirmation target, in blocks, a send is expected to keep up with
+const EXPECTED_TARGET_BLOCKS: u32 = 6;
+/// Target the suggested bump aims for
+const BUMP_TARGET_BLOCKS: u32 = 2;
+// Advise again only once the suggestion has risen this much (percent)
+const READVISE_INCREASE_PERCENT: u64 = 25;
+
+// Suggested rate last advised per txid, so a stuck send isn't reported every poll
+static ADVISED: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeAdvice {
+    pub device_id: String,
+    pub txid: String,
+    /// sat/vB the send pays
+    pub fee_rate: u64,
+    /// sat/vB the mempool currently asks for within [`EXPECTED_TARGET_BLOCKS`]
+    pub expected_fee_rate: u64,
+    /// sat/vB to bump to
+    pub suggested_fee_rate: u64,
+    pub pending_minutes: u64,
+}
+
+/// Rate from the estimate with the closest target at or below `target_blocks`
+fn estimate_for(estimates: &[(u32, f64)], target_blocks: u32) -> Option<u64> {
+    estimates
+        .iter()
+        .filter(|(target, _)| *target <= target_blocks)
+        .max_by_key(|(target, _)| *target)
+        .map(|(_, rate)| rate.ceil() as u64)
+}
+
+/// `(expected, suggested)` sat/vB if a send paying `fee_rate` for `pending_secs` looks stuck
+fn assess(fee_rate: u64, pending_secs: i64, estimates: &[(u32, f64)]) -> Option<(u64, u64)> {
+    if pending_secs < STUCK_AFTER_SECS {
+        return None;
+    }
+    let expected = estimate_for(estimates, EXPECTED_TARGET_BLOCKS)?;
+    if fee_rate >= expected {
+        return None;
+    }
+    let suggested = estimate_for(estimates, BUMP_TARGET_BLOCKS)
+        .unwrap_or(expected)
+        .max(fee_rate + INCREMENTAL_RELAY_FEE);
+    Some((expected, suggested))
+}
+
+/// Whether `advice` is news given what was last advised for its txid
+fn should_advise(advice: &FeeAdvice) -> bool {
+    let mut advised = ADVISED.lock().unwrap_or_else(|e| e.into_inner());
+    match advised.get(&advice.txid) {
+        Some(last) if advice.suggested_fee_rate * 100 < last * (100 + READVISE_INCREASE_PERCENT) => false,
+        _ => {
+            advised.insert(advice.txid.clone(), advice.suggested_fee_rate);
+            true
+        }
+    }
+}
+
+/// Check every recent send once and return the advice to deliver
+async fn poll() -> Vec<FeeAdvice> {
+    let sent = match rbf::recent() {
+        Ok(sent) => sent,
+        Err(e) => {
+            log::warn!("Fee advice: {}", e);
+            return Vec::new();
+        }
+    };
+    let now = chrono::Utc::now().timestamp();
+    if !sent.iter().any(|t| now - t.sent_at >= STUCK_AFTER_SECS) {
+        return Vec::new();
+    }
+
+    let estimates = match pioneer::fee_estimates().await {
+        Ok(estimates) => estimates,
+        Err(e) => {
+            log::warn!("Fee advice: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut advice = Vec::new();
+    for sent in sent.into_iter().filter(|t| now - t.sent_at >= STUCK_AFTER_SECS) {
+        let network = match pioneer::transaction(&sent.txid).await {
+            Ok(network) => network,
+            Err(e) => {
+                log::warn!("Fee advice: {}", e);
+                continue;
+            }
+        };
+        if network.status.confirmed {
+            // Nothing left to bump
+            ADVISED.lock().unwrap_or_else(|e| e.into_inner()).remove(&sent.txid);
+            if let Err(e) = rbf::forget(&sent.txid) {
+                log::warn!("Failed to forget confirmed send {}: {}", sent.txid, e);
+            }
+            continue;
+        }
+
+        let (_, fee_rate) = rbf::network_fee(&network);
+        let pending_secs = now - sent.sent_at;
+        if let Some((expected, suggested)) = assess(fee_rate, pending_secs, &estimates) {
+            let candidate = FeeAdvice {
+                device_id: sent.composed.device_id.clone(),
+                txid: sent.txid.clone(),
+                fee_rate,
+                expected_fee_rate: expected,
+                suggested_fee_rate: suggested,
+                pending_minutes: (pending_secs / 60) as u64,
+            };
+            if should_advise(&candidate) {
+                advice.push(candidate);
+            }
+        }
+    }
+    advice
+}
+
+async fn deliver(app: &AppHandle, advice: &FeeAdvice) {
+    log::info!(
+        "Send {} likely stuck at {} sat/vB after {} min; suggesting {} sat/vB",
+        advice.txid,
+        advice.fee_rate,
+        advice.pending_minutes,
+        advice.suggested_fee_rate
+    );
+    let payload = serde_json::to_value(advice).unwrap_or_default();
+    let _ = crate::commands::emit_or_queue_event(app, STUCK_EVENT, payload.clone()).await;
+
+    let short_txid = &advice.txid[..advice.txid.len().min(12)];
+    notifications::notify(
+        app,
+        NotificationKind::FeeBumpSuggested,
+        Some(&advice.device_id),
+        "Transaction likely stuck",
+        format!(
+            "Transaction {}… pays {} sat/vB, below the {} sat/vB the mempool currently needs. Consider an RBF bump to {} sat/vB.",
+            short_txid, advice.fee_rate, advice.expected_fee_rate, advice.suggested_fee_rate
+        ),
+        payload,
+    )
+    .await;
+}
+
+/// Background loop; spawned once at startup
+pub async fn run(app: AppHandle) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        for advice in poll().await {
+            deliver(&app, &advice).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_old_underpaying_sends_are_stuck() {
+        let estimates = [(1, 40.0), (2, 30.5), (3, 25.0), (6, 12.0), (144, 2.0)];
+
+        // Too recent to judge, or paying enough for the hour
+        assert_eq!(assess(3, 10 * 60, &estimates), None);
+        assert_eq!(assess(12, 2 * 60 * 60, &estimates), None);
+
+        assert_eq!(assess(3, 2 * 60 * 60, &estimates), Some((12, 31)));
+        // The suggestion always outbids the original
+        assert_eq!(assess(11, 2 * 60 * 60, &[(2, 8.0), (6, 12.0)]), Some((12, 12)));
+        assert_eq!(assess(3, 2 * 60 * 60, &[(144, 2.0)]), None);
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use super::pioneer::FeeRates;
+
+// Fallbacks when Pioneer omits a level
+const DEFAULT_SLOW: f64 = 10.0;
+const DEFAULT_AVERAGE: f64 = 20.0;
+const DEFAULT_FASTEST: f64 = 50.0;
+
+// Estimates run low, so preset levels get a margin and a floor
+const PRESET_MARGIN: f64 = 1.2;
+const PRESET_FLOOR: u64 = 5;
+const PRESET_FLOOR_BUMP: u64 = 8;
+
+/// How the user wants to pay for confirmation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeePreference {
+    Slow,
+    Average,
+    #[default]
+    Fastest,
+    /// Explicit sat/vB, used as-is
+    Custom(u64),
+}
+
+/// sat/vB to build with for `preference`
+pub fn resolve_fee_rate(rates: &FeeRates, preference: FeePreference) -> Result<u64, String> {
+    let estimate = match preference {
+        FeePreference::Custom(0) => return Err("Custom fee rate must be at least 1 sat/vB".to_string()),
+        FeePreference::Custom(rate) => return Ok(rate),
+        FeePreference::Slow => rates.slow.unwrap_or(DEFAULT_SLOW),
+        FeePreference::Average => rates.average.or(rates.fast).unwrap_or(DEFAULT_AVERAGE),
+        FeePreference::Fastest => rates.fastest.or(rates.fast).unwrap_or(DEFAULT_FASTEST),
+    };
+
+    let rate = (estimate * PRESET_MARGIN).round() as u64;
+    if rate <= PRESET_FLOOR {
+        Ok(PRESET_FLOOR_BUMP)
+    } else {
+        Ok(rate)
+    }
+}
@@ -0,0 +1,16 @@
+// Bitcoin wallet logic for the vault UI: sends are built here from the user's
+// intent (destination, amount, fee preference), and accounts can be exported
+// for watch-only wallets or joined to multisig wallets as a cosigner.
+pub mod accounts;
+pub mod address_watch;
+pub mod address;
+pub mod broadcast;
+pub mod chain_providers;
+pub mod descriptors;
+pub mod fee_advice;
+pub mod fees;
+pub mod multisig;
+pub mod pioneer;
+pub mod rbf;
+pub mod send;
+pub mod tx_builder;
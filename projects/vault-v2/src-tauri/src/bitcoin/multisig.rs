@@ -0,0 +1,252 @@
+//! Multisig wallets the KeepKey is a cosigner of.
+//!
+//! A wallet is imported from a `wsh(sortedmulti(...))` descriptor exported by
+//! the coordinator (Sparrow, Specter, Caravan). On import the device's own key
+//! is read back at the descriptor's origin path and must match, so a
+//! descriptor that swaps out our key is refused. Wallets are kept in
+//! `~/.keepkey/multisig.json`; addresses are derived locally and confirmed by
+//! the device with the full cosigner set, and PSBTs are signed with the key
+//! for this device's fingerprint only.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tauri::State;
+use utoipa::ToSchema;
+
+use keepkey_rust::device_queue::DeviceQueueHandle;
+use keepkey_rust::messages::Message;
+use keepkey_rust::multisig::MultisigDescriptor;
+use keepkey_rust::psbt::{parse_psbt, Fingerprint};
+
+use super::accounts::{device_xpub, queue_handle};
+use super::descriptors::device_fingerprint;
+use crate::commands::DeviceQueueManager;
+
+const MULTISIG_FILE: &str = "multisig.json";
+
+// Serializes read-modify-write cycles on the wallets file
+static MULTISIG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigWallet {
+    /// Derived from the descriptor, so re-importing a wallet replaces it
+    pub id: String,
+    pub name: String,
+    /// Canonical descriptor with checksum
+    pub descriptor: String,
+    pub threshold: usize,
+    pub cosigners: usize,
+    /// Master fingerprint of the KeepKey's key in this wallet
+    pub fingerprint: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigAddress {
+    pub wallet_id: String,
+    pub address: String,
+    pub index: u32,
+    pub change: bool,
+    /// The device showed the address and the user confirmed it
+    pub shown_on_device: bool,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedMultisigPsbt {
+    /// Base64 PSBT with the device's partial signatures added
+    pub psbt: String,
+    /// Every input has enough signatures to finalize
+    pub complete: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MultisigFile {
+    #[serde(default)]
+    wallets: Vec<MultisigWallet>,
+}
+
+fn multisig_path() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    Ok(home_dir.join(".keepkey").join(MULTISIG_FILE))
+}
+
+fn read_store() -> Result<MultisigFile, String> {
+    let path = multisig_path()?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse multisig wallets {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MultisigFile::default()),
+        Err(e) => Err(format!("Failed to read multisig wallets {}: {}", path.display(), e)),
+    }
+}
+
+fn write_store(store: &MultisigFile) -> Result<(), String> {
+    let path = multisig_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create multisig directory: {}", e))?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    let contents = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("Failed to serialize multisig wallets: {}", e))?;
+    std::fs::write(&tmp_path, contents).map_err(|e| format!("Failed to write multisig wallets: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to replace multisig wallets: {}", e))
+}
+
+fn wallet_id(descriptor: &str) -> String {
+    hex::encode(&Sha256::digest(descriptor.as_bytes())[..8])
+}
+
+pub fn list_wallets() -> Result<Vec<MultisigWallet>, String> {
+    Ok(read_store()?.wallets)
+}
+
+fn find_wallet(wallet_id: &str) -> Result<(MultisigWallet, MultisigDescriptor), String> {
+    let wallet = list_wallets()?
+        .into_iter()
+        .find(|w| w.id == wallet_id)
+        .ok_or_else(|| format!("No multisig wallet {}", wallet_id))?;
+    let descriptor = MultisigDescriptor::parse(&wallet.descriptor).map_err(|e| e.to_string())?;
+    Ok((wallet, descriptor))
+}
+
+fn parse_fingerprint(fingerprint: &str) -> Result<Fingerprint, String> {
+    Fingerprint::from_str(fingerprint).map_err(|e| format!("Invalid fingerprint {}: {}", fingerprint, e))
+}
+
+/// Check that the device is a cosigner of `descriptor` and store the wallet
+pub async fn import_wallet(handle: &DeviceQueueHandle, descriptor: &str, name: Option<String>) -> Result<MultisigWallet, String> {
+    let parsed = MultisigDescriptor::parse(descriptor).map_err(|e| e.to_string())?;
+    let fingerprint = device_fingerprint(handle).await?;
+    let cosigner = parsed.cosigner(parse_fingerprint(&fingerprint)?).map_err(|e| e.to_string())?;
+    let xpub = device_xpub(handle, &cosigner.origin_path.to_string(), false).await?;
+    parsed.verify_cosigner(cosigner.fingerprint, &xpub).map_err(|e| e.to_string())?;
+
+    let descriptor = parsed.to_string();
+    let wallet = MultisigWallet {
+        id: wallet_id(&descriptor),
+        name: name
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| format!("{}-of-{} multisig", parsed.threshold, parsed.cosigners.len())),
+        threshold: parsed.threshold,
+        cosigners: parsed.cosigners.len(),
+        fingerprint,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        descriptor,
+    };
+
+    let _lock = MULTISIG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut store = read_store()?;
+    store.wallets.retain(|w| w.id != wallet.id);
+    store.wallets.push(wallet.clone());
+    write_store(&store)?;
+    log::info!("Imported {} ({}) as cosigner {}", wallet.name, wallet.id, wallet.fingerprint);
+    Ok(wallet)
+}
+
+/// Derive address `index` and have the device compute it from the cosigner
+/// set; a mismatch means the host's view of the wallet can't be trusted
+pub async fn wallet_address(
+    handle: &DeviceQueueHandle,
+    wallet_id: &str,
+    index: u32,
+    change: bool,
+    show_display: bool,
+) -> Result<MultisigAddress, String> {
+    let (wallet, descriptor) = find_wallet(wallet_id)?;
+    let expected = descriptor.address(change, index).map_err(|e| e.to_string())?.to_string();
+    let request = descriptor
+        .get_address(parse_fingerprint(&wallet.fingerprint)?, change, index, show_display)
+        .map_err(|e| e.to_string())?;
+
+    match handle
+        .send_raw(Message::GetAddress(request), show_display)
+        .await
+        .map_err(|e| format!("Failed to get multisig address: {}", e))?
+    {
+        Message::Address(address) if address.address.eq_ignore_ascii_case(&expected) => Ok(MultisigAddress {
+            wallet_id: wallet.id,
+            address: expected,
+            index,
+            change,
+            shown_on_device: show_display,
+        }),
+        Message::Address(address) => Err(format!(
+            "Device derived {} but the descriptor gives {}; refusing to use this address",
+            address.address, expected
+        )),
+        Message::Failure(failure) => Err(format!("Device returned error: {}", failure.message.unwrap_or_default())),
+        _ => Err("Unexpected response from device for multisig address request".to_string()),
+    }
+}
+
+/// Add the device's signatures to a base64 PSBT spending from the wallet
+pub async fn sign_psbt(handle: &DeviceQueueHandle, wallet_id: &str, psbt: &str) -> Result<SignedMultisigPsbt, String> {
+    let (wallet, descriptor) = find_wallet(wallet_id)?;
+    let mut psbt = parse_psbt(psbt.as_bytes()).map_err(|e| e.to_string())?;
+    descriptor.add_global_xpubs(&mut psbt);
+
+    let signed = handle
+        .sign_psbt_as(psbt, Some(parse_fingerprint(&wallet.fingerprint)?))
+        .await
+        .map_err(|e| format!("Failed to sign multisig PSBT: {}", e))?;
+    let complete = signed.psbt.inputs.iter().all(|input| input.partial_sigs.len() >= wallet.threshold);
+    Ok(SignedMultisigPsbt { psbt: signed.psbt.to_string(), complete })
+}
+
+async fn device_handle(device_id: &str, queue_manager: &DeviceQueueManager) -> Result<DeviceQueueHandle, String> {
+    if crate::commands::is_device_in_pin_flow(device_id) {
+        return Err("Device is currently in PIN entry mode. Please complete PIN entry first.".to_string());
+    }
+    let mut manager = queue_manager.lock().await;
+    queue_handle(device_id, &mut manager)
+}
+
+/// Import a multisig descriptor the device is a cosigner of
+#[tauri::command]
+pub async fn import_multisig_descriptor(
+    device_id: String,
+    descriptor: String,
+    name: Option<String>,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<MultisigWallet, String> {
+    let handle = device_handle(&device_id, &queue_manager).await?;
+    import_wallet(&handle, &descriptor, name).await
+}
+
+#[tauri::command]
+pub async fn list_multisig_wallets() -> Result<Vec<MultisigWallet>, String> {
+    list_wallets()
+}
+
+/// Receive (or change) address `index` of a multisig wallet, checked by the
+/// device and optionally shown on it
+#[tauri::command]
+pub async fn get_multisig_address(
+    device_id: String,
+    wallet_id: String,
+    index: u32,
+    change: Option<bool>,
+    show_display: Option<bool>,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<MultisigAddress, String> {
+    let handle = device_handle(&device_id, &queue_manager).await?;
+    wallet_address(&handle, &wallet_id, index, change.unwrap_or(false), show_display.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn sign_multisig_psbt(
+    device_id: String,
+    wallet_id: String,
+    psbt: String,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<SignedMultisigPsbt, String> {
+    let handle = device_handle(&device_id, &queue_manager).await?;
+    sign_psbt(&handle, &wallet_id, &psbt).await
+}
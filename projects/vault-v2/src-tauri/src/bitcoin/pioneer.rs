@@ -0,0 +1,232 @@
+use serde::Deserialize;
+use std::time::Duration;
+
+// Same endpoints the frontend's PioneerAPI used before building moved to Rust
+const PIONEER_BASE_URL: &str = "https://pioneers.dev";
+// Esplora instances, tried in order
+const ESPLORA_URLS: [&str; 2] = ["https://mempool.space/api", "https://blockstream.info/api"];
+
+pub const BITCOIN_NETWORK_ID: &str = "bip122:000000000019d6689c085ae165831e93";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BROADCAST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Unspent output as returned by `/api/v1/listUnspent`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PioneerUtxo {
+    pub txid: String,
+    pub vout: u32,
+    // Pioneer returns satoshis as a string
+    pub value: String,
+    pub address: Option<String>,
+    pub path: Option<String>,
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+}
+
+/// sat/vB estimates from `/api/v1/GetFeeRate`; any level may be missing
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FeeRates {
+    pub slow: Option<f64>,
+    pub average: Option<f64>,
+    pub fast: Option<f64>,
+    pub fastest: Option<f64>,
+}
+
+/// Transaction touching an address, from Esplora's `/address/:address/txs`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddressTx {
+    pub txid: String,
+    pub vout: Vec<AddressTxOutput>,
+    pub status: AddressTxStatus,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddressTxOutput {
+    pub scriptpubkey_address: Option<String>,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AddressTxStatus {
+    pub confirmed: bool,
+    pub block_height: Option<u64>,
+    /// Unix seconds
+    pub block_time: Option<u64>,
+}
+
+/// Transaction from Esplora's `/tx/:txid`
+#[derive(Debug, Clone, Deserialize)]
+pub struct EsploraTx {
+    pub txid: String,
+    pub vin: Vec<EsploraTxInput>,
+    pub vout: Vec<AddressTxOutput>,
+    pub fee: u64,
+    pub weight: u64,
+    pub status: AddressTxStatus,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EsploraTxInput {
+    pub txid: String,
+    pub vout: u32,
+    pub sequence: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangeAddressResponse {
+    change_index: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct BroadcastResponse {
+    txid: Option<String>,
+}
+
+fn client(timeout: Duration) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, String> {
+    client(REQUEST_TIMEOUT)?
+        .get(url)
+        .header("accept", "application/json")
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Request to {} failed: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from {}: {}", url, e))
+}
+
+pub async fn list_unspent(xpub: &str) -> Result<Vec<PioneerUtxo>, String> {
+    get_json(&format!("{}/api/v1/listUnspent/BTC/{}", PIONEER_BASE_URL, xpub)).await
+}
+
+/// Next unused change index for the account behind `xpub`
+pub async fn change_index(xpub: &str) -> Result<u32, String> {
+    let resp: ChangeAddressResponse =
+        get_json(&format!("{}/api/v1/getChangeAddress/BTC/{}", PIONEER_BASE_URL, xpub)).await?;
+    Ok(resp.change_index)
+}
+
+pub async fn fee_rates() -> Result<FeeRates, String> {
+    let caip = format!("{}/slip44:0", BITCOIN_NETWORK_ID);
+    let encoded: String = url::form_urlencoded::byte_serialize(caip.as_bytes()).collect();
+    get_json(&format!("{}/api/v1/GetFeeRate/{}", PIONEER_BASE_URL, encoded)).await
+}
+
+/// Raw hex of a previous transaction, trying mempool.space before blockstream.info
+pub async fn raw_transaction(txid: &str) -> Result<String, String> {
+    let client = client(REQUEST_TIMEOUT)?;
+    let mut last_error = String::new();
+    for base in ESPLORA_URLS {
+        let url = format!("{}/tx/{}/hex", base, txid);
+        match client.get(&url).send().await.and_then(|r| r.error_for_status()) {
+            Ok(resp) => match resp.text().await {
+                Ok(hex) if !hex.trim().is_empty() => return Ok(hex.trim().to_string()),
+                Ok(_) => last_error = format!("{} returned an empty body", url),
+                Err(e) => last_error = format!("{}: {}", url, e),
+            },
+            Err(e) => last_error = format!("{}: {}", url, e),
+        }
+        log::warn!("Raw transaction lookup failed, trying next source: {}", last_error);
+    }
+    Err(format!("Failed to fetch raw transaction {}: {}", txid, last_error))
+}
+
+/// Inputs, outputs, fee and confirmation status of a transaction
+pub async fn transaction(txid: &str) -> Result<EsploraTx, String> {
+    let mut last_error = String::new();
+    for base in ESPLORA_URLS {
+        match get_json(&format!("{}/tx/{}", base, txid)).await {
+            Ok(tx) => return Ok(tx),
+            Err(e) => last_error = e,
+        }
+        log::warn!("Transaction lookup failed, trying next source: {}", last_error);
+    }
+    Err(format!("Failed to fetch transaction {}: {}", txid, last_error))
+}
+
+/// Mempool and most recent confirmed transactions involving `address`
+pub async fn address_transactions(address: &str) -> Result<Vec<AddressTx>, String> {
+    let mut last_error = String::new();
+    for base in ESPLORA_URLS {
+        match get_json(&format!("{}/address/{}/txs", base, address)).await {
+            Ok(txs) => return Ok(txs),
+            Err(e) => last_error = e,
+        }
+        log::warn!("Address lookup failed, trying next source: {}", last_error);
+    }
+    Err(format!("Failed to fetch transactions for {}: {}", address, last_error))
+}
+
+/// sat/vB needed to confirm within each target, in blocks, lowest target first
+pub async fn fee_estimates() -> Result<Vec<(u32, f64)>, String> {
+    let mut last_error = String::new();
+    for base in ESPLORA_URLS {
+        match get_json::<std::collections::HashMap<String, f64>>(&format!("{}/fee-estimates", base)).await {
+            Ok(estimates) => {
+                let mut estimates: Vec<(u32, f64)> = estimates
+                    .into_iter()
+                    .filter_map(|(target, rate)| target.parse().ok().map(|target| (target, rate)))
+                    .collect();
+                estimates.sort_by_key(|(target, _)| *target);
+                return Ok(estimates);
+            }
+            Err(e) => last_error = e,
+        }
+        log::warn!("Fee estimate lookup failed, trying next source: {}", last_error);
+    }
+    Err(format!("Failed to fetch fee estimates: {}", last_error))
+}
+
+/// Height of the best block
+pub async fn tip_height() -> Result<u64, String> {
+    let client = client(REQUEST_TIMEOUT)?;
+    let mut last_error = String::new();
+    for base in ESPLORA_URLS {
+        let url = format!("{}/blocks/tip/height", base);
+        match client.get(&url).send().await.and_then(|r| r.error_for_status()) {
+            Ok(resp) => match resp.text().await.map(|body| body.trim().parse::<u64>()) {
+                Ok(Ok(height)) => return Ok(height),
+                Ok(Err(e)) => last_error = format!("{} returned an invalid height: {}", url, e),
+                Err(e) => last_error = format!("{}: {}", url, e),
+            },
+            Err(e) => last_error = format!("{}: {}", url, e),
+        }
+        log::warn!("Tip height lookup failed, trying next source: {}", last_error);
+    }
+    Err(format!("Failed to fetch tip height: {}", last_error))
+}
+
+pub async fn broadcast(serialized: &str) -> Result<String, String> {
+    let url = format!("{}/api/v1/broadcast", PIONEER_BASE_URL);
+    let resp = client(BROADCAST_TIMEOUT)?
+        .post(&url)
+        .json(&serde_json::json!({
+            "networkId": BITCOIN_NETWORK_ID,
+            "serialized": serialized,
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Transaction broadcast failed: {}", e))?;
+
+    let status = resp.status();
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| format!("Transaction broadcast failed: {}", e))?;
+    if !status.is_success() {
+        return Err(format!("Transaction broadcast failed ({}): {}", status, body));
+    }
+    serde_json::from_str::<BroadcastResponse>(&body)
+        .ok()
+        .and_then(|r| r.txid)
+        .ok_or_else(|| format!("Broadcast response missing txid: {}", body))
+}
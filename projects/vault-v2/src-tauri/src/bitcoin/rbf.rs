@@ -0,0 +1,305 @@
+//! Replace-by-fee for sends that are stuck in the mempool.
+//!
+//! Every send the vault broadcasts is recorded in
+//! `~/.keepkey/sent_transactions.json` with the inputs and outputs it was
+//! signed with, since the network copy of a transaction carries no derivation
+//! paths. A bump takes the original's inputs, checks them against the copy in
+//! the mempool, and pays the extra fee out of change (or out of the amount for
+//! a send-max). The replacement follows BIP125: a higher feerate than the
+//! original, and an absolute fee increase that covers its own size at the
+//! incremental relay feerate.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::pioneer::EsploraTx;
+use super::send::ComposedTransaction;
+use super::tx_builder::{self, SpendableUtxo, DUST_THRESHOLD};
+
+/// nSequence that signals BIP125 replaceability without enabling relative timelocks
+pub const RBF_SEQUENCE: u32 = 0xffff_fffd;
+/// Bitcoin Core's default incremental relay feerate, sat/vB
+pub const INCREMENTAL_RELAY_FEE: u64 = 1;
+
+const SENT_FILE: &str = "sent_transactions.json";
+// Anything older has long confirmed or been dropped from mempools
+const SENT_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// A broadcast send, kept so it can be bumped later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentTransaction {
+    pub txid: String,
+    /// Unix seconds
+    pub sent_at: i64,
+    pub composed: ComposedTransaction,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SentFile {
+    #[serde(default)]
+    transactions: Vec<SentTransaction>,
+}
+
+fn sent_path() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    Ok(home_dir.join(".keepkey").join(SENT_FILE))
+}
+
+fn read_store() -> Result<SentFile, String> {
+    let path = sent_path()?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse sent transactions {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SentFile::default()),
+        Err(e) => Err(format!("Failed to read sent transactions {}: {}", path.display(), e)),
+    }
+}
+
+fn write_store(store: &SentFile) -> Result<(), String> {
+    let path = sent_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create sent transactions directory: {}", e))?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    let contents = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("Failed to serialize sent transactions: {}", e))?;
+    std::fs::write(&tmp_path, contents).map_err(|e| format!("Failed to write sent transactions: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to replace sent transactions: {}", e))
+}
+
+/// Remember a broadcast send; `replaces` is dropped since it can no longer confirm
+pub fn record(txid: &str, composed: &ComposedTransaction, replaces: Option<&str>) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp();
+    let mut store = read_store()?;
+    store
+        .transactions
+        .retain(|t| now - t.sent_at < SENT_RETENTION_SECS && t.txid != txid && Some(t.txid.as_str()) != replaces);
+    store.transactions.push(SentTransaction {
+        txid: txid.to_string(),
+        sent_at: now,
+        composed: composed.clone(),
+    });
+    write_store(&store)
+}
+
+pub fn find(device_id: &str, txid: &str) -> Result<SentTransaction, String> {
+    read_store()?
+        .transactions
+        .into_iter()
+        .find(|t| t.txid == txid && t.composed.device_id == device_id)
+        .ok_or_else(|| format!("Transaction {} was not sent from this vault with this device", txid))
+}
+
+/// Sends recorded in the last month, oldest first; most have long confirmed
+pub fn recent() -> Result<Vec<SentTransaction>, String> {
+    let now = chrono::Utc::now().timestamp();
+    let mut sent: Vec<SentTransaction> =
+        read_store()?.transactions.into_iter().filter(|t| now - t.sent_at < SENT_RETENTION_SECS).collect();
+    sent.sort_by_key(|t| t.sent_at);
+    Ok(sent)
+}
+
+pub fn forget(txid: &str) -> Result<(), String> {
+    let mut store = read_store()?;
+    store.transactions.retain(|t| t.txid != txid);
+    write_store(&store)
+}
+
+/// Original fee and feerate as the network sees them
+pub fn network_fee(tx: &EsploraTx) -> (u64, u64) {
+    let vsize = tx.weight.div_ceil(4).max(1);
+    (tx.fee, tx.fee.div_ceil(vsize))
+}
+
+/// Rebuild `original` at `fee_rate` sat/vB, spending the same inputs as the
+/// mempool copy `network`
+pub fn replacement(original: &ComposedTransaction, network: &EsploraTx, fee_rate: u64) -> Result<ComposedTransaction, String> {
+    let mut signed: Vec<String> = original.inputs.iter().map(|i| format!("{}:{}", i.txid, i.vout)).collect();
+    let mut broadcast: Vec<String> = network.vin.iter().map(|i| format!("{}:{}", i.txid, i.vout)).collect();
+    signed.sort();
+    broadcast.sort();
+    if signed != broadcast {
+        return Err(format!("Transaction {} does not spend the inputs this vault signed", network.txid));
+    }
+
+    let (old_fee, old_rate) = network_fee(network);
+    let min_rate = old_rate + INCREMENTAL_RELAY_FEE;
+    if fee_rate < min_rate {
+        return Err(format!(
+            "Replacement needs at least {} sat/vB; the original pays {} sat/vB",
+            min_rate, old_rate
+        ));
+    }
+
+    let utxos = original
+        .inputs
+        .iter()
+        .map(|i| {
+            Ok(SpendableUtxo {
+                address_n_list: i.address_n_list.clone(),
+                txid: i.txid.clone(),
+                vout: i.vout,
+                value: i.amount.parse().map_err(|_| format!("Input {}:{} has invalid amount {:?}", i.txid, i.vout, i.amount))?,
+                script_type: i.script_type.clone(),
+                confirmations: 0,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let total_in: u64 = utxos.iter().map(|u| u.value).sum();
+    // BIP125 rules 3 and 4: pay more than before, plus the replacement's own relay cost
+    let required_fee = |vsize: u64| (vsize * fee_rate).max(old_fee + vsize * INCREMENTAL_RELAY_FEE);
+
+    let mut amount = original.amount_sats;
+    let mut change = original.change_sats;
+    let mut vsize = tx_builder::estimate_vsize(&utxos, &original.destination, change.is_some());
+    let mut fee = required_fee(vsize);
+    let extra = fee.saturating_sub(old_fee);
+
+    match change {
+        Some(old_change) if old_change >= extra + DUST_THRESHOLD => change = Some(old_change - extra),
+        Some(_) => {
+            // Change would be dust; drop it and let the fee take the rest
+            change = None;
+            vsize = tx_builder::estimate_vsize(&utxos, &original.destination, false);
+            fee = total_in.saturating_sub(amount);
+            if fee < required_fee(vsize) {
+                return Err(format!(
+                    "Change of {} sats can't cover a {} sat/vB fee",
+                    original.change_sats.unwrap_or_default(),
+                    fee_rate
+                ));
+            }
+        }
+        None if original.send_max => {
+            amount = amount
+                .checked_sub(extra)
+                .filter(|a| *a >= DUST_THRESHOLD)
+                .ok_or_else(|| format!("Sending the whole balance leaves nothing for a {} sat/vB fee", fee_rate))?;
+        }
+        None => {
+            return Err(format!(
+                "Transaction {} has no change to pay a higher fee from",
+                network.txid
+            ))
+        }
+    }
+
+    let mut inputs = original.inputs.clone();
+    for input in &mut inputs {
+        input.sequence = Some(RBF_SEQUENCE);
+    }
+    let mut outputs = Vec::with_capacity(original.outputs.len());
+    for output in &original.outputs {
+        let mut output = output.clone();
+        if output.is_change == Some(true) {
+            match change {
+                Some(sats) => output.amount = sats,
+                None => continue,
+            }
+        } else if output.address_type == "spend" {
+            output.amount = amount;
+        }
+        outputs.push(output);
+    }
+
+    Ok(ComposedTransaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        amount_sats: amount,
+        fee_sats: fee,
+        fee_rate: fee.div_ceil(vsize),
+        vsize,
+        change_sats: change,
+        unconfirmed_inputs: 0,
+        inputs,
+        outputs,
+        ..original.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitcoin::pioneer::{AddressTxOutput, AddressTxStatus, EsploraTxInput};
+    use crate::bitcoin::tx_builder::CoinSelection;
+    use crate::commands::{BitcoinUtxoInput, BitcoinUtxoOutput};
+
+    const DEST: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+    const PREV: &str = "aa00000000000000000000000000000000000000000000000000000000000000";
+
+    fn original(change: Option<u64>) -> (ComposedTransaction, EsploraTx) {
+        let mut outputs = vec![BitcoinUtxoOutput {
+            address: DEST.to_string(),
+            amount: 50_000,
+            address_type: "spend".to_string(),
+            is_change: None,
+            address_n_list: None,
+            script_type: None,
+            op_return_data: None,
+        }];
+        if let Some(sats) = change {
+            outputs.push(BitcoinUtxoOutput {
+                address: String::new(),
+                amount: sats,
+                address_type: "change".to_string(),
+                is_change: Some(true),
+                address_n_list: Some(vec![0x8000_0054, 0x8000_0000, 0x8000_0000, 1, 0]),
+                script_type: Some("p2wpkh".to_string()),
+                op_return_data: None,
+            });
+        }
+        // 1-in 2-out p2wpkh is 141 vB; at 2 sat/vB the fee is 282
+        let composed = ComposedTransaction {
+            id: "original".to_string(),
+            device_id: "device".to_string(),
+            destination: DEST.to_string(),
+            amount_sats: 50_000,
+            fee_sats: 282,
+            fee_rate: 2,
+            vsize: 141,
+            change_sats: change,
+            send_max: false,
+            inputs: vec![BitcoinUtxoInput {
+                address_n_list: vec![0x8000_0054, 0x8000_0000, 0x8000_0000, 0, 0],
+                script_type: "p2wpkh".to_string(),
+                amount: (50_282 + change.unwrap_or_default()).to_string(),
+                vout: 0,
+                txid: PREV.to_string(),
+                prev_tx_hex: None,
+                sequence: Some(RBF_SEQUENCE),
+            }],
+            outputs,
+            unconfirmed_inputs: 0,
+            coin_selection: CoinSelection::MinimizeFee,
+        };
+        let network = EsploraTx {
+            txid: "bb".to_string(),
+            vin: vec![EsploraTxInput { txid: PREV.to_string(), vout: 0, sequence: RBF_SEQUENCE }],
+            vout: vec![AddressTxOutput { scriptpubkey_address: Some(DEST.to_string()), value: 50_000 }],
+            fee: 282,
+            weight: 561,
+            status: AddressTxStatus { confirmed: false, block_height: None, block_time: None },
+        };
+        (composed, network)
+    }
+
+    #[test]
+    fn bump_takes_the_extra_fee_from_change() {
+        let (composed, network) = original(Some(20_000));
+        assert!(replacement(&composed, &network, 2).is_err());
+
+        let bumped = replacement(&composed, &network, 10).unwrap();
+        assert_eq!(bumped.amount_sats, 50_000);
+        assert_eq!(bumped.fee_sats, 1_410);
+        assert_eq!(bumped.change_sats, Some(20_000 - (1_410 - 282)));
+        assert_eq!(bumped.outputs[1].amount, bumped.change_sats.unwrap());
+        assert!(bumped.inputs.iter().all(|i| i.sequence == Some(RBF_SEQUENCE)));
+
+        // Without change there's nothing to pay from unless it was a send-max
+        let (composed, network) = original(None);
+        assert!(replacement(&composed, &network, 10).is_err());
+        let send_max = ComposedTransaction { send_max: true, ..composed };
+        let bumped = replacement(&send_max, &network, 10).unwrap();
+        assert_eq!(bumped.amount_sats + bumped.fee_sats, 50_282);
+    }
+}
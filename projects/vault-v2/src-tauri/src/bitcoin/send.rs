@@ -0,0 +1,542 @@
+use keepkey_rust::derivation_path::DerivationPath;
+use keepkey_rust::device_queue::DeviceQueueHandle;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+use super::accounts::{device_xpub, queue_handle, ACCOUNTS};
+use super::chain_providers;
+use super::fees::{resolve_fee_rate, FeePreference};
+use super::pioneer;
+use super::rbf;
+use super::tx_builder::{self, CoinSelection, SendAmount, SpendableUtxo, TxPlan};
+use crate::commands::{
+    BitcoinUtxoInput, BitcoinUtxoOutput, DeviceQueueManager, DeviceRequest, DeviceRequestWrapper, DeviceResponse,
+};
+
+const CHANGE_ACCOUNT_PATH: &str = "m/84'/0'/0'";
+const CHANGE_SCRIPT_TYPE: &str = "p2wpkh";
+
+// Composed transactions go stale as UTXOs and fees move
+const COMPOSED_TX_TTL: Duration = Duration::from_secs(10 * 60);
+// Preview warns when the fee is more than this share of the amount sent
+const HIGH_FEE_PERCENT: f64 = 10.0;
+// keepkey.json key holding the coin selection strategy per account path
+const COIN_SELECTION_KEY: &str = "coin_selection";
+// UTXOs listed for coin control are reused by the compose that follows
+const UTXO_CACHE_TTL: Duration = Duration::from_secs(60);
+
+static COMPOSED: Lazy<Mutex<HashMap<String, (Instant, ComposedTransaction)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// Keyed by wallet scope, so a hidden wallet never sees the standard wallet's coins
+static UTXO_CACHE: Lazy<Mutex<HashMap<String, (Instant, WalletUtxos)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+struct WalletUtxos {
+    utxos: Vec<SpendableUtxo>,
+    /// SLIP-132 xpub of the change account
+    change_xpub: Option<String>,
+}
+
+/// What the user wants to send; everything else is worked out here
+#[derive(Debug, Clone, Deserialize)]
+pub struct SendIntent {
+    pub destination: String,
+    /// Amount in satoshis; ignored when `send_max` is set
+    pub amount_sats: Option<u64>,
+    #[serde(default)]
+    pub send_max: bool,
+    #[serde(default)]
+    pub fee: FeePreference,
+    /// Spend only from this account (e.g. "m/84'/0'/0'") using its coin
+    /// selection preference. Without it every account is pooled and the
+    /// change account's preference applies.
+    #[serde(default)]
+    pub account: Option<String>,
+    /// Coin control: spend exactly these outpoints ("txid:vout") instead of
+    /// letting the coin selection strategy choose
+    #[serde(default)]
+    pub inputs: Vec<String>,
+}
+
+/// An unsigned transaction ready for the device, kept until it is signed or expires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposedTransaction {
+    pub id: String,
+    pub device_id: String,
+    pub destination: String,
+    pub amount_sats: u64,
+    pub fee_sats: u64,
+    pub fee_rate: u64,
+    pub vsize: u64,
+    pub change_sats: Option<u64>,
+    pub send_max: bool,
+    pub inputs: Vec<BitcoinUtxoInput>,
+    pub outputs: Vec<BitcoinUtxoOutput>,
+    pub unconfirmed_inputs: usize,
+    pub coin_selection: CoinSelection,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionPreview {
+    pub id: String,
+    pub destination: String,
+    pub amount_sats: u64,
+    pub fee_sats: u64,
+    pub fee_rate: u64,
+    pub vsize: u64,
+    pub change_sats: Option<u64>,
+    pub total_sats: u64,
+    pub input_count: usize,
+    /// Outpoints ("txid:vout") being spent
+    pub spent_outpoints: Vec<String>,
+    pub coin_selection: CoinSelection,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastResult {
+    pub txid: String,
+    pub signed_tx: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FeeBumpResult {
+    pub txid: String,
+    /// The transaction this one replaces
+    pub replaced_txid: String,
+    pub signed_tx: String,
+    pub previous_fee_sats: u64,
+    pub fee_sats: u64,
+    pub fee_rate: u64,
+    pub amount_sats: u64,
+    pub change_sats: Option<u64>,
+}
+
+/// Stored strategy for `account_path`, or the default when none is set
+fn coin_selection_for(account_path: &str) -> Result<CoinSelection, String> {
+    let config = crate::commands::load_config()?;
+    match config.get(COIN_SELECTION_KEY).and_then(|m| m.get(account_path)) {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid coin selection for {}: {}", account_path, e)),
+        None => Ok(CoinSelection::default()),
+    }
+}
+
+/// Coin selection strategy for each of the vault's accounts, keyed by account path
+#[tauri::command]
+pub async fn get_coin_selection_preferences() -> Result<HashMap<String, CoinSelection>, String> {
+    ACCOUNTS
+        .iter()
+        .map(|(path, _)| Ok((path.to_string(), coin_selection_for(path)?)))
+        .collect()
+}
+
+#[tauri::command]
+pub async fn set_coin_selection_preference(account_path: String, strategy: CoinSelection) -> Result<(), String> {
+    if !ACCOUNTS.iter().any(|(path, _)| *path == account_path) {
+        return Err(format!("Unknown account {}", account_path));
+    }
+    if strategy == CoinSelection::Manual {
+        return Err("Manual coin selection is chosen per send by picking inputs".to_string());
+    }
+    let mut config = crate::commands::load_config()?;
+    if let Some(obj) = config.as_object_mut() {
+        let strategies = obj
+            .entry(COIN_SELECTION_KEY)
+            .or_insert_with(|| serde_json::json!({}));
+        if !strategies.is_object() {
+            *strategies = serde_json::json!({});
+        }
+        strategies[account_path.as_str()] = serde_json::json!(strategy);
+    }
+    crate::commands::save_config(&config)?;
+    log::info!("Coin selection for {} set to {}", account_path, strategy.as_str());
+    Ok(())
+}
+
+async fn spendable_utxos(xpub: &str, script_type: &str) -> Result<Vec<SpendableUtxo>, String> {
+    pioneer::list_unspent(xpub)
+        .await?
+        .into_iter()
+        .map(|u| {
+            let path = u.path.as_deref().ok_or_else(|| format!("UTXO {}:{} has no derivation path", u.txid, u.vout))?;
+            let value = u
+                .value
+                .parse::<u64>()
+                .map_err(|_| format!("UTXO {}:{} has invalid value {:?}", u.txid, u.vout, u.value))?;
+            Ok(SpendableUtxo {
+                address_n_list: path.parse::<DerivationPath>().map_err(|e| e.to_string())?.into_vec(),
+                txid: u.txid,
+                vout: u.vout,
+                value,
+                script_type: script_type.to_string(),
+                confirmations: u.confirmations.unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+fn wallet_scope(device_id: &str) -> String {
+    crate::passphrase_profiles::active_profile(device_id)
+        .map(|profile| profile.scope)
+        .unwrap_or_else(|| device_id.to_string())
+}
+
+/// Every account's UTXOs, from the cache unless `refresh` is set or it has expired
+async fn wallet_utxos(device_id: &str, handle: &DeviceQueueHandle, refresh: bool) -> Result<WalletUtxos, String> {
+    let scope = wallet_scope(device_id);
+    if !refresh {
+        if let Some((fetched, wallet)) = UTXO_CACHE.lock().await.get(&scope) {
+            if fetched.elapsed() < UTXO_CACHE_TTL {
+                return Ok(wallet.clone());
+            }
+        }
+    }
+
+    let mut wallet = WalletUtxos { utxos: Vec::new(), change_xpub: None };
+    for (path, script_type) in ACCOUNTS {
+        // Pioneer derives addresses from the SLIP-132 prefix
+        let xpub = crate::slip132::convert_xpub_prefix(&device_xpub(handle, path, false).await?, script_type)?;
+        wallet.utxos.extend(spendable_utxos(&xpub, script_type).await?);
+        if path == CHANGE_ACCOUNT_PATH {
+            wallet.change_xpub = Some(xpub);
+        }
+    }
+
+    let mut cache = UTXO_CACHE.lock().await;
+    cache.retain(|_, (fetched, _)| fetched.elapsed() < UTXO_CACHE_TTL);
+    cache.insert(scope, (Instant::now(), wallet.clone()));
+    Ok(wallet)
+}
+
+async fn device_handle(device_id: &str, queue_manager: &DeviceQueueManager) -> Result<DeviceQueueHandle, String> {
+    if crate::commands::is_device_in_pin_flow(device_id) {
+        return Err("Device is currently in PIN entry mode. Please complete PIN entry first.".to_string());
+    }
+    let mut manager = queue_manager.lock().await;
+    queue_handle(device_id, &mut manager)
+}
+
+/// Spendable UTXOs across the vault's accounts, for picking inputs by hand
+#[tauri::command]
+pub async fn list_spendable_utxos(
+    device_id: String,
+    refresh: Option<bool>,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<Vec<SpendableUtxo>, String> {
+    let handle = device_handle(&device_id, &queue_manager).await?;
+    let mut utxos = wallet_utxos(&device_id, &handle, refresh.unwrap_or(false)).await?.utxos;
+    utxos.sort_by_key(|u| std::cmp::Reverse(u.value));
+    Ok(utxos)
+}
+
+async fn device_payload(plan: &TxPlan, destination: &str, change_index: Option<u32>) -> Result<(Vec<BitcoinUtxoInput>, Vec<BitcoinUtxoOutput>), String> {
+    let mut inputs = Vec::with_capacity(plan.inputs.len());
+    for utxo in &plan.inputs {
+        // Only legacy inputs need the previous transaction; segwit commits to the amount
+        let prev_tx_hex = if utxo.script_type == "p2pkh" {
+            Some(pioneer::raw_transaction(&utxo.txid).await?)
+        } else {
+            None
+        };
+        inputs.push(BitcoinUtxoInput {
+            address_n_list: utxo.address_n_list.clone(),
+            script_type: utxo.script_type.clone(),
+            amount: utxo.value.to_string(),
+            vout: utxo.vout,
+            txid: utxo.txid.clone(),
+            prev_tx_hex,
+            // Every send can be fee-bumped while it is unconfirmed
+            sequence: Some(rbf::RBF_SEQUENCE),
+        });
+    }
+
+    let mut outputs = vec![BitcoinUtxoOutput {
+        address: destination.to_string(),
+        amount: plan.amount,
+        address_type: "spend".to_string(),
+        is_change: None,
+        address_n_list: None,
+        script_type: None,
+        op_return_data: None,
+    }];
+    if let (Some(change), Some(index)) = (plan.change, change_index) {
+        let path = CHANGE_ACCOUNT_PATH
+            .parse::<DerivationPath>()
+            .map_err(|e| e.to_string())?
+            .extend([1, index])
+            .into_vec();
+        outputs.push(BitcoinUtxoOutput {
+            address: String::new(),
+            amount: change,
+            address_type: "change".to_string(),
+            is_change: Some(true),
+            address_n_list: Some(path),
+            script_type: Some(CHANGE_SCRIPT_TYPE.to_string()),
+            op_return_data: None,
+        });
+    }
+    Ok((inputs, outputs))
+}
+
+/// Select inputs, fees and change for `intent` and keep the result for signing
+#[tauri::command]
+pub async fn compose_transaction(
+    device_id: String,
+    intent: SendIntent,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<ComposedTransaction, String> {
+    // Pasted addresses often carry whitespace; anything else wrong is a hard error
+    let destination = intent.destination.trim().to_string();
+    super::address::validate_address(&destination)?;
+    let amount = match (intent.send_max, intent.amount_sats) {
+        (true, _) => SendAmount::Max,
+        (false, Some(sats)) => SendAmount::Exact(sats),
+        (false, None) => return Err("Amount is required unless sending max".to_string()),
+    };
+    if let Some(account) = &intent.account {
+        if !ACCOUNTS.iter().any(|(path, _)| path == account) {
+            return Err(format!("Unknown account {}", account));
+        }
+    }
+    let strategy = coin_selection_for(intent.account.as_deref().unwrap_or(CHANGE_ACCOUNT_PATH))?;
+    let handle = device_handle(&device_id, &queue_manager).await?;
+
+    let WalletUtxos { mut utxos, change_xpub } = wallet_utxos(&device_id, &handle, false).await?;
+    if let Some(account) = &intent.account {
+        let prefix = account.parse::<DerivationPath>().map_err(|e| e.to_string())?.into_vec();
+        utxos.retain(|u| u.address_n_list.starts_with(&prefix));
+    }
+
+    let rates = chain_providers::fee_rates().await.unwrap_or_else(|e| {
+        log::warn!("Fee rates unavailable, using defaults: {}", e);
+        Default::default()
+    });
+    let fee_rate = resolve_fee_rate(&rates, intent.fee)?;
+    let plan = if intent.inputs.is_empty() {
+        tx_builder::build(&utxos, &destination, amount, fee_rate, strategy)?
+    } else {
+        tx_builder::build_manual(&utxos, &intent.inputs, &destination, amount, fee_rate)?
+    };
+
+    let change_index = match (plan.change, &change_xpub) {
+        (Some(_), Some(xpub)) => Some(pioneer::change_index(xpub).await?),
+        (Some(_), None) => return Err("No native segwit account to receive change".to_string()),
+        (None, _) => None,
+    };
+    let (inputs, outputs) = device_payload(&plan, &destination, change_index).await?;
+
+    let composed = ComposedTransaction {
+        id: uuid::Uuid::new_v4().to_string(),
+        device_id,
+        destination,
+        amount_sats: plan.amount,
+        fee_sats: plan.fee,
+        fee_rate,
+        vsize: plan.vsize,
+        change_sats: plan.change,
+        send_max: intent.send_max,
+        unconfirmed_inputs: plan.inputs.iter().filter(|u| u.confirmations == 0).count(),
+        coin_selection: plan.strategy,
+        inputs,
+        outputs,
+    };
+
+    let audit = serde_json::json!({
+        "transaction_id": composed.id,
+        "coin_selection": composed.coin_selection.as_str(),
+        "account": intent.account,
+        "inputs": plan.inputs.iter().map(SpendableUtxo::outpoint).collect::<Vec<_>>(),
+        "amount_sats": composed.amount_sats,
+        "fee_sats": composed.fee_sats,
+        "fee_rate": composed.fee_rate,
+        "change_sats": composed.change_sats,
+    });
+    if let Err(e) = crate::logging::log_audit_event(&composed.device_id, "transaction_built", &audit).await {
+        log::warn!("Failed to write audit entry for {}: {}", composed.id, e);
+    }
+
+    let mut composed_txs = COMPOSED.lock().await;
+    composed_txs.retain(|_, (created, _)| created.elapsed() < COMPOSED_TX_TTL);
+    composed_txs.insert(composed.id.clone(), (Instant::now(), composed.clone()));
+    Ok(composed)
+}
+
+/// Human-facing summary of a composed transaction, for the confirm screen
+#[tauri::command]
+pub async fn preview_transaction(transaction_id: String) -> Result<TransactionPreview, String> {
+    let composed = {
+        let composed_txs = COMPOSED.lock().await;
+        match composed_txs.get(&transaction_id) {
+            Some((created, tx)) if created.elapsed() < COMPOSED_TX_TTL => tx.clone(),
+            _ => return Err(format!("Transaction {} not found or expired; compose it again", transaction_id)),
+        }
+    };
+
+    let mut warnings = Vec::new();
+    let fee_percent = composed.fee_sats as f64 * 100.0 / composed.amount_sats.max(1) as f64;
+    if fee_percent > HIGH_FEE_PERCENT {
+        warnings.push(format!("Fee is {:.1}% of the amount sent", fee_percent));
+    }
+    if composed.unconfirmed_inputs > 0 {
+        warnings.push(format!("Spends {} unconfirmed input(s)", composed.unconfirmed_inputs));
+    }
+    if composed.send_max {
+        warnings.push("Sends the entire balance; no change is returned".to_string());
+    }
+
+    Ok(TransactionPreview {
+        id: composed.id,
+        destination: composed.destination,
+        amount_sats: composed.amount_sats,
+        fee_sats: composed.fee_sats,
+        fee_rate: composed.fee_rate,
+        vsize: composed.vsize,
+        change_sats: composed.change_sats,
+        total_sats: composed.amount_sats + composed.fee_sats,
+        input_count: composed.inputs.len(),
+        spent_outpoints: composed.inputs.iter().map(|i| format!("{}:{}", i.txid, i.vout)).collect(),
+        coin_selection: composed.coin_selection,
+        warnings,
+    })
+}
+
+/// Sign `composed` through the device queue and broadcast it, returning the
+/// txid and signed hex
+async fn sign_composed(composed: &ComposedTransaction, app: &AppHandle) -> Result<(String, String), String> {
+    let request = DeviceRequestWrapper {
+        device_id: composed.device_id.clone(),
+        request_id: format!("send_{}", composed.id),
+        request: DeviceRequest::SignTransaction {
+            coin: "Bitcoin".to_string(),
+            inputs: composed.inputs.clone(),
+            outputs: composed.outputs.clone(),
+            version: 1,
+            lock_time: 0,
+        },
+    };
+    let queue_manager = app.state::<DeviceQueueManager>();
+    let last_responses = app.state::<Arc<Mutex<HashMap<String, DeviceResponse>>>>();
+    let request_id =
+        crate::device::queue::add_to_device_queue(request, queue_manager, last_responses.clone(), app.clone()).await?;
+
+    let signed_tx = match last_responses.lock().await.get(&request_id) {
+        Some(DeviceResponse::SignedTransaction { success: true, signed_tx, .. }) if !signed_tx.is_empty() => signed_tx.clone(),
+        Some(DeviceResponse::SignedTransaction { error, .. }) => {
+            return Err(error.clone().unwrap_or_else(|| "Device did not return a signed transaction".to_string()))
+        }
+        _ => return Err("Device did not return a signed transaction".to_string()),
+    };
+
+    let txid = pioneer::broadcast(&signed_tx).await?;
+    // The spent inputs are gone; the next listing must not offer them
+    UTXO_CACHE.lock().await.remove(&wallet_scope(&composed.device_id));
+    Ok((txid, signed_tx))
+}
+
+/// Sign a composed transaction on its device and broadcast it
+#[tauri::command]
+pub async fn sign_and_broadcast(transaction_id: String, app: AppHandle) -> Result<BroadcastResult, String> {
+    // Taken out so a double click can't sign twice
+    let composed = match COMPOSED.lock().await.remove(&transaction_id) {
+        Some((created, tx)) if created.elapsed() < COMPOSED_TX_TTL => tx,
+        _ => return Err(format!("Transaction {} not found or expired; compose it again", transaction_id)),
+    };
+
+    let (txid, signed_tx) = sign_composed(&composed, &app).await?;
+    log::info!("📡 Broadcast {} ({} sats to {})", txid, composed.amount_sats, composed.destination);
+    if let Err(e) = rbf::record(&txid, &composed, None) {
+        log::warn!("Failed to record {} for fee bumping: {}", txid, e);
+    }
+
+    let _ = app.emit(
+        "wallet:transaction-broadcast",
+        serde_json::json!({
+            "deviceId": composed.device_id,
+            "txid": txid,
+            "amountSats": composed.amount_sats,
+            "feeSats": composed.fee_sats,
+        }),
+    );
+
+    Ok(BroadcastResult { txid, signed_tx })
+}
+
+/// Replace the unconfirmed send `txid` with one paying `fee`, re-signed on the
+/// device. Shared by the Tauri command and the REST route.
+pub async fn bump_transaction(app: &AppHandle, device_id: &str, txid: &str, fee: FeePreference) -> Result<FeeBumpResult, String> {
+    let sent = rbf::find(device_id, txid)?;
+    let network = pioneer::transaction(txid).await?;
+    if network.status.confirmed {
+        if let Err(e) = rbf::forget(txid) {
+            log::warn!("Failed to forget confirmed send {}: {}", txid, e);
+        }
+        return Err(format!("Transaction {} is already confirmed", txid));
+    }
+    if network.vin.iter().any(|i| i.sequence > rbf::RBF_SEQUENCE) {
+        // Sent before opt-in signalling; most nodes now relay full-RBF replacements anyway
+        log::warn!("Transaction {} does not signal replaceability", txid);
+    }
+
+    let rates = chain_providers::fee_rates().await.unwrap_or_else(|e| {
+        log::warn!("Fee rates unavailable, using defaults: {}", e);
+        Default::default()
+    });
+    let (previous_fee_sats, previous_rate) = rbf::network_fee(&network);
+    // A preset below what the original already pays still has to outbid it
+    let fee_rate = match fee {
+        FeePreference::Custom(_) => resolve_fee_rate(&rates, fee)?,
+        _ => resolve_fee_rate(&rates, fee)?.max(previous_rate + rbf::INCREMENTAL_RELAY_FEE),
+    };
+    let replacement = rbf::replacement(&sent.composed, &network, fee_rate)?;
+
+    let audit = serde_json::json!({
+        "transaction_id": replacement.id,
+        "replaces": txid,
+        "previous_fee_sats": previous_fee_sats,
+        "fee_sats": replacement.fee_sats,
+        "fee_rate": replacement.fee_rate,
+        "change_sats": replacement.change_sats,
+    });
+    if let Err(e) = crate::logging::log_audit_event(device_id, "transaction_fee_bumped", &audit).await {
+        log::warn!("Failed to write audit entry for {}: {}", replacement.id, e);
+    }
+
+    let (new_txid, signed_tx) = sign_composed(&replacement, app).await?;
+    log::info!("📡 Replaced {} with {} ({} → {} sats fee)", txid, new_txid, previous_fee_sats, replacement.fee_sats);
+    if let Err(e) = rbf::record(&new_txid, &replacement, Some(txid)) {
+        log::warn!("Failed to record {} for fee bumping: {}", new_txid, e);
+    }
+
+    let _ = app.emit(
+        "wallet:transaction-replaced",
+        serde_json::json!({
+            "deviceId": device_id,
+            "txid": new_txid,
+            "replacedTxid": txid,
+            "feeSats": replacement.fee_sats,
+        }),
+    );
+
+    Ok(FeeBumpResult {
+        txid: new_txid,
+        replaced_txid: txid.to_string(),
+        signed_tx,
+        previous_fee_sats,
+        fee_sats: replacement.fee_sats,
+        fee_rate: replacement.fee_rate,
+        amount_sats: replacement.amount_sats,
+        change_sats: replacement.change_sats,
+    })
+}
+
+/// Fee-bump an unconfirmed send from this vault (replace-by-fee)
+#[tauri::command]
+pub async fn bump_fee(device_id: String, txid: String, fee: Option<FeePreference>, app: AppHandle) -> Result<FeeBumpResult, String> {
+    bump_transaction(&app, &device_id, txid.trim(), fee.unwrap_or_default()).await
+}
@@ -0,0 +1,544 @@
+// Coin selection and size/fee estimation for the wallet's own UTXOs.
+// Sizes are the usual vbyte estimates for single-sig KeepKey scripts, so the
+// fee may be off by a vbyte or two versus the signed transaction.
+//
+// The default strategy first looks for an input set that pays the amount and
+// fee with no change (branch and bound, as Bitcoin Core does), and falls back
+// to largest-first with change. Coin control skips selection entirely and
+// spends the inputs the user picked.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Outputs below this are rejected by default relay policy
+pub const DUST_THRESHOLD: u64 = 546;
+
+// Version, locktime, in/out counts and the segwit marker (rounded up)
+const TX_OVERHEAD_VBYTES: u64 = 11;
+const LEGACY_TX_OVERHEAD_VBYTES: u64 = 10;
+// Change always goes to the BIP84 account
+const CHANGE_OUTPUT_VBYTES: u64 = 31;
+// Spending the change later costs a P2WPKH input
+const CHANGE_SPEND_VBYTES: u64 = 68;
+// Branch and bound gives up after this many steps and leaves it to largest-first
+const BNB_MAX_TRIES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendAmount {
+    Exact(u64),
+    /// Everything spendable minus the fee, with no change output
+    Max,
+}
+
+/// Order in which UTXOs are tried when funding an exact amount
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoinSelection {
+    /// Highest value after its own input cost first, for the smallest transaction
+    #[default]
+    MinimizeFee,
+    /// Smallest first, consolidating dust while fees are low
+    MinimizeUtxoCount,
+    /// Fund from a single account and branch (receive or change) so unrelated
+    /// addresses are not linked on-chain; fails rather than mixing them
+    BranchIsolation,
+    /// Most confirmations first
+    OldestFirst,
+    /// Exactly the inputs the user picked (coin control); chosen per send
+    /// rather than stored as an account preference
+    Manual,
+}
+
+impl CoinSelection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoinSelection::MinimizeFee => "minimize_fee",
+            CoinSelection::MinimizeUtxoCount => "minimize_utxo_count",
+            CoinSelection::BranchIsolation => "branch_isolation",
+            CoinSelection::OldestFirst => "oldest_first",
+            CoinSelection::Manual => "manual",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpendableUtxo {
+    pub txid: String,
+    pub vout: u32,
+    pub value: u64,
+    /// "p2pkh", "p2sh-p2wpkh" or "p2wpkh"
+    pub script_type: String,
+    pub address_n_list: Vec<u32>,
+    pub confirmations: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxPlan {
+    pub inputs: Vec<SpendableUtxo>,
+    pub amount: u64,
+    pub change: Option<u64>,
+    pub fee: u64,
+    pub vsize: u64,
+    pub strategy: CoinSelection,
+}
+
+impl SpendableUtxo {
+    pub fn outpoint(&self) -> String {
+        format!("{}:{}", self.txid, self.vout)
+    }
+
+    /// Value left after paying for its own input at `fee_rate`
+    fn effective_value(&self, fee_rate: u64) -> u64 {
+        self.value.saturating_sub(input_vbytes(&self.script_type) * fee_rate)
+    }
+}
+
+impl TxPlan {
+    pub fn total_input(&self) -> u64 {
+        self.inputs.iter().map(|u| u.value).sum()
+    }
+}
+
+pub fn input_vbytes(script_type: &str) -> u64 {
+    match script_type {
+        "p2wpkh" => 68,
+        "p2sh-p2wpkh" => 91,
+        _ => 148,
+    }
+}
+
+/// Output size from the destination address format
+pub fn output_vbytes(address: &str) -> u64 {
+    let lower = address.to_ascii_lowercase();
+    if lower.starts_with("bc1p") || lower.starts_with("tb1p") {
+        43
+    } else if lower.starts_with("bc1") || lower.starts_with("tb1") {
+        // P2WPKH addresses are 42 characters, P2WSH ones 62
+        if lower.len() > 42 { 43 } else { 31 }
+    } else if lower.starts_with('3') || lower.starts_with('2') {
+        32
+    } else {
+        34
+    }
+}
+
+pub fn estimate_vsize(inputs: &[SpendableUtxo], destination: &str, with_change: bool) -> u64 {
+    let overhead = if inputs.iter().all(|u| u.script_type == "p2pkh") {
+        LEGACY_TX_OVERHEAD_VBYTES
+    } else {
+        TX_OVERHEAD_VBYTES
+    };
+    let inputs: u64 = inputs.iter().map(|u| input_vbytes(&u.script_type)).sum();
+    let change = if with_change { CHANGE_OUTPUT_VBYTES } else { 0 };
+    overhead + inputs + output_vbytes(destination) + change
+}
+
+/// Pick inputs for a payment to `destination` at `fee_rate` sat/vB.
+///
+/// Exact amounts take UTXOs in the order `strategy` gives and add change unless
+/// it would be dust, in which case the remainder goes to the fee;
+/// `MinimizeFee` tries a changeless match first. `Max` spends every UTXO worth
+/// more than the fee to include it, whatever the strategy. For `Manual`, call
+/// [`build_manual`] with the picked inputs instead.
+/// Malformed or non-mainnet destinations are rejected before any coins are
+/// selected.
+pub fn build(
+    utxos: &[SpendableUtxo],
+    destination: &str,
+    amount: SendAmount,
+    fee_rate: u64,
+    strategy: CoinSelection,
+) -> Result<TxPlan, String> {
+    super::address::validate_address(destination)?;
+    if fee_rate == 0 {
+        return Err("Fee rate must be at least 1 sat/vB".to_string());
+    }
+    if utxos.is_empty() {
+        return Err("No UTXOs found".to_string());
+    }
+    if strategy == CoinSelection::Manual {
+        return Err("Manual coin selection needs the inputs to spend".to_string());
+    }
+
+    match amount {
+        SendAmount::Exact(amount) if amount < DUST_THRESHOLD => {
+            Err(format!("Amount {} sats is below the dust threshold of {} sats", amount, DUST_THRESHOLD))
+        }
+        SendAmount::Exact(amount) => match strategy {
+            CoinSelection::BranchIsolation => build_isolated(utxos, destination, amount, fee_rate),
+            CoinSelection::MinimizeFee => match build_changeless(utxos, destination, amount, fee_rate) {
+                Some(plan) => Ok(plan),
+                None => build_exact(ordered(utxos, strategy, fee_rate), destination, amount, fee_rate, strategy),
+            },
+            _ => build_exact(ordered(utxos, strategy, fee_rate), destination, amount, fee_rate, strategy),
+        },
+        SendAmount::Max => build_max(utxos, destination, fee_rate, strategy),
+    }
+}
+
+/// Spend exactly the UTXOs in `selected` ("txid:vout"), with change for an
+/// exact amount unless it would be dust.
+///
+/// Every picked input is spent even when fewer would do, and `Max` spends them
+/// all however little some are worth; that is what the user chose.
+pub fn build_manual(
+    utxos: &[SpendableUtxo],
+    selected: &[String],
+    destination: &str,
+    amount: SendAmount,
+    fee_rate: u64,
+) -> Result<TxPlan, String> {
+    super::address::validate_address(destination)?;
+    if fee_rate == 0 {
+        return Err("Fee rate must be at least 1 sat/vB".to_string());
+    }
+    if selected.is_empty() {
+        return Err("No inputs selected".to_string());
+    }
+
+    let mut inputs: Vec<SpendableUtxo> = Vec::with_capacity(selected.len());
+    for outpoint in selected {
+        if inputs.iter().any(|u| u.outpoint() == *outpoint) {
+            return Err(format!("Input {} is selected twice", outpoint));
+        }
+        let utxo = utxos
+            .iter()
+            .find(|u| u.outpoint() == *outpoint)
+            .ok_or_else(|| format!("Input {} is not an unspent output of this wallet", outpoint))?;
+        inputs.push(utxo.clone());
+    }
+
+    match amount {
+        SendAmount::Exact(amount) if amount < DUST_THRESHOLD => {
+            Err(format!("Amount {} sats is below the dust threshold of {} sats", amount, DUST_THRESHOLD))
+        }
+        SendAmount::Exact(amount) => fund(inputs, destination, amount, fee_rate, CoinSelection::Manual)
+            .map_err(|inputs| insufficient(&inputs, destination, amount, fee_rate)),
+        SendAmount::Max => {
+            let total: u64 = inputs.iter().map(|u| u.value).sum();
+            let vsize = estimate_vsize(&inputs, destination, false);
+            let fee = vsize * fee_rate;
+            let amount = total.saturating_sub(fee);
+            if amount < DUST_THRESHOLD {
+                return Err(format!("Insufficient funds: {} sats left after a {} sat fee", amount, fee));
+            }
+            Ok(TxPlan { inputs, amount, change: None, fee, vsize, strategy: CoinSelection::Manual })
+        }
+    }
+}
+
+fn ordered(utxos: &[SpendableUtxo], strategy: CoinSelection, fee_rate: u64) -> Vec<SpendableUtxo> {
+    let mut candidates = utxos.to_vec();
+    match strategy {
+        CoinSelection::MinimizeFee | CoinSelection::BranchIsolation => {
+            candidates.sort_by_key(|u| std::cmp::Reverse(u.effective_value(fee_rate)));
+        }
+        CoinSelection::MinimizeUtxoCount => candidates.sort_by_key(|u| u.value),
+        CoinSelection::OldestFirst => {
+            candidates.sort_by_key(|u| std::cmp::Reverse((u.confirmations, u.value)))
+        }
+        // The user's order
+        CoinSelection::Manual => {}
+    }
+    candidates
+}
+
+/// Cheapest plan that spends from one (account, branch) group only
+fn build_isolated(utxos: &[SpendableUtxo], destination: &str, amount: u64, fee_rate: u64) -> Result<TxPlan, String> {
+    // address_n_list is account path + [branch, index]
+    let mut groups: BTreeMap<&[u32], Vec<SpendableUtxo>> = BTreeMap::new();
+    for utxo in utxos {
+        let branch = &utxo.address_n_list[..utxo.address_n_list.len().saturating_sub(1)];
+        groups.entry(branch).or_default().push(utxo.clone());
+    }
+
+    groups
+        .values()
+        .filter_map(|group| {
+            let candidates = ordered(group, CoinSelection::BranchIsolation, fee_rate);
+            build_exact(candidates, destination, amount, fee_rate, CoinSelection::BranchIsolation).ok()
+        })
+        .min_by_key(|plan| (plan.fee, plan.inputs.len()))
+        .ok_or_else(|| {
+            format!(
+                "No single account branch holds {} sats plus fees; choose another coin selection strategy to combine them",
+                amount
+            )
+        })
+}
+
+fn build_exact(
+    candidates: Vec<SpendableUtxo>,
+    destination: &str,
+    amount: u64,
+    fee_rate: u64,
+    strategy: CoinSelection,
+) -> Result<TxPlan, String> {
+    let mut selected = Vec::new();
+    for utxo in candidates {
+        selected.push(utxo);
+        selected = match fund(selected, destination, amount, fee_rate, strategy) {
+            Ok(plan) => return Ok(plan),
+            Err(selected) => selected,
+        };
+    }
+    Err(insufficient(&selected, destination, amount, fee_rate))
+}
+
+/// Plan paying `amount` from exactly `inputs`, or the inputs back if they
+/// don't cover it
+fn fund(
+    inputs: Vec<SpendableUtxo>,
+    destination: &str,
+    amount: u64,
+    fee_rate: u64,
+    strategy: CoinSelection,
+) -> Result<TxPlan, Vec<SpendableUtxo>> {
+    let total: u64 = inputs.iter().map(|u| u.value).sum();
+
+    let vsize_with_change = estimate_vsize(&inputs, destination, true);
+    let fee_with_change = vsize_with_change * fee_rate;
+    if total >= amount + fee_with_change + DUST_THRESHOLD {
+        return Ok(TxPlan {
+            change: Some(total - amount - fee_with_change),
+            inputs,
+            amount,
+            fee: fee_with_change,
+            vsize: vsize_with_change,
+            strategy,
+        });
+    }
+
+    let vsize = estimate_vsize(&inputs, destination, false);
+    if total >= amount + vsize * fee_rate {
+        // Leftover is too small for a change output, so it pays the miner
+        return Ok(TxPlan {
+            inputs,
+            amount,
+            change: None,
+            fee: total - amount,
+            vsize,
+            strategy,
+        });
+    }
+    Err(inputs)
+}
+
+fn insufficient(inputs: &[SpendableUtxo], destination: &str, amount: u64, fee_rate: u64) -> String {
+    let total: u64 = inputs.iter().map(|u| u.value).sum();
+    let fee = estimate_vsize(inputs, destination, false) * fee_rate;
+    format!(
+        "Insufficient funds: need {} sats ({} + {} fee) but only have {} sats",
+        amount + fee,
+        amount,
+        fee,
+        total
+    )
+}
+
+/// Branch and bound search for inputs whose effective value lands between the
+/// changeless target and that target plus what a change output would cost
+/// (creating it now and spending it later). The overshoot goes to the fee;
+/// among matches the smallest overshoot wins.
+fn build_changeless(utxos: &[SpendableUtxo], destination: &str, amount: u64, fee_rate: u64) -> Option<TxPlan> {
+    let mut candidates: Vec<&SpendableUtxo> = utxos.iter().filter(|u| u.effective_value(fee_rate) > 0).collect();
+    candidates.sort_by_key(|u| std::cmp::Reverse(u.effective_value(fee_rate)));
+    let values: Vec<u64> = candidates.iter().map(|u| u.effective_value(fee_rate)).collect();
+
+    let target = amount + (TX_OVERHEAD_VBYTES + output_vbytes(destination)) * fee_rate;
+    let upper = target + (CHANGE_OUTPUT_VBYTES + CHANGE_SPEND_VBYTES) * fee_rate;
+    // remaining[i] is what values[i..] could still add
+    let mut remaining = vec![0u64; values.len() + 1];
+    for i in (0..values.len()).rev() {
+        remaining[i] = remaining[i + 1] + values[i];
+    }
+    if remaining[0] < target {
+        return None;
+    }
+
+    let mut best: Option<(u64, Vec<usize>)> = None;
+    let mut picked: Vec<usize> = Vec::new();
+    let mut sum = 0u64;
+    let mut index = 0usize;
+    for _ in 0..BNB_MAX_TRIES {
+        let backtrack = if sum > upper || sum + remaining[index] < target {
+            true
+        } else if sum >= target {
+            if best.as_ref().is_none_or(|(excess, _)| sum - target < *excess) {
+                best = Some((sum - target, picked.clone()));
+            }
+            // Adding more only overshoots further
+            true
+        } else {
+            false
+        };
+
+        if backtrack || index == values.len() {
+            // Drop the last included UTXO and try the branch without it
+            match picked.pop() {
+                Some(last) => {
+                    sum -= values[last];
+                    index = last + 1;
+                }
+                None => break,
+            }
+            if best.as_ref().is_some_and(|(excess, _)| *excess == 0) {
+                break;
+            }
+        } else {
+            picked.push(index);
+            sum += values[index];
+            index += 1;
+        }
+    }
+
+    let (_, picked) = best?;
+    let inputs: Vec<SpendableUtxo> = picked.iter().map(|&i| candidates[i].clone()).collect();
+    let total: u64 = inputs.iter().map(|u| u.value).sum();
+    let vsize = estimate_vsize(&inputs, destination, false);
+    if total < amount + vsize * fee_rate {
+        return None;
+    }
+    Some(TxPlan { inputs, amount, change: None, fee: total - amount, vsize, strategy: CoinSelection::MinimizeFee })
+}
+
+fn build_max(utxos: &[SpendableUtxo], destination: &str, fee_rate: u64, strategy: CoinSelection) -> Result<TxPlan, String> {
+    let inputs: Vec<SpendableUtxo> = utxos
+        .iter()
+        .filter(|u| u.value > input_vbytes(&u.script_type) * fee_rate)
+        .cloned()
+        .collect();
+    if inputs.is_empty() {
+        return Err(format!("All UTXOs cost more to spend than they are worth at {} sat/vB", fee_rate));
+    }
+
+    let vsize = estimate_vsize(&inputs, destination, false);
+    let fee = vsize * fee_rate;
+    let total: u64 = inputs.iter().map(|u| u.value).sum();
+    let amount = total.saturating_sub(fee);
+    if amount < DUST_THRESHOLD {
+        return Err(format!("Insufficient funds: {} sats left after a {} sat fee", amount, fee));
+    }
+
+    Ok(TxPlan { inputs, amount, change: None, fee, vsize, strategy })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEST: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+    fn utxo(value: u64, script_type: &str) -> SpendableUtxo {
+        SpendableUtxo {
+            txid: format!("{:064x}", value),
+            vout: 0,
+            value,
+            script_type: script_type.to_string(),
+            address_n_list: vec![],
+            confirmations: 1,
+        }
+    }
+
+    fn utxo_at(value: u64, address_n_list: Vec<u32>, confirmations: u64) -> SpendableUtxo {
+        SpendableUtxo { address_n_list, confirmations, ..utxo(value, "p2wpkh") }
+    }
+
+    #[test]
+    fn exact_send_adds_change_from_largest_utxo() {
+        let utxos = vec![utxo(10_000, "p2wpkh"), utxo(100_000, "p2wpkh")];
+        let plan = build(&utxos, DEST, SendAmount::Exact(50_000), 2, CoinSelection::MinimizeFee).unwrap();
+
+        assert_eq!(plan.inputs.len(), 1);
+        assert_eq!(plan.inputs[0].value, 100_000);
+        assert_eq!(plan.vsize, 11 + 68 + 31 + 31);
+        assert_eq!(plan.fee, plan.vsize * 2);
+        assert_eq!(plan.change, Some(100_000 - 50_000 - plan.fee));
+    }
+
+    #[test]
+    fn dust_change_goes_to_fee() {
+        let utxos = vec![utxo(50_500, "p2wpkh")];
+        let plan = build(&utxos, DEST, SendAmount::Exact(50_000), 1, CoinSelection::MinimizeFee).unwrap();
+
+        assert_eq!(plan.change, None);
+        assert_eq!(plan.fee, 500);
+        assert_eq!(plan.total_input(), plan.amount + plan.fee);
+    }
+
+    #[test]
+    fn max_send_skips_uneconomical_utxos() {
+        let utxos = vec![utxo(100, "p2pkh"), utxo(60_000, "p2wpkh")];
+        let plan = build(&utxos, DEST, SendAmount::Max, 5, CoinSelection::MinimizeFee).unwrap();
+
+        assert_eq!(plan.inputs.len(), 1);
+        assert_eq!(plan.change, None);
+        assert_eq!(plan.amount + plan.fee, 60_000);
+    }
+
+    #[test]
+    fn insufficient_funds_is_an_error() {
+        let utxos = vec![utxo(20_000, "p2wpkh")];
+        let err = build(&utxos, DEST, SendAmount::Exact(20_000), 1, CoinSelection::MinimizeFee).unwrap_err();
+        assert!(err.starts_with("Insufficient funds"));
+    }
+
+    #[test]
+    fn strategy_decides_which_utxos_are_spent() {
+        const H: u32 = 0x8000_0000;
+        let receive = |index| vec![84 | H, H, H, 0, index];
+        let change = |index| vec![84 | H, H, H, 1, index];
+        let utxos = vec![
+            utxo_at(30_000, receive(0), 500),
+            utxo_at(40_000, change(0), 2),
+            utxo_at(80_000, receive(1), 10),
+        ];
+        let spent = |strategy| {
+            let plan = build(&utxos, DEST, SendAmount::Exact(60_000), 1, strategy).unwrap();
+            assert_eq!(plan.strategy, strategy);
+            plan.inputs.iter().map(|u| u.value).collect::<Vec<_>>()
+        };
+
+        assert_eq!(spent(CoinSelection::MinimizeFee), vec![80_000]);
+        assert_eq!(spent(CoinSelection::MinimizeUtxoCount), vec![30_000, 40_000]);
+        assert_eq!(spent(CoinSelection::OldestFirst), vec![30_000, 80_000]);
+        assert_eq!(spent(CoinSelection::BranchIsolation), vec![80_000]);
+
+        // Neither branch can pay 115k alone, and isolation won't combine them
+        let err = build(&utxos, DEST, SendAmount::Exact(115_000), 1, CoinSelection::BranchIsolation).unwrap_err();
+        assert!(err.contains("No single account branch"));
+        assert!(build(&utxos, DEST, SendAmount::Exact(115_000), 1, CoinSelection::MinimizeFee).is_ok());
+    }
+
+    #[test]
+    fn changeless_match_beats_largest_first() {
+        let utxos = vec![utxo(60_000, "p2wpkh"), utxo(30_000, "p2wpkh"), utxo(20_200, "p2wpkh")];
+        let plan = build(&utxos, DEST, SendAmount::Exact(50_000), 1, CoinSelection::MinimizeFee).unwrap();
+
+        assert_eq!(plan.inputs.iter().map(|u| u.value).collect::<Vec<_>>(), vec![30_000, 20_200]);
+        assert_eq!(plan.change, None);
+        assert_eq!(plan.fee, 200);
+        assert!(plan.fee >= plan.vsize);
+    }
+
+    #[test]
+    fn manual_selection_spends_exactly_the_picked_inputs() {
+        let utxos = vec![utxo(100_000, "p2wpkh"), utxo(30_000, "p2wpkh"), utxo(40_000, "p2pkh")];
+        let picked = vec![utxos[1].outpoint(), utxos[2].outpoint()];
+
+        let plan = build_manual(&utxos, &picked, DEST, SendAmount::Exact(20_000), 2).unwrap();
+        assert_eq!(plan.strategy, CoinSelection::Manual);
+        assert_eq!(plan.inputs, vec![utxos[1].clone(), utxos[2].clone()]);
+        assert_eq!(plan.total_input(), plan.amount + plan.fee + plan.change.unwrap());
+
+        let max = build_manual(&utxos, &picked[..1], DEST, SendAmount::Max, 2).unwrap();
+        assert_eq!(max.amount + max.fee, 30_000);
+
+        let err = build_manual(&utxos, &picked[..1], DEST, SendAmount::Exact(50_000), 2).unwrap_err();
+        assert!(err.starts_with("Insufficient funds"));
+        let unknown = format!("{:064x}:7", 1);
+        assert!(build_manual(&utxos, &[unknown], DEST, SendAmount::Max, 2).is_err());
+        assert!(build_manual(&utxos, &[picked[0].clone(), picked[0].clone()], DEST, SendAmount::Max, 2).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+//! What code this backend is.
+//!
+//! Support asks "which version are you on?" first, and the answer is rarely
+//! exact. [`build_info`] reports the app version, the commit and target it was
+//! built from, the release channel, the keepkey-rust features compiled in and
+//! the firmware range this build handles. It is logged at startup, so it lands
+//! in every log file a user sends, and served from `GET /api/version`.
+
+use keepkey_rust::protocol;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FirmwareCompatibility {
+    /// Oldest firmware that handles every request the app makes; older
+    /// firmware works, but refuses the newer messages
+    pub full_support_from: String,
+    /// Latest firmware and bootloader in the bundled releases.json, which the
+    /// updater installs
+    pub bundled_firmware: String,
+    pub bundled_bootloader: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    pub app_version: String,
+    /// Short commit hash, or `unknown` outside a git checkout
+    pub git_hash: String,
+    /// `stable`, `beta` or `dev` (set with KEEPKEY_RELEASE_CHANNEL at build time)
+    pub channel: String,
+    pub target: String,
+    pub profile: String,
+    pub keepkey_rust_version: String,
+    pub keepkey_rust_features: Vec<String>,
+    pub firmware: FirmwareCompatibility,
+}
+
+pub fn build_info() -> BuildInfo {
+    let bundled = crate::device::firmware_check::bundled_versions();
+    BuildInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: env!("VAULT_GIT_HASH").to_string(),
+        channel: env!("VAULT_RELEASE_CHANNEL").to_string(),
+        target: env!("VAULT_BUILD_TARGET").to_string(),
+        profile: env!("VAULT_BUILD_PROFILE").to_string(),
+        keepkey_rust_version: protocol::LIBRARY_VERSION.to_string(),
+        keepkey_rust_features: protocol::enabled_features().into_iter().map(str::to_string).collect(),
+        firmware: FirmwareCompatibility {
+            full_support_from: protocol::full_support_firmware().to_string(),
+            bundled_firmware: bundled.firmware,
+            bundled_bootloader: bundled.bootloader,
+        },
+    }
+}
+
+/// One line for the log header
+pub fn summary() -> String {
+    let info = build_info();
+    format!(
+        "KeepKey Vault {} ({}, {} channel, {} {}) keepkey-rust {} [{}]; firmware {}+ fully supported, bundled {}",
+        info.app_version,
+        info.git_hash,
+        info.channel,
+        info.target,
+        info.profile,
+        info.keepkey_rust_version,
+        info.keepkey_rust_features.join(","),
+        info.firmware.full_support_from,
+        info.firmware.bundled_firmware,
+    )
+}
+
+#[tauri::command]
+pub fn get_build_info() -> BuildInfo {
+    build_info()
+}
@@ -2,7 +2,9 @@ use tauri::{AppHandle, Emitter, State};
 use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
 use keepkey_rust::{
+    derivation_path::{hardened, DerivationPath},
     device_queue::{DeviceQueueFactory, DeviceQueueHandle},
+    failure::{DeviceFailure, FailureCode},
     features::DeviceFeatures,
 };
 use uuid;
@@ -11,6 +13,7 @@ use std::io::Cursor;
 // Removed unused imports that were moved to device/updates.rs
 use crate::logging::{log_device_request, log_device_response, log_raw_device_message};
 use crate::device;
+use crate::event_delivery::{DeliveryLog, LoggedEvent, DELIVERY_EVENT};
 use lazy_static;
 use std::path::PathBuf;
 use std::fs;
@@ -22,6 +25,30 @@ use once_cell::sync::Lazy;
 
 pub type DeviceQueueManager = Arc<tokio::sync::Mutex<std::collections::HashMap<String, DeviceQueueHandle>>>;
 
+/// Error text for a Failure answer, led by its stable code in brackets, e.g.
+/// `[pin_invalid] Recovery PIN failed: Invalid PIN`. The frontend branches on
+/// and localizes the code; the rest stays readable in logs.
+fn device_failure_error(context: &str, failure: &keepkey_rust::messages::Failure) -> String {
+    let failure = DeviceFailure::from(failure);
+    format!("[{}] {}: {}", failure.code, context, failure.message)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureDescription {
+    pub code: FailureCode,
+    pub description: &'static str,
+}
+
+/// Description of every device failure code in `language` (English when
+/// there is no table for it)
+#[tauri::command]
+pub fn get_failure_descriptions(language: Option<String>) -> Vec<FailureDescription> {
+    keepkey_rust::failure::descriptions(language.as_deref().unwrap_or("en"))
+        .iter()
+        .map(|(code, description)| FailureDescription { code: *code, description })
+        .collect()
+}
+
 // Change the response storage to use request_id as key instead of device_id
 #[allow(dead_code)]
 type LastResponsesMap = Arc<tokio::sync::Mutex<std::collections::HashMap<String, DeviceResponse>>>;
@@ -31,26 +58,11 @@ lazy_static::lazy_static! {
     static ref FRONTEND_READY_STATE: Arc<tokio::sync::RwLock<FrontendReadyState>> = Arc::new(tokio::sync::RwLock::new(FrontendReadyState::default()));
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Default)]
 struct FrontendReadyState {
     is_ready: bool,
-    queued_events: Vec<QueuedEvent>,
-}
-
-impl Default for FrontendReadyState {
-    fn default() -> Self {
-        Self {
-            is_ready: false,
-            queued_events: Vec::new(),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct QueuedEvent {
-    event_name: String,
-    payload: serde_json::Value,
-    timestamp: u64,
+    /// Sequenced events awaiting an ack from the frontend
+    delivery: DeliveryLog,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +74,8 @@ pub struct BitcoinUtxoInput {
     pub txid: String,                 // Transaction ID
     #[serde(alias = "hex")]           // Accept both "prev_tx_hex" and "hex" field names
     pub prev_tx_hex: Option<String>,  // Raw previous transaction hex
+    #[serde(default)]
+    pub sequence: Option<u32>,        // nSequence; final (0xffffffff) when not set
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]  
@@ -72,15 +86,17 @@ pub struct BitcoinUtxoOutput {
     pub is_change: Option<bool>,      // Optional change flag
     pub address_n_list: Option<Vec<u32>>, // Derivation path for change outputs
     pub script_type: Option<String>,  // Script type for change outputs
+    #[serde(default)]
+    pub op_return_data: Option<String>, // Hex payload; makes this an OP_RETURN output (address ignored)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DeviceRequest {
     GetXpub {
-        path: String,
+        path: DerivationPath,
     },
     GetAddress {
-        path: String,
+        path: DerivationPath,
         coin_name: String,
         script_type: Option<String>,
         show_display: Option<bool>,
@@ -111,7 +127,7 @@ pub enum DeviceResponse {
     Xpub {
         request_id: String,
         device_id: String,
-        path: String,
+        path: DerivationPath,
         xpub: String,
         script_type: Option<String>,
         success: bool,
@@ -120,7 +136,7 @@ pub enum DeviceResponse {
     Address {
         request_id: String,
         device_id: String,
-        path: String,
+        path: DerivationPath,
         address: String,
         success: bool,
         error: Option<String>,
@@ -171,6 +187,8 @@ pub struct DeviceStatus {
     pub bootloader_check: Option<BootloaderCheck>,
     pub firmware_check: Option<FirmwareCheck>,
     pub initialization_check: Option<InitializationCheck>,
+    /// Vendor/bootloader/firmware hash check against official releases
+    pub authenticity_check: Option<device::authenticity::AuthenticityCheck>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -288,33 +306,62 @@ pub async fn get_connected_devices() -> Result<Vec<serde_json::Value>, String> {
 /// Get blocking actions (enhanced version)
 #[tauri::command]
 pub async fn get_blocking_actions() -> Result<Vec<serde_json::Value>, String> {
-    // For now, return empty array since vault v2 uses DeviceUpdateManager with its own logic
-    // TODO: Implement proper blocking actions registry like vault v1
-    Ok(vec![])
-}
-
-/// Helper function to parse derivation path string to Vec<u32>
-pub fn parse_derivation_path(path: &str) -> Result<Vec<u32>, String> {
-    let path = path.trim_start_matches("m/");
-    let parts: Result<Vec<u32>, String> = path
-        .split('/')
-        .map(|part| {
-            // Check if this specific part is hardened (ends with ')
-            let is_hardened = part.ends_with('\'');
-            let part = part.trim_end_matches('\'');
-            let mut value = part.parse::<u32>()
-                .map_err(|_| format!("Invalid path component: {}", part))?;
-            
-            // Apply hardened derivation if this part ends with '
-            if is_hardened {
-                value |= 0x80000000;
-            }
-            
-            Ok(value)
-        })
-        .collect();
-    
-    parts.map_err(|e: String| format!("Failed to parse derivation path '{}': {}", path, e))
+    // Updates are driven by DeviceUpdateManager; the only blocking action here is
+    // an unacknowledged authenticity warning
+    let mut actions = Vec::new();
+    for (device_id, features) in device::firmware_check::known_devices().await {
+        let check = device::authenticity::check_device_authenticity(&features);
+        if !check.needs_acknowledgment() || device::authenticity::is_acknowledged(&device_id, &check).await {
+            continue;
+        }
+        actions.push(serde_json::json!({
+            "device_id": device_id,
+            "action_type": "device_authenticity_warning",
+            "message": format!("This device could not be verified as a genuine KeepKey: {}", check.warnings.join("; ")),
+            "priority": 120,
+            "current_version": features.version,
+            "required_version": null,
+            "requires_acknowledgment": true,
+            "authenticity": check,
+        }));
+    }
+    Ok(actions)
+}
+
+/// Acknowledge a device authenticity warning so it stops blocking. The
+/// acknowledgment only covers the hashes seen now.
+#[tauri::command]
+pub async fn acknowledge_device_authenticity(device_id: String) -> Result<(), String> {
+    let features = device::firmware_check::known_devices()
+        .await
+        .into_iter()
+        .find(|(id, _)| *id == device_id)
+        .map(|(_, features)| features)
+        .ok_or_else(|| format!("Device {} not found", device_id))?;
+    let check = device::authenticity::check_device_authenticity(&features);
+    println!("⚠️ User acknowledged authenticity warning for device {} ({:?})", device_id, check.verdict);
+    device::authenticity::acknowledge(&device_id, &check).await
+}
+
+/// Forget a device: drop its queue, the cached features used for update and
+/// authenticity checks, any acknowledged warnings and its passphrase profiles. The frontend clears its
+/// own wallet storage when it receives `device:forgotten`.
+#[tauri::command]
+pub async fn forget_device(
+    device_id: String,
+    app: AppHandle,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<(), String> {
+    println!("🗑️ Forgetting device: {}", device_id);
+
+    if let Some(handle) = queue_manager.lock().await.remove(&device_id) {
+        let _ = handle.shutdown().await;
+    }
+    device::firmware_check::forget_device(&device_id).await;
+    device::authenticity::forget(&device_id)?;
+    crate::passphrase_profiles::forget_device(&device_id)?;
+
+    emit_or_queue_event(&app, "device:forgotten", serde_json::json!({ "deviceId": device_id })).await
 }
 
 /// Test command to demonstrate the unified device queue interface
@@ -792,7 +839,7 @@ pub async fn wipe_device(
                     Ok(())
                 }
                 keepkey_rust::messages::Message::Failure(failure) => {
-                    let error = format!("Device rejected wipe request: {}", failure.message.unwrap_or_default());
+                    let error = device_failure_error("Device rejected wipe request", &failure);
                     println!("❌ Failed to wipe device {}: {}", device_id, error);
                     
                     // Log the error response
@@ -991,7 +1038,7 @@ pub async fn set_device_label(
                     Ok(())
                 }
                 keepkey_rust::messages::Message::Failure(failure) => {
-                    let error = format!("Device rejected label change: {}", failure.message.unwrap_or_default());
+                    let error = device_failure_error("Device rejected label change", &failure);
                     println!("❌ Failed to set device label for {}: {}", device_id, error);
                     
                     // Log the error response
@@ -1220,10 +1267,20 @@ pub fn evaluate_device_status(device_id: String, features: Option<&DeviceFeature
         bootloader_check: None,
         firmware_check: None,
         initialization_check: None,
+        authenticity_check: None,
     };
     
     if let Some(features) = features {
-        let latest_bootloader_version = "2.1.4".to_string(); // Fixed: 2.1.4 is the actual latest official bootloader
+        let authenticity = device::authenticity::check_device_authenticity(features);
+        if authenticity.needs_acknowledgment() {
+            println!("⚠️ Device {} failed authenticity check ({:?}): {}",
+                    device_id, authenticity.verdict, authenticity.warnings.join("; "));
+        }
+        status.authenticity_check = Some(authenticity);
+        
+        // Latest versions come from releases.json, refreshed by the background firmware-check job
+        let latest = device::firmware_check::latest_versions();
+        let latest_bootloader_version = latest.bootloader.clone();
         
         // CRITICAL FIX: Check bootloader version regardless of current mode
         // For OOB devices, we can infer bootloader version from firmware version
@@ -1246,7 +1303,7 @@ pub fn evaluate_device_status(device_id: String, features: Option<&DeviceFeature
                 bl_version.clone()
             } else {
                 // For modern firmware without explicit bootloader version, assume it's recent enough
-                "2.1.4".to_string() // Assume recent bootloader if not specified
+                latest_bootloader_version.clone()
             }
         };
         
@@ -1307,12 +1364,15 @@ pub fn evaluate_device_status(device_id: String, features: Option<&DeviceFeature
                 // Firmware 4.0.0 is an OOB firmware that needs bootloader update first
                 true // Both bootloader and firmware need updates
             } else {
-                !current_fw_version.starts_with("7.10.")
+                match (semver::Version::parse(&current_fw_version), semver::Version::parse(&latest.firmware)) {
+                    (Ok(current_ver), Ok(latest_ver)) => current_ver < latest_ver,
+                    _ => current_fw_version != latest.firmware,
+                }
             };
             (current_fw_version, needs_update)
         };
         
-        let latest_version = "7.10.0".to_string(); // Latest firmware version
+        let latest_version = latest.firmware.clone();
         
         status.firmware_check = Some(FirmwareCheck {
             current_version: current_firmware_version.clone(),
@@ -1477,6 +1537,28 @@ pub async fn cleanup_device_logs() -> Result<String, String> {
     Ok("Old device logs cleaned up successfully".to_string())
 }
 
+/// Messages recently exchanged with devices, oldest first: those after `since`
+/// (a `seq`), at most `limit` (default 200). Empty unless tracing is on.
+#[tauri::command]
+pub async fn get_protocol_trace(
+    since: Option<u64>,
+    limit: Option<usize>,
+) -> Result<Vec<keepkey_rust::protocol_trace::TraceEntry>, String> {
+    Ok(keepkey_rust::protocol_trace::entries(since, limit.unwrap_or(200)))
+}
+
+/// Record every message exchanged with devices for bug reports, remembered
+/// across restarts
+#[tauri::command]
+pub async fn set_protocol_trace_enabled(enabled: bool) -> Result<(), String> {
+    set_preference("protocolTrace".to_string(), enabled.to_string()).await?;
+    keepkey_rust::protocol_trace::set_tracing_enabled(enabled);
+    if !enabled {
+        keepkey_rust::protocol_trace::clear();
+    }
+    Ok(())
+}
+
 /// Parse transaction from hex string
 /// Returns (metadata, inputs, outputs) where metadata is (version, input_count, output_count, lock_time)
 pub fn parse_transaction_from_hex(hex_data: &str) -> Result<((u32, u32, u32, u32), Vec<keepkey_rust::messages::TxInputType>, Vec<keepkey_rust::messages::TxOutputBinType>), String> {
@@ -1648,7 +1730,9 @@ pub async fn test_status_emission(app: tauri::AppHandle) -> Result<String, Strin
     }
 }
 
-/// Signal that the frontend is ready to receive events
+/// Signal that the frontend is ready to receive events. Replays every event
+/// it hasn't acked yet, which covers both events raised before it loaded and
+/// ones a reloaded webview lost.
 #[tauri::command]
 pub async fn frontend_ready(app: AppHandle) -> Result<(), String> {
     println!("🎯 Frontend ready signal received - enabling event emission");
@@ -1656,18 +1740,17 @@ pub async fn frontend_ready(app: AppHandle) -> Result<(), String> {
     let mut state = FRONTEND_READY_STATE.write().await;
     state.is_ready = true;
     
-    // Flush any queued events
-    if !state.queued_events.is_empty() {
-        println!("📦 Flushing {} queued events to frontend", state.queued_events.len());
+    if !state.delivery.is_empty() {
+        println!("📦 Replaying {} unacked event(s) to frontend", state.delivery.len());
         
-        for event in state.queued_events.drain(..) {
-            println!("📡 Sending queued event: {} (queued at: {})", event.event_name, event.timestamp);
-            if let Err(e) = app.emit(&event.event_name, &event.payload) {
+        for event in state.delivery.pending() {
+            println!("📡 Sending event #{}: {} (first sent at: {})", event.seq, event.event_name, event.timestamp);
+            if let Err(e) = deliver_event(&app, event) {
                 println!("❌ Failed to emit queued event {}: {}", event.event_name, e);
             }
         }
         
-        println!("✅ All queued events have been sent to frontend");
+        println!("✅ All unacked events have been sent to frontend");
     } else {
         println!("✅ No queued events to flush");
     }
@@ -1675,31 +1758,39 @@ pub async fn frontend_ready(app: AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-/// Helper function to emit events (either immediately or queue them)
+/// Mark the webview as gone (page reload or crash); events are held until it
+/// signals `frontend_ready` again
+pub async fn frontend_unloaded() {
+    FRONTEND_READY_STATE.write().await.is_ready = false;
+}
+
+/// The frontend handled these events; stop replaying them
+#[tauri::command]
+pub async fn ack_events(seqs: Vec<u64>) -> Result<(), String> {
+    let mut state = FRONTEND_READY_STATE.write().await;
+    let acked = state.delivery.ack(&seqs);
+    log::debug!("Frontend acked {} event(s), {} still pending", acked, state.delivery.len());
+    Ok(())
+}
+
+fn deliver_event(app: &AppHandle, event: &LoggedEvent) -> tauri::Result<()> {
+    app.emit(&event.event_name, &event.payload)?;
+    app.emit(DELIVERY_EVENT, serde_json::json!({ "seq": event.seq, "event": event.event_name }))
+}
+
+/// Helper function to emit events with guaranteed delivery: the event is sent
+/// now if the frontend is listening and replayed by `frontend_ready` until acked
 pub async fn emit_or_queue_event(app: &AppHandle, event_name: &str, payload: serde_json::Value) -> Result<(), String> {
-    let state = FRONTEND_READY_STATE.read().await;
+    let mut state = FRONTEND_READY_STATE.write().await;
+    let event = state.delivery.record(event_name, payload);
     
     if state.is_ready {
         // Frontend is ready, emit immediately
-        app.emit(event_name, &payload)
+        deliver_event(app, &event)
             .map_err(|e| format!("Failed to emit event {}: {}", event_name, e))?;
-        println!("📡 Emitted event: {}", event_name);
+        println!("📡 Emitted event #{}: {}", event.seq, event_name);
     } else {
-        // Frontend not ready, queue the event
-        drop(state); // Release read lock
-        let mut state = FRONTEND_READY_STATE.write().await;
-        
-        let queued_event = QueuedEvent {
-            event_name: event_name.to_string(),
-            payload,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64,
-        };
-        
-        state.queued_events.push(queued_event);
-        println!("📋 Queued event: {} (total queued: {})", event_name, state.queued_events.len());
+        println!("📋 Queued event #{}: {} (total unacked: {})", event.seq, event_name, state.delivery.len());
     }
     
     Ok(())
@@ -1729,7 +1820,7 @@ fn get_config_file_path() -> Result<PathBuf, String> {
 }
 
 /// Load configuration from file
-fn load_config() -> Result<serde_json::Value, String> {
+pub(crate) fn load_config() -> Result<serde_json::Value, String> {
     let config_path = get_config_file_path()?;
     
     if !config_path.exists() {
@@ -1750,7 +1841,7 @@ fn load_config() -> Result<serde_json::Value, String> {
 }
 
 /// Save configuration to file
-fn save_config(config: &serde_json::Value) -> Result<(), String> {
+pub(crate) fn save_config(config: &serde_json::Value) -> Result<(), String> {
     let config_path = get_config_file_path()?;
     
     let config_str = serde_json::to_string_pretty(config)
@@ -1836,6 +1927,17 @@ pub async fn set_preference(key: String, value: String) -> Result<(), String> {
     Ok(())
 }
 
+/// Remove a preference; missing keys are not an error
+pub(crate) fn remove_preference(key: &str) -> Result<(), String> {
+    let mut config = load_config()?;
+    if let Some(obj) = config.as_object_mut() {
+        if obj.remove(key).is_none() {
+            return Ok(());
+        }
+    }
+    save_config(&config)
+}
+
 /// Debug onboarding state
 #[tauri::command]
 pub async fn debug_onboarding_state() -> Result<String, String> {
@@ -1851,7 +1953,11 @@ pub async fn restart_app(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-/// Get API enable status
+/// Shown on the device when the local API is turned on
+const API_ENABLE_PROMPT: &str = "Enable local API?";
+
+/// Get API enable status. The API only counts as enabled when the device
+/// confirmed it, so setting `api_enabled` in the config file alone does nothing.
 #[tauri::command]
 pub async fn get_api_enabled() -> Result<bool, String> {
     log::debug!("Getting API enabled status");
@@ -1859,18 +1965,38 @@ pub async fn get_api_enabled() -> Result<bool, String> {
     let enabled = config.get("api_enabled")
         .and_then(|v| v.as_bool())
         .unwrap_or(false); // Default to false (disabled) if not set
+    let confirmed = config.get("api_enabled_confirmation").map_or(false, |v| v.is_object());
+    if enabled && !confirmed {
+        log::warn!("🔒 api_enabled is set but was never confirmed on a device; keeping the API off");
+        return Ok(false);
+    }
     log::debug!("API enabled status: {}", enabled);
     Ok(enabled)
 }
 
-/// Set API enable status
+/// Set API enable status. Enabling needs a button press on the device
+/// (`device_id`, or the first connected KeepKey), and is recorded in the audit log.
 #[tauri::command]
-pub async fn set_api_enabled(enabled: bool) -> Result<(), String> {
+pub async fn set_api_enabled(
+    enabled: bool,
+    device_id: Option<String>,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<(), String> {
     log::info!("Setting API enabled status: {}", enabled);
+    let confirmation = if enabled {
+        Some(confirm_api_enable(device_id, &queue_manager).await?)
+    } else {
+        None
+    };
+
     let mut config = load_config()?;
     
     if let Some(obj) = config.as_object_mut() {
         obj.insert("api_enabled".to_string(), serde_json::Value::Bool(enabled));
+        match confirmation {
+            Some(confirmation) => obj.insert("api_enabled_confirmation".to_string(), confirmation),
+            None => obj.remove("api_enabled_confirmation"),
+        };
     }
     
     save_config(&config)?;
@@ -1878,6 +2004,55 @@ pub async fn set_api_enabled(enabled: bool) -> Result<(), String> {
     Ok(())
 }
 
+/// Ask the device to confirm turning the API on; returns what to store as proof
+async fn confirm_api_enable(
+    device_id: Option<String>,
+    queue_manager: &DeviceQueueManager,
+) -> Result<serde_json::Value, String> {
+    let device_id = match device_id {
+        Some(device_id) => device_id,
+        None => keepkey_rust::features::list_connected_devices()
+            .first()
+            .map(|d| d.unique_id.clone())
+            .ok_or_else(|| "Connect your KeepKey to confirm enabling the local API".to_string())?,
+    };
+    let handle = {
+        let mut manager = queue_manager.lock().await;
+        crate::bitcoin::accounts::queue_handle(&device_id, &mut manager)?
+    };
+
+    let ping = keepkey_rust::messages::Ping {
+        message: Some(API_ENABLE_PROMPT.to_string()),
+        button_protection: Some(true),
+        ..Default::default()
+    };
+    let result = match handle.send_raw(keepkey_rust::messages::Message::Ping(ping), true).await {
+        Ok(keepkey_rust::messages::Message::Success(_)) => Ok(()),
+        Ok(keepkey_rust::messages::Message::Failure(failure)) => {
+            Err(device_failure_error("Enabling the local API was not confirmed", &failure))
+        }
+        Ok(other) => Err(format!("Unexpected response to Ping: {:?}", other.message_type())),
+        Err(e) => Err(format!("Failed to ask the device to confirm: {}", e)),
+    };
+
+    let confirmed_at = chrono::Utc::now().to_rfc3339();
+    let audit = serde_json::json!({
+        "prompt": API_ENABLE_PROMPT,
+        "confirmed": result.is_ok(),
+        "error": result.as_ref().err(),
+    });
+    if let Err(e) = crate::logging::log_audit_event(&device_id, "api_enable_confirmation", &audit).await {
+        log::warn!("Failed to write audit entry for enabling the API: {}", e);
+    }
+    result?;
+
+    log::info!("🔓 Enabling the local API confirmed on device {}", device_id);
+    Ok(serde_json::json!({
+        "device_id": device_id,
+        "confirmed_at": confirmed_at,
+    }))
+}
+
 /// Get API status (running or not)
 #[tauri::command]
 pub async fn get_api_status() -> Result<serde_json::Value, String> {
@@ -2969,7 +3144,7 @@ pub async fn start_device_recovery(
                         sessions.remove(&session_id);
                     }
                     let _ = unmark_device_in_recovery_flow(&device_id);
-                    Err(format!("Device rejected recovery: {}", f.message.unwrap_or_default()))
+                    Err(device_failure_error("Device rejected recovery", &f))
                 }
                 _ => {
                     log::warn!("Unexpected response to RecoveryDevice: {:?}", response);
@@ -3118,7 +3293,7 @@ pub async fn send_recovery_character(
                     // Remove from recovery flow
                     let _ = unmark_device_in_recovery_flow(&device_id);
                     
-                    Err(format!("Recovery failed: {}", f.message.unwrap_or_default()))
+                    Err(device_failure_error("Recovery failed", &f))
                 }
                 _ => {
                     Err(format!("Unexpected response: {:?}", response))
@@ -3255,7 +3430,7 @@ pub async fn send_recovery_pin_response(
                     })
                 }
                 keepkey_rust::messages::Message::Failure(f) => {
-                    Err(format!("Recovery PIN failed: {}", f.message.unwrap_or_default()))
+                    Err(device_failure_error("Recovery PIN failed", &f))
                 }
                 _ => {
                     Err(format!("Unexpected response to recovery PIN: {:?}", response))
@@ -3469,7 +3644,7 @@ pub async fn start_seed_verification(
                         sessions.remove(&session_id);
                     }
                     let _ = unmark_device_in_recovery_flow(&device_id);
-                    Err(format!("Device rejected seed verification: {}", f.message.unwrap_or_default()))
+                    Err(device_failure_error("Device rejected seed verification", &f))
                 }
                 _ => {
                     log::warn!("Unexpected response to dry run RecoveryDevice: {:?}", response);
@@ -3650,16 +3825,14 @@ pub fn are_devices_potentially_same(id1: &str, id2: &str) -> bool {
         return true;
     }
     
-    // Check if they're both KeepKey devices (same VID/PID)
-    let keepkey_pattern = "keepkey_2b24_0002_";
-    if id1.contains(keepkey_pattern) && id2.contains(keepkey_pattern) {
-        log::info!("Both {} and {} appear to be KeepKey devices", id1, id2);
-        return true;
-    }
+    // Two serial-less KeepKeys are never the same device: they hold separate
+    // identity slots while both are attached
     
-    // Check if one has a serial and the other is a bus/address format
+    // Check if one has a serial and the other is a serial-less id
     let is_serial_format = |id: &str| id.len() == 24 && id.chars().all(|c| c.is_alphanumeric());
-    let is_bus_addr_format = |id: &str| id.contains("bus") && id.contains("addr");
+    let is_bus_addr_format = |id: &str| {
+        keepkey_rust::device_identity::is_slot_id(id) || (id.contains("bus") && id.contains("addr"))
+    };
     
     if (is_serial_format(id1) && is_bus_addr_format(id2)) ||
        (is_bus_addr_format(id1) && is_serial_format(id2)) {
@@ -3712,6 +3885,11 @@ pub async fn send_pin_matrix_ack(
             log::info!("✅ PIN accepted! Device unlocked successfully");
             // Unmark device from PIN flow as PIN has been accepted
             let _ = unmark_device_in_pin_flow(&device_id);
+            // A serial-less device can be told apart by its wallet now
+            let handle = queue_handle.clone();
+            tokio::spawn(async move {
+                crate::device::identity::bind_unlocked_wallet(&device_id, &handle).await;
+            });
             Ok(true)
         }
         Ok(keepkey_rust::messages::Message::Failure(f)) => {
@@ -3719,12 +3897,11 @@ pub async fn send_pin_matrix_ack(
             // Unmark device from PIN flow on failure
             let _ = unmark_device_in_pin_flow(&device_id);
             
-            // Determine if it's an incorrect PIN or other error
-            let error_msg = f.message.unwrap_or_else(|| "PIN verification failed".to_string());
-            if error_msg.contains("PIN") || error_msg.contains("Invalid") {
-                Err("Incorrect PIN. Please try again.".to_string())
-            } else {
-                Err(format!("PIN verification failed: {}", error_msg))
+            match FailureCode::from_failure(&f) {
+                code @ (FailureCode::PinInvalid | FailureCode::PinExpected) => {
+                    Err(format!("[{}] Incorrect PIN. Please try again.", code))
+                }
+                _ => Err(device_failure_error("PIN verification failed", &f)),
             }
         }
         Ok(other_msg) => {
@@ -3776,7 +3953,7 @@ pub async fn trigger_pin_request(
         
     // Create a simple GetAddress request that will trigger PIN on locked device
     let get_address = keepkey_rust::messages::GetAddress {
-        address_n: vec![44, 0, 0, 0, 0], // m/44'/0'/0'/0/0
+        address_n: vec![hardened(44), hardened(0), hardened(0), 0, 0], // m/44'/0'/0'/0/0
         coin_name: Some("Bitcoin".to_string()),
         script_type: Some(0), // SPENDADDRESS
         show_display: Some(false),
@@ -3995,8 +4172,8 @@ pub async fn clear_all_device_caches() {
     
     // Clear frontend ready state and queued events
     let mut state = FRONTEND_READY_STATE.write().await;
-    println!("  📋 Clearing {} queued event(s)", state.queued_events.len());
-    state.queued_events.clear();
+    println!("  📋 Clearing {} unacked event(s)", state.delivery.len());
+    state.delivery.clear();
     // Don't reset is_ready as frontend is still connected
     drop(state); // Explicitly drop to release the lock
     
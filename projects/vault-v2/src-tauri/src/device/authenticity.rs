@@ -0,0 +1,244 @@
+use keepkey_rust::features::DeviceFeatures;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Vendor string every genuine KeepKey reports in Features
+const KEEPKEY_VENDOR: &str = "keepkey.com";
+
+/// Preference prefix for acknowledged warnings; the value is the fingerprint
+/// that was acknowledged, so a device whose hashes change is flagged again
+const ACKNOWLEDGED_PREFERENCE_PREFIX: &str = "authenticityAcknowledged:";
+
+#[derive(Debug, Default, Deserialize)]
+struct ManifestHashes {
+    #[serde(default)]
+    bootloader: HashMap<String, String>,
+    #[serde(default)]
+    firmware: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HashesManifest {
+    #[serde(default)]
+    hashes: ManifestHashes,
+}
+
+/// Known-good bootloader and firmware hashes (hex) mapped to their release version
+#[derive(Debug, Default, Clone)]
+struct KnownHashes {
+    bootloader: HashMap<String, String>,
+    firmware: HashMap<String, String>,
+}
+
+impl KnownHashes {
+    fn from_manifest_str(json: &str) -> Result<Self, String> {
+        let manifest: HashesManifest = serde_json::from_str(json)
+            .map_err(|e| format!("Failed to parse releases.json hashes: {}", e))?;
+        let normalize = |map: HashMap<String, String>| {
+            map.into_iter()
+                .map(|(hash, version)| (hash.to_lowercase(), version.trim_start_matches('v').to_string()))
+                .collect()
+        };
+        Ok(Self {
+            bootloader: normalize(manifest.hashes.bootloader),
+            firmware: normalize(manifest.hashes.firmware),
+        })
+    }
+}
+
+static KNOWN_HASHES: Lazy<std::sync::RwLock<KnownHashes>> = Lazy::new(|| {
+    let hashes = KnownHashes::from_manifest_str(super::firmware_check::BUNDLED_RELEASES).unwrap_or_else(|e| {
+        eprintln!("⚠️ {} - device authenticity checks disabled until the next manifest refresh", e);
+        KnownHashes::default()
+    });
+    std::sync::RwLock::new(hashes)
+});
+
+/// Merge hashes from a freshly fetched releases.json. Hashes are only ever
+/// added, so a manifest that drops an old release doesn't flag devices running it.
+pub fn update_known_hashes(manifest_json: &str) {
+    match KnownHashes::from_manifest_str(manifest_json) {
+        Ok(fresh) => {
+            let mut known = KNOWN_HASHES.write().unwrap_or_else(|p| p.into_inner());
+            known.bootloader.extend(fresh.bootloader);
+            known.firmware.extend(fresh.firmware);
+        }
+        Err(e) => log::warn!("{}", e),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthenticityVerdict {
+    /// Vendor and hashes match an official release
+    Genuine,
+    /// Nothing contradicts an official release, but something couldn't be
+    /// verified (e.g. firmware newer than the manifest, or custom firmware)
+    Unverified,
+    /// Looks like a clone or a tampered unit
+    Suspicious,
+}
+
+/// Result of checking a device's Features against known-good releases
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticityCheck {
+    pub verdict: AuthenticityVerdict,
+    pub vendor: Option<String>,
+    /// Release the bootloader hash belongs to, if known
+    pub bootloader_release: Option<String>,
+    /// Release the firmware hash belongs to, if known
+    pub firmware_release: Option<String>,
+    pub warnings: Vec<String>,
+    /// Identifies what was checked; acknowledging a warning applies to this fingerprint only
+    pub fingerprint: String,
+}
+
+impl AuthenticityCheck {
+    pub fn needs_acknowledgment(&self) -> bool {
+        self.verdict != AuthenticityVerdict::Genuine
+    }
+}
+
+/// Check the vendor string, bootloader hash and firmware hash from Features
+pub fn check_device_authenticity(features: &DeviceFeatures) -> AuthenticityCheck {
+    let known = KNOWN_HASHES.read().unwrap_or_else(|p| p.into_inner()).clone();
+    check_against(features, &known)
+}
+
+fn check_against(features: &DeviceFeatures, known: &KnownHashes) -> AuthenticityCheck {
+    let mut suspicious = Vec::new();
+    let mut unverified = Vec::new();
+
+    match features.vendor.as_deref() {
+        Some(vendor) if vendor.eq_ignore_ascii_case(KEEPKEY_VENDOR) => {}
+        Some(vendor) => suspicious.push(format!("Unexpected vendor \"{}\" (genuine devices report {})", vendor, KEEPKEY_VENDOR)),
+        None => suspicious.push("Device did not report a vendor".to_string()),
+    }
+
+    let bootloader_hash = features.bootloader_hash.as_deref().map(str::to_lowercase);
+    let bootloader_release = bootloader_hash.as_ref().and_then(|h| known.bootloader.get(h).cloned());
+    match (&bootloader_hash, &bootloader_release) {
+        (Some(_), Some(_)) => {}
+        (Some(hash), None) => suspicious.push(format!("Bootloader hash {} does not match any official release", hash)),
+        // Very old bootloaders don't report a hash while in bootloader mode
+        (None, _) if features.bootloader_mode => {}
+        (None, _) => suspicious.push("Device did not report a bootloader hash".to_string()),
+    }
+
+    let firmware_hash = features.firmware_hash.as_deref().map(str::to_lowercase);
+    let firmware_release = firmware_hash.as_ref().and_then(|h| known.firmware.get(h).cloned());
+    if !features.bootloader_mode {
+        match (&firmware_hash, &firmware_release) {
+            (Some(_), Some(release)) if *release != features.version => suspicious.push(format!(
+                "Firmware reports v{} but its hash belongs to v{}",
+                features.version, release
+            )),
+            (Some(_), Some(_)) => {}
+            (Some(hash), None) => unverified.push(format!(
+                "Firmware hash {} is not an official release (custom or newer firmware)",
+                hash
+            )),
+            (None, _) => unverified.push("Device did not report a firmware hash".to_string()),
+        }
+    }
+
+    let verdict = if !suspicious.is_empty() {
+        AuthenticityVerdict::Suspicious
+    } else if !unverified.is_empty() {
+        AuthenticityVerdict::Unverified
+    } else {
+        AuthenticityVerdict::Genuine
+    };
+
+    let fingerprint = format!(
+        "{}|{}|{}",
+        features.vendor.as_deref().unwrap_or_default(),
+        bootloader_hash.as_deref().unwrap_or_default(),
+        firmware_hash.as_deref().unwrap_or_default()
+    );
+
+    suspicious.extend(unverified);
+    AuthenticityCheck {
+        verdict,
+        vendor: features.vendor.clone(),
+        bootloader_release,
+        firmware_release,
+        warnings: suspicious,
+        fingerprint,
+    }
+}
+
+/// Whether the user already acknowledged this exact warning for the device
+pub async fn is_acknowledged(device_id: &str, check: &AuthenticityCheck) -> bool {
+    crate::commands::get_preference(format!("{}{}", ACKNOWLEDGED_PREFERENCE_PREFIX, device_id))
+        .await
+        .ok()
+        .flatten()
+        .as_deref()
+        == Some(check.fingerprint.as_str())
+}
+
+pub async fn acknowledge(device_id: &str, check: &AuthenticityCheck) -> Result<(), String> {
+    crate::commands::set_preference(
+        format!("{}{}", ACKNOWLEDGED_PREFERENCE_PREFIX, device_id),
+        check.fingerprint.clone(),
+    )
+    .await
+}
+
+/// Drop any acknowledgment stored for a forgotten device
+pub fn forget(device_id: &str) -> Result<(), String> {
+    crate::commands::remove_preference(&format!("{}{}", ACKNOWLEDGED_PREFERENCE_PREFIX, device_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(vendor: &str, bootloader_hash: &str, firmware_hash: &str, version: &str) -> DeviceFeatures {
+        DeviceFeatures {
+            label: None,
+            vendor: Some(vendor.to_string()),
+            model: None,
+            firmware_variant: None,
+            device_id: None,
+            language: None,
+            bootloader_mode: false,
+            version: version.to_string(),
+            firmware_hash: Some(firmware_hash.to_string()),
+            bootloader_hash: Some(bootloader_hash.to_string()),
+            bootloader_version: None,
+            initialized: true,
+            imported: None,
+            no_backup: false,
+            pin_protection: false,
+            pin_cached: false,
+            passphrase_protection: false,
+            passphrase_cached: false,
+            wipe_code_protection: false,
+            auto_lock_delay_ms: None,
+            policies: vec![],
+        }
+    }
+
+    #[test]
+    fn flags_clones_against_bundled_manifest() {
+        let known = KnownHashes::from_manifest_str(crate::device::firmware_check::BUNDLED_RELEASES).unwrap();
+        let bootloader = "fe98454e7ebd4aef4a6db5bd4c60f52cf3f58b974283a7c1e1fcc5fea02cf3eb";
+        let firmware = "cac0256bd334fee270547c99ca77af1934863a95151b8dcac726c84da585b22f";
+
+        let genuine = check_against(&features("keepkey.com", bootloader, firmware, "7.9.2"), &known);
+        assert_eq!(genuine.verdict, AuthenticityVerdict::Genuine);
+        assert_eq!(genuine.bootloader_release.as_deref(), Some("2.1.4"));
+
+        let relabeled = check_against(&features("keepkey.com", bootloader, firmware, "7.10.0"), &known);
+        assert_eq!(relabeled.verdict, AuthenticityVerdict::Suspicious);
+
+        let clone = check_against(&features("keepkey.com", &"00".repeat(32), firmware, "7.9.2"), &known);
+        assert_eq!(clone.verdict, AuthenticityVerdict::Suspicious);
+        assert!(clone.needs_acknowledgment());
+    }
+}
@@ -0,0 +1,245 @@
+use keepkey_rust::features::DeviceFeatures;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::sync::{Mutex, RwLock};
+
+// Same manifest keepkey-desktop and kkcli track
+const REMOTE_RELEASES_URL: &str = "https://raw.githubusercontent.com/keepkey/keepkey-desktop/master/firmware/releases.json";
+// Manifest shipped with the app, used until the first successful fetch
+pub(crate) const BUNDLED_RELEASES: &str = include_str!("../../firmware/releases.json");
+
+const FIRMWARE_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const FIRMWARE_CHECK_STARTUP_DELAY: Duration = Duration::from_secs(30);
+const RELEASES_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Preference key for an optional URL that receives update notifications as JSON POSTs
+pub const UPDATE_WEBHOOK_PREFERENCE: &str = "updateWebhookUrl";
+
+#[derive(Debug, Deserialize)]
+struct ReleasesManifest {
+    latest: LatestReleases,
+}
+
+#[derive(Debug, Deserialize)]
+struct LatestReleases {
+    firmware: ReleaseEntry,
+    bootloader: ReleaseEntry,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseEntry {
+    version: String,
+}
+
+/// Latest known firmware/bootloader versions (without the leading `v`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatestVersions {
+    pub firmware: String,
+    pub bootloader: String,
+}
+
+impl LatestVersions {
+    fn from_manifest_str(json: &str) -> Result<Self, String> {
+        let manifest: ReleasesManifest = serde_json::from_str(json)
+            .map_err(|e| format!("Failed to parse releases.json: {}", e))?;
+        Ok(Self {
+            firmware: manifest.latest.firmware.version.trim_start_matches('v').to_string(),
+            bootloader: manifest.latest.bootloader.version.trim_start_matches('v').to_string(),
+        })
+    }
+}
+
+static LATEST_VERSIONS: Lazy<std::sync::RwLock<LatestVersions>> =
+    Lazy::new(|| std::sync::RwLock::new(bundled_versions()));
+
+// Last features seen for every connected device, keyed by device id
+static KNOWN_DEVICES: Lazy<RwLock<HashMap<String, DeviceFeatures>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+// Firmware version each device was last notified about, so a release is announced once
+static NOTIFIED_VERSIONS: Lazy<Mutex<HashMap<String, String>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Latest firmware/bootloader versions in the releases.json shipped with the app
+pub fn bundled_versions() -> LatestVersions {
+    LatestVersions::from_manifest_str(BUNDLED_RELEASES).unwrap_or_else(|e| {
+        eprintln!("⚠️ {} - falling back to built-in versions", e);
+        LatestVersions {
+            firmware: "7.10.0".to_string(),
+            bootloader: "2.1.4".to_string(),
+        }
+    })
+}
+
+/// Latest firmware/bootloader versions from the most recent releases.json
+pub fn latest_versions() -> LatestVersions {
+    LATEST_VERSIONS
+        .read()
+        .map(|v| v.clone())
+        .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+}
+
+/// Record the features of a device so the background job can re-evaluate it
+pub async fn remember_device(device_id: &str, features: &DeviceFeatures) {
+    KNOWN_DEVICES.write().await.insert(device_id.to_string(), features.clone());
+}
+
+/// Stop tracking a device (called on disconnect)
+pub async fn forget_device(device_id: &str) {
+    KNOWN_DEVICES.write().await.remove(device_id);
+    NOTIFIED_VERSIONS.lock().await.remove(device_id);
+}
+
+/// Last features of every connected device
+pub async fn known_devices() -> Vec<(String, DeviceFeatures)> {
+    KNOWN_DEVICES
+        .read()
+        .await
+        .iter()
+        .map(|(id, features)| (id.clone(), features.clone()))
+        .collect()
+}
+
+async fn fetch_releases_manifest() -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .timeout(RELEASES_FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    client
+        .get(REMOTE_RELEASES_URL)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to fetch releases.json: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read releases.json: {}", e))
+}
+
+async fn post_webhook(url: &str, payload: &serde_json::Value) {
+    let client = match reqwest::Client::builder().timeout(RELEASES_FETCH_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            log::warn!("Failed to build webhook client: {}", e);
+            return;
+        }
+    };
+
+    match client.post(url).json(payload).send().await {
+        Ok(resp) if resp.status().is_success() => {
+            log::info!("📨 Update webhook delivered to {}", url);
+        }
+        Ok(resp) => log::warn!("Update webhook {} returned {}", url, resp.status()),
+        Err(e) => log::warn!("Update webhook {} failed: {}", url, e),
+    }
+}
+
+/// Refresh releases.json and re-evaluate every known device, emitting
+/// `device:update-available` for devices that are behind the latest release.
+pub async fn check_known_devices(app: &AppHandle) {
+    let fetched = fetch_releases_manifest().await.and_then(|body| {
+        super::authenticity::update_known_hashes(&body);
+        LatestVersions::from_manifest_str(&body)
+    });
+    match fetched {
+        Ok(versions) => {
+            let mut latest = LATEST_VERSIONS.write().unwrap_or_else(|p| p.into_inner());
+            if *latest != versions {
+                println!("🆕 Latest releases: firmware v{}, bootloader v{}", versions.firmware, versions.bootloader);
+                *latest = versions;
+            }
+        }
+        Err(e) => {
+            // Keep evaluating against the last known manifest
+            log::warn!("Firmware check could not refresh releases: {}", e);
+        }
+    }
+
+    let latest = latest_versions();
+    let devices = known_devices().await;
+
+    if devices.is_empty() {
+        return;
+    }
+
+    let webhook_url = crate::commands::get_preference(UPDATE_WEBHOOK_PREFERENCE.to_string())
+        .await
+        .ok()
+        .flatten()
+        .filter(|url| !url.trim().is_empty());
+
+    for (device_id, features) in devices {
+        let status = crate::commands::evaluate_device_status(device_id.clone(), Some(&features));
+        if !status.needs_firmware_update && !status.needs_bootloader_update {
+            continue;
+        }
+
+        {
+            let mut notified = NOTIFIED_VERSIONS.lock().await;
+            if notified.get(&device_id) == Some(&latest.firmware) {
+                continue;
+            }
+            notified.insert(device_id.clone(), latest.firmware.clone());
+        }
+
+        println!("🔔 Update available for {}: firmware v{} -> v{}", device_id, features.version, latest.firmware);
+
+        let payload = serde_json::json!({
+            "deviceId": device_id,
+            "currentFirmware": features.version,
+            "latestFirmware": latest.firmware,
+            "latestBootloader": latest.bootloader,
+            "status": status,
+        });
+
+        if let Err(e) = crate::commands::emit_or_queue_event(app, "device:update-available", payload.clone()).await {
+            println!("❌ Failed to emit/queue device:update-available event: {}", e);
+        }
+
+        crate::notifications::notify(
+            app,
+            crate::notifications::NotificationKind::UpdateAvailable,
+            Some(&device_id),
+            "Firmware update available",
+            format!("Firmware v{} is available (installed: v{})", latest.firmware, features.version),
+            payload.clone(),
+        )
+        .await;
+        
+        if let Some(url) = &webhook_url {
+            post_webhook(url, &payload).await;
+        }
+    }
+}
+
+/// Spawn the periodic firmware-check job
+pub fn spawn_firmware_check_job(app: &AppHandle) {
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        // Let device discovery populate the known devices first
+        tokio::time::sleep(FIRMWARE_CHECK_STARTUP_DELAY).await;
+
+        let mut interval = tokio::time::interval(FIRMWARE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            check_known_devices(&app_handle).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_manifest_parses() {
+        let versions = LatestVersions::from_manifest_str(BUNDLED_RELEASES).unwrap();
+        assert!(!versions.firmware.starts_with('v'));
+        assert!(semver::Version::parse(&versions.firmware).is_ok());
+        assert!(semver::Version::parse(&versions.bootloader).is_ok());
+    }
+}
@@ -0,0 +1,79 @@
+//! Identity of KeepKeys that have no USB serial number.
+//!
+//! keepkey-rust names such devices after sticky slots (`keepkey_slot1`, ...)
+//! kept in `~/.keepkey/device-slots.json`, so a replug doesn't turn the same
+//! device into a new one (see `keepkey_rust::device_identity`). Before unlock
+//! the slot is a guess; once the device is unlocked its wallet fingerprint
+//! settles it here. When the wallet belongs in another slot the event
+//! controller's next scan reports the old id as disconnected and the wallet's
+//! slot as connected.
+
+use keepkey_rust::device_identity::{self, Binding, IdentitySlot};
+use keepkey_rust::device_queue::DeviceQueueHandle;
+
+const SLOTS_FILE: &str = "device-slots.json";
+
+/// Load the saved slots. Must run before the first device scan.
+pub fn load_identity_slots() -> Result<(), String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    device_identity::load_identity_slots(home_dir.join(".keepkey").join(SLOTS_FILE))
+        .map_err(|e| format!("Failed to load device identity slots: {}", e))
+}
+
+/// Bind the unlocked wallet on a serial-less device to its slot. Returns the
+/// slot the device moved to, if it turned out to be another wallet's device.
+pub async fn bind_unlocked_wallet(device_id: &str, handle: &DeviceQueueHandle) -> Option<String> {
+    if !device_identity::is_slot_id(device_id) {
+        return None;
+    }
+    let features = match handle.get_features().await {
+        Ok(features) => features,
+        Err(e) => {
+            log::warn!("Can't read features of {} to bind its wallet: {}", device_id, e);
+            return None;
+        }
+    };
+    let locked = features.pin_protection.unwrap_or(false) && !features.pin_cached.unwrap_or(false);
+    // With a passphrase the fingerprint depends on what was typed, not the device
+    if !features.initialized.unwrap_or(false)
+        || features.bootloader_mode.unwrap_or(false)
+        || locked
+        || features.passphrase_protection.unwrap_or(false)
+    {
+        return None;
+    }
+
+    let fingerprint = match crate::bitcoin::descriptors::device_fingerprint(handle).await {
+        Ok(fingerprint) => fingerprint,
+        Err(e) => {
+            log::warn!("Can't read the wallet fingerprint of {}: {}", device_id, e);
+            return None;
+        }
+    };
+    match device_identity::bind_wallet_fingerprint(device_id, &fingerprint) {
+        Ok(Binding::Bound) => None,
+        Ok(Binding::Moved { from, to }) => {
+            log::info!("Device {} holds wallet {}, known as {}", from, fingerprint, to);
+            Some(to)
+        }
+        Err(e) => {
+            log::warn!("Failed to bind wallet {} to {}: {}", fingerprint, device_id, e);
+            None
+        }
+    }
+}
+
+/// Slots of serial-less devices, attached or not
+#[tauri::command]
+pub async fn get_device_identity_slots() -> Result<Vec<IdentitySlot>, String> {
+    Ok(device_identity::identity_slots())
+}
+
+/// Forget a slot that is not in use, e.g. for a device given away
+#[tauri::command]
+pub async fn forget_device_identity_slot(device_id: String) -> Result<(), String> {
+    match device_identity::forget_identity_slot(&device_id).map_err(|e| e.to_string())? {
+        true => Ok(()),
+        false => Err(format!("No identity slot {}", device_id)),
+    }
+}
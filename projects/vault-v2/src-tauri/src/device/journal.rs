@@ -0,0 +1,236 @@
+//! On-disk journal of in-flight signing jobs.
+//!
+//! Every SignTransaction request is written to `~/.keepkey/signing-journal.json`
+//! before it reaches the device and removed once the request finishes. Anything
+//! still in the journal at startup was interrupted by a crash, and the user is
+//! told which transaction it was and whether the device may already have signed
+//! it, so they don't blindly retry and double-spend.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+use crate::commands::{BitcoinUtxoInput, BitcoinUtxoOutput};
+
+const JOURNAL_FILE: &str = "signing-journal.json";
+
+// Serializes read-modify-write cycles on the journal file
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningStage {
+    /// Accepted but nothing sent to the device yet
+    Queued,
+    /// SignTx sent; the device may have produced signatures
+    SentToDevice,
+    /// The device returned the fully signed transaction
+    Signed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalOutput {
+    pub address: String,
+    pub amount: u64,
+    pub is_change: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningJob {
+    pub request_id: String,
+    pub device_id: String,
+    pub coin: String,
+    /// Spent outpoints as `txid:vout`
+    pub inputs: Vec<String>,
+    pub outputs: Vec<JournalOutput>,
+    pub stage: SigningStage,
+    pub signatures_received: usize,
+    pub started_at: String,
+    pub updated_at: String,
+}
+
+impl SigningJob {
+    /// Whether the device could have produced a valid signature for this job
+    pub fn device_may_have_signed(&self) -> bool {
+        self.stage != SigningStage::Queued
+    }
+
+    pub fn recovery_advice(&self) -> &'static str {
+        match self.stage {
+            SigningStage::Queued => "The transaction never reached the device. It is safe to retry.",
+            SigningStage::SentToDevice => {
+                "The device may have signed this transaction. Check that its inputs are still unspent before retrying."
+            }
+            SigningStage::Signed => {
+                "The device signed this transaction and it may already have been broadcast. Check the inputs before retrying."
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalFile {
+    #[serde(default)]
+    active: Vec<SigningJob>,
+    /// Jobs found in `active` at startup, kept until the user dismisses them
+    #[serde(default)]
+    interrupted: Vec<SigningJob>,
+}
+
+fn journal_path() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    Ok(home_dir.join(".keepkey").join(JOURNAL_FILE))
+}
+
+fn read_journal() -> Result<JournalFile, String> {
+    let path = journal_path()?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse signing journal {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(JournalFile::default()),
+        Err(e) => Err(format!("Failed to read signing journal {}: {}", path.display(), e)),
+    }
+}
+
+fn write_journal(journal: &JournalFile) -> Result<(), String> {
+    let path = journal_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create journal directory: {}", e))?;
+    }
+
+    // Write-then-rename so a crash mid-write never leaves a truncated journal
+    let tmp_path = path.with_extension("json.tmp");
+    let contents = serde_json::to_string_pretty(journal)
+        .map_err(|e| format!("Failed to serialize signing journal: {}", e))?;
+    std::fs::write(&tmp_path, contents).map_err(|e| format!("Failed to write signing journal: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to replace signing journal: {}", e))
+}
+
+fn update_journal<T>(f: impl FnOnce(&mut JournalFile) -> T) -> Result<T, String> {
+    let _lock = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut journal = read_journal()?;
+    let result = f(&mut journal);
+    write_journal(&journal)?;
+    Ok(result)
+}
+
+/// Journal entry for one signing request; the entry is removed when this is
+/// dropped, so only a crash leaves it behind.
+pub struct JournalGuard {
+    request_id: String,
+}
+
+impl JournalGuard {
+    pub fn set_stage(&self, stage: SigningStage, signatures_received: usize) {
+        let request_id = &self.request_id;
+        if let Err(e) = update_journal(|journal| {
+            if let Some(job) = journal.active.iter_mut().find(|j| &j.request_id == request_id) {
+                job.stage = stage;
+                job.signatures_received = signatures_received;
+                job.updated_at = chrono::Utc::now().to_rfc3339();
+            }
+        }) {
+            eprintln!("⚠️ Failed to update signing journal for {}: {}", request_id, e);
+        }
+    }
+}
+
+impl Drop for JournalGuard {
+    fn drop(&mut self) {
+        let request_id = &self.request_id;
+        if let Err(e) = update_journal(|journal| journal.active.retain(|j| &j.request_id != request_id)) {
+            eprintln!("⚠️ Failed to clear signing journal entry {}: {}", request_id, e);
+        }
+    }
+}
+
+/// Record a signing job before anything is sent to the device
+pub fn begin_signing_job(
+    request_id: &str,
+    device_id: &str,
+    coin: &str,
+    inputs: &[BitcoinUtxoInput],
+    outputs: &[BitcoinUtxoOutput],
+) -> Result<JournalGuard, String> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let job = SigningJob {
+        request_id: request_id.to_string(),
+        device_id: device_id.to_string(),
+        coin: coin.to_string(),
+        inputs: inputs.iter().map(|i| format!("{}:{}", i.txid, i.vout)).collect(),
+        outputs: outputs
+            .iter()
+            .map(|o| JournalOutput {
+                address: o.address.clone(),
+                amount: o.amount,
+                is_change: o.is_change.unwrap_or(o.address_type == "change"),
+            })
+            .collect(),
+        stage: SigningStage::Queued,
+        signatures_received: 0,
+        started_at: now.clone(),
+        updated_at: now,
+    };
+
+    update_journal(|journal| {
+        journal.active.retain(|j| j.request_id != job.request_id);
+        journal.active.push(job);
+    })?;
+
+    Ok(JournalGuard {
+        request_id: request_id.to_string(),
+    })
+}
+
+/// Move jobs left over from the previous run to the interrupted list.
+/// Must run once at startup, before any new signing request is accepted.
+pub fn recover_interrupted_jobs() -> Result<Vec<SigningJob>, String> {
+    update_journal(|journal| {
+        let recovered: Vec<SigningJob> = journal.active.drain(..).collect();
+        journal.interrupted.extend(recovered.iter().cloned());
+        recovered
+    })
+}
+
+/// Tell the frontend about every interrupted job that hasn't been dismissed
+pub async fn notify_interrupted_jobs(app: &AppHandle) {
+    let jobs = match read_journal() {
+        Ok(journal) => journal.interrupted,
+        Err(e) => {
+            eprintln!("⚠️ {}", e);
+            return;
+        }
+    };
+
+    for job in jobs {
+        println!(
+            "⚠️ Signing job {} on device {} was interrupted at stage {:?} (inputs: {})",
+            job.request_id,
+            job.device_id,
+            job.stage,
+            job.inputs.join(", ")
+        );
+        let payload = serde_json::json!({
+            "job": job,
+            "deviceMayHaveSigned": job.device_may_have_signed(),
+            "advice": job.recovery_advice(),
+        });
+        if let Err(e) = crate::commands::emit_or_queue_event(app, "signing:interrupted", payload).await {
+            eprintln!("Failed to emit signing:interrupted event: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_interrupted_signing_jobs() -> Result<Vec<SigningJob>, String> {
+    let _lock = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(read_journal()?.interrupted)
+}
+
+#[tauri::command]
+pub async fn dismiss_interrupted_signing_job(request_id: String) -> Result<(), String> {
+    update_journal(|journal| journal.interrupted.retain(|j| j.request_id != request_id))
+}
@@ -1,3 +1,8 @@
+pub mod authenticity;
+pub mod firmware_check;
+pub mod identity;
+pub mod journal;
+pub mod pending_requests;
 pub mod queue;
 pub mod updates;
 
@@ -0,0 +1,92 @@
+//! Background device requests interrupted by a crash.
+//!
+//! Frontloading and balance sync queue their device requests in the
+//! background lane; keepkey-rust journals those to
+//! `~/.keepkey/device-requests.db` (see `keepkey_rust::request_journal`). After
+//! a crash the frontend lists what was left with `get_pending_requests` and
+//! replays or discards each one. Signing has its own journal in
+//! [`super::journal`].
+
+use keepkey_rust::request_journal::{self, PendingRequest, Replayed, RequestJournal};
+use std::sync::Arc;
+use tauri::State;
+
+use crate::commands::DeviceQueueManager;
+
+const JOURNAL_FILE: &str = "device-requests.db";
+
+/// Open the journal and start journaling background requests. Must run once
+/// at startup, before any device worker is spawned.
+pub fn open_request_journal() -> Result<Vec<PendingRequest>, String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    let dir = home_dir.join(".keepkey");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create journal directory: {}", e))?;
+
+    let journal = RequestJournal::open(dir.join(JOURNAL_FILE))
+        .map_err(|e| format!("Failed to open device request journal: {}", e))?;
+    let pending = journal.pending().map_err(|e| format!("Failed to read device request journal: {}", e))?;
+    request_journal::set_request_journal(Some(Arc::new(journal)));
+    Ok(pending)
+}
+
+fn journal() -> Result<Arc<RequestJournal>, String> {
+    request_journal::request_journal().ok_or_else(|| "Device request journal is not open".to_string())
+}
+
+/// What a replayed request returned, in a form the frontend can show
+fn replayed_json(replayed: Replayed) -> serde_json::Value {
+    match replayed {
+        Replayed::Features(features) => serde_json::json!({
+            "deviceId": features.device_id,
+            "label": features.label,
+            "initialized": features.initialized,
+        }),
+        Replayed::Address(address) => serde_json::json!({ "address": address }),
+        Replayed::Message(message) => serde_json::json!({ "messageType": format!("{:?}", message.message_type()) }),
+        Replayed::SignedPsbt(signed) => serde_json::json!({ "psbt": signed.psbt.to_string() }),
+    }
+}
+
+/// Background requests the last run didn't finish, oldest first
+#[tauri::command]
+pub async fn get_pending_requests() -> Result<Vec<PendingRequest>, String> {
+    journal()?.pending().map_err(|e| e.to_string())
+}
+
+/// Send an interrupted request to its device again
+#[tauri::command]
+pub async fn replay_pending_request(
+    id: i64,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<serde_json::Value, String> {
+    let journal = journal()?;
+    let pending = journal
+        .pending()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("No interrupted request {}", id))?;
+    if crate::commands::is_device_in_pin_flow(&pending.device_id) {
+        return Err("Device is currently in PIN entry mode. Please complete PIN entry first.".to_string());
+    }
+    let handle = {
+        let mut manager = queue_manager.lock().await;
+        crate::bitcoin::accounts::queue_handle(&pending.device_id, &mut manager)?
+    };
+
+    let replayed = journal
+        .replay(id, &handle)
+        .await
+        .map_err(|e| format!("Failed to replay request {}: {}", id, e))?;
+    log::info!("Replayed interrupted request {} on {}", id, pending.device_id);
+    Ok(replayed_json(replayed))
+}
+
+/// Forget an interrupted request without sending it
+#[tauri::command]
+pub async fn discard_pending_request(id: i64) -> Result<(), String> {
+    match journal()?.discard(id).map_err(|e| e.to_string())? {
+        true => Ok(()),
+        false => Err(format!("No interrupted request {}", id)),
+    }
+}
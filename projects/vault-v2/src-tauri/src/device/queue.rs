@@ -6,6 +6,7 @@ use tokio::sync::RwLock;
 
 // Import types needed for DeviceRequestWrapper
 use crate::commands::{DeviceRequestWrapper, DeviceRequest, DeviceResponse, DeviceQueueManager, parse_transaction_from_hex};
+use keepkey_rust::derivation_path::{hardened, DerivationPath};
 
 // Create a cache for device states to remember OOB bootloader status
 lazy_static::lazy_static! {
@@ -20,6 +21,29 @@ struct DeviceStateCache {
     last_update: std::time::Instant,
 }
 
+/// Script type implied by a hardened BIP-44/49/84 purpose
+fn script_type_for_path(path: &DerivationPath) -> Option<String> {
+    let script_type = match path.as_slice().first().copied()? {
+        p if p == hardened(44) => "p2pkh",
+        p if p == hardened(49) => "p2sh-p2wpkh",
+        p if p == hardened(84) => "p2wpkh",
+        _ => return None,
+    };
+    Some(script_type.to_string())
+}
+
+/// Largest OP_RETURN payload Bitcoin Core relays as standard
+const MAX_OP_RETURN_BYTES: usize = 80;
+
+/// Decode and size-check the hex payload of an OP_RETURN output
+fn op_return_bytes(data: &str) -> Result<Vec<u8>, String> {
+    let bytes = hex::decode(data).map_err(|e| format!("Invalid OP_RETURN data hex: {}", e))?;
+    if bytes.len() > MAX_OP_RETURN_BYTES {
+        return Err(format!("OP_RETURN data is {} bytes; at most {} are allowed", bytes.len(), MAX_OP_RETURN_BYTES));
+    }
+    Ok(bytes)
+}
+
 #[tauri::command]
 pub async fn add_to_device_queue(
     request: DeviceRequestWrapper,
@@ -74,7 +98,9 @@ pub async fn add_to_device_queue(
             manager.insert(request.device_id.clone(), handle.clone());
             handle
         }
-    };
+    }
+    // Device diagnostics read during this request carry its id
+    .with_request_id(request.request_id.clone());
 
     // ------------------------------------------------------------------
     // Check if device is in PIN flow BEFORE doing anything else
@@ -205,7 +231,7 @@ pub async fn add_to_device_queue(
             
             // Create a simple request that will trigger PIN on locked device
             let get_address = keepkey_rust::messages::GetAddress {
-                address_n: vec![44, 0, 0, 0, 0], // m/44'/0'/0'/0/0
+                address_n: vec![hardened(44), hardened(0), hardened(0), 0, 0], // m/44'/0'/0'/0/0
                 coin_name: Some("Bitcoin".to_string()),
                 script_type: Some(0), // SPENDADDRESS
                 show_display: Some(false),
@@ -270,13 +296,10 @@ pub async fn add_to_device_queue(
     // Process the request based on type
     let result = match request.request {
         DeviceRequest::GetXpub { ref path } => {
-            // Parse derivation path
-            let path_parts = crate::commands::parse_derivation_path(&path)?;
-            
             // Create GetPublicKey message for xpub
             let get_public_key = keepkey_rust::messages::Message::GetPublicKey(
                 keepkey_rust::messages::GetPublicKey {
-                    address_n: path_parts,
+                    address_n: path.as_slice().to_vec(),
                     coin_name: Some("Bitcoin".to_string()),
                     script_type: None, // Default script type
                     ecdsa_curve_name: Some("secp256k1".to_string()),
@@ -310,7 +333,7 @@ pub async fn add_to_device_queue(
             }
         }
         DeviceRequest::GetAddress { ref path, ref coin_name, ref script_type, show_display } => {
-            let path_parts = crate::commands::parse_derivation_path(&path)?;
+            let path_parts = path.as_slice().to_vec();
             let script_type_int = match script_type.as_deref() {
                 Some("p2pkh") => Some(0),       // SPENDADDRESS = 0
                 Some("p2sh-p2wpkh") => Some(4), // SPENDP2SHWITNESS = 4  
@@ -345,6 +368,15 @@ pub async fn add_to_device_queue(
             Ok(features_json.to_string())
         }
         DeviceRequest::SignTransaction { ref coin, ref inputs, ref outputs, version, lock_time } => {
+            // Journal the job first so a crash mid-signing can be reported on restart
+            let journal_entry = crate::device::journal::begin_signing_job(
+                &request.request_id,
+                &request.device_id,
+                coin,
+                inputs,
+                outputs,
+            )?;
+            
             // Build transaction map with previous transactions and unsigned transaction
             let mut tx_map = std::collections::HashMap::new();
             
@@ -408,7 +440,7 @@ pub async fn add_to_device_queue(
                     prev_hash: hex::decode(&input.txid).map_err(|e| format!("Invalid txid hex: {}", e))?,
                     prev_index: input.vout,
                     script_sig: None,
-                    sequence: Some(0xffffffff),
+                    sequence: Some(input.sequence.unwrap_or(0xffffffff)),
                     script_type: Some(script_type as i32),
                     amount: Some(input.amount.parse::<u64>().map_err(|_| "Invalid amount")?),
                     ..Default::default()
@@ -417,6 +449,16 @@ pub async fn add_to_device_queue(
 
             let mut new_tx_outputs = Vec::new();
             for output in outputs {
+                if let Some(data) = &output.op_return_data {
+                    new_tx_outputs.push(keepkey_rust::messages::TxOutputType {
+                        amount: output.amount,
+                        script_type: keepkey_rust::messages::OutputScriptType::Paytoopreturn as i32,
+                        op_return_data: Some(op_return_bytes(data)?),
+                        address_type: Some(keepkey_rust::messages::OutputAddressType::Spend as i32),
+                        ..Default::default()
+                    });
+                    continue;
+                }
                 let script_type = match output.address_type.as_str() {
                     "change" => {
                         // For change outputs, use address_n and appropriate script type
@@ -485,6 +527,8 @@ pub async fn add_to_device_queue(
             let mut signatures = Vec::new();
             let mut serialized_tx_parts = Vec::new();
             
+            journal_entry.set_stage(crate::device::journal::SigningStage::SentToDevice, 0);
+            
             let signing_result = loop {
                 let response = queue_handle.send_raw(current_message, false).await
                     .map_err(|e| format!("Device communication error: {}", e))?;
@@ -499,6 +543,7 @@ pub async fn add_to_device_queue(
                             if let Some(signature) = &serialized.signature {
                                 if let Some(sig_index) = serialized.signature_index {
                                     signatures.push((sig_index, hex::encode(signature)));
+                                    journal_entry.set_stage(crate::device::journal::SigningStage::SentToDevice, signatures.len());
                                 }
                             }
                         }
@@ -514,6 +559,7 @@ pub async fn add_to_device_queue(
                                 }
                                 
                                 let signed_tx_hex = hex::encode(&serialized_tx);
+                                journal_entry.set_stage(crate::device::journal::SigningStage::Signed, signatures.len());
                                 
                                 println!("✅ Transaction signed successfully!");
                                 println!("   Signatures: {}", signatures.len());
@@ -569,16 +615,7 @@ pub async fn add_to_device_queue(
     // Create and store the response
     let device_response = match (&request.request, &result) {
         (DeviceRequest::GetXpub { path }, Ok(ref xpub)) => {
-            // Infer script_type from path
-            let script_type = if path.starts_with("m/44'") {
-                Some("p2pkh".to_string())
-            } else if path.starts_with("m/49'") {
-                Some("p2sh-p2wpkh".to_string())
-            } else if path.starts_with("m/84'") {
-                Some("p2wpkh".to_string())
-            } else {
-                None
-            };
+            let script_type = script_type_for_path(path);
             // Debug logging for xpub conversion
             println!("[slip132-debug] Original xpub: {}", xpub);
             println!("[slip132-debug] Inferred script_type: {:?}", script_type);
@@ -608,16 +645,7 @@ pub async fn add_to_device_queue(
             }
         }
         (DeviceRequest::GetXpub { path }, Err(e)) => {
-            // Infer script_type from path for error case as well
-            let script_type = if path.starts_with("m/44'") {
-                Some("p2pkh".to_string())
-            } else if path.starts_with("m/49'") {
-                Some("p2sh-p2wpkh".to_string())
-            } else if path.starts_with("m/84'") {
-                Some("p2wpkh".to_string())
-            } else {
-                None
-            };
+            let script_type = script_type_for_path(path);
             DeviceResponse::Xpub {
                 request_id: request.request_id.clone(),
                 device_id: request.device_id.clone(),
@@ -712,6 +740,18 @@ pub async fn add_to_device_queue(
         println!("    event_payload: {}", serde_json::to_string_pretty(&event_payload).unwrap_or_else(|_| "failed to serialize".to_string()));
     }
     
+    if let DeviceResponse::SignedTransaction { success: false, ref error, .. } = device_response {
+        crate::notifications::notify(
+            &app,
+            crate::notifications::NotificationKind::SigningFailed,
+            Some(&request.device_id),
+            "Transaction signing failed",
+            error.clone().unwrap_or_else(|| "The device did not sign the transaction".to_string()),
+            serde_json::json!({ "requestId": request.request_id }),
+        )
+        .await;
+    }
+    
     if let Err(e) = app.emit("device:response", &event_payload) {
         eprintln!("Failed to emit device:response event: {}", e);
     } else {
@@ -8,6 +8,12 @@ use tokio::sync::RwLock;
 use std::collections::HashMap;
 use crate::logging::{log_device_request, log_device_response};
 use crate::commands::DeviceQueueManager;
+use keepkey_rust::device_queue::DeviceQueueFactory;
+use keepkey_rust::progress::{Progress, ProgressOperation, UpdateStage};
+
+/// How long the device gets to come back after rebooting into new firmware
+const REBOOT_VERIFY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(90);
+const REBOOT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
 
 // Track devices that just completed bootloader updates
 pub type BootloaderUpdateTracker = Arc<RwLock<HashMap<String, std::time::Instant>>>;
@@ -618,11 +624,23 @@ pub async fn update_device_firmware(
                 }
             }
             
+            // Report `verifying` and then `finished`/`failed` once the device is back
+            tauri::async_runtime::spawn(verify_after_reboot(
+                device_id.clone(),
+                target_version.clone(),
+                queue_manager.inner().clone(),
+            ));
+            
             Ok(success)
         }
         Err(e) => {
             let error_msg = e.to_string();
             println!("❌ Firmware update failed for device {}: {}", device_id, error_msg);
+            DeviceQueueFactory::report_progress(
+                &device_id,
+                Progress::update(ProgressOperation::FirmwareUpdate, UpdateStage::Failed, 0, 1)
+                    .with_message(error_msg.clone()),
+            );
             
             // Log the error response
             let response_data = serde_json::json!({
@@ -637,4 +655,55 @@ pub async fn update_device_firmware(
             Err(format!("Firmware update failed: {}", error_msg))
         }
     }
-} 
\ No newline at end of file
+} 
+/// Wait for the device to reconnect after a firmware update and check it
+/// reports the version that was flashed
+async fn verify_after_reboot(device_id: String, target_version: String, queue_manager: DeviceQueueManager) {
+    let report = |stage, message: String| {
+        let current = if stage == UpdateStage::Failed { 0 } else { 1 };
+        DeviceQueueFactory::report_progress(
+            &device_id,
+            Progress::update(ProgressOperation::FirmwareUpdate, stage, current, 1).with_message(message),
+        );
+    };
+    report(UpdateStage::Verifying, format!("Waiting for the device to restart with v{}", target_version));
+
+    let started = std::time::Instant::now();
+    let mut last_seen = None;
+    while started.elapsed() < REBOOT_VERIFY_TIMEOUT {
+        tokio::time::sleep(REBOOT_POLL_INTERVAL).await;
+
+        let devices = keepkey_rust::features::list_connected_devices();
+        let Some(device_info) = devices.iter().find(|d| d.unique_id == device_id) else {
+            continue;
+        };
+        let queue_handle = {
+            let mut manager = queue_manager.lock().await;
+            manager
+                .entry(device_id.clone())
+                .or_insert_with(|| DeviceQueueFactory::spawn_worker(device_id.clone(), device_info.clone()))
+                .clone()
+        };
+        // The device enumerates before it answers; keep polling until it does
+        let features = match queue_handle.get_features().await {
+            Ok(features) => crate::commands::convert_features_to_device_features(features),
+            Err(_) => continue,
+        };
+        if features.bootloader_mode {
+            continue;
+        }
+        if features.version == target_version {
+            report(UpdateStage::Finished, format!("Device is running v{}", features.version));
+            return;
+        }
+        last_seen = Some(features.version);
+        break;
+    }
+
+    let message = match last_seen {
+        Some(version) => format!("Device restarted with v{} instead of v{}", version, target_version),
+        None => "Device did not reconnect after the update. Please unplug and reconnect it.".to_string(),
+    };
+    log::warn!("Firmware update verification failed for {}: {}", device_id, message);
+    report(UpdateStage::Failed, message);
+}
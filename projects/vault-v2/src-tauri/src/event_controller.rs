@@ -1,8 +1,10 @@
 use keepkey_rust::friendly_usb::FriendlyUsbDevice;
+use keepkey_rust::hotplug::{DeviceWatcher, WatchMode};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager};
-use tokio::time::interval;
+use tokio::sync::Notify;
+use tokio::time::{interval, Interval};
 use tokio_util::sync::CancellationToken;
 
 pub struct EventController {
@@ -30,8 +32,25 @@ impl EventController {
         let cancellation_token = self.cancellation_token.clone();
         
         let task_handle = tauri::async_runtime::spawn(async move {
-            let mut interval = interval(Duration::from_millis(1000)); // Check every second
+            // USB changes wake the loop straight away; the interval only catches
+            // what the watcher missed, so it can be slow when hotplug works
+            let usb_changed = Arc::new(Notify::new());
+            let watcher = {
+                let usb_changed = Arc::clone(&usb_changed);
+                DeviceWatcher::start(move |_| usb_changed.notify_one())
+            };
+            let rescan_every = match &watcher {
+                Ok(watcher) if watcher.mode() == WatchMode::Hotplug => Duration::from_secs(5),
+                Ok(_) => Duration::from_millis(1000),
+                Err(e) => {
+                    println!("⚠️ USB change watcher unavailable, polling every second: {}", e);
+                    Duration::from_millis(1000)
+                }
+            };
+            let mut interval = interval(rescan_every);
             let mut last_devices: Vec<FriendlyUsbDevice> = Vec::new();
+            // Devices already plugged in at launch have long since settled
+            let mut first_scan = true;
             
             println!("✅ Event controller started - monitoring device connections");
             
@@ -70,7 +89,7 @@ impl EventController {
                         println!("🛑 Event controller shutting down on cancellation signal");
                         break;
                     }
-                    _ = interval.tick() => {
+                    _ = next_scan(&mut interval, &usb_changed) => {
                         // Get current devices using high-level API
                         let current_devices = keepkey_rust::features::list_connected_devices();
                         
@@ -136,9 +155,10 @@ impl EventController {
                                 // Proactively fetch features and emit device:ready when successful
                                 let app_for_task = app_handle.clone();
                                 let device_for_task = device.clone();
+                                let settle = if first_scan { Duration::ZERO } else { Duration::from_millis(500) };
                                 tokio::spawn(async move {
-                                    // Give device a moment to settle after connection
-                                    tokio::time::sleep(Duration::from_millis(500)).await;
+                                    // Give a hot-plugged device a moment to settle after connection
+                                    tokio::time::sleep(settle).await;
                                     println!("📡 Fetching device features for: {}", device_for_task.unique_id);
                                     
                                     // Emit getting features status
@@ -167,6 +187,20 @@ impl EventController {
                                                 println!("❌ Failed to emit device info status: {}", e);
                                             }
                                             
+                                            // Track the device so the background firmware check can re-evaluate it
+                                            crate::device::firmware_check::remember_device(&device_for_task.unique_id, &features).await;
+                                            
+                                            if features.initialized && features.no_backup {
+                                                crate::notifications::notify(
+                                                    &app_for_task,
+                                                    crate::notifications::NotificationKind::BackupReminder,
+                                                    Some(&device_for_task.unique_id),
+                                                    "Back up your recovery sentence",
+                                                    format!("{} has no verified backup. Write down its recovery sentence before storing funds on it.", device_label),
+                                                    serde_json::Value::Null,
+                                                ).await;
+                                            }
+                                            
                                             // Evaluate device status to determine if updates are needed
                                             let status = crate::commands::evaluate_device_status(
                                                 device_for_task.unique_id.clone(), 
@@ -186,6 +220,14 @@ impl EventController {
                                                    !status.needs_initialization &&
                                                    !is_pin_locked;  // Device is NOT ready if locked with PIN
                             
+                            // Once unlocked, a serial-less device may turn out to hold a wallet known under another slot
+                            if is_actually_ready {
+                                if let Some(slot) = bind_slot_wallet(&device_for_task.unique_id, &app_for_task).await {
+                                    println!("🔀 {} holds the wallet of {}; it reconnects under that id on the next scan", device_for_task.unique_id, slot);
+                                    return;
+                                }
+                            }
+                            
                             if is_actually_ready {
                                                 println!("✅ Device is fully ready, emitting device:ready event");
                                                 println!("📡 Emitting status: Device ready");
@@ -379,6 +421,8 @@ impl EventController {
                                     });
                                 }
                                 
+                                crate::device::firmware_check::forget_device(&device.unique_id).await;
+                                crate::passphrase_profiles::deactivate(&device.unique_id);
                                 let _ = app_handle.emit("device:disconnected", &device.unique_id);
                             }
                         }
@@ -399,9 +443,15 @@ impl EventController {
                         }
                         
                         last_devices = current_devices;
+                        first_scan = false;
                     }
                 }
             }
+
+            // Stopping the watcher joins its thread
+            if let Ok(watcher) = watcher {
+                let _ = tokio::task::spawn_blocking(move || drop(watcher)).await;
+            }
             
             println!("✅ Event controller stopped cleanly");
         });
@@ -441,6 +491,24 @@ impl Drop for EventController {
     }
 }
 
+/// Wait for a USB change or, failing that, the next periodic rescan
+async fn next_scan(interval: &mut Interval, usb_changed: &Notify) {
+    tokio::select! {
+        _ = interval.tick() => {}
+        _ = usb_changed.notified() => interval.reset(),
+    }
+}
+
+/// Settle which slot a serial-less device belongs in from its unlocked wallet
+async fn bind_slot_wallet(device_id: &str, app_handle: &AppHandle) -> Option<String> {
+    if !keepkey_rust::device_identity::is_slot_id(device_id) {
+        return None;
+    }
+    let queue_manager = app_handle.try_state::<crate::commands::DeviceQueueManager>()?;
+    let handle = queue_manager.inner().lock().await.get(device_id).cloned()?;
+    crate::device::identity::bind_unlocked_wallet(device_id, &handle).await
+}
+
 /// Try to get device features without blocking the event loop
 /// Returns features if successful, error message if failed
 /// This function handles OOB bootloader detection by trying Initialize message when GetFeatures fails
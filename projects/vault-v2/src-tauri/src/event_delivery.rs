@@ -0,0 +1,116 @@
+//! Guaranteed delivery for events the frontend must not miss.
+//!
+//! Every event sent through `commands::emit_or_queue_event` gets a sequence
+//! number and stays in the [`DeliveryLog`] until the frontend acks it with
+//! `ack_events`. Events emitted while no webview is listening (startup, a
+//! hot-reload, a crashed renderer) are held, and `frontend_ready` replays
+//! everything unacked in order, so a PIN prompt raised mid-reload still
+//! reaches the user.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Envelope emitted after each delivered event; the frontend acks its `seq`
+pub const DELIVERY_EVENT: &str = "event:delivery";
+
+/// Unacked events kept per run. A frontend that never acks (an old build)
+/// must not grow the log forever, so the oldest are dropped past this.
+const MAX_UNACKED: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggedEvent {
+    pub seq: u64,
+    pub event_name: String,
+    pub payload: serde_json::Value,
+    pub timestamp: u64,
+}
+
+#[derive(Debug)]
+pub struct DeliveryLog {
+    next_seq: u64,
+    unacked: VecDeque<LoggedEvent>,
+}
+
+impl Default for DeliveryLog {
+    fn default() -> Self {
+        Self { next_seq: 1, unacked: VecDeque::new() }
+    }
+}
+
+impl DeliveryLog {
+    /// Assign the next sequence number to an event and keep it until acked.
+    /// Object payloads get the number as `eventSeq` so listeners can dedupe
+    /// a replay of something they already handled.
+    pub fn record(&mut self, event_name: &str, mut payload: serde_json::Value) -> LoggedEvent {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if let Some(object) = payload.as_object_mut() {
+            object.insert("eventSeq".to_string(), seq.into());
+        }
+        let event = LoggedEvent {
+            seq,
+            event_name: event_name.to_string(),
+            payload,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+        };
+        if self.unacked.len() >= MAX_UNACKED {
+            if let Some(dropped) = self.unacked.pop_front() {
+                log::warn!("Dropping unacked event {} (seq {}): delivery log is full", dropped.event_name, dropped.seq);
+            }
+        }
+        self.unacked.push_back(event.clone());
+        event
+    }
+
+    /// Forget acked events; returns how many were still pending
+    pub fn ack(&mut self, seqs: &[u64]) -> usize {
+        let before = self.unacked.len();
+        self.unacked.retain(|event| !seqs.contains(&event.seq));
+        before - self.unacked.len()
+    }
+
+    /// Unacked events, oldest first
+    pub fn pending(&self) -> impl Iterator<Item = &LoggedEvent> {
+        self.unacked.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.unacked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unacked.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.unacked.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unacked_events_are_kept_in_order_until_acked() {
+        let mut log = DeliveryLog::default();
+        let first = log.record("device:pin-unlock-needed", serde_json::json!({ "deviceId": "kk1" }));
+        log.record("notification:new", serde_json::json!("not an object"));
+        log.record("device:ready", serde_json::json!({}));
+        assert_eq!(first.payload["eventSeq"], 1);
+
+        assert_eq!(log.ack(&[2, 99]), 1);
+        let pending: Vec<u64> = log.pending().map(|e| e.seq).collect();
+        assert_eq!(pending, vec![1, 3]);
+
+        for i in 0..MAX_UNACKED {
+            log.record("device:features-updated", serde_json::json!({ "i": i }));
+        }
+        assert_eq!(log.len(), MAX_UNACKED);
+        assert_eq!(log.pending().next().unwrap().seq, 4);
+    }
+}
@@ -0,0 +1,174 @@
+//! Startup check of the firmware assets bundled with the app.
+//!
+//! A missing or mismatched manifest or binary otherwise only shows up when a
+//! device needing an update is plugged in, as a "could not find releases.json"
+//! or a hash failure halfway through the update flow. Checking at startup
+//! turns that into one precise error before any device is touched.
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::device::firmware_check::BUNDLED_RELEASES;
+
+// Firmware images start with a 256-byte header (magic `KPKY`); release hashes
+// cover the code after it. Bootloader updaters are hashed whole.
+const FIRMWARE_MAGIC: &[u8] = b"KPKY";
+const FIRMWARE_HEADER_LEN: usize = 256;
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    latest: LatestEntries,
+    hashes: HashTables,
+}
+
+#[derive(Debug, Deserialize)]
+struct LatestEntries {
+    firmware: AssetEntry,
+    bootloader: AssetEntry,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetEntry {
+    version: String,
+    url: String,
+    hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HashTables {
+    bootloader: HashMap<String, String>,
+    firmware: HashMap<String, String>,
+}
+
+fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn parse_manifest(json: &str) -> Result<Manifest, String> {
+    let manifest: Manifest =
+        serde_json::from_str(json).map_err(|e| format!("Bundled releases.json is malformed: {}", e))?;
+
+    for (name, table) in [("bootloader", &manifest.hashes.bootloader), ("firmware", &manifest.hashes.firmware)] {
+        if table.is_empty() {
+            return Err(format!("Bundled releases.json has an empty {} hash table", name));
+        }
+        if let Some(bad) = table.keys().find(|hash| !is_sha256_hex(hash)) {
+            return Err(format!("Bundled releases.json {} hash table has an invalid entry {:?}", name, bad));
+        }
+    }
+    for (name, entry) in [("firmware", &manifest.latest.firmware), ("bootloader", &manifest.latest.bootloader)] {
+        if !is_sha256_hex(&entry.hash) {
+            return Err(format!("Bundled releases.json latest {} {} has an invalid hash {:?}", name, entry.version, entry.hash));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Check one bundled binary against the hash its manifest entry pins
+fn verify_binary(dir: &Path, name: &str, entry: &AssetEntry, firmware_image: bool) -> Result<(), String> {
+    let path = dir.join(&entry.url);
+    let data = std::fs::read(&path)
+        .map_err(|e| format!("Bundled {} {} is missing at {}: {}", name, entry.version, path.display(), e))?;
+
+    let payload = if firmware_image {
+        if data.len() <= FIRMWARE_HEADER_LEN || !data.starts_with(FIRMWARE_MAGIC) {
+            return Err(format!("Bundled {} {} at {} is not a KeepKey firmware image", name, entry.version, path.display()));
+        }
+        &data[FIRMWARE_HEADER_LEN..]
+    } else {
+        &data[..]
+    };
+
+    let actual = sha256_hex(payload);
+    if !actual.eq_ignore_ascii_case(&entry.hash) {
+        return Err(format!(
+            "Bundled {} {} at {} does not match releases.json: expected {}, found {}",
+            name,
+            entry.version,
+            path.display(),
+            entry.hash,
+            actual
+        ));
+    }
+    Ok(())
+}
+
+/// Verify a firmware directory against the manifest compiled into the app
+pub fn verify_firmware_dir(dir: &Path) -> Result<(), String> {
+    let manifest = parse_manifest(BUNDLED_RELEASES)?;
+
+    // keepkey-rust reads the on-disk copy during updates; it must be the one we were built with
+    let on_disk_path = dir.join("releases.json");
+    let on_disk = std::fs::read(&on_disk_path)
+        .map_err(|e| format!("Bundled releases.json is missing at {}: {}", on_disk_path.display(), e))?;
+    if sha256_hex(&on_disk) != sha256_hex(BUNDLED_RELEASES.as_bytes()) {
+        return Err(format!(
+            "{} differs from the releases.json this build was compiled with",
+            on_disk_path.display()
+        ));
+    }
+
+    verify_binary(dir, "firmware", &manifest.latest.firmware, true)?;
+    verify_binary(dir, "bootloader", &manifest.latest.bootloader, false)?;
+    Ok(())
+}
+
+/// Where the bundled `firmware/` resource directory lives: the Tauri resource
+/// dir when packaged, the crate directory during development
+fn firmware_dir(app: &AppHandle) -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Ok(resources) = app.path().resource_dir() {
+        candidates.push(resources.join("firmware"));
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        candidates.push(cwd.join("firmware"));
+    }
+    candidates.into_iter().find(|dir| dir.join("releases.json").exists())
+}
+
+/// Fail fast if the bundled firmware manifest or update binaries are missing or corrupt
+pub fn verify_bundled_assets(app: &AppHandle) -> Result<(), String> {
+    let dir = firmware_dir(app).ok_or_else(|| {
+        "Bundled firmware directory not found (expected firmware/releases.json in the app resources)".to_string()
+    })?;
+    verify_firmware_dir(&dir)?;
+    log::info!("✅ Bundled firmware assets verified in {}", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repository_firmware_dir_matches_manifest() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("firmware");
+        verify_firmware_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn tampered_image_is_reported() {
+        let dir = std::env::temp_dir().join(format!("kk-integrity-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("v1")).unwrap();
+        let mut image = FIRMWARE_MAGIC.to_vec();
+        image.resize(FIRMWARE_HEADER_LEN + 16, 0);
+        std::fs::write(dir.join("v1/firmware.keepkey.bin"), &image).unwrap();
+
+        let entry = AssetEntry {
+            version: "v1".to_string(),
+            url: "v1/firmware.keepkey.bin".to_string(),
+            hash: "00".repeat(32),
+        };
+        let err = verify_binary(&dir, "firmware", &entry, true).unwrap_err();
+        assert!(err.contains("does not match releases.json"), "{}", err);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
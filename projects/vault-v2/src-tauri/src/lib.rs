@@ -2,10 +2,16 @@ use tauri::{Emitter, Manager};
 
 // Modules for better organization
 
+mod bitcoin;
+mod build_info;
 mod commands;
 mod device;
 mod event_controller;
+mod event_delivery;
+mod integrity;
 mod logging;
+mod notifications;
+mod passphrase_profiles;
 mod slip132;
 mod server;
 
@@ -189,6 +195,13 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_sql::Builder::default().build())
         .plugin(tauri_plugin_process::init())
+        .on_page_load(|_webview, payload| {
+            // A reload drops the frontend's listeners; hold events until it
+            // signals ready again and replays what it never acked
+            if payload.event() == tauri::webview::PageLoadEvent::Started {
+                tauri::async_runtime::spawn(commands::frontend_unloaded());
+            }
+        })
         .setup(|app| {
             // Initialize device logging system
             if let Err(e) = logging::init_device_logger() {
@@ -196,6 +209,14 @@ pub fn run() {
             } else {
                 println!("✅ Device logging initialized - logs will be written to ~/.keepkey/logs/");
             }
+            log::info!("{}", build_info::summary());
+            
+            // Refuse to start with a missing or corrupt firmware bundle rather
+            // than failing halfway through a device update
+            if let Err(e) = integrity::verify_bundled_assets(&app.handle()) {
+                log::error!("❌ {}", e);
+                return Err(e.into());
+            }
             
             // Initialize real device system using keepkey_rust
             let device_queue_manager = Arc::new(tokio::sync::Mutex::new(
@@ -216,9 +237,123 @@ pub fn run() {
             app.manage(last_responses);
             app.manage(bootloader_tracker);
             
+            // Journal background device requests; report those the last run didn't finish
+            match device::pending_requests::open_request_journal() {
+                Ok(pending) if !pending.is_empty() => println!("⚠️ Found {} interrupted device request(s) from the previous run", pending.len()),
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to open device request journal: {}", e),
+            }
+            
+            // Sticky ids for KeepKeys without a serial number
+            if let Err(e) = device::identity::load_identity_slots() {
+                eprintln!("{}", e);
+            }
+            
             // Start event controller with proper management
             let _event_controller = event_controller::spawn_event_controller(&app.handle());
             
+            // Report signing jobs that were in flight when the last run crashed
+            match device::journal::recover_interrupted_jobs() {
+                Ok(jobs) if !jobs.is_empty() => println!("⚠️ Found {} interrupted signing job(s) from the previous run", jobs.len()),
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to read signing journal: {}", e),
+            }
+            let journal_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                device::journal::notify_interrupted_jobs(&journal_handle).await;
+            });
+            
+            // Periodically re-check releases.json against known devices
+            device::firmware_check::spawn_firmware_check_job(&app.handle());
+            
+            // Forward firmware update, signing and recovery progress to the frontend
+            let progress_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut progress = keepkey_rust::device_queue::DeviceQueueFactory::subscribe_progress();
+                loop {
+                    match progress.recv().await {
+                        Ok(event) => {
+                            let _ = progress_handle.emit("device:progress", &event);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            log::debug!("Dropped {} device progress events", skipped);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Copy firmware DEBUG_LINK logs into the device log next to the requests that caused them
+            tauri::async_runtime::spawn(async move {
+                if let Ok(Some(on)) = commands::get_preference("deviceDiagnostics".to_string()).await {
+                    keepkey_rust::diagnostics::set_collection_enabled(on == "true");
+                }
+                if let Ok(Some(on)) = commands::get_preference("protocolTrace".to_string()).await {
+                    keepkey_rust::protocol_trace::set_tracing_enabled(on == "true");
+                }
+                let mut diagnostics = keepkey_rust::diagnostics::subscribe();
+                loop {
+                    match diagnostics.recv().await {
+                        Ok(diagnostic) => {
+                            if let Err(e) = logging::log_device_diagnostic(&diagnostic).await {
+                                eprintln!("Failed to log device diagnostic: {}", e);
+                            }
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            log::debug!("Dropped {} device diagnostics", skipped);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // A passphrase profile switch changes the wallet under the UI
+            let profile_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut selections = passphrase_profiles::subscribe_selections();
+                loop {
+                    match selections.recv().await {
+                        Ok(active) => {
+                            let payload = serde_json::to_value(&active).unwrap_or_default();
+                            let _ = commands::emit_or_queue_event(&profile_handle, "passphrase:profile-selected", payload).await;
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Payments to addresses integrations asked us to watch
+            tauri::async_runtime::spawn(bitcoin::address_watch::run(app.handle().clone()));
+
+            // Suggest fee bumps for our own sends that look stuck in the mempool
+            tauri::async_runtime::spawn(bitcoin::fee_advice::run(app.handle().clone()));
+
+            // Apply device queue tuning: slow-request warnings and keep-claimed mode
+            tauri::async_runtime::spawn(async move {
+                if let Ok(Some(ms)) = commands::get_preference("slowRequestThresholdMs".to_string()).await {
+                    match ms.parse::<u64>() {
+                        Ok(ms) => keepkey_rust::device_queue::set_slow_request_threshold(std::time::Duration::from_millis(ms)),
+                        Err(e) => log::warn!("Ignoring invalid slowRequestThresholdMs preference {:?}: {}", ms, e),
+                    }
+                }
+                // Hold the USB interface between requests while a burst lasts
+                if let Ok(Some(ms)) = commands::get_preference("keepClaimedIdleMs".to_string()).await {
+                    match ms.parse::<u64>() {
+                        Ok(0) => keepkey_rust::device_queue::set_keep_claimed(None),
+                        Ok(ms) => keepkey_rust::device_queue::set_keep_claimed(Some(std::time::Duration::from_millis(ms))),
+                        Err(e) => log::warn!("Ignoring invalid keepClaimedIdleMs preference {:?}: {}", ms, e),
+                    }
+                }
+                // Per-message read timeouts, e.g. "GetFeatures=2s, SignTx=90s, confirm=10m"
+                if let Ok(Some(spec)) = commands::get_preference("deviceTimeouts".to_string()).await {
+                    match keepkey_rust::messages::TimeoutOverrides::parse(&spec) {
+                        Ok(overrides) => keepkey_rust::messages::set_timeout_overrides(overrides),
+                        Err(e) => log::warn!("Ignoring invalid deviceTimeouts preference {:?}: {}", spec, e),
+                    }
+                }
+            });
+
             // Start background log cleanup task
             let _app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -250,7 +385,7 @@ pub fn run() {
                 if api_enabled {
                     log::info!("🚀 API is enabled in preferences, starting server...");
                     
-                    if let Err(e) = server::start_server(server_queue_manager).await {
+                    if let Err(e) = server::start_server(server_queue_manager, server_handle.clone()).await {
                         log::error!("❌ Server error: {}", e);
                         // Optionally emit error event to frontend
                         let _ = server_handle.emit("server:error", serde_json::json!({
@@ -272,13 +407,60 @@ pub fn run() {
             open_url,
             restart_backend_startup,
             // Frontend readiness
+            build_info::get_build_info,
             commands::frontend_ready,
+            commands::ack_events,
             // Device operations - unified queue interface
             device::queue::add_to_device_queue,
+            device::journal::get_interrupted_signing_jobs,
+            device::journal::dismiss_interrupted_signing_job,
+            device::pending_requests::get_pending_requests,
+            device::pending_requests::replay_pending_request,
+            device::pending_requests::discard_pending_request,
+            device::identity::get_device_identity_slots,
+            device::identity::forget_device_identity_slot,
+            notifications::list_notifications,
+            notifications::mark_notification_read,
+            notifications::mark_all_notifications_read,
+            notifications::record_notification,
             commands::get_queue_status,
+            // Transaction building - frontend sends intent, Rust builds and signs
+            bitcoin::send::compose_transaction,
+            bitcoin::send::list_spendable_utxos,
+            bitcoin::send::preview_transaction,
+            bitcoin::send::sign_and_broadcast,
+            bitcoin::send::bump_fee,
+            bitcoin::send::get_coin_selection_preferences,
+            bitcoin::send::set_coin_selection_preference,
+            // Chain data from the configured mempool.space instance
+            bitcoin::chain_providers::get_fee_rates,
+            bitcoin::chain_providers::get_block_height,
+            bitcoin::chain_providers::get_transaction_confirmation,
+            bitcoin::chain_providers::broadcast_raw_transaction,
+            bitcoin::broadcast::broadcast_transaction,
+            bitcoin::broadcast::get_electrum_url,
+            bitcoin::broadcast::set_electrum_url_preference,
+            bitcoin::chain_providers::get_mempool_url,
+            bitcoin::chain_providers::set_mempool_url_preference,
+            // Watch-only export
+            bitcoin::descriptors::get_account_descriptors,
+            bitcoin::descriptors::export_account_descriptors,
+            bitcoin::descriptors::export_watch_only_wallet,
+            bitcoin::multisig::import_multisig_descriptor,
+            bitcoin::multisig::list_multisig_wallets,
+            bitcoin::multisig::get_multisig_address,
+            bitcoin::multisig::sign_multisig_psbt,
+            passphrase_profiles::list_passphrase_profiles,
+            passphrase_profiles::create_passphrase_profile,
+            passphrase_profiles::delete_passphrase_profile,
+            passphrase_profiles::get_active_passphrase_profile,
+            passphrase_profiles::select_passphrase_profile,
             // Basic device enumeration (non-queue operations)
             commands::get_connected_devices,
             commands::get_blocking_actions,
+            commands::get_failure_descriptions,
+            commands::acknowledge_device_authenticity,
+            commands::forget_device,
             // New device commands (all go through queue)
             commands::get_device_status,
             commands::get_device_info_by_id,
@@ -305,6 +487,8 @@ pub fn run() {
             commands::get_device_log_path,
             commands::get_recent_device_logs,
             commands::cleanup_device_logs,
+            commands::get_protocol_trace,
+            commands::set_protocol_trace_enabled,
             // Configuration and onboarding commands
             commands::is_first_time_install,
             commands::is_onboarded,
@@ -136,6 +136,47 @@ impl DeviceLogger {
         self.write_log_entry(&log_entry).await
     }
     
+    /// Log a wallet decision worth auditing later, such as how a transaction was built
+    pub async fn log_audit(
+        &self,
+        device_id: &str,
+        event: &str,
+        details: &serde_json::Value,
+    ) -> Result<(), String> {
+        let timestamp = Utc::now().to_rfc3339();
+        
+        let log_entry = serde_json::json!({
+            "timestamp": timestamp,
+            "direction": "AUDIT",
+            "device_id": device_id,
+            "event": event,
+            "data": details
+        });
+        
+        self.write_log_entry(&log_entry).await
+    }
+    
+    /// Log a line the firmware wrote to its DEBUG_LINK interface
+    pub async fn log_device_diagnostic(
+        &self,
+        diagnostic: &keepkey_rust::diagnostics::DeviceDiagnostic,
+    ) -> Result<(), String> {
+        let timestamp = Utc::now().to_rfc3339();
+        
+        let log_entry = serde_json::json!({
+            "timestamp": timestamp,
+            "direction": "DEVICE_LOG",
+            "device_id": diagnostic.device_id,
+            "request_id": diagnostic.request_id,
+            "operation": diagnostic.operation,
+            "level": diagnostic.level,
+            "bucket": diagnostic.bucket,
+            "text": diagnostic.text
+        });
+        
+        self.write_log_entry(&log_entry).await
+    }
+    
     /// Write a log entry to the current log file
     async fn write_log_entry(&self, log_entry: &serde_json::Value) -> Result<(), String> {
         let current_date = Self::get_current_date();
@@ -286,4 +327,20 @@ pub async fn log_raw_device_message(
 ) -> Result<(), String> {
     let logger = get_device_logger();
     logger.log_raw_message(device_id, direction, message_type, message_data).await
-} 
\ No newline at end of file
+} 
+
+/// Helper function to log a device diagnostic
+pub async fn log_device_diagnostic(diagnostic: &keepkey_rust::diagnostics::DeviceDiagnostic) -> Result<(), String> {
+    let logger = get_device_logger();
+    logger.log_device_diagnostic(diagnostic).await
+}
+
+/// Helper function to log an audit event
+pub async fn log_audit_event(
+    device_id: &str,
+    event: &str,
+    details: &serde_json::Value,
+) -> Result<(), String> {
+    let logger = get_device_logger();
+    logger.log_audit(device_id, event, details).await
+}
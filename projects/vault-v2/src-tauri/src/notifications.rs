@@ -0,0 +1,255 @@
+//! Persisted notification center.
+//!
+//! Events the user should see even if the app wasn't in front of them when
+//! they fired (an update became available, a signing request failed, a payment
+//! arrived, a send got stuck) are kept in `~/.keepkey/notifications.json` with read/unread state.
+//! Each new entry is also pushed to the frontend as `notification:new`; REST
+//! clients list them from `GET /api/notifications`.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::AppHandle;
+use utoipa::ToSchema;
+
+const NOTIFICATIONS_FILE: &str = "notifications.json";
+// Oldest entries are dropped past this, read ones first
+const MAX_NOTIFICATIONS: usize = 200;
+
+// Serializes read-modify-write cycles on the notifications file
+static NOTIFICATIONS_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    UpdateAvailable,
+    BackupReminder,
+    PaymentReceived,
+    SigningFailed,
+    /// An unconfirmed send pays too little for current mempool conditions
+    FeeBumpSuggested,
+}
+
+impl NotificationKind {
+    /// Reminders about a standing condition: one unread entry per device is enough
+    fn is_reminder(self) -> bool {
+        matches!(self, NotificationKind::UpdateAvailable | NotificationKind::BackupReminder)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub message: String,
+    pub device_id: Option<String>,
+    /// Kind-specific details (versions, txid, amount, ...)
+    #[schema(value_type = Object)]
+    pub data: serde_json::Value,
+    pub created_at: String,
+    pub read: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NotificationsFile {
+    #[serde(default)]
+    notifications: Vec<Notification>,
+}
+
+fn notifications_path() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    Ok(home_dir.join(".keepkey").join(NOTIFICATIONS_FILE))
+}
+
+fn read_store() -> Result<NotificationsFile, String> {
+    let path = notifications_path()?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse notifications {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(NotificationsFile::default()),
+        Err(e) => Err(format!("Failed to read notifications {}: {}", path.display(), e)),
+    }
+}
+
+fn write_store(store: &NotificationsFile) -> Result<(), String> {
+    let path = notifications_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create notifications directory: {}", e))?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    let contents = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("Failed to serialize notifications: {}", e))?;
+    std::fs::write(&tmp_path, contents).map_err(|e| format!("Failed to write notifications: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to replace notifications: {}", e))
+}
+
+fn update_store<T>(f: impl FnOnce(&mut NotificationsFile) -> T) -> Result<T, String> {
+    let _lock = NOTIFICATIONS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut store = read_store()?;
+    let result = f(&mut store);
+    write_store(&store)?;
+    Ok(result)
+}
+
+fn unread_count(store: &NotificationsFile) -> usize {
+    store.notifications.iter().filter(|n| !n.read).count()
+}
+
+/// Add `notification` unless it repeats an unread reminder; returns the unread count
+/// after insertion, or `None` if it was a duplicate
+fn insert(store: &mut NotificationsFile, notification: Notification) -> Option<usize> {
+    if notification.kind.is_reminder()
+        && store
+            .notifications
+            .iter()
+            .any(|n| !n.read && n.kind == notification.kind && n.device_id == notification.device_id)
+    {
+        return None;
+    }
+    store.notifications.push(notification);
+
+    while store.notifications.len() > MAX_NOTIFICATIONS {
+        let oldest = store.notifications.iter().position(|n| n.read).unwrap_or(0);
+        store.notifications.remove(oldest);
+    }
+    Some(unread_count(store))
+}
+
+/// Record a notification and push it to the frontend
+pub async fn notify(
+    app: &AppHandle,
+    kind: NotificationKind,
+    device_id: Option<&str>,
+    title: impl Into<String>,
+    message: impl Into<String>,
+    data: serde_json::Value,
+) {
+    let notification = Notification {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        title: title.into(),
+        message: message.into(),
+        device_id: device_id.map(str::to_string),
+        data,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        read: false,
+    };
+
+    let unread = match update_store(|store| insert(store, notification.clone())) {
+        Ok(Some(unread)) => unread,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("Failed to store notification {:?}: {}", kind, e);
+            return;
+        }
+    };
+
+    let payload = serde_json::json!({ "notification": notification, "unreadCount": unread });
+    if let Err(e) = crate::commands::emit_or_queue_event(app, "notification:new", payload).await {
+        log::warn!("Failed to emit notification:new event: {}", e);
+    }
+}
+
+/// Newest first
+pub fn list(unread_only: bool) -> Result<Vec<Notification>, String> {
+    let _lock = NOTIFICATIONS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut notifications: Vec<Notification> =
+        read_store()?.notifications.into_iter().filter(|n| !unread_only || !n.read).collect();
+    notifications.reverse();
+    Ok(notifications)
+}
+
+/// Mark one notification (or all, with `None`) as read. Returns the remaining
+/// unread count, or `None` if `id` doesn't exist.
+pub fn mark_read(id: Option<&str>) -> Result<Option<usize>, String> {
+    update_store(|store| {
+        let mut found = id.is_none();
+        for notification in store.notifications.iter_mut() {
+            if id.map_or(true, |id| notification.id == id) {
+                notification.read = true;
+                found = true;
+            }
+        }
+        found.then(|| unread_count(store))
+    })
+}
+
+#[tauri::command]
+pub async fn list_notifications(unread_only: Option<bool>) -> Result<Vec<Notification>, String> {
+    list(unread_only.unwrap_or(false))
+}
+
+#[tauri::command]
+pub async fn mark_notification_read(id: String, app: AppHandle) -> Result<(), String> {
+    let unread = mark_read(Some(&id))?.ok_or_else(|| format!("Notification {} not found", id))?;
+    crate::commands::emit_or_queue_event(&app, "notification:read", serde_json::json!({ "unreadCount": unread })).await
+}
+
+#[tauri::command]
+pub async fn mark_all_notifications_read(app: AppHandle) -> Result<(), String> {
+    let unread = mark_read(None)?.unwrap_or(0);
+    crate::commands::emit_or_queue_event(&app, "notification:read", serde_json::json!({ "unreadCount": unread })).await
+}
+
+/// For events only the frontend sees, such as a balance increase after a portfolio refresh
+#[tauri::command]
+pub async fn record_notification(
+    kind: NotificationKind,
+    title: String,
+    message: String,
+    device_id: Option<String>,
+    data: Option<serde_json::Value>,
+    app: AppHandle,
+) -> Result<(), String> {
+    notify(&app, kind, device_id.as_deref(), title, message, data.unwrap_or(serde_json::Value::Null)).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(kind: NotificationKind, device_id: &str) -> Notification {
+        Notification {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            title: String::new(),
+            message: String::new(),
+            device_id: Some(device_id.to_string()),
+            data: serde_json::Value::Null,
+            created_at: String::new(),
+            read: false,
+        }
+    }
+
+    #[test]
+    fn unread_reminders_are_not_repeated() {
+        let mut store = NotificationsFile::default();
+        assert_eq!(insert(&mut store, notification(NotificationKind::BackupReminder, "kk1")), Some(1));
+        assert_eq!(insert(&mut store, notification(NotificationKind::BackupReminder, "kk1")), None);
+        assert_eq!(insert(&mut store, notification(NotificationKind::BackupReminder, "kk2")), Some(2));
+        // Events are always recorded
+        assert_eq!(insert(&mut store, notification(NotificationKind::SigningFailed, "kk1")), Some(3));
+        assert_eq!(insert(&mut store, notification(NotificationKind::SigningFailed, "kk1")), Some(4));
+
+        store.notifications[0].read = true;
+        assert_eq!(insert(&mut store, notification(NotificationKind::BackupReminder, "kk1")), Some(4));
+    }
+
+    #[test]
+    fn read_entries_are_dropped_first() {
+        let mut store = NotificationsFile::default();
+        for _ in 0..MAX_NOTIFICATIONS {
+            insert(&mut store, notification(NotificationKind::PaymentReceived, "kk1"));
+        }
+        store.notifications[10].read = true;
+        let kept = store.notifications[0].id.clone();
+        insert(&mut store, notification(NotificationKind::PaymentReceived, "kk1"));
+        assert_eq!(store.notifications.len(), MAX_NOTIFICATIONS);
+        assert!(store.notifications.iter().all(|n| !n.read));
+        assert_eq!(store.notifications[0].id, kept);
+    }
+}
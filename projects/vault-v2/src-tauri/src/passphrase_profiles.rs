@@ -0,0 +1,376 @@
+//! Named passphrase profiles ("Savings", "Spending") for hidden wallets.
+//!
+//! Every BIP-39 passphrase opens a different wallet, and a typo silently opens
+//! an empty one. A profile remembers a passphrase only as a salted, stretched
+//! verifier hash, so a mistyped passphrase is refused before it reaches the
+//! device. Selecting a profile clears the device session, answers the
+//! device's PassphraseRequest and binds the resulting wallet's master
+//! fingerprint to the profile; the fingerprint then scopes cached accounts,
+//! and a later selection that yields a different wallet is refused.
+//!
+//! Profiles are kept per device in `~/.keepkey/passphrase_profiles.json`. The
+//! active profile per device lives in memory only: unplugging the device ends
+//! its session.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::State;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use keepkey_rust::derivation_path::DerivationPath;
+use keepkey_rust::device_queue::DeviceQueueHandle;
+use keepkey_rust::messages::{ClearSession, GetFeatures, GetPublicKey, Message, PassphraseAck};
+
+use crate::bitcoin::descriptors::{master_fingerprint, FINGERPRINT_PATH};
+use crate::commands::DeviceQueueManager;
+
+const PROFILES_FILE: &str = "passphrase_profiles.json";
+// SHA-256 rounds applied to salt || passphrase
+const VERIFIER_ROUNDS: u32 = 100_000;
+
+// Serializes read-modify-write cycles on the profiles file
+static PROFILES_LOCK: Mutex<()> = Mutex::new(());
+
+// Profile currently unlocked on each device, by device id
+static ACTIVE_PROFILES: Lazy<Mutex<HashMap<String, ActiveProfile>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// Selections from the UI and from REST clients alike, forwarded to the frontend
+static SELECTIONS: Lazy<broadcast::Sender<ActiveProfile>> = Lazy::new(|| broadcast::channel(16).0);
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PassphraseProfile {
+    pub id: String,
+    pub device_id: String,
+    pub name: String,
+    /// Master fingerprint of the wallet the passphrase opens, set on first use
+    pub wallet_fingerprint: Option<String>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveProfile {
+    pub device_id: String,
+    pub profile_id: String,
+    pub name: String,
+    pub wallet_fingerprint: String,
+    /// Key for caches and accounts of this wallet: `<device_id>:<fingerprint>`
+    pub scope: String,
+}
+
+/// A profile with its verifier, which stays on disk: handed to a REST client it
+/// would allow offline guessing of the passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredProfile {
+    #[serde(flatten)]
+    profile: PassphraseProfile,
+    /// Hex salt and stretched SHA-256 of salt || passphrase
+    salt: String,
+    verifier: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: Vec<StoredProfile>,
+}
+
+fn profiles_path() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
+    Ok(home_dir.join(".keepkey").join(PROFILES_FILE))
+}
+
+fn read_store() -> Result<ProfilesFile, String> {
+    let path = profiles_path()?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse passphrase profiles {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ProfilesFile::default()),
+        Err(e) => Err(format!("Failed to read passphrase profiles {}: {}", path.display(), e)),
+    }
+}
+
+fn write_store(store: &ProfilesFile) -> Result<(), String> {
+    let path = profiles_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create profiles directory: {}", e))?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    let contents = serde_json::to_string_pretty(store)
+        .map_err(|e| format!("Failed to serialize passphrase profiles: {}", e))?;
+    std::fs::write(&tmp_path, contents).map_err(|e| format!("Failed to write passphrase profiles: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to replace passphrase profiles: {}", e))
+}
+
+fn update_store<T>(f: impl FnOnce(&mut ProfilesFile) -> Result<T, String>) -> Result<T, String> {
+    let _lock = PROFILES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut store = read_store()?;
+    let result = f(&mut store)?;
+    write_store(&store)?;
+    Ok(result)
+}
+
+fn verifier(salt: &[u8], passphrase: &str) -> String {
+    let mut digest = Sha256::new().chain_update(salt).chain_update(passphrase.as_bytes()).finalize();
+    for _ in 1..VERIFIER_ROUNDS {
+        digest = Sha256::new().chain_update(salt).chain_update(digest).finalize();
+    }
+    hex::encode(digest)
+}
+
+fn matches(profile: &StoredProfile, passphrase: &str) -> bool {
+    hex::decode(&profile.salt).is_ok_and(|salt| verifier(&salt, passphrase) == profile.verifier)
+}
+
+pub fn list_profiles(device_id: Option<&str>) -> Result<Vec<PassphraseProfile>, String> {
+    Ok(read_store()?
+        .profiles
+        .into_iter()
+        .map(|stored| stored.profile)
+        .filter(|p| device_id.is_none_or(|id| p.device_id == id))
+        .collect())
+}
+
+pub fn create_profile(device_id: &str, name: &str, passphrase: &str) -> Result<PassphraseProfile, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name is required".to_string());
+    }
+    if passphrase.is_empty() {
+        return Err("An empty passphrase opens the standard wallet; it doesn't need a profile".to_string());
+    }
+    let salt = *uuid::Uuid::new_v4().as_bytes();
+    let profile = PassphraseProfile {
+        id: uuid::Uuid::new_v4().to_string(),
+        device_id: device_id.to_string(),
+        name: name.to_string(),
+        wallet_fingerprint: None,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        last_used_at: None,
+    };
+
+    update_store(|store| {
+        let same_device = || store.profiles.iter().filter(|p| p.profile.device_id == device_id);
+        if same_device().any(|p| p.profile.name.eq_ignore_ascii_case(name)) {
+            return Err(format!("A profile named {:?} already exists", name));
+        }
+        if same_device().any(|p| matches(p, passphrase)) {
+            return Err("Another profile already uses this passphrase".to_string());
+        }
+        store.profiles.push(StoredProfile {
+            profile: profile.clone(),
+            salt: hex::encode(salt),
+            verifier: verifier(&salt, passphrase),
+        });
+        Ok(())
+    })?;
+    Ok(profile)
+}
+
+/// Delete a profile; returns whether it existed
+pub fn delete_profile(profile_id: &str) -> Result<bool, String> {
+    let removed = update_store(|store| {
+        let before = store.profiles.len();
+        store.profiles.retain(|p| p.profile.id != profile_id);
+        Ok(store.profiles.len() != before)
+    })?;
+    if removed {
+        ACTIVE_PROFILES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, active| active.profile_id != profile_id);
+    }
+    Ok(removed)
+}
+
+/// Profiles as they are selected, whoever selected them
+pub fn subscribe_selections() -> broadcast::Receiver<ActiveProfile> {
+    SELECTIONS.subscribe()
+}
+
+pub fn active_profile(device_id: &str) -> Option<ActiveProfile> {
+    ACTIVE_PROFILES.lock().unwrap_or_else(|e| e.into_inner()).get(device_id).cloned()
+}
+
+/// The device's session ended (unplugged or reset): no profile is unlocked
+pub fn deactivate(device_id: &str) {
+    ACTIVE_PROFILES.lock().unwrap_or_else(|e| e.into_inner()).remove(device_id);
+}
+
+/// Drop the device's profiles along with its other stored state
+pub fn forget_device(device_id: &str) -> Result<(), String> {
+    deactivate(device_id);
+    update_store(|store| {
+        store.profiles.retain(|p| p.profile.device_id != device_id);
+        Ok(())
+    })
+}
+
+/// Clear the session, then read a depth-1 xpub answering the passphrase
+/// prompt with `passphrase`; returns the fingerprint of the opened wallet
+async fn open_wallet(handle: &DeviceQueueHandle, passphrase: &str) -> Result<String, String> {
+    // Fresh features: the cached ones may predate another profile's unlock
+    let features = match handle.send_raw(Message::GetFeatures(GetFeatures {}), true).await {
+        Ok(Message::Features(features)) => features,
+        Ok(other) => return Err(format!("Unexpected response from device: {:?}", other.message_type())),
+        Err(e) => return Err(format!("Failed to read device features: {}", e)),
+    };
+    if !features.passphrase_protection.unwrap_or(false) {
+        return Err("Passphrase protection is off on this device; enable it in settings first".to_string());
+    }
+    if features.passphrase_cached.unwrap_or(false) {
+        // Another passphrase is unlocked; the device only asks again after this.
+        // It also forgets the PIN, so a PIN-protected device has to be unlocked
+        // before selecting the profile once more.
+        handle
+            .send_raw(Message::ClearSession(ClearSession {}), true)
+            .await
+            .map_err(|e| format!("Failed to clear device session: {}", e))?;
+    }
+
+    let get_public_key = Message::GetPublicKey(GetPublicKey {
+        address_n: FINGERPRINT_PATH.parse::<DerivationPath>().map_err(|e| e.to_string())?.into_vec(),
+        coin_name: Some("Bitcoin".to_string()),
+        ecdsa_curve_name: Some("secp256k1".to_string()),
+        show_display: Some(false),
+        ..Default::default()
+    });
+    let mut response = handle.send_raw(get_public_key, true).await.map_err(|e| e.to_string())?;
+    if let Message::PassphraseRequest(_) = response {
+        let ack = Message::PassphraseAck(PassphraseAck { passphrase: passphrase.to_string() });
+        response = handle.send_raw(ack, true).await.map_err(|e| e.to_string())?;
+    }
+    match response {
+        Message::PublicKey(public_key) => master_fingerprint(public_key.xpub.as_deref().unwrap_or_default()),
+        Message::PinMatrixRequest(_) => Err("Device is locked. Unlock it with your PIN, then select the profile again.".to_string()),
+        Message::Failure(failure) => Err(format!("Device returned error: {}", failure.message.unwrap_or_default())),
+        other => Err(format!("Unexpected response from device: {:?}", other.message_type())),
+    }
+}
+
+/// Unlock the wallet behind a profile on the device
+pub async fn select_profile(handle: &DeviceQueueHandle, profile_id: &str, passphrase: &str) -> Result<ActiveProfile, String> {
+    let stored = read_store()?
+        .profiles
+        .into_iter()
+        .find(|p| p.profile.id == profile_id)
+        .ok_or_else(|| format!("No passphrase profile {}", profile_id))?;
+    if !matches(&stored, passphrase) {
+        return Err(format!("Passphrase doesn't match profile {:?}", stored.profile.name));
+    }
+    let profile = stored.profile;
+
+    // Whatever was unlocked before is gone once the session is cleared
+    deactivate(&profile.device_id);
+    let fingerprint = open_wallet(handle, passphrase).await?;
+    if let Some(bound) = profile.wallet_fingerprint.as_deref().filter(|bound| *bound != fingerprint) {
+        return Err(format!(
+            "Profile {:?} belongs to wallet {} but the device opened {}; is this the right device?",
+            profile.name, bound, fingerprint
+        ));
+    }
+
+    update_store(|store| {
+        if let Some(stored) = store.profiles.iter_mut().find(|p| p.profile.id == profile.id) {
+            stored.profile.wallet_fingerprint = Some(fingerprint.clone());
+            stored.profile.last_used_at = Some(chrono::Utc::now().to_rfc3339());
+        }
+        Ok(())
+    })?;
+
+    let active = ActiveProfile {
+        scope: format!("{}:{}", profile.device_id, fingerprint),
+        device_id: profile.device_id,
+        profile_id: profile.id,
+        name: profile.name,
+        wallet_fingerprint: fingerprint,
+    };
+    ACTIVE_PROFILES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(active.device_id.clone(), active.clone());
+    let _ = SELECTIONS.send(active.clone());
+    log::info!("Passphrase profile {:?} unlocked wallet {} on {}", active.name, active.wallet_fingerprint, active.device_id);
+    Ok(active)
+}
+
+/// Device queue handle for the profile's device
+pub async fn profile_queue_handle(profile_id: &str, queue_manager: &DeviceQueueManager) -> Result<DeviceQueueHandle, String> {
+    let device_id = list_profiles(None)?
+        .into_iter()
+        .find(|p| p.id == profile_id)
+        .map(|p| p.device_id)
+        .ok_or_else(|| format!("No passphrase profile {}", profile_id))?;
+    if crate::commands::is_device_in_pin_flow(&device_id) {
+        return Err("Device is currently in PIN entry mode. Please complete PIN entry first.".to_string());
+    }
+    let mut manager = queue_manager.lock().await;
+    crate::bitcoin::accounts::queue_handle(&device_id, &mut manager)
+}
+
+#[tauri::command]
+pub async fn list_passphrase_profiles(device_id: Option<String>) -> Result<Vec<PassphraseProfile>, String> {
+    list_profiles(device_id.as_deref())
+}
+
+#[tauri::command]
+pub async fn create_passphrase_profile(device_id: String, name: String, passphrase: String) -> Result<PassphraseProfile, String> {
+    create_profile(&device_id, &name, &passphrase)
+}
+
+#[tauri::command]
+pub async fn delete_passphrase_profile(profile_id: String) -> Result<bool, String> {
+    delete_profile(&profile_id)
+}
+
+#[tauri::command]
+pub async fn get_active_passphrase_profile(device_id: String) -> Result<Option<ActiveProfile>, String> {
+    Ok(active_profile(&device_id))
+}
+
+/// Unlock a profile's wallet. The frontend drops the previous wallet's
+/// accounts on `passphrase:profile-selected` and loads the new ones.
+#[tauri::command]
+pub async fn select_passphrase_profile(
+    profile_id: String,
+    passphrase: String,
+    queue_manager: State<'_, DeviceQueueManager>,
+) -> Result<ActiveProfile, String> {
+    let handle = profile_queue_handle(&profile_id, &queue_manager).await?;
+    select_profile(&handle, &profile_id, &passphrase).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifier_accepts_only_the_same_passphrase() {
+        let salt = [7u8; 16];
+        let profile = StoredProfile {
+            profile: PassphraseProfile {
+                id: "p".to_string(),
+                device_id: "kk1".to_string(),
+                name: "Savings".to_string(),
+                wallet_fingerprint: None,
+                created_at: String::new(),
+                last_used_at: None,
+            },
+            salt: hex::encode(salt),
+            verifier: verifier(&salt, "correct horse"),
+        };
+        assert!(matches(&profile, "correct horse"));
+        assert!(!matches(&profile, "correct horse "));
+        assert!(!matches(&profile, ""));
+        assert_ne!(profile.verifier, verifier(&[8u8; 16], "correct horse"));
+    }
+}
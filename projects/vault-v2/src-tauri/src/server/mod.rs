@@ -1,11 +1,12 @@
 pub mod routes;
 pub mod context;
 pub mod proxy;
+pub mod ws;
 
 use axum::{
     Router,
     serve,
-    routing::{get, post},
+    routing::{delete, get, post},
     response::Json,
 };
 
@@ -18,26 +19,89 @@ use utoipa_swagger_ui::SwaggerUi;
 
 pub struct ServerState {
     pub device_queue_manager: crate::commands::DeviceQueueManager,
+    /// For work that goes through the app's device queue and events, like signing
+    pub app: tauri::AppHandle,
+    /// Device event frames for `/ws` clients
+    pub events: tokio::sync::broadcast::Sender<String>,
 }
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         routes::health_check,
+        routes::api_version,
+        routes::api_get_metrics,
+        routes::api_list_notifications,
+        routes::api_mark_notification_read,
         // Context endpoints - commented out until full device interaction is implemented
         // routes::api_get_context,
         // routes::api_set_context,
         // routes::api_clear_context,
         routes::api_list_devices,
         routes::api_get_features,
+        routes::api_device_diagnostics,
+        routes::api_protocol_trace,
+        routes::api_import_multisig,
+        routes::api_list_multisig,
+        routes::api_multisig_address,
+        routes::api_sign_multisig_psbt,
+        routes::api_list_passphrase_profiles,
+        routes::api_create_passphrase_profile,
+        routes::api_delete_passphrase_profile,
+        routes::api_select_passphrase_profile,
+        routes::api_create_address_watch,
+        routes::api_list_address_watches,
+        routes::api_get_address_watch,
+        routes::api_cancel_address_watch,
+        routes::api_bump_fee,
+        routes::api_chain_fees,
+        routes::api_chain_height,
+        routes::api_chain_transaction,
+        routes::api_chain_broadcast,
+        routes::api_broadcast_transaction,
         routes::mcp_handle,
     ),
     components(
         schemas(
             routes::HealthResponse,
+            crate::build_info::BuildInfo,
+            crate::build_info::FirmwareCompatibility,
+            routes::QueueMetricsResponse,
             routes::DeviceInfo,
             routes::KeepKeyInfo,
+            crate::device::authenticity::AuthenticityCheck,
+            crate::device::authenticity::AuthenticityVerdict,
             routes::Features,
+            routes::DeviceDiagnosticsResponse,
+            routes::DeviceDiagnostic,
+            routes::ProtocolTraceResponse,
+            routes::ProtocolTraceEntry,
+            crate::notifications::Notification,
+            crate::notifications::NotificationKind,
+            routes::ImportMultisigRequest,
+            routes::MultisigAddressRequest,
+            routes::SignMultisigPsbtRequest,
+            crate::bitcoin::multisig::MultisigWallet,
+            crate::bitcoin::multisig::MultisigAddress,
+            crate::bitcoin::multisig::SignedMultisigPsbt,
+            routes::CreatePassphraseProfileRequest,
+            routes::SelectPassphraseProfileRequest,
+            crate::passphrase_profiles::PassphraseProfile,
+            crate::passphrase_profiles::ActiveProfile,
+            crate::bitcoin::address_watch::WatchRequest,
+            crate::bitcoin::address_watch::AddressWatch,
+            crate::bitcoin::address_watch::WatchStatus,
+            crate::bitcoin::address_watch::WatchEvent,
+            routes::BumpFeeRequest,
+            crate::bitcoin::send::FeeBumpResult,
+            crate::bitcoin::chain_providers::FeePercentiles,
+            crate::bitcoin::chain_providers::RecommendedFees,
+            crate::bitcoin::chain_providers::ProjectedBlock,
+            crate::bitcoin::chain_providers::TxConfirmation,
+            routes::ChainHeightResponse,
+            routes::ChainBroadcastRequest,
+            routes::ChainBroadcastResponse,
+            crate::bitcoin::broadcast::ProviderBroadcast,
             // Context schemas - commented out until needed
             // context::DeviceContext,
             // context::ContextResponse,
@@ -47,6 +111,12 @@ pub struct ServerState {
     tags(
         (name = "system", description = "System health and status endpoints"),
         (name = "device", description = "Device management endpoints"),
+        (name = "notifications", description = "Stored user-facing notifications"),
+        (name = "multisig", description = "Multisig wallets the device cosigns"),
+        (name = "passphrase", description = "Named passphrase profiles for hidden wallets"),
+        (name = "address-watch", description = "Temporary payment watches on single addresses"),
+        (name = "transactions", description = "Fee bumping for unconfirmed sends"),
+        (name = "chain", description = "Fees, broadcasts and confirmations from the configured mempool.space instance, with Electrum and Blockstream as broadcast fallbacks"),
         (name = "mcp", description = "Model Context Protocol endpoints")
     ),
     info(
@@ -57,7 +127,10 @@ pub struct ServerState {
 )]
 struct ApiDoc;
 
-pub async fn start_server(device_queue_manager: crate::commands::DeviceQueueManager) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn start_server(
+    device_queue_manager: crate::commands::DeviceQueueManager,
+    app: tauri::AppHandle,
+) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing if not already done
     if std::env::var("RUST_LOG").is_err() {
         std::env::set_var("RUST_LOG", "vault_v2=info,axum=info");
@@ -69,6 +142,8 @@ pub async fn start_server(device_queue_manager: crate::commands::DeviceQueueMana
     // Create server state
     let server_state = Arc::new(ServerState {
         device_queue_manager,
+        events: ws::event_stream(&app),
+        app,
     });
     
     // Create Swagger UI
@@ -79,6 +154,10 @@ pub async fn start_server(device_queue_manager: crate::commands::DeviceQueueMana
     let app = Router::new()
         // System endpoints
         .route("/api/health", get(routes::health_check))
+        .route("/api/version", get(routes::api_version))
+        .route("/api/metrics", get(routes::api_get_metrics))
+        .route("/api/notifications", get(routes::api_list_notifications))
+        .route("/api/notifications/:id/read", post(routes::api_mark_notification_read))
         
         // Add compatibility route for Pioneer SDK kkapi detection
         .route("/spec/swagger.json", get(|| async move {
@@ -92,7 +171,30 @@ pub async fn start_server(device_queue_manager: crate::commands::DeviceQueueMana
         
         // Device management endpoints
         .route("/api/devices", get(routes::api_list_devices))
+        .route("/ws", get(ws::ws_handler))
         .route("/system/info/get-features", post(routes::api_get_features))
+        .route("/api/devices/:device_id/diagnostics", get(routes::api_device_diagnostics))
+        .route("/api/protocol-trace", get(routes::api_protocol_trace))
+
+        // Multisig wallets
+        .route("/api/v2/multisig/wallets", get(routes::api_list_multisig).post(routes::api_import_multisig))
+        .route("/api/v2/multisig/address", post(routes::api_multisig_address))
+        .route("/api/v2/multisig/sign-psbt", post(routes::api_sign_multisig_psbt))
+
+        // Passphrase profiles
+        .route("/api/v2/passphrase-profiles", get(routes::api_list_passphrase_profiles).post(routes::api_create_passphrase_profile))
+        .route("/api/v2/passphrase-profiles/select", post(routes::api_select_passphrase_profile))
+        .route("/api/v2/passphrase-profiles/:id", delete(routes::api_delete_passphrase_profile))
+
+        // Address watches for integrations
+        .route("/api/v2/address-watch", get(routes::api_list_address_watches).post(routes::api_create_address_watch))
+        .route("/api/v2/address-watch/:id", get(routes::api_get_address_watch).delete(routes::api_cancel_address_watch))
+        .route("/api/v2/transactions/bump-fee", post(routes::api_bump_fee))
+        .route("/api/v2/chain/fees", get(routes::api_chain_fees))
+        .route("/api/v2/chain/height", get(routes::api_chain_height))
+        .route("/api/v2/chain/tx/:txid", get(routes::api_chain_transaction))
+        .route("/api/v2/chain/broadcast", post(routes::api_chain_broadcast))
+        .route("/api/v2/tx/broadcast", post(routes::api_broadcast_transaction))
         
         // MCP endpoint - Model Context Protocol
         .route("/mcp", post(routes::mcp_handle))
@@ -127,7 +229,9 @@ pub async fn start_server(device_queue_manager: crate::commands::DeviceQueueMana
     info!("  📋 REST API: http://{}/api", addr);
     info!("  🌍 Proxy: http://{} -> keepkey.com", proxy_addr);
     info!("  📚 API Documentation: http://{}/docs", addr);
+    debug!("  📈 Queue Metrics: http://{}/api/metrics", addr);
     debug!("  🔌 Device Management: http://{}/api/devices", addr);
+    debug!("  📡 Device Events: ws://{}/ws", addr);
     debug!("  🤖 MCP Endpoint: http://{}/mcp", addr);
     debug!("  📄 Swagger JSON: http://{}/spec/swagger.json", addr);
     
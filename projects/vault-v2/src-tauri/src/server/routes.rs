@@ -1,6 +1,6 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     Json,
     response::IntoResponse,
 };
@@ -44,6 +44,8 @@ pub struct KeepKeyInfo {
     pub bootloader_version: Option<String>,
     pub initialized: bool,
     pub bootloader_mode: bool,
+    /// Clone/tamper check; anything other than `genuine` should be shown prominently
+    pub authenticity: crate::device::authenticity::AuthenticityCheck,
 }
 
 // SDK compatible Features structure
@@ -72,6 +74,23 @@ pub struct Features {
     pub no_backup: Option<bool>,
 }
 
+/// Name a REST caller is queued under on the device, so the worker can take
+/// turns between callers and the app's own UI. Keys are shortened so they
+/// don't end up verbatim in /api/metrics.
+fn queue_client(headers: &HeaderMap) -> String {
+    let header_str = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+    let api_key = header_str("x-api-key").or_else(|| {
+        header_str(header::AUTHORIZATION.as_str()).and_then(|v| v.strip_prefix("Bearer "))
+    });
+    if let Some(key) = api_key.filter(|k| !k.is_empty()) {
+        return format!("key:{}", key.chars().take(8).collect::<String>());
+    }
+    match header_str(header::ORIGIN.as_str()) {
+        Some(origin) if !origin.is_empty() => format!("origin:{}", origin),
+        _ => "rest".to_string(),
+    }
+}
+
 /// Health check endpoint
 #[utoipa::path(
     get,
@@ -88,6 +107,18 @@ pub async fn health_check() -> Json<HealthResponse> {
     })
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/version",
+    responses(
+        (status = 200, description = "Version, commit, features and firmware range of this backend", body = crate::build_info::BuildInfo)
+    ),
+    tag = "system"
+)]
+pub async fn api_version() -> Json<crate::build_info::BuildInfo> {
+    Json(crate::build_info::build_info())
+}
+
 /// Get device context
 #[utoipa::path(
     get,
@@ -132,6 +163,89 @@ pub async fn api_clear_context() -> StatusCode {
 }
 
 /// List connected devices
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueMetricsResponse {
+    pub slow_request_threshold_ms: u64,
+    /// Per-device queue metrics, including latency histograms per message type and
+    /// per-client served/starved counters
+    #[schema(value_type = Object)]
+    pub devices: std::collections::HashMap<String, Value>,
+}
+
+/// Device queue metrics
+#[utoipa::path(
+    get,
+    path = "/api/metrics",
+    responses(
+        (status = 200, description = "Queue latency histograms and per-client counters per device", body = QueueMetricsResponse),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "system"
+)]
+pub async fn api_get_metrics(State(state): State<Arc<ServerState>>) -> Result<Json<QueueMetricsResponse>, StatusCode> {
+    let manager = state.device_queue_manager.lock().await;
+    let mut devices = std::collections::HashMap::new();
+    for (device_id, handle) in manager.iter() {
+        let metrics = serde_json::to_value(handle.metrics()).map_err(|e| {
+            error!("Failed to serialize metrics for device {}: {}", device_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        devices.insert(device_id.clone(), metrics);
+    }
+    
+    Ok(Json(QueueMetricsResponse {
+        slow_request_threshold_ms: keepkey_rust::device_queue::slow_request_threshold().as_millis() as u64,
+        devices,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationQuery {
+    #[serde(default)]
+    pub unread: bool,
+}
+
+/// Notifications stored by the vault, newest first
+#[utoipa::path(
+    get,
+    path = "/api/notifications",
+    params(("unread" = Option<bool>, Query, description = "Only unread notifications")),
+    responses(
+        (status = 200, description = "Stored notifications", body = Vec<crate::notifications::Notification>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "notifications"
+)]
+pub async fn api_list_notifications(Query(query): Query<NotificationQuery>) -> Result<Json<Vec<crate::notifications::Notification>>, StatusCode> {
+    crate::notifications::list(query.unread).map(Json).map_err(|e| {
+        error!("Failed to list notifications: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/notifications/{id}/read",
+    params(("id" = String, Path, description = "Notification id")),
+    responses(
+        (status = 204, description = "Marked as read"),
+        (status = 404, description = "No notification with this id"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "notifications"
+)]
+pub async fn api_mark_notification_read(Path(id): Path<String>) -> StatusCode {
+    match crate::notifications::mark_read(Some(&id)) {
+        Ok(Some(_)) => StatusCode::NO_CONTENT,
+        Ok(None) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to mark notification {} read: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/api/devices",
@@ -141,7 +255,7 @@ pub async fn api_clear_context() -> StatusCode {
     ),
     tag = "device"
 )]
-pub async fn api_list_devices(State(state): State<Arc<ServerState>>) -> Result<Json<Vec<DeviceInfo>>, StatusCode> {
+pub async fn api_list_devices(State(state): State<Arc<ServerState>>, headers: HeaderMap) -> Result<Json<Vec<DeviceInfo>>, StatusCode> {
     // List connected devices (direct access for enumeration is OK)
     let devices = keepkey_rust::features::list_connected_devices();
     
@@ -166,7 +280,8 @@ pub async fn api_list_devices(State(state): State<Arc<ServerState>>) -> Result<J
                 manager.insert(device.unique_id.clone(), handle.clone());
                 handle
             }
-        };
+        }
+        .for_client(queue_client(&headers));
         
         // Try to get features through the queue (non-blocking, with timeout)
         let keepkey_info = match tokio::time::timeout(
@@ -175,15 +290,20 @@ pub async fn api_list_devices(State(state): State<Arc<ServerState>>) -> Result<J
         ).await {
             Ok(Ok(raw_features)) => {
                 let features = crate::commands::convert_features_to_device_features(raw_features);
+                let authenticity = crate::device::authenticity::check_device_authenticity(&features);
+                if authenticity.needs_acknowledgment() {
+                    warn!("Device {} failed authenticity check: {}", device.unique_id, authenticity.warnings.join("; "));
+                }
                 Some(KeepKeyInfo {
                     label: features.label.clone(),
                     device_id: features.device_id.clone(),
                     firmware_version: features.version.clone(),
                     revision: features.firmware_hash.clone(),
                     bootloader_hash: features.bootloader_hash.clone(),
-                    bootloader_version: None,
+                    bootloader_version: authenticity.bootloader_release.clone(),
                     initialized: features.initialized,
                     bootloader_mode: features.bootloader_mode,
+                    authenticity,
                 })
             }
             Ok(Err(e)) => {
@@ -213,6 +333,140 @@ pub async fn api_list_devices(State(state): State<Arc<ServerState>>) -> Result<J
     Ok(Json(device_infos))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DiagnosticsQuery {
+    pub request_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// A line the firmware wrote to its DEBUG_LINK interface
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceDiagnostic {
+    /// Id of the vault request the device was serving, as in the device log
+    pub request_id: Option<String>,
+    /// Queue operation, e.g. `send_raw:SignTx`
+    pub operation: String,
+    pub level: u32,
+    pub bucket: String,
+    pub text: String,
+    /// Milliseconds since the Unix epoch
+    pub received_at: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceDiagnosticsResponse {
+    pub device_id: String,
+    /// Whether diagnostics are being collected; only debug firmware has DEBUG_LINK
+    pub collecting: bool,
+    /// Oldest first
+    pub diagnostics: Vec<DeviceDiagnostic>,
+}
+
+/// Recent diagnostics from the device firmware
+#[utoipa::path(
+    get,
+    path = "/api/devices/{device_id}/diagnostics",
+    params(
+        ("device_id" = String, Path, description = "Device id"),
+        ("request_id" = Option<String>, Query, description = "Only lines read while serving this request"),
+        ("limit" = Option<usize>, Query, description = "Most recent lines to return (default 200)")
+    ),
+    responses(
+        (status = 200, description = "Firmware log lines, tagged with the request they belong to", body = DeviceDiagnosticsResponse)
+    ),
+    tag = "device"
+)]
+pub async fn api_device_diagnostics(
+    Path(device_id): Path<String>,
+    Query(query): Query<DiagnosticsQuery>,
+) -> Json<DeviceDiagnosticsResponse> {
+    let diagnostics = keepkey_rust::diagnostics::recent(Some(&device_id), query.request_id.as_deref(), query.limit.unwrap_or(200))
+        .into_iter()
+        .map(|line| DeviceDiagnostic {
+            request_id: line.request_id,
+            operation: line.operation,
+            level: line.level,
+            bucket: line.bucket,
+            text: line.text,
+            received_at: line.received_at,
+        })
+        .collect();
+    Json(DeviceDiagnosticsResponse {
+        device_id,
+        collecting: keepkey_rust::diagnostics::collection_enabled(),
+        diagnostics,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProtocolTraceQuery {
+    pub since: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+/// A message written to or read from a device
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProtocolTraceEntry {
+    /// Increases by one per entry; pass the last one seen as `since`
+    pub seq: u64,
+    /// `to_device` or `from_device`
+    pub direction: String,
+    pub message_type: String,
+    /// Size of the whole frame in bytes
+    pub len: usize,
+    /// Hex of the start of the frame; empty for messages carrying secrets
+    pub frame: String,
+    pub truncated: bool,
+    /// For replies, milliseconds since the request went out
+    pub elapsed_ms: Option<u64>,
+    /// Milliseconds since the Unix epoch
+    pub at: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProtocolTraceResponse {
+    /// Whether messages are being recorded (the `protocolTrace` preference)
+    pub tracing: bool,
+    /// Oldest first
+    pub entries: Vec<ProtocolTraceEntry>,
+}
+
+/// Messages recently exchanged with devices, for bug reports
+#[utoipa::path(
+    get,
+    path = "/api/protocol-trace",
+    params(
+        ("since" = Option<u64>, Query, description = "Only entries after this seq"),
+        ("limit" = Option<usize>, Query, description = "Most recent entries to return (default 200)")
+    ),
+    responses(
+        (status = 200, description = "Traced messages across all devices", body = ProtocolTraceResponse)
+    ),
+    tag = "device"
+)]
+pub async fn api_protocol_trace(Query(query): Query<ProtocolTraceQuery>) -> Json<ProtocolTraceResponse> {
+    use keepkey_rust::protocol_trace::{self, TraceDirection};
+
+    let entries = protocol_trace::entries(query.since, query.limit.unwrap_or(200))
+        .into_iter()
+        .map(|entry| ProtocolTraceEntry {
+            seq: entry.seq,
+            direction: match entry.direction {
+                TraceDirection::ToDevice => "to_device",
+                TraceDirection::FromDevice => "from_device",
+            }
+            .to_string(),
+            message_type: entry.message_type,
+            len: entry.len,
+            frame: entry.frame,
+            truncated: entry.truncated,
+            elapsed_ms: entry.elapsed_ms,
+            at: entry.at,
+        })
+        .collect();
+    Json(ProtocolTraceResponse { tracing: protocol_trace::tracing_enabled(), entries })
+}
+
 /// Get device features (SDK compatible format)
 #[utoipa::path(
     post,
@@ -225,7 +479,7 @@ pub async fn api_list_devices(State(state): State<Arc<ServerState>>) -> Result<J
     ),
     tag = "device"
 )]
-pub async fn api_get_features(State(state): State<Arc<ServerState>>) -> Result<Json<Features>, StatusCode> {
+pub async fn api_get_features(State(state): State<Arc<ServerState>>, headers: HeaderMap) -> Result<Json<Features>, StatusCode> {
     // Get the current device context or default to first available device
     let devices = keepkey_rust::features::list_connected_devices();
     
@@ -271,7 +525,8 @@ pub async fn api_get_features(State(state): State<Arc<ServerState>>) -> Result<J
             manager.insert(device_id.clone(), handle.clone());
             handle
         }
-    };
+    }
+    .for_client(queue_client(&headers));
     
     // Get device features through the queue
     match queue_handle.get_features().await {
@@ -318,6 +573,471 @@ pub async fn api_get_features(State(state): State<Arc<ServerState>>) -> Result<J
     }
 }
 
+// Multisig wallets the device cosigns
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportMultisigRequest {
+    pub device_id: String,
+    /// `wsh(sortedmulti(...))` descriptor with key origins
+    pub descriptor: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigAddressRequest {
+    pub device_id: String,
+    pub wallet_id: String,
+    pub index: u32,
+    #[serde(default)]
+    pub change: bool,
+    #[serde(default)]
+    pub show_display: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignMultisigPsbtRequest {
+    pub device_id: String,
+    pub wallet_id: String,
+    /// Base64 PSBT
+    pub psbt: String,
+}
+
+async fn multisig_queue_handle(
+    state: &ServerState,
+    headers: &HeaderMap,
+    device_id: &str,
+) -> Result<keepkey_rust::device_queue::DeviceQueueHandle, (StatusCode, String)> {
+    let mut manager = state.device_queue_manager.lock().await;
+    crate::bitcoin::accounts::queue_handle(device_id, &mut manager)
+        .map(|handle| handle.for_client(queue_client(headers)))
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+fn multisig_rejected(e: String) -> (StatusCode, String) {
+    warn!("Multisig request rejected: {}", e);
+    (StatusCode::BAD_REQUEST, e)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/multisig/wallets",
+    request_body = ImportMultisigRequest,
+    responses(
+        (status = 200, description = "Descriptor checked against the device and stored", body = crate::bitcoin::multisig::MultisigWallet),
+        (status = 400, description = "Invalid descriptor, or the device is not a cosigner"),
+        (status = 404, description = "Device not found")
+    ),
+    tag = "multisig"
+)]
+pub async fn api_import_multisig(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<ImportMultisigRequest>,
+) -> Result<Json<crate::bitcoin::multisig::MultisigWallet>, (StatusCode, String)> {
+    let handle = multisig_queue_handle(&state, &headers, &request.device_id).await?;
+    crate::bitcoin::multisig::import_wallet(&handle, &request.descriptor, request.name)
+        .await
+        .map(Json)
+        .map_err(multisig_rejected)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/multisig/wallets",
+    responses(
+        (status = 200, description = "Imported multisig wallets", body = Vec<crate::bitcoin::multisig::MultisigWallet>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "multisig"
+)]
+pub async fn api_list_multisig() -> Result<Json<Vec<crate::bitcoin::multisig::MultisigWallet>>, StatusCode> {
+    crate::bitcoin::multisig::list_wallets().map(Json).map_err(|e| {
+        error!("Failed to list multisig wallets: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/multisig/address",
+    request_body = MultisigAddressRequest,
+    responses(
+        (status = 200, description = "Address derived locally and confirmed by the device", body = crate::bitcoin::multisig::MultisigAddress),
+        (status = 400, description = "Unknown wallet, or the device derived a different address"),
+        (status = 404, description = "Device not found")
+    ),
+    tag = "multisig"
+)]
+pub async fn api_multisig_address(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<MultisigAddressRequest>,
+) -> Result<Json<crate::bitcoin::multisig::MultisigAddress>, (StatusCode, String)> {
+    let handle = multisig_queue_handle(&state, &headers, &request.device_id).await?;
+    crate::bitcoin::multisig::wallet_address(&handle, &request.wallet_id, request.index, request.change, request.show_display)
+        .await
+        .map(Json)
+        .map_err(multisig_rejected)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/multisig/sign-psbt",
+    request_body = SignMultisigPsbtRequest,
+    responses(
+        (status = 200, description = "PSBT with the device's signatures added", body = crate::bitcoin::multisig::SignedMultisigPsbt),
+        (status = 400, description = "Unknown wallet, invalid PSBT, or signing refused"),
+        (status = 404, description = "Device not found")
+    ),
+    tag = "multisig"
+)]
+pub async fn api_sign_multisig_psbt(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<SignMultisigPsbtRequest>,
+) -> Result<Json<crate::bitcoin::multisig::SignedMultisigPsbt>, (StatusCode, String)> {
+    let handle = multisig_queue_handle(&state, &headers, &request.device_id).await?;
+    crate::bitcoin::multisig::sign_psbt(&handle, &request.wallet_id, &request.psbt)
+        .await
+        .map(Json)
+        .map_err(multisig_rejected)
+}
+
+// Passphrase profiles (hidden wallets)
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePassphraseProfileRequest {
+    pub device_id: String,
+    pub name: String,
+    pub passphrase: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectPassphraseProfileRequest {
+    pub profile_id: String,
+    pub passphrase: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PassphraseProfileQuery {
+    pub device_id: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/passphrase-profiles",
+    params(("deviceId" = Option<String>, Query, description = "Only this device's profiles")),
+    responses(
+        (status = 200, description = "Passphrase profiles (names and bound fingerprints only)", body = Vec<crate::passphrase_profiles::PassphraseProfile>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "passphrase"
+)]
+pub async fn api_list_passphrase_profiles(
+    Query(query): Query<PassphraseProfileQuery>,
+) -> Result<Json<Vec<crate::passphrase_profiles::PassphraseProfile>>, StatusCode> {
+    crate::passphrase_profiles::list_profiles(query.device_id.as_deref()).map(Json).map_err(|e| {
+        error!("Failed to list passphrase profiles: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/passphrase-profiles",
+    request_body = CreatePassphraseProfileRequest,
+    responses(
+        (status = 200, description = "Profile created", body = crate::passphrase_profiles::PassphraseProfile),
+        (status = 400, description = "Missing name or passphrase, or a duplicate profile")
+    ),
+    tag = "passphrase"
+)]
+pub async fn api_create_passphrase_profile(
+    Json(request): Json<CreatePassphraseProfileRequest>,
+) -> Result<Json<crate::passphrase_profiles::PassphraseProfile>, (StatusCode, String)> {
+    crate::passphrase_profiles::create_profile(&request.device_id, &request.name, &request.passphrase)
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v2/passphrase-profiles/{id}",
+    params(("id" = String, Path, description = "Profile id")),
+    responses(
+        (status = 204, description = "Profile deleted"),
+        (status = 404, description = "No profile with this id"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "passphrase"
+)]
+pub async fn api_delete_passphrase_profile(Path(id): Path<String>) -> StatusCode {
+    match crate::passphrase_profiles::delete_profile(&id) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to delete passphrase profile {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/passphrase-profiles/select",
+    request_body = SelectPassphraseProfileRequest,
+    responses(
+        (status = 200, description = "Profile's wallet unlocked on the device", body = crate::passphrase_profiles::ActiveProfile),
+        (status = 400, description = "Wrong passphrase, locked device, or a different wallet than the profile's")
+    ),
+    tag = "passphrase"
+)]
+pub async fn api_select_passphrase_profile(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<SelectPassphraseProfileRequest>,
+) -> Result<Json<crate::passphrase_profiles::ActiveProfile>, (StatusCode, String)> {
+    let handle = crate::passphrase_profiles::profile_queue_handle(&request.profile_id, &state.device_queue_manager)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e))?
+        .for_client(queue_client(&headers));
+    crate::passphrase_profiles::select_profile(&handle, &request.profile_id, &request.passphrase)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            warn!("Passphrase profile selection refused: {}", e);
+            (StatusCode::BAD_REQUEST, e)
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/address-watch",
+    request_body = crate::bitcoin::address_watch::WatchRequest,
+    responses(
+        (status = 200, description = "Watch registered; events go to the callback URL if one is given", body = crate::bitcoin::address_watch::AddressWatch),
+        (status = 400, description = "Invalid address, TTL, confirmations or callback URL, or too many watches")
+    ),
+    tag = "address-watch"
+)]
+pub async fn api_create_address_watch(
+    headers: HeaderMap,
+    Json(request): Json<crate::bitcoin::address_watch::WatchRequest>,
+) -> Result<Json<crate::bitcoin::address_watch::AddressWatch>, (StatusCode, String)> {
+    crate::bitcoin::address_watch::register(&queue_client(&headers), request)
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/address-watch",
+    responses(
+        (status = 200, description = "The caller's watches, including ones finished in the last 10 minutes", body = Vec<crate::bitcoin::address_watch::AddressWatch>)
+    ),
+    tag = "address-watch"
+)]
+pub async fn api_list_address_watches(headers: HeaderMap) -> Json<Vec<crate::bitcoin::address_watch::AddressWatch>> {
+    Json(crate::bitcoin::address_watch::list(&queue_client(&headers)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/address-watch/{id}",
+    params(("id" = String, Path, description = "Watch id")),
+    responses(
+        (status = 200, description = "Current state of the watch", body = crate::bitcoin::address_watch::AddressWatch),
+        (status = 404, description = "No such watch for this caller, or it finished and was dropped")
+    ),
+    tag = "address-watch"
+)]
+pub async fn api_get_address_watch(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<crate::bitcoin::address_watch::AddressWatch>, StatusCode> {
+    crate::bitcoin::address_watch::get(&queue_client(&headers), &id)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v2/address-watch/{id}",
+    params(("id" = String, Path, description = "Watch id")),
+    responses(
+        (status = 204, description = "Watch cancelled"),
+        (status = 404, description = "No such watch for this caller")
+    ),
+    tag = "address-watch"
+)]
+pub async fn api_cancel_address_watch(headers: HeaderMap, Path(id): Path<String>) -> StatusCode {
+    if crate::bitcoin::address_watch::cancel(&queue_client(&headers), &id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+// Fee bumping
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BumpFeeRequest {
+    pub device_id: String,
+    /// Unconfirmed send from this vault to replace
+    pub txid: String,
+    /// New sat/vB; defaults to the fastest estimate, and never less than the
+    /// original's rate plus 1 sat/vB
+    pub fee_rate: Option<u64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/transactions/bump-fee",
+    request_body = BumpFeeRequest,
+    responses(
+        (status = 200, description = "Replacement signed on the device and broadcast", body = crate::bitcoin::send::FeeBumpResult),
+        (status = 400, description = "Unknown or confirmed transaction, fee too low, no change to pay from, or signing refused")
+    ),
+    tag = "transactions"
+)]
+pub async fn api_bump_fee(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<BumpFeeRequest>,
+) -> Result<Json<crate::bitcoin::send::FeeBumpResult>, (StatusCode, String)> {
+    let fee = request
+        .fee_rate
+        .map(crate::bitcoin::fees::FeePreference::Custom)
+        .unwrap_or_default();
+    crate::bitcoin::send::bump_transaction(&state.app, &request.device_id, request.txid.trim(), fee)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            warn!("Fee bump for {} refused: {}", request.txid, e);
+            (StatusCode::BAD_REQUEST, e)
+        })
+}
+
+// Chain data
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChainHeightResponse {
+    pub height: u64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainBroadcastRequest {
+    /// Signed transaction, hex encoded
+    pub raw_tx: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChainBroadcastResponse {
+    pub txid: String,
+}
+
+fn chain_error(e: String) -> (StatusCode, String) {
+    warn!("Chain request failed: {}", e);
+    if e.starts_with("Invalid txid") || e.starts_with("Transaction must") || e.starts_with("Broadcast rejected") {
+        (StatusCode::BAD_REQUEST, e)
+    } else {
+        (StatusCode::BAD_GATEWAY, e)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/chain/fees",
+    responses(
+        (status = 200, description = "Recommended rates and fee percentiles of the next projected blocks", body = crate::bitcoin::chain_providers::FeePercentiles),
+        (status = 502, description = "The mempool instance could not be reached")
+    ),
+    tag = "chain"
+)]
+pub async fn api_chain_fees() -> Result<Json<crate::bitcoin::chain_providers::FeePercentiles>, (StatusCode, String)> {
+    crate::bitcoin::chain_providers::get_fee_rates().await.map(Json).map_err(chain_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/chain/height",
+    responses(
+        (status = 200, description = "Height of the best block", body = ChainHeightResponse),
+        (status = 502, description = "The mempool instance could not be reached")
+    ),
+    tag = "chain"
+)]
+pub async fn api_chain_height() -> Result<Json<ChainHeightResponse>, (StatusCode, String)> {
+    crate::bitcoin::chain_providers::get_block_height()
+        .await
+        .map(|height| Json(ChainHeightResponse { height }))
+        .map_err(chain_error)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/chain/tx/{txid}",
+    params(("txid" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "Confirmation status of the transaction", body = crate::bitcoin::chain_providers::TxConfirmation),
+        (status = 400, description = "Invalid txid"),
+        (status = 502, description = "Unknown transaction, or the mempool instance could not be reached")
+    ),
+    tag = "chain"
+)]
+pub async fn api_chain_transaction(
+    Path(txid): Path<String>,
+) -> Result<Json<crate::bitcoin::chain_providers::TxConfirmation>, (StatusCode, String)> {
+    crate::bitcoin::chain_providers::confirmation(txid.trim()).await.map(Json).map_err(chain_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/chain/broadcast",
+    request_body = ChainBroadcastRequest,
+    responses(
+        (status = 200, description = "Transaction accepted by the mempool instance", body = ChainBroadcastResponse),
+        (status = 400, description = "Not hex, or rejected by the node"),
+        (status = 502, description = "The mempool instance could not be reached")
+    ),
+    tag = "chain"
+)]
+pub async fn api_chain_broadcast(
+    Json(request): Json<ChainBroadcastRequest>,
+) -> Result<Json<ChainBroadcastResponse>, (StatusCode, String)> {
+    crate::bitcoin::chain_providers::broadcast(&request.raw_tx)
+        .await
+        .map(|txid| Json(ChainBroadcastResponse { txid }))
+        .map_err(chain_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/tx/broadcast",
+    request_body = ChainBroadcastRequest,
+    responses(
+        (status = 200, description = "Transaction accepted; names the provider that took it", body = crate::bitcoin::broadcast::ProviderBroadcast),
+        (status = 400, description = "Not hex, or rejected by the node"),
+        (status = 502, description = "No provider could be reached")
+    ),
+    tag = "chain"
+)]
+pub async fn api_broadcast_transaction(
+    Json(request): Json<ChainBroadcastRequest>,
+) -> Result<Json<crate::bitcoin::broadcast::ProviderBroadcast>, (StatusCode, String)> {
+    crate::bitcoin::broadcast::broadcast_with_fallback(&request.raw_tx)
+        .await
+        .map(Json)
+        .map_err(chain_error)
+}
+
 // MCP (Model Context Protocol) Types
 
 #[derive(Debug, Deserialize)]
@@ -358,6 +1078,7 @@ struct McpError {
 )]
 pub async fn mcp_handle(
     State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
     Json(request): Json<Value>,
 ) -> impl IntoResponse {
     info!("MCP request received: {:?}", request);
@@ -561,7 +1282,7 @@ pub async fn mcp_handle(
                             }
                         }
                         "get_device_features" => {
-                            match api_get_features(State(state.clone())).await {
+                            match api_get_features(State(state.clone()), headers.clone()).await {
                                 Ok(Json(features)) => {
                                     McpResponse {
                                         jsonrpc: "2.0".to_string(),
@@ -592,7 +1313,7 @@ pub async fn mcp_handle(
                             }
                         }
                         "list_devices" => {
-                            match api_list_devices(State(state.clone())).await {
+                            match api_list_devices(State(state.clone()), headers.clone()).await {
                                 Ok(Json(devices)) => {
                                     McpResponse {
                                         jsonrpc: "2.0".to_string(),
@@ -0,0 +1,112 @@
+//! `/ws`: device lifecycle events as JSON text frames, so integrations can
+//! follow connects, disconnects, Features changes, prompts and signing
+//! progress instead of polling `/api/devices`.
+//!
+//! Frames mirror the events the app sends its own UI:
+//!
+//! ```json
+//! {"type": "device_connected", "event": "device:connected", "payload": {...}, "timestamp": 1760000000000}
+//! ```
+//!
+//! A client that falls behind gets a `lagged` frame with the number of frames
+//! it missed; it should re-read `/api/devices` to catch up.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tauri::Listener;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use super::ServerState;
+
+/// Frames buffered per client before it counts as lagging
+const CHANNEL_SIZE: usize = 256;
+
+/// App events streamed to clients, and the frame `type` each becomes
+const STREAMED_EVENTS: &[(&str, &str)] = &[
+    ("device:connected", "device_connected"),
+    ("device:disconnected", "device_disconnected"),
+    ("device:ready", "device_ready"),
+    ("device:forgotten", "device_forgotten"),
+    ("device:features-updated", "features_updated"),
+    // The device is waiting on the user before it can be used
+    ("device:pin-unlock-needed", "blocking_action"),
+    ("device:pin-request-triggered", "blocking_action"),
+    ("device:invalid-state", "blocking_action"),
+    ("device:update-available", "blocking_action"),
+    ("device:access-error", "blocking_action"),
+    // Signing, firmware update and recovery steps
+    ("device:progress", "progress"),
+    ("signing:interrupted", "signing_interrupted"),
+    // Fee bump suggestions for sends stuck in the mempool
+    ("wallet:transaction-stuck", "fee_advice"),
+];
+
+/// Listen for the streamed app events and fan them out as frames. The
+/// listeners live as long as the app.
+pub fn event_stream(app: &tauri::AppHandle) -> broadcast::Sender<String> {
+    let (tx, _) = broadcast::channel(CHANNEL_SIZE);
+    for &(event_name, frame_type) in STREAMED_EVENTS {
+        let tx = tx.clone();
+        app.listen_any(event_name, move |event| {
+            // No receivers just means no client is connected
+            let _ = tx.send(frame(frame_type, event_name, event.payload()));
+        });
+    }
+    tx
+}
+
+fn frame(frame_type: &str, event_name: &str, payload: &str) -> String {
+    let payload: Value = serde_json::from_str(payload).unwrap_or(Value::Null);
+    json!({
+        "type": frame_type,
+        "event": event_name,
+        "payload": payload,
+        "timestamp": chrono::Utc::now().timestamp_millis(),
+    })
+    .to_string()
+}
+
+/// Upgrade to a WebSocket that streams device events
+pub async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<ServerState>>) -> Response {
+    let events = state.events.subscribe();
+    ws.on_upgrade(move |socket| stream(socket, events))
+}
+
+async fn stream(mut socket: WebSocket, mut events: broadcast::Receiver<String>) {
+    let types: Vec<&str> = STREAMED_EVENTS.iter().map(|(_, frame_type)| *frame_type).collect();
+    let hello = json!({ "type": "hello", "types": types }).to_string();
+    if socket.send(Message::Text(hello)).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let text = match event {
+                    Ok(text) => text,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        json!({ "type": "lagged", "skipped": skipped }).to_string()
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                // Clients only listen; pings are answered by the socket itself
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    debug!("WebSocket event client disconnected");
+}
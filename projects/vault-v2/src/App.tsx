@@ -14,6 +14,7 @@ import { useOnboardingState } from './hooks/useOnboardingState';
 import { VaultInterface } from './components/VaultInterface';
 import { useWallet } from './contexts/WalletContext';
 import { DialogProvider, useDialog } from './contexts/DialogContext'
+import { startEventDelivery } from './services/eventDelivery';
 
 // Define the expected structure of DeviceFeatures from Rust
 interface DeviceFeatures {
@@ -158,6 +159,7 @@ function App() {
                 setTimeout(async () => {
                     try {
                         console.log('🎯 Re-signaling backend that frontend is ready after restart...');
+                        await startEventDelivery();
                         await invoke('frontend_ready');
                         console.log('✅ Frontend ready signal sent successfully after restart');
                     } catch (error) {
@@ -247,6 +249,7 @@ function App() {
                     // Signal backend that frontend is ready to receive events FIRST
                     try {
                         console.log('🎯 Signaling backend that frontend is ready...');
+                        await startEventDelivery();
                         await invoke('frontend_ready');
                         console.log('✅ Frontend ready signal sent successfully');
                     } catch (error) {
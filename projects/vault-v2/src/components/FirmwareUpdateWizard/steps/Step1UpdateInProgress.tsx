@@ -1,8 +1,22 @@
-import React, { useEffect, useState } from 'react';
+import React, { useEffect, useRef, useState } from 'react';
 import { Box, VStack, Text, Spinner, Icon } from '@chakra-ui/react';
 import { FaCog } from 'react-icons/fa';
 import { StepProps } from '../FirmwareUpdateWizard';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import type { DeviceProgress, UpdateStage } from '../../../types';
+
+/** Overall progress shown when each update stage starts */
+const STAGE_PROGRESS: Record<UpdateStage, { value: number; message: string }> = {
+  downloading: { value: 5, message: 'Downloading firmware...' },
+  verifying_hash: { value: 10, message: 'Validating firmware package...' },
+  erasing: { value: 20, message: 'Erasing old firmware... Please confirm on your device.' },
+  uploading: { value: 30, message: 'Uploading firmware to device...' },
+  rebooting: { value: 85, message: 'Firmware update successful! Device is restarting...' },
+  verifying: { value: 90, message: 'Verifying update...' },
+  finished: { value: 100, message: 'Firmware update complete' },
+  failed: { value: 100, message: 'Firmware update failed' },
+};
 
 export const Step1UpdateInProgress: React.FC<StepProps> = ({
   deviceId,
@@ -16,6 +30,34 @@ export const Step1UpdateInProgress: React.FC<StepProps> = ({
   const [isUpdating, setIsUpdating] = useState(false);
   const [hasStarted, setHasStarted] = useState(false);
   const [hasCompleted, setHasCompleted] = useState(false); // Prevent multiple completions
+  // Resolved by the `finished`/`failed` event sent once the rebooted device is checked
+  const verifiedRef = useRef<((failure: string | null) => void) | null>(null);
+
+  // Follow the real update stages reported by the device queue
+  useEffect(() => {
+    const unlisten = listen<DeviceProgress>('device:progress', (event) => {
+      const { device_id, operation, stage, current, total, message } = event.payload;
+      if (device_id !== deviceId || operation !== 'firmware_update') return;
+      const step = STAGE_PROGRESS[stage as UpdateStage];
+      if (!step) return;
+
+      let { value, message: status } = step;
+      if (stage === 'uploading' && total > 0) {
+        // Uploading spans 30-80%
+        value = 30 + Math.round((current / total) * 50);
+        status = `Uploading firmware to device... ${Math.round((current / total) * 100)}%`;
+      }
+      setCurrentProgressValue(value);
+      setStatusMessage(status);
+      onSetProgress?.({ value, message: status });
+
+      if (stage === 'finished') verifiedRef.current?.(null);
+      if (stage === 'failed') verifiedRef.current?.(message || step.message);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [deviceId]);
 
   useEffect(() => {
     // Prevent multiple simultaneous updates or completions
@@ -29,7 +71,7 @@ export const Step1UpdateInProgress: React.FC<StepProps> = ({
         setHasStarted(true);
         setIsUpdating(true);
         
-        // Progress simulation for visual feedback
+        // Progress until the device queue reports its own stages
         const updateProgress = (value: number, message: string) => {
           setCurrentProgressValue(value);
           setStatusMessage(message);
@@ -38,17 +80,12 @@ export const Step1UpdateInProgress: React.FC<StepProps> = ({
           }
         };
         
-        updateProgress(10, 'Verifying device connection...');
-        await new Promise(resolve => setTimeout(resolve, 1000));
-        
-        updateProgress(20, 'Validating firmware package...');
-        await new Promise(resolve => setTimeout(resolve, 1500));
+        updateProgress(5, 'Preparing device for update...');
         
-        updateProgress(30, 'Preparing device for update...');
-        await new Promise(resolve => setTimeout(resolve, 1000));
-
-        // Call the REAL firmware update command
-        updateProgress(40, 'Uploading firmware to device... Please confirm on your device.');
+        // Further progress comes from the device:progress listener above
+        const verified = new Promise<string | null>(resolve => {
+          verifiedRef.current = resolve;
+        });
         
         try {
           console.log('🚀 Calling REAL update_device_firmware with deviceId:', deviceId, 'targetVersion:', targetVersion);
@@ -61,14 +98,11 @@ export const Step1UpdateInProgress: React.FC<StepProps> = ({
           console.log('✅ Real firmware update result:', result);
           
           if (result) {
-            updateProgress(80, 'Firmware update successful! Device may restart...');
-            await new Promise(resolve => setTimeout(resolve, 2000));
-            
-            updateProgress(95, 'Verifying update...');
-            await new Promise(resolve => setTimeout(resolve, 2000));
-            
-            updateProgress(100, 'Firmware update complete');
-            await new Promise(resolve => setTimeout(resolve, 1000));
+            // The backend checks the version once the device has rebooted
+            const failure = await verified;
+            if (failure) {
+              throw new Error(failure);
+            }
             
             setIsUpdating(false);
             
@@ -147,7 +181,7 @@ export const Step1UpdateInProgress: React.FC<StepProps> = ({
       </Box>
       
       {/* Show device confirmation message when needed */}
-      {currentProgressValue >= 40 && currentProgressValue < 80 && (
+      {currentProgressValue >= 20 && currentProgressValue < 30 && (
         <Box bg="blue.900" p={4} borderRadius="md" borderColor="blue.500" borderWidth="1px">
           <Text fontSize="sm" color="blue.200">
             <strong>Action Required:</strong> Please check your KeepKey device and confirm the firmware update by holding the button when prompted.
@@ -2,9 +2,10 @@ import { VStack, HStack, Box, Text, Button, Badge, Icon, Spinner, IconButton, Fl
 import { useState, useEffect } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { listen } from '@tauri-apps/api/event'
-import { FaUsb, FaDownload, FaWallet, FaShieldAlt, FaExclamationTriangle, FaTools, FaTrash, FaCheckCircle } from 'react-icons/fa'
+import { FaUsb, FaDownload, FaWallet, FaShieldAlt, FaExclamationTriangle, FaTools, FaTrash, FaCheckCircle, FaEye, FaUserSecret } from 'react-icons/fa'
 import type { DeviceFeatures, DeviceStatus } from '../types/device'
 import { useTroubleshootingWizard } from '../contexts/DialogContext'
+import { PassphraseProfiles } from './PassphraseProfiles'
 const TAG = " | KeepKeyDeviceList | "
 interface Device {
   id: string
@@ -13,6 +14,24 @@ interface Device {
   status?: DeviceStatus
 }
 
+interface AccountDescriptor {
+  name: string
+  script_type: string
+  path: string
+  xpub: string
+  slip132_xpub: string
+  check_code: string
+  receive_descriptor: string
+  change_descriptor: string
+  qr_svg?: string
+}
+
+interface DescriptorExport {
+  fingerprint: string
+  shown_on_device: boolean
+  accounts: AccountDescriptor[]
+}
+
 interface KeepKeyDeviceListProps {
   onBootloaderUpdate: (deviceId: string) => void
   onFirmwareUpdate: (deviceId: string) => void
@@ -30,6 +49,11 @@ export const KeepKeyDeviceList = ({
   const [loading, setLoading] = useState(true)
   const [wipingDevice, setWipingDevice] = useState<string | null>(null)
   const [connectStatus, setConnectStatus] = useState<string | null>(null) // NEW: connection attempt status
+  const [watchOnly, setWatchOnly] = useState<{ deviceId: string; export: DescriptorExport } | null>(null)
+  const [loadingWatchOnly, setLoadingWatchOnly] = useState<string | null>(null)
+  const [exportedPath, setExportedPath] = useState<string | null>(null)
+  const [verifyingXpubs, setVerifyingXpubs] = useState<string | null>(null)
+  const [profilesDevice, setProfilesDevice] = useState<string | null>(null)
   const troubleshootingWizard = useTroubleshootingWizard()
 
   // Listen for feature fetch retrying events from backend
@@ -124,6 +148,49 @@ export const KeepKeyDeviceList = ({
     onVerifySeed(device.id, device.features?.label || device.name)
   }
 
+  const handleShowWatchOnly = async (deviceId: string) => {
+    if (watchOnly?.deviceId === deviceId) {
+      setWatchOnly(null)
+      return
+    }
+    setLoadingWatchOnly(deviceId)
+    setExportedPath(null)
+    try {
+      const result = await invoke<DescriptorExport>('get_account_descriptors', { deviceId })
+      setWatchOnly({ deviceId, export: result })
+    } catch (error) {
+      console.error(TAG, 'Failed to load account descriptors:', error)
+      alert(`Failed to load accounts: ${error}`)
+    } finally {
+      setLoadingWatchOnly(null)
+    }
+  }
+
+  // Show each account xpub on the KeepKey so the user can compare it with the screen
+  const handleVerifyWatchOnly = async (deviceId: string) => {
+    setVerifyingXpubs(deviceId)
+    try {
+      const result = await invoke<DescriptorExport>('get_account_descriptors', { deviceId, showOnDevice: true })
+      setWatchOnly({ deviceId, export: result })
+    } catch (error) {
+      console.error(TAG, 'Failed to verify account xpubs on device:', error)
+      alert(`Failed to verify accounts on device: ${error}`)
+    } finally {
+      setVerifyingXpubs(null)
+    }
+  }
+
+  const handleExportWatchOnly = async (deviceId: string) => {
+    try {
+      const showOnDevice = watchOnly?.deviceId === deviceId && watchOnly.export.shown_on_device
+      const path = await invoke<string>('export_account_descriptors', { deviceId, showOnDevice })
+      setExportedPath(path)
+    } catch (error) {
+      console.error(TAG, 'Failed to export account descriptors:', error)
+      alert(`Failed to export accounts: ${error}`)
+    }
+  }
+
   useEffect(() => {
     // Initial load of devices
     loadDevices()
@@ -434,9 +501,128 @@ export const KeepKeyDeviceList = ({
                     </HStack>
                   </Button>
                 )}
+
+                {/* Watch-only export - xpubs and descriptors for other wallets */}
+                {device.features?.initialized && (
+                  <Button
+                    size="sm"
+                    variant="outline"
+                    onClick={() => handleShowWatchOnly(device.id)}
+                    disabled={device.features?.bootloaderMode || loadingWatchOnly === device.id}
+                    flex="1"
+                    minW="120px"
+                  >
+                    <HStack gap={1}>
+                      {loadingWatchOnly === device.id ? <Spinner size="xs" /> : <FaEye />}
+                      <Text fontSize="xs">Watch-only</Text>
+                    </HStack>
+                  </Button>
+                )}
+
+                {/* Hidden wallets - named passphrase profiles */}
+                {device.features?.initialized && device.features?.passphraseProtection && (
+                  <Button
+                    size="sm"
+                    variant="outline"
+                    onClick={() => setProfilesDevice(profilesDevice === device.id ? null : device.id)}
+                    disabled={device.features?.bootloaderMode}
+                    flex="1"
+                    minW="120px"
+                  >
+                    <HStack gap={1}>
+                      <FaUserSecret />
+                      <Text fontSize="xs">Hidden wallets</Text>
+                    </HStack>
+                  </Button>
+                )}
               </Flex>
             )}
 
+            {watchOnly?.deviceId === device.id && (
+              <Box p={3} bg="gray.900" borderRadius="md" borderWidth="1px" borderColor="gray.700">
+                <HStack justify="space-between" mb={2}>
+                  <Text fontSize="sm" color="gray.300">
+                    Master fingerprint: <Text as="span" fontFamily="mono">{watchOnly.export.fingerprint}</Text>
+                  </Text>
+                  <HStack gap={2}>
+                    <Button
+                      size="xs"
+                      variant="outline"
+                      onClick={() => handleVerifyWatchOnly(device.id)}
+                      disabled={verifyingXpubs === device.id}
+                    >
+                      {verifyingXpubs === device.id ? <Spinner size="xs" /> : <FaShieldAlt />}
+                      <Text ml={1}>Verify on device</Text>
+                    </Button>
+                    <Button size="xs" onClick={() => handleExportWatchOnly(device.id)}>
+                      <FaDownload />
+                      <Text ml={1}>Save to file</Text>
+                    </Button>
+                  </HStack>
+                </HStack>
+                {watchOnly.export.shown_on_device ? (
+                  <Text fontSize="xs" color="green.300" mb={2}>
+                    Shown on your KeepKey. Each xpub should end with the check code listed below.
+                  </Text>
+                ) : (
+                  <Text fontSize="xs" color="orange.300" mb={2}>
+                    Not verified on the device. Use "Verify on device" before sharing these keys.
+                  </Text>
+                )}
+                {exportedPath && (
+                  <Text fontSize="xs" color="green.300" mb={2}>Saved to {exportedPath}</Text>
+                )}
+                <VStack align="stretch" gap={4}>
+                  {watchOnly.export.accounts.map((account) => (
+                    <HStack key={account.path} align="start" gap={3}>
+                      {account.qr_svg && (
+                        <Box
+                          bg="white"
+                          p={1}
+                          borderRadius="sm"
+                          w="120px"
+                          minW="120px"
+                          dangerouslySetInnerHTML={{ __html: account.qr_svg }}
+                        />
+                      )}
+                      <VStack align="stretch" gap={1} minW={0}>
+                        <Text fontSize="sm" color="white" fontWeight="medium">
+                          {account.name} <Text as="span" color="gray.500">({account.path})</Text>
+                        </Text>
+                        <Text
+                          fontSize="xs"
+                          fontFamily="mono"
+                          color="gray.300"
+                          wordBreak="break-all"
+                          cursor="pointer"
+                          title="Click to copy"
+                          onClick={() => navigator.clipboard.writeText(account.slip132_xpub)}
+                        >
+                          {account.slip132_xpub}
+                        </Text>
+                        <Text fontSize="xs" color="gray.400">
+                          Device check code (plain xpub): <Text as="span" fontFamily="mono">{account.check_code}</Text>
+                        </Text>
+                        <Text
+                          fontSize="xs"
+                          fontFamily="mono"
+                          color="gray.500"
+                          wordBreak="break-all"
+                          cursor="pointer"
+                          title="Click to copy"
+                          onClick={() => navigator.clipboard.writeText(account.receive_descriptor)}
+                        >
+                          {account.receive_descriptor}
+                        </Text>
+                      </VStack>
+                    </HStack>
+                  ))}
+                </VStack>
+              </Box>
+            )}
+
+            {profilesDevice === device.id && <PassphraseProfiles deviceId={device.id} />}
+
             {/* Update Status */}
             {device.status && (
               <Box 
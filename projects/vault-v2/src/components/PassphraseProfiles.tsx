@@ -0,0 +1,145 @@
+import { VStack, HStack, Box, Text, Button, Input, Spinner, IconButton } from '@chakra-ui/react'
+import { useState, useEffect, useCallback } from 'react'
+import { FaTrash, FaUnlock } from 'react-icons/fa'
+import { PassphraseProfileAPI, type PassphraseProfile, type ActivePassphraseProfile } from '../lib'
+
+const TAG = " | PassphraseProfiles | "
+
+interface PassphraseProfilesProps {
+  deviceId: string
+}
+
+/**
+ * Named hidden wallets for one device. Passphrases are typed each time and
+ * checked against the profile before they reach the device.
+ */
+export const PassphraseProfiles = ({ deviceId }: PassphraseProfilesProps) => {
+  const [profiles, setProfiles] = useState<PassphraseProfile[]>([])
+  const [active, setActive] = useState<ActivePassphraseProfile | null>(null)
+  const [unlocking, setUnlocking] = useState<string | null>(null)
+  const [passphrase, setPassphrase] = useState('')
+  const [newName, setNewName] = useState('')
+  const [newPassphrase, setNewPassphrase] = useState('')
+  const [busy, setBusy] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  const reload = useCallback(async () => {
+    try {
+      setProfiles(await PassphraseProfileAPI.list(deviceId))
+      setActive(await PassphraseProfileAPI.active(deviceId))
+    } catch (e) {
+      console.error(TAG, 'Failed to load passphrase profiles:', e)
+      setError(String(e))
+    }
+  }, [deviceId])
+
+  useEffect(() => {
+    reload()
+  }, [reload])
+
+  const handleCreate = async () => {
+    setBusy(true)
+    setError(null)
+    try {
+      await PassphraseProfileAPI.create(deviceId, newName, newPassphrase)
+      setNewName('')
+      setNewPassphrase('')
+      await reload()
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setBusy(false)
+    }
+  }
+
+  const handleSelect = async (profileId: string) => {
+    setBusy(true)
+    setError(null)
+    try {
+      setActive(await PassphraseProfileAPI.select(profileId, passphrase))
+      setUnlocking(null)
+      await reload()
+    } catch (e) {
+      setError(String(e))
+    } finally {
+      setPassphrase('')
+      setBusy(false)
+    }
+  }
+
+  const handleDelete = async (profileId: string) => {
+    try {
+      await PassphraseProfileAPI.remove(profileId)
+      await reload()
+    } catch (e) {
+      setError(String(e))
+    }
+  }
+
+  return (
+    <Box p={3} bg="gray.900" borderRadius="md" borderWidth="1px" borderColor="gray.700">
+      <Text fontSize="sm" color="gray.300" mb={2}>
+        Hidden wallets{active && (
+          <> — using <Text as="span" color="green.300">{active.name}</Text> ({active.walletFingerprint})</>
+        )}
+      </Text>
+      <VStack align="stretch" gap={2}>
+        {profiles.map((profile) => (
+          <Box key={profile.id}>
+            <HStack justify="space-between">
+              <Text fontSize="sm" color="white">
+                {profile.name}
+                {profile.walletFingerprint && (
+                  <Text as="span" color="gray.500" fontFamily="mono"> {profile.walletFingerprint}</Text>
+                )}
+              </Text>
+              <HStack gap={1}>
+                <Button
+                  size="xs"
+                  variant="outline"
+                  onClick={() => { setUnlocking(profile.id); setPassphrase(''); setError(null) }}
+                  disabled={busy || active?.profileId === profile.id}
+                >
+                  <FaUnlock />
+                  <Text ml={1}>{active?.profileId === profile.id ? 'In use' : 'Use'}</Text>
+                </Button>
+                <IconButton aria-label="Delete profile" size="xs" variant="ghost" onClick={() => handleDelete(profile.id)}>
+                  <FaTrash />
+                </IconButton>
+              </HStack>
+            </HStack>
+            {unlocking === profile.id && (
+              <HStack mt={2}>
+                <Input
+                  size="sm"
+                  type="password"
+                  placeholder="Passphrase"
+                  value={passphrase}
+                  onChange={(e) => setPassphrase(e.target.value)}
+                  onKeyDown={(e) => e.key === 'Enter' && handleSelect(profile.id)}
+                />
+                <Button size="sm" onClick={() => handleSelect(profile.id)} disabled={busy || !passphrase}>
+                  {busy ? <Spinner size="xs" /> : 'Unlock'}
+                </Button>
+              </HStack>
+            )}
+          </Box>
+        ))}
+        <HStack>
+          <Input size="sm" placeholder="Profile name" value={newName} onChange={(e) => setNewName(e.target.value)} />
+          <Input
+            size="sm"
+            type="password"
+            placeholder="Passphrase"
+            value={newPassphrase}
+            onChange={(e) => setNewPassphrase(e.target.value)}
+          />
+          <Button size="sm" onClick={handleCreate} disabled={busy || !newName.trim() || !newPassphrase}>
+            Add
+          </Button>
+        </HStack>
+        {error && <Text fontSize="xs" color="red.300">{error}</Text>}
+      </VStack>
+    </Box>
+  )
+}
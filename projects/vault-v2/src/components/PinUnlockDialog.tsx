@@ -2,6 +2,7 @@ import { useState, useEffect, useCallback } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { Button, Text, HStack, Icon, VStack, Box, Spinner, SimpleGrid, Heading } from '@chakra-ui/react'
 import { FaCircle, FaExclamationTriangle, FaTimes, FaCheckCircle, FaSync, FaBackspace } from 'react-icons/fa'
+import { deviceFailureCode } from '../types/device'
 
 interface PinUnlockDialogProps {
   isOpen: boolean
@@ -175,7 +176,7 @@ export const PinUnlockDialog = ({ isOpen, deviceId, onUnlocked, onClose }: PinUn
       
       // This is a real PIN validation error - show it clearly
       const errorStr = String(err)
-      if (errorStr.toLowerCase().includes('incorrect') || errorStr.toLowerCase().includes('invalid') || errorStr.toLowerCase().includes('wrong')) {
+      if (deviceFailureCode(err) === 'pin_invalid' || errorStr.toLowerCase().includes('incorrect') || errorStr.toLowerCase().includes('invalid') || errorStr.toLowerCase().includes('wrong')) {
         setError('Incorrect PIN. Please check your device screen and try again.')
         setRetryCount(prev => prev + 1)
         
@@ -16,8 +16,7 @@ import {
 import { FaArrowLeft, FaQrcode, FaPaperPlane, FaEye, FaSignature, FaCheck, FaChevronUp, FaChevronDown } from 'react-icons/fa';
 import { SiBitcoin } from 'react-icons/si';
 import { useWallet } from '../contexts/WalletContext';
-import { PioneerAPI, DeviceQueueAPI } from '../lib/api';
-import { createUnsignedUxtoTx } from '../lib/createUnsignedUxtoTx';
+import { PioneerAPI, DeviceQueueAPI, TransactionAPI, FeePreference } from '../lib/api';
 
 interface SendPageProps {
   onBack: () => void;
@@ -38,13 +37,20 @@ interface TransactionReview {
   fee: number;
   feeRate: number;
   total: number;
-  inputs: any[];
-  outputs: any[];
-  unsignedTx: any;
+  transactionId: string;
+  inputCount: number;
+  warnings: string[];
 }
 
+// UI fee choices map onto the backend's preset levels
+const FEE_PREFERENCE: Record<'slow' | 'medium' | 'fast', FeePreference> = {
+  slow: 'slow',
+  medium: 'average',
+  fast: 'fastest',
+};
+
 const Send: React.FC<SendPageProps> = ({ onBack }) => {
-  const { portfolio, loading: walletLoading, error: walletError, selectAsset, selectedAsset, signTransaction, fetchedXpubs } = useWallet();
+  const { portfolio, loading: walletLoading, error: walletError, selectAsset, selectedAsset } = useWallet();
   
   // Step management
   const [currentStep, setCurrentStep] = useState<SendStep>('compose');
@@ -253,25 +259,29 @@ const Send: React.FC<SendPageProps> = ({ onBack }) => {
       setError(null);
       setSuccess(null);
 
-      console.log('🔄 Building transaction...');
+      console.log('🔄 Composing transaction...');
 
-      const selectedFeeRateValue = feeRates[feeRate];
-      const estimatedFee = (250 * selectedFeeRateValue) / 100000000; // ~250 vBytes typical tx
+      const deviceId = await getConnectedDeviceId();
       const amountInSats = Math.round(sendAmountInBtc * 100000000);
-      const feeInSats = Math.round(estimatedFee * 100000000);
-      
+      const composed = await TransactionAPI.compose(deviceId, {
+        destination: recipientAddress,
+        amount_sats: isMaxSend ? undefined : amountInSats,
+        send_max: isMaxSend,
+        fee: FEE_PREFERENCE[feeRate],
+      });
+      const preview = await TransactionAPI.preview(composed.id);
+      console.log('📊 Transaction composed:', preview);
+
       const review: TransactionReview = {
-        to: recipientAddress,
-        amount: `${sendAmountInBtc.toFixed(8)} BTC`,
-        amountInSats,
-        fee: estimatedFee,
-        feeRate: selectedFeeRateValue,
-        total: sendAmountInBtc + estimatedFee,
-        inputs: [], // Will be populated by createUnsignedUxtoTx
-        outputs: [
-          { address: recipientAddress, amount: amountInSats }
-        ],
-        unsignedTx: null // Will be populated by createUnsignedUxtoTx
+        to: preview.destination,
+        amount: `${(preview.amount_sats / 100000000).toFixed(8)} BTC`,
+        amountInSats: preview.amount_sats,
+        fee: preview.fee_sats / 100000000,
+        feeRate: preview.fee_rate,
+        total: preview.total_sats / 100000000,
+        transactionId: preview.id,
+        inputCount: preview.input_count,
+        warnings: preview.warnings,
       };
 
       setTransactionReview(review);
@@ -280,12 +290,21 @@ const Send: React.FC<SendPageProps> = ({ onBack }) => {
 
     } catch (error) {
       console.error('Error building transaction:', error);
-      setError(error instanceof Error ? error.message : 'Failed to build transaction');
+      setError(error instanceof Error ? error.message : String(error));
     } finally {
       setLoading(false);
     }
   };
 
+  const getConnectedDeviceId = async (): Promise<string> => {
+    const connectedDevices = await DeviceQueueAPI.getConnectedDevices();
+    if (!connectedDevices || connectedDevices.length === 0) {
+      throw new Error('No KeepKey device connected');
+    }
+    const device = connectedDevices[0].device || connectedDevices[0];
+    return device.unique_id;
+  };
+
   const handleSignTransaction = async () => {
     if (!transactionReview) {
       setError('No transaction to sign');
@@ -295,206 +314,25 @@ const Send: React.FC<SendPageProps> = ({ onBack }) => {
     try {
       setLoading(true);
       setError(null);
-      
       setCurrentStep('sign');
-      
-            console.log('🔐 Building and signing real Bitcoin transaction with proper UTXO selection...');
-      
-      // Get the connected device
-      const connectedDevices = await DeviceQueueAPI.getConnectedDevices();
-      if (!connectedDevices || connectedDevices.length === 0) {
-        throw new Error('No KeepKey device connected');
-      }
-      
-      const device = connectedDevices[0].device || connectedDevices[0];
-      const deviceId = device.unique_id;
-console.debug('[Send] deviceId from device.unique_id:', deviceId);
-      
-      console.log('🔑 Using device:', deviceId);
-      
-      // Get ALL Bitcoin xpubs (legacy, segwit, native segwit)
-      const btcNetworkId = "bip122:000000000019d6689c085ae165831e93";
-      const btcXpubs = fetchedXpubs.filter(x => 
-        x.caip && x.caip.includes(btcNetworkId)
-      );
-      
-      if (!btcXpubs || btcXpubs.length === 0) {
-        throw new Error('No Bitcoin xpubs found. Please sync your wallet first.');
-      }
-      
-      console.log('💰 Using ALL Bitcoin xpubs:', btcXpubs.length, 'found');
-      btcXpubs.forEach((xpub, i) => {
-        const prefix = xpub.xpub.substring(0, 4);
-        const scriptType = prefix === 'xpub' ? 'p2pkh' : prefix === 'ypub' ? 'p2sh-p2wpkh' : prefix === 'zpub' ? 'p2wpkh' : 'unknown';
-        console.log(`   ${i+1}. ${prefix}... (${scriptType}) - CAIP: ${xpub.caip}`);
-      });
-      
-      // Prepare pubkeys array for createUnsignedUxtoTx with ALL Bitcoin xpubs
-      const pubkeys = btcXpubs.map(btcXpub => {
-        const prefix = btcXpub.xpub.substring(0, 4);
-        let scriptType = 'p2pkh'; // Default to legacy
-        
-        if (prefix === 'ypub') {
-          scriptType = 'p2sh-p2wpkh'; // P2WPKH nested in P2SH
-        } else if (prefix === 'zpub') {
-          scriptType = 'p2wpkh'; // Native SegWit
-        }
-        
-        return {
-          pubkey: btcXpub.xpub,
-          xpub: btcXpub.xpub,
-          networks: ['bip122:000000000019d6689c085ae165831e93'],
-          scriptType: scriptType
-        };
-      });
-      
-      // Create Pioneer API client that uses our already-loaded fee rates
-      const pioneer = {
-        async ListUnspent({ network, xpub }: { network: string; xpub: string }) {
-          const data = await PioneerAPI.listUnspent('Bitcoin', xpub);
-          return { data };
-        },
-        async GetChangeAddress({ network, xpub }: { network: string; xpub: string }) {
-          const data = await PioneerAPI.getChangeAddress('Bitcoin', xpub);
-          return { data };
-        },
-        async GetFeeRate({ networkId }: { networkId: string }) {
-          // Use our already-loaded fee rates instead of fetching again
-          console.log('💰 Using pre-loaded fee rates instead of fetching again');
-          const selectedFeeRateValue = feeRates[feeRate];
-          return { 
-            data: {
-              slow: feeRates.slow,
-              average: feeRates.medium, 
-              fastest: feeRates.fast
-            }
-          };
-        }
-      };
-      
-                    // Use the proper transaction builder with real input selection
-       console.log('⚙️ Building transaction with proper coinselect algorithm...');
-        const sendAmountBtc = amountCurrency === 'USD' ? convertUsdToBtc(parseFloat(amount)) : parseFloat(amount); // Amount in BTC (not satoshis)
-      
-      console.log(`💰 Transaction type: ${isMaxSend ? 'MAX SEND (coinSelectSplit)' : 'REGULAR SEND (coinSelect)'}`);
-      console.log(`💰 Send amount: ${sendAmountBtc} BTC (${isMaxSend ? 'will be calculated by coinSelectSplit' : 'fixed amount'})`);
-      
-      
-      const unsignedTx = await createUnsignedUxtoTx(
-        btcXpubs[0].caip, // Use first Bitcoin CAIP (any will work since they're all Bitcoin)
-        recipientAddress,
-        sendAmountBtc,
-        '', // memo (empty for now)
-        pubkeys,
-        pioneer,
-        null, // keepKeySdk (not needed for signing)
-        isMaxSend // Use tracked max send state instead of hardcoded false
-      );
-      
-      console.log('📊 Transaction built by coinselect:');
-      console.log(`   Inputs: ${unsignedTx.inputs.length} UTXOs`);
-      console.log(`   Outputs: ${unsignedTx.outputs.length} outputs`);
-      console.log(`   Total input value: ${unsignedTx.inputs.reduce((sum: number, input: any) => sum + parseInt(input.amount), 0)} sats`);
-      console.log(`   Total output value: ${unsignedTx.outputs.reduce((sum: number, output: any) => sum + parseInt(output.amount), 0)} sats`);
-      
-      // Transform to format expected by our device signing API
-      const realInputs = unsignedTx.inputs.map((input: any) => ({
-        address_n_list: input.addressNList,
-        script_type: input.scriptType,
-        amount: input.amount,
-        vout: input.vout,
-        txid: input.txid,
-        hex: input.hex  // Device expects 'hex' field name
-      }));
-      
-      const realOutputs = unsignedTx.outputs.map((output: any) => ({
-        address: output.address || '',  // Ensure address is always a string
-        amount: parseInt(output.amount),
-        address_type: output.addressType === 'change' ? 'change' : 'spend',
-        is_change: output.addressType === 'change' ? true : false,
-        address_n_list: output.addressNList || null,
-        script_type: output.scriptType || 'p2pkh'
-      }));
-      
-      // Debug log the outputs being sent
-      console.log('📤 Outputs being sent to device:', realOutputs);
-      realOutputs.forEach((output: any, idx: number) => {
-        console.log(`   Output ${idx + 1}:`, {
-          address: output.address,
-          amount: output.amount,
-          address_type: output.address_type,
-          is_change: output.is_change,
-          has_address_n_list: !!output.address_n_list,
-          script_type: output.script_type
-        });
-      });
-      
-      // Sign the transaction using real device with properly selected UTXOs
-      console.log('🔐 Calling signTransaction with device queue...');
-      console.log('🔐 Request ID will be:', `sign_tx_${Date.now()}_${Math.random().toString(36).substr(2, 9)}`);
-      
-      // Log the EXACT payload being sent to the device
-      console.log('📝 COMPLETE SIGN PAYLOAD TO DEVICE:');
-      console.log('==========================================');
-      console.log(JSON.stringify({
-        coin: unsignedTx.coin,
-        inputs: realInputs,
-        outputs: realOutputs,
-        version: unsignedTx.version,
-        locktime: unsignedTx.locktime
-      }, null, 2));
-      console.log('==========================================');
-      
-      // Also log each input individually for detailed inspection
-      console.log('🔍 DETAILED INPUT INSPECTION:');
-      realInputs.forEach((input: any, index: number) => {
-        console.log(`Input ${index + 1} Full Details:`);
-        console.log('  - txid:', input.txid);
-        console.log('  - vout:', input.vout);
-        console.log('  - amount:', input.amount);
-        console.log('  - script_type:', input.script_type);
-        console.log('  - address_n_list:', JSON.stringify(input.address_n_list));
-        console.log('  - hex present?:', !!input.hex);
-        console.log('  - hex length:', input.hex ? input.hex.length : 0);
-        console.log('  - hex first 100 chars:', input.hex ? input.hex.substring(0, 100) : 'NO HEX');
-        console.log('  - hex last 20 chars:', input.hex ? input.hex.substring(input.hex.length - 20) : 'NO HEX');
-        console.log('  - All fields:', Object.keys(input));
-      });
-      
-      let signedTxHex: string;
-      try {
-        signedTxHex = await signTransaction(
-          deviceId,
-          unsignedTx.coin,
-          realInputs,
-          realOutputs,
-          unsignedTx.version,
-          unsignedTx.locktime
-        );
-        
-        console.log('✅ SEND COMPONENT: signTransaction promise resolved successfully!');
-        console.log('🔐 SEND COMPONENT: Signed transaction hex received:');
-        console.log(`   Type: ${typeof signedTxHex}`);
-        console.log(`   Length: ${signedTxHex ? signedTxHex.length : 'null/undefined'} characters`);
-        console.log(`   Full hex: "${signedTxHex}"`);
-        
-        if (!signedTxHex || signedTxHex.length === 0) {
-          throw new Error('Received empty or null signed transaction hex');
-        }
-        
-      } catch (promiseError) {
-        console.error('❌ SEND COMPONENT: signTransaction promise rejected:', promiseError);
-        throw promiseError;
-      }
-      
-      setSignedTransaction(signedTxHex);
+
+      // Signs on the device, then broadcasts; the backend holds the unsigned transaction
+      console.log('🔐 Signing and broadcasting transaction', transactionReview.transactionId);
+      const result = await TransactionAPI.signAndBroadcast(transactionReview.transactionId);
+      console.log('✅ Transaction broadcast:', result.txid);
+
+      setSignedTransaction(result.signed_tx);
+      setTxid(result.txid);
+      setBroadcastSuccess(true);
       setCurrentStep('complete');
-      setSuccess('Transaction signed successfully!');
+      setSuccess('Transaction broadcast successfully! ✅');
 
     } catch (error) {
       console.error('Error signing transaction:', error);
-      setError(error instanceof Error ? error.message : 'Failed to sign transaction');
-      setCurrentStep('review'); // Go back to review on error
+      setError(error instanceof Error ? error.message : String(error));
+      // The composed transaction is consumed by a signing attempt, so start over
+      setTransactionReview(null);
+      setCurrentStep('compose');
     } finally {
       setLoading(false);
     }
@@ -844,7 +682,11 @@ console.debug('[Send] deviceId from device.unique_id:', deviceId);
               <Text color="white">{transactionReview.feeRate} sat/vB</Text>
             </HStack>
             <HStack justify="space-between">
-              <Text color="gray.400">Est. Fee:</Text>
+              <Text color="gray.400">Inputs:</Text>
+              <Text color="white">{transactionReview.inputCount}</Text>
+            </HStack>
+            <HStack justify="space-between">
+              <Text color="gray.400">Fee:</Text>
               <VStack align="end" gap={0}>
                 <Text color="white">{transactionReview.fee.toFixed(8)} BTC</Text>
                 <Text color="gray.500" fontSize="xs">≈ ${convertBtcToUsd(transactionReview.fee).toFixed(2)} USD</Text>
@@ -858,6 +700,9 @@ console.debug('[Send] deviceId from device.unique_id:', deviceId);
               </VStack>
             </HStack>
           </VStack>
+          {transactionReview.warnings.map((warning) => (
+            <Text key={warning} color="orange.300" fontSize="sm" mt={3}>⚠️ {warning}</Text>
+          ))}
         </Box>
 
         <VStack gap={3} w="100%">
@@ -100,7 +100,10 @@ export const SettingsDialog = ({ isOpen, onClose }: SettingsDialogProps) => {
     try {
       console.log('Toggling API to:', enabled)
       
-      // Set the API enabled preference
+      // Enabling waits for a button press on the KeepKey
+      if (enabled) {
+        showToast('Confirm "Enable local API?" on your KeepKey', 'info')
+      }
       await invoke('set_api_enabled', { enabled })
       setApiEnabled(enabled)
       
@@ -3,6 +3,7 @@ import { FaDownload, FaExclamationTriangle } from "react-icons/fa";
 import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import type { DeviceProgress, UpdateStage } from "../../../types";
 
 interface StepFirmwareUpdateProps {
   deviceId: string;
@@ -29,65 +30,51 @@ export function StepFirmwareUpdate({ deviceId, onNext, onBack, onFirmwareUpdateS
   const [updateState, setUpdateState] = useState<UpdateState>('idle');
   const [updateProgress, setUpdateProgress] = useState(0);
   const [isWaitingForReboot, setIsWaitingForReboot] = useState(false);
-  const progressIntervalRef = useRef<NodeJS.Timeout | null>(null);
+  const [uploadedBytes, setUploadedBytes] = useState<{ sent: number; total: number } | null>(null);
   const unlistenRef = useRef<(() => void) | null>(null);
-  const rebootPollRef = useRef<NodeJS.Timeout | null>(null);
 
   useEffect(() => {
     checkDeviceStatus();
   }, [deviceId]);
 
-  // Cleanup on unmount
-  useEffect(() => {
-    return () => {
-      if (rebootPollRef.current) {
-        clearInterval(rebootPollRef.current);
-      }
-    };
-  }, []);
-
   // Set up event listener for firmware update events
   useEffect(() => {
     if (!isUpdating) return;
 
     const setupListener = async () => {
-      unlistenRef.current = await listen('firmware:update-status', (event) => {
-        const { status, progress } = event.payload as { status: string; progress?: number };
-        console.log('Firmware update status:', status, progress);
+      unlistenRef.current = await listen<DeviceProgress>('device:progress', (event) => {
+        const { device_id, operation, stage, current, total, message } = event.payload;
+        if (device_id !== deviceId || operation !== 'firmware_update') return;
+        console.log('Firmware update progress:', stage, current, total);
         
-        switch (status) {
-          case 'loading_firmware':
+        switch (stage as UpdateStage) {
+          case 'verifying_hash':
             setUpdateState('loading_firmware');
             break;
-          case 'firmware_erase':
+          case 'erasing':
             setUpdateState('erasing');
             break;
-          case 'button_request':
-            setUpdateState('waiting_confirmation');
-            break;
-          case 'firmware_upload':
+          case 'uploading':
             setUpdateState('uploading');
-            // Start progress animation when upload begins
-            if (!progressIntervalRef.current) {
-              let prog = 0;
-              progressIntervalRef.current = setInterval(() => {
-                prog += (100 / 60); // 100% over 60 seconds
-                if (prog >= 100) {
-                  prog = 100;
-                  if (progressIntervalRef.current) {
-                    clearInterval(progressIntervalRef.current);
-                  }
-                }
-                setUpdateProgress(prog);
-              }, 1000);
-            }
+            setUploadedBytes({ sent: current, total });
+            setUpdateProgress(total > 0 ? (current / total) * 100 : 0);
             break;
-          case 'complete':
+          case 'rebooting':
+          case 'verifying':
             setUpdateState('complete');
-            if (progressIntervalRef.current) {
-              clearInterval(progressIntervalRef.current);
-            }
             setUpdateProgress(100);
+            setIsWaitingForReboot(true);
+            break;
+          case 'finished':
+            setIsWaitingForReboot(false);
+            setIsUpdating(false);
+            onNext();
+            break;
+          case 'failed':
+            setIsWaitingForReboot(false);
+            setIsUpdating(false);
+            setUpdateState('idle');
+            setError(message || 'Firmware update failed');
             break;
         }
       });
@@ -99,11 +86,8 @@ export function StepFirmwareUpdate({ deviceId, onNext, onBack, onFirmwareUpdateS
       if (unlistenRef.current) {
         unlistenRef.current();
       }
-      if (progressIntervalRef.current) {
-        clearInterval(progressIntervalRef.current);
-      }
     };
-  }, [isUpdating]);
+  }, [isUpdating, deviceId]);
 
   const checkDeviceStatus = async () => {
     try {
@@ -134,6 +118,7 @@ export function StepFirmwareUpdate({ deviceId, onNext, onBack, onFirmwareUpdateS
     setIsUpdating(true);
     setError(null);
     setUpdateProgress(0);
+    setUploadedBytes(null);
     // Start with loading state - the event listener will update based on actual events
     setUpdateState('loading_firmware');
     
@@ -158,103 +143,16 @@ export function StepFirmwareUpdate({ deviceId, onNext, onBack, onFirmwareUpdateS
         onFirmwareUpdateComplete();
       }
       
-      // After firmware update, device will reboot - wait longer and let the backend handle reconnection
-      console.log("Firmware update complete - device will reboot, waiting for reconnection...");
+      // The device reboots now; the backend reports `verifying` until it is
+      // back and then `finished` (or `failed`) once its version is checked
+      console.log("Firmware update complete - device will reboot, waiting for verification...");
       setIsWaitingForReboot(true);
       
-      // Wait 10 seconds before starting to poll - device needs time to fully reboot
-      console.log("Waiting 10 seconds for device to reboot...");
-      setTimeout(() => {
-        console.log("Starting to poll for device reconnection...");
-        
-        // Start polling for device reconnection after reboot
-        let pollAttempts = 0;
-        const maxPollAttempts = 20; // 20 attempts over 10 seconds after initial wait
-        
-        rebootPollRef.current = setInterval(async () => {
-        pollAttempts++;
-        console.log(`Polling for device after reboot (attempt ${pollAttempts}/${maxPollAttempts})...`);
-        
-        try {
-          // First try with the original device ID
-          const status = await invoke<any>('get_device_status', { deviceId });
-          
-          if (status?.firmwareCheck?.currentVersion === "7.10.0") {
-            console.log("✅ Device reconnected with firmware 7.10.0!");
-            
-            // Clear the polling interval
-            if (rebootPollRef.current) {
-              clearInterval(rebootPollRef.current);
-              rebootPollRef.current = null;
-            }
-            
-            setIsWaitingForReboot(false);
-            setIsUpdating(false);
-            setDeviceStatus(status);
-            
-            // Move to next step
-            onNext();
-          }
-        } catch (err) {
-          // If the original device ID fails, try to find any connected device
-          try {
-            console.log("Original device ID failed, looking for any connected device...");
-            const devices = await invoke<any[]>('list_devices');
-            
-            if (devices && devices.length > 0) {
-              // Take the first device found
-              const newDeviceId = devices[0].unique_id || devices[0].id;
-              console.log(`Found device with ID: ${newDeviceId}`);
-              
-              // Try to get status with the new device ID
-              const status = await invoke<any>('get_device_status', { deviceId: newDeviceId });
-              
-              if (status?.firmwareCheck?.currentVersion === "7.10.0") {
-                console.log("✅ Device reconnected with firmware 7.10.0 (new ID)!");
-                
-                // Clear the polling interval
-                if (rebootPollRef.current) {
-                  clearInterval(rebootPollRef.current);
-                  rebootPollRef.current = null;
-                }
-                
-                setIsWaitingForReboot(false);
-                setIsUpdating(false);
-                setDeviceStatus(status);
-                
-                // Move to next step
-                onNext();
-              }
-            } else {
-              console.log("No devices found yet, continuing to poll...");
-            }
-          } catch (listErr) {
-            console.log("Device not ready yet, continuing to poll...");
-          }
-        }
-        
-        if (pollAttempts >= maxPollAttempts) {
-          console.error("Device did not reconnect after firmware update");
-          if (rebootPollRef.current) {
-            clearInterval(rebootPollRef.current);
-            rebootPollRef.current = null;
-          }
-          setIsWaitingForReboot(false);
-          setIsUpdating(false);
-          setError("Device did not reconnect after update. Please unplug and reconnect your device.");
-        }
-        }, 500); // Poll every 500ms
-      }, 10000); // Wait 10 seconds before starting to poll
-      
     } catch (err) {
       console.error("Failed to update firmware:", err);
       setError(`Failed to update firmware: ${err}`);
       setIsUpdating(false);
       setUpdateState('idle');
-      // Clear any running progress interval
-      if (progressIntervalRef.current) {
-        clearInterval(progressIntervalRef.current);
-      }
     }
   };
 
@@ -485,7 +383,8 @@ export function StepFirmwareUpdate({ deviceId, onNext, onBack, onFirmwareUpdateS
                     />
                   </Box>
                   <Text fontSize="xs" color="gray.500" mt={2}>
-                    {Math.round(updateProgress)}% - Estimated time remaining: {Math.max(0, 60 - Math.round(updateProgress * 0.6))}s
+                    {Math.round(updateProgress)}%
+                    {uploadedBytes && ` - ${uploadedBytes.sent.toLocaleString()} / ${uploadedBytes.total.toLocaleString()} bytes`}
                   </Text>
                   <Text fontSize="xs" color="gray.500">
                     Your device will restart when complete.
@@ -11,7 +11,7 @@ import { listen } from '@tauri-apps/api/event';
 
 // Import organized types and services
 import { Asset, Portfolio, QueueStatus } from '../types';
-import { PortfolioAPI, DeviceQueueAPI, PioneerAPI } from '../lib';
+import { PortfolioAPI, DeviceQueueAPI, PioneerAPI, NotificationAPI } from '../lib';
 import { usePinUnlockDialog } from './DialogContext';
 
 const TAG = " | WalletContext | ";
@@ -89,6 +89,9 @@ export const WalletProvider: React.FC<WalletProviderProps> = ({ children }) => {
   // PIN unlock dialog hook
   const pinUnlockDialog = usePinUnlockDialog();
 
+  // Last BTC balance seen, to notice incoming payments between refreshes
+  const lastBtcBalanceRef = useRef<number | null>(null);
+
   const refreshPortfolio = useCallback(async () => {
     const tag = TAG + " | refreshPortfolio | ";
     setLoading(true);
@@ -187,6 +190,20 @@ export const WalletProvider: React.FC<WalletProviderProps> = ({ children }) => {
       setPortfolio(portfolio);
       setError(null);
       console.log(tag, 'Portfolio refreshed successfully:', portfolio);
+
+      const btcBalance = symbolGroups.get('BTC')?.balance ?? 0;
+      const previousBtcBalance = lastBtcBalanceRef.current;
+      lastBtcBalanceRef.current = btcBalance;
+      if (previousBtcBalance !== null && btcBalance > previousBtcBalance) {
+        const received = (btcBalance - previousBtcBalance).toFixed(8);
+        NotificationAPI.record(
+          'payment_received',
+          'Payment received',
+          `Received ${received} BTC`,
+          undefined,
+          { amount: received, balance: btcBalance.toFixed(8) }
+        ).catch(e => console.error(tag, 'Failed to record payment notification:', e));
+      }
       
     } catch (error) {
       console.error(tag, 'Failed to refresh portfolio:', error);
@@ -860,6 +877,21 @@ export const WalletProvider: React.FC<WalletProviderProps> = ({ children }) => {
     };
   }, [pinUnlockDialog]);
 
+  // Unlocking another passphrase profile swaps the wallet on the device:
+  // drop the old wallet's xpubs and portfolio and fetch the new ones
+  useEffect(() => {
+    const unlisten = listen('passphrase:profile-selected', (event: any) => {
+      console.log(TAG, 'Passphrase profile selected, reloading wallet:', event.payload?.scope);
+      setFetchedXpubs([]);
+      setPortfolio(null);
+      lastBtcBalanceRef.current = null;
+      getXpubsFromDeviceQueue();
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
   // Watch fetchedXpubs and refresh portfolio when all expected xpubs are present
   useEffect(() => {
     const tag = TAG + " | fetchedXpubs useEffect | ";
@@ -52,6 +52,101 @@ export interface PioneerChangeAddressResponse {
   address: string;
 }
 
+// Transaction building (done in Rust; the UI only passes intent)
+export type FeePreference = 'slow' | 'average' | 'fastest' | { custom: number };
+
+export interface SendIntent {
+  destination: string;
+  amount_sats?: number;
+  send_max?: boolean;
+  fee?: FeePreference;
+  /** Coin control: spend exactly these "txid:vout" outpoints */
+  inputs?: string[];
+}
+
+export interface SpendableUtxo {
+  txid: string;
+  vout: number;
+  value: number;
+  script_type: string;
+  address_n_list: number[];
+  confirmations: number;
+}
+
+export interface ComposedTransaction {
+  id: string;
+  device_id: string;
+  destination: string;
+  amount_sats: number;
+  fee_sats: number;
+  fee_rate: number;
+  vsize: number;
+  change_sats: number | null;
+  send_max: boolean;
+  unconfirmed_inputs: number;
+}
+
+export interface TransactionPreview {
+  id: string;
+  destination: string;
+  amount_sats: number;
+  fee_sats: number;
+  fee_rate: number;
+  vsize: number;
+  change_sats: number | null;
+  total_sats: number;
+  input_count: number;
+  spent_outpoints: string[];
+  warnings: string[];
+}
+
+export interface BroadcastResult {
+  txid: string;
+  signed_tx: string;
+}
+
+export interface FeeBumpResult {
+  txid: string;
+  replaced_txid: string;
+  signed_tx: string;
+  previous_fee_sats: number;
+  fee_sats: number;
+  fee_rate: number;
+  amount_sats: number;
+  change_sats: number | null;
+}
+
+export type NotificationKind = 'update_available' | 'backup_reminder' | 'payment_received' | 'signing_failed' | 'fee_bump_suggested';
+
+export interface VaultNotification {
+  id: string;
+  kind: NotificationKind;
+  title: string;
+  message: string;
+  deviceId?: string;
+  data: any;
+  createdAt: string;
+  read: boolean;
+}
+
+export interface PassphraseProfile {
+  id: string;
+  deviceId: string;
+  name: string;
+  walletFingerprint?: string;
+  createdAt: string;
+  lastUsedAt?: string;
+}
+
+export interface ActivePassphraseProfile {
+  deviceId: string;
+  profileId: string;
+  name: string;
+  walletFingerprint: string;
+  /** `<deviceId>:<fingerprint>`, for keying wallet caches */
+  scope: string;
+}
+
 // Database Cache Types
 export interface BalanceCache {
   id: number;
@@ -697,4 +792,84 @@ export class DeviceQueueAPI {
       throw error;
     }
   }
-} 
\ No newline at end of file
+}
+
+/**
+ * Bitcoin sends: the backend selects inputs, fees and change, signs on the
+ * device and broadcasts. Composed transactions expire after 10 minutes.
+ */
+export class TransactionAPI {
+  /** UTXOs to pick from for coin control, largest first; cached for a minute */
+  static async listUtxos(deviceId: string, refresh: boolean = false): Promise<SpendableUtxo[]> {
+    return invoke('list_spendable_utxos', { deviceId, refresh });
+  }
+
+  static async compose(deviceId: string, intent: SendIntent): Promise<ComposedTransaction> {
+    return invoke('compose_transaction', { deviceId, intent });
+  }
+
+  static async preview(transactionId: string): Promise<TransactionPreview> {
+    return invoke('preview_transaction', { transactionId });
+  }
+
+  static async signAndBroadcast(transactionId: string): Promise<BroadcastResult> {
+    return invoke('sign_and_broadcast', { transactionId });
+  }
+
+  /**
+   * Replace an unconfirmed send with one paying more (RBF), signed on the
+   * device. The fee comes out of change, or the amount for a send-max.
+   */
+  static async bumpFee(deviceId: string, txid: string, fee?: FeePreference): Promise<FeeBumpResult> {
+    return invoke('bump_fee', { deviceId, txid, fee });
+  }
+}
+
+/**
+ * Persisted notifications. New ones arrive as `notification:new`
+ * ({ notification, unreadCount }); `notification:read` carries the new unreadCount.
+ */
+export class NotificationAPI {
+  static async list(unreadOnly: boolean = false): Promise<VaultNotification[]> {
+    return invoke('list_notifications', { unreadOnly });
+  }
+
+  static async markRead(id: string): Promise<void> {
+    return invoke('mark_notification_read', { id });
+  }
+
+  static async markAllRead(): Promise<void> {
+    return invoke('mark_all_notifications_read');
+  }
+
+  static async record(kind: NotificationKind, title: string, message: string, deviceId?: string, data?: any): Promise<void> {
+    return invoke('record_notification', { kind, title, message, deviceId, data });
+  }
+}
+
+/**
+ * Named passphrase profiles for hidden wallets. Selecting one unlocks its
+ * wallet on the device and emits `passphrase:profile-selected` with the
+ * ActivePassphraseProfile.
+ */
+export class PassphraseProfileAPI {
+  static async list(deviceId?: string): Promise<PassphraseProfile[]> {
+    return invoke('list_passphrase_profiles', { deviceId });
+  }
+
+  static async create(deviceId: string, name: string, passphrase: string): Promise<PassphraseProfile> {
+    return invoke('create_passphrase_profile', { deviceId, name, passphrase });
+  }
+
+  static async remove(profileId: string): Promise<boolean> {
+    return invoke('delete_passphrase_profile', { profileId });
+  }
+
+  static async active(deviceId: string): Promise<ActivePassphraseProfile | null> {
+    return invoke('get_active_passphrase_profile', { deviceId });
+  }
+
+  static async select(profileId: string, passphrase: string): Promise<ActivePassphraseProfile> {
+    return invoke('select_passphrase_profile', { profileId, passphrase });
+  }
+}
@@ -0,0 +1,46 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+/**
+ * Acks backend events sent with guaranteed delivery. Each one is followed by
+ * an `event:delivery` envelope; anything not acked is replayed by the backend
+ * when `frontend_ready` is signalled after a reload.
+ */
+interface DeliveryEnvelope {
+  seq: number;
+  event: string;
+}
+
+const ACK_DELAY_MS = 100;
+
+let started: Promise<void> | null = null;
+let pendingAcks: number[] = [];
+let flushTimer: ReturnType<typeof setTimeout> | null = null;
+
+async function flushAcks() {
+  flushTimer = null;
+  const seqs = pendingAcks;
+  pendingAcks = [];
+  try {
+    await invoke('ack_events', { seqs });
+  } catch (error) {
+    // Unacked events are replayed on the next frontend_ready, which is harmless
+    console.warn('Failed to ack events:', error);
+  }
+}
+
+/**
+ * Start acking delivered events. Call before `invoke('frontend_ready')` so
+ * the replayed events are acked too; later calls are no-ops.
+ */
+export function startEventDelivery(): Promise<void> {
+  if (!started) {
+    started = listen<DeliveryEnvelope>('event:delivery', ({ payload }) => {
+      pendingAcks.push(payload.seq);
+      if (!flushTimer) {
+        flushTimer = setTimeout(flushAcks, ACK_DELAY_MS);
+      }
+    }).then(() => undefined);
+  }
+  return started;
+}
@@ -57,4 +57,57 @@ export interface DeviceFeatures {
   wipeCodeProtection: boolean
   autoLockDelayMs?: number
   policies: string[]
-} 
\ No newline at end of file
+} 
+export type ProgressOperation = 'frontload' | 'firmware_update' | 'bootloader_update' | 'signing' | 'recovery'
+
+/** Stage of a firmware or bootloader update (keepkey-rust `UpdateStage`) */
+export type UpdateStage =
+  | 'downloading'
+  | 'verifying_hash'
+  | 'erasing'
+  | 'uploading'
+  | 'rebooting'
+  | 'verifying'
+  | 'finished'
+  | 'failed'
+
+/** Payload of the `device:progress` event */
+export interface DeviceProgress {
+  device_id: string
+  operation: ProgressOperation
+  /** An `UpdateStage` for updates; signing and recovery use their own step names */
+  stage: string
+  current: number
+  total: number
+  message?: string
+}
+
+/** Stable code of a Failure the device answered with (keepkey-rust `FailureCode`) */
+export type DeviceFailureCode =
+  | 'unexpected_message'
+  | 'unknown_message'
+  | 'button_expected'
+  | 'syntax_error'
+  | 'action_cancelled'
+  | 'pin_expected'
+  | 'pin_cancelled'
+  | 'pin_invalid'
+  | 'pin_mismatch'
+  | 'invalid_signature'
+  | 'not_enough_funds'
+  | 'not_initialized'
+  | 'already_initialized'
+  | 'firmware_error'
+  | 'other'
+
+/** Entry of the `get_failure_descriptions` table */
+export interface FailureDescription {
+  code: DeviceFailureCode
+  description: string
+}
+
+/** Code leading a device command error such as `[pin_invalid] Recovery PIN failed: ...` */
+export function deviceFailureCode(error: unknown): DeviceFailureCode | null {
+  const match = /^\[([a-z_]+)\]/.exec(String(error))
+  return match ? (match[1] as DeviceFailureCode) : null
+}
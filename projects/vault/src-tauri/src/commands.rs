@@ -2,6 +2,7 @@ use tauri::{AppHandle, Emitter, State};
 use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
 use keepkey_rust::{
+    derivation_path::{hardened, DerivationPath},
     device_queue::{DeviceQueueFactory, DeviceQueueHandle},
     features::DeviceFeatures,
 };
@@ -72,10 +73,10 @@ pub struct BitcoinUtxoOutput {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DeviceRequest {
     GetXpub {
-        path: String,
+        path: DerivationPath,
     },
     GetAddress {
-        path: String,
+        path: DerivationPath,
         coin_name: String,
         script_type: Option<String>,
         show_display: Option<bool>,
@@ -106,7 +107,7 @@ pub enum DeviceResponse {
     Xpub {
         request_id: String,
         device_id: String,
-        path: String,
+        path: DerivationPath,
         xpub: String,
         script_type: Option<String>,
         success: bool,
@@ -115,7 +116,7 @@ pub enum DeviceResponse {
     Address {
         request_id: String,
         device_id: String,
-        path: String,
+        path: DerivationPath,
         address: String,
         success: bool,
         error: Option<String>,
@@ -306,30 +307,6 @@ pub async fn get_blocking_actions() -> Result<Vec<serde_json::Value>, String> {
     Ok(vec![])
 }
 
-/// Helper function to parse derivation path string to Vec<u32>
-pub fn parse_derivation_path(path: &str) -> Result<Vec<u32>, String> {
-    let path = path.trim_start_matches("m/");
-    let parts: Result<Vec<u32>, String> = path
-        .split('/')
-        .map(|part| {
-            // Check if this specific part is hardened (ends with ')
-            let is_hardened = part.ends_with('\'');
-            let part = part.trim_end_matches('\'');
-            let mut value = part.parse::<u32>()
-                .map_err(|_| format!("Invalid path component: {}", part))?;
-            
-            // Apply hardened derivation if this part ends with '
-            if is_hardened {
-                value |= 0x80000000;
-            }
-            
-            Ok(value)
-        })
-        .collect();
-    
-    parts.map_err(|e: String| format!("Failed to parse derivation path '{}': {}", path, e))
-}
-
 /// Test command to demonstrate the unified device queue interface
 #[tauri::command]
 pub async fn test_device_queue() -> Result<String, String> {
@@ -3619,7 +3596,7 @@ pub async fn trigger_pin_request(
         
     // Create a simple GetAddress request that will trigger PIN on locked device
     let get_address = keepkey_rust::messages::GetAddress {
-        address_n: vec![44, 0, 0, 0, 0], // m/44'/0'/0'/0/0
+        address_n: vec![hardened(44), hardened(0), hardened(0), 0, 0], // m/44'/0'/0'/0/0
         coin_name: Some("Bitcoin".to_string()),
         script_type: Some(0), // SPENDADDRESS
         show_display: Some(false),
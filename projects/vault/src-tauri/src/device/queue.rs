@@ -6,6 +6,7 @@ use tokio::sync::RwLock;
 
 // Import types needed for DeviceRequestWrapper
 use crate::commands::{DeviceRequestWrapper, DeviceRequest, DeviceResponse, DeviceQueueManager, parse_transaction_from_hex};
+use keepkey_rust::derivation_path::{hardened, DerivationPath};
 
 // Create a cache for device states to remember OOB bootloader status
 lazy_static::lazy_static! {
@@ -19,6 +20,17 @@ struct DeviceStateCache {
     last_update: std::time::Instant,
 }
 
+/// Script type implied by a hardened BIP-44/49/84 purpose
+fn script_type_for_path(path: &DerivationPath) -> Option<String> {
+    let script_type = match path.as_slice().first().copied()? {
+        p if p == hardened(44) => "p2pkh",
+        p if p == hardened(49) => "p2sh-p2wpkh",
+        p if p == hardened(84) => "p2wpkh",
+        _ => return None,
+    };
+    Some(script_type.to_string())
+}
+
 #[tauri::command]
 pub async fn add_to_device_queue(
     request: DeviceRequestWrapper,
@@ -179,7 +191,7 @@ pub async fn add_to_device_queue(
             
             // Create a simple request that will trigger PIN on locked device
             let get_address = keepkey_rust::messages::GetAddress {
-                address_n: vec![44, 0, 0, 0, 0], // m/44'/0'/0'/0/0
+                address_n: vec![hardened(44), hardened(0), hardened(0), 0, 0], // m/44'/0'/0'/0/0
                 coin_name: Some("Bitcoin".to_string()),
                 script_type: Some(0), // SPENDADDRESS
                 show_display: Some(false),
@@ -244,13 +256,10 @@ pub async fn add_to_device_queue(
     // Process the request based on type
     let result = match request.request {
         DeviceRequest::GetXpub { ref path } => {
-            // Parse derivation path
-            let path_parts = crate::commands::parse_derivation_path(&path)?;
-            
             // Create GetPublicKey message for xpub
             let get_public_key = keepkey_rust::messages::Message::GetPublicKey(
                 keepkey_rust::messages::GetPublicKey {
-                    address_n: path_parts,
+                    address_n: path.as_slice().to_vec(),
                     coin_name: Some("Bitcoin".to_string()),
                     script_type: None, // Default script type
                     ecdsa_curve_name: Some("secp256k1".to_string()),
@@ -284,7 +293,7 @@ pub async fn add_to_device_queue(
             }
         }
         DeviceRequest::GetAddress { ref path, ref coin_name, ref script_type, show_display } => {
-            let path_parts = crate::commands::parse_derivation_path(&path)?;
+            let path_parts = path.as_slice().to_vec();
             let script_type_int = match script_type.as_deref() {
                 Some("p2pkh") => Some(0),       // SPENDADDRESS = 0
                 Some("p2sh-p2wpkh") => Some(4), // SPENDP2SHWITNESS = 4  
@@ -533,16 +542,7 @@ pub async fn add_to_device_queue(
     // Create and store the response
     let device_response = match (&request.request, &result) {
         (DeviceRequest::GetXpub { path }, Ok(ref xpub)) => {
-            // Infer script_type from path
-            let script_type = if path.starts_with("m/44'") {
-                Some("p2pkh".to_string())
-            } else if path.starts_with("m/49'") {
-                Some("p2sh-p2wpkh".to_string())
-            } else if path.starts_with("m/84'") {
-                Some("p2wpkh".to_string())
-            } else {
-                None
-            };
+            let script_type = script_type_for_path(path);
             // Debug logging for xpub conversion
             println!("[slip132-debug] Original xpub: {}", xpub);
             println!("[slip132-debug] Inferred script_type: {:?}", script_type);
@@ -572,16 +572,7 @@ pub async fn add_to_device_queue(
             }
         }
         (DeviceRequest::GetXpub { path }, Err(e)) => {
-            // Infer script_type from path for error case as well
-            let script_type = if path.starts_with("m/44'") {
-                Some("p2pkh".to_string())
-            } else if path.starts_with("m/49'") {
-                Some("p2sh-p2wpkh".to_string())
-            } else if path.starts_with("m/84'") {
-                Some("p2wpkh".to_string())
-            } else {
-                None
-            };
+            let script_type = script_type_for_path(path);
             DeviceResponse::Xpub {
                 request_id: request.request_id.clone(),
                 device_id: request.device_id.clone(),
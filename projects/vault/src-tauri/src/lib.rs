@@ -152,6 +152,23 @@ pub fn run() {
             // Start event controller with proper management
             let _event_controller = event_controller::spawn_event_controller(&app.handle());
             
+            // Forward firmware update progress to the frontend
+            let progress_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut progress = keepkey_rust::device_queue::DeviceQueueFactory::subscribe_progress();
+                loop {
+                    match progress.recv().await {
+                        Ok(event) => {
+                            let _ = progress_handle.emit("device:progress", &event);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            log::debug!("Dropped {} device progress events", skipped);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+            
             // Start background log cleanup task
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -57,4 +57,27 @@ export interface DeviceFeatures {
   wipeCodeProtection: boolean
   autoLockDelayMs?: number
   policies: string[]
-} 
\ No newline at end of file
+}
+export type ProgressOperation = 'frontload' | 'firmware_update' | 'bootloader_update' | 'signing' | 'recovery'
+
+/** Stage of a firmware or bootloader update (keepkey-rust `UpdateStage`) */
+export type UpdateStage =
+  | 'downloading'
+  | 'verifying_hash'
+  | 'erasing'
+  | 'uploading'
+  | 'rebooting'
+  | 'verifying'
+  | 'finished'
+  | 'failed'
+
+/** Payload of the `device:progress` event */
+export interface DeviceProgress {
+  device_id: string
+  operation: ProgressOperation
+  /** An `UpdateStage` for updates; signing and recovery use their own step names */
+  stage: string
+  current: number
+  total: number
+  message?: string
+}